@@ -0,0 +1,122 @@
+#!/usr/bin/env bash
+#
+# bench_uring.sh — Compare the sensor-port receive datapaths: epoll vs io_uring
+#
+# Prerequisites:
+#   1. Linux 5.6+ with io_uring enabled (not blocked by seccomp)
+#   2. Python3 for the load generator
+#
+# Each run pins the bridge to one core with a single sensor receiver, so
+# the numbers compare syscall overhead rather than scheduling luck.
+# Reported per datapath: peak STATS pps and packets per CPU-second.
+#
+# Usage:
+#   ./bench/bench_uring.sh [pps] [duration_secs] [cpu]
+
+set -euo pipefail
+
+PPS=${1:-100000}
+DURATION=${2:-20}
+CPU=${3:-1}
+SCRIPT_DIR="$(cd "$(dirname "$0")" && pwd)"
+ROOT_DIR="$(dirname "$SCRIPT_DIR")"
+RESULTS_DIR="$ROOT_DIR/bench/results"
+TIMESTAMP="$(date +%Y%m%d_%H%M%S)"
+RESULT_FILE="$RESULTS_DIR/bench_uring_${TIMESTAMP}.txt"
+
+SENSOR_PORT=9102
+RUST_BIN="$ROOT_DIR/rust-udp-mqtt/target/release/vad-sensor-bridge"
+LOAD_GEN="$SCRIPT_DIR/load_gen.py"
+CLK_TCK=$(getconf CLK_TCK)
+
+mkdir -p "$RESULTS_DIR"
+exec > >(tee -a "$RESULT_FILE") 2>&1
+
+echo "================================================================="
+echo "  VAD Sensor Bridge — UDP Datapath Comparison (epoll vs io_uring)"
+echo "  Date:     $(date)"
+echo "  Rate:     ${PPS} pps × ${DURATION}s, bridge pinned to CPU ${CPU}"
+echo "  System:   $(uname -srm)"
+echo "  CPU:      $(grep -m1 'model name' /proc/cpuinfo | cut -d: -f2 | xargs)"
+echo "  Results:  $RESULT_FILE"
+echo "================================================================="
+echo
+
+echo "🔨 Building Rust (--features io-uring)..."
+cd "$ROOT_DIR/rust-udp-mqtt" && cargo build --release --features io-uring 2>&1 | tail -3
+echo
+cd "$ROOT_DIR"
+
+# ── Helper: kill a process and its group ──
+kill_bridge() {
+    local PID="$1"
+    kill -TERM -- -$PID 2>/dev/null || kill -TERM $PID 2>/dev/null || true
+    sleep 1
+    kill -9 -- -$PID 2>/dev/null || kill -9 $PID 2>/dev/null || true
+    wait $PID 2>/dev/null || true
+}
+
+# ── Helper: user+system CPU ticks of a process ──
+cpu_ticks() {
+    awk '{print $14 + $15}' "/proc/$1/stat" 2>/dev/null || echo 0
+}
+
+declare -A PEAK CPU_SECS
+
+run_bench() {
+    local IO="$1"
+    local LOG_FILE="$RESULTS_DIR/uring_${IO}_${TIMESTAMP}.log"
+
+    echo "───────────────────────────────────────────────────────────────"
+    echo "  ${IO^^}  (${PPS} pps × ${DURATION}s)"
+    echo "───────────────────────────────────────────────────────────────"
+
+    setsid taskset -c "$CPU" env RUST_LOG=info "$RUST_BIN" \
+        --udp-io "$IO" \
+        --recv-threads 1 \
+        --sensor-port "$SENSOR_PORT" \
+        --stats-interval-secs 5 > "$LOG_FILE" 2>&1 &
+    local PID=$!
+    sleep 2
+
+    if ! kill -0 $PID 2>/dev/null; then
+        echo "   ❌ Failed to start — check $LOG_FILE"
+        cat "$LOG_FILE"
+        return 1
+    fi
+    echo "   PID=$PID sensor port :$SENSOR_PORT via $IO"
+
+    local START
+    START=$(cpu_ticks $PID)
+    python3 "$LOAD_GEN" --transport udp --port "$SENSOR_PORT" --rate "$PPS" --duration "$DURATION" 2>&1 || true
+    sleep 6
+    local END
+    END=$(cpu_ticks $PID)
+
+    kill_bridge $PID
+
+    grep -E "\[STATS\]" "$LOG_FILE" || echo "(no stats)"
+    PEAK[$IO]=$(grep -oP '\[STATS[^]]*\] \K[0-9]+(?= pps)' "$LOG_FILE" 2>/dev/null | sort -rn | head -1 || echo 0)
+    CPU_SECS[$IO]=$(awk -v t=$((END - START)) -v hz="$CLK_TCK" 'BEGIN { printf "%.2f", t / hz }')
+    echo
+}
+
+run_bench "epoll"
+run_bench "uring"
+
+# ── Summary table ──
+echo "================================================================="
+echo "  📊 Sensor datapath (1 receiver, pinned)"
+echo "================================================================="
+printf "  %-8s | %-12s | %-10s | %-14s\n" "I/O" "peak pps" "CPU secs" "pkts / CPU sec"
+printf "  %-8s-+-%-12s-+-%-10s-+-%-14s\n" "--------" "------------" "----------" "--------------"
+for IO in epoll uring; do
+    PER_CPU=$(awk -v n=$((PPS * DURATION)) -v s="${CPU_SECS[$IO]:-0}" 'BEGIN { if (s > 0) printf "%.0f", n / s; else print "?" }')
+    printf "  %-8s | %-12s | %-10s | %-14s\n" "$IO" "${PEAK[$IO]:-?}" "${CPU_SECS[$IO]:-?}" "$PER_CPU"
+done
+echo
+echo "  ⚠ pkts / CPU sec assumes every offered packet was received;"
+echo "    check drops / recv_err in the STATS lines above."
+echo "  📁 Full results: $RESULT_FILE"
+echo "  🔁 Run again: ./bench/bench_uring.sh $PPS $DURATION $CPU"
+echo "================================================================="
@@ -0,0 +1,65 @@
+#!/usr/bin/env bash
+#
+# perf_gate.sh — Fail when a hot-path benchmark regressed against a baseline
+#
+# Runs the criterion suite (rust-udp-mqtt/benches/hot_paths.rs) and
+# compares each benchmark's mean time to a saved baseline.
+#
+# Usage:
+#   ./bench/perf_gate.sh save [baseline]            # record (e.g. on main)
+#   ./bench/perf_gate.sh check [baseline] [max_pct] # compare (default 10 %)
+
+set -euo pipefail
+
+MODE=${1:-check}
+BASELINE=${2:-main}
+MAX_PCT=${3:-10}
+SCRIPT_DIR="$(cd "$(dirname "$0")" && pwd)"
+ROOT_DIR="$(dirname "$SCRIPT_DIR")"
+CRITERION_DIR="$ROOT_DIR/rust-udp-mqtt/target/criterion"
+
+cd "$ROOT_DIR/rust-udp-mqtt"
+
+case "$MODE" in
+    save)
+        cargo bench --features bench --bench hot_paths -- --save-baseline "$BASELINE"
+        echo "   ✅ Baseline '$BASELINE' saved under $CRITERION_DIR"
+        exit 0
+        ;;
+    check)
+        if ! find "$CRITERION_DIR" -path "*/$BASELINE/estimates.json" 2>/dev/null | grep -q .; then
+            echo "❌ No baseline '$BASELINE' — run: ./bench/perf_gate.sh save $BASELINE"
+            exit 1
+        fi
+        # Compare only; the baseline itself is left untouched
+        cargo bench --features bench --bench hot_paths -- --baseline "$BASELINE"
+        ;;
+    *)
+        echo "usage: $0 save|check [baseline] [max_pct]"
+        exit 2
+        ;;
+esac
+
+echo
+echo "================================================================="
+echo "  📊 Hot paths vs '$BASELINE' (fail above +${MAX_PCT}% mean time)"
+echo "================================================================="
+python3 - "$CRITERION_DIR" "$MAX_PCT" <<'EOF'
+import json, pathlib, sys
+
+root, max_pct = pathlib.Path(sys.argv[1]), float(sys.argv[2])
+failed = 0
+for change in sorted(root.glob("*/*/change/estimates.json")):
+    bench = change.parent.parent.relative_to(root)
+    mean = json.loads(change.read_text())["mean"]
+    pct = mean["point_estimate"] * 100
+    # Noise guard: the whole confidence interval must be past the limit
+    bad = mean["confidence_interval"]["lower_bound"] * 100 > max_pct
+    failed += bad
+    print(f"  {'❌' if bad else '✅'} {str(bench):<28} {pct:+7.2f}%")
+print()
+if failed:
+    print(f"  {failed} benchmark(s) regressed by more than {max_pct:g}%")
+    sys.exit(1)
+print("  No regressions")
+EOF
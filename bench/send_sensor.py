@@ -36,16 +36,21 @@ import time
 
 # ── Wire format ─────────────────────────────────────────────────────
 # Matches sensor_header_t (32 bytes, packed, little-endian):
-#   [ sensor_id: u32 ][ timestamp_us: u64 ][ data_type: u8 ][ reserved: 3 ]
-#   [ payload_len: u16 ][ reserved: 2 ][ seq: u64 ][ padding: 4 ]
-HEADER_FMT = "<IQBxxxHxxQ4x"
+#   [ sensor_id: u32 ][ timestamp_us: u64 ][ data_type: u8 ][ sample_format: u8 ]
+#   [ reserved: 2 ][ payload_len: u16 ][ reserved: 2 ][ seq: u64 ][ padding: 4 ]
+# sample_format is read for audio only (0 = s16 LE, 1 = s8, 2 = s24,
+# 3 = s32, 4 = f32); the payloads below are all s16.
+HEADER_FMT = "<IQBBxxHxxQ4x"
 HEADER_SIZE = struct.calcsize(HEADER_FMT)  # 32
 
 DATA_TYPE_AUDIO = 1
 DATA_TYPE_SENSOR_VECTOR = 2
 
+SAMPLE_FORMAT_S16 = 0
 
-def build_packet(sensor_id: int, seq: int, data_type: int, payload: bytes) -> bytes:
+
+def build_packet(sensor_id: int, seq: int, data_type: int, payload: bytes,
+                 sample_format: int = SAMPLE_FORMAT_S16) -> bytes:
     """Build a binary sensor packet matching the bridge wire format."""
     timestamp_us = int(time.time() * 1_000_000)
     header = struct.pack(
@@ -53,6 +58,7 @@ def build_packet(sensor_id: int, seq: int, data_type: int, payload: bytes) -> by
         sensor_id,
         timestamp_us,
         data_type,
+        sample_format,
         len(payload),
         seq,
     )
@@ -318,6 +318,8 @@ def main():
             print(f"     ← {describe(seq, pkt_type, flags, payload)}")
             expect("type == CONTROL", pkt_type == PKT_CONTROL)
             expect("cmd == ACK", payload and payload[0] == CTRL_ACK)
+            expect("ACK for SESSION_END, status OK",
+                   len(payload) >= 3 and payload[1] == CTRL_SESSION_END and payload[2] == 0x00)
     except socket.timeout:
         expect("ACK received", False)
 
@@ -7,15 +7,19 @@
 
 /*
  * Binary wire format (32-byte fixed header + variable payload):
- *   [ sensor_id: u32 LE ][ timestamp_us: u64 LE ][ data_type: u8 ][ reserved: 3 ]
+ *   [ sensor_id: u32 LE ][ timestamp_us: u64 LE ][ data_type: u8 ][ sample_format: u8 ]
+ *   [ reserved: 2 ]
  *   [ payload_len: u16 LE ][ reserved: 2 ][ seq: u64 LE ][ padding: 4 ]
  *   [ payload: payload_len bytes ]
  *
  * For TCP: length-prefixed: [ total_len: u32 LE ][ binary_packet ]
  *
  * Data types:
- *   1 = 16-bit LE PCM audio  (for audio RMS VAD)
+ *   1 = PCM audio  (for audio RMS VAD), format per sample_format
  *   2 = 10×f32 LE sensor vector (for emotional VAD: Valence-Arousal-Dominance)
+ *
+ * Sample formats (audio only; the byte was reserved, ignored for other types):
+ *   0 = s16 LE, 1 = s8, 2 = s24 LE packed, 3 = s32 LE, 4 = f32 LE (±1.0)
  */
 
 #define SENSOR_HEADER_SIZE 32
@@ -24,6 +28,12 @@
 #define DATA_TYPE_AUDIO          1
 #define DATA_TYPE_SENSOR_VECTOR  2
 
+#define SAMPLE_FORMAT_S16  0
+#define SAMPLE_FORMAT_S8   1
+#define SAMPLE_FORMAT_S24  2
+#define SAMPLE_FORMAT_S32  3
+#define SAMPLE_FORMAT_F32  4
+
 #define SENSOR_VECTOR_LEN    10
 #define SENSOR_VECTOR_BYTES  (SENSOR_VECTOR_LEN * sizeof(float))  /* 40 */
 
@@ -31,7 +41,8 @@ typedef struct __attribute__((packed)) {
     uint32_t sensor_id;
     uint64_t timestamp_us;
     uint8_t  data_type;
-    uint8_t  reserved1[3];
+    uint8_t  sample_format;
+    uint8_t  reserved1[2];
     uint16_t payload_len;
     uint8_t  reserved2[2];
     uint64_t seq;
@@ -42,6 +53,7 @@ typedef struct {
     uint32_t sensor_id;
     uint64_t timestamp_us;
     uint8_t  data_type;
+    uint8_t  sample_format;   /* audio only; SAMPLE_FORMAT_S16 otherwise */
     uint64_t seq;
     uint16_t payload_len;
     uint8_t  payload[SENSOR_MAX_PAYLOAD];
@@ -67,6 +79,8 @@ static inline int sensor_parse_binary(const uint8_t *buf, size_t len,
     out->sensor_id    = hdr->sensor_id;
     out->timestamp_us = hdr->timestamp_us;
     out->data_type    = hdr->data_type;
+    out->sample_format = hdr->data_type == DATA_TYPE_AUDIO ? hdr->sample_format
+                                                           : SAMPLE_FORMAT_S16;
     out->seq          = hdr->seq;
     out->payload_len  = plen;
     __builtin_memcpy(out->payload, buf + SENSOR_HEADER_SIZE, plen);
@@ -12,6 +12,7 @@
  *   ./vad-sensor-bridge --transport udp --port 9000
  *   ./vad-sensor-bridge --transport tcp --port 9000
  *   ./vad-sensor-bridge --transport mqtt --mqtt-host 127.0.0.1
+ *   ./vad-sensor-bridge --transport mqtt --mqtt-qos 1 --mqtt-share-group vad
  */
 
 #ifndef _GNU_SOURCE
@@ -44,6 +45,8 @@
 #define DEFAULT_MQTT_PORT       1883
 #define DEFAULT_MQTT_TOPIC      "vad/sensors/+"
 #define DEFAULT_CLIENT_ID       "vad-c-processor"
+#define DEFAULT_MQTT_QOS        0
+#define MAX_TOPIC_LEN           256
 #define DEFAULT_RING_CAPACITY   262144
 #define DEFAULT_RECV_BUF        (4 * 1024 * 1024)
 #define DEFAULT_STATS_INTERVAL  5
@@ -89,6 +92,11 @@ typedef struct {
     const char        *transport_str;
 } proc_thread_ctx_t;
 
+typedef struct {
+    char               topic[MAX_TOPIC_LEN];  /* incl. $share/<group>/ */
+    int                qos;
+} mqtt_sub_t;
+
 /* ─── Signal handler ─────────────────────────────────────────────── */
 static void sig_handler(int sig) {
     (void)sig;
@@ -262,11 +270,12 @@ static void mosq_on_message(struct mosquitto *mosq, void *obj,
 
 static void mosq_on_connect(struct mosquitto *mosq, void *obj, int rc)
 {
-    (void)obj;
+    const mqtt_sub_t *sub = (const mqtt_sub_t *)obj;
     if (rc == 0) {
-        printf("[MQTT] Connected, subscribing...\n");
+        printf("[MQTT] Connected, subscribing to %s (QoS %d)...\n",
+               sub->topic, sub->qos);
         /* Subscribe on connect (and auto-reconnect) */
-        mosquitto_subscribe(mosq, NULL, (const char *)mosquitto_userdata(mosq), 0);
+        mosquitto_subscribe(mosq, NULL, sub->topic, sub->qos);
     } else {
         fprintf(stderr, "[MQTT] Connect failed, rc=%d\n", rc);
     }
@@ -275,8 +284,18 @@ static void mosq_on_connect(struct mosquitto *mosq, void *obj, int rc)
 static void mosq_on_subscribe(struct mosquitto *mosq, void *obj, int mid,
                               int qos_count, const int *granted_qos)
 {
-    (void)mosq; (void)obj; (void)mid; (void)qos_count; (void)granted_qos;
-    printf("[MQTT] Subscribed successfully\n");
+    (void)mosq; (void)mid;
+    const mqtt_sub_t *sub = (const mqtt_sub_t *)obj;
+    /* 0x80 = refused (e.g. a broker without shared subscriptions) */
+    if (qos_count < 1 || granted_qos[0] > 2) {
+        fprintf(stderr, "[MQTT] Subscription to %s refused\n", sub->topic);
+        return;
+    }
+    if (granted_qos[0] < sub->qos)
+        printf("[MQTT] Subscribed, broker downgraded QoS %d → %d\n",
+               sub->qos, granted_qos[0]);
+    else
+        printf("[MQTT] Subscribed successfully\n");
 }
 
 /* ─── VAD Processor Thread ───────────────────────────────────────── */
@@ -333,13 +352,18 @@ static void usage(const char *prog) {
            "  --mqtt-host H      MQTT broker host (default %s)\n"
            "  --mqtt-port N      MQTT broker port (default %d)\n"
            "  --mqtt-topic T     MQTT subscribe topic (default %s)\n"
+           "  --mqtt-qos N       Subscription QoS: 0, 1 or 2 (default %d)\n"
+           "  --mqtt-share-group G  Shared subscription $share/G/<topic> (MQTT 5);\n"
+           "                     instances in one group split the messages\n"
+           "  --mqtt-client-id C MQTT client id (default %s, +pid with a group)\n"
            "  --recv-threads N   Receiver threads (default %d, UDP only)\n"
            "  --proc-threads N   VAD processor threads (default %d)\n"
            "  --ring-cap N       Ring buffer capacity (default %d)\n"
            "  --stats-interval N Stats interval secs (default %d, 0=off)\n"
            "  --help             Show this help\n",
            prog, DEFAULT_PORT, DEFAULT_MQTT_HOST, DEFAULT_MQTT_PORT,
-           DEFAULT_MQTT_TOPIC, DEFAULT_RECV_THREADS, DEFAULT_PROC_THREADS,
+           DEFAULT_MQTT_TOPIC, DEFAULT_MQTT_QOS, DEFAULT_CLIENT_ID,
+           DEFAULT_RECV_THREADS, DEFAULT_PROC_THREADS,
            DEFAULT_RING_CAPACITY, DEFAULT_STATS_INTERVAL);
 }
 
@@ -349,6 +373,9 @@ int main(int argc, char *argv[])
     const char *mqtt_host      = DEFAULT_MQTT_HOST;
     int         mqtt_port      = DEFAULT_MQTT_PORT;
     const char *mqtt_topic     = DEFAULT_MQTT_TOPIC;
+    int         mqtt_qos       = DEFAULT_MQTT_QOS;
+    const char *mqtt_group     = NULL;
+    const char *mqtt_client_id = NULL;
     int         n_recv_threads = DEFAULT_RECV_THREADS;
     int         n_proc_threads = DEFAULT_PROC_THREADS;
     int         ring_cap       = DEFAULT_RING_CAPACITY;
@@ -373,6 +400,12 @@ int main(int argc, char *argv[])
             mqtt_port = atoi(argv[++i]);
         else if (!strcmp(argv[i], "--mqtt-topic") && i+1 < argc)
             mqtt_topic = argv[++i];
+        else if (!strcmp(argv[i], "--mqtt-qos") && i+1 < argc)
+            mqtt_qos = atoi(argv[++i]);
+        else if (!strcmp(argv[i], "--mqtt-share-group") && i+1 < argc)
+            mqtt_group = argv[++i];
+        else if (!strcmp(argv[i], "--mqtt-client-id") && i+1 < argc)
+            mqtt_client_id = argv[++i];
         else if (!strcmp(argv[i], "--recv-threads") && i+1 < argc)
             n_recv_threads = atoi(argv[++i]);
         else if (!strcmp(argv[i], "--proc-threads") && i+1 < argc)
@@ -387,6 +420,15 @@ int main(int argc, char *argv[])
         }
     }
 
+    if (mqtt_qos < 0 || mqtt_qos > 2) {
+        fprintf(stderr, "--mqtt-qos must be 0, 1 or 2\n");
+        return 1;
+    }
+    if (mqtt_group && (!*mqtt_group || strpbrk(mqtt_group, "/+#"))) {
+        fprintf(stderr, "--mqtt-share-group must be non-empty without '/', '+' or '#'\n");
+        return 1;
+    }
+
     if (n_recv_threads < 1) n_recv_threads = 1;
     if (n_recv_threads > MAX_RECV_THREADS) n_recv_threads = MAX_RECV_THREADS;
     if (n_proc_threads < 1) n_proc_threads = 1;
@@ -395,8 +437,12 @@ int main(int argc, char *argv[])
     printf("=== vad-sensor-bridge (C) ===\n");
     printf("Transport:       %s\n", transport_name[transport]);
     printf("Port:            %d\n", port);
-    if (transport == TRANSPORT_MQTT)
+    if (transport == TRANSPORT_MQTT) {
         printf("MQTT broker:     %s:%d\n", mqtt_host, mqtt_port);
+        printf("MQTT QoS:        %d\n", mqtt_qos);
+        if (mqtt_group)
+            printf("MQTT group:      %s (shared, MQTT 5)\n", mqtt_group);
+    }
     printf("Recv threads:    %d\n", transport == TRANSPORT_UDP ? n_recv_threads : 1);
     printf("Proc threads:    %d\n", n_proc_threads);
     printf("Ring capacity:   %d\n", ring_cap);
@@ -477,14 +523,39 @@ int main(int argc, char *argv[])
     } else if (transport == TRANSPORT_MQTT) {
         g_mqtt_ring = &ring;
 
+        /* Shared subscription: the broker hands each message to one member
+         * of the group instead of to every subscriber */
+        mqtt_sub_t sub = { .qos = mqtt_qos };
+        int n = mqtt_group
+            ? snprintf(sub.topic, sizeof(sub.topic), "$share/%s/%s", mqtt_group, mqtt_topic)
+            : snprintf(sub.topic, sizeof(sub.topic), "%s", mqtt_topic);
+        if (n < 0 || (size_t)n >= sizeof(sub.topic)) {
+            fprintf(stderr, "MQTT: topic too long\n");
+            return 1;
+        }
+
+        /* Group members need distinct client ids, or the broker keeps
+         * disconnecting one in favour of the other */
+        char client_id[64];
+        if (mqtt_client_id)
+            snprintf(client_id, sizeof(client_id), "%s", mqtt_client_id);
+        else if (mqtt_group)
+            snprintf(client_id, sizeof(client_id), "%s-%d", DEFAULT_CLIENT_ID, (int)getpid());
+        else
+            snprintf(client_id, sizeof(client_id), "%s", DEFAULT_CLIENT_ID);
+
         mosquitto_lib_init();
 
-        struct mosquitto *mosq = mosquitto_new(DEFAULT_CLIENT_ID, true, (void *)mqtt_topic);
+        struct mosquitto *mosq = mosquitto_new(client_id, true, &sub);
         if (!mosq) {
             fprintf(stderr, "MQTT: mosquitto_new failed\n");
             return 1;
         }
 
+        /* $share is standardised in MQTT 5 (brokers differ on 3.1.1) */
+        if (mqtt_group)
+            mosquitto_int_option(mosq, MOSQ_OPT_PROTOCOL_VERSION, MQTT_PROTOCOL_V5);
+
         mosquitto_message_callback_set(mosq, mosq_on_message);
         mosquitto_connect_callback_set(mosq, mosq_on_connect);
         mosquitto_subscribe_callback_set(mosq, mosq_on_subscribe);
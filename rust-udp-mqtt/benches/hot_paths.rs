@@ -0,0 +1,156 @@
+// ─────────────────────────────────────────────────────────────────────
+//  Hot-path benchmarks (`cargo bench --features bench`)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  The per-packet work of both ports, one benchmark each:
+//
+//    esp_parse         EspPacket::parse of a full 20 ms AUDIO_UP packet
+//    sensor_parse      SensorPacket::from_binary of a sensor vector
+//    audio_rms         audio VAD (RMS energy) of 20 ms of 16 kHz PCM
+//    resample          16 kHz → 24 kHz of the same 20 ms
+//    emotional_vad     smoothing + the V/A/D weighted sums of one vector
+//    session_map       per-packet session upserts from several receive
+//                      threads: DashMap (what `transport_udp` uses) vs
+//                      the RwLock<HashMap> it replaced
+//
+//  Save a baseline before a performance change and compare after it
+//  with `bench/perf_gate.sh`, which fails on regressions.
+
+use criterion::{ criterion_group, criterion_main, Criterion, Throughput };
+use dashmap::DashMap;
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::net::SocketAddr;
+use std::sync::RwLock;
+use std::time::{ Duration, Instant };
+use vad_sensor_bridge::audio_window::AudioWindow;
+use vad_sensor_bridge::esp_audio_protocol::{ build_packet, EspPacket, FLAG_START, PKT_AUDIO_UP };
+use vad_sensor_bridge::persona::PersonaTrait;
+use vad_sensor_bridge::sensor::{ SensorPacket, SensorVector, DATA_TYPE_AUDIO, DATA_TYPE_SENSOR_VECTOR };
+use vad_sensor_bridge::sensor_smoother::SensorSmoother;
+use vad_sensor_bridge::transport_openai::resample_16k_to_24k;
+use vad_sensor_bridge::vad::{ process_packet_with, EmotionEngine, DEFAULT_THRESHOLDS };
+
+/// 20 ms of 16 kHz mono s16le speech-like PCM (mixed tones).
+fn pcm_20ms() -> Vec<u8> {
+    (0..320)
+        .flat_map(|i| {
+            let t = (i as f32) / 16_000.0;
+            let s = 6000.0 * (t * 220.0 * std::f32::consts::TAU).sin() + 2000.0 * (t * 1800.0 * std::f32::consts::TAU).sin();
+            (s as i16).to_le_bytes()
+        })
+        .collect()
+}
+
+fn packet(data_type: u8, payload: Vec<u8>) -> SensorPacket {
+    SensorPacket { seq: 1, ..SensorPacket::new(7, data_type, payload) }
+}
+
+fn sensor_vector() -> SensorVector {
+    SensorVector {
+        people_count: 0.4,
+        known_face: 1.0,
+        idle_time: 0.2,
+        sound_energy: 0.6,
+        voice_rate: 0.5,
+        motion_energy: 0.3,
+        ..SensorVector::default()
+    }
+}
+
+fn parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Elements(1));
+
+    let esp = build_packet(1, PKT_AUDIO_UP, FLAG_START, &pcm_20ms());
+    group.bench_function("esp_parse", |b| b.iter(|| EspPacket::parse(black_box(&esp))));
+
+    let sensor = packet(DATA_TYPE_SENSOR_VECTOR, sensor_vector().to_payload()).to_binary();
+    group.bench_function("sensor_parse", |b| b.iter(|| SensorPacket::from_binary(black_box(&sensor))));
+    group.finish();
+}
+
+fn audio(c: &mut Criterion) {
+    let mut group = c.benchmark_group("audio");
+    group.throughput(Throughput::Bytes(640));
+
+    let (smoother, engine) = (SensorSmoother::new(), EmotionEngine::default());
+    let window = AudioWindow::new(Duration::ZERO);
+    let pkt = packet(DATA_TYPE_AUDIO, pcm_20ms());
+    group.bench_function("audio_rms", |b| {
+        b.iter(|| {
+            process_packet_with(black_box(&pkt), PersonaTrait::Obedient, &smoother, &window, &engine, DEFAULT_THRESHOLDS)
+        })
+    });
+
+    let pcm = pcm_20ms();
+    group.bench_function("resample", |b| b.iter(|| resample_16k_to_24k(black_box(&pcm))));
+    group.finish();
+}
+
+fn emotional(c: &mut Criterion) {
+    let mut group = c.benchmark_group("emotional");
+    group.throughput(Throughput::Elements(1));
+
+    let (smoother, engine) = (SensorSmoother::new(), EmotionEngine::default());
+    let window = AudioWindow::new(Duration::ZERO);
+    let pkt = packet(DATA_TYPE_SENSOR_VECTOR, sensor_vector().to_payload());
+    group.bench_function("emotional_vad", |b| {
+        b.iter(|| {
+            process_packet_with(black_box(&pkt), PersonaTrait::Mischievous, &smoother, &window, &engine, DEFAULT_THRESHOLDS)
+        })
+    });
+    group.finish();
+}
+
+/// Receive threads hammering the session map at once.
+const MAP_THREADS: u16 = 4;
+
+/// Packets per thread per iteration.
+const MAP_PACKETS: u64 = 10_000;
+
+/// ESP sessions per thread (each thread serves its own devices).
+const MAP_SESSIONS: u16 = 64;
+
+/// Time `MAP_THREADS` threads each upserting `MAP_PACKETS` sessions
+/// through `touch(addr, seq)`, the way every AUDIO_UP refreshes its entry.
+fn contended(iters: u64, touch: impl Fn(SocketAddr, u64) + Sync) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iters {
+        std::thread::scope(|scope| {
+            for thread in 0..MAP_THREADS {
+                let touch = &touch;
+                scope.spawn(move || {
+                    for seq in 0..MAP_PACKETS {
+                        let port = 40_000 + thread * MAP_SESSIONS + ((seq as u16) % MAP_SESSIONS);
+                        touch(SocketAddr::from(([10, 0, 0, 1], port)), seq);
+                    }
+                });
+            }
+        });
+    }
+    start.elapsed()
+}
+
+fn session_map(c: &mut Criterion) {
+    let mut group = c.benchmark_group("session_map");
+    group.throughput(Throughput::Elements((MAP_THREADS as u64) * MAP_PACKETS));
+
+    let dash: DashMap<SocketAddr, u64> = DashMap::new();
+    group.bench_function("dashmap", |b| {
+        b.iter_custom(|iters| contended(iters, |addr, seq| *dash.entry(addr).or_insert(0) = black_box(seq)))
+    });
+
+    let locked: RwLock<HashMap<SocketAddr, u64>> = RwLock::default();
+    group.bench_function("rwlock_hashmap", |b| {
+        b.iter_custom(|iters| {
+            contended(iters, |addr, seq| {
+                *locked.write().unwrap().entry(addr).or_insert(0) = black_box(seq);
+            })
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parsing, audio, emotional, session_map);
+criterion_main!(benches);
@@ -0,0 +1,4 @@
+#![no_main]
+
+// ESP audio-protocol packets (audio port), OTA payloads included
+libfuzzer_sys::fuzz_target!(|data: &[u8]| vad_sensor_bridge::wire_check::esp_packet(data));
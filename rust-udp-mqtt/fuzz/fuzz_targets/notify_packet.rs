@@ -0,0 +1,4 @@
+#![no_main]
+
+// 0xAA/0xB0 notification frames (audio port)
+libfuzzer_sys::fuzz_target!(|data: &[u8]| vad_sensor_bridge::wire_check::notify_packet(data));
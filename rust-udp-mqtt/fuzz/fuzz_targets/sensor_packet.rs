@@ -0,0 +1,11 @@
+#![no_main]
+
+use arbitrary::Unstructured;
+use vad_sensor_bridge::wire_check;
+
+// Binary sensor packets (sensor port): raw bytes, then the same bytes
+// as a structured packet for the encode round trip
+libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+    wire_check::sensor_packet(data);
+    let _ = wire_check::sensor_packet_roundtrip(&mut Unstructured::new(data));
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use arbitrary::Unstructured;
+use vad_sensor_bridge::wire_check;
+
+// VAD response datagrams, v1 and v2: raw decode, then structured round trip
+libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+    wire_check::vad_response(data);
+    let _ = wire_check::vad_response_roundtrip(&mut Unstructured::new(data));
+});
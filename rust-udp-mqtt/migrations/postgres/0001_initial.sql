@@ -0,0 +1,41 @@
+-- Tables of the shared store (store.rs).  IF NOT EXISTS: databases
+-- created before migrations existed already have them.
+
+CREATE TABLE IF NOT EXISTS bridge_devices (
+    tenant      TEXT   NOT NULL,
+    sensor_id   BIGINT NOT NULL,
+    config      JSONB  NOT NULL,
+    updated_ms  BIGINT NOT NULL,
+    updated_by  TEXT   NOT NULL,
+    PRIMARY KEY (tenant, sensor_id)
+);
+CREATE TABLE IF NOT EXISTS bridge_persona (
+    tenant      TEXT   PRIMARY KEY,
+    persona     TEXT   NOT NULL,
+    updated_ms  BIGINT NOT NULL,
+    updated_by  TEXT   NOT NULL
+);
+CREATE TABLE IF NOT EXISTS bridge_sessions (
+    id             BIGSERIAL PRIMARY KEY,
+    tenant         TEXT   NOT NULL,
+    instance       TEXT   NOT NULL,
+    session_id     BIGINT NOT NULL,
+    device         TEXT   NOT NULL,
+    addr           TEXT   NOT NULL,
+    started_ms     BIGINT NOT NULL,
+    ended_ms       BIGINT,
+    openai_events  BIGINT NOT NULL,
+    responses      BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS bridge_sessions_device ON bridge_sessions (tenant, lower(device), started_ms);
+CREATE TABLE IF NOT EXISTS bridge_transcripts (
+    id        BIGSERIAL PRIMARY KEY,
+    tenant    TEXT   NOT NULL,
+    instance  TEXT   NOT NULL,
+    device    TEXT   NOT NULL,
+    role      TEXT   NOT NULL,
+    text      TEXT   NOT NULL,
+    speaker   TEXT,
+    at_ms     BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS bridge_transcripts_device ON bridge_transcripts (tenant, lower(device), at_ms);
@@ -0,0 +1,7 @@
+-- Correlation ids (trace.rs) of sessions and transcripts.  NULL on rows
+-- written before the column existed.
+
+ALTER TABLE bridge_sessions ADD COLUMN IF NOT EXISTS trace TEXT;
+ALTER TABLE bridge_transcripts ADD COLUMN IF NOT EXISTS trace TEXT;
+CREATE INDEX IF NOT EXISTS bridge_sessions_trace ON bridge_sessions (tenant, trace);
+CREATE INDEX IF NOT EXISTS bridge_transcripts_trace ON bridge_transcripts (tenant, trace);
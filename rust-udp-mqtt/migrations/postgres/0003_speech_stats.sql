@@ -0,0 +1,7 @@
+-- Speech statistics of sessions (session_log.rs).  NULL on rows written
+-- before the columns existed.
+
+ALTER TABLE bridge_sessions ADD COLUMN IF NOT EXISTS child_talk_ms BIGINT;
+ALTER TABLE bridge_sessions ADD COLUMN IF NOT EXISTS robot_talk_ms BIGINT;
+ALTER TABLE bridge_sessions ADD COLUMN IF NOT EXISTS turns INTEGER;
+ALTER TABLE bridge_sessions ADD COLUMN IF NOT EXISTS interruptions INTEGER;
@@ -0,0 +1,326 @@
+use crate::config::Config;
+use crate::devices::{ DeviceConfig, DeviceRegistry };
+use crate::diagnostics::Diagnostics;
+use crate::mqtt_health::{ self, MqttHealth };
+use crate::persona::{ PersonaState, PersonaTrait };
+use rumqttc::v5::mqttbytes::v5::{ LastWill, Packet, PublishProperties };
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::{ AsyncClient, Event };
+use serde::{ Deserialize, Serialize };
+use std::sync::atomic::{ AtomicBool, AtomicU64, Ordering };
+use std::sync::Arc;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  MQTT control plane
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Bridges behind NAT can't be reached on their REST port, but they can
+//  reach a broker.  With `--admin-mqtt-host` each bridge instance
+//  subscribes to
+//
+//    <--admin-mqtt-topic>/<instance>[/<tenant>]/cmd
+//
+//  and answers every command on `.../reply` (or the command's
+//  `reply_to` topic).  `<instance>` is `--instance-id`, by default the
+//  host name.  Commands are JSON objects tagged by `cmd`:
+//
+//    {"id": 7, "cmd": "status"}
+//    {"id": 8, "cmd": "set_persona", "persona": "cute"}        (or "index")
+//    {"id": 9, "cmd": "set_thresholds", "sensor_id": 42, "arousal_threshold": 0.5}
+//    {"id": 10, "cmd": "pause_ingest"}   /  {"cmd": "resume_ingest"}
+//    {"id": 11, "cmd": "diagnostics", "device": "aa:bb:cc:dd:ee:ff"}
+//
+//  Replies echo `id` and `cmd`:  {"id": 8, "cmd": "set_persona",
+//  "ok": true, "result": {...}}  or  {"ok": false, "error": "..."}.
+//
+//  Presence: `.../status` holds a retained "online" while the bridge is
+//  connected and "offline" (the connection's last will) once it is gone.
+//
+//  Anyone who can publish to the topic can administer the bridge: lock
+//  it down with broker credentials / ACLs.
+
+/// Sensor-packet ingest switch (`pause_ingest` / `resume_ingest`).
+/// While paused the VAD workers drop sensor packets unprocessed.
+/// Clone-friendly (Arc inside).
+#[derive(Clone, Default)]
+pub struct IngestGate {
+    paused: Arc<AtomicBool>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl IngestGate {
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// True (and the packet counted) when a packet must be dropped.
+    pub fn drop_packet(&self) -> bool {
+        if !self.is_paused() {
+            return false;
+        }
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Packets dropped while paused, since startup.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// What the control plane can change.  Clone-friendly.
+#[derive(Clone)]
+pub struct AdminPlane {
+    pub persona: PersonaState,
+    pub devices: DeviceRegistry,
+    pub diagnostics: Diagnostics,
+    pub ingest: IngestGate,
+}
+
+/// One command message.
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// Opaque correlation id, echoed in the reply
+    #[serde(default)]
+    id: serde_json::Value,
+    /// Reply here instead of the instance's reply topic
+    #[serde(default)]
+    reply_to: Option<String>,
+    #[serde(flatten)]
+    command: Command,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Command {
+    Status,
+    SetPersona {
+        #[serde(default)]
+        persona: Option<PersonaTrait>,
+        #[serde(default)]
+        index: Option<u8>,
+    },
+    SetThresholds {
+        sensor_id: u32,
+        #[serde(default)]
+        audio_threshold: Option<f64>,
+        #[serde(default)]
+        arousal_threshold: Option<f32>,
+    },
+    PauseIngest,
+    ResumeIngest,
+    Diagnostics {
+        device: String,
+    },
+}
+
+impl Command {
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Status => "status",
+            Command::SetPersona { .. } => "set_persona",
+            Command::SetThresholds { .. } => "set_thresholds",
+            Command::PauseIngest => "pause_ingest",
+            Command::ResumeIngest => "resume_ingest",
+            Command::Diagnostics { .. } => "diagnostics",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Reply {
+    id: serde_json::Value,
+    cmd: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl AdminPlane {
+    /// Connect to `--admin-mqtt-host` and serve commands until shutdown
+    /// (no-op when the control plane is disabled).
+    pub fn spawn(self, config: &Config, health: &MqttHealth) {
+        if config.admin_mqtt_host.is_empty() {
+            return;
+        }
+        let mut base = format!("{}/{}", config.admin_mqtt_topic, config.resolved_instance_id());
+        if !config.tenant_id.is_empty() {
+            base = format!("{base}/{}", config.tenant_id);
+        }
+        // One client id per instance (and tenant)
+        let mut opts = mqtt_health::client_options("admin", &base.replace('/', "-"), &config.admin_mqtt_host, config.admin_mqtt_port);
+        // The broker flips the retained presence to offline if we vanish
+        opts.set_last_will(LastWill::new(format!("{base}/status"), "offline", QoS::AtLeastOnce, true, None));
+        if !config.admin_mqtt_username.is_empty() {
+            opts.set_credentials(&config.admin_mqtt_username, &config.admin_mqtt_password);
+        }
+        info!(
+            broker = format!("{}:{}", config.admin_mqtt_host, config.admin_mqtt_port),
+            topic = format!("{base}/cmd"),
+            "🛂 MQTT control plane enabled"
+        );
+        let props = crate::mqtt5::json("admin-reply", &config.tenant_id, config.mqtt_expiry_secs);
+        mqtt_health::spawn_client(opts, 64, health, "admin", move |client, event| self.on_event(client, event, &base, &props));
+    }
+
+    /// Subscribe and announce on every ConnAck; serve commands.
+    fn on_event(&self, client: &AsyncClient, event: Event, base: &str, props: &PublishProperties) {
+        let cmd_topic = format!("{base}/cmd");
+        let reply_topic = format!("{base}/reply");
+        let status_topic = format!("{base}/status");
+        match event {
+            Event::Incoming(Packet::ConnAck(_)) => {
+                // (Re)subscribe on every connect: the session is not persistent
+                if let Err(e) = client.try_subscribe(cmd_topic.as_str(), QoS::AtLeastOnce) {
+                    warn!(topic = %cmd_topic, error = %e, "failed to subscribe");
+                }
+                // Overwrites the retained "offline" of a previous will
+                if let Err(e) = client.try_publish(status_topic.as_str(), QoS::AtLeastOnce, true, "online") {
+                    warn!(topic = %status_topic, error = %e, "failed to publish presence");
+                }
+            }
+            Event::Incoming(Packet::Publish(msg)) if msg.topic == cmd_topic => {
+                // Commands run on their own task: diagnostics take seconds
+                let admin = self.clone();
+                let client = client.clone();
+                let props = props.clone();
+                tokio::spawn(async move {
+                    let (topic, reply) = match serde_json::from_slice::<Request>(&msg.payload) {
+                        Ok(req) => (req.reply_to.unwrap_or(reply_topic), admin.handle(req.id, req.command).await),
+                        Err(e) => {
+                            let reply = Reply {
+                                id: serde_json::Value::Null,
+                                cmd: String::new(),
+                                ok: false,
+                                result: None,
+                                error: Some(format!("invalid command: {e}")),
+                            };
+                            (reply_topic, reply)
+                        }
+                    };
+                    let body = serde_json::to_vec(&reply).unwrap_or_default();
+                    if let Err(e) = client.publish_with_properties(topic, QoS::AtLeastOnce, false, body, props).await {
+                        warn!(error = %e, "failed to queue admin reply");
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+
+    async fn handle(&self, id: serde_json::Value, command: Command) -> Reply {
+        let cmd = command.name().to_string();
+        info!(cmd = %cmd, "🛂 admin command");
+        match self.execute(command).await {
+            Ok(result) => Reply { id, cmd, ok: true, result: Some(result), error: None },
+            Err(error) => {
+                warn!(cmd = %cmd, error = %error, "admin command failed");
+                Reply { id, cmd, ok: false, result: None, error: Some(error) }
+            }
+        }
+    }
+
+    async fn execute(&self, command: Command) -> Result<serde_json::Value, String> {
+        match command {
+            Command::Status => {}
+            Command::SetPersona { persona, index } => {
+                let persona = match (persona, index) {
+                    (Some(p), _) => p,
+                    (None, Some(i)) =>
+                        PersonaTrait::from_index(i).ok_or_else(|| format!("invalid persona index: {i} (valid: 0–3)"))?,
+                    (None, None) => {
+                        return Err("provide either \"persona\" (string) or \"index\" (0–3)".into());
+                    }
+                };
+                self.persona.set(persona).await;
+            }
+            Command::SetThresholds { sensor_id, audio_threshold, arousal_threshold } => {
+                DeviceConfig { audio_threshold, arousal_threshold, ..Default::default() }.validate()?;
+                let cfg = self.devices.set_thresholds(sensor_id, audio_threshold, arousal_threshold);
+                return Ok(
+                    serde_json::json!({
+                    "sensor_id": sensor_id,
+                    "config": cfg,
+                    "effective": self.devices.thresholds(sensor_id),
+                })
+                );
+            }
+            Command::PauseIngest => self.ingest.set_paused(true),
+            Command::ResumeIngest => self.ingest.set_paused(false),
+            Command::Diagnostics { device } => {
+                let mut report = self.diagnostics.run(&device).await.map_err(|e| e.to_string())?;
+                report.device = device;
+                return serde_json::to_value(report).map_err(|e| e.to_string());
+            }
+        }
+        let persona = self.persona.get().await;
+        Ok(
+            serde_json::json!({
+            "persona": persona,
+            "ingest_paused": self.ingest.is_paused(),
+            "ingest_dropped": self.ingest.dropped(),
+            "devices": self.devices.list().len(),
+        })
+        )
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::Thresholds;
+
+    fn admin() -> AdminPlane {
+        AdminPlane {
+            persona: PersonaState::new(PersonaTrait::Obedient),
+            devices: DeviceRegistry::new(Thresholds { audio: 100.0, arousal: 0.35 }),
+            diagnostics: Diagnostics::new().0,
+            ingest: IngestGate::default(),
+        }
+    }
+
+    fn request(json: &str) -> Request {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_commands() {
+        let admin = admin();
+
+        let req = request(r#"{"id": 1, "cmd": "set_persona", "persona": "cute"}"#);
+        let reply = admin.handle(req.id, req.command).await;
+        assert!(reply.ok);
+        assert_eq!(reply.id, serde_json::json!(1));
+        assert_eq!(reply.result.unwrap()["persona"], "cute");
+
+        let req = request(r#"{"cmd": "set_thresholds", "sensor_id": 4, "arousal_threshold": 0.6}"#);
+        let reply = admin.handle(req.id, req.command).await;
+        assert_eq!(reply.result.unwrap()["effective"]["arousal"], 0.6f32 as f64);
+        assert_eq!(admin.devices.thresholds(4).arousal, 0.6);
+
+        let req = request(r#"{"cmd": "set_thresholds", "sensor_id": 4, "arousal_threshold": 7}"#);
+        assert!(!admin.handle(req.id, req.command).await.ok);
+
+        let req = request(r#"{"cmd": "pause_ingest", "reply_to": "ops/replies"}"#);
+        assert_eq!(req.reply_to.as_deref(), Some("ops/replies"));
+        admin.handle(req.id, req.command).await;
+        assert!(admin.ingest.drop_packet());
+        let req = request(r#"{"cmd": "resume_ingest"}"#);
+        let reply = admin.handle(req.id, req.command).await;
+        assert_eq!(reply.result.unwrap()["ingest_dropped"], 1);
+        assert!(!admin.ingest.drop_packet());
+
+        assert!(serde_json::from_str::<Request>(r#"{"cmd": "reboot"}"#).is_err());
+    }
+}
@@ -0,0 +1,390 @@
+use crate::clock::{ self, SharedClock };
+use crate::config::Config;
+use crate::downlink_pacing::DownlinkPacer;
+use crate::stats::StatsSnapshot;
+use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+use tokio::sync::mpsc;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Anomaly detection on pipeline metrics
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Static thresholds miss slow degradations and fire constantly on
+//  noisy links.  Each series below instead keeps an exponentially
+//  weighted mean and variance (weight `--anomaly-alpha` per sample) and
+//  flags a sample whose z-score passes `--anomaly-z`:
+//
+//    parse_error_rate   parse errors / received packets, per stats
+//                       interval (`--stats-interval-secs`)
+//    drop_rate          channel drops + shed packets / received packets
+//    downlink_loss      a device's reported AUDIO_DOWN loss (heartbeat
+//                       link reports, see `downlink_pacing.rs`)
+//    silent_device      a device's current silence against its usual
+//                       gap between packets, checked every presence
+//                       sweep — flagged before `--presence-offline-secs`
+//
+//  A series needs `WARMUP` samples before it can flag, its standard
+//  deviation is floored (so a perfectly flat baseline does not alarm on
+//  noise), and a flagged series re-arms once its z-score is back under
+//  half the limit.  Flagged samples are folded into the baseline
+//  clipped to the limit, so one spike does not blind the detector.
+//
+//  Anomalies are logged, kept for `GET /anomalies` and POSTed to every
+//  `--anomaly-webhook`.
+
+/// Samples before a series may flag.
+const WARMUP: u64 = 20;
+
+/// Anomalies kept for `GET /anomalies`.
+const RECENT: usize = 100;
+
+/// Which series an anomaly was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    ParseErrorRate,
+    DropRate,
+    DownlinkLoss,
+    SilentDevice,
+}
+
+impl AnomalyKind {
+    /// Smallest standard deviation a z-score is taken against.
+    fn min_std(self) -> f64 {
+        match self {
+            AnomalyKind::ParseErrorRate | AnomalyKind::DropRate | AnomalyKind::DownlinkLoss => 0.01,
+            // seconds
+            AnomalyKind::SilentDevice => 1.0,
+        }
+    }
+}
+
+/// One flagged sample.
+#[derive(Debug, Clone, Serialize)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    /// Device of a per-device series (MAC or `ip:port`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    pub value: f64,
+    /// Mean of the series before the sample
+    pub baseline: f64,
+    pub z: f64,
+    pub timestamp_ms: u64,
+    /// Tenant of the bridge instance (omitted in single-tenant mode)
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tenant: String,
+}
+
+/// Exponentially weighted mean / variance of one series.
+#[derive(Debug, Clone, Copy, Default)]
+struct Ewma {
+    mean: f64,
+    var: f64,
+    samples: u64,
+    /// Flagged and not yet back to normal
+    flagged: bool,
+}
+
+impl Ewma {
+    /// z-score of `x` against the baseline (None while warming up).
+    fn z(&self, x: f64, min_std: f64) -> Option<f64> {
+        (self.samples >= WARMUP).then(|| (x - self.mean) / self.var.sqrt().max(min_std))
+    }
+
+    fn update(&mut self, x: f64, alpha: f64) {
+        if self.samples == 0 {
+            self.mean = x;
+        } else {
+            let diff = x - self.mean;
+            self.mean += alpha * diff;
+            self.var = (1.0 - alpha) * (self.var + alpha * diff * diff);
+        }
+        self.samples += 1;
+    }
+}
+
+/// Cadence of one device: time between packets, and the last one seen.
+#[derive(Debug, Clone, Copy)]
+struct Cadence {
+    gaps: Ewma,
+    last_heard: Instant,
+}
+
+/// Detector over every series.  Clone-friendly; a disabled handle
+/// (no `--anomaly-detection`) ignores everything.
+#[derive(Clone)]
+pub struct AnomalyDetector {
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    alpha: f64,
+    z_limit: f64,
+    series: DashMap<(AnomalyKind, String), Ewma>,
+    cadence: DashMap<String, Cadence>,
+    /// Link reports seen per device (a link is sampled once per report)
+    link_reports: DashMap<String, u64>,
+    recent: Mutex<VecDeque<Anomaly>>,
+    downlink: DownlinkPacer,
+    /// Webhook delivery (None = no `--anomaly-webhook`)
+    sink: Option<mpsc::Sender<Anomaly>>,
+    tenant: String,
+    clock: SharedClock,
+}
+
+impl AnomalyDetector {
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    pub fn new(alpha: f64, z_limit: f64, downlink: DownlinkPacer, tenant: &str, clock: SharedClock) -> Self {
+        Self {
+            inner: Some(
+                Arc::new(Inner {
+                    alpha: alpha.clamp(0.001, 1.0),
+                    z_limit,
+                    series: DashMap::new(),
+                    cadence: DashMap::new(),
+                    link_reports: DashMap::new(),
+                    recent: Mutex::default(),
+                    downlink,
+                    sink: None,
+                    tenant: tenant.to_string(),
+                    clock,
+                })
+            ),
+        }
+    }
+
+    /// Detector for `--anomaly-detection`, with the webhook delivery task.
+    pub fn from_config(config: &Config, downlink: DownlinkPacer, clock: SharedClock) -> anyhow::Result<Self> {
+        if !config.anomaly_detection {
+            return Ok(Self::disabled());
+        }
+        let mut detector = Self::new(config.anomaly_alpha, config.anomaly_z, downlink, &config.tenant_id, clock);
+        info!(
+            alpha = config.anomaly_alpha,
+            z = config.anomaly_z,
+            webhooks = config.anomaly_webhook.len(),
+            "📈 anomaly detection enabled"
+        );
+        if !config.anomaly_webhook.is_empty() {
+            let http = reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?;
+            let webhooks = config.anomaly_webhook.clone();
+            let (tx, mut rx) = mpsc::channel::<Anomaly>(256);
+            tokio::spawn(async move {
+                while let Some(anomaly) = rx.recv().await {
+                    for url in &webhooks {
+                        match http.post(url).json(&anomaly).send().await {
+                            Ok(resp) if resp.status().is_success() => {}
+                            Ok(resp) => warn!(url = %url, status = %resp.status(), "anomaly webhook rejected"),
+                            Err(e) => warn!(url = %url, error = %e, "anomaly webhook failed"),
+                        }
+                    }
+                }
+            });
+            if let Some(inner) = detector.inner.as_mut().and_then(Arc::get_mut) {
+                inner.sink = Some(tx);
+            }
+        }
+        Ok(detector)
+    }
+
+    /// Sample the pipeline counters of one stats interval, plus every
+    /// device's link loss reported since the last call.
+    pub fn observe_stats(&self, snap: &StatsSnapshot) {
+        let Some(ref inner) = self.inner else {
+            return;
+        };
+        if snap.recv_packets > 0 {
+            let packets = snap.recv_packets as f64;
+            inner.sample(AnomalyKind::ParseErrorRate, None, (snap.parse_errors as f64) / packets);
+            inner.sample(AnomalyKind::DropRate, None, ((snap.channel_drops + snap.shed) as f64) / packets);
+        }
+        for link in inner.downlink.list() {
+            let device = link.addr.to_string();
+            let seen = inner.link_reports.insert(device.clone(), link.reports).unwrap_or(0);
+            if link.reports > seen {
+                inner.sample(AnomalyKind::DownlinkLoss, Some(&device), link.loss);
+            }
+        }
+    }
+
+    /// A presence sweep saw `device` last heard at `last_heard`.
+    pub fn observe_heard(&self, device: &str, last_heard: Instant, now: Instant) {
+        let Some(ref inner) = self.inner else {
+            return;
+        };
+        let mut cadence = inner.cadence
+            .entry(device.to_string())
+            .or_insert(Cadence { gaps: Ewma::default(), last_heard });
+        let kind = AnomalyKind::SilentDevice;
+        if last_heard > cadence.last_heard {
+            // Heard again: learn the gap (clipped) and re-arm
+            let gap = last_heard.duration_since(cadence.last_heard).as_secs_f64();
+            let gap = match cadence.gaps.samples >= WARMUP {
+                true => gap.min(cadence.gaps.mean + inner.z_limit * cadence.gaps.var.sqrt().max(kind.min_std())),
+                false => gap,
+            };
+            cadence.gaps.update(gap, inner.alpha);
+            cadence.gaps.flagged = false;
+            cadence.last_heard = last_heard;
+            return;
+        }
+        let silence = now.saturating_duration_since(cadence.last_heard).as_secs_f64();
+        let Some(z) = cadence.gaps.z(silence, kind.min_std()) else {
+            return;
+        };
+        if z >= inner.z_limit && !cadence.gaps.flagged {
+            cadence.gaps.flagged = true;
+            let baseline = cadence.gaps.mean;
+            drop(cadence);
+            inner.flag(kind, Some(device), silence, baseline, z);
+        }
+    }
+
+    /// Latest anomalies, newest first.
+    pub fn recent(&self) -> Vec<Anomaly> {
+        let Some(ref inner) = self.inner else {
+            return Vec::new();
+        };
+        let recent = inner.recent.lock().unwrap_or_else(|e| e.into_inner());
+        recent.iter().rev().cloned().collect()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+}
+
+impl Inner {
+    /// Score `value` against its series, flag it if anomalous, and fold
+    /// it into the baseline.
+    fn sample(&self, kind: AnomalyKind, device: Option<&str>, value: f64) {
+        let key = (kind, device.unwrap_or_default().to_string());
+        let mut series = self.series.entry(key).or_default();
+        let min_std = kind.min_std();
+        let mut folded = value;
+        let mut flag = None;
+        if let Some(z) = series.z(value, min_std) {
+            let limit = series.mean + self.z_limit * series.var.sqrt().max(min_std);
+            if z >= self.z_limit {
+                folded = value.min(limit);
+                if !series.flagged {
+                    series.flagged = true;
+                    flag = Some((series.mean, z));
+                }
+            } else if z < self.z_limit / 2.0 {
+                series.flagged = false;
+            }
+        }
+        series.update(folded, self.alpha);
+        drop(series);
+        if let Some((baseline, z)) = flag {
+            self.flag(kind, device, value, baseline, z);
+        }
+    }
+
+    fn flag(&self, kind: AnomalyKind, device: Option<&str>, value: f64, baseline: f64, z: f64) {
+        let anomaly = Anomaly {
+            kind,
+            device: device.map(str::to_string),
+            value,
+            baseline,
+            z,
+            timestamp_ms: clock::unix_millis(self.clock.as_ref()),
+            tenant: self.tenant.clone(),
+        };
+        warn!(
+            kind = ?kind,
+            device = device.unwrap_or("-"),
+            value = format!("{value:.4}"),
+            baseline = format!("{baseline:.4}"),
+            z = format!("{z:.1}"),
+            "📈 anomaly"
+        );
+        if let Some(ref sink) = self.sink {
+            if sink.try_send(anomaly.clone()).is_err() {
+                warn!("anomaly queue full — dropping webhook delivery");
+            }
+        }
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        recent.push_back(anomaly);
+        while recent.len() > RECENT {
+            recent.pop_front();
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{ Clock, SimClock };
+
+    fn snapshot(recv_packets: u64, parse_errors: u64) -> StatsSnapshot {
+        StatsSnapshot {
+            recv_pps: 0.0,
+            recv_mbps: 0.0,
+            proc_pps: 0.0,
+            recv_packets,
+            vad_active: 0,
+            parse_errors,
+            recv_errors: 0,
+            channel_drops: 0,
+            shed: 0,
+            inference_errors: 0,
+            sensor_clients: 0,
+            esp_online: 0,
+        }
+    }
+
+    #[test]
+    fn test_error_spike_and_silent_device_flag_once() {
+        let sim = SimClock::starting_at(std::time::SystemTime::now());
+        let detector = AnomalyDetector::new(0.1, 4.0, DownlinkPacer::default(), "", Arc::new(sim.clone()));
+
+        // 1% parse errors is this link's normal; 2% is within the floor
+        for i in 0..WARMUP {
+            detector.observe_stats(&snapshot(1000, 10 + (i % 3)));
+        }
+        detector.observe_stats(&snapshot(1000, 20));
+        assert!(detector.recent().is_empty());
+        detector.observe_stats(&snapshot(1000, 300));
+        detector.observe_stats(&snapshot(1000, 300));
+        let recent = detector.recent();
+        assert_eq!(recent.len(), 1, "a sustained spike flags once");
+        assert_eq!(recent[0].kind, AnomalyKind::ParseErrorRate);
+        assert!(recent[0].z >= 4.0 && recent[0].baseline < 0.02);
+
+        // A device heard every 2 s goes quiet
+        let device = "aa:bb:cc:dd:ee:ff";
+        let mut last = sim.now();
+        for _ in 0..=WARMUP {
+            last = sim.now();
+            detector.observe_heard(device, last, last);
+            sim.advance(Duration::from_secs(2));
+        }
+        sim.advance(Duration::from_secs(3));
+        detector.observe_heard(device, last, sim.now());
+        assert_eq!(detector.recent().len(), 1, "5 s of silence is within 2 s + 4 × 1 s");
+        for _ in 0..2 {
+            sim.advance(Duration::from_secs(1));
+            detector.observe_heard(device, last, sim.now());
+        }
+        let recent = detector.recent();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].kind, AnomalyKind::SilentDevice);
+        assert_eq!(recent[0].device.as_deref(), Some("aa:bb:cc:dd:ee:ff"));
+        assert!(!AnomalyDetector::disabled().is_enabled());
+    }
+}
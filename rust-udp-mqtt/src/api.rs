@@ -1,9 +1,335 @@
+use crate::anomaly::AnomalyDetector;
+use crate::api_limits::{ self, ApiLimits };
+use crate::audio_analysis::{ AnalysisError, AnalyzeQuery, AudioAnalyzer };
+use crate::audio_routes::{ AudioRoutes, RouteError, RouteSpec };
+use crate::broadcast::{ Broadcaster, MAX_WAV_BYTES };
+use crate::calibrate::CHANNEL_NAMES;
+use crate::capture::{ CaptureRequest, PacketCapture };
+use crate::clips::{ ClipPlayer, PlayError };
+use crate::cluster::Cluster;
+use crate::conversation::{ ConversationStore, Turn };
+use crate::device_data::{ DeviceData, ExportError };
+use crate::device_import::{ self, ImportError, TenantScope };
+use crate::devices::{ DeviceConfig, DeviceGroup, DeviceProfile, DeviceRegistry, ImportReport, Location, Site, Thresholds };
+use crate::diagnostics::Diagnostics;
+use crate::downlink_pacing::DownlinkPacer;
+use crate::emotion_history::{ EmotionHistory, EmotionQuery, EmotionTimeline };
+use crate::events::EventBus;
+use crate::experiments::Experiments;
+use crate::handover::Sockets;
+use crate::mqtt_health::MqttHealth;
+use crate::ota::{ Ota, OtaError, MAX_IMAGE_BYTES };
 use crate::persona::{ PersonaState, PersonaTrait };
-use axum::{ extract::State, http::StatusCode, response::IntoResponse, routing::get, Json, Router };
+use crate::presence::Presence;
+use crate::quiet_hours::{ QuietHours, QuietSchedule };
+use crate::recording_policy::{ RecordingPolicies, RecordingPolicy };
+use crate::recordings::{ RecordingError, Recordings, TranscodeQuery };
+use crate::redaction::{ self, RedactRequest, RedactionReport };
+use crate::reports::{ self, DailyReports };
+use crate::self_test::SelfTestReport;
+use crate::sensor::SENSOR_VECTOR_LEN;
+use crate::sensor_schema::{ DataSchema, SchemaRegistry };
+use crate::sensor_smoother::SmootherState;
+use crate::session_log::SessionLogs;
+use crate::sound_levels::{ SoundLevelQuery, SoundLevels };
+use crate::stats::Stats;
+use crate::speakers::{ Diarizer, SpeakerSummary };
+use crate::start_policy::{ StartPolicies, StartPolicy };
+use crate::subscriptions::{ Rule, Subscriptions };
+use crate::systemd::Liveness;
+use crate::telemetry::{ Counters, DeviceTelemetry, TelemetryQuery };
+use crate::tenants::{ TenantId, TenantInfo };
+use crate::transport_openai::OpenAiHealth;
+use crate::transport_udp::{ prompt_mode, PromptMode };
+use crate::vad::{ EmotionEngine, EmotionWeights };
+use crate::volume::{ OutputChange, VolumeControl, VolumeError };
+use axum::{
+    body::{ Body, Bytes },
+    extract::{ ws::{ Message, WebSocket, WebSocketUpgrade }, DefaultBodyLimit, FromRef, Path, Query, State },
+    http::{ header, HeaderMap, StatusCode },
+    response::IntoResponse,
+    routing::{ delete, get, post, put },
+    Json,
+    Router,
+};
 use serde::{ Deserialize, Serialize };
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
-use tokio::net::TcpListener;
-use tracing::info;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{ debug, info };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Shared API state
+// ─────────────────────────────────────────────────────────────────────
+
+/// Everything the REST handlers need.  Handlers extract only the piece
+/// they use via `State<T>` (see the `FromRef` impls below).
+#[derive(Clone)]
+pub struct ApiState {
+    /// This bridge's tenant ("" = single tenant)
+    pub tenant: TenantId,
+    pub persona: PersonaState,
+    /// The VAD workers' emotion engine (persona weights / preview)
+    pub emotion_engine: Arc<EmotionEngine>,
+    pub events: EventBus,
+    pub devices: DeviceRegistry,
+    pub conversations: ConversationStore,
+    pub clips: ClipPlayer,
+    pub broadcast: Broadcaster,
+    pub diagnostics: Diagnostics,
+    pub ota: Ota,
+    pub capture: PacketCapture,
+    pub emotions: EmotionHistory,
+    pub sound_levels: SoundLevels,
+    /// Bridge packet counters (`GET /stats`)
+    pub stats: Arc<Stats>,
+    pub subscriptions: Subscriptions,
+    pub cluster: Cluster,
+    pub sessions: SessionLogs,
+    pub start_policies: StartPolicies,
+    pub recording_policies: RecordingPolicies,
+    pub reports: DailyReports,
+    pub anomalies: AnomalyDetector,
+    pub experiments: Experiments,
+    pub downlink: DownlinkPacer,
+    pub speakers: Diarizer,
+    pub volume: VolumeControl,
+    pub quiet: QuietHours,
+    pub routes: AudioRoutes,
+    pub telemetry: DeviceTelemetry,
+    pub presence: Presence,
+    pub device_data: DeviceData,
+    pub recordings: Recordings,
+    pub analyzer: AudioAnalyzer,
+    pub schemas: SchemaRegistry,
+    pub openai: OpenAiHealth,
+    pub mqtt: MqttHealth,
+    pub self_test: SelfTestReport,
+    pub liveness: Liveness,
+}
+
+impl FromRef<ApiState> for PersonaState {
+    fn from_ref(state: &ApiState) -> Self {
+        state.persona.clone()
+    }
+}
+
+impl FromRef<ApiState> for Arc<EmotionEngine> {
+    fn from_ref(state: &ApiState) -> Self {
+        state.emotion_engine.clone()
+    }
+}
+
+impl FromRef<ApiState> for EventBus {
+    fn from_ref(state: &ApiState) -> Self {
+        state.events.clone()
+    }
+}
+
+impl FromRef<ApiState> for DeviceRegistry {
+    fn from_ref(state: &ApiState) -> Self {
+        state.devices.clone()
+    }
+}
+
+impl FromRef<ApiState> for TenantId {
+    fn from_ref(state: &ApiState) -> Self {
+        state.tenant.clone()
+    }
+}
+
+impl FromRef<ApiState> for OpenAiHealth {
+    fn from_ref(state: &ApiState) -> Self {
+        state.openai.clone()
+    }
+}
+
+impl FromRef<ApiState> for MqttHealth {
+    fn from_ref(state: &ApiState) -> Self {
+        state.mqtt.clone()
+    }
+}
+
+impl FromRef<ApiState> for SelfTestReport {
+    fn from_ref(state: &ApiState) -> Self {
+        state.self_test.clone()
+    }
+}
+
+impl FromRef<ApiState> for Liveness {
+    fn from_ref(state: &ApiState) -> Self {
+        state.liveness.clone()
+    }
+}
+
+impl FromRef<ApiState> for DownlinkPacer {
+    fn from_ref(state: &ApiState) -> Self {
+        state.downlink.clone()
+    }
+}
+
+impl FromRef<ApiState> for ClipPlayer {
+    fn from_ref(state: &ApiState) -> Self {
+        state.clips.clone()
+    }
+}
+
+impl FromRef<ApiState> for Diagnostics {
+    fn from_ref(state: &ApiState) -> Self {
+        state.diagnostics.clone()
+    }
+}
+
+impl FromRef<ApiState> for Ota {
+    fn from_ref(state: &ApiState) -> Self {
+        state.ota.clone()
+    }
+}
+
+impl FromRef<ApiState> for PacketCapture {
+    fn from_ref(state: &ApiState) -> Self {
+        state.capture.clone()
+    }
+}
+
+impl FromRef<ApiState> for EmotionHistory {
+    fn from_ref(state: &ApiState) -> Self {
+        state.emotions.clone()
+    }
+}
+
+impl FromRef<ApiState> for Recordings {
+    fn from_ref(state: &ApiState) -> Self {
+        state.recordings.clone()
+    }
+}
+
+impl FromRef<ApiState> for AudioAnalyzer {
+    fn from_ref(state: &ApiState) -> Self {
+        state.analyzer.clone()
+    }
+}
+
+impl FromRef<ApiState> for SoundLevels {
+    fn from_ref(state: &ApiState) -> Self {
+        state.sound_levels.clone()
+    }
+}
+
+impl FromRef<ApiState> for Subscriptions {
+    fn from_ref(state: &ApiState) -> Self {
+        state.subscriptions.clone()
+    }
+}
+
+impl FromRef<ApiState> for Broadcaster {
+    fn from_ref(state: &ApiState) -> Self {
+        state.broadcast.clone()
+    }
+}
+
+impl FromRef<ApiState> for AudioRoutes {
+    fn from_ref(state: &ApiState) -> Self {
+        state.routes.clone()
+    }
+}
+
+impl FromRef<ApiState> for DeviceTelemetry {
+    fn from_ref(state: &ApiState) -> Self {
+        state.telemetry.clone()
+    }
+}
+
+impl FromRef<ApiState> for Presence {
+    fn from_ref(state: &ApiState) -> Self {
+        state.presence.clone()
+    }
+}
+
+impl FromRef<ApiState> for Cluster {
+    fn from_ref(state: &ApiState) -> Self {
+        state.cluster.clone()
+    }
+}
+
+impl FromRef<ApiState> for SessionLogs {
+    fn from_ref(state: &ApiState) -> Self {
+        state.sessions.clone()
+    }
+}
+
+impl FromRef<ApiState> for StartPolicies {
+    fn from_ref(state: &ApiState) -> Self {
+        state.start_policies.clone()
+    }
+}
+
+impl FromRef<ApiState> for RecordingPolicies {
+    fn from_ref(state: &ApiState) -> Self {
+        state.recording_policies.clone()
+    }
+}
+
+impl FromRef<ApiState> for AnomalyDetector {
+    fn from_ref(state: &ApiState) -> Self {
+        state.anomalies.clone()
+    }
+}
+
+impl FromRef<ApiState> for Experiments {
+    fn from_ref(state: &ApiState) -> Self {
+        state.experiments.clone()
+    }
+}
+
+impl FromRef<ApiState> for DailyReports {
+    fn from_ref(state: &ApiState) -> Self {
+        state.reports.clone()
+    }
+}
+
+impl FromRef<ApiState> for Diarizer {
+    fn from_ref(state: &ApiState) -> Self {
+        state.speakers.clone()
+    }
+}
+
+impl FromRef<ApiState> for VolumeControl {
+    fn from_ref(state: &ApiState) -> Self {
+        state.volume.clone()
+    }
+}
+
+impl FromRef<ApiState> for QuietHours {
+    fn from_ref(state: &ApiState) -> Self {
+        state.quiet.clone()
+    }
+}
+
+impl FromRef<ApiState> for DeviceData {
+    fn from_ref(state: &ApiState) -> Self {
+        state.device_data.clone()
+    }
+}
+
+impl FromRef<ApiState> for SchemaRegistry {
+    fn from_ref(state: &ApiState) -> Self {
+        state.schemas.clone()
+    }
+}
+
+impl FromRef<ApiState> for ConversationStore {
+    fn from_ref(state: &ApiState) -> Self {
+        state.conversations.clone()
+    }
+}
+
+impl FromRef<ApiState> for Arc<Stats> {
+    fn from_ref(state: &ApiState) -> Self {
+        state.stats.clone()
+    }
+}
 
 // ─────────────────────────────────────────────────────────────────────
 //  JSON request / response types
@@ -36,11 +362,278 @@ struct SetPersonaRequest {
     index: Option<u8>,
 }
 
+#[derive(Serialize)]
+struct PersonaWeightsResponse {
+    persona: PersonaTrait,
+    /// Order of the `levels` / `rates` entries
+    channels: [&'static str; SENSOR_VECTOR_LEN],
+    #[serde(flatten)]
+    weights: EmotionWeights,
+}
+
+#[derive(Deserialize)]
+struct PersonaPreviewRequest {
+    /// Channel name → value in [0, 1]; missing channels are 0
+    sensors: BTreeMap<String, f32>,
+}
+
+#[derive(Serialize)]
+struct PersonaPreview {
+    persona: PersonaTrait,
+    valence: f32,
+    arousal: f32,
+    dominance: f32,
+}
+
+#[derive(Deserialize)]
+struct EvaluateRequest {
+    /// The 10 raw channels, in `CHANNEL_NAMES` order
+    sensors: [f32; SENSOR_VECTOR_LEN],
+    /// Default: the sensor's persona, as the VAD workers pick it
+    #[serde(default)]
+    persona: Option<PersonaTrait>,
+    /// Whose persona / thresholds apply (default 0)
+    #[serde(default)]
+    sensor_id: u32,
+    /// State before this vector; default: a sensor's first packet
+    #[serde(default)]
+    smoother: SmootherState,
+}
+
+#[derive(Serialize)]
+struct EvaluateResponse {
+    persona: PersonaTrait,
+    valence: f32,
+    arousal: f32,
+    dominance: f32,
+    is_active: bool,
+    /// The prompt style this V/A/D maps to
+    emotion: PromptMode,
+    /// State after this vector — pass it back in to continue a sequence
+    smoother: SmootherState,
+}
+
 #[derive(Serialize)]
 struct ErrorResponse {
     error: String,
 }
 
+#[derive(Serialize)]
+struct DeviceResponse {
+    sensor_id: u32,
+    #[serde(flatten)]
+    config: DeviceConfig,
+    /// Thresholds actually applied (overrides merged over defaults)
+    effective: Thresholds,
+}
+
+#[derive(Serialize)]
+struct ConversationResponse {
+    device: String,
+    active: bool,
+    turns: Vec<Turn>,
+}
+
+#[derive(Serialize)]
+struct ClipEntry {
+    name: String,
+    duration_ms: u64,
+}
+
+#[derive(Serialize)]
+struct PlayResponse {
+    device: String,
+    clip: String,
+    addr: String,
+    duration_ms: u64,
+}
+
+#[derive(Deserialize)]
+struct SayRequest {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct BroadcastQuery {
+    /// Also play on devices in their quiet hours
+    #[serde(default)]
+    emergency: bool,
+    /// Only the ESPs of this device group
+    #[serde(default)]
+    group: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GroupQuery {
+    /// Only members of this device group
+    #[serde(default)]
+    group: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DailyReportQuery {
+    /// UTC day, `YYYY-MM-DD` (default today)
+    #[serde(default)]
+    date: Option<String>,
+}
+
+#[derive(Serialize)]
+struct StartPolicyResponse {
+    device: String,
+    /// Policy in effect for this device
+    policy: StartPolicy,
+    /// Per-device override (None = `--duplicate-start-policy`)
+    #[serde(rename = "override")]
+    override_policy: Option<StartPolicy>,
+}
+
+#[derive(Deserialize)]
+struct SetStartPolicyRequest {
+    /// `null` clears the override
+    policy: Option<StartPolicy>,
+}
+
+#[derive(Serialize)]
+struct RecordingPolicyResponse {
+    device: String,
+    /// Policy in effect for this device
+    policy: RecordingPolicy,
+    /// Per-device override (None = `--recording-policy` defaults)
+    #[serde(rename = "override")]
+    override_policy: Option<RecordingPolicy>,
+}
+
+#[derive(Deserialize)]
+struct SetRecordingPolicyRequest {
+    /// `null` clears the override
+    policy: Option<RecordingPolicy>,
+}
+
+#[derive(Serialize)]
+struct SpeakerListResponse {
+    device: String,
+    speakers: Vec<SpeakerSummary>,
+}
+
+#[derive(Deserialize)]
+struct RenameSpeakerRequest {
+    /// `null` (or blank) goes back to `speaker_<n>`
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SetVolumeRequest {
+    /// Percent, 0..=100 (absent = unchanged)
+    #[serde(default)]
+    volume: Option<u8>,
+    /// Absent = unchanged
+    #[serde(default)]
+    muted: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct OtaStartRequest {
+    image: String,
+}
+
+#[derive(Serialize)]
+struct SayResponse {
+    device: String,
+    addr: String,
+    chars: usize,
+}
+
+#[derive(Serialize)]
+struct DeviceListResponse {
+    defaults: Thresholds,
+    devices: Vec<DeviceResponse>,
+}
+
+#[derive(Serialize)]
+struct ProfileResponse {
+    mac: String,
+    #[serde(flatten)]
+    profile: DeviceProfile,
+}
+
+#[derive(Serialize)]
+struct GroupResponse {
+    name: String,
+    #[serde(flatten)]
+    group: DeviceGroup,
+}
+
+#[derive(Serialize)]
+struct GroupSummary {
+    name: String,
+    #[serde(flatten)]
+    group: DeviceGroup,
+    /// Persona each sensor runs with (override or global)
+    personas: BTreeMap<u32, PersonaTrait>,
+    /// Sessions of the group's ESPs still receiving audio
+    open_sessions: usize,
+    /// Open plus recently ended sessions of the group's ESPs
+    recent_sessions: usize,
+    /// Latest telemetry counters of each ESP that reported any
+    counters: BTreeMap<String, Counters>,
+}
+
+#[derive(Deserialize)]
+struct GroupPersonaRequest {
+    /// `null` clears the overrides (back to the global persona)
+    persona: Option<PersonaTrait>,
+}
+
+#[derive(Serialize)]
+struct GroupPersonaResponse {
+    group: String,
+    persona: Option<PersonaTrait>,
+    sensors: Vec<u32>,
+}
+
+#[derive(Serialize)]
+struct SiteResponse {
+    site: String,
+    rooms: Vec<String>,
+    sensors: usize,
+    devices: usize,
+}
+
+#[derive(Deserialize)]
+struct SiteQuery {
+    /// Only members tagged with this room
+    #[serde(default)]
+    room: Option<String>,
+    /// Range + resolution of the emotion timeline
+    #[serde(flatten)]
+    emotions: EmotionQuery,
+}
+
+#[derive(Serialize)]
+struct SiteOverview {
+    site: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    room: Option<String>,
+    /// Every room of the site (not only `room`)
+    rooms: Vec<String>,
+    /// Members, each with its room
+    #[serde(flatten)]
+    members: Site,
+    /// ESPs currently online
+    online_devices: usize,
+    /// Mean uptime of the ESPs seen since the bridge started
+    uptime_pct: Option<f64>,
+    /// Sessions of the site's ESPs still receiving audio
+    open_sessions: usize,
+    /// Open plus recently ended sessions of the site's ESPs
+    recent_sessions: usize,
+    /// Latest telemetry counters of each ESP that reported any
+    counters: BTreeMap<String, Counters>,
+    /// The site's sensors pooled into one timeline (null when emotion
+    /// history is disabled)
+    emotions: Option<EmotionTimeline>,
+}
+
 // ─────────────────────────────────────────────────────────────────────
 //  Handlers
 // ─────────────────────────────────────────────────────────────────────
@@ -111,22 +704,1331 @@ async fn set_persona(
     )
 }
 
+/// `GET /persona/{name}/weights` — the V/A/D weight vectors `name`
+/// actually runs with: the base (or `--emotion-weights`) weights plus
+/// the persona's deltas.
+async fn get_persona_weights(
+    State(engine): State<Arc<EmotionEngine>>,
+    Path(name): Path<String>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let persona = <PersonaTrait as clap::ValueEnum>::from_str(&name, true).map_err(|_| {
+        let error = format!("unknown persona: {name}");
+        (StatusCode::NOT_FOUND, Json(ErrorResponse { error }))
+    })?;
+    let Some(weights) = engine.persona_weights(persona) else {
+        let error = "the ONNX emotion engine has no weight vectors".to_string();
+        return Err((StatusCode::CONFLICT, Json(ErrorResponse { error })));
+    };
+    Ok(Json(PersonaWeightsResponse { persona, channels: CHANNEL_NAMES, weights }))
+}
+
+/// `POST /persona/preview` — the V/A/D every persona would produce for
+/// a sample sensor vector, held steady (no rate-of-change).
+async fn preview_personas(
+    State(engine): State<Arc<EmotionEngine>>,
+    Json(req): Json<PersonaPreviewRequest>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error }));
+    let mut sensors = [0.0f32; SENSOR_VECTOR_LEN];
+    for (name, &value) in &req.sensors {
+        let Some(i) = CHANNEL_NAMES.iter().position(|c| c == name) else {
+            return Err(bad_request(format!("unknown channel: {name} (valid: {})", CHANNEL_NAMES.join(", "))));
+        };
+        if !(0.0..=1.0).contains(&value) {
+            return Err(bad_request(format!("{name} must be within 0.0–1.0, got {value}")));
+        }
+        sensors[i] = value;
+    }
+    let previews: Vec<PersonaPreview> = PersonaTrait::ALL.iter()
+        .map(|&persona| {
+            let (valence, arousal, dominance) = engine.preview(&sensors, persona);
+            PersonaPreview { persona, valence, arousal, dominance }
+        })
+        .collect();
+    Ok(Json(previews))
+}
+
+/// `POST /vad/evaluate` — run one raw sensor vector through the
+/// smoother and emotion engine without a packet: V/A/D, `is_active`
+/// and emotion, plus the smoother state to chain the next call from.
+/// Nothing live (smoother, history, subscribers) is touched.
+async fn evaluate_vector(
+    State(engine): State<Arc<EmotionEngine>>,
+    State(persona): State<PersonaState>,
+    State(devices): State<DeviceRegistry>,
+    State(experiments): State<Experiments>,
+    Json(req): Json<EvaluateRequest>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let in_range = |v: &f32| (0.0..=1.0).contains(v);
+    let smoother = &req.smoother;
+    if !req.sensors.iter().all(in_range) {
+        let error = "sensors must be 10 values within 0.0–1.0".to_string();
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error })));
+    }
+    if
+        !in_range(&smoother.idle_time) ||
+        !smoother.rate.iter().all(in_range) ||
+        !smoother.prev_raw.iter().flatten().all(in_range)
+    {
+        let error = "smoother state values must be within 0.0–1.0".to_string();
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error })));
+    }
+
+    let sensor_id = req.sensor_id;
+    let active_persona = match req.persona {
+        Some(p) => p,
+        None =>
+            match experiments.persona(sensor_id).or_else(|| devices.persona(sensor_id)) {
+                Some(p) => p,
+                None => persona.get().await,
+            }
+    };
+    let thresholds = experiments.thresholds(sensor_id, devices.thresholds(sensor_id));
+    let mut state = req.smoother;
+    let (valence, arousal, dominance) = engine.evaluate_vector(&req.sensors, active_persona, &mut state);
+    Ok(
+        Json(EvaluateResponse {
+            persona: active_persona,
+            valence,
+            arousal,
+            dominance,
+            is_active: arousal > thresholds.arousal,
+            emotion: prompt_mode(valence, arousal, dominance),
+            smoother: state,
+        })
+    )
+}
+
+/// `POST /vad/analyze-audio` — offline energy VAD over an uploaded WAV:
+/// segments, energy curve and (`?transcript=true`) a transcript.  The
+/// threshold is `?threshold=` or `?sensor_id=`'s effective one.
+async fn analyze_audio(
+    State(analyzer): State<AudioAnalyzer>,
+    State(devices): State<DeviceRegistry>,
+    State(experiments): State<Experiments>,
+    Query(query): Query<AnalyzeQuery>,
+    body: Bytes
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let threshold = match query.threshold {
+        Some(t) if t.is_finite() && t >= 0.0 => t,
+        Some(t) => {
+            let error = format!("threshold must be a non-negative number, got {t}");
+            return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error })));
+        }
+        None => experiments.thresholds(query.sensor_id, devices.thresholds(query.sensor_id)).audio,
+    };
+    analyzer.analyze(&body, threshold, &query).await.map(Json).map_err(|e| {
+        let status = match e {
+            AnalysisError::BadAudio(_) | AnalysisError::BadFrame(_) => StatusCode::BAD_REQUEST,
+            AnalysisError::NoTranscriber => StatusCode::CONFLICT,
+            AnalysisError::Transcription(_) => StatusCode::BAD_GATEWAY,
+        };
+        (status, Json(ErrorResponse { error: e.to_string() }))
+    })
+}
+
+fn device_response(devices: &DeviceRegistry, sensor_id: u32, config: DeviceConfig) -> DeviceResponse {
+    DeviceResponse {
+        sensor_id,
+        config,
+        effective: devices.thresholds(sensor_id),
+    }
+}
+
+/// Look up a device group by name (404 if there is none).
+fn find_group(devices: &DeviceRegistry, name: &str) -> Result<DeviceGroup, (StatusCode, Json<ErrorResponse>)> {
+    devices.group(name).ok_or_else(|| {
+        (StatusCode::NOT_FOUND, Json(ErrorResponse { error: format!("unknown group: {name}") }))
+    })
+}
+
+/// `GET /devices?group=` — list devices with overrides, plus the global
+/// defaults.  With `group`, every sensor of the group instead.
+async fn list_devices(
+    State(devices): State<DeviceRegistry>,
+    Query(query): Query<GroupQuery>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let list = match &query.group {
+        Some(name) =>
+            find_group(&devices, name)?
+                .sensors.into_iter()
+                .map(|id| device_response(&devices, id, devices.get(id).unwrap_or_default()))
+                .collect(),
+        None =>
+            devices
+                .list()
+                .into_iter()
+                .map(|(id, cfg)| device_response(&devices, id, cfg))
+                .collect(),
+    };
+    Ok(
+        Json(DeviceListResponse {
+            defaults: devices.defaults(),
+            devices: list,
+        })
+    )
+}
+
+/// `GET /devices/{sensor_id}` — one device's overrides and effective thresholds.
+async fn get_device(
+    State(devices): State<DeviceRegistry>,
+    Path(sensor_id): Path<u32>
+) -> impl IntoResponse {
+    let cfg = devices.get(sensor_id).unwrap_or_default();
+    Json(device_response(&devices, sensor_id, cfg))
+}
+
+/// `PUT /devices/{sensor_id}/thresholds` — replace threshold overrides.
+///
+/// Body: `{"audio_threshold": 80.0, "arousal_threshold": null}`; omitted
+/// or `null` fields fall back to the global default.
+async fn set_device_thresholds(
+    State(devices): State<DeviceRegistry>,
+    Path(sensor_id): Path<u32>,
+    Json(req): Json<DeviceConfig>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    req.validate().map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+
+    let cfg = devices.set_thresholds(sensor_id, req.audio_threshold, req.arousal_threshold);
+    info!(
+        sensor_id,
+        audio = ?cfg.audio_threshold,
+        arousal = ?cfg.arousal_threshold,
+        "🎚️  device thresholds updated"
+    );
+    Ok(Json(device_response(&devices, sensor_id, cfg)))
+}
+
+/// `PUT /devices/{sensor_id}/location` — tag a sensor with a site and
+/// room.
+///
+/// Body: `{"site": "hq", "room": "Lobby"}`; omitted or `null` fields
+/// clear the tag.
+async fn set_device_location(
+    State(devices): State<DeviceRegistry>,
+    Path(sensor_id): Path<u32>,
+    Json(req): Json<Location>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let location = req.normalize().map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    let cfg = devices.set_location(sensor_id, location);
+    info!(sensor_id, site = ?cfg.location.site, room = ?cfg.location.room, "📍 device location set");
+    Ok(Json(device_response(&devices, sensor_id, cfg)))
+}
+
+/// `GET /devices/profiles` — provisioning profiles, by MAC.
+async fn list_profiles(State(devices): State<DeviceRegistry>) -> impl IntoResponse {
+    let profiles: Vec<ProfileResponse> = devices
+        .profiles()
+        .into_iter()
+        .map(|(mac, profile)| ProfileResponse { mac, profile })
+        .collect();
+    Json(profiles)
+}
+
+/// Parse a manifest body by its content type (`text/csv` or JSON).
+fn parse_manifest(headers: &HeaderMap, body: &[u8]) -> Result<Vec<(usize, device_import::ManifestRow)>, ImportError> {
+    let csv = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|t| t.to_str().ok())
+        .is_some_and(|t| t.starts_with("text/csv"));
+    device_import::parse(body, csv)
+}
+
+/// `POST /devices/import` — upsert provisioning profiles from a CSV or
+/// JSON manifest (MAC, name, tenant, persona, sensor id).  All rows are
+/// validated first; any invalid row rejects the whole manifest.
+async fn import_devices(
+    State(devices): State<DeviceRegistry>,
+    State(tenant): State<TenantId>,
+    headers: HeaderMap,
+    body: Bytes
+) -> Result<Json<ImportReport>, (StatusCode, Json<ImportError>)> {
+    let rows = parse_manifest(&headers, &body).map_err(|e| (StatusCode::BAD_REQUEST, Json(e)))?;
+    let profiles = device_import
+        ::validate(rows, &TenantScope::Bridge(&tenant))
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(e)))?;
+    let report = devices.import(profiles);
+    info!(tenant = %tenant, created = report.created, updated = report.updated, "📋 devices imported");
+    Ok(Json(report))
+}
+
+/// `POST /devices/import` on the multi-tenant directory: every row names
+/// its tenant and goes to that tenant's registry, all or nothing.
+async fn import_tenant_devices(
+    registries: &[(String, DeviceRegistry)],
+    headers: HeaderMap,
+    body: Bytes
+) -> Result<Json<BTreeMap<String, ImportReport>>, (StatusCode, Json<ImportError>)> {
+    let ids: Vec<String> = registries
+        .iter()
+        .map(|(id, _)| id.clone())
+        .collect();
+    let rows = parse_manifest(&headers, &body).map_err(|e| (StatusCode::BAD_REQUEST, Json(e)))?;
+    let profiles = device_import
+        ::validate(rows, &TenantScope::Directory(&ids))
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(e)))?;
+    let mut reports = BTreeMap::new();
+    for (id, devices) in registries {
+        let own: Vec<_> = profiles
+            .iter()
+            .filter(|(_, p)| p.tenant.as_deref() == Some(id.as_str()))
+            .cloned()
+            .collect();
+        if !own.is_empty() {
+            let report = devices.import(own);
+            info!(tenant = %id, created = report.created, updated = report.updated, "📋 devices imported");
+            reports.insert(id.clone(), report);
+        }
+    }
+    Ok(Json(reports))
+}
+
+/// `GET /groups` — device groups.
+async fn list_groups(State(devices): State<DeviceRegistry>) -> impl IntoResponse {
+    let groups: Vec<GroupResponse> = devices
+        .groups()
+        .into_iter()
+        .map(|(name, group)| GroupResponse { name, group })
+        .collect();
+    Json(groups)
+}
+
+/// `GET /groups/{name}` — a group's members plus its personas, session
+/// counts and latest ESP telemetry.
+async fn get_group(
+    State(devices): State<DeviceRegistry>,
+    State(persona): State<PersonaState>,
+    State(sessions): State<SessionLogs>,
+    State(telemetry): State<DeviceTelemetry>,
+    Path(name): Path<String>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let group = find_group(&devices, &name)?;
+    let global = persona.get().await;
+    let personas = group.sensors
+        .iter()
+        .map(|&id| (id, devices.persona(id).unwrap_or(global)))
+        .collect();
+    let recent: Vec<_> = sessions
+        .list()
+        .into_iter()
+        .filter(|s| group.has_device(&s.device))
+        .collect();
+    let counters = group.devices
+        .iter()
+        .filter_map(|device| Some((device.clone(), telemetry.counters(device)?)))
+        .collect();
+    Ok(
+        Json(GroupSummary {
+            name: name.to_ascii_lowercase(),
+            open_sessions: recent.iter().filter(|s| s.ended_ms.is_none()).count(),
+            recent_sessions: recent.len(),
+            group,
+            personas,
+            counters,
+        })
+    )
+}
+
+/// `PUT /groups/{name}` — create or replace a group:
+/// `{"sensors": [1, 2], "devices": ["aa:bb:cc:dd:ee:ff", "10.0.0.8:5000"]}`.
+async fn set_group(
+    State(devices): State<DeviceRegistry>,
+    Path(name): Path<String>,
+    Json(group): Json<DeviceGroup>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let group = devices
+        .set_group(&name, group)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    info!(group = %name, sensors = group.sensors.len(), devices = group.devices.len(), "🗂️  device group set");
+    Ok(Json(GroupResponse { name: name.trim().to_ascii_lowercase(), group }))
+}
+
+/// `DELETE /groups/{name}` — delete a group (its devices are untouched).
+async fn remove_group(State(devices): State<DeviceRegistry>, Path(name): Path<String>) -> StatusCode {
+    if devices.remove_group(&name) { StatusCode::NO_CONTENT } else { StatusCode::NOT_FOUND }
+}
+
+/// `PUT /groups/{name}/persona` — set (or with `null`, clear) the
+/// persona override of every sensor in the group.
+async fn set_group_persona(
+    State(devices): State<DeviceRegistry>,
+    Path(name): Path<String>,
+    Json(req): Json<GroupPersonaRequest>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let group = find_group(&devices, &name)?;
+    for &id in &group.sensors {
+        devices.set_persona(id, req.persona);
+    }
+    info!(group = %name, persona = ?req.persona, sensors = group.sensors.len(), "🎭 group persona set");
+    Ok(
+        Json(GroupPersonaResponse {
+            group: name.to_ascii_lowercase(),
+            persona: req.persona,
+            sensors: group.sensors,
+        })
+    )
+}
+
+/// `GET /sites` — every site with its rooms and member counts.
+async fn list_sites(State(devices): State<DeviceRegistry>) -> impl IntoResponse {
+    let sites: Vec<SiteResponse> = devices
+        .sites()
+        .into_iter()
+        .map(|(site, members)| SiteResponse {
+            rooms: members.rooms(),
+            sensors: members.sensors.len(),
+            devices: members.devices.len(),
+            site,
+        })
+        .collect();
+    Json(sites)
+}
+
+/// `GET /sites/{site}/overview?room=&from=&to=&resolution=` — a site's
+/// (or one room's) members, presence, session counts, telemetry and
+/// pooled emotion timeline.
+async fn get_site_overview(
+    State(devices): State<DeviceRegistry>,
+    State(sessions): State<SessionLogs>,
+    State(telemetry): State<DeviceTelemetry>,
+    State(presence): State<Presence>,
+    State(emotions): State<EmotionHistory>,
+    Path(site): Path<String>,
+    Query(query): Query<SiteQuery>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let not_found = |error: String| (StatusCode::NOT_FOUND, Json(ErrorResponse { error }));
+    let mut members = devices.site(&site).ok_or_else(|| not_found(format!("unknown site: {site}")))?;
+    let rooms = members.rooms();
+    if let Some(ref room) = query.room {
+        members = members.in_room(room);
+        if members.sensors.is_empty() && members.devices.is_empty() {
+            return Err(not_found(format!("no devices in room {room:?} of site {site}")));
+        }
+    }
+
+    let seen: Vec<_> = presence
+        .list()
+        .into_iter()
+        .filter(|p| members.devices.contains_key(&p.device))
+        .collect();
+    let uptime_pct = (!seen.is_empty()).then(|| {
+        seen.iter().map(|p| p.uptime_pct).sum::<f64>() / (seen.len() as f64)
+    });
+    let recent: Vec<_> = sessions
+        .list()
+        .into_iter()
+        .filter(|s| members.devices.contains_key(&s.device.to_ascii_lowercase()))
+        .collect();
+    let counters = members.devices
+        .keys()
+        .filter_map(|device| Some((device.clone(), telemetry.counters(device)?)))
+        .collect();
+    let timeline = if emotions.enabled() {
+        let sensors: Vec<u32> = members.sensors.keys().copied().collect();
+        let timeline = emotions
+            .query_combined(&sensors, &query.emotions)
+            .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+        Some(timeline)
+    } else {
+        None
+    };
+    Ok(
+        Json(SiteOverview {
+            site: site.to_ascii_lowercase(),
+            room: query.room,
+            rooms,
+            online_devices: seen.iter().filter(|p| p.online).count(),
+            uptime_pct,
+            open_sessions: recent.iter().filter(|s| s.ended_ms.is_none()).count(),
+            recent_sessions: recent.len(),
+            counters,
+            emotions: timeline,
+            members,
+        })
+    )
+}
+
+/// `GET /devices/{device}/conversation` — remembered OpenAI turns for an
+/// ESP device (`device` is its MAC or UDP address, as in `{{device_name}}`).
+async fn get_conversation(
+    State(conversations): State<ConversationStore>,
+    Path(device): Path<String>
+) -> impl IntoResponse {
+    Json(ConversationResponse {
+        active: conversations.is_active(&device),
+        turns: conversations.history(&device),
+        device,
+    })
+}
+
+/// `DELETE /devices/{device}/conversation` — forget a device's history
+/// (and the live OpenAI conversation, if it is the active device).
+async fn reset_conversation(
+    State(conversations): State<ConversationStore>,
+    Path(device): Path<String>
+) -> impl IntoResponse {
+    let cleared = conversations.reset(&device);
+    info!(device = %device, cleared, "🧽 conversation history reset");
+    StatusCode::NO_CONTENT
+}
+
+/// `GET /devices/{device}/export` — ZIP of everything stored about the
+/// device (recordings, conversation, speakers, session rows).
+async fn export_device_data(
+    State(data): State<DeviceData>,
+    Path(device): Path<String>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let chunks = data.export(&device).await.map_err(|e| {
+        let status = match e {
+            ExportError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            ExportError::Failed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(ErrorResponse { error: e.to_string() }))
+    })?;
+    // An error chunk aborts the response instead of ending it cleanly
+    let body = Body::from_stream(
+        futures_util::stream::unfold(chunks, |mut chunks| async move { chunks.recv().await.map(|chunk| (chunk, chunks)) })
+    );
+    let filename = format!("attachment; filename=\"{}.zip\"", device.replace([':', '/', '\\', '"'], "-"));
+    Ok(([(header::CONTENT_TYPE, "application/zip".to_string()), (header::CONTENT_DISPOSITION, filename)], body))
+}
+
+/// `DELETE /devices/{device}/data` — delete everything stored about the
+/// device; returns what was removed.
+async fn purge_device_data(State(data): State<DeviceData>, Path(device): Path<String>) -> impl IntoResponse {
+    Json(data.purge(&device).await)
+}
+
+/// `GET /devices/{device}/start-policy` — what a SESSION_START does while
+/// the device's session is still receiving.
+async fn get_start_policy(
+    State(policies): State<StartPolicies>,
+    Path(device): Path<String>
+) -> impl IntoResponse {
+    Json(start_policy_response(&policies, device))
+}
+
+/// `PUT /devices/{device}/start-policy` — `{"policy": "resume" | "restart"
+/// | "reject"}`, or `{"policy": null}` to fall back to the default.
+async fn set_start_policy(
+    State(policies): State<StartPolicies>,
+    Path(device): Path<String>,
+    Json(req): Json<SetStartPolicyRequest>
+) -> impl IntoResponse {
+    policies.set(&device, req.policy);
+    info!(device = %device, policy = ?req.policy, "🔁 duplicate START policy updated");
+    Json(start_policy_response(&policies, device))
+}
+
+fn start_policy_response(policies: &StartPolicies, device: String) -> StartPolicyResponse {
+    StartPolicyResponse {
+        policy: policies.policy(&device),
+        override_policy: policies.override_for(&device),
+        device,
+    }
+}
+
+/// `GET /devices/{device}/recording-policy` — what is kept of the
+/// device's sessions.
+async fn get_recording_policy(
+    State(policies): State<RecordingPolicies>,
+    Path(device): Path<String>
+) -> impl IntoResponse {
+    Json(recording_policy_response(&policies, device))
+}
+
+/// `PUT /devices/{device}/recording-policy` — `{"policy": {"mode":
+/// "record" | "metadata" | "never", "retention_days": N}}`, or
+/// `{"policy": null}` to fall back to the default.  Applies from the
+/// device's next session; audited.
+async fn set_recording_policy(
+    State(policies): State<RecordingPolicies>,
+    Path(device): Path<String>,
+    Json(req): Json<SetRecordingPolicyRequest>
+) -> Result<Json<RecordingPolicyResponse>, (StatusCode, Json<ErrorResponse>)> {
+    policies.set(&device, req.policy).map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse { error: format!("failed to save policy: {e}") }))
+    })?;
+    Ok(Json(recording_policy_response(&policies, device)))
+}
+
+fn recording_policy_response(policies: &RecordingPolicies, device: String) -> RecordingPolicyResponse {
+    RecordingPolicyResponse {
+        policy: policies.policy(&device),
+        override_policy: policies.override_for(&device),
+        device,
+    }
+}
+
+/// `GET /devices/{device}/speakers` — speakers diarization has heard on
+/// a device.
+async fn list_speakers(
+    State(speakers): State<Diarizer>,
+    Path(device): Path<String>
+) -> impl IntoResponse {
+    Json(SpeakerListResponse { speakers: speakers.list(&device), device })
+}
+
+/// `PUT /devices/{device}/speakers/{id}` — `{"name": "Maya"}`: later
+/// transcripts of that speaker carry the name.
+async fn rename_speaker(
+    State(speakers): State<Diarizer>,
+    Path((device, id)): Path<(String, u32)>,
+    Json(req): Json<RenameSpeakerRequest>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if !speakers.rename(&device, id, req.name.clone()) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse { error: format!("no speaker {id} on device {device}") }),
+        ));
+    }
+    info!(device = %device, speaker = id, name = ?req.name, "🗣️ speaker renamed");
+    Ok(Json(SpeakerListResponse { speakers: speakers.list(&device), device }))
+}
+
+/// `GET /devices/{device}/volume` — output level last sent to an ESP.
+async fn get_volume(
+    State(volume): State<VolumeControl>,
+    Path(device): Path<String>
+) -> impl IntoResponse {
+    let level = volume.level(&device);
+    Json(serde_json::json!({ "device": device, "volume": level.volume, "muted": level.muted }))
+}
+
+/// `PUT /devices/{device}/volume` — `{"volume": 0-100, "muted": bool}`
+/// (either may be omitted); sent to the ESP at once.
+async fn set_volume(
+    State(volume): State<VolumeControl>,
+    Path(device): Path<String>,
+    Json(req): Json<SetVolumeRequest>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if req.volume.is_some_and(|v| v > 100) {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "volume must be 0-100".into() })));
+    }
+    if req.volume.is_none() && req.muted.is_none() {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "nothing to set".into() })));
+    }
+    let change = OutputChange::Set { volume: req.volume, muted: req.muted };
+    let status = volume.request(&device, change).await.map_err(|e| {
+        let status = match e {
+            VolumeError::UnknownDevice(_) => StatusCode::NOT_FOUND,
+            VolumeError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        };
+        (status, Json(ErrorResponse { error: e.to_string() }))
+    })?;
+    Ok(Json(status))
+}
+
+/// `GET /devices/{device}/quiet-hours` — the device's schedule and
+/// whether it is in force now.
+async fn get_quiet_hours(State(quiet): State<QuietHours>, Path(device): Path<String>) -> impl IntoResponse {
+    let schedule = quiet.get(&device);
+    let active = quiet.active(&device).is_some();
+    Json(serde_json::json!({ "device": device, "schedule": schedule, "active": active }))
+}
+
+/// `PUT /devices/{device}/quiet-hours` — set the schedule:
+/// `{"start": "21:00", "end": "07:00", "timezone": "local", "volume": 20, "mute_audio": false}`.
+async fn set_quiet_hours(
+    State(quiet): State<QuietHours>,
+    Path(device): Path<String>,
+    Json(schedule): Json<QuietSchedule>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    quiet
+        .set(&device, Some(schedule))
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    let active = quiet.active(&device).is_some();
+    Ok(Json(serde_json::json!({ "device": device, "schedule": quiet.get(&device), "active": active })))
+}
+
+/// `DELETE /devices/{device}/quiet-hours` — remove the schedule.
+async fn clear_quiet_hours(State(quiet): State<QuietHours>, Path(device): Path<String>) -> StatusCode {
+    match quiet.set(&device, None) {
+        Ok(true) => StatusCode::NO_CONTENT,
+        _ => StatusCode::NOT_FOUND,
+    }
+}
+
+/// `GET /devices/{device}/telemetry?lines=N` — firmware log lines and
+/// counters (heap, RSSI, battery) the device reported.
+async fn get_telemetry(
+    State(telemetry): State<DeviceTelemetry>,
+    Path(device): Path<String>,
+    Query(query): Query<TelemetryQuery>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    telemetry.report(&device, &query).map(Json).ok_or_else(|| {
+        let error = format!("no telemetry from device: {device}");
+        (StatusCode::NOT_FOUND, Json(ErrorResponse { error }))
+    })
+}
+
+/// `GET /schemas` — sensor data schemas with per-channel violation counters.
+async fn list_schemas(State(schemas): State<SchemaRegistry>) -> impl IntoResponse {
+    Json(schemas.list())
+}
+
+/// `PUT /schemas/{data_type}` — add or replace a schema:
+/// `{"name": "thermal", "channels": [{"name": "temp_c", "min": -20, "max": 60, "unit": "°C"}], "on_violation": "reject"}`.
+async fn set_schema(
+    State(schemas): State<SchemaRegistry>,
+    Path(data_type): Path<u8>,
+    Json(schema): Json<DataSchema>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let schema = DataSchema { data_type, ..schema };
+    schemas.set(schema.clone()).map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    Ok(Json(schema))
+}
+
+/// `DELETE /schemas/{data_type}` — stop validating a data type.
+async fn delete_schema(State(schemas): State<SchemaRegistry>, Path(data_type): Path<u8>) -> StatusCode {
+    if schemas.remove(data_type) { StatusCode::NO_CONTENT } else { StatusCode::NOT_FOUND }
+}
+
+/// `GET /clips` — canned clips available for playback.
+async fn list_clips(State(clips): State<ClipPlayer>) -> impl IntoResponse {
+    let list: Vec<ClipEntry> = clips
+        .list()
+        .into_iter()
+        .map(|(name, d)| ClipEntry { name, duration_ms: d.as_millis() as u64 })
+        .collect();
+    Json(list)
+}
+
+/// `GET /downlink` — per-device AUDIO_DOWN link estimates and pace.
+async fn list_downlink(State(downlink): State<DownlinkPacer>) -> impl IntoResponse {
+    Json(downlink.list())
+}
+
+/// `POST /devices/{device}/play/{clip}` — play a canned clip on an ESP
+/// (`device` is its MAC or `ip:port`).  Returns once playback has started.
+async fn play_clip(
+    State(clips): State<ClipPlayer>,
+    Path((device, clip)): Path<(String, String)>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let (addr, duration) = clips.request(&device, &clip).await.map_err(play_error)?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(PlayResponse {
+            device,
+            clip,
+            addr: addr.to_string(),
+            duration_ms: duration.as_millis() as u64,
+        }),
+    ))
+}
+
+/// `POST /devices/{device}/say` — speak `{"text": "..."}` on an ESP
+/// through the `--tts-backend`.  Returns once synthesis has been started.
+async fn say(
+    State(clips): State<ClipPlayer>,
+    Path(device): Path<String>,
+    Json(req): Json<SayRequest>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let text = req.text.trim();
+    if text.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "empty text".into() })));
+    }
+    let addr = clips.request_speech(&device, text).await.map_err(play_error)?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(SayResponse {
+            device,
+            addr: addr.to_string(),
+            chars: text.chars().count(),
+        }),
+    ))
+}
+
+/// `POST /broadcast/say?emergency=&group=` — speak `{"text": "..."}`
+/// (or play a WAV body sent as `audio/*`) on every online ESP, or every
+/// online ESP of a group.  Returns once playback has started everywhere.
+async fn broadcast_say(
+    State(broadcaster): State<Broadcaster>,
+    State(devices): State<DeviceRegistry>,
+    Query(query): Query<BroadcastQuery>,
+    headers: HeaderMap,
+    body: Bytes
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let only = match &query.group {
+        Some(name) => Some(find_group(&devices, name)?.devices),
+        None => None,
+    };
+    let is_wav = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|t| t.to_str().ok())
+        .is_some_and(|t| t.starts_with("audio/"));
+    let report = if is_wav {
+        broadcaster.play_wav(&body, query.emergency, only).await
+    } else {
+        let req: SayRequest = serde_json
+            ::from_slice(&body)
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error: e.to_string() })))?;
+        let text = req.text.trim();
+        if text.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse { error: "empty text".into() })));
+        }
+        broadcaster.say(text, query.emergency, only).await
+    };
+    Ok((StatusCode::ACCEPTED, Json(report.map_err(play_error)?)))
+}
+
+/// `POST /devices/{device}/diagnostics` — play a test tone with the
+/// ESP's mic looped back and report latency, level and pass/fail.
+/// Returns when the test is over (a few seconds).
+async fn run_diagnostics(
+    State(diagnostics): State<Diagnostics>,
+    Path(device): Path<String>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let mut report = diagnostics.run(&device).await.map_err(play_error)?;
+    report.device = device;
+    Ok(Json(report))
+}
+
+/// `GET /firmware` — hosted firmware images.
+async fn list_firmware(State(ota): State<Ota>) -> impl IntoResponse {
+    Json(ota.list())
+}
+
+/// `PUT /firmware/{name}` — store a firmware image (raw request body).
+async fn upload_firmware(
+    State(ota): State<Ota>,
+    Path(name): Path<String>,
+    body: Bytes
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let info = ota.upload(&name, body.to_vec()).await.map_err(ota_error)?;
+    Ok((StatusCode::CREATED, Json(info)))
+}
+
+/// `DELETE /firmware/{name}` — remove a firmware image.
+async fn remove_firmware(
+    State(ota): State<Ota>,
+    Path(name): Path<String>
+) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
+    let removed = ota.remove(&name).await.map_err(ota_error)?;
+    Ok(if removed { StatusCode::NO_CONTENT } else { StatusCode::NOT_FOUND })
+}
+
+/// `POST /devices/{device}/ota` — offer `{"image": "..."}` to an ESP.
+/// Returns once the offer is sent; follow progress on `GET /ota`.
+async fn start_ota(
+    State(ota): State<Ota>,
+    Path(device): Path<String>,
+    Json(req): Json<OtaStartRequest>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let status = ota.request(&device, &req.image).await.map_err(ota_error)?;
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+/// `GET /ota` — every device's latest firmware transfer.
+async fn list_ota(State(ota): State<Ota>) -> impl IntoResponse {
+    Json(ota.transfers())
+}
+
+fn ota_error(e: OtaError) -> (StatusCode, Json<ErrorResponse>) {
+    let status = match e {
+        OtaError::InvalidName(_) | OtaError::InvalidImage(_) => StatusCode::BAD_REQUEST,
+        OtaError::UnknownImage(_) | OtaError::UnknownDevice(_) => StatusCode::NOT_FOUND,
+        OtaError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        OtaError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(ErrorResponse { error: e.to_string() }))
+}
+
+/// `GET /sessions?group=` — open and recent ESP sessions (of a group's
+/// ESPs), newest first.
+async fn list_sessions(
+    State(sessions): State<SessionLogs>,
+    State(devices): State<DeviceRegistry>,
+    Query(query): Query<GroupQuery>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let mut list = sessions.list();
+    if let Some(name) = &query.group {
+        let group = find_group(&devices, name)?;
+        list.retain(|s| group.has_device(&s.device));
+    }
+    Ok(Json(list))
+}
+
+/// `GET /sessions/{id}` — one session with its OpenAI event timeline.
+async fn get_session(
+    State(sessions): State<SessionLogs>,
+    Path(id): Path<u64>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    sessions
+        .get(id)
+        .map(Json)
+        .ok_or_else(|| {
+            (StatusCode::NOT_FOUND, Json(ErrorResponse { error: format!("unknown session {id}") }))
+        })
+}
+
+/// `GET /sessions/{id}/monitor` — the session's Ogg Opus monitoring copy.
+async fn get_session_monitor(
+    State(sessions): State<SessionLogs>,
+    Path(id): Path<u64>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let not_found = |error: String| (StatusCode::NOT_FOUND, Json(ErrorResponse { error }));
+    let record = sessions.get(id).ok_or_else(|| not_found(format!("unknown session {id}")))?;
+    let path = record.monitor.ok_or_else(|| not_found(format!("session {id} has no monitoring copy")))?;
+    let ogg = tokio::fs
+        ::read(&path).await
+        .map_err(|e| not_found(format!("{path}: {e}")))?;
+    Ok(([(header::CONTENT_TYPE, "audio/ogg")], ogg))
+}
+
+/// `GET /recordings/{id}?format=wav|flac|ogg|mp3&rate=` — a session WAV,
+/// transcoded on demand (cached).
+async fn get_recording(
+    State(recordings): State<Recordings>,
+    Path(id): Path<String>,
+    Query(query): Query<TranscodeQuery>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let (format, data) = recordings.get(&id, &query).await.map_err(recording_error)?;
+    Ok(([(header::CONTENT_TYPE, format.content_type())], data))
+}
+
+/// `POST /recordings/{id}/redact` — silence audio ranges and / or matched
+/// utterances of a session, and redact their transcripts.
+async fn redact_recording(
+    State(recordings): State<Recordings>,
+    State(conversations): State<ConversationStore>,
+    Path(id): Path<String>,
+    Json(req): Json<RedactRequest>
+) -> Result<Json<RedactionReport>, (StatusCode, Json<ErrorResponse>)> {
+    redaction::redact(&recordings, &conversations, &id, &req).await.map(Json).map_err(recording_error)
+}
+
+fn recording_error(e: RecordingError) -> (StatusCode, Json<ErrorResponse>) {
+    let status = match e {
+        RecordingError::BadId(_) | RecordingError::BadRate(_) | RecordingError::Invalid(_) => StatusCode::BAD_REQUEST,
+        RecordingError::NotFound(_) => StatusCode::NOT_FOUND,
+        RecordingError::Unsupported(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        RecordingError::Failed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(ErrorResponse { error: e.to_string() }))
+}
+
+/// `GET /debug/capture` — current (or last) packet capture.
+async fn get_capture(State(capture): State<PacketCapture>) -> impl IntoResponse {
+    Json(capture.status())
+}
+
+/// `PUT /debug/capture` — start a pcap capture for the given devices
+/// (`{"enabled": true, "devices": [...], "max_bytes": .., "max_secs": ..}`)
+/// or stop it (`{"enabled": false}`).
+async fn set_capture(
+    State(capture): State<PacketCapture>,
+    Json(req): Json<CaptureRequest>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    capture
+        .apply(&req)
+        .map(Json)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))
+}
+
+/// `GET /devices/{id}/emotions?from=&to=&resolution=` — downsampled
+/// V/A/D + emotion label history of one device.
+async fn get_emotions(
+    State(emotions): State<EmotionHistory>,
+    Path(sensor_id): Path<u32>,
+    Query(query): Query<EmotionQuery>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if !emotions.enabled() {
+        let error = "emotion history disabled (--emotion-history-hours 0)".to_string();
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error })));
+    }
+    emotions
+        .query(sensor_id, &query)
+        .map(Json)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))
+}
+
+/// `GET /anomalies` — latest anomalies in pipeline rates, link loss
+/// and device silence, newest first.
+async fn list_anomalies(
+    State(anomalies): State<AnomalyDetector>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if !anomalies.is_enabled() {
+        let error = "anomaly detection disabled (--anomaly-detection)".to_string();
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error })));
+    }
+    Ok(Json(anomalies.recent()))
+}
+
+/// `GET /experiments` — every A/B experiment with its variants'
+/// results side by side.
+async fn list_experiments(
+    State(experiments): State<Experiments>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if !experiments.is_enabled() {
+        let error = "no experiments running (--experiments-file)".to_string();
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error })));
+    }
+    Ok(Json(experiments.report()))
+}
+
+/// `GET /reports/daily?date=YYYY-MM-DD` — today's running totals, or a
+/// finished day written to `--report-dir`.
+async fn get_daily_report(
+    State(daily): State<DailyReports>,
+    Query(query): Query<DailyReportQuery>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if !daily.is_enabled() {
+        let error = "daily reports disabled (set --report-dir or --report-webhook)".to_string();
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error })));
+    }
+    let date = query.date
+        .as_deref()
+        .map(reports::parse_date)
+        .transpose()
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))?;
+    daily.report(date).map(Json).ok_or_else(|| {
+        let error = format!("no report for {}", query.date.unwrap_or_default());
+        (StatusCode::NOT_FOUND, Json(ErrorResponse { error }))
+    })
+}
+
+/// `GET /stats` — packet counters and rates since startup or the last
+/// reset.  Reading does not reset anything.
+async fn get_stats(State(stats): State<Arc<Stats>>) -> impl IntoResponse {
+    Json(stats.live())
+}
+
+/// `POST /stats/reset` — start the `GET /stats` counts over.  Returns the
+/// period that just ended; the periodic stats reporter is unaffected.
+async fn reset_stats(State(stats): State<Arc<Stats>>) -> impl IntoResponse {
+    Json(stats.reset())
+}
+
+/// `GET /stats/sound-levels` — rolling ambient sound level of every device.
+async fn list_sound_levels(
+    State(levels): State<SoundLevels>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if !levels.enabled() {
+        let error = "sound level monitoring disabled (--sound-level-days 0)".to_string();
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error })));
+    }
+    Ok(Json(levels.list()))
+}
+
+/// `GET /stats/sound-levels/{id}?from=&to=` — hourly sound levels of one
+/// device.
+async fn get_sound_levels(
+    State(levels): State<SoundLevels>,
+    Path(sensor_id): Path<u32>,
+    Query(query): Query<SoundLevelQuery>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if !levels.enabled() {
+        let error = "sound level monitoring disabled (--sound-level-days 0)".to_string();
+        return Err((StatusCode::NOT_FOUND, Json(ErrorResponse { error })));
+    }
+    levels
+        .history(sensor_id, &query)
+        .map(Json)
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))
+}
+
+/// `GET /subscriptions` — registered threshold-crossing rules.
+async fn list_subscriptions(State(subscriptions): State<Subscriptions>) -> impl IntoResponse {
+    Json(subscriptions.list())
+}
+
+/// `POST /subscriptions` — register a rule
+/// (`{"device": 42, "metric": "arousal", "op": "above", "threshold": 0.8,
+/// "for_secs": 5, "deliver": {"type": "webhook", "url": "..."}}`).
+async fn add_subscription(
+    State(subscriptions): State<Subscriptions>,
+    Json(rule): Json<Rule>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    subscriptions
+        .add(rule)
+        .map(|sub| (StatusCode::CREATED, Json(sub)))
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })))
+}
+
+/// `DELETE /subscriptions/{id}` — remove a rule.
+async fn remove_subscription(State(subscriptions): State<Subscriptions>, Path(id): Path<u64>) -> StatusCode {
+    if subscriptions.remove(id) { StatusCode::NO_CONTENT } else { StatusCode::NOT_FOUND }
+}
+
+/// `GET /routes` — ESP ↔ ESP audio routes in place.
+async fn list_routes(State(routes): State<AudioRoutes>) -> impl IntoResponse {
+    Json(routes.list())
+}
+
+/// `POST /routes` — play one device's mic on another's speaker
+/// (`{"from": "AA:BB:CC:DD:EE:01", "to": "10.0.0.8:5000",
+/// "bidirectional": true, "divert": true}`).
+async fn add_route(
+    State(routes): State<AudioRoutes>,
+    Json(spec): Json<RouteSpec>
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    routes
+        .request(spec)
+        .await
+        .map(|route| (StatusCode::CREATED, Json(route)))
+        .map_err(route_error)
+}
+
+/// `DELETE /routes/{id}` — stop routing.
+async fn remove_route(State(routes): State<AudioRoutes>, Path(id): Path<u64>) -> StatusCode {
+    if routes.remove(id) { StatusCode::NO_CONTENT } else { StatusCode::NOT_FOUND }
+}
+
+fn route_error(e: RouteError) -> (StatusCode, Json<ErrorResponse>) {
+    let status = match e {
+        RouteError::UnknownDevice(_) => StatusCode::NOT_FOUND,
+        RouteError::SameDevice => StatusCode::BAD_REQUEST,
+        RouteError::Busy(_) => StatusCode::CONFLICT,
+        RouteError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(ErrorResponse { error: e.to_string() }))
+}
+
+fn play_error(e: PlayError) -> (StatusCode, Json<ErrorResponse>) {
+    let status = match e {
+        PlayError::UnknownClip(_) | PlayError::UnknownDevice(_) => StatusCode::NOT_FOUND,
+        PlayError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        PlayError::NoTts => StatusCode::NOT_IMPLEMENTED,
+        PlayError::QuietHours(_) => StatusCode::CONFLICT,
+        PlayError::Tts(_) => StatusCode::BAD_GATEWAY,
+        PlayError::BadAudio(_) => StatusCode::BAD_REQUEST,
+    };
+    (status, Json(ErrorResponse { error: e.to_string() }))
+}
+
+/// `GET /events/ws` — WebSocket stream of discrete sensor events and
+/// device online / offline transitions (JSON, one event per text frame).
+async fn events_ws(
+    ws: WebSocketUpgrade,
+    State(events): State<EventBus>,
+    State(presence): State<Presence>
+) -> impl IntoResponse {
+    let rx = events.subscribe();
+    let transitions = presence.subscribe();
+    ws.on_upgrade(move |socket| stream_json_merged(socket, rx, Some(transitions), "events"))
+}
+
+/// `GET /subscriptions/ws` — WebSocket stream of alerts from `ws`
+/// subscriptions (JSON, one alert per text frame).
+async fn alerts_ws(ws: WebSocketUpgrade, State(subscriptions): State<Subscriptions>) -> impl IntoResponse {
+    let rx = subscriptions.subscribe_ws();
+    ws.on_upgrade(move |socket| stream_json(socket, rx, "alerts"))
+}
+
+/// Forward everything from `rx` to `socket` as JSON text frames until
+/// either side goes away.
+async fn stream_json<T: Serialize + Clone>(socket: WebSocket, rx: broadcast::Receiver<T>, stream: &str) {
+    stream_json_merged(socket, rx, None::<broadcast::Receiver<T>>, stream).await
+}
+
+/// [`stream_json`] interleaving a second channel (which may close early).
+async fn stream_json_merged<T: Serialize + Clone, U: Serialize + Clone>(
+    mut socket: WebSocket,
+    mut rx: broadcast::Receiver<T>,
+    mut extra: Option<broadcast::Receiver<U>>,
+    stream: &str
+) {
+    debug!(stream, "WebSocket subscriber connected");
+    loop {
+        let text = tokio::select! {
+            text = next_json(&mut rx, stream) => {
+                match text {
+                    Some(text) => text,
+                    None => break,
+                }
+            }
+            text = async { next_json(extra.as_mut()?, stream).await }, if extra.is_some() => {
+                match text {
+                    Some(text) => text,
+                    None => {
+                        extra = None;
+                        continue;
+                    }
+                }
+            }
+            incoming = socket.recv() => {
+                // Only watch for the client going away
+                match incoming {
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    Some(Ok(_)) => continue,
+                }
+            }
+        };
+        if socket.send(Message::Text(text)).await.is_err() {
+            break;
+        }
+    }
+    debug!(stream, "WebSocket subscriber disconnected");
+}
+
+/// Next message from `rx` as JSON, skipping over lag (None = closed).
+async fn next_json<T: Serialize + Clone>(rx: &mut broadcast::Receiver<T>, stream: &str) -> Option<String> {
+    loop {
+        match rx.recv().await {
+            Ok(ev) => {
+                if let Ok(text) = serde_json::to_string(&ev) {
+                    return Some(text);
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                debug!(stream, skipped = n, "WebSocket subscriber lagging");
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+/// `GET /presence` — every ESP seen: online or not, for how long and its
+/// uptime percentage.
+async fn list_presence(State(presence): State<Presence>) -> impl IntoResponse {
+    Json(presence.list())
+}
+
+/// `GET /cluster` — cluster members and where each device was last seen.
+async fn get_cluster(State(cluster): State<Cluster>) -> impl IntoResponse {
+    Json(cluster.status())
+}
+
 /// `GET /health` — simple health check.
 async fn health() -> impl IntoResponse {
     Json(serde_json::json!({ "status": "ok" }))
 }
 
+/// `GET /readyz` — 200 when the OpenAI WebSocket (if enabled) is
+/// connected and recently heard from, every configured MQTT broker
+/// connection is up, no critical startup self-test check failed and the
+/// socket tasks and worker queues are live, 503 otherwise.
+async fn readyz(
+    State(openai): State<OpenAiHealth>,
+    State(mqtt): State<MqttHealth>,
+    State(self_test): State<SelfTestReport>,
+    State(liveness): State<Liveness>
+) -> impl IntoResponse {
+    let openai = openai.snapshot();
+    let mqtt = mqtt.snapshot();
+    let self_test = self_test.snapshot();
+    let liveness = liveness.snapshot();
+    let ready = openai.ready && mqtt.ready && self_test.ready && liveness.live;
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (
+        status,
+        Json(
+            serde_json::json!({ "ready": ready, "openai": openai, "mqtt": mqtt, "self_test": self_test, "liveness": liveness })
+        ),
+    )
+}
+
 // ─────────────────────────────────────────────────────────────────────
 //  Server bootstrap
 // ─────────────────────────────────────────────────────────────────────
 
-/// Build the axum Router with all persona routes.
-pub fn build_router(persona: PersonaState) -> Router {
+/// Build the axum Router with all API routes.
+pub fn build_router(state: ApiState) -> Router {
     Router::new()
         .route("/health", get(health))
+        .route("/readyz", get(readyz))
         .route("/persona", get(get_persona).put(set_persona))
         .route("/persona/list", get(list_personas))
-        .with_state(persona)
+        .route("/persona/preview", post(preview_personas))
+        .route("/persona/:name/weights", get(get_persona_weights))
+        .route("/vad/evaluate", post(evaluate_vector))
+        .route("/vad/analyze-audio", post(analyze_audio).layer(DefaultBodyLimit::max(MAX_WAV_BYTES)))
+        .route("/events/ws", get(events_ws))
+        .route("/presence", get(list_presence))
+        .route("/devices", get(list_devices))
+        .route("/devices/import", post(import_devices))
+        .route("/devices/profiles", get(list_profiles))
+        .route("/devices/:id", get(get_device))
+        .route("/devices/:id/thresholds", put(set_device_thresholds))
+        .route("/devices/:id/location", put(set_device_location))
+        .route("/devices/:id/conversation", get(get_conversation).delete(reset_conversation))
+        .route("/devices/:id/play/:clip", post(play_clip))
+        .route("/devices/:id/say", post(say))
+        .route("/devices/:id/diagnostics", post(run_diagnostics))
+        .route("/devices/:id/emotions", get(get_emotions))
+        .route("/stats", get(get_stats))
+        .route("/stats/reset", post(reset_stats))
+        .route("/stats/sound-levels", get(list_sound_levels))
+        .route("/reports/daily", get(get_daily_report))
+        .route("/anomalies", get(list_anomalies))
+        .route("/experiments", get(list_experiments))
+        .route("/stats/sound-levels/:id", get(get_sound_levels))
+        .route("/devices/:id/ota", post(start_ota))
+        .route("/devices/:id/start-policy", get(get_start_policy).put(set_start_policy))
+        .route("/devices/:id/recording-policy", get(get_recording_policy).put(set_recording_policy))
+        .route("/devices/:id/volume", get(get_volume).put(set_volume))
+        .route("/devices/:id/export", get(export_device_data))
+        .route("/devices/:id/data", delete(purge_device_data))
+        .route("/devices/:id/quiet-hours", get(get_quiet_hours).put(set_quiet_hours).delete(clear_quiet_hours))
+        .route("/devices/:id/telemetry", get(get_telemetry))
+        .route("/schemas", get(list_schemas))
+        .route("/schemas/:data_type", put(set_schema).delete(delete_schema))
+        .route("/devices/:id/speakers", get(list_speakers))
+        .route("/devices/:id/speakers/:speaker", put(rename_speaker))
+        .route("/firmware", get(list_firmware))
+        .route(
+            "/firmware/:name",
+            put(upload_firmware).delete(remove_firmware).layer(DefaultBodyLimit::max(MAX_IMAGE_BYTES))
+        )
+        .route("/ota", get(list_ota))
+        .route("/clips", get(list_clips))
+        .route("/downlink", get(list_downlink))
+        .route("/subscriptions", get(list_subscriptions).post(add_subscription))
+        .route("/subscriptions/:id", delete(remove_subscription))
+        .route("/subscriptions/ws", get(alerts_ws))
+        .route(
+            "/broadcast/say",
+            post(broadcast_say).layer(DefaultBodyLimit::max(MAX_WAV_BYTES))
+        )
+        .route("/groups", get(list_groups))
+        .route("/groups/:name", get(get_group).put(set_group).delete(remove_group))
+        .route("/groups/:name/persona", put(set_group_persona))
+        .route("/sites", get(list_sites))
+        .route("/sites/:site/overview", get(get_site_overview))
+        .route("/routes", get(list_routes).post(add_route))
+        .route("/routes/:id", delete(remove_route))
+        .route("/cluster", get(get_cluster))
+        .route("/sessions", get(list_sessions))
+        .route("/sessions/:id", get(get_session))
+        .route("/sessions/:id/monitor", get(get_session_monitor))
+        .route("/recordings/:id", get(get_recording))
+        .route("/recordings/:id/redact", post(redact_recording))
+        .route("/debug/capture", get(get_capture).put(set_capture))
+        .with_state(state)
+}
+
+/// Multi-tenant directory router: `GET /tenants` lists the tenants,
+/// `POST /devices/import` provisions devices of any tenant, and each
+/// tenant's full API is mounted under `/tenants/{id}/...`.
+pub fn build_tenants_router(tenants: Vec<(TenantInfo, ApiState)>) -> Router {
+    let infos: Vec<TenantInfo> = tenants
+        .iter()
+        .map(|(info, _)| info.clone())
+        .collect();
+    let registries: Arc<[(String, DeviceRegistry)]> = tenants
+        .iter()
+        .map(|(info, state)| (info.id.clone(), state.devices.clone()))
+        .collect();
+    let mut router = Router::new()
+        .route(
+            "/tenants",
+            get(move || {
+                let infos = infos.clone();
+                async move { Json(infos) }
+            })
+        )
+        .route(
+            "/devices/import",
+            post(move |headers: HeaderMap, body: Bytes| {
+                let registries = registries.clone();
+                async move { import_tenant_devices(&registries, headers, body).await }
+            })
+        );
+    for (info, state) in tenants {
+        router = router.nest(&format!("/tenants/{}", info.id), build_router(state));
+    }
+    router
 }
 
 /// Start the REST API server.  Returns the `JoinHandle` so the caller
@@ -134,19 +2036,28 @@ pub fn build_router(persona: PersonaState) -> Router {
 pub async fn start_api_server(
     host: &str,
     port: u16,
-    persona: PersonaState
+    state: ApiState,
+    limits: ApiLimits,
+    sockets: &Sockets
+) -> anyhow::Result<tokio::task::JoinHandle<()>> {
+    serve_router(host, port, build_router(state), limits, sockets).await
+}
+
+/// Bind `host:port` (or take it from `sockets`) and serve `app` in a
+/// background task under `limits` (see `api_limits.rs`).
+pub async fn serve_router(
+    host: &str,
+    port: u16,
+    app: Router,
+    limits: ApiLimits,
+    sockets: &Sockets
 ) -> anyhow::Result<tokio::task::JoinHandle<()>> {
     let addr: SocketAddr = format!("{host}:{port}").parse()?;
-    let app = build_router(persona);
 
-    let listener = TcpListener::bind(addr).await?;
+    let listener = sockets.tcp(addr).await?;
     info!(addr = %addr, "🌐 REST API listening");
 
-    let handle = tokio::spawn(async move {
-        if let Err(e) = axum::serve(listener, app).await {
-            tracing::error!(error = %e, "REST API server error");
-        }
-    });
+    let handle = tokio::spawn(api_limits::serve(listener, app, limits));
 
     Ok(handle)
 }
@@ -0,0 +1,260 @@
+use crate::config::Config;
+use axum::extract::{ DefaultBodyLimit, Request, State };
+use axum::http::{ header, StatusCode };
+use axum::middleware::Next;
+use axum::response::{ IntoResponse, Response };
+use axum::Router;
+use dashmap::DashMap;
+use hyper_util::rt::{ TokioIo, TokioTimer };
+use hyper_util::service::TowerToHyperService;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tracing::{ debug, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  REST API connection limits (slowloris protection)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  The API is served by our own accept loop over hyper's HTTP/1 server
+//  instead of `axum::serve`, so that a misbehaving client cannot pin
+//  tasks and file descriptors forever:
+//
+//    --api-max-conns-per-ip     further connections from an address at
+//                               its cap are closed on accept
+//    --api-header-timeout-secs  request headers (including the next
+//                               request on an idle keep-alive
+//                               connection) must arrive within this
+//    --api-max-header-bytes     read buffer cap; larger headers fail
+//    --api-request-timeout-secs request body and handler, up to the
+//                               response head → 408
+//    --api-max-body-bytes       default body limit → 413 (firmware
+//                               uploads keep their own, larger limit)
+//
+//  Streaming routes are exempt from the request timeout: WebSocket
+//  upgrades (`/events/ws`, `/subscriptions/ws`) skip it entirely, and a
+//  streamed response body (`/devices/{id}/export`) is sent after the
+//  timer has stopped, however long it takes.
+
+/// hyper's smallest allowed read buffer.
+const MIN_HEADER_BYTES: usize = 8192;
+
+/// Connection and request limits of the REST API.
+#[derive(Debug, Clone, Copy)]
+pub struct ApiLimits {
+    /// 0 = unlimited
+    pub max_conns_per_ip: usize,
+    pub header_timeout: Duration,
+    pub max_header_bytes: usize,
+    pub request_timeout: Duration,
+    pub max_body_bytes: usize,
+}
+
+impl ApiLimits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_conns_per_ip: config.api_max_conns_per_ip,
+            header_timeout: Duration::from_secs(config.api_header_timeout_secs.max(1)),
+            max_header_bytes: config.api_max_header_bytes.max(MIN_HEADER_BYTES),
+            request_timeout: Duration::from_secs(config.api_request_timeout_secs.max(1)),
+            max_body_bytes: config.api_max_body_bytes,
+        }
+    }
+
+    /// Add the body limit and request timeout to `app`.
+    fn apply(&self, app: Router) -> Router {
+        app.layer(axum::middleware::from_fn_with_state(self.request_timeout, request_timeout)).layer(
+            DefaultBodyLimit::max(self.max_body_bytes)
+        )
+    }
+}
+
+/// 408 for requests (body reading included) whose response head is not
+/// ready within `timeout`.  WebSocket upgrades are let through.
+async fn request_timeout(State(timeout): State<Duration>, req: Request, next: Next) -> Response {
+    if req.headers().contains_key(header::UPGRADE) {
+        return next.run(req).await;
+    }
+    let path = req.uri().path().to_string();
+    match tokio::time::timeout(timeout, next.run(req)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!(path = %path, secs = timeout.as_secs(), "REST request timed out");
+            (StatusCode::REQUEST_TIMEOUT, "request timed out").into_response()
+        }
+    }
+}
+
+/// Open connections per client address.
+#[derive(Clone, Default)]
+struct ConnCounter(Arc<DashMap<IpAddr, usize>>);
+
+/// One counted connection; released on drop.
+struct ConnGuard {
+    counter: ConnCounter,
+    ip: IpAddr,
+}
+
+impl ConnCounter {
+    /// Count a new connection from `ip`, or None if it is at `max`.
+    fn acquire(&self, ip: IpAddr, max: usize) -> Option<ConnGuard> {
+        let mut open = self.0.entry(ip).or_insert(0);
+        if max > 0 && *open >= max {
+            return None;
+        }
+        *open += 1;
+        Some(ConnGuard { counter: self.clone(), ip })
+    }
+
+    fn open(&self, ip: IpAddr) -> usize {
+        self.0.get(&ip).map_or(0, |n| *n)
+    }
+}
+
+impl Drop for ConnGuard {
+    fn drop(&mut self) {
+        self.counter.0.remove_if_mut(&self.ip, |_, open| {
+            *open -= 1;
+            *open == 0
+        });
+    }
+}
+
+/// Accept connections on `listener` and serve `app` under `limits`
+/// until the listener fails.
+pub async fn serve(listener: TcpListener, app: Router, limits: ApiLimits) {
+    let app = limits.apply(app);
+    let conns = ConnCounter::default();
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(v) => v,
+            Err(e) => {
+                // e.g. EMFILE: back off instead of spinning
+                warn!(error = %e, "REST API accept failed");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let Some(guard) = conns.acquire(peer.ip(), limits.max_conns_per_ip) else {
+            debug!(peer = %peer, open = conns.open(peer.ip()), "REST connection refused: per-IP cap");
+            continue;
+        };
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            let _guard = guard;
+            let conn = hyper::server::conn::http1::Builder
+                ::new()
+                .timer(TokioTimer::new())
+                .header_read_timeout(limits.header_timeout)
+                .max_buf_size(limits.max_header_bytes)
+                .serve_connection(TokioIo::new(stream), service)
+                .with_upgrades();
+            if let Err(e) = conn.await {
+                debug!(peer = %peer, error = %e, "REST connection closed");
+            }
+        });
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tokio::io::{ AsyncReadExt, AsyncWriteExt };
+
+    fn limits() -> ApiLimits {
+        ApiLimits {
+            max_conns_per_ip: 1,
+            header_timeout: Duration::from_millis(200),
+            max_header_bytes: MIN_HEADER_BYTES,
+            request_timeout: Duration::from_secs(5),
+            max_body_bytes: 1024,
+        }
+    }
+
+    #[test]
+    fn test_conn_counter_caps_per_ip() {
+        let conns = ConnCounter::default();
+        let (a, b): (IpAddr, IpAddr) = ("10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap());
+        let first = conns.acquire(a, 1).unwrap();
+        assert!(conns.acquire(a, 1).is_none());
+        assert!(conns.acquire(b, 1).is_some());
+        drop(first);
+        assert_eq!(conns.open(a), 0);
+        assert!(conns.acquire(a, 1).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_slow_headers_are_cut_off_and_free_the_slot() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, Router::new().route("/health", get(|| async { "ok" })), limits()));
+
+        // Slowloris: half a request line, then nothing
+        let mut slow = tokio::net::TcpStream::connect(addr).await.unwrap();
+        slow.write_all(b"GET /health HTTP/1.1\r\nHost: x\r\n").await.unwrap();
+        let mut buf = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(2), slow.read_to_end(&mut buf)).await;
+        assert!(read.is_ok(), "connection should be closed by the header timeout");
+
+        // The per-IP slot is free again
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut ok = tokio::net::TcpStream::connect(addr).await.unwrap();
+        ok.write_all(b"GET /health HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        ok.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    }
+
+    async fn fetch(addr: std::net::SocketAddr, path: &str, extra_headers: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {path} HTTP/1.1\r\nHost: x\r\n{extra_headers}Connection: close\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+        String::from_utf8_lossy(&response).into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_spares_streaming_routes() {
+        let limits = ApiLimits { request_timeout: Duration::from_millis(200), max_conns_per_ip: 0, ..limits() };
+        let slow = || async {
+            tokio::time::sleep(Duration::from_millis(600)).await;
+            "late"
+        };
+        let app = Router::new()
+            .route("/slow", get(slow))
+            .route("/ws", get(slow))
+            .route(
+                "/export",
+                get(|| async {
+                    let chunks = futures_util::stream::unfold(0, |i| async move {
+                        if i == 3 {
+                            return None;
+                        }
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                        Some((Ok::<_, std::io::Error>(format!("chunk{i};")), i + 1))
+                    });
+                    axum::body::Body::from_stream(chunks)
+                })
+            );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, app, limits));
+
+        // A plain request/response route is still cut off
+        assert!(fetch(addr, "/slow", "").await.starts_with("HTTP/1.1 408"));
+        // A streamed body keeps flowing past the timeout
+        let export = fetch(addr, "/export", "").await;
+        assert!(export.starts_with("HTTP/1.1 200"), "{export}");
+        assert!(export.contains("chunk0;") && export.contains("chunk2;"), "{export}");
+        // Upgrade requests are never timed out
+        let upgrade = fetch(addr, "/ws", "Upgrade: websocket\r\n").await;
+        assert!(upgrade.starts_with("HTTP/1.1 200"), "{upgrade}");
+    }
+}
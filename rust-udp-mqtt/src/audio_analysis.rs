@@ -0,0 +1,326 @@
+use crate::audio_window::AudioWindow;
+use crate::clips::decode_wav;
+use crate::config::Config;
+use crate::pcm::SampleFormat;
+use crate::sensor::{ SensorPacket, DATA_TYPE_AUDIO };
+use crate::vad::compute_audio_vad;
+use crate::wav_writer::wav_header;
+use serde::{ Deserialize, Serialize };
+use std::hash::{ BuildHasher, Hasher };
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Offline audio analysis
+// ─────────────────────────────────────────────────────────────────────
+//
+//  `POST /vad/analyze-audio` takes a WAV upload (any format the clip
+//  decoder reads, see `clips.rs`), decodes it to 16 kHz mono and runs it
+//  frame by frame through the same energy VAD the workers apply to live
+//  audio — rolling `--audio-vad-window-ms` window included — and answers
+//  synchronously with:
+//
+//    segments   runs of active frames, in ms from the start of the file
+//    energy     the RMS energy / dBFS of every frame (the energy curve)
+//    transcript with `?transcript=true`, the whole file transcribed by
+//               `--openai-transcription-model` (needs an OpenAI key)
+//
+//  Query:
+//
+//    ?sensor_id=N     use that sensor's effective audio threshold
+//                     (default 0, i.e. the global one for most fleets)
+//    ?threshold=X     explicit energy threshold, overrides sensor_id
+//    ?frame_ms=N      curve resolution, 10..=1000 (default 20)
+//
+//  Nothing live is touched: the file gets its own window and no result
+//  reaches the VAD response, history or subscribers.
+
+/// Rate everything is analysed at (the ESP's).
+const SAMPLE_RATE: u64 = 16_000;
+
+const DEFAULT_FRAME_MS: u32 = 20;
+const FRAME_MS_RANGE: std::ops::RangeInclusive<u32> = 10..=1000;
+
+/// Transcription endpoint for `?transcript=true`.
+const OPENAI_TRANSCRIPTIONS_URL: &str = "https://api.openai.com/v1/audio/transcriptions";
+
+/// Query of `POST /vad/analyze-audio`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AnalyzeQuery {
+    #[serde(default)]
+    pub sensor_id: u32,
+    pub threshold: Option<f64>,
+    pub frame_ms: Option<u32>,
+    #[serde(default)]
+    pub transcript: bool,
+}
+
+/// One point of the energy curve.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EnergyPoint {
+    /// Start of the frame
+    pub t_ms: u64,
+    pub energy: f64,
+    pub dbfs: f32,
+}
+
+/// A run of active frames.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct VadSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    /// Highest frame energy inside the segment
+    pub peak_energy: f64,
+}
+
+/// Result of `POST /vad/analyze-audio`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AudioAnalysis {
+    pub duration_ms: u64,
+    pub frame_ms: u32,
+    pub threshold: f64,
+    /// Total length of all segments
+    pub active_ms: u64,
+    pub segments: Vec<VadSegment>,
+    pub energy: Vec<EnergyPoint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum AnalysisError {
+    /// The upload could not be decoded
+    BadAudio(String),
+    BadFrame(u32),
+    /// `?transcript=true` without an OpenAI key
+    NoTranscriber,
+    /// The transcription request failed
+    Transcription(String),
+}
+
+impl std::fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnalysisError::BadAudio(e) => write!(f, "bad audio: {e}"),
+            AnalysisError::BadFrame(ms) => write!(f, "frame_ms must be 10..=1000, got {ms}"),
+            AnalysisError::NoTranscriber => write!(f, "transcripts need --openai-api-key / OPENAI_API_KEY"),
+            AnalysisError::Transcription(e) => write!(f, "transcription failed: {e}"),
+        }
+    }
+}
+
+/// OpenAI speech-to-text client.
+struct Transcriber {
+    client: reqwest::Client,
+    key: String,
+    model: String,
+}
+
+impl Transcriber {
+    /// Transcribe 16 kHz s16 mono PCM in one request.
+    async fn transcribe(&self, pcm: &[u8]) -> anyhow::Result<String> {
+        // RandomState is seeded randomly per process, and differently per call
+        let nonce = std::collections::hash_map::RandomState::new().build_hasher().finish();
+        let boundary = format!("vad-sensor-bridge-{nonce:016x}");
+        let mut body = Vec::with_capacity(pcm.len() + 512);
+        let mut field = |name: &str, extra: &str, content_type: Option<&str>, value: &[u8]| {
+            body.extend_from_slice(format!("--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"{extra}\r\n").as_bytes());
+            if let Some(t) = content_type {
+                body.extend_from_slice(format!("Content-Type: {t}\r\n").as_bytes());
+            }
+            body.extend_from_slice(b"\r\n");
+            body.extend_from_slice(value);
+            body.extend_from_slice(b"\r\n");
+        };
+        field("model", "", None, self.model.as_bytes());
+        let mut wav = wav_header(pcm.len() as u32, 1).to_vec();
+        wav.extend_from_slice(pcm);
+        field("file", "; filename=\"audio.wav\"", Some("audio/wav"), &wav);
+        body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+
+        let resp = self.client
+            .post(OPENAI_TRANSCRIPTIONS_URL)
+            .bearer_auth(&self.key)
+            .header("Content-Type", format!("multipart/form-data; boundary={boundary}"))
+            .body(body)
+            .send().await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            anyhow::bail!("OpenAI returned {status}: {}", body.trim());
+        }
+        let json: serde_json::Value = resp.json().await?;
+        Ok(json["text"].as_str().unwrap_or_default().to_string())
+    }
+}
+
+/// Runs `POST /vad/analyze-audio`.  Clone-friendly.
+#[derive(Clone)]
+pub struct AudioAnalyzer {
+    /// `--audio-vad-window-ms`
+    window: Duration,
+    transcriber: Option<Arc<Transcriber>>,
+}
+
+impl AudioAnalyzer {
+    pub fn new(window: Duration) -> Self {
+        Self { window, transcriber: None }
+    }
+
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let mut analyzer = Self::new(Duration::from_millis(config.audio_vad_window_ms));
+        let key = config.openai_api_key.trim();
+        if !key.is_empty() {
+            let client = reqwest::Client
+                ::builder()
+                .timeout(Duration::from_secs(120))
+                .build()?;
+            analyzer.transcriber = Some(
+                Arc::new(Transcriber {
+                    client,
+                    key: key.to_string(),
+                    model: config.openai_transcription_model.clone(),
+                })
+            );
+        }
+        Ok(analyzer)
+    }
+
+    /// Analyse a WAV upload with energy `threshold` (already resolved
+    /// from the query by the caller).
+    pub async fn analyze(&self, wav: &[u8], threshold: f64, query: &AnalyzeQuery) -> Result<AudioAnalysis, AnalysisError> {
+        let frame_ms = query.frame_ms.unwrap_or(DEFAULT_FRAME_MS);
+        if !FRAME_MS_RANGE.contains(&frame_ms) {
+            return Err(AnalysisError::BadFrame(frame_ms));
+        }
+        let transcriber = match (query.transcript, &self.transcriber) {
+            (false, _) => None,
+            (true, Some(t)) => Some(t.clone()),
+            (true, None) => {
+                return Err(AnalysisError::NoTranscriber);
+            }
+        };
+
+        let pcm = decode_wav(wav).map_err(|e| AnalysisError::BadAudio(e.to_string()))?;
+        let window = self.window;
+        let (pcm, mut analysis) = tokio::task
+            ::spawn_blocking(move || {
+                let analysis = analyze_pcm(&pcm, frame_ms, window, threshold);
+                (pcm, analysis)
+            }).await
+            .map_err(|e| AnalysisError::BadAudio(e.to_string()))?;
+        if let Some(t) = transcriber {
+            analysis.transcript = Some(t.transcribe(&pcm).await.map_err(|e| AnalysisError::Transcription(e.to_string()))?);
+        }
+        info!(
+            duration_ms = analysis.duration_ms,
+            segments = analysis.segments.len(),
+            transcript = analysis.transcript.is_some(),
+            "🔬 audio file analysed"
+        );
+        Ok(analysis)
+    }
+}
+
+/// Energy VAD over 16 kHz s16 mono `pcm` in `frame_ms` frames.
+pub fn analyze_pcm(pcm: &[u8], frame_ms: u32, window: Duration, threshold: f64) -> AudioAnalysis {
+    let frame_bytes = ((SAMPLE_RATE * (frame_ms as u64)) / 1000) as usize * 2;
+    let window = AudioWindow::new(window);
+    let mut packet = SensorPacket {
+        sensor_id: 0,
+        timestamp_us: 0,
+        data_type: DATA_TYPE_AUDIO,
+        sample_format: SampleFormat::S16,
+        seq: 0,
+        payload: Vec::with_capacity(frame_bytes),
+        tenant: Default::default(),
+        ingested_at: None,
+        trace: None,
+    };
+
+    let mut energy = Vec::with_capacity(pcm.len() / frame_bytes + 1);
+    let mut segments: Vec<VadSegment> = Vec::new();
+    let mut open: Option<VadSegment> = None;
+    let mut t_ms = 0u64;
+    for frame in pcm.chunks(frame_bytes) {
+        packet.payload.clear();
+        packet.payload.extend_from_slice(frame);
+        let result = compute_audio_vad(&packet, &window, threshold);
+        packet.seq += 1;
+        let end_ms = t_ms + ((frame.len() as u64) * 1000) / (SAMPLE_RATE * 2);
+        energy.push(EnergyPoint { t_ms, energy: result.energy, dbfs: result.dbfs });
+        match (&mut open, result.is_active) {
+            (Some(seg), true) => {
+                seg.end_ms = end_ms;
+                seg.peak_energy = seg.peak_energy.max(result.energy);
+            }
+            (None, true) => {
+                open = Some(VadSegment { start_ms: t_ms, end_ms, peak_energy: result.energy });
+            }
+            (Some(_), false) => segments.extend(open.take()),
+            (None, false) => {}
+        }
+        t_ms = end_ms;
+    }
+    segments.extend(open);
+
+    AudioAnalysis {
+        duration_ms: t_ms,
+        frame_ms,
+        threshold,
+        active_ms: segments
+            .iter()
+            .map(|s| s.end_ms - s.start_ms)
+            .sum(),
+        segments,
+        energy,
+        transcript: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(value: i16, ms: usize) -> Vec<u8> {
+        (0..ms * 16)
+            .flat_map(|i| (if i % 2 == 0 { value } else { -value }).to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_segments_follow_loud_stretches() {
+        let mut pcm = level(0, 200);
+        pcm.extend(level(3000, 300));
+        pcm.extend(level(5, 100));
+        pcm.extend(level(2000, 100));
+        let a = analyze_pcm(&pcm, 20, Duration::ZERO, 30.0);
+        assert_eq!(a.duration_ms, 700);
+        assert_eq!(a.energy.len(), 35);
+        let spans: Vec<(u64, u64)> = a.segments
+            .iter()
+            .map(|s| (s.start_ms, s.end_ms))
+            .collect();
+        assert_eq!(spans, [(200, 500), (600, 700)]);
+        assert_eq!(a.active_ms, 400);
+        assert!(a.segments[0].peak_energy > a.segments[1].peak_energy);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_wav_upload() {
+        let pcm = level(3000, 100);
+        let mut wav = wav_header(pcm.len() as u32, 1).to_vec();
+        wav.extend_from_slice(&pcm);
+        let analyzer = AudioAnalyzer::new(Duration::ZERO);
+
+        let a = analyzer.analyze(&wav, 30.0, &AnalyzeQuery::default()).await.unwrap();
+        assert_eq!((a.duration_ms, a.segments.len(), a.transcript), (100, 1, None));
+
+        let bad_frame = AnalyzeQuery { frame_ms: Some(5), ..Default::default() };
+        assert!(matches!(analyzer.analyze(&wav, 30.0, &bad_frame).await, Err(AnalysisError::BadFrame(5))));
+        let transcript = AnalyzeQuery { transcript: true, ..Default::default() };
+        assert!(matches!(analyzer.analyze(&wav, 30.0, &transcript).await, Err(AnalysisError::NoTranscriber)));
+        assert!(matches!(analyzer.analyze(b"nope", 30.0, &AnalyzeQuery::default()).await, Err(AnalysisError::BadAudio(_))));
+    }
+}
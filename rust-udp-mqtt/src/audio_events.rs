@@ -0,0 +1,240 @@
+use crate::clock::{ self, SharedClock };
+use crate::config::Config;
+use crate::events::{ SensorEvent, SensorEventKind };
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{ Duration, Instant };
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Acoustic event classifier (cry / scream / glass break / silence)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Energy VAD hears that a child is loud, not whether they laugh or cry.
+//  With `--audio-event-model-path` (needs `--features onnx`, see
+//  `audio_events_onnx.rs`) the VAD workers also feed every audio packet
+//  — ESP AUDIO_UP and sensor-port audio alike — into a per-sensor
+//  window of `--audio-event-window-ms`, scored by the model every half
+//  window.  Each class works like the sensor events in `events.rs`:
+//
+//    fires   when its score rises ≥ --audio-event-threshold (re-armed)
+//    re-arms when its score drops  < half the threshold
+//
+//  plus `--audio-event-cooldown-secs` per class.  Events go onto the
+//  same bus as sensor events (`/events/ws`); cry, scream and glass break
+//  are priority events, silence is not (it marks a room going quiet).
+//  Audio is assumed to be 16 kHz mono, as everywhere on the audio path.
+
+/// Model output order.
+pub const CLASSES: [SensorEventKind; 4] = [
+    SensorEventKind::Cry,
+    SensorEventKind::Scream,
+    SensorEventKind::GlassBreak,
+    SensorEventKind::Silence,
+];
+
+const SAMPLE_RATE: usize = 16_000;
+
+enum AudioEventModel {
+    #[cfg(feature = "onnx")]
+    Onnx(crate::audio_events_onnx::OnnxAudioEventModel),
+}
+
+impl AudioEventModel {
+    fn load(path: &str) -> anyhow::Result<Self> {
+        #[cfg(feature = "onnx")]
+        {
+            Ok(AudioEventModel::Onnx(crate::audio_events_onnx::OnnxAudioEventModel::load(path)?))
+        }
+        #[cfg(not(feature = "onnx"))]
+        {
+            let _ = path;
+            anyhow::bail!("--audio-event-model-path requires a build with `--features onnx`")
+        }
+    }
+
+    fn scores(&self, pcm: &[i16]) -> anyhow::Result<Vec<f32>> {
+        match self {
+            #[cfg(feature = "onnx")]
+            AudioEventModel::Onnx(model) => model.scores(pcm),
+            #[cfg(not(feature = "onnx"))]
+            _ => {
+                let _ = pcm;
+                unreachable!("no audio-event model without the `onnx` feature")
+            }
+        }
+    }
+}
+
+/// Per-sensor, per-class debounce state.
+#[derive(Debug, Clone, Copy)]
+struct ClassState {
+    armed: bool,
+    last_fired: Option<Instant>,
+}
+
+impl Default for ClassState {
+    fn default() -> Self {
+        Self { armed: true, last_fired: None }
+    }
+}
+
+/// Turns class scores into debounced events.
+struct EventGate {
+    threshold: f32,
+    cooldown: Duration,
+    state: Mutex<HashMap<u32, [ClassState; CLASSES.len()]>>,
+    clock: SharedClock,
+}
+
+impl EventGate {
+    fn apply(&self, sensor_id: u32, seq: u64, scores: &[f32]) -> Vec<SensorEvent> {
+        let now = self.clock.now();
+        let mut events = Vec::new();
+        let mut map = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let states = map.entry(sensor_id).or_default();
+        for ((kind, st), &score) in CLASSES.iter().zip(states.iter_mut()).zip(scores) {
+            if score < self.threshold / 2.0 {
+                st.armed = true;
+                continue;
+            }
+            if !st.armed || score < self.threshold {
+                continue;
+            }
+            if st.last_fired.is_some_and(|t| now.saturating_duration_since(t) < self.cooldown) {
+                continue;
+            }
+            st.armed = false;
+            st.last_fired = Some(now);
+            events.push(SensorEvent {
+                sensor_id,
+                seq,
+                kind: *kind,
+                value: score,
+                timestamp_ms: clock::unix_millis(self.clock.as_ref()),
+                tenant: String::new(),
+                priority: *kind != SensorEventKind::Silence,
+                snippet: None,
+            });
+        }
+        events
+    }
+}
+
+/// Audio not yet scored, per sensor.
+#[derive(Default)]
+struct Window {
+    pcm: Vec<i16>,
+}
+
+/// Audio-event classifier shared across VAD workers.
+pub struct AudioEventClassifier {
+    model: AudioEventModel,
+    /// Samples scored at once
+    window: usize,
+    windows: Mutex<HashMap<u32, Window>>,
+    gate: EventGate,
+}
+
+impl AudioEventClassifier {
+    /// Classifier for `--audio-event-model-path`; None when it is unset.
+    pub fn from_config(config: &Config, clock: SharedClock) -> anyhow::Result<Option<Self>> {
+        if config.audio_event_model_path.is_empty() {
+            return Ok(None);
+        }
+        if !(config.audio_event_threshold > 0.0 && config.audio_event_threshold <= 1.0) {
+            anyhow::bail!("--audio-event-threshold must be in (0, 1], got {}", config.audio_event_threshold);
+        }
+        let window = ((config.audio_event_window_ms as usize) * SAMPLE_RATE) / 1000;
+        if window < SAMPLE_RATE / 10 {
+            anyhow::bail!("--audio-event-window-ms must be at least 100");
+        }
+        let model = AudioEventModel::load(&config.audio_event_model_path)?;
+        info!(
+            model = %config.audio_event_model_path,
+            threshold = %config.audio_event_threshold,
+            window_ms = config.audio_event_window_ms,
+            "👂 audio-event classifier enabled"
+        );
+        Ok(
+            Some(Self {
+                model,
+                window,
+                windows: Mutex::default(),
+                gate: EventGate {
+                    threshold: config.audio_event_threshold,
+                    cooldown: Duration::from_secs(config.audio_event_cooldown_secs),
+                    state: Mutex::default(),
+                    clock,
+                },
+            })
+        )
+    }
+
+    /// Feed one packet of 16 kHz s16 LE mono audio from `sensor_id`;
+    /// returns the events raised when a window was scored.
+    pub fn observe(&self, sensor_id: u32, seq: u64, pcm: &[u8]) -> Vec<SensorEvent> {
+        let full = {
+            let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+            let w = windows.entry(sensor_id).or_default();
+            w.pcm.extend(pcm.chunks_exact(2).map(|c| i16::from_le_bytes([c[0], c[1]])));
+            if w.pcm.len() < self.window {
+                return Vec::new();
+            }
+            let full = w.pcm[w.pcm.len() - self.window..].to_vec();
+            // Keep the second half: windows overlap by half
+            w.pcm.drain(..w.pcm.len() - self.window / 2);
+            full
+        };
+        // Scored outside the lock: other sensors keep streaming
+        match self.model.scores(&full) {
+            Ok(scores) => self.gate.apply(sensor_id, seq, &scores),
+            Err(e) => {
+                warn!(sensor_id, error = %e, "audio-event inference failed");
+                Vec::new()
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimClock;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_scores_debounced_per_class() {
+        let sim = SimClock::default();
+        let gate = EventGate {
+            threshold: 0.7,
+            cooldown: Duration::from_secs(10),
+            state: Mutex::default(),
+            clock: Arc::new(sim.clone()),
+        };
+        let kinds = |events: Vec<SensorEvent>| events.into_iter().map(|e| (e.kind, e.priority)).collect::<Vec<_>>();
+
+        assert_eq!(kinds(gate.apply(1, 0, &[0.9, 0.1, 0.0, 0.8])), [
+            (SensorEventKind::Cry, true),
+            (SensorEventKind::Silence, false),
+        ]);
+        // Held high, or dipping above half the threshold: no repeat
+        assert!(gate.apply(1, 1, &[0.9, 0.1, 0.0, 0.5]).is_empty());
+        assert!(gate.apply(1, 2, &[0.5, 0.1, 0.0, 0.9]).is_empty());
+        assert_eq!(kinds(gate.apply(2, 0, &[0.8, 0.0, 0.0, 0.0])), [(SensorEventKind::Cry, true)], "per sensor");
+
+        // Re-armed, but inside the cooldown; then after it
+        assert!(gate.apply(1, 3, &[0.1, 0.1, 0.0, 0.1]).is_empty());
+        assert!(gate.apply(1, 4, &[0.9, 0.1, 0.0, 0.1]).is_empty());
+        gate.apply(1, 5, &[0.1, 0.1, 0.0, 0.1]);
+        sim.advance(Duration::from_secs(10));
+        assert_eq!(kinds(gate.apply(1, 6, &[0.95, 0.0, 0.75, 0.0])), [
+            (SensorEventKind::Cry, true),
+            (SensorEventKind::GlassBreak, true),
+        ]);
+    }
+}
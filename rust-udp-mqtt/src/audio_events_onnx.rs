@@ -0,0 +1,45 @@
+//! Learned audio-event classifier (ONNX Runtime).
+//!
+//! Input:  `[1, samples]` f32 — 16 kHz mono waveform scaled to ±1.0 (the
+//!         model must include its own feature front end, e.g. log-mel).
+//! Output: first output tensor, ≥ 4 f32 scores in \[0, 1\] — cry, scream,
+//!         glass break, silence (see `audio_events::CLASSES`).  Models
+//!         producing logits should end in a sigmoid or softmax.
+//!
+//! ONNX Runtime is loaded dynamically, as for `emotion_onnx.rs`.
+
+use ort::session::Session;
+use ort::value::Tensor;
+use std::sync::Mutex;
+
+/// A loaded ONNX audio-event model, shared by every device.
+pub struct OnnxAudioEventModel {
+    session: Mutex<Session>,
+}
+
+impl OnnxAudioEventModel {
+    /// Load a model from disk.
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let session = Session::builder()
+            .map_err(|e| anyhow::anyhow!("ONNX Runtime init failed: {e}"))?
+            .commit_from_file(path)
+            .map_err(|e| anyhow::anyhow!("failed to load ONNX model {path}: {e}"))?;
+        Ok(Self { session: Mutex::new(session) })
+    }
+
+    /// Class scores of one window of 16 kHz s16 audio.
+    pub fn scores(&self, pcm: &[i16]) -> anyhow::Result<Vec<f32>> {
+        let input: Vec<f32> = pcm
+            .iter()
+            .map(|&s| (s as f32) / 32768.0)
+            .collect();
+        let tensor = Tensor::from_array(([1usize, input.len()], input))?;
+        let mut session = self.session.lock().unwrap_or_else(|e| e.into_inner());
+        let outputs = session.run(ort::inputs![tensor])?;
+        let (_shape, data) = outputs[0].try_extract_tensor::<f32>()?;
+        if data.len() < 4 {
+            anyhow::bail!("ONNX audio-event model returned {} scores, expected 4", data.len());
+        }
+        Ok(data[..4].to_vec())
+    }
+}
@@ -0,0 +1,281 @@
+use crate::capture::CapturedSocket;
+use crate::clips::send_paced_chunks;
+use crate::downlink_pacing::DownlinkPacer;
+use crate::esp_audio_protocol::{ build_control, CTRL_STREAM_END };
+use dashmap::DashMap;
+use serde::{ Deserialize, Serialize };
+use std::net::SocketAddr;
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{ mpsc, oneshot };
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Audio routes (intercom between ESPs)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  `POST /routes` with `{"from": "<device>", "to": "<device>"}` plays
+//  one device's mic on another device's speaker: every AUDIO_UP (or raw
+//  PCM) chunk from `from`, downmixed to mono, goes out to `to` as
+//  AUDIO_DOWN — packetised and paced by the same machinery as clips
+//  (`send_paced_chunks`, honouring `--downlink-pacing`).  A gap of
+//  `STREAM_GAP` in the mic audio closes the stream with STREAM_END, so
+//  the receiving ESP drains its buffer between talkspurts.
+//
+//    bidirectional   also `to` → `from` (robot ↔ robot intercom,
+//                    robot ↔ operator console)
+//    divert          routed audio skips the session pipeline (no VAD,
+//                    WAV or OpenAI) — otherwise it is heard by both
+//
+//  Devices are resolved to addresses when the route is created; a
+//  device that reconnects from a new port needs a new route.  Each
+//  device feeds at most one route.  `DELETE /routes/{id}` ends it.
+
+/// Mic silence after which a routed stream gets STREAM_END.
+const STREAM_GAP: Duration = Duration::from_millis(300);
+
+/// Chunks queued per leg before new audio is dropped (about 4 s).
+const LEG_QUEUE: usize = 128;
+
+/// A route as requested over the API (devices are MACs or `ip:port`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouteSpec {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub bidirectional: bool,
+    #[serde(default)]
+    pub divert: bool,
+}
+
+/// A route in place, as served by `GET /routes`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Route {
+    pub id: u64,
+    pub from: String,
+    pub to: String,
+    pub from_addr: SocketAddr,
+    pub to_addr: SocketAddr,
+    pub bidirectional: bool,
+    pub divert: bool,
+}
+
+/// Why a route was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteError {
+    UnknownDevice(String),
+    /// `from` and `to` are the same device
+    SameDevice,
+    /// The device already feeds another route
+    Busy(String),
+    /// The UDP side is not running (no receiver for requests)
+    Unavailable,
+}
+
+impl std::fmt::Display for RouteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RouteError::UnknownDevice(d) => write!(f, "unknown device: {d}"),
+            RouteError::SameDevice => write!(f, "a device cannot be routed to itself"),
+            RouteError::Busy(d) => write!(f, "device already routed: {d}"),
+            RouteError::Unavailable => write!(f, "audio transport not running"),
+        }
+    }
+}
+
+/// A request from the API to open a route.
+pub struct RouteRequest {
+    pub spec: RouteSpec,
+    pub reply: oneshot::Sender<Result<Route, RouteError>>,
+}
+
+/// One direction of a route: where a source's mic audio goes.
+struct Leg {
+    route: u64,
+    divert: bool,
+    tx: mpsc::Sender<Vec<u8>>,
+}
+
+/// Routes in place + the queue REST requests go through.  Clone-friendly.
+#[derive(Clone)]
+pub struct AudioRoutes {
+    requests: mpsc::Sender<RouteRequest>,
+    routes: Arc<DashMap<u64, Route>>,
+    /// Source address → its leg
+    legs: Arc<DashMap<SocketAddr, Leg>>,
+    next_id: Arc<AtomicU64>,
+    downlink: DownlinkPacer,
+}
+
+impl AudioRoutes {
+    pub fn new(downlink: DownlinkPacer) -> (Self, mpsc::Receiver<RouteRequest>) {
+        let (requests, rx) = mpsc::channel(8);
+        let routes = Self {
+            requests,
+            routes: Arc::default(),
+            legs: Arc::default(),
+            next_id: Arc::new(AtomicU64::new(1)),
+            downlink,
+        };
+        (routes, rx)
+    }
+
+    /// Ask the UDP side to resolve the devices and open the route.
+    pub async fn request(&self, spec: RouteSpec) -> Result<Route, RouteError> {
+        let (reply, rx) = oneshot::channel();
+        self.requests.send(RouteRequest { spec, reply }).await.map_err(|_| RouteError::Unavailable)?;
+        rx.await.map_err(|_| RouteError::Unavailable)?
+    }
+
+    /// Every route in place, oldest first.
+    pub fn list(&self) -> Vec<Route> {
+        let mut routes: Vec<Route> = self.routes.iter().map(|r| r.value().clone()).collect();
+        routes.sort_by_key(|r| r.id);
+        routes
+    }
+
+    /// Open a route between resolved addresses, sending on `socket`.
+    pub fn open(
+        &self,
+        spec: RouteSpec,
+        from_addr: SocketAddr,
+        to_addr: SocketAddr,
+        socket: Arc<CapturedSocket>
+    ) -> Result<Route, RouteError> {
+        if from_addr == to_addr {
+            return Err(RouteError::SameDevice);
+        }
+        if self.legs.contains_key(&from_addr) {
+            return Err(RouteError::Busy(spec.from));
+        }
+        if spec.bidirectional && self.legs.contains_key(&to_addr) {
+            return Err(RouteError::Busy(spec.to));
+        }
+        let route = Route {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            from: spec.from,
+            to: spec.to,
+            from_addr,
+            to_addr,
+            bidirectional: spec.bidirectional,
+            divert: spec.divert,
+        };
+        self.add_leg(route.id, from_addr, to_addr, route.divert, socket.clone());
+        if route.bidirectional {
+            self.add_leg(route.id, to_addr, from_addr, route.divert, socket);
+        }
+        info!(
+            route = route.id,
+            from = %route.from,
+            to = %route.to,
+            bidirectional = route.bidirectional,
+            divert = route.divert,
+            "🔀 audio route opened"
+        );
+        self.routes.insert(route.id, route.clone());
+        Ok(route)
+    }
+
+    fn add_leg(&self, route: u64, from: SocketAddr, to: SocketAddr, divert: bool, socket: Arc<CapturedSocket>) {
+        let (tx, rx) = mpsc::channel(LEG_QUEUE);
+        self.legs.insert(from, Leg { route, divert, tx });
+        tokio::spawn(pump(rx, socket, to, self.downlink.clone()));
+    }
+
+    /// Close a route; false if there is none with this id.
+    pub fn remove(&self, id: u64) -> bool {
+        let Some((_, route)) = self.routes.remove(&id) else {
+            return false;
+        };
+        // Dropping a leg's sender ends its pump (with STREAM_END)
+        self.legs.retain(|_, leg| leg.route != id);
+        info!(route = id, from = %route.from, to = %route.to, "🔀 audio route closed");
+        true
+    }
+
+    /// Whether `src`'s mic audio is routed anywhere.
+    pub fn is_routed(&self, src: SocketAddr) -> bool {
+        self.legs.contains_key(&src)
+    }
+
+    /// Queue `src`'s mono 16 kHz PCM for its route (never blocks: a full
+    /// queue drops it).  True when the audio is diverted from the
+    /// session pipeline.
+    pub fn forward(&self, src: SocketAddr, pcm: &[u8]) -> bool {
+        let Some(leg) = self.legs.get(&src) else {
+            return false;
+        };
+        let _ = leg.tx.try_send(pcm.to_vec());
+        leg.divert
+    }
+}
+
+/// Play one leg's audio on `to` until the route is closed.
+async fn pump(mut rx: mpsc::Receiver<Vec<u8>>, socket: Arc<CapturedSocket>, to: SocketAddr, downlink: DownlinkPacer) {
+    let mut seq: u16 = 0;
+    let mut streaming = false;
+    loop {
+        match tokio::time::timeout(STREAM_GAP, rx.recv()).await {
+            Ok(Some(pcm)) => {
+                if let Err(e) = send_paced_chunks(&socket, to, &pcm, &downlink, &mut seq).await {
+                    warn!(error = %e, esp = %to, "failed to send routed AUDIO_DOWN");
+                }
+                streaming = true;
+            }
+            Ok(None) | Err(_) => {
+                if streaming {
+                    let _ = socket.send_to(&build_control(seq, CTRL_STREAM_END, 0), to).await;
+                    seq = seq.wrapping_add(1);
+                    streaming = false;
+                }
+                if rx.is_closed() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::esp_audio_protocol::{ EspPacket, PKT_AUDIO_DOWN, PKT_CONTROL };
+
+    fn spec(bidirectional: bool) -> RouteSpec {
+        RouteSpec { from: "a".into(), to: "b".into(), bidirectional, divert: true }
+    }
+
+    #[tokio::test]
+    async fn test_route_forwards_and_closes_stream() {
+        let (routes, _rx) = AudioRoutes::new(DownlinkPacer::default());
+        let speaker = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let to = speaker.local_addr().unwrap();
+        let from: SocketAddr = "10.0.0.7:5000".parse().unwrap();
+        let server = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let socket = Arc::new(CapturedSocket::new(server, crate::capture::PacketCapture::new("")).unwrap());
+
+        assert_eq!(routes.open(spec(false), from, from, socket.clone()).unwrap_err(), RouteError::SameDevice);
+        let route = routes.open(spec(true), from, to, socket.clone()).unwrap();
+        assert_eq!(routes.open(spec(false), to, from, socket).unwrap_err(), RouteError::Busy("a".into()));
+        assert!(routes.is_routed(to), "bidirectional routes feed both ways");
+
+        assert!(routes.forward(from, &[1, 0, 2, 0]));
+        let mut buf = [0u8; 2048];
+        let len = speaker.recv(&mut buf).await.unwrap();
+        let pkt = EspPacket::parse(&buf[..len]).unwrap();
+        assert_eq!((pkt.pkt_type, &pkt.payload[..]), (PKT_AUDIO_DOWN, &[1u8, 0, 2, 0][..]));
+
+        // Closing the route ends the stream on the speaker
+        assert!(routes.remove(route.id));
+        assert!(!routes.remove(route.id));
+        assert!(!routes.is_routed(from) && !routes.forward(from, &[0, 0]));
+        let len = speaker.recv(&mut buf).await.unwrap();
+        let pkt = EspPacket::parse(&buf[..len]).unwrap();
+        assert_eq!((pkt.pkt_type, pkt.control_cmd()), (PKT_CONTROL, Some(CTRL_STREAM_END)));
+    }
+}
@@ -0,0 +1,299 @@
+use crate::clock::{ self, SharedClock };
+use std::collections::{ HashMap, VecDeque };
+use std::sync::Mutex;
+use std::time::{ Duration, Instant };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Per-sensor rolling PCM window for audio VAD
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Audio packets carry whatever the firmware happened to buffer (often
+//  ~43 ms), which makes per-packet RMS jumpy for quiet speech onsets.
+//  Instead each sensor keeps the last `window` worth of 16-bit samples
+//  and energy is computed over that fixed span:
+//
+//    packet N-k … packet N  →  [ last 300 ms of samples ]  →  RMS
+//
+//  Alongside RMS the window reports a few cheap spectral hints:
+//
+//    dBFS        20·log10(rms / 32768), floored at -96 dB
+//    zcr         zero crossings per sample pair (0 = DC, 1 = Nyquist tone)
+//    band_ratio  low/high band energy from a 2-tap Haar split at fs/4
+//                (x[n]+x[n-1] vs x[n]-x[n-1]); voiced speech ≫ 1,
+//                hiss and fricatives ≈ 1 or below
+//
+//  All accumulators (Σx², crossings, Σ(x+x')², Σ(x−x')²) are maintained
+//  incrementally in integers, so each packet costs O(samples in packet)
+//  regardless of window length, with no floating-point drift.
+//
+//  If a sensor goes quiet for longer than the window, its buffer is
+//  dropped on the next packet — stale audio must not bleed into a new
+//  utterance.
+
+/// Sample rate assumed for sensor-port PCM (matches the ESP firmware).
+pub const AUDIO_SAMPLE_RATE: u32 = 16_000;
+
+/// dBFS reported for digital silence.
+pub const DBFS_FLOOR: f32 = -96.0;
+
+/// Level and spectral features of one audio window.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioFeatures {
+    pub rms: f64,
+    pub dbfs: f32,
+    pub zcr: f32,
+    pub band_ratio: f32,
+}
+
+#[derive(Default)]
+struct WindowState {
+    samples: VecDeque<i16>,
+    sum_sq: i64,
+    crossings: i64,
+    low_energy: i64,
+    high_energy: i64,
+    last_seen: Option<Instant>,
+}
+
+impl WindowState {
+    fn clear(&mut self) {
+        self.samples.clear();
+        self.sum_sq = 0;
+        self.crossings = 0;
+        self.low_energy = 0;
+        self.high_energy = 0;
+    }
+
+    /// Add (`sign = 1`) or remove (`sign = -1`) the contribution of the
+    /// adjacent pair `(a, b)`.
+    #[inline]
+    fn account_pair(&mut self, a: i16, b: i16, sign: i64) {
+        let (a, b) = (a as i64, b as i64);
+        if (a < 0) != (b < 0) {
+            self.crossings += sign;
+        }
+        self.low_energy += sign * (a + b) * (a + b);
+        self.high_energy += sign * (a - b) * (a - b);
+    }
+
+    fn push(&mut self, s: i16, capacity: usize) {
+        if let Some(&prev) = self.samples.back() {
+            self.account_pair(prev, s, 1);
+        }
+        self.samples.push_back(s);
+        self.sum_sq += (s as i64) * (s as i64);
+
+        if self.samples.len() > capacity {
+            if let Some(old) = self.samples.pop_front() {
+                self.sum_sq -= (old as i64) * (old as i64);
+                if let Some(&next) = self.samples.front() {
+                    self.account_pair(old, next, -1);
+                }
+            }
+        }
+    }
+
+    fn features(&self) -> AudioFeatures {
+        let n = self.samples.len();
+        if n == 0 {
+            return AudioFeatures { dbfs: DBFS_FLOOR, ..Default::default() };
+        }
+        let rms = ((self.sum_sq as f64) / (n as f64)).sqrt();
+        let dbfs = if rms > 0.0 {
+            ((20.0 * (rms / 32768.0).log10()) as f32).max(DBFS_FLOOR)
+        } else {
+            DBFS_FLOOR
+        };
+        let pairs = n.saturating_sub(1).max(1) as f32;
+        AudioFeatures {
+            rms,
+            dbfs,
+            zcr: (self.crossings as f32) / pairs,
+            // +1 keeps silence at 1.0 instead of 0/0
+            band_ratio: ((self.low_energy + 1) as f32) / ((self.high_energy + 1) as f32),
+        }
+    }
+}
+
+/// Thread-safe rolling PCM windows, keyed by sensor_id.
+pub struct AudioWindow {
+    capacity: usize,
+    window: Duration,
+    state: Mutex<HashMap<u32, WindowState>>,
+    clock: SharedClock,
+}
+
+impl AudioWindow {
+    /// Create a store holding `window` of audio per sensor.
+    /// A zero window disables buffering (per-packet features).
+    pub fn new(window: Duration) -> Self {
+        Self::with_clock(window, clock::system())
+    }
+
+    /// Like [`Self::new`], timing gaps on `clock`.
+    pub fn with_clock(window: Duration, clock: SharedClock) -> Self {
+        let capacity = (window.as_secs_f64() * (AUDIO_SAMPLE_RATE as f64)) as usize;
+        Self {
+            capacity,
+            window,
+            state: Mutex::new(HashMap::new()),
+            clock,
+        }
+    }
+
+    /// Append a 16-bit LE PCM payload to the sensor's window and return
+    /// the features over the whole window.
+    pub fn push(&self, sensor_id: u32, payload: &[u8]) -> AudioFeatures {
+        if self.capacity == 0 {
+            let mut st = WindowState::default();
+            for chunk in payload.chunks_exact(2) {
+                st.push(i16::from_le_bytes([chunk[0], chunk[1]]), usize::MAX);
+            }
+            return st.features();
+        }
+
+        let now = self.clock.now();
+        let mut map = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let st = map.entry(sensor_id).or_default();
+
+        if st.last_seen.is_some_and(|t| now.duration_since(t) > self.window) {
+            st.clear();
+        }
+        st.last_seen = Some(now);
+
+        for chunk in payload.chunks_exact(2) {
+            st.push(i16::from_le_bytes([chunk[0], chunk[1]]), self.capacity);
+        }
+        st.features()
+    }
+
+    /// Drop windows for sensors not seen within `max_age`.
+    /// Returns how many were evicted.
+    pub fn evict_stale(&self, max_age: Duration) -> usize {
+        let now = self.clock.now();
+        let mut map = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let before = map.len();
+        map.retain(|_, st| st.last_seen.is_some_and(|t| now.duration_since(t) < max_age));
+        before - map.len()
+    }
+}
+
+/// Background sweep evicting idle sensor windows.
+pub async fn evict_stale_loop(windows: std::sync::Arc<AudioWindow>, max_age_secs: u64) {
+    if max_age_secs == 0 {
+        std::future::pending::<()>().await;
+        return;
+    }
+
+    let max_age = Duration::from_secs(max_age_secs);
+    let period = Duration::from_secs((max_age_secs / 4).clamp(1, 60));
+    loop {
+        windows.clock.sleep(period).await;
+        let evicted = windows.evict_stale(max_age);
+        if evicted > 0 {
+            tracing::info!(evicted, "🧹 evicted stale audio VAD windows");
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pcm(value: i16, n: usize) -> Vec<u8> {
+        (0..n).flat_map(|_| value.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_disabled_window_is_per_packet() {
+        let w = AudioWindow::new(Duration::ZERO);
+        assert!((w.push(1, &pcm(1000, 160)).rms - 1000.0).abs() < 1e-9);
+        assert_eq!(w.push(1, &pcm(0, 160)).rms, 0.0);
+    }
+
+    #[test]
+    fn test_window_spans_packets() {
+        // 10 ms window = 160 samples
+        let w = AudioWindow::new(Duration::from_millis(10));
+        w.push(1, &pcm(1000, 80));
+        // Half loud, half silent → RMS = 1000 / √2
+        let rms = w.push(1, &pcm(0, 80)).rms;
+        assert!((rms - 1000.0 / 2f64.sqrt()).abs() < 1e-6, "rms={rms}");
+
+        // Loud samples fully pushed out
+        assert_eq!(w.push(1, &pcm(0, 160)).rms, 0.0);
+    }
+
+    #[test]
+    fn test_windows_are_per_sensor() {
+        let w = AudioWindow::new(Duration::from_millis(10));
+        w.push(1, &pcm(1000, 160));
+        assert_eq!(w.push(2, &pcm(0, 160)).rms, 0.0);
+    }
+
+    #[test]
+    fn test_gap_longer_than_window_clears() {
+        let w = AudioWindow::new(Duration::from_millis(5));
+        w.push(1, &pcm(1000, 40));
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(w.push(1, &pcm(0, 40)).rms, 0.0);
+    }
+
+    #[test]
+    fn test_dbfs() {
+        let w = AudioWindow::new(Duration::ZERO);
+        assert_eq!(w.push(1, &pcm(0, 160)).dbfs, DBFS_FLOOR);
+        let full = w.push(1, &pcm(i16::MIN, 160));
+        assert!(full.dbfs.abs() < 1e-3, "dbfs={}", full.dbfs);
+        let half = w.push(1, &pcm(16384, 160));
+        assert!((half.dbfs + 6.02).abs() < 0.01, "dbfs={}", half.dbfs);
+    }
+
+    #[test]
+    fn test_zcr_and_band_ratio() {
+        let w = AudioWindow::new(Duration::ZERO);
+
+        // Nyquist square wave: crosses every sample, all high-band energy
+        let nyquist: Vec<u8> = (0..160)
+            .flat_map(|i| (if i % 2 == 0 { 1000i16 } else { -1000 }).to_le_bytes())
+            .collect();
+        let f = w.push(1, &nyquist);
+        assert!((f.zcr - 1.0).abs() < 1e-6);
+        assert!(f.band_ratio < 1e-3);
+
+        // Slow square wave (period 40 samples): few crossings, mostly low band
+        let slow: Vec<u8> = (0..160)
+            .flat_map(|i| (if (i / 20) % 2 == 0 { 1000i16 } else { -1000 }).to_le_bytes())
+            .collect();
+        let f = w.push(1, &slow);
+        assert!(f.zcr < 0.05);
+        assert!(f.band_ratio > 10.0);
+    }
+
+    #[test]
+    fn test_incremental_matches_fresh_computation() {
+        let w = AudioWindow::new(Duration::from_millis(10));
+        let noise: Vec<i16> = (0..1000).map(|i: i32| ((i * 7919) % 2001 - 1000) as i16).collect();
+        let mut last = AudioFeatures::default();
+        for chunk in noise.chunks(70) {
+            let bytes: Vec<u8> = chunk.iter().flat_map(|s| s.to_le_bytes()).collect();
+            last = w.push(1, &bytes);
+        }
+        let tail: Vec<u8> = noise[noise.len() - 160..].iter().flat_map(|s| s.to_le_bytes()).collect();
+        let fresh = AudioWindow::new(Duration::ZERO).push(1, &tail);
+        assert_eq!(last, fresh);
+    }
+
+    #[test]
+    fn test_evict_stale() {
+        let w = AudioWindow::new(Duration::from_millis(10));
+        w.push(1, &pcm(1, 10));
+        std::thread::sleep(Duration::from_millis(10));
+        w.push(2, &pcm(1, 10));
+        assert_eq!(w.evict_stale(Duration::from_millis(5)), 1);
+    }
+}
@@ -0,0 +1,842 @@
+use crate::clock::{ self, SharedClock };
+use crate::config::Config;
+use crate::error::{ BridgeError, ResultExt };
+use crate::events::EventDetector;
+use crate::persona::PersonaState;
+use crate::sensor_smoother::SensorSmoother;
+use crate::stats::Stats;
+use crate::transport_loopback::LoopbackTransport;
+use crate::{
+    admin,
+    anomaly,
+    api,
+    api_limits,
+    audio_analysis,
+    audio_events,
+    audio_routes,
+    audio_window,
+    broadcast,
+    capture,
+    clips,
+    cluster,
+    conversation,
+    device_data,
+    devices,
+    diagnostics,
+    distress,
+    downlink_pacing,
+    emotion_history,
+    events,
+    experiments,
+    failover,
+    handover,
+    migrations,
+    mqtt_health,
+    ota,
+    presence,
+    prompt,
+    quiet_hours,
+    recorder,
+    recording_policy,
+    recordings,
+    reports,
+    safety,
+    self_test,
+    sensor,
+    sensor_sanitize,
+    sensor_schema,
+    sensor_smoother,
+    session_log,
+    shard,
+    sound_levels,
+    speakers,
+    start_policy,
+    stats,
+    store,
+    subscriptions,
+    systemd,
+    telemetry,
+    tenants,
+    transcripts,
+    transport_local,
+    transport_loopback,
+    transport_openai,
+    transport_serial,
+    transport_sip,
+    transport_udp,
+    transport_zmq,
+    tts,
+    uring,
+    vad,
+    vad_shadow,
+    voice_commands,
+    volume,
+};
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+use tracing::{ info, debug, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Bridge instance wiring
+// ─────────────────────────────────────────────────────────────────────
+
+/// How a bridge instance exchanges packets with its devices.
+pub enum Transport {
+    /// UDP sockets on the configured ports, plus the REST API
+    Udp,
+    /// In-memory packets / responses, no sockets at all (embedding and
+    /// integration tests — see `transport_loopback`)
+    Loopback(LoopbackTransport),
+    /// ZeroMQ PULL / PUB sockets in place of the UDP ports, plus the REST
+    /// API (`--zmq-pull`, see `transport_zmq`)
+    Zmq,
+}
+
+impl Transport {
+    /// Clock the instance runs on: wall clock for UDP, the transport's
+    /// own (possibly simulated) clock for loopback.
+    pub fn clock(&self) -> SharedClock {
+        match self {
+            Transport::Udp | Transport::Zmq => clock::system(),
+            Transport::Loopback(loopback) => loopback.clock(),
+        }
+    }
+
+    /// The socket transport `config` asks for.
+    fn of(config: &Config) -> Self {
+        if config.zmq_pull.is_empty() { Transport::Udp } else { Transport::Zmq }
+    }
+}
+
+/// `serve` — run the full bridge until the UDP receivers exit.  With
+/// `--tenants-file`, one isolated bridge per tenant runs side by side and
+/// `--api-port` serves the tenant directory (`/tenants/{id}/...`).  With
+/// `--failover-role`, nothing starts until this side of the pair is the
+/// active one.  Sockets passed by systemd or, with `--handover-socket`,
+/// by the bridge being replaced are used instead of binding afresh.
+pub async fn serve(mut config: Config) -> crate::error::Result<()> {
+    if config.migrate_only {
+        let configs = if config.tenants_file.is_empty() {
+            vec![config]
+        } else {
+            tenants::load(&config.tenants_file, &config).config("--tenants-file")?
+        };
+        for cfg in &configs {
+            migrations::migrate(cfg).await.storage("schema migrations")?;
+        }
+        info!(stores = configs.len(), "✅ migrations applied (--migrate-only)");
+        return Ok(());
+    }
+
+    // Hot-standby pair: heartbeat the peer and start only once active
+    let failover = failover::Failover::from_config(&config).await.config("--failover-role")?;
+    failover.wait_active().await;
+
+    // Zero-downtime restart: take over the running bridge's sockets and
+    // stand ready to hand them to the next one
+    config.sockets = handover::Sockets::inherit(&config.handover_socket).await;
+    if !config.handover_socket.is_empty() {
+        config.sockets.listen(&config.handover_socket).config("--handover-socket")?;
+    }
+    tokio::select! {
+        result = serve_tenants(config) => result,
+        _ = failover.demoted() => Err(BridgeError::transport("failover peer is active, standing down")),
+    }
+}
+
+/// The bridge instance(s) of `config`: one, or one per tenant.
+async fn serve_tenants(config: Config) -> crate::error::Result<()> {
+    if config.tenants_file.is_empty() {
+        let transport = Transport::of(&config);
+        return serve_instance(config, None, transport).await;
+    }
+    let tenants = tenants::load(&config.tenants_file, &config).config("--tenants-file")?;
+    let count = tenants.len();
+    info!(file = %config.tenants_file, tenants = count, "🏢 multi-tenant mode");
+
+    // Each instance hands back its API state once it is wired up
+    let (api_tx, mut api_rx) = mpsc::unbounded_channel();
+    let instances = futures_util::future::try_join_all(
+        tenants.into_iter().map(|cfg| {
+            let transport = Transport::of(&cfg);
+            serve_instance(cfg, Some(api_tx.clone()), transport)
+        })
+    );
+    let directory = async {
+        let mut registered = Vec::with_capacity(count);
+        while registered.len() < count {
+            match api_rx.recv().await {
+                Some(entry) => registered.push(entry),
+                None => return Ok(()),
+            }
+        }
+        registered.sort_by(|(a, _): &(tenants::TenantInfo, _), (b, _)| a.id.cmp(&b.id));
+        tokio::spawn(systemd::supervise(registered.iter().map(|(_, state)| state.liveness.clone()).collect()));
+        let router = api::build_tenants_router(registered);
+        let limits = api_limits::ApiLimits::from_config(&config);
+        let api = api::serve_router(&config.host, config.api_port, router, limits, &config.sockets).await
+            .transport("tenant directory API")?;
+        let sockets = config.sockets.clone();
+        tokio::spawn(async move {
+            sockets.handed_over().await;
+            api.abort();
+        });
+        Ok::<_, BridgeError>(())
+    };
+    tokio::try_join!(instances, directory)?;
+    Ok(())
+}
+
+/// One bridge instance: every piece of state (sessions, persona, devices,
+/// OpenAI session, ...) is created here, so tenants never share any.
+/// In multi-tenant mode the instance's API state is also sent on
+/// `directory` for mounting under `/tenants/{id}`.
+///
+/// Runs until the transport shuts down (UDP receivers exit, or every
+/// `LoopbackHandle` is dropped).
+pub async fn serve_instance(
+    config: Config,
+    directory: Option<mpsc::UnboundedSender<(tenants::TenantInfo, api::ApiState)>>,
+    transport: Transport
+) -> crate::error::Result<()> {
+    info!(
+        tenant = %config.tenant_id,
+        listen = config.listen_addr(),
+        recv_threads = config.resolved_recv_threads(),
+        proc_threads = config.resolved_proc_threads(),
+        audio_proc_threads = config.resolved_audio_proc_threads(),
+        channel_cap = config.channel_capacity,
+        audio_channel_cap = config.audio_channel_capacity,
+        "🚀 vad-sensor-bridge starting"
+    );
+
+    uring::check(config.udp_io).config("--udp-io")?;
+    transport_zmq::check(&config).config("--zmq-pull")?;
+    transport_serial::check(&config).config("--serial")?;
+    transport_sip::check(&config).config("--sip-port")?;
+
+    // Persistent files are upgraded before anything reads them
+    // (PostgreSQL is migrated when the store connects)
+    migrations::migrate_data_dir(&config.audio_save_dir).storage("--audio-save-dir migrations")?;
+
+    // Ports, save dir, models, OpenAI key and brokers, checked before
+    // anything depends on them (reported by /readyz)
+    let self_test = match transport {
+        Transport::Loopback(_) => self_test::SelfTestReport::default(),
+        Transport::Udp | Transport::Zmq => self_test::run(&config, matches!(transport, Transport::Udp)).await,
+    };
+    if config.strict_startup {
+        self_test.enforce().config("--strict-startup")?;
+    }
+
+    let clock = transport.clock();
+    let stats = Stats::with_clock(clock.clone());
+
+    // Shared personality state (changeable via REST API)
+    let persona_state = PersonaState::new(config.persona);
+    info!(tenant = %config.tenant_id, persona = %config.persona, "🎭 Default persona loaded");
+
+    // Mandatory safety banner (locked file) prepended to every prompt
+    let safety = std::sync::Arc::new(safety::SafetyPolicy::load(&config).config("safety banner")?);
+
+    // Device registry (per-sensor threshold overrides, groups and
+    // provisioning profiles, editable via REST)
+    let devices = devices::DeviceRegistry::new(devices::Thresholds {
+        audio: config.audio_threshold,
+        arousal: config.arousal_threshold,
+    });
+
+    // A/B experiments: devices bucketed into variants of the thresholds,
+    // persona and prompt, results compared per variant (REST)
+    let experiments = experiments::Experiments
+        ::load(&config.experiments_file, devices.clone())
+        .config("--experiments-file")?;
+
+    // OpenAI instruction template, rendered when a device is wired
+    let prompt = prompt::PromptContext
+        ::new(prompt::load_template(&config).storage("prompt template")?, persona_state.clone())
+        .with_profiles(devices.clone())
+        .with_experiments(experiments.clone());
+
+    // Canned audio clips + TTS (REST-triggered; clips also spoken when the
+    // cloud is down)
+    let offline_clip = if config.openai_realtime && !config.offline_clip.is_empty() {
+        Some(config.offline_clip.clone())
+    } else {
+        None
+    };
+    let tts = tts::TtsEngine::from_config(&config).config("TTS backend")?;
+    if let Some(ref engine) = tts {
+        info!(backend = engine.name(), "🗣️ TTS backend enabled");
+    }
+    // Per-device AUDIO_DOWN pacing from heartbeat RTT / loss reports
+    let downlink = downlink_pacing::DownlinkPacer::from_config(&config);
+    // EWMA / z-score watch on pipeline rates, link loss and device silence
+    let anomalies = anomaly::AnomalyDetector
+        ::from_config(&config, downlink.clone(), clock.clone())
+        .config("anomaly detection")?;
+    // Per-device quiet hours (REST-managed)
+    let quiet = quiet_hours::QuietHours::new();
+    let (clips, clip_requests) = clips::ClipPlayer::load(
+        &config.clips_dir,
+        offline_clip,
+        tts,
+        downlink.clone(),
+        quiet.clone()
+    ).storage("--clips-dir")?;
+
+    // Announcements to every online ESP (REST-triggered)
+    let (broadcaster, broadcast_requests) = broadcast::Broadcaster::new(clips.clone());
+
+    // Locally spotted voice commands (stop / volume), bypassing OpenAI
+    let (commands, command_requests) = voice_commands::VoiceCommands::load(
+        &config.voice_commands_dir,
+        config.voice_command_threshold
+    ).storage("--voice-commands-dir")?;
+
+    // One-click test-tone / mic loopback check (REST-triggered)
+    let (diagnostics, diagnostics_requests) = diagnostics::Diagnostics::new();
+
+    // Hosted firmware images + OTA transfers to ESPs (REST-triggered)
+    let firmware_dir = if config.tenant_id.is_empty() {
+        config.firmware_dir.clone()
+    } else {
+        format!("{}/{}", config.firmware_dir, config.tenant_id)
+    };
+    let (ota, ota_requests) = ota::Ota::load(&firmware_dir, clock.clone());
+
+    // ESP ↔ ESP intercom routes (REST-managed)
+    let (routes, route_requests) = audio_routes::AudioRoutes::new(downlink.clone());
+
+    // Firmware log lines + counters sent by ESPs (PKT_TELEMETRY)
+    let telemetry = telemetry::DeviceTelemetry::new(config.telemetry_log_lines);
+
+    // Runtime-toggleable pcap capture of ESP datagrams (REST-triggered)
+    let capture = if config.tenant_id.is_empty() {
+        capture::PacketCapture::new(&config.capture_dir)
+    } else {
+        capture::PacketCapture::new(&format!("{}/{}", config.capture_dir, config.tenant_id))
+    };
+
+    // OpenAI WebSocket liveness (keepalive + watchdog, reported by /readyz)
+    let openai_health = transport_openai::OpenAiHealth::new(
+        config.openai_realtime,
+        std::time::Duration::from_secs(config.openai_ping_secs),
+        std::time::Duration::from_secs(config.openai_stale_secs)
+    );
+
+    // Broker connections of the admin / alert / transcript clients (/readyz)
+    let mqtt_health = mqtt_health::MqttHealth::default();
+
+    // Per-device OpenAI conversation history (re-injected on reconnect)
+    let conversations = conversation::ConversationStore::new(config.conversation_history_turns);
+
+    // Device overrides, persona, sessions + transcripts shared with other
+    // instances in PostgreSQL (loaded now, synced in the background)
+    let store = store::SharedStore
+        ::from_config(&config, persona_state.clone(), devices.clone(), conversations.clone()).await
+        .storage("PostgreSQL store")?;
+
+    // Per-device emotion timeline (REST-queried)
+    let emotions = emotion_history::EmotionHistory::new(
+        std::time::Duration::from_secs(config.emotion_history_hours * 3600),
+        clock.clone()
+    );
+    tokio::spawn(emotion_history::evict_expired_loop(emotions.clone()));
+
+    // Daily per-tenant summaries (sessions, emotions, errors, OpenAI cost)
+    let reports = reports::DailyReports::from_config(&config, emotions.clone(), clock.clone());
+    tokio::spawn(reports::run(reports.clone()));
+
+    // Per-ESP-session JSONL logs + recent OpenAI timelines (sessions API)
+    // (every file written is indexed per device for export / erasure)
+    let device_files = device_data::DeviceFiles::load(&config.audio_save_dir);
+    let session_logs = session_log::SessionLogs
+        ::new(config.session_log.then_some(config.audio_save_dir.as_str()), device_files.clone())
+        .with_store(store.clone())
+        .with_reports(reports.clone())
+        .with_experiments(experiments.clone());
+
+    // SESSION_START while receiving: per-device policy (editable via REST)
+    let start_policies = start_policy::StartPolicies::new(config.duplicate_start_policy);
+
+    // Per-device speaker labels for user transcripts (names via REST)
+    let diarizer = speakers::Diarizer::from_config(&config).config("speaker diarization")?;
+
+    // Export / erasure of everything stored per device (REST)
+    let device_data = device_data::DeviceData
+        ::new(device_files.clone(), session_logs.clone(), conversations.clone(), diarizer.clone())
+        .with_store(store.clone());
+
+    // Per-device recording consent + retention (REST, persisted, audited)
+    let recording_policies = recording_policy::RecordingPolicies
+        ::load(&config, device_files.clone(), safety.clone(), clock.clone())
+        .storage("recording policies")?;
+    tokio::spawn(recording_policy::sweep_loop(recording_policies.clone()));
+
+    // Session WAVs transcoded on demand for browsers (REST, cached)
+    let recordings = recordings::Recordings::from_config(&config, device_files);
+
+    // Offline energy VAD (+ transcripts) over uploaded WAVs (REST)
+    let analyzer = audio_analysis::AudioAnalyzer::from_config(&config).config("audio analysis")?;
+
+    // Transcript forwarding (MQTT / webhooks / PostgreSQL)
+    let transcripts = transcripts::TranscriptSink
+        ::from_config(&config, &mqtt_health, store)
+        .config("transcript sinks")?
+        .with_experiments(experiments.clone());
+
+    // Device online / offline events (WebSocket, MQTT, webhooks) + uptime
+    let presence = presence::Presence
+        ::from_config(&config, &mqtt_health, clock.clone())
+        .config("presence sinks")?
+        .with_anomalies(anomalies.clone());
+
+    // Shared sensor smoother (EMA decay for idle_time), striped like the
+    // sensor-lane VAD worker queues
+    let proc_threads = config.resolved_proc_threads();
+    let smoother = std::sync::Arc::new(
+        SensorSmoother::with_shards(
+            std::time::Duration::from_secs(config.smoother_reset_gap_secs),
+            clock.clone(),
+            proc_threads
+        )
+    );
+
+    // Log what input sanitization repaired, each stats interval
+    let sanitize_stats = smoother.sanitize_stats();
+    let interval = config.stats_interval_secs;
+    tokio::spawn(async move {
+        sensor_sanitize::sanitize_reporter(sanitize_stats, interval).await;
+    });
+
+    // Spawn smoother eviction sweep
+    let smoother_clone = smoother.clone();
+    let evict_secs = config.smoother_evict_secs;
+    tokio::spawn(async move {
+        sensor_smoother::evict_stale_loop(smoother_clone, evict_secs).await;
+    });
+
+    // ESP volume / mute, tracked in the registry (REST + voice commands)
+    let (volume, volume_requests) = volume::VolumeControl::new(devices.clone());
+
+    // Optional multi-instance coordination (shared persona, overrides,
+    // device registry)
+    let cluster = cluster::Cluster::from_config(
+        &config,
+        persona_state.clone(),
+        devices.clone(),
+        smoother.clone(),
+        clock.clone()
+    ).config("cluster")?;
+
+    // Per-sensor rolling PCM windows for audio VAD energy
+    let audio_window = std::sync::Arc::new(
+        audio_window::AudioWindow::with_clock(
+            std::time::Duration::from_millis(config.audio_vad_window_ms),
+            clock.clone()
+        )
+    );
+    let audio_window_clone = audio_window.clone();
+    tokio::spawn(async move {
+        audio_window::evict_stale_loop(audio_window_clone, evict_secs).await;
+    });
+
+    // Emotional V/A/D engine (linear weights or learned ONNX model)
+    let engine = std::sync::Arc::new(
+        vad::EmotionEngine::from_config(
+            config.emotion_engine,
+            &config.emotion_model_path,
+            &config.emotion_weights,
+            &stats
+        ).config("emotion engine")?
+    );
+    info!(engine = ?config.emotion_engine, "🧠 emotion engine loaded");
+
+    // Optional shadow engine for side-by-side comparison
+    let shadow = match config.shadow_emotion_engine {
+        Some(kind) => {
+            let shadow_engine = vad::EmotionEngine::from_config(
+                kind,
+                &config.shadow_emotion_model_path,
+                &config.shadow_emotion_weights,
+                &stats
+            ).config("shadow emotion engine")?;
+            info!(engine = ?kind, "👥 shadow emotion engine loaded");
+            Some(std::sync::Arc::new(shadow_engine))
+        }
+        None => None,
+    };
+    let shadow_stats = vad_shadow::ShadowStats::new();
+    if shadow.is_some() {
+        let shadow_stats = shadow_stats.clone();
+        let interval = config.stats_interval_secs;
+        tokio::spawn(async move {
+            vad_shadow::shadow_reporter(shadow_stats, interval).await;
+        });
+    }
+
+    // Discrete event extraction (fall, pickup, ...) + subscriber fan-out
+    let detector = std::sync::Arc::new(EventDetector::with_clock(clock.clone()));
+    let event_bus = events::event_bus(1024);
+    events::spawn_forwarder(&config, &mqtt_health, &event_bus).config("sensor event forwarding")?;
+    let detector_clone = detector.clone();
+    tokio::spawn(async move {
+        events::evict_stale_loop(detector_clone, evict_secs).await;
+    });
+
+    // Optional acoustic event classifier (cry, scream, ...) on the audio path
+    let audio_events = audio_events::AudioEventClassifier
+        ::from_config(&config, clock.clone())
+        .config("audio-event classifier")?
+        .map(std::sync::Arc::new);
+
+    // Opt-in distress alarm on the emotional V/A/D stream
+    let distress = distress::DistressDetector
+        ::from_config(&config, clock.clone())
+        .config("distress alarm")?
+        .map(std::sync::Arc::new);
+
+    // Optional raw sensor stream recording (CSV / Parquet)
+    let recorder = recorder::SensorRecorder::from_config(&config, clock.clone()).storage("sensor recorder")?;
+
+    // Per-device ambient sound levels (hourly, REST-queried)
+    let sound_levels = sound_levels::SoundLevels::new(
+        std::time::Duration::from_secs(config.sound_level_window_secs.max(1)),
+        std::time::Duration::from_secs(config.sound_level_days * 86_400),
+        clock.clone()
+    );
+    tokio::spawn(sound_levels::sweep_loop(sound_levels.clone()));
+
+    // Threshold-crossing alert rules (REST / MQTT registered)
+    let subscriptions = subscriptions::Subscriptions::from_config(&config, clock.clone(), &mqtt_health).config("subscriptions")?;
+
+    // Per-data_type channel validation (clamp / reject out-of-range values)
+    let schemas = sensor_schema::SchemaRegistry::load(&config.sensor_schemas).config("--sensor-schemas")?;
+
+    // Sensor ingest switch (MQTT control plane `pause_ingest`)
+    let ingest = admin::IngestGate::default();
+
+    // Channels: UDP receivers → VAD processors, separate audio and sensor
+    // lanes, one queue per worker, sharded by sensor_id (per-device ordering)
+    let (tx, rxs) = shard::channels(
+        shard::LaneConfig { workers: config.resolved_audio_proc_threads(), capacity: config.audio_channel_capacity },
+        shard::LaneConfig { workers: proc_threads, capacity: config.channel_capacity },
+        clock.clone()
+    );
+    let deadline = std::time::Duration::from_millis(config.packet_deadline_ms);
+
+    // Socket tasks alive + worker queues draining (systemd watchdog, /readyz)
+    let liveness = systemd::Liveness::new(
+        &config.tenant_id,
+        tx.clone(),
+        stats.clone(),
+        std::time::Duration::from_secs(config.watchdog_stall_secs.max(1)),
+        clock.clone()
+    );
+
+    // Channel: VAD processors → response senders
+    let (vad_tx, vad_rx) = mpsc::channel(config.channel_capacity);
+
+    // Spawn stats reporter
+    let stats_clone = stats.clone();
+    let stats_interval = config.stats_interval_secs;
+    let stats_tenant = config.tenant_id.clone();
+    let stats_clock = clock.clone();
+    let stats_anomalies = anomalies.clone();
+    let stats_sinks = stats::StatsSinks::from_config(&config, &mqtt_health).config("stats sinks")?;
+    tokio::spawn(async move {
+        stats::stats_reporter(stats_clone, stats_interval, stats_tenant, stats_clock, stats_anomalies, stats_sinks).await;
+    });
+
+    // Spawn VAD processor workers, each draining its own shard
+    let vad_tx_clone = vad_tx.clone();
+    for (i, (lane, mut rx)) in rxs.into_iter().enumerate() {
+        let stats = stats.clone();
+        let vad_tx = vad_tx_clone.clone();
+        let persona = persona_state.clone();
+        let smoother = smoother.clone();
+        let detector = detector.clone();
+        let event_bus = event_bus.clone();
+        let engine = engine.clone();
+        let shadow = shadow.clone();
+        let audio_window = audio_window.clone();
+        let devices = devices.clone();
+        let experiments = experiments.clone();
+        let shadow_stats = shadow_stats.clone();
+        let prompt = prompt.clone();
+        let recorder = recorder.clone();
+        let emotions = emotions.clone();
+        let sound_levels = sound_levels.clone();
+        let distress = distress.clone();
+        let audio_events = audio_events.clone();
+        let subscriptions = subscriptions.clone();
+        let ingest = ingest.clone();
+        let schemas = schemas.clone();
+        let clock = clock.clone();
+        tokio::spawn(async move {
+            while let Some(mut pkt) = rx.recv().await {
+                // Everything logged for the packet carries its trace id
+                let _span = tracing::info_span!("packet", trace = pkt.trace.map(tracing::field::display)).entered();
+                if shard::is_stale(&pkt, deadline, &clock) {
+                    stats.record_shed();
+                    continue;
+                }
+                if ingest.drop_packet() {
+                    continue;
+                }
+                if schemas.check(&mut pkt) == sensor_schema::Verdict::Rejected {
+                    debug!(sensor_id = pkt.sensor_id, seq = pkt.seq, data_type = pkt.data_type, "sensor packet rejected by schema");
+                    continue;
+                }
+                if pkt.data_type == sensor::DATA_TYPE_SENSOR_VECTOR {
+                    if let Some(sv) = sensor::SensorVector::from_payload(&pkt.payload) {
+                        prompt.observe_battery_low(pkt.sensor_id, sv.battery_low);
+                        for mut ev in detector.observe(pkt.sensor_id, pkt.seq, &sv) {
+                            ev.tenant = pkt.tenant.to_string();
+                            info!(
+                                tenant = %pkt.tenant,
+                                sensor_id = ev.sensor_id,
+                                seq = ev.seq,
+                                kind = %ev.kind,
+                                value = format!("{:.2}", ev.value),
+                                "⚡ sensor event"
+                            );
+                            // No subscribers is fine
+                            let _ = event_bus.send(ev);
+                        }
+                    }
+                }
+                if pkt.data_type == sensor::DATA_TYPE_AUDIO {
+                    let pcm = pkt.sample_format.to_s16le(&pkt.payload);
+                    sound_levels.record(pkt.sensor_id, &pcm);
+                    if let Some(ref audio_events) = audio_events {
+                        for mut ev in audio_events.observe(pkt.sensor_id, pkt.seq, &pcm) {
+                            ev.tenant = pkt.tenant.to_string();
+                            info!(
+                                tenant = %pkt.tenant,
+                                sensor_id = ev.sensor_id,
+                                seq = ev.seq,
+                                kind = %ev.kind,
+                                score = format!("{:.2}", ev.value),
+                                "👂 audio event"
+                            );
+                            let _ = event_bus.send(ev);
+                        }
+                    }
+                }
+                let active_persona = experiments
+                    .persona(pkt.sensor_id)
+                    .or_else(|| devices.persona(pkt.sensor_id))
+                    .unwrap_or_else(|| persona.get_blocking());
+                let thresholds = experiments.thresholds(pkt.sensor_id, devices.thresholds(pkt.sensor_id));
+                let result = match &shadow {
+                    Some(shadow) => {
+                        let (result, shadow_result) = vad::process_packet_shadowed(
+                            &pkt,
+                            active_persona,
+                            &smoother,
+                            &audio_window,
+                            &engine,
+                            shadow,
+                            thresholds
+                        );
+                        if let Some(shadow_result) = shadow_result {
+                            shadow_stats.record(&result, &shadow_result);
+                        }
+                        result
+                    }
+                    None =>
+                        vad::process_packet_with(
+                            &pkt,
+                            active_persona,
+                            &smoother,
+                            &audio_window,
+                            &engine,
+                            thresholds
+                        ),
+                };
+                match result.kind {
+                    vad::VadKind::Audio => {
+                        debug!(
+                            sensor_id = result.sensor_id,
+                            seq = result.seq,
+                            is_active = result.is_active,
+                            energy = format!("{:.2}", result.energy),
+                            "🎙️  VAD audio"
+                        );
+                    }
+                    vad::VadKind::Emotional => {
+                        info!(
+                            tenant = %pkt.tenant,
+                            sensor_id = result.sensor_id,
+                            seq = result.seq,
+                            is_active = result.is_active,
+                            valence = format!("{:.3}", result.valence),
+                            arousal = format!("{:.3}", result.arousal),
+                            dominance = format!("{:.3}", result.dominance),
+                            "💡 VAD emotional"
+                        );
+                    }
+                }
+                if let Some(ref recorder) = recorder {
+                    recorder.record(&pkt, &result);
+                }
+                emotions.record(&result);
+                experiments.record_vad(&result);
+                if let Some(ref distress) = distress {
+                    if let Some(mut ev) = distress.observe(&result) {
+                        ev.tenant = pkt.tenant.to_string();
+                        warn!(
+                            tenant = %pkt.tenant,
+                            sensor_id = ev.sensor_id,
+                            seq = ev.seq,
+                            valence_drop = format!("{:.2}", ev.value),
+                            "🚨 distress detected"
+                        );
+                        distress.notify(&ev);
+                        let _ = event_bus.send(ev);
+                    }
+                }
+                subscriptions.observe(&result);
+                stats.record_processed(result.is_active);
+                let _ = vad_tx.try_send(result);
+            }
+            tracing::debug!(worker = i, lane = %lane, "VAD processor stopped");
+        });
+    }
+
+    // Spawn REST API server (persona management + event stream)
+    let api_state = api::ApiState {
+        tenant: config.tenant_id.as_str().into(),
+        persona: persona_state.clone(),
+        emotion_engine: engine.clone(),
+        events: event_bus.clone(),
+        devices: devices.clone(),
+        conversations: conversations.clone(),
+        clips: clips.clone(),
+        broadcast: broadcaster,
+        diagnostics,
+        ota: ota.clone(),
+        capture: capture.clone(),
+        emotions,
+        sound_levels,
+        stats: stats.clone(),
+        subscriptions,
+        cluster: cluster.clone(),
+        sessions: session_logs.clone(),
+        start_policies: start_policies.clone(),
+        recording_policies: recording_policies.clone(),
+        reports,
+        anomalies,
+        experiments,
+        downlink: downlink.clone(),
+        speakers: diarizer.clone(),
+        volume: volume.clone(),
+        quiet: quiet.clone(),
+        routes: routes.clone(),
+        telemetry: telemetry.clone(),
+        presence: presence.clone(),
+        device_data,
+        recordings,
+        analyzer,
+        schemas,
+        openai: openai_health.clone(),
+        mqtt: mqtt_health.clone(),
+        self_test,
+        liveness: liveness.clone(),
+    };
+
+    // Remote administration over MQTT (fleets behind NAT)
+    admin::AdminPlane {
+        persona: persona_state.clone(),
+        devices: devices.clone(),
+        diagnostics: api_state.diagnostics.clone(),
+        ingest,
+    }.spawn(&config, &mqtt_health);
+
+    // A single instance reports to systemd itself; with tenants `serve`
+    // waits for all of them
+    if let Some(directory) = directory {
+        let _ = directory.send((tenants::TenantInfo::of(&config), api_state.clone()));
+    } else if !matches!(transport, Transport::Loopback(_)) {
+        tokio::spawn(systemd::supervise(vec![liveness.clone()]));
+    }
+
+    if let Transport::Loopback(loopback) = transport {
+        info!(tenant = %config.tenant_id, "✅ All systems go — loopback transport (no sockets)");
+        return transport_loopback::run(loopback, tx, vad_rx, stats, config.tenant_id.as_str().into()).await;
+    }
+
+    let limits = api_limits::ApiLimits::from_config(&config);
+    let api_handle = api::start_api_server(&config.host, config.api_port, api_state, limits, &config.sockets).await
+        .transport("REST API")?;
+
+    // Sidecars on this host (Unix sockets / stdin) feed the same workers
+    transport_local::spawn(&config, tx.clone(), stats.clone()).transport("local ingest")?;
+    // ... and so do wired nodes on serial ports
+    transport_serial::spawn(&config, tx.clone(), stats.clone()).transport("serial ingest")?;
+
+    if let Transport::Zmq = transport {
+        liveness.started([]);
+        info!(tenant = %config.tenant_id, "✅ All systems go — listening for sensor data via ZeroMQ");
+        return transport_zmq::run(&config, tx, vad_rx, stats).await;
+    }
+
+    // Spawn UDP receivers + response handlers
+    let draining = openai_health.clone();
+    let handles = transport_udp::spawn_udp_receivers(
+        &config,
+        tx,
+        vad_rx,
+        stats.clone(),
+        smoother.clone(),
+        prompt,
+        conversations,
+        openai_health,
+        transcripts,
+        safety,
+        clips,
+        clip_requests,
+        broadcast_requests,
+        diagnostics_requests,
+        ota,
+        ota_requests,
+        capture,
+        cluster,
+        session_logs,
+        start_policies,
+        recording_policies,
+        downlink.clone(),
+        diarizer,
+        commands,
+        command_requests,
+        volume,
+        volume_requests,
+        quiet,
+        routes,
+        route_requests,
+        telemetry,
+        presence,
+        clock
+    ).await.transport("UDP receivers")?;
+    let tasks: Vec<_> = handles.iter().map(|h| h.abort_handle()).collect();
+    liveness.started(tasks.clone());
+
+    info!("✅ All systems go — listening for sensor data via UDP");
+
+    tokio::select! {
+        result = futures_util::future::try_join_all(handles) => {
+            result.transport("UDP receiver task")?;
+        }
+        // The next bridge reads our sockets now: stop receiving, let
+        // replies in flight finish, then exit
+        _ = config.sockets.handed_over() => {
+            tasks.iter().for_each(AbortHandle::abort);
+            api_handle.abort();
+            handover::drain(&draining, std::time::Duration::from_secs(config.handover_drain_secs)).await;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,258 @@
+use crate::prompt::PromptContext;
+use crate::silence_trim::SilenceTrimmer;
+use crate::speakers::{ Diarizer, SpeakerProbe };
+use crate::transport_openai::OpenAiSession;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{ debug, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  OpenAI attach / detach (transport-agnostic)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  A device audio session on any ingest path goes through the same
+//  steps against the persistent Realtime session, whatever protocol
+//  framed the audio:
+//
+//    attach   session start: the device becomes the active one (it
+//             hears the replies), its prompt and conversation history
+//             are loaded, and it gets an `AudioLink`
+//    forward  every PCM chunk (16 kHz s16): silence-trimmed, queued
+//             (and kept as a voice probe with `--speaker-engine`)
+//    finish   session end: trimmed tail flushed, buffer committed,
+//             response requested (conversation mode), speaker identified
+//    cancel   abort: reply stopped, device detached, audio discarded
+//    discard  audio discarded only (a voice command was spotted)
+//
+//  Transports keep everything protocol-specific: control replies, WAV
+//  saving, session logs and the offline clip fallback (`Finish::Offline`).
+//  `addr` is where the device listens for AUDIO_DOWN; `device` is its
+//  name in prompts and conversation history (MAC or `ip:port`).
+
+/// Attach point of device audio sessions to the persistent OpenAI
+/// session (a no-op without `--openai-realtime`).  Clone-friendly.
+#[derive(Clone)]
+pub struct RealtimeBridge {
+    session: Option<Arc<OpenAiSession>>,
+    prompt: PromptContext,
+    speakers: Diarizer,
+}
+
+/// One attached device session's audio path to OpenAI.
+pub struct AudioLink {
+    tx: mpsc::Sender<Vec<u8>>,
+    /// Head/tail silence trimming (`--openai-trim-silence`)
+    trimmer: Option<SilenceTrimmer>,
+    /// Voice probe for speaker diarization (`--speaker-engine`)
+    speaker: Option<SpeakerProbe>,
+}
+
+/// What became of a finished session's audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finish {
+    /// Committed (and a response requested in conversation mode)
+    Committed,
+    /// Only silence was heard; nothing committed
+    Silent,
+    /// Transcription-only session is down; the audio is lost
+    NotTranscribed,
+    /// No OpenAI to answer: the transport should fall back to clips
+    Offline,
+}
+
+impl RealtimeBridge {
+    pub fn new(session: Option<Arc<OpenAiSession>>, prompt: PromptContext, speakers: Diarizer) -> Self {
+        Self { session, prompt, speakers }
+    }
+
+    /// Whether `--openai-realtime` is on.
+    pub fn enabled(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// Whether attached sessions get spoken replies (false in
+    /// `--openai-mode transcribe`).
+    pub fn responds(&self) -> bool {
+        self.session.as_ref().is_some_and(|oai| oai.responds())
+    }
+
+    /// Wire a starting device session to OpenAI (None when disabled).
+    pub async fn attach(&self, addr: SocketAddr, device: &str) -> Option<AudioLink> {
+        let Some(oai) = self.session.as_ref() else {
+            debug!(src = %addr, "OpenAI Realtime not enabled — skipping");
+            return None;
+        };
+        oai.set_active_esp(addr).await;
+        oai.clear_input_buffer().await;
+        self.prompt.set_device(device.to_string());
+        oai.update_instructions(&self.prompt.instructions().await).await;
+        oai.activate_device(device).await;
+        info!(src = %addr, device = %device, "🤖 wired ESP client to persistent OpenAI session");
+        Some(AudioLink {
+            tx: oai.audio_tx.clone(),
+            trimmer: oai.silence_trimmer(),
+            speaker: self.speakers.probe(device),
+        })
+    }
+
+    /// Commit a finished session's audio.
+    pub async fn finish(&self, addr: SocketAddr, link: Option<AudioLink>, audio_secs: f64) -> Finish {
+        match self.session.as_ref() {
+            Some(oai) if oai.is_connected() => {
+                let mut probe = None;
+                if let Some(mut link) = link {
+                    probe = link.speaker.take();
+                    if !link.flush(addr).await {
+                        return Finish::Silent;
+                    }
+                }
+                oai.commit_input_buffer().await;
+                if let Some(probe) = probe {
+                    self.speakers.identify(probe);
+                }
+                if oai.responds() {
+                    oai.create_response().await;
+                }
+                info!(src = %addr, audio_secs = format!("{:.1}", audio_secs),
+                      respond = oai.responds(),
+                      "📝 committed OpenAI audio buffer");
+                Finish::Committed
+            }
+            Some(oai) if !oai.responds() => {
+                warn!(src = %addr, "OpenAI down — session audio not transcribed");
+                Finish::NotTranscribed
+            }
+            _ => Finish::Offline,
+        }
+    }
+
+    /// Stop the reply this device is hearing, detach it and discard its
+    /// buffered audio.
+    pub async fn cancel(&self, addr: SocketAddr) {
+        let Some(oai) = self.session.as_ref() else {
+            return;
+        };
+        if *oai.active_esp.read().await == Some(addr) {
+            oai.cancel_response().await;
+        }
+        oai.clear_active_esp().await;
+        oai.clear_input_buffer().await;
+    }
+
+    /// Discard this device's buffered audio; a reply it is hearing keeps
+    /// playing.
+    pub async fn discard(&self, addr: SocketAddr) {
+        let Some(oai) = self.session.as_ref() else {
+            return;
+        };
+        if *oai.active_esp.read().await == Some(addr) {
+            oai.clear_input_buffer().await;
+        }
+    }
+
+    /// Deliver replies for this device (16 kHz s16le) to `tap` instead
+    /// of sending AUDIO_DOWN.  False when OpenAI is off.
+    pub fn tap_replies(&self, addr: SocketAddr, tap: mpsc::UnboundedSender<Vec<u8>>) -> bool {
+        let Some(oai) = self.session.as_ref() else {
+            return false;
+        };
+        oai.reply_taps.insert(addr, tap);
+        true
+    }
+
+    pub fn untap_replies(&self, addr: SocketAddr) {
+        if let Some(oai) = self.session.as_ref() {
+            oai.reply_taps.remove(&addr);
+        }
+    }
+}
+
+impl AudioLink {
+    /// Queue one PCM chunk (never blocks: a full queue drops it).  Safe
+    /// to call while holding a session map guard.
+    pub fn forward(&mut self, addr: SocketAddr, pcm: &[u8]) {
+        if let Some(ref mut probe) = self.speaker {
+            probe.push(pcm);
+        }
+        let chunks = match self.trimmer {
+            Some(ref mut t) => t.push(pcm),
+            None => vec![pcm.to_vec()],
+        };
+        for chunk in chunks {
+            let payload_len = chunk.len();
+            match self.tx.try_send(chunk) {
+                Ok(()) => {
+                    debug!(src = %addr, bytes = payload_len, "audio forwarded to OpenAI tx");
+                }
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    warn!(src = %addr, "OpenAI tx channel full — dropping audio chunk");
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    debug!(src = %addr, "OpenAI tx channel closed — session may have ended");
+                }
+            }
+        }
+    }
+
+    /// Forward the trimmed tail.  Returns `false` if the trimmer heard
+    /// no speech at all (nothing to commit).
+    async fn flush(self, addr: SocketAddr) -> bool {
+        let Some(mut trimmer) = self.trimmer else {
+            return true;
+        };
+        for chunk in trimmer.finish() {
+            let _ = self.tx.send(chunk).await;
+        }
+        let (head, tail) = trimmer.trimmed();
+        if !trimmer.heard_speech() {
+            info!(src = %addr, "⏭️ session held only silence — skipping OpenAI commit");
+            return false;
+        }
+        info!(
+            src = %addr,
+            head_ms = head.as_millis() as u64,
+            tail_ms = tail.as_millis() as u64,
+            "✂️ trimmed silence before OpenAI commit"
+        );
+        true
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_link_trims_and_flushes() {
+        let (tx, mut rx) = mpsc::channel(64);
+        let addr: SocketAddr = "10.0.0.1:9000".parse().unwrap();
+
+        // Untrimmed: every chunk goes straight through
+        let mut link = AudioLink { tx: tx.clone(), trimmer: None, speaker: None };
+        link.forward(addr, &[0u8; 320]);
+        assert_eq!(rx.try_recv().unwrap().len(), 320);
+        assert!(link.flush(addr).await);
+
+        // Trimmed silence only: nothing to commit
+        let mut link = AudioLink { tx, trimmer: Some(SilenceTrimmer::new(500.0, std::time::Duration::ZERO)), speaker: None };
+        for _ in 0..5 {
+            link.forward(addr, &[0u8; 320]);
+        }
+        assert!(!link.flush(addr).await);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_bridge() {
+        let bridge = RealtimeBridge::new(None, PromptContext::new("hi".into(), crate::persona::PersonaState::new(crate::persona::PersonaTrait::Obedient)), Diarizer::default());
+        let addr: SocketAddr = "10.0.0.1:9000".parse().unwrap();
+        assert!(bridge.attach(addr, "aa:bb").await.is_none());
+        assert_eq!(bridge.finish(addr, None, 1.0).await, Finish::Offline);
+        bridge.cancel(addr).await;
+    }
+}
@@ -0,0 +1,198 @@
+use crate::capture::CapturedSocket;
+use crate::clips::{ decode_wav, pcm_duration, ClipPlayer, PlayError };
+use crate::quiet_hours::QuietHours;
+use chrono::{ DateTime, Utc };
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::{ mpsc, oneshot };
+use tracing::info;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Broadcast announcements
+// ─────────────────────────────────────────────────────────────────────
+//
+//  `POST /broadcast/say` plays one announcement ("dinner is ready",
+//  "smoke alarm in the kitchen") on every online ESP at once:
+//
+//    {"text": "..."}          synthesized once through `--tts-backend`
+//    Content-Type: audio/*    a WAV file, decoded like a clip
+//
+//  A device is online when it was heard from (heartbeat, session control
+//  or audio) within `--broadcast-online-secs`.  Each device gets its own
+//  paced AUDIO_DOWN stream (`ClipPlayer::play_pcm`, so
+//  `--downlink-pacing` applies per link), replacing any clip or TTS it
+//  was playing.
+//
+//  Devices in their quiet hours are skipped, unless the broadcast is
+//  sent with `?emergency=true`.  `?group=` limits it to the ESPs of a
+//  device group.
+
+/// Largest WAV accepted by `POST /broadcast/say`.
+pub const MAX_WAV_BYTES: usize = 16 * 1024 * 1024;
+
+/// One device a broadcast went to (or skipped).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Recipient {
+    pub device: String,
+    pub addr: String,
+}
+
+/// What a broadcast reached.
+#[derive(Debug, Clone, Serialize)]
+pub struct BroadcastReport {
+    pub devices: Vec<Recipient>,
+    /// Online devices left out (quiet hours)
+    pub skipped: Vec<Recipient>,
+    pub duration_ms: u64,
+    pub emergency: bool,
+}
+
+/// A decoded announcement for the UDP side to fan out.
+pub struct BroadcastRequest {
+    pub pcm: Arc<Vec<u8>>,
+    pub emergency: bool,
+    /// Only these device names (None = every online device)
+    pub devices: Option<Vec<String>>,
+    pub reply: oneshot::Sender<BroadcastReport>,
+}
+
+/// API handle for broadcasts.  Clone-friendly.
+#[derive(Clone)]
+pub struct Broadcaster {
+    clips: ClipPlayer,
+    requests: mpsc::Sender<BroadcastRequest>,
+}
+
+impl Broadcaster {
+    pub fn new(clips: ClipPlayer) -> (Self, mpsc::Receiver<BroadcastRequest>) {
+        let (requests, rx) = mpsc::channel(4);
+        (Self { clips, requests }, rx)
+    }
+
+    /// Synthesize `text` and play it on every online device (of
+    /// `devices`, if given).
+    pub async fn say(
+        &self,
+        text: &str,
+        emergency: bool,
+        devices: Option<Vec<String>>
+    ) -> Result<BroadcastReport, PlayError> {
+        let pcm = self.clips.synthesize(text).await?;
+        self.send(pcm, emergency, devices).await
+    }
+
+    /// Decode a WAV file and play it on every online device (of
+    /// `devices`, if given).
+    pub async fn play_wav(
+        &self,
+        wav: &[u8],
+        emergency: bool,
+        devices: Option<Vec<String>>
+    ) -> Result<BroadcastReport, PlayError> {
+        let pcm = decode_wav(wav).map_err(|e| PlayError::BadAudio(e.to_string()))?;
+        self.send(pcm, emergency, devices).await
+    }
+
+    async fn send(
+        &self,
+        pcm: Vec<u8>,
+        emergency: bool,
+        devices: Option<Vec<String>>
+    ) -> Result<BroadcastReport, PlayError> {
+        let (reply, rx) = oneshot::channel();
+        let req = BroadcastRequest { pcm: Arc::new(pcm), emergency, devices, reply };
+        self.requests.send(req).await.map_err(|_| PlayError::Unavailable)?;
+        rx.await.map_err(|_| PlayError::Unavailable)
+    }
+}
+
+/// Split the online devices (name, address), limited to `only`, into
+/// who hears a broadcast at `now` and who is skipped.
+pub fn recipients(
+    online: Vec<(String, SocketAddr)>,
+    only: Option<&[String]>,
+    quiet: &QuietHours,
+    emergency: bool,
+    now: DateTime<Utc>
+) -> (Vec<(String, SocketAddr)>, Vec<Recipient>) {
+    let (heard, skipped): (Vec<_>, Vec<_>) = online
+        .into_iter()
+        .filter(|(device, _)| only.is_none_or(|only| only.iter().any(|d| d.eq_ignore_ascii_case(device))))
+        .partition(|(device, _)| emergency || quiet.active_at(device, now).is_none());
+    let skipped = skipped
+        .into_iter()
+        .map(|(device, addr)| Recipient { device, addr: addr.to_string() })
+        .collect();
+    (heard, skipped)
+}
+
+/// Start the announcement on every recipient and reply with the report.
+pub fn deliver(
+    req: BroadcastRequest,
+    online: Vec<(String, SocketAddr)>,
+    clips: &ClipPlayer,
+    quiet: &QuietHours,
+    socket: &Arc<CapturedSocket>
+) {
+    let (heard, skipped) = recipients(online, req.devices.as_deref(), quiet, req.emergency, Utc::now());
+    let mut devices = Vec::with_capacity(heard.len());
+    for (device, addr) in heard {
+        clips.play_pcm(socket.clone(), addr, req.pcm.clone());
+        devices.push(Recipient { device, addr: addr.to_string() });
+    }
+    let duration = pcm_duration(&req.pcm);
+    info!(
+        devices = devices.len(),
+        skipped = skipped.len(),
+        emergency = req.emergency,
+        secs = format!("{:.1}", duration.as_secs_f64()),
+        "📢 broadcast started"
+    );
+    let _ = req.reply.send(BroadcastReport {
+        devices,
+        skipped,
+        duration_ms: duration.as_millis() as u64,
+        emergency: req.emergency,
+    });
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quiet_hours::QuietSchedule;
+
+    #[test]
+    fn test_quiet_devices_skipped_unless_emergency() {
+        let quiet = QuietHours::new();
+        let night = QuietSchedule {
+            start: "21:00".into(),
+            end: "07:00".into(),
+            timezone: "UTC".into(),
+            volume: None,
+            mute_audio: false,
+        };
+        quiet.set("Bedroom", Some(night)).unwrap();
+        let now = "2026-01-01T23:00:00Z".parse().unwrap();
+        let online = vec![
+            ("kitchen".to_string(), "10.0.0.7:5000".parse().unwrap()),
+            ("bedroom".to_string(), "10.0.0.8:5000".parse().unwrap())
+        ];
+
+        let (heard, skipped) = recipients(online.clone(), None, &quiet, false, now);
+        assert_eq!(heard, online[..1]);
+        assert_eq!(skipped, [Recipient { device: "bedroom".into(), addr: "10.0.0.8:5000".into() }]);
+
+        let (heard, skipped) = recipients(online.clone(), None, &quiet, true, now);
+        assert_eq!((heard, skipped.len()), (online.clone(), 0));
+
+        // A group broadcast only reaches (or skips) its own devices
+        let group = ["Bedroom".to_string()];
+        let (heard, skipped) = recipients(online.clone(), Some(&group), &quiet, false, now);
+        assert_eq!((heard.len(), skipped.len()), (0, 1));
+    }
+}
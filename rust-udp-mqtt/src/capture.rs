@@ -0,0 +1,459 @@
+use crate::udp_batch;
+use serde::{ Deserialize, Serialize };
+use std::io::{ BufWriter, Write };
+use std::net::{ IpAddr, SocketAddr };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant, SystemTime, UNIX_EPOCH };
+use tokio::net::UdpSocket;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Raw packet capture (pcap)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  `PUT /debug/capture` starts writing every datagram the bridge
+//  receives or sends on its audio / sensor / test ports to a classic
+//  pcap file in `--capture-dir`, for selected devices only:
+//
+//    {"enabled": true, "devices": ["10.0.0.7", "10.0.0.8:5000"],
+//     "max_bytes": 10485760, "max_secs": 300}
+//
+//  An empty `devices` list captures everything.  The capture stops by
+//  itself once the file reaches `max_bytes` or `max_secs` have passed;
+//  `{"enabled": false}` stops it early and `GET /debug/capture` reports
+//  progress.  Only one capture runs at a time.
+//
+//  Records are LINKTYPE_RAW with synthesised IPv4/IPv6 + UDP headers
+//  (UDP checksum 0), so Wireshark / tcpdump -r decode ports and
+//  payloads directly.  Sockets bound to 0.0.0.0 show that as the local
+//  address.
+//
+//  All sockets the bridge serves ESPs through are `CapturedSocket`s;
+//  while no capture is running the only cost is one relaxed atomic
+//  load per datagram.
+
+/// pcap LINKTYPE_RAW: each record starts with an IPv4 or IPv6 header.
+const LINKTYPE_RAW: u32 = 101;
+
+/// Default / largest allowed capture file size.
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const LIMIT_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Default / longest allowed capture duration.
+const DEFAULT_MAX_SECS: u64 = 300;
+const LIMIT_MAX_SECS: u64 = 24 * 3600;
+
+/// `PUT /debug/capture` body.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CaptureRequest {
+    pub enabled: bool,
+    /// `ip` or `ip:port` of each device to capture (empty = all)
+    #[serde(default)]
+    pub devices: Vec<String>,
+    pub max_bytes: Option<u64>,
+    pub max_secs: Option<u64>,
+}
+
+/// What `GET/PUT /debug/capture` report.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CaptureStatus {
+    pub active: bool,
+    /// Current (or last) capture file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub devices: Vec<String>,
+    pub packets: u64,
+    pub bytes: u64,
+    pub max_bytes: u64,
+    pub max_secs: u64,
+    /// Why the last capture stopped (`size limit`, `time limit`, `stopped`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stopped: Option<String>,
+}
+
+/// Device selector: a whole host or one `ip:port`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    Host(IpAddr),
+    Addr(SocketAddr),
+}
+
+impl Filter {
+    fn parse(s: &str) -> Option<Self> {
+        s.parse()
+            .map(Filter::Addr)
+            .or_else(|_| s.parse().map(Filter::Host))
+            .ok()
+    }
+
+    fn matches(&self, addr: SocketAddr) -> bool {
+        match *self {
+            Filter::Host(ip) => addr.ip() == ip,
+            Filter::Addr(a) => addr == a,
+        }
+    }
+}
+
+struct Active {
+    writer: BufWriter<std::fs::File>,
+    filters: Vec<Filter>,
+    deadline: Instant,
+}
+
+struct Inner {
+    dir: String,
+    /// Fast-path flag mirroring `active.is_some()`
+    capturing: AtomicBool,
+    active: Mutex<Option<Active>>,
+    status: Mutex<CaptureStatus>,
+}
+
+/// Runtime-toggleable packet capture.  Clone-friendly (Arc inside).
+#[derive(Clone)]
+pub struct PacketCapture {
+    inner: Arc<Inner>,
+}
+
+/// Datagram direction relative to the bridge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+impl PacketCapture {
+    /// Captures are written to `dir`.
+    pub fn new(dir: &str) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                dir: dir.to_string(),
+                capturing: AtomicBool::new(false),
+                active: Mutex::new(None),
+                status: Mutex::default(),
+            }),
+        }
+    }
+
+    /// Start or stop a capture as requested; returns the new status.
+    pub fn apply(&self, req: &CaptureRequest) -> Result<CaptureStatus, String> {
+        if !req.enabled {
+            self.stop("stopped");
+            return Ok(self.status());
+        }
+
+        let filters = req.devices
+            .iter()
+            .map(|d| Filter::parse(d).ok_or_else(|| format!("invalid device {d:?} (use ip or ip:port)")))
+            .collect::<Result<Vec<_>, _>>()?;
+        let max_bytes = req.max_bytes.unwrap_or(DEFAULT_MAX_BYTES);
+        let max_secs = req.max_secs.unwrap_or(DEFAULT_MAX_SECS);
+        if max_bytes == 0 || max_bytes > LIMIT_MAX_BYTES {
+            return Err(format!("max_bytes must be 1..={LIMIT_MAX_BYTES}"));
+        }
+        if max_secs == 0 || max_secs > LIMIT_MAX_SECS {
+            return Err(format!("max_secs must be 1..={LIMIT_MAX_SECS}"));
+        }
+
+        self.stop("restarted");
+        std::fs::create_dir_all(&self.inner.dir).map_err(|e| format!("capture dir {}: {e}", self.inner.dir))?;
+        let ts = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let path = format!("{}/capture_{ts}.pcap", self.inner.dir);
+        let file = std::fs::File::create(&path).map_err(|e| format!("{path}: {e}"))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&pcap_header()).map_err(|e| format!("{path}: {e}"))?;
+
+        *self.lock_status() = CaptureStatus {
+            active: true,
+            path: Some(path.clone()),
+            devices: req.devices.clone(),
+            packets: 0,
+            bytes: 24,
+            max_bytes,
+            max_secs,
+            stopped: None,
+        };
+        *self.lock_active() = Some(Active {
+            writer,
+            filters,
+            deadline: Instant::now() + Duration::from_secs(max_secs),
+        });
+        self.inner.capturing.store(true, Ordering::Release);
+        info!(path = %path, devices = ?req.devices, max_bytes, max_secs, "📼 packet capture started");
+        Ok(self.status())
+    }
+
+    /// Current status (expires a capture whose time is up).
+    pub fn status(&self) -> CaptureStatus {
+        let expired = self
+            .lock_active()
+            .as_ref()
+            .is_some_and(|a| Instant::now() >= a.deadline);
+        if expired {
+            self.stop("time limit");
+        }
+        self.lock_status().clone()
+    }
+
+    /// Record one datagram between our `local` socket and `remote`.
+    #[inline]
+    pub fn record(&self, dir: Direction, local: SocketAddr, remote: SocketAddr, data: &[u8]) {
+        if !self.inner.capturing.load(Ordering::Relaxed) {
+            return;
+        }
+        self.record_slow(dir, local, remote, data);
+    }
+
+    fn record_slow(&self, dir: Direction, local: SocketAddr, remote: SocketAddr, data: &[u8]) {
+        let mut active = self.lock_active();
+        let Some(ref mut capture) = *active else {
+            return;
+        };
+        if !capture.filters.is_empty() && !capture.filters.iter().any(|f| f.matches(remote)) {
+            return;
+        }
+        if Instant::now() >= capture.deadline {
+            drop(active);
+            self.stop("time limit");
+            return;
+        }
+
+        let (src, dst) = match dir {
+            Direction::In => (remote, local),
+            Direction::Out => (local, remote),
+        };
+        let record = pcap_record(SystemTime::now(), src, dst, data);
+        let mut status = self.lock_status();
+        if status.bytes + (record.len() as u64) > status.max_bytes {
+            drop(status);
+            drop(active);
+            self.stop("size limit");
+            return;
+        }
+        if let Err(e) = capture.writer.write_all(&record) {
+            warn!(error = %e, "packet capture write failed");
+            drop(status);
+            drop(active);
+            self.stop("write error");
+            return;
+        }
+        status.packets += 1;
+        status.bytes += record.len() as u64;
+    }
+
+    /// Finish the running capture, if any.
+    fn stop(&self, reason: &str) {
+        let Some(mut capture) = self.lock_active().take() else {
+            return;
+        };
+        self.inner.capturing.store(false, Ordering::Release);
+        let _ = capture.writer.flush();
+        let mut status = self.lock_status();
+        status.active = false;
+        status.stopped = Some(reason.to_string());
+        info!(path = ?status.path, packets = status.packets, bytes = status.bytes, reason, "📼 packet capture stopped");
+    }
+
+    fn lock_active(&self) -> std::sync::MutexGuard<'_, Option<Active>> {
+        self.inner.active.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_status(&self) -> std::sync::MutexGuard<'_, CaptureStatus> {
+        self.inner.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Capturing socket
+// ─────────────────────────────────────────────────────────────────────
+
+/// A UDP socket whose datagrams are offered to a [`PacketCapture`].
+pub struct CapturedSocket {
+    socket: UdpSocket,
+    local: SocketAddr,
+    capture: PacketCapture,
+}
+
+impl CapturedSocket {
+    pub fn new(socket: UdpSocket, capture: PacketCapture) -> std::io::Result<Self> {
+        let local = socket.local_addr()?;
+        Ok(Self { socket, local, capture })
+    }
+
+    /// Raw fd of the socket, for receive paths outside tokio
+    /// (`uring.rs`); those report their datagrams via [`Self::record_in`].
+    #[cfg(unix)]
+    pub fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        std::os::fd::AsRawFd::as_raw_fd(&self.socket)
+    }
+
+    /// Capture a datagram received from `src` without `recv_from`.
+    pub fn record_in(&self, src: SocketAddr, data: &[u8]) {
+        self.capture.record(Direction::In, self.local, src, data);
+    }
+
+    pub async fn recv_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+        let (len, src) = self.socket.recv_from(buf).await?;
+        self.capture.record(Direction::In, self.local, src, &buf[..len]);
+        Ok((len, src))
+    }
+
+    pub async fn send_to(&self, buf: &[u8], target: SocketAddr) -> std::io::Result<usize> {
+        let sent = self.socket.send_to(buf, target).await?;
+        self.capture.record(Direction::Out, self.local, target, &buf[..sent]);
+        Ok(sent)
+    }
+
+    /// Send a batch of datagrams with as few syscalls as possible (see
+    /// `udp_batch.rs`).  Returns the ones that failed, by index.
+    pub async fn send_batch(&self, packets: &[(&[u8], SocketAddr)]) -> Vec<(usize, std::io::Error)> {
+        let failed = udp_batch::send_batch(&self.socket, packets).await;
+        for (i, (payload, target)) in packets.iter().enumerate() {
+            if !failed.iter().any(|(f, _)| *f == i) {
+                self.capture.record(Direction::Out, self.local, *target, payload);
+            }
+        }
+        failed
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  pcap encoding
+// ─────────────────────────────────────────────────────────────────────
+
+/// Classic pcap global header (µs timestamps, little-endian).
+fn pcap_header() -> [u8; 24] {
+    let mut h = [0u8; 24];
+    h[0..4].copy_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+    h[4..6].copy_from_slice(&2u16.to_le_bytes());
+    h[6..8].copy_from_slice(&4u16.to_le_bytes());
+    h[16..20].copy_from_slice(&65_535u32.to_le_bytes());
+    h[20..24].copy_from_slice(&LINKTYPE_RAW.to_le_bytes());
+    h
+}
+
+/// One pcap record: record header + IP header + UDP header + payload.
+fn pcap_record(at: SystemTime, src: SocketAddr, dst: SocketAddr, payload: &[u8]) -> Vec<u8> {
+    let packet = ip_udp_packet(src, dst, payload);
+    let ts = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let mut rec = Vec::with_capacity(16 + packet.len());
+    rec.extend_from_slice(&(ts.as_secs() as u32).to_le_bytes());
+    rec.extend_from_slice(&ts.subsec_micros().to_le_bytes());
+    rec.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    rec.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+    rec.extend_from_slice(&packet);
+    rec
+}
+
+fn ip_udp_packet(src: SocketAddr, dst: SocketAddr, payload: &[u8]) -> Vec<u8> {
+    let udp_len = (8 + payload.len()) as u16;
+    let mut udp = Vec::with_capacity(udp_len as usize);
+    udp.extend_from_slice(&src.port().to_be_bytes());
+    udp.extend_from_slice(&dst.port().to_be_bytes());
+    udp.extend_from_slice(&udp_len.to_be_bytes());
+    udp.extend_from_slice(&[0, 0]);
+    udp.extend_from_slice(payload);
+
+    match (src.ip(), dst.ip()) {
+        (IpAddr::V4(s), IpAddr::V4(d)) => {
+            let mut ip = vec![0x45, 0];
+            ip.extend_from_slice(&(20 + udp_len).to_be_bytes());
+            ip.extend_from_slice(&[0, 0, 0x40, 0, 64, 17, 0, 0]);
+            ip.extend_from_slice(&s.octets());
+            ip.extend_from_slice(&d.octets());
+            let csum = ipv4_checksum(&ip);
+            ip[10..12].copy_from_slice(&csum.to_be_bytes());
+            ip.extend_from_slice(&udp);
+            ip
+        }
+        (s, d) => {
+            let v6 = |ip: IpAddr| match ip {
+                IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+                IpAddr::V6(v6) => v6,
+            };
+            let mut ip = vec![0x60, 0, 0, 0];
+            ip.extend_from_slice(&udp_len.to_be_bytes());
+            ip.extend_from_slice(&[17, 64]);
+            ip.extend_from_slice(&v6(s).octets());
+            ip.extend_from_slice(&v6(d).octets());
+            ip.extend_from_slice(&udp);
+            ip
+        }
+    }
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks_exact(2)
+        .map(|w| u16::from_be_bytes([w[0], w[1]]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ipv4_record_layout() {
+        let rec = pcap_record(UNIX_EPOCH + Duration::from_micros(1_500_000), addr("10.0.0.7:5000"), addr("10.0.0.1:9001"), b"hi");
+        assert_eq!(&rec[0..4], &1u32.to_le_bytes());
+        assert_eq!(&rec[4..8], &500_000u32.to_le_bytes());
+        assert_eq!(&rec[8..12], &30u32.to_le_bytes()); // 20 IP + 8 UDP + 2
+        let ip = &rec[16..];
+        assert_eq!(ip[0], 0x45);
+        assert_eq!(ipv4_checksum(&ip[..20]), 0, "header checksum must verify");
+        assert_eq!(u16::from_be_bytes([ip[20], ip[21]]), 5000);
+        assert_eq!(u16::from_be_bytes([ip[22], ip[23]]), 9001);
+        assert_eq!(&ip[28..], b"hi");
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(Filter::parse("10.0.0.7"), Some(Filter::Host("10.0.0.7".parse().unwrap())));
+        assert!(Filter::parse("10.0.0.7:5000").unwrap().matches(addr("10.0.0.7:5000")));
+        assert!(!Filter::parse("10.0.0.7:5000").unwrap().matches(addr("10.0.0.7:5001")));
+        assert!(Filter::parse("aa:bb").is_none());
+    }
+
+    #[test]
+    fn test_capture_filters_and_size_limit() {
+        let dir = std::env::temp_dir().join(format!("vad-capture-{}", std::process::id()));
+        let capture = PacketCapture::new(dir.to_str().unwrap());
+        let local = addr("0.0.0.0:9001");
+        let req = CaptureRequest {
+            enabled: true,
+            devices: vec!["10.0.0.7".into()],
+            max_bytes: Some(24 + 2 * (16 + 28 + 100)),
+            max_secs: None,
+        };
+        assert!(capture.apply(&req).unwrap().active);
+
+        capture.record(Direction::In, local, addr("10.0.0.9:1"), &[0; 100]); // filtered out
+        capture.record(Direction::In, local, addr("10.0.0.7:1"), &[0; 100]);
+        capture.record(Direction::Out, local, addr("10.0.0.7:1"), &[0; 100]);
+        assert_eq!(capture.status().packets, 2);
+        capture.record(Direction::Out, local, addr("10.0.0.7:1"), &[0; 100]); // over the limit
+
+        let status = capture.status();
+        assert!(!status.active);
+        assert_eq!(status.stopped.as_deref(), Some("size limit"));
+        let written = std::fs::metadata(status.path.unwrap()).unwrap().len();
+        assert_eq!(written, status.bytes);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let bad = CaptureRequest { devices: vec!["nope".into()], ..req };
+        assert!(capture.apply(&bad).is_err());
+    }
+}
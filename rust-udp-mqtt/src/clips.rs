@@ -0,0 +1,519 @@
+use crate::capture::CapturedSocket;
+use crate::downlink_pacing::DownlinkPacer;
+use crate::esp_audio_protocol::{ build_audio_down, build_control, CTRL_STREAM_END, ESP_MAX_PAYLOAD };
+use crate::pcm::SampleFormat;
+use crate::quiet_hours::QuietHours;
+use crate::tts::TtsEngine;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+use tokio::sync::{ mpsc, oneshot };
+use tokio::task::JoinHandle;
+use tracing::{ debug, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Canned audio clips
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Pre-rendered WAV files in `--clips-dir` (e.g. `greeting.wav`,
+//  `wifi_sad.wav`, `lullaby.wav`) are decoded once at startup to the
+//  ESP's 16 kHz s16 mono and can be played to any device:
+//
+//    * on demand — `POST /devices/{device}/play/{clip}`
+//    * automatically — `--offline-clip` is played when an ESP session
+//      ends with speech but the OpenAI session is down
+//
+//  Playback is paced at real time (one ESP_MAX_PAYLOAD chunk per
+//  43.75 ms, or the device's adaptive pace with `--downlink-pacing`) as
+//  AUDIO_DOWN packets followed by STREAM_END, exactly like an OpenAI
+//  response, so the ESP's jitter buffer never overflows.
+//  Text sent to `POST /devices/{device}/say` is spoken through the
+//  `--tts-backend` (see `tts.rs`) the same way.  At most one clip or
+//  utterance plays per device; a new one or CTRL_CANCEL stops the
+//  previous one mid-stream.
+//
+//  During a device's quiet hours (`quiet_hours.rs`) play / say requests
+//  are refused, and with `mute_audio` the offline clip is not played.
+//
+//  WAV files may be 8/16/24/32-bit integer or 32-bit float, any sample
+//  rate, mono or multi-channel (averaged).  Opus is not supported.
+
+/// ESP playback rate.
+const CLIP_SAMPLE_RATE: u32 = 16_000;
+
+/// Why a play request was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayError {
+    UnknownClip(String),
+    UnknownDevice(String),
+    /// The UDP side is not running (no receiver for requests)
+    Unavailable,
+    /// `say` without `--tts-backend`
+    NoTts,
+    /// The device is in its quiet hours
+    QuietHours(String),
+    /// The TTS backend failed to synthesize
+    Tts(String),
+    /// Uploaded audio could not be decoded
+    BadAudio(String),
+}
+
+impl std::fmt::Display for PlayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayError::UnknownClip(c) => write!(f, "unknown clip: {c}"),
+            PlayError::UnknownDevice(d) => write!(f, "unknown device: {d}"),
+            PlayError::Unavailable => write!(f, "audio transport not running"),
+            PlayError::NoTts => write!(f, "no --tts-backend configured"),
+            PlayError::QuietHours(d) => write!(f, "{d} is in its quiet hours"),
+            PlayError::Tts(e) => write!(f, "TTS failed: {e}"),
+            PlayError::BadAudio(e) => write!(f, "bad audio: {e}"),
+        }
+    }
+}
+
+/// What to play.
+pub enum Playback {
+    Clip(String),
+    /// Text for the TTS backend
+    Speech(String),
+}
+
+/// A request from the API to play something on `device` (MAC or
+/// `ip:port`).  Replies with the device address and, for clips, the
+/// duration.
+pub struct PlayRequest {
+    pub device: String,
+    pub what: Playback,
+    pub reply: oneshot::Sender<Result<(SocketAddr, Option<Duration>), PlayError>>,
+}
+
+/// Loaded clip library plus the request channel into the UDP side.
+/// Clone-friendly (Arc inside).
+#[derive(Clone)]
+pub struct ClipPlayer {
+    clips: Arc<HashMap<String, Arc<Vec<u8>>>>,
+    offline_clip: Option<String>,
+    requests: mpsc::Sender<PlayRequest>,
+    /// Paced playback task per device
+    playing: Arc<Mutex<HashMap<SocketAddr, JoinHandle<()>>>>,
+    tts: Option<Arc<TtsEngine>>,
+    downlink: DownlinkPacer,
+    quiet: QuietHours,
+}
+
+impl ClipPlayer {
+    /// Load every `*.wav` in `dir` ("" = no clips).  `offline_clip` is
+    /// the clip played when the cloud is unreachable (None = never);
+    /// `tts` speaks `say` requests (None = refused); `downlink` paces
+    /// the AUDIO_DOWN packets; `quiet` holds requests back at night.
+    pub fn load(
+        dir: &str,
+        offline_clip: Option<String>,
+        tts: Option<TtsEngine>,
+        downlink: DownlinkPacer,
+        quiet: QuietHours
+    ) -> anyhow::Result<(Self, mpsc::Receiver<PlayRequest>)> {
+        let mut clips = HashMap::new();
+        if !dir.is_empty() {
+            let entries = std::fs
+                ::read_dir(dir)
+                .map_err(|e| anyhow::anyhow!("failed to read clips dir {dir}: {e}"))?;
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("wav") {
+                    continue;
+                }
+                let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                    continue;
+                };
+                match std::fs::read(&path).map_err(Into::into).and_then(|b| decode_wav(&b)) {
+                    Ok(pcm) => {
+                        debug!(clip = name, secs = format!("{:.1}", pcm_duration(&pcm).as_secs_f64()), "clip loaded");
+                        clips.insert(name.to_string(), Arc::new(pcm));
+                    }
+                    Err(e) => warn!(path = %path.display(), error = %e, "skipping unreadable clip"),
+                }
+            }
+        }
+        if let Some(ref name) = offline_clip {
+            if !dir.is_empty() && !clips.contains_key(name) {
+                warn!(clip = %name, "offline clip not found in clips dir");
+            }
+        }
+        info!(clips = clips.len(), dir = %dir, "🔈 clip library loaded");
+
+        let (requests, rx) = mpsc::channel(32);
+        Ok((
+            Self {
+                clips: Arc::new(clips),
+                offline_clip,
+                requests,
+                playing: Arc::default(),
+                tts: tts.map(Arc::new),
+                downlink,
+                quiet,
+            },
+            rx,
+        ))
+    }
+
+    /// Clip names and durations, sorted by name.
+    pub fn list(&self) -> Vec<(String, Duration)> {
+        let mut list: Vec<_> = self.clips
+            .iter()
+            .map(|(name, pcm)| (name.clone(), pcm_duration(pcm)))
+            .collect();
+        list.sort();
+        list
+    }
+
+    /// Ask the UDP side to play `clip` on `device` (API entry point).
+    pub async fn request(&self, device: &str, clip: &str) -> Result<(SocketAddr, Duration), PlayError> {
+        if !self.clips.contains_key(clip) {
+            return Err(PlayError::UnknownClip(clip.to_string()));
+        }
+        let (addr, duration) = self.send_request(device, Playback::Clip(clip.to_string())).await?;
+        Ok((addr, duration.unwrap_or_default()))
+    }
+
+    /// Ask the UDP side to speak `text` on `device` (API entry point).
+    pub async fn request_speech(&self, device: &str, text: &str) -> Result<SocketAddr, PlayError> {
+        if self.tts.is_none() {
+            return Err(PlayError::NoTts);
+        }
+        let (addr, _) = self.send_request(device, Playback::Speech(text.to_string())).await?;
+        Ok(addr)
+    }
+
+    async fn send_request(
+        &self,
+        device: &str,
+        what: Playback
+    ) -> Result<(SocketAddr, Option<Duration>), PlayError> {
+        if self.quiet.active(device).is_some() {
+            return Err(PlayError::QuietHours(device.to_string()));
+        }
+        let (reply, rx) = oneshot::channel();
+        let req = PlayRequest { device: device.to_string(), what, reply };
+        self.requests.send(req).await.map_err(|_| PlayError::Unavailable)?;
+        rx.await.map_err(|_| PlayError::Unavailable)?
+    }
+
+    /// Start paced playback of `clip` to `addr`; returns its duration.
+    pub fn play(&self, socket: Arc<CapturedSocket>, addr: SocketAddr, clip: &str) -> Option<Duration> {
+        let pcm = self.clips.get(clip)?.clone();
+        let duration = pcm_duration(&pcm);
+        info!(clip = %clip, esp = %addr, secs = format!("{:.1}", duration.as_secs_f64()), "🔈 playing clip");
+        self.play_pcm(socket, addr, pcm);
+        Some(duration)
+    }
+
+    /// Start paced playback of 16 kHz s16 mono `pcm` to `addr`,
+    /// replacing whatever it is playing.
+    pub fn play_pcm(&self, socket: Arc<CapturedSocket>, addr: SocketAddr, pcm: Arc<Vec<u8>>) {
+        let downlink = self.downlink.clone();
+        self.track(
+            addr,
+            tokio::spawn(async move {
+                send_paced(&socket, addr, &pcm, &downlink).await;
+            })
+        );
+    }
+
+    /// Synthesize `text` in full through the TTS backend.
+    pub async fn synthesize(&self, text: &str) -> Result<Vec<u8>, PlayError> {
+        let tts = self.tts.as_ref().ok_or(PlayError::NoTts)?;
+        tts.synthesize(text).await.map_err(|e| PlayError::Tts(e.to_string()))
+    }
+
+    /// Start speaking `text` to `addr` through the TTS backend.
+    pub fn say(&self, socket: Arc<CapturedSocket>, addr: SocketAddr, text: &str) -> Result<(), PlayError> {
+        let tts = self.tts.clone().ok_or(PlayError::NoTts)?;
+        let text = text.to_string();
+        info!(esp = %addr, backend = tts.name(), chars = text.chars().count(), "🗣️ speaking text");
+        let downlink = self.downlink.clone();
+        self.track(
+            addr,
+            tokio::spawn(async move {
+                if let Err(e) = tts.speak(&socket, addr, &text, &downlink).await {
+                    warn!(esp = %addr, error = %e, "TTS playback failed");
+                }
+            })
+        );
+        Ok(())
+    }
+
+    /// Remember `task` as `addr`'s playback, stopping the previous one.
+    fn track(&self, addr: SocketAddr, task: JoinHandle<()>) {
+        let mut playing = self.playing.lock().unwrap_or_else(|e| e.into_inner());
+        playing.retain(|_, t| !t.is_finished());
+        if let Some(previous) = playing.insert(addr, task) {
+            previous.abort();
+        }
+    }
+
+    /// Stop any clip still being paced out to `addr` (flushes the
+    /// remaining AUDIO_DOWN packets).  Returns `true` if one was playing.
+    pub fn stop(&self, addr: SocketAddr) -> bool {
+        let task = self.playing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&addr);
+        match task {
+            Some(t) if !t.is_finished() => {
+                t.abort();
+                info!(esp = %addr, "🔇 clip playback stopped");
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Play `--offline-clip` to `addr` (named `device`), if configured,
+    /// loaded and not muted by quiet hours.
+    pub fn play_offline(&self, socket: Arc<CapturedSocket>, addr: SocketAddr, device: &str) {
+        if self.quiet.mutes_audio(device) {
+            debug!(device = %device, "offline clip muted by quiet hours");
+            return;
+        }
+        if let Some(ref clip) = self.offline_clip {
+            self.play(socket, addr, clip);
+        }
+    }
+}
+
+/// Duration of 16 kHz s16 mono PCM.
+pub(crate) fn pcm_duration(pcm: &[u8]) -> Duration {
+    Duration::from_secs_f64((pcm.len() as f64) / ((CLIP_SAMPLE_RATE as f64) * 2.0))
+}
+
+/// Send `pcm` as AUDIO_DOWN packets paced by `downlink` (real time for
+/// `DownlinkPacer::default()`), then STREAM_END.
+pub async fn send_paced(socket: &CapturedSocket, addr: SocketAddr, pcm: &[u8], downlink: &DownlinkPacer) {
+    let mut seq: u16 = 0;
+    if let Err(e) = send_paced_chunks(socket, addr, pcm, downlink, &mut seq).await {
+        warn!(error = %e, esp = %addr, "failed to send clip AUDIO_DOWN");
+        return;
+    }
+    let _ = socket.send_to(&build_control(seq, CTRL_STREAM_END, 0), addr).await;
+}
+
+/// Send `pcm` as paced AUDIO_DOWN packets numbered from `seq` (left at
+/// the next number), without STREAM_END — for audio that keeps coming.
+pub async fn send_paced_chunks(
+    socket: &CapturedSocket,
+    addr: SocketAddr,
+    pcm: &[u8],
+    downlink: &DownlinkPacer,
+    seq: &mut u16
+) -> std::io::Result<()> {
+    for chunk in pcm.chunks(ESP_MAX_PAYLOAD) {
+        downlink.pace(addr, pcm_duration(chunk)).await;
+        socket.send_to(&build_audio_down(*seq, 0, chunk), addr).await?;
+        *seq = seq.wrapping_add(1);
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  WAV decoding
+// ─────────────────────────────────────────────────────────────────────
+
+/// `fmt` format tag whose real format is in the SubFormat GUID.
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+/// Bytes 2.. of every `KSDATAFORMAT_SUBTYPE_*` GUID
+/// (`xxxxxxxx-0000-0010-8000-00aa00389b71`).
+const KSDATAFORMAT_SUFFIX: [u8; 14] = [0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71];
+
+/// Decode a RIFF/WAVE file to 16 kHz s16 LE mono.
+pub(crate) fn decode_wav(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        anyhow::bail!("not a RIFF/WAVE file");
+    }
+
+    let mut fmt: Option<(u16, u16, u32, u16)> = None;
+    let mut data: Option<&[u8]> = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let len = u32::from_le_bytes([bytes[pos + 4], bytes[pos + 5], bytes[pos + 6], bytes[pos + 7]]) as usize;
+        let body = &bytes[pos + 8..(pos + 8 + len).min(bytes.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let u16_at = |i: usize| u16::from_le_bytes([body[i], body[i + 1]]);
+                let rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                let mut format_tag = u16_at(0);
+                if format_tag == WAVE_FORMAT_EXTENSIBLE {
+                    // The real format is the SubFormat GUID at offset 24:
+                    // its first two bytes are the tag, the rest is fixed
+                    format_tag = match body.get(24..40) {
+                        Some(guid) if guid[2..] == KSDATAFORMAT_SUFFIX => u16::from_le_bytes([guid[0], guid[1]]),
+                        _ => anyhow::bail!("WAVE_FORMAT_EXTENSIBLE without a known SubFormat"),
+                    };
+                }
+                fmt = Some((format_tag, u16_at(2), rate, u16_at(14)));
+            }
+            b"data" => {
+                data = Some(body);
+            }
+            _ => {}
+        }
+        // Chunks are word-aligned
+        pos += 8 + len + (len & 1);
+    }
+
+    let (format_tag, channels, rate, bits) = fmt.ok_or_else(|| anyhow::anyhow!("missing fmt chunk"))?;
+    let data = data.ok_or_else(|| anyhow::anyhow!("missing data chunk"))?;
+    if channels == 0 || rate == 0 {
+        anyhow::bail!("invalid fmt chunk");
+    }
+
+    // 1 = PCM, 3 = IEEE float (also as the SubFormat of an extensible fmt)
+    let sample_format = match (format_tag, bits) {
+        (1, 8) => None, // unsigned 8-bit, handled below
+        (1, 16) => Some(SampleFormat::S16),
+        (1, 24) => Some(SampleFormat::S24),
+        (1, 32) => Some(SampleFormat::S32),
+        (3, 32) => Some(SampleFormat::F32),
+        _ => anyhow::bail!("unsupported WAV format {format_tag} / {bits}-bit"),
+    };
+    let s16 = match sample_format {
+        Some(f) => f.to_s16le(data).into_owned(),
+        None => {
+            let signed: Vec<u8> = data
+                .iter()
+                .map(|b| b ^ 0x80)
+                .collect();
+            SampleFormat::S8.to_s16le(&signed).into_owned()
+        }
+    };
+
+    // Downmix interleaved channels by averaging
+    let mono: Vec<u8> = if channels == 1 {
+        s16
+    } else {
+        s16.chunks_exact(2 * (channels as usize))
+            .flat_map(|frame| {
+                let sum: i32 = frame
+                    .chunks_exact(2)
+                    .map(|s| i16::from_le_bytes([s[0], s[1]]) as i32)
+                    .sum();
+                ((sum / (channels as i32)) as i16).to_le_bytes()
+            })
+            .collect()
+    };
+
+    Ok(if rate == CLIP_SAMPLE_RATE {
+        mono
+    } else {
+        crate::transport_openai::resample(&mono, rate as u64, CLIP_SAMPLE_RATE as u64)
+    })
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(format_tag: u16, channels: u16, rate: u32, bits: u16, data: &[u8]) -> Vec<u8> {
+        let mut w = Vec::new();
+        w.extend_from_slice(b"RIFF");
+        w.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        w.extend_from_slice(b"WAVEfmt ");
+        w.extend_from_slice(&16u32.to_le_bytes());
+        w.extend_from_slice(&format_tag.to_le_bytes());
+        w.extend_from_slice(&channels.to_le_bytes());
+        w.extend_from_slice(&rate.to_le_bytes());
+        w.extend_from_slice(&(rate * (channels as u32) * (bits as u32) / 8).to_le_bytes());
+        w.extend_from_slice(&(channels * bits / 8).to_le_bytes());
+        w.extend_from_slice(&bits.to_le_bytes());
+        w.extend_from_slice(b"data");
+        w.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        w.extend_from_slice(data);
+        w
+    }
+
+    /// `wav` with a 40-byte WAVE_FORMAT_EXTENSIBLE fmt chunk of `sub_format`.
+    fn wav_extensible(sub_format: u16, bits: u16, data: &[u8]) -> Vec<u8> {
+        let mut w = wav(WAVE_FORMAT_EXTENSIBLE, 1, 16_000, bits, data);
+        let mut ext = Vec::new();
+        ext.extend_from_slice(&22u16.to_le_bytes());
+        ext.extend_from_slice(&bits.to_le_bytes());
+        ext.extend_from_slice(&4u32.to_le_bytes());
+        ext.extend_from_slice(&sub_format.to_le_bytes());
+        ext.extend_from_slice(&KSDATAFORMAT_SUFFIX);
+        w[16..20].copy_from_slice(&40u32.to_le_bytes());
+        w.splice(36..36, ext);
+        w
+    }
+
+    #[test]
+    fn test_decode_extensible_by_sub_format() {
+        let float: Vec<u8> = [0.5f32, -0.25].iter().flat_map(|s| s.to_le_bytes()).collect();
+        let pcm: Vec<u8> = [0x4000_0000i32, -0x2000_0000].iter().flat_map(|s| s.to_le_bytes()).collect();
+        // 32-bit float and 32-bit integer PCM share the bit depth
+        for (sub_format, data) in [(3, &float), (1, &pcm)] {
+            let got: Vec<i16> = decode_wav(&wav_extensible(sub_format, 32, data))
+                .unwrap()
+                .chunks_exact(2)
+                .map(|s| i16::from_le_bytes([s[0], s[1]]))
+                .collect();
+            assert!(got.iter().zip([16384i16, -8192]).all(|(g, w)| (g - w).abs() <= 1), "{sub_format}: {got:?}");
+        }
+        assert!(decode_wav(&wav_extensible(0x55, 32, &float)).is_err());
+        // Extensible fmt chunk cut short of the GUID
+        assert!(decode_wav(&wav(WAVE_FORMAT_EXTENSIBLE, 1, 16_000, 16, &[0; 4])).is_err());
+    }
+
+    #[test]
+    fn test_decode_16k_mono_is_passthrough() {
+        let data: Vec<u8> = [100i16, -200, 300].iter().flat_map(|s| s.to_le_bytes()).collect();
+        assert_eq!(decode_wav(&wav(1, 1, 16_000, 16, &data)).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decode_stereo_u8_and_resample() {
+        // Stereo u8 @ 16 kHz: (0xC0, 0x40) averages to 0
+        let out = decode_wav(&wav(1, 2, 16_000, 8, &[0xc0, 0x40, 0xc0, 0xc0])).unwrap();
+        assert_eq!(out, [0i16, 0x4000].iter().flat_map(|s| s.to_le_bytes()).collect::<Vec<u8>>());
+
+        // 24 kHz mono float → ⅔ as many samples at 16 kHz
+        let data: Vec<u8> = (0..1050).flat_map(|_| 0.25f32.to_le_bytes()).collect();
+        assert_eq!(decode_wav(&wav(3, 1, 24_000, 32, &data)).unwrap().len() / 2, 700);
+    }
+
+    #[tokio::test]
+    async fn test_stop_flushes_playback() {
+        let dir = std::env::temp_dir().join(format!("vad-clips-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // 2 s of silence — far longer than the test
+        std::fs::write(dir.join("long.wav"), wav(1, 1, 16_000, 16, &vec![0; 64_000])).unwrap();
+        let (player, _rx) = ClipPlayer::load(dir.to_str().unwrap(), None, None, DownlinkPacer::default(), QuietHours::new()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let esp = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = esp.local_addr().unwrap();
+        let server = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let capture = crate::capture::PacketCapture::new("");
+        let socket = Arc::new(CapturedSocket::new(server, capture).unwrap());
+
+        assert!(!player.stop(addr));
+        assert!(player.play(socket.clone(), addr, "long").is_some());
+        let mut buf = [0u8; 2048];
+        esp.recv(&mut buf).await.unwrap();
+        assert!(player.stop(addr));
+        assert!(!player.stop(addr));
+        assert_eq!(player.say(socket, addr, "hi"), Err(PlayError::NoTts));
+    }
+
+    #[test]
+    fn test_decode_rejects_garbage() {
+        assert!(decode_wav(b"not a wav").is_err());
+        assert!(decode_wav(&wav(2, 1, 16_000, 4, &[0; 8])).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant, SystemTime };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Pipeline clock
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Everything time-dependent in the pipeline — ESP session timing, the
+//  sensor smoother's EMA decay and reset gap, event debouncing, audio
+//  windows, sensor client eviction, stats intervals and replay /
+//  simulate pacing — reads time through a `SharedClock` instead of
+//  `Instant::now()` / `SystemTime::now()` / `tokio::time::sleep`.
+//
+//  * `SystemClock` — wall clock (the default everywhere)
+//  * `SimClock`    — virtual time that only moves when advanced; a
+//    sleep jumps the clock to its deadline and returns immediately, so
+//    replay / simulation run as fast as the CPU allows and produce the
+//    same results on every run
+//
+//  ESP downlink pacing (clips, TTS, diagnostics tones) stays on the
+//  wall clock: the device plays what it receives in real time.
+
+/// Boxed sleep future returned by [`Clock::sleep_until`].
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Source of time for the pipeline.
+pub trait Clock: Debug + Send + Sync {
+    /// Monotonic now.
+    fn now(&self) -> Instant;
+
+    /// Wall-clock now (packet timestamps, file names).
+    fn system_now(&self) -> SystemTime;
+
+    /// Resolve once `now()` has reached `deadline`.
+    fn sleep_until(&self, deadline: Instant) -> Sleep;
+
+    /// Resolve after `duration` of this clock's time.
+    fn sleep(&self, duration: Duration) -> Sleep {
+        self.sleep_until(self.now() + duration)
+    }
+}
+
+/// A clock shared by every component of one bridge instance.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The wall clock.
+pub fn system() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// How often to sweep state older than `max_age_secs`: a few times per
+/// eviction window, but at most once a minute.
+pub fn eviction_period(max_age_secs: u64) -> Duration {
+    Duration::from_secs((max_age_secs / 4).clamp(1, 60))
+}
+
+/// Wall clock: `Instant::now()`, `SystemTime::now()`, tokio timers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        Box::pin(tokio::time::sleep_until(deadline.into()))
+    }
+}
+
+/// Deterministic virtual clock.  Starts at the wall-clock time it was
+/// created (or a fixed epoch) and only moves forward via `advance` or
+/// when something sleeps on it.  Clone-friendly (Arc inside).
+#[derive(Debug, Clone)]
+pub struct SimClock {
+    inner: Arc<SimInner>,
+}
+
+#[derive(Debug)]
+struct SimInner {
+    base: Instant,
+    base_system: SystemTime,
+    offset: Mutex<Duration>,
+}
+
+impl SimClock {
+    /// Virtual clock whose wall-clock time starts at `epoch`.
+    pub fn starting_at(epoch: SystemTime) -> Self {
+        Self {
+            inner: Arc::new(SimInner {
+                base: Instant::now(),
+                base_system: epoch,
+                offset: Mutex::new(Duration::ZERO),
+            }),
+        }
+    }
+
+    /// Move time forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        *self.lock() += by;
+    }
+
+    /// Move time forward to `deadline` (no-op if already past it).
+    pub fn advance_to(&self, deadline: Instant) {
+        let target = deadline.saturating_duration_since(self.inner.base);
+        let mut offset = self.lock();
+        if target > *offset {
+            *offset = target;
+        }
+    }
+
+    /// Virtual time elapsed since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.lock()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Duration> {
+        self.inner.offset.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self::starting_at(SystemTime::now())
+    }
+}
+
+impl Clock for SimClock {
+    fn now(&self) -> Instant {
+        self.inner.base + self.elapsed()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        self.inner.base_system + self.elapsed()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        self.advance_to(deadline);
+        // Still yield, so a sleeping loop lets other tasks run
+        Box::pin(tokio::task::yield_now())
+    }
+}
+
+/// Fixed-rate ticker on a [`Clock`] (like `tokio::time::interval` with
+/// the default burst behaviour: the first tick is immediate and late
+/// ticks catch up).
+pub struct Ticker {
+    clock: SharedClock,
+    period: Duration,
+    next: Instant,
+}
+
+impl Ticker {
+    pub fn new(clock: SharedClock, period: Duration) -> Self {
+        let next = clock.now();
+        Self { clock, period, next }
+    }
+
+    /// Wait for the next tick.
+    pub async fn tick(&mut self) {
+        let deadline = self.next;
+        self.next += self.period;
+        if self.clock.now() < deadline {
+            self.clock.sleep_until(deadline).await;
+        }
+    }
+}
+
+/// Microseconds since the UNIX epoch on `clock`.
+pub fn unix_micros(clock: &dyn Clock) -> u64 {
+    since_epoch(clock).as_micros() as u64
+}
+
+/// Milliseconds since the UNIX epoch on `clock`.
+pub fn unix_millis(clock: &dyn Clock) -> u64 {
+    since_epoch(clock).as_millis() as u64
+}
+
+fn since_epoch(clock: &dyn Clock) -> Duration {
+    clock.system_now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default()
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eviction_period() {
+        let secs = |max_age| eviction_period(max_age).as_secs();
+        assert_eq!((secs(0), secs(2), secs(120), secs(3600)), (1, 1, 30, 60));
+    }
+
+    #[tokio::test]
+    async fn test_sim_clock_sleep_advances_instantly() {
+        let clock = SimClock::starting_at(std::time::UNIX_EPOCH);
+        let start = clock.now();
+        let wall = Instant::now();
+
+        clock.sleep(Duration::from_secs(3600)).await;
+        assert_eq!(clock.now() - start, Duration::from_secs(3600));
+        assert_eq!(unix_micros(&clock), 3_600_000_000);
+        assert!(wall.elapsed() < Duration::from_secs(1));
+
+        // Sleeping until the past does not move time backwards
+        clock.sleep_until(start).await;
+        assert_eq!(clock.elapsed(), Duration::from_secs(3600));
+    }
+
+    #[tokio::test]
+    async fn test_ticker_on_sim_clock() {
+        let sim = SimClock::default();
+        let mut ticker = Ticker::new(Arc::new(sim.clone()), Duration::from_millis(100));
+        for _ in 0..11 {
+            ticker.tick().await;
+        }
+        // First tick is immediate
+        assert_eq!(sim.elapsed(), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,511 @@
+use crate::clock::{ self, SharedClock, Ticker };
+use crate::config::Config;
+use crate::devices::{ DeviceConfig, DeviceRegistry };
+use crate::persona::{ PersonaState, PersonaTrait };
+use crate::sensor_smoother::SensorSmoother;
+use dashmap::DashMap;
+use serde::{ Deserialize, Serialize };
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{ broadcast, mpsc };
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Instance clustering
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Several bridge instances behind a UDP load balancer can share their
+//  configuration and one view of where each device is (`--cluster-redis-url`,
+//  build with `--features cluster`).  Live per-sensor state (smoother
+//  EMA, VAD, audio sessions) is NOT replicated: it lives on the instance
+//  that receives the traffic, so the load balancer must keep source-address
+//  affinity.  Instances publish JSON envelopes on one Redis pub/sub
+//  channel per namespace (`--cluster-namespace`, plus the tenant):
+//
+//    heartbeat   every HEARTBEAT, with the instance's API address
+//    persona     on every persona change (also SET `<ns>:persona`)
+//    thresholds  on every device override change (HSET `<ns>:devices`)
+//    sensor      when a sensor shows up on an instance (or new address)
+//    session     when an ESP audio session opens / closes
+//
+//  Persona and device overrides are state: a joining instance loads
+//  them from Redis, then follows the channel.  Sensor and session claims
+//  are a registry: each instance knows where every device was last
+//  seen, and when a sensor moves to another instance the old one drops
+//  its smoother state, so nothing stale is reused if it comes back.  The
+//  new instance starts that sensor's EMA from scratch.  ESP audio
+//  sessions (OpenAI conversation) stay on the instance that received
+//  SESSION_START; if the balancer moves a device mid-session, the
+//  session is lost.
+//
+//  Members silent for MEMBER_TIMEOUT are considered gone, along with
+//  their claims.  Redis outages only pause sync: every instance keeps
+//  serving on its local state.
+
+/// Heartbeat period.
+const HEARTBEAT: Duration = Duration::from_secs(2);
+
+/// A member not heard from for this long is considered gone.
+const MEMBER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Envelopes waiting for the backend.
+const OUTBOX_CAPACITY: usize = 1024;
+
+/// One message on the cluster channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    /// Sending instance
+    pub from: String,
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub message: ClusterMessage,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClusterMessage {
+    Heartbeat {
+        api: String,
+    },
+    Persona {
+        persona: PersonaTrait,
+    },
+    Thresholds {
+        sensor_id: u32,
+        config: DeviceConfig,
+    },
+    Sensor {
+        sensor_id: u32,
+        addr: String,
+    },
+    Session {
+        addr: String,
+        device: String,
+        open: bool,
+    },
+}
+
+/// Where a device was last seen.
+#[derive(Debug, Clone, Serialize)]
+pub struct Claim {
+    pub instance: String,
+    pub addr: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub device: String,
+    pub since_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Member {
+    pub instance: String,
+    pub api: String,
+    pub last_seen_ms: u64,
+}
+
+/// Response of `GET /cluster`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterStatus {
+    pub enabled: bool,
+    pub instance: String,
+    pub members: Vec<Member>,
+    pub sensors: Vec<(u32, Claim)>,
+    pub sessions: Vec<Claim>,
+}
+
+/// Cluster membership + shared registry of one bridge instance.
+/// Clone-friendly; a disabled handle (single instance) ignores
+/// everything.
+#[derive(Clone)]
+pub struct Cluster {
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    instance: String,
+    api: String,
+    outbox: mpsc::Sender<Envelope>,
+    members: DashMap<String, Member>,
+    sensors: DashMap<u32, Claim>,
+    sessions: DashMap<SocketAddr, Claim>,
+    persona: PersonaState,
+    devices: DeviceRegistry,
+    smoother: Arc<SensorSmoother>,
+    clock: SharedClock,
+}
+
+impl Cluster {
+    /// A single, unclustered instance.
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    /// Join the cluster if `--cluster-redis-url` is set.
+    pub fn from_config(
+        config: &Config,
+        persona: PersonaState,
+        devices: DeviceRegistry,
+        smoother: Arc<SensorSmoother>,
+        clock: SharedClock
+    ) -> anyhow::Result<Self> {
+        if config.cluster_redis_url.is_empty() {
+            return Ok(Self::disabled());
+        }
+        #[cfg(not(feature = "cluster"))]
+        {
+            let _ = (persona, devices, smoother, clock);
+            anyhow::bail!("--cluster-redis-url requires a build with `--features cluster`");
+        }
+        #[cfg(feature = "cluster")]
+        {
+            let namespace = if config.tenant_id.is_empty() {
+                config.cluster_namespace.clone()
+            } else {
+                format!("{}:{}", config.cluster_namespace, config.tenant_id)
+            };
+            let api = format!("{}:{}", config.host, config.api_port);
+            let (cluster, outbox) = Self::new(config.resolved_instance_id(), api, persona, devices, smoother, clock);
+            let redis = redis::Client::open(config.cluster_redis_url.as_str())?;
+            tokio::spawn(crate::cluster_redis::run(redis, namespace.clone(), outbox, cluster.clone()));
+            cluster.spawn_local_tasks();
+            info!(
+                instance = %cluster.instance(),
+                namespace = %namespace,
+                "🕸️  cluster coordination enabled (Redis)"
+            );
+            Ok(cluster)
+        }
+    }
+
+    #[cfg_attr(not(feature = "cluster"), allow(dead_code))]
+    fn new(
+        instance: String,
+        api: String,
+        persona: PersonaState,
+        devices: DeviceRegistry,
+        smoother: Arc<SensorSmoother>,
+        clock: SharedClock
+    ) -> (Self, mpsc::Receiver<Envelope>) {
+        let (outbox, rx) = mpsc::channel(OUTBOX_CAPACITY);
+        let inner = Inner {
+            instance,
+            api,
+            outbox,
+            members: DashMap::new(),
+            sensors: DashMap::new(),
+            sessions: DashMap::new(),
+            persona,
+            devices,
+            smoother,
+            clock,
+        };
+        (Self { inner: Some(Arc::new(inner)) }, rx)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// This instance's name ("" when disabled).
+    pub fn instance(&self) -> &str {
+        self.inner.as_ref().map_or("", |inner| inner.instance.as_str())
+    }
+
+    /// Forward local persona / threshold changes and send heartbeats.
+    #[cfg_attr(not(feature = "cluster"), allow(dead_code))]
+    fn spawn_local_tasks(&self) {
+        let Some(inner) = self.inner.clone() else {
+            return;
+        };
+        let cluster = self.clone();
+        let mut personas = inner.persona.subscribe_changes();
+        let mut devices = inner.devices.subscribe_changes();
+        tokio::spawn(async move {
+            let mut heartbeat = Ticker::new(inner.clock.clone(), HEARTBEAT);
+            loop {
+                let message = tokio::select! {
+                    _ = heartbeat.tick() => ClusterMessage::Heartbeat { api: inner.api.clone() },
+                    persona = personas.recv() => match persona {
+                        Ok(persona) => ClusterMessage::Persona { persona },
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    change = devices.recv() => match change {
+                        Ok((sensor_id, config)) => ClusterMessage::Thresholds { sensor_id, config },
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                };
+                cluster.send(message);
+            }
+        });
+    }
+
+    fn send(&self, message: ClusterMessage) {
+        let Some(ref inner) = self.inner else {
+            return;
+        };
+        let envelope = Envelope {
+            from: inner.instance.clone(),
+            at_ms: clock::unix_millis(inner.clock.as_ref()),
+            message,
+        };
+        if inner.outbox.try_send(envelope).is_err() {
+            warn!("cluster outbox full — dropping update");
+        }
+    }
+
+    /// A sensor packet from `addr` arrived here: claim the sensor unless
+    /// this instance already holds it at that address.
+    pub fn claim_sensor(&self, sensor_id: u32, addr: SocketAddr) {
+        let Some(ref inner) = self.inner else {
+            return;
+        };
+        let addr = addr.to_string();
+        if
+            inner.sensors
+                .get(&sensor_id)
+                .is_some_and(|claim| claim.instance == inner.instance && claim.addr == addr)
+        {
+            return;
+        }
+        let claim = Claim {
+            instance: inner.instance.clone(),
+            addr: addr.clone(),
+            device: String::new(),
+            since_ms: clock::unix_millis(inner.clock.as_ref()),
+        };
+        inner.sensors.insert(sensor_id, claim);
+        self.send(ClusterMessage::Sensor { sensor_id, addr });
+    }
+
+    /// An ESP audio session from `addr` (`device` = MAC or address)
+    /// opened or closed here.
+    pub fn session(&self, addr: SocketAddr, device: &str, open: bool) {
+        let Some(ref inner) = self.inner else {
+            return;
+        };
+        if open {
+            let claim = Claim {
+                instance: inner.instance.clone(),
+                addr: addr.to_string(),
+                device: device.to_string(),
+                since_ms: clock::unix_millis(inner.clock.as_ref()),
+            };
+            inner.sessions.insert(addr, claim);
+        } else {
+            inner.sessions.remove(&addr);
+        }
+        self.send(ClusterMessage::Session { addr: addr.to_string(), device: device.to_string(), open });
+    }
+
+    /// Apply shared state loaded from the backend (persona, overrides).
+    pub async fn apply_state(&self, message: &ClusterMessage) {
+        let Some(ref inner) = self.inner else {
+            return;
+        };
+        match message {
+            ClusterMessage::Persona { persona } => {
+                let current = inner.persona.get().await;
+                if current != *persona {
+                    info!(persona = %persona, "🕸️  persona changed by cluster");
+                    inner.persona.apply_remote(*persona).await;
+                }
+            }
+            ClusterMessage::Thresholds { sensor_id, config } => {
+                inner.devices.apply_remote(*sensor_id, config.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /// Apply an envelope received from the cluster channel.
+    pub async fn apply_remote(&self, envelope: &Envelope) {
+        let Some(ref inner) = self.inner else {
+            return;
+        };
+        if envelope.from == inner.instance {
+            return;
+        }
+        let now_ms = clock::unix_millis(inner.clock.as_ref());
+        let mut member = inner.members.entry(envelope.from.clone()).or_insert_with(|| {
+            info!(instance = %envelope.from, "🕸️  cluster member joined");
+            Member { instance: envelope.from.clone(), api: String::new(), last_seen_ms: now_ms }
+        });
+        member.last_seen_ms = now_ms;
+        if let ClusterMessage::Heartbeat { api } = &envelope.message {
+            member.api = api.clone();
+        }
+        drop(member);
+
+        match &envelope.message {
+            ClusterMessage::Heartbeat { .. } => {}
+            ClusterMessage::Persona { .. } | ClusterMessage::Thresholds { .. } => {
+                self.apply_state(&envelope.message).await;
+            }
+            ClusterMessage::Sensor { sensor_id, addr } => {
+                let claim = Claim {
+                    instance: envelope.from.clone(),
+                    addr: addr.clone(),
+                    device: String::new(),
+                    since_ms: envelope.at_ms,
+                };
+                let previous = inner.sensors.insert(*sensor_id, claim);
+                // Smoothing continues on the new instance: ours is stale now
+                if previous.is_some_and(|p| p.instance == inner.instance) {
+                    info!(sensor_id, to = %envelope.from, "🕸️  sensor moved to another instance");
+                    inner.smoother.reset_sensor(*sensor_id);
+                }
+            }
+            ClusterMessage::Session { addr, device, open } => {
+                let Ok(addr) = addr.parse::<SocketAddr>() else {
+                    return;
+                };
+                if *open {
+                    let claim = Claim {
+                        instance: envelope.from.clone(),
+                        addr: addr.to_string(),
+                        device: device.clone(),
+                        since_ms: envelope.at_ms,
+                    };
+                    inner.sessions.insert(addr, claim);
+                } else {
+                    inner.sessions.remove_if(&addr, |_, claim| claim.instance == envelope.from);
+                }
+            }
+        }
+    }
+
+    /// Members (this one included) and the devices they hold.
+    pub fn status(&self) -> ClusterStatus {
+        let Some(ref inner) = self.inner else {
+            return ClusterStatus {
+                enabled: false,
+                instance: String::new(),
+                members: Vec::new(),
+                sensors: Vec::new(),
+                sessions: Vec::new(),
+            };
+        };
+        let now_ms = clock::unix_millis(inner.clock.as_ref());
+        let alive = |instance: &str| {
+            instance == inner.instance ||
+                inner.members
+                    .get(instance)
+                    .is_some_and(|m| now_ms.saturating_sub(m.last_seen_ms) < (MEMBER_TIMEOUT.as_millis() as u64))
+        };
+
+        let mut members: Vec<Member> = inner.members
+            .iter()
+            .filter(|m| alive(&m.instance))
+            .map(|m| m.clone())
+            .collect();
+        members.push(Member { instance: inner.instance.clone(), api: inner.api.clone(), last_seen_ms: now_ms });
+        members.sort_by(|a, b| a.instance.cmp(&b.instance));
+
+        let mut sensors: Vec<(u32, Claim)> = inner.sensors
+            .iter()
+            .filter(|c| alive(&c.instance))
+            .map(|c| (*c.key(), c.value().clone()))
+            .collect();
+        sensors.sort_by_key(|(id, _)| *id);
+
+        let mut sessions: Vec<Claim> = inner.sessions
+            .iter()
+            .filter(|c| alive(&c.instance))
+            .map(|c| c.value().clone())
+            .collect();
+        sessions.sort_by(|a, b| a.addr.cmp(&b.addr));
+
+        ClusterStatus { enabled: true, instance: inner.instance.clone(), members, sensors, sessions }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimClock;
+    use crate::devices::Thresholds;
+
+    fn node(name: &str, sim: &SimClock) -> (Cluster, mpsc::Receiver<Envelope>) {
+        Cluster::new(
+            name.to_string(),
+            format!("{name}:8080"),
+            PersonaState::new(PersonaTrait::Obedient),
+            DeviceRegistry::new(Thresholds { audio: 30.0, arousal: 0.35 }),
+            Arc::new(SensorSmoother::with_clock(Duration::from_secs(60), Arc::new(sim.clone()))),
+            Arc::new(sim.clone())
+        )
+    }
+
+    #[tokio::test]
+    async fn test_sensor_claims_move_between_instances() {
+        let sim = SimClock::default();
+        let (a, mut a_out) = node("a", &sim);
+        let (b, mut b_out) = node("b", &sim);
+        let addr: SocketAddr = "10.0.0.5:4000".parse().unwrap();
+
+        a.claim_sensor(7, addr);
+        a.claim_sensor(7, addr);
+        let claim = a_out.try_recv().unwrap();
+        assert!(a_out.try_recv().is_err(), "repeat packets are not re-announced");
+        b.apply_remote(&claim).await;
+        assert_eq!(b.status().sensors[0].1.instance, "a");
+
+        // The load balancer sends sensor 7 to b: a hears about it
+        b.claim_sensor(7, addr);
+        a.apply_remote(&b_out.try_recv().unwrap()).await;
+        let status = a.status();
+        assert_eq!(status.sensors[0].1.instance, "b");
+        assert_eq!(
+            status.members
+                .iter()
+                .map(|m| m.instance.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+
+        // ... and claims it back on the next packet
+        a.claim_sensor(7, addr);
+        assert!(matches!(a_out.try_recv().unwrap().message, ClusterMessage::Sensor { sensor_id: 7, .. }));
+
+        // Silent members and their claims drop out of the view
+        sim.advance(MEMBER_TIMEOUT);
+        b.apply_remote(&Envelope { from: "c".into(), at_ms: 0, message: ClusterMessage::Heartbeat { api: String::new() } }).await;
+        sim.advance(MEMBER_TIMEOUT);
+        assert_eq!(b.status().members.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_shared_state_and_own_messages() {
+        let sim = SimClock::default();
+        let (a, _) = node("a", &sim);
+        let inner = a.inner.as_ref().unwrap();
+
+        let persona = |from: &str| Envelope {
+            from: from.into(),
+            at_ms: 0,
+            message: ClusterMessage::Persona { persona: PersonaTrait::Cute },
+        };
+        a.apply_remote(&persona("a")).await;
+        assert_eq!(inner.persona.get().await, PersonaTrait::Obedient);
+        a.apply_remote(&persona("b")).await;
+        assert_eq!(inner.persona.get().await, PersonaTrait::Cute);
+
+        let config = DeviceConfig { audio_threshold: Some(99.0), ..Default::default() };
+        let json = serde_json::to_string(&Envelope {
+            from: "b".into(),
+            at_ms: 1,
+            message: ClusterMessage::Thresholds { sensor_id: 3, config },
+        }).unwrap();
+        assert!(json.contains(r#""kind":"thresholds""#));
+        a.apply_remote(&serde_json::from_str(&json).unwrap()).await;
+        assert_eq!(inner.devices.thresholds(3).audio, 99.0);
+
+        assert!(!Cluster::disabled().status().enabled);
+    }
+}
@@ -0,0 +1,108 @@
+//! Redis backend for cluster coordination (`--cluster-redis-url`).
+//!
+//! One pub/sub channel (`<ns>:events`) carries every envelope; persona
+//! and device overrides are also written to `<ns>:persona` and the
+//! `<ns>:devices` hash so joining instances can load them.  Both the
+//! subscriber and the publisher reconnect on their own after errors.
+
+use crate::cluster::{ Cluster, ClusterMessage, Envelope };
+use crate::devices::DeviceConfig;
+use crate::persona::PersonaTrait;
+use futures_util::StreamExt;
+use redis::AsyncCommands;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{ debug, warn };
+
+/// Pause before reconnecting after a Redis error.
+const RETRY: Duration = Duration::from_secs(1);
+
+/// Publish local envelopes and apply everyone else's until shutdown.
+pub async fn run(client: redis::Client, namespace: String, mut outbox: mpsc::Receiver<Envelope>, cluster: Cluster) {
+    tokio::spawn(subscribe_loop(client.clone(), namespace.clone(), cluster));
+
+    let channel = format!("{namespace}:events");
+    let mut conn: Option<redis::aio::MultiplexedConnection> = None;
+    while let Some(envelope) = outbox.recv().await {
+        if conn.is_none() {
+            match client.get_multiplexed_async_connection().await {
+                Ok(c) => {
+                    conn = Some(c);
+                }
+                Err(e) => {
+                    warn!(error = %e, "cluster Redis unavailable — dropping update");
+                    tokio::time::sleep(RETRY).await;
+                    continue;
+                }
+            }
+        }
+        let Some(c) = conn.as_mut() else {
+            continue;
+        };
+        if let Err(e) = publish(c, &namespace, &channel, &envelope).await {
+            warn!(error = %e, "cluster Redis publish failed — reconnecting");
+            conn = None;
+        }
+    }
+}
+
+async fn publish(
+    conn: &mut redis::aio::MultiplexedConnection,
+    namespace: &str,
+    channel: &str,
+    envelope: &Envelope
+) -> anyhow::Result<()> {
+    match &envelope.message {
+        ClusterMessage::Persona { persona } => {
+            let value = serde_json::to_string(persona)?;
+            conn.set::<_, _, ()>(format!("{namespace}:persona"), value).await?;
+        }
+        ClusterMessage::Thresholds { sensor_id, config } => {
+            let value = serde_json::to_string(config)?;
+            conn.hset::<_, _, _, ()>(format!("{namespace}:devices"), sensor_id, value).await?;
+        }
+        _ => {}
+    }
+    conn.publish::<_, _, ()>(channel, serde_json::to_string(envelope)?).await?;
+    Ok(())
+}
+
+/// Follow the channel, (re)loading shared state on every connect.
+async fn subscribe_loop(client: redis::Client, namespace: String, cluster: Cluster) {
+    let channel = format!("{namespace}:events");
+    loop {
+        if let Err(e) = follow(&client, &namespace, &channel, &cluster).await {
+            warn!(error = %e, "cluster Redis subscription lost — retrying in 1 s");
+        }
+        tokio::time::sleep(RETRY).await;
+    }
+}
+
+async fn follow(client: &redis::Client, namespace: &str, channel: &str, cluster: &Cluster) -> anyhow::Result<()> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(channel).await?;
+
+    // Subscribed first, so no change between the snapshot and the stream is lost
+    let mut conn = client.get_multiplexed_async_connection().await?;
+    let persona: Option<String> = conn.get(format!("{namespace}:persona")).await?;
+    if let Some(persona) = persona.and_then(|p| serde_json::from_str::<PersonaTrait>(&p).ok()) {
+        cluster.apply_state(&ClusterMessage::Persona { persona }).await;
+    }
+    let devices: Vec<(u32, String)> = conn.hgetall(format!("{namespace}:devices")).await?;
+    for (sensor_id, config) in devices {
+        if let Ok(config) = serde_json::from_str::<DeviceConfig>(&config) {
+            cluster.apply_state(&ClusterMessage::Thresholds { sensor_id, config }).await;
+        }
+    }
+    debug!(channel = %channel, "cluster state loaded from Redis");
+
+    let mut messages = pubsub.on_message();
+    while let Some(msg) = messages.next().await {
+        let payload: String = msg.get_payload()?;
+        match serde_json::from_str::<Envelope>(&payload) {
+            Ok(envelope) => cluster.apply_remote(&envelope).await,
+            Err(e) => debug!(error = %e, "ignoring malformed cluster message"),
+        }
+    }
+    anyhow::bail!("subscription closed")
+}
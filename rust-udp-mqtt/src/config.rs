@@ -1,9 +1,42 @@
-use clap::Parser;
+use crate::failover::FailoverRole;
+use crate::handover::Sockets;
+use crate::pcm::{ MixMode, SampleFormat };
+use crate::persona::PersonaTrait;
+use crate::recorder::RecordFormat;
+use crate::recording_policy::RecordingMode;
+use crate::speakers::SpeakerEngineKind;
+use crate::start_policy::StartPolicy;
+use crate::stats::StatsSinkKind;
+use crate::transport_openai::OpenAiMode;
+use crate::tts::TtsKind;
+use crate::vad::EmotionEngineKind;
+use crate::vad_response::CoalesceMode;
+use clap::{ Args, Parser, Subcommand };
 
 /// High-performance UDP sensor data processor with VAD computation
 /// and OpenAI Realtime API bridge for ESP32 audio.
+///
+/// Running without a subcommand is the same as `serve` (flags may be
+/// given directly, as before subcommands existed).
 #[derive(Parser, Debug, Clone)]
-#[command(author, version, about)]
+#[command(author, version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub serve: Config,
+}
+
+impl Cli {
+    /// The subcommand to run, defaulting to `serve` with the top-level flags.
+    pub fn into_command(self) -> Command {
+        self.command.unwrap_or(Command::Serve(Box::new(self.serve)))
+    }
+}
+
+/// Bridge configuration (`serve` / `validate` flags).
+#[derive(Args, Debug, Clone)]
 pub struct Config {
     /// Listen address
     #[arg(long, default_value = "0.0.0.0")]
@@ -29,10 +62,151 @@ pub struct Config {
     #[arg(long, default_value_t = 8080)]
     pub api_port: u16,
 
-    /// Size of the internal processing channel
+    /// Open REST API connections allowed per client IP (0 = unlimited)
+    #[arg(long, default_value_t = 32)]
+    pub api_max_conns_per_ip: usize,
+
+    /// Seconds a REST client has to send its request headers (also the
+    /// keep-alive idle limit)
+    #[arg(long, default_value_t = 10)]
+    pub api_header_timeout_secs: u64,
+
+    /// Largest REST request header block, in bytes (min 8192)
+    #[arg(long, default_value_t = 16 * 1024)]
+    pub api_max_header_bytes: usize,
+
+    /// Seconds a REST request may take end to end, body included (408 after)
+    #[arg(long, default_value_t = 60)]
+    pub api_request_timeout_secs: u64,
+
+    /// Largest REST request body, in bytes (firmware uploads excepted)
+    #[arg(long, default_value_t = 1024 * 1024)]
+    pub api_max_body_bytes: usize,
+
+    /// Persona active at startup (changeable via `PUT /persona`)
+    #[arg(long, value_enum, default_value_t = PersonaTrait::Obedient)]
+    pub persona: PersonaTrait,
+
+    /// Tenants TOML: run one isolated bridge per `[[tenant]]` (own ports,
+    /// sessions, persona, OpenAI key) in this process ("" = single tenant)
+    #[arg(long, default_value = "")]
+    pub tenants_file: String,
+
+    /// Tenant this instance serves (set from `--tenants-file`, "" = none)
+    #[arg(skip)]
+    pub tenant_id: String,
+
+    /// Sockets inherited from systemd or the previous bridge (set by
+    /// `serve`, shared by every tenant)
+    #[arg(skip)]
+    pub sockets: Sockets,
+
+    /// Sensor-port receive datapath: epoll (tokio) or uring (one io_uring
+    /// per receiver thread; needs `--features io-uring`, Linux 5.6+)
+    #[arg(long, value_enum, default_value_t = crate::uring::UdpIo::Epoll)]
+    pub udp_io: crate::uring::UdpIo,
+
+    /// Take sensor packets from a ZeroMQ PULL socket bound here instead of
+    /// the UDP ports, e.g. tcp://0.0.0.0:5557 ("" = UDP; needs
+    /// `--features zmq`)
+    #[arg(long, default_value = "")]
+    pub zmq_pull: String,
+
+    /// Publish VAD results of `--zmq-pull` packets on a ZeroMQ PUB socket
+    /// bound here, e.g. tcp://0.0.0.0:5558 ("" = not published)
+    #[arg(long, default_value = "")]
+    pub zmq_pub: String,
+
+    /// Also take sensor packets from a Unix datagram socket bound at this
+    /// path, one packet per datagram ("" = off)
+    #[arg(long, default_value = "")]
+    pub uds_dgram: String,
+
+    /// Also take length-prefixed sensor packets (u32 LE length, then the
+    /// packet) from a Unix stream socket bound at this path ("" = off)
+    #[arg(long, default_value = "")]
+    pub uds_stream: String,
+
+    /// Also read length-prefixed sensor packets from standard input
+    #[arg(long)]
+    pub stdin: bool,
+
+    /// Also read SLIP-framed packets from a serial port, as
+    /// PATH[:BAUD][:sensor|esp] (repeatable; needs `--features serial`)
+    #[arg(long)]
+    pub serial: Vec<String>,
+
+    /// Also accept RTP audio streams on this UDP port, RTCP on the port
+    /// above it (0 = off)
+    #[arg(long, default_value_t = 0)]
+    pub rtp_port: u16,
+
+    /// Dynamic RTP payload type of L16 mono audio at --rtp-l16-rate
+    #[arg(long, default_value_t = 96)]
+    pub rtp_l16_pt: u8,
+
+    /// Sample rate of --rtp-l16-pt audio (resampled to 16 kHz)
+    #[arg(long, default_value_t = 16000)]
+    pub rtp_l16_rate: u32,
+
+    /// Dynamic RTP payload type of Opus audio (needs `--features opus`)
+    #[arg(long, default_value_t = 111)]
+    pub rtp_opus_pt: u8,
+
+    /// End an RTP stream's session after this long without packets
+    #[arg(long, default_value_t = 1500)]
+    pub rtp_idle_ms: u64,
+
+    /// UDP port of the SIP endpoint that answers phone calls (0 = off)
+    #[arg(long, default_value_t = 0)]
+    pub sip_port: u16,
+
+    /// PBX to REGISTER with (host or host:port; empty = direct calls only)
+    #[arg(long, default_value = "")]
+    pub sip_registrar: String,
+
+    /// SIP user (extension) the bridge registers as
+    #[arg(long, default_value = "robot")]
+    pub sip_user: String,
+
+    /// Password for the registrar's digest challenge (or set SIP_PASSWORD env var)
+    #[arg(long, env = "SIP_PASSWORD", default_value = "")]
+    pub sip_password: String,
+
+    /// Address advertised in Contact and SDP (empty = route towards the registrar)
+    #[arg(long, default_value = "")]
+    pub sip_public_ip: String,
+
+    /// Local UDP port of a call's RTP leg
+    #[arg(long, default_value_t = 40000)]
+    pub sip_rtp_port: u16,
+
+    /// Registration expiry requested from the PBX (renewed at 80 %)
+    #[arg(long, default_value_t = 300)]
+    pub sip_register_secs: u32,
+
+    /// ESP (ip:port or MAC) whose speaker also plays the caller's voice
+    #[arg(long, default_value = "")]
+    pub sip_speaker: String,
+
+    /// Size of each emotional VAD worker's processing queue
     #[arg(long, default_value_t = 65536)]
     pub channel_capacity: usize,
 
+    /// Size of each audio VAD worker's processing queue
+    #[arg(long, default_value_t = 65536)]
+    pub audio_channel_capacity: usize,
+
+    /// VAD workers skip (shed) packets that waited longer than this in
+    /// their queue, so bursts drop stale work instead of lagging (0 = off)
+    #[arg(long, default_value_t = 0)]
+    pub packet_deadline_ms: u64,
+
+    /// Under a systemd watchdog (`WatchdogSec=`), stop pinging once the
+    /// worker queues hold packets none of which was taken for N seconds
+    #[arg(long, default_value_t = 10)]
+    pub watchdog_stall_secs: u64,
+
     /// UDP receive buffer size (SO_RCVBUF)
     #[arg(long, default_value_t = 4 * 1024 * 1024)]
     pub recv_buf_size: usize,
@@ -41,22 +215,452 @@ pub struct Config {
     #[arg(long, default_value_t = 4)]
     pub recv_threads: usize,
 
-    /// Number of VAD processor threads (0 = num CPUs)
+    /// Number of emotional (sensor vector) VAD threads (0 = num CPUs);
+    /// sensors are sharded over them by sensor_id
     #[arg(long, default_value_t = 2)]
     pub proc_threads: usize,
 
-    /// Stats logging interval in seconds (0 = disabled)
+    /// Number of audio VAD threads (0 = num CPUs), a separate pool so
+    /// sensor bursts cannot starve audio and vice versa
+    #[arg(long, default_value_t = 2)]
+    pub audio_proc_threads: usize,
+
+    /// Stats sampling interval in seconds: counters are read, fed to
+    /// anomaly detection and reported this often (0 = disabled)
     #[arg(long, default_value_t = 5)]
     pub stats_interval_secs: u64,
 
+    /// Where stats are reported (repeatable)
+    #[arg(long, value_enum, default_values_t = [StatsSinkKind::Stdout])]
+    pub stats_sink: Vec<StatsSinkKind>,
+
+    /// Report period of the `stdout` stats sink, in seconds (0 = every
+    /// sample; rounded up to a multiple of --stats-interval-secs)
+    #[arg(long, default_value_t = 0)]
+    pub stats_stdout_secs: u64,
+
+    /// Report period of the `tracing` stats sink, in seconds (0 = every sample)
+    #[arg(long, default_value_t = 0)]
+    pub stats_tracing_secs: u64,
+
+    /// JSONL file the `file` stats sink appends to
+    #[arg(long, default_value = "")]
+    pub stats_file: String,
+
+    /// Report period of the `file` stats sink, in seconds (0 = every sample)
+    #[arg(long, default_value_t = 0)]
+    pub stats_file_secs: u64,
+
+    /// MQTT broker of the `mqtt` stats sink
+    #[arg(long, env = "STATS_MQTT_HOST", default_value = "")]
+    pub stats_mqtt_host: String,
+
+    /// MQTT broker port for stats
+    #[arg(long, default_value_t = 1883)]
+    pub stats_mqtt_port: u16,
+
+    /// Stats are published to `<topic>[/<tenant>]`
+    #[arg(long, default_value = "vad/stats")]
+    pub stats_mqtt_topic: String,
+
+    /// Report period of the `mqtt` stats sink, in seconds (0 = every sample)
+    #[arg(long, default_value_t = 0)]
+    pub stats_mqtt_secs: u64,
+
+    /// Flag unusual parse-error / drop rates, downlink loss and silent
+    /// devices against their own EWMA baseline
+    #[arg(long, default_value_t = false)]
+    pub anomaly_detection: bool,
+
+    /// z-score at which a sample counts as an anomaly
+    #[arg(long, default_value_t = 4.0)]
+    pub anomaly_z: f64,
+
+    /// EWMA weight of each new sample in an anomaly baseline (smaller =
+    /// slower to adapt)
+    #[arg(long, default_value_t = 0.05)]
+    pub anomaly_alpha: f64,
+
+    /// POST every anomaly as JSON to this URL (repeatable)
+    #[arg(long)]
+    pub anomaly_webhook: Vec<String>,
+
+    /// Default audio RMS energy threshold for `is_active`
+    /// (per-device overrides via `PUT /devices/{id}/thresholds`)
+    #[arg(long, default_value_t = crate::vad::VAD_ENERGY_THRESHOLD)]
+    pub audio_threshold: f64,
+
+    /// Default emotional arousal threshold for `is_active`
+    #[arg(long, default_value_t = crate::vad::EMOTIONAL_ACTIVE_THRESHOLD)]
+    pub arousal_threshold: f32,
+
+    /// Reset a sensor's idle-time EMA when it reappears after this many
+    /// seconds of silence (treated as a reconnect)
+    #[arg(long, default_value_t = 300)]
+    pub smoother_reset_gap_secs: u64,
+
+    /// Evict smoother and event debounce state for sensors not seen in
+    /// this many seconds (0 = never)
+    #[arg(long, default_value_t = 1800)]
+    pub smoother_evict_secs: u64,
+
+    /// Forget sensor-port client addresses not heard from in this many
+    /// seconds (0 = never)
+    #[arg(long, default_value_t = 1800)]
+    pub client_evict_secs: u64,
+
+    /// Rolling PCM window per sensor for audio VAD energy, in ms
+    /// (0 = per-packet RMS)
+    #[arg(long, default_value_t = 300)]
+    pub audio_vad_window_ms: u64,
+
+    /// VAD response packet version (1 = 34 bytes, 2 = + dBFS/ZCR/band ratio)
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub vad_response_version: u8,
+
+    /// Coalesce VAD responses per sensor over this window, in ms
+    /// (0 = one response per result)
+    #[arg(long, default_value_t = 0)]
+    pub response_coalesce_ms: u64,
+
+    /// How coalesced results are merged
+    #[arg(long, value_enum, default_value_t = CoalesceMode::Latest)]
+    pub response_coalesce_mode: CoalesceMode,
+
+    /// Emotional V/A/D engine
+    #[arg(long, value_enum, default_value_t = EmotionEngineKind::Linear)]
+    pub emotion_engine: EmotionEngineKind,
+
+    /// ONNX model path for `--emotion-engine onnx`
+    #[arg(long, default_value = "")]
+    pub emotion_model_path: String,
+
+    /// Linear weights TOML (from `calibrate`) replacing the built-in weights
+    #[arg(long, default_value = "")]
+    pub emotion_weights: String,
+
+    /// Shadow emotional engine: scored on the same features as the primary
+    /// and logged for divergence, never sent
+    #[arg(long, value_enum)]
+    pub shadow_emotion_engine: Option<EmotionEngineKind>,
+
+    /// ONNX model path for `--shadow-emotion-engine onnx`
+    #[arg(long, default_value = "")]
+    pub shadow_emotion_model_path: String,
+
+    /// Linear weights TOML for `--shadow-emotion-engine linear`
+    #[arg(long, default_value = "")]
+    pub shadow_emotion_weights: String,
+
+    /// A/B experiments TOML: buckets devices into variants of the
+    /// thresholds, persona and prompt, and compares their results
+    /// (`GET /experiments`)
+    #[arg(long, default_value = "")]
+    pub experiments_file: String,
+
+    /// Speaker diarization engine: tags user transcripts with the
+    /// speaker heard in the session (off = no tags)
+    #[arg(long, value_enum, default_value_t = SpeakerEngineKind::Off)]
+    pub speaker_engine: SpeakerEngineKind,
+
+    /// ONNX speaker-embedding model path for `--speaker-engine onnx`
+    #[arg(long, default_value = "")]
+    pub speaker_model_path: String,
+
+    /// Cosine similarity at or above which a session matches a known speaker
+    #[arg(long, default_value_t = 0.75)]
+    pub speaker_threshold: f32,
+
+    /// PCM sample format sent by devices on the ESP audio port
+    /// (normalised to s16 before recording / OpenAI / VAD)
+    #[arg(long, value_enum, default_value_t = SampleFormat::S16)]
+    pub esp_sample_format: SampleFormat,
+
+    /// How multi-channel ESP sessions are reduced to mono for VAD and
+    /// OpenAI (session WAVs keep every channel)
+    #[arg(long, value_enum, default_value_t = MixMode::Mix)]
+    pub mic_mix: MixMode,
+
+    /// Channel kept by `--mic-mix channel` (0 = first)
+    #[arg(long, default_value_t = 0)]
+    pub mic_channel: usize,
+
     /// Directory to save ESP audio session recordings
     #[arg(long, default_value = "../esp_audio")]
     pub audio_save_dir: String,
 
+    /// Seconds between flushes of session WAVs being streamed to disk
+    /// (at most this much audio is lost if the bridge crashes)
+    #[arg(long, default_value_t = 1)]
+    pub wav_flush_secs: u64,
+
+    /// Force-close an ESP session that has been receiving for this many
+    /// seconds without SESSION_END (audio saved, device notified; 0 = no cap)
+    #[arg(long, default_value_t = 300)]
+    pub max_session_secs: u64,
+
+    /// Force-close an ESP session once it has sent this many audio bytes
+    /// (0 = no cap)
+    #[arg(long, default_value_t = 0)]
+    pub max_session_bytes: u64,
+
+    /// SESSION_START while the device's session is still receiving:
+    /// resume it, restart it (audio so far discarded) or reject the START
+    /// (per-device override: `PUT /devices/{device}/start-policy`)
+    #[arg(long, value_enum, default_value_t = StartPolicy::Resume)]
+    pub duplicate_start_policy: StartPolicy,
+
     /// Save debug audio (incoming PCM + OpenAI response WAVs) to audio_save_dir/debug/
     #[arg(long, default_value_t = false)]
     pub save_debug_audio: bool,
 
+    /// Write a JSONL event log per ESP session next to its WAV
+    /// (packets, losses, VAD transitions, control, OpenAI events)
+    #[arg(long, default_value_t = false)]
+    pub session_log: bool,
+
+    /// Also save each session as low-bitrate Ogg Opus at this many kbps
+    /// (dashboard playback; 0 = off, requires `--features opus`)
+    #[arg(long, default_value_t = 0)]
+    pub monitor_opus_kbps: u32,
+
+    /// Disk budget for transcodes served by `GET /recordings/{id}`, in MB
+    /// (`<audio_save_dir>/transcoded`; 0 = no caching)
+    #[arg(long, default_value_t = 256)]
+    pub transcode_cache_mb: u64,
+
+    /// What is kept of a device's sessions: everything (record), session
+    /// timing only (metadata: no WAV, no transcripts) or nothing (never)
+    /// (per-device override: `PUT /devices/{device}/recording-policy`)
+    #[arg(long, value_enum, default_value_t = RecordingMode::Record)]
+    pub recording_policy: RecordingMode,
+
+    /// Delete recorded session files older than this many days
+    /// (0 = keep until erased; per-device override as above)
+    #[arg(long, default_value_t = 0)]
+    pub recording_retention_days: u32,
+
+    /// Pace AUDIO_DOWN per device from the RTT / loss ESPs report in
+    /// their heartbeats, slowing down on a degraded link (off = OpenAI
+    /// audio sent as it arrives, clips and TTS at real time)
+    #[arg(long, default_value_t = false)]
+    pub downlink_pacing: bool,
+
+    /// Smoothed heartbeat RTT above which a device's downlink counts as
+    /// degraded (`--downlink-pacing`)
+    #[arg(long, default_value_t = 250)]
+    pub downlink_rtt_limit_ms: u64,
+
+    /// Directory for pcap files started via `PUT /debug/capture`
+    /// (per-tenant subdirectory when --tenant-id is set)
+    #[arg(long, default_value = "captures")]
+    pub capture_dir: String,
+
+    /// Sensor schemas TOML (`[[schema]]` channel names / ranges / units per
+    /// data_type) on top of the built-in sensor vector schema ("" = none)
+    #[arg(long, default_value = "")]
+    pub sensor_schemas: String,
+
+    /// Record every sensor vector (raw channels + computed V/A/D) to
+    /// rotating files in this directory (empty = off)
+    #[arg(long, default_value = "")]
+    pub record_dir: String,
+
+    /// Sensor recording file format (parquet needs `--features parquet`)
+    #[arg(long, value_enum, default_value_t = RecordFormat::Csv)]
+    pub record_format: RecordFormat,
+
+    /// Start a new sensor recording file every N seconds
+    #[arg(long, default_value_t = 3600)]
+    pub record_rotate_secs: u64,
+
+    /// Hours of per-device emotion history kept for
+    /// `GET /devices/{id}/emotions` (0 = disabled)
+    #[arg(long, default_value_t = 24)]
+    pub emotion_history_hours: u64,
+
+    /// Days of hourly per-device sound levels kept for
+    /// `GET /stats/sound-levels/{id}` (0 = disabled)
+    #[arg(long, default_value_t = 30)]
+    pub sound_level_days: u64,
+
+    /// Rolling sound level window of `GET /stats/sound-levels`, in seconds
+    #[arg(long, default_value_t = 60)]
+    pub sound_level_window_secs: u64,
+
+    /// Raise a priority `distress` event when a sensor's valence falls
+    /// fast while arousal is high (off unless set)
+    #[arg(long)]
+    pub distress_alarm: bool,
+
+    /// The valence drop must happen within N seconds
+    #[arg(long, default_value_t = 5)]
+    pub distress_window_secs: u64,
+
+    /// Smallest valence drop (0..1) within the window that counts
+    #[arg(long, default_value_t = 0.3)]
+    pub distress_valence_drop: f32,
+
+    /// Valence must have fallen to this or below (0..1)
+    #[arg(long, default_value_t = 0.35)]
+    pub distress_max_valence: f32,
+
+    /// Arousal must be at least this (0..1)
+    #[arg(long, default_value_t = 0.6)]
+    pub distress_min_arousal: f32,
+
+    /// No second distress event from a sensor within N seconds
+    #[arg(long, default_value_t = 60)]
+    pub distress_cooldown_secs: u64,
+
+    /// POST every distress event as JSON to this URL (repeatable)
+    #[arg(long)]
+    pub distress_webhook: Vec<String>,
+
+    /// ONNX audio-event classifier (cry / scream / glass break / silence)
+    /// run on every audio stream; needs `--features onnx` ("" = off)
+    #[arg(long, default_value = "")]
+    pub audio_event_model_path: String,
+
+    /// Class score (0..1) that raises an audio event
+    #[arg(long, default_value_t = 0.7)]
+    pub audio_event_threshold: f32,
+
+    /// Audio scored per classification, in milliseconds (windows overlap
+    /// by half)
+    #[arg(long, default_value_t = 1000)]
+    pub audio_event_window_ms: u64,
+
+    /// No second event of the same class from a sensor within N seconds
+    #[arg(long, default_value_t = 10)]
+    pub audio_event_cooldown_secs: u64,
+
+    /// Publish sensor events (falls, distress, audio events, ...) to this
+    /// MQTT broker ("" = off)
+    #[arg(long, env = "EVENT_MQTT_HOST", default_value = "")]
+    pub event_mqtt_host: String,
+
+    /// MQTT broker port for sensor events
+    #[arg(long, default_value_t = 1883)]
+    pub event_mqtt_port: u16,
+
+    /// Sensor events are published to `<prefix>/<sensor id>/<kind>`
+    #[arg(long, default_value = "vad/events")]
+    pub event_mqtt_topic: String,
+
+    /// POST every sensor event as JSON to this URL (repeatable)
+    #[arg(long)]
+    pub event_webhook: Vec<String>,
+
+    /// MQTT broker for threshold-crossing alerts and rule registration
+    /// ("" = REST / webhook / WebSocket only)
+    #[arg(long, env = "ALERT_MQTT_HOST", default_value = "")]
+    pub alert_mqtt_host: String,
+
+    /// MQTT broker port for alerts
+    #[arg(long, default_value_t = 1883)]
+    pub alert_mqtt_port: u16,
+
+    /// Alerts are published to `<prefix>/<subscription id>`; rules are
+    /// accepted on `<prefix>/subscribe` and `<prefix>/unsubscribe`
+    #[arg(long, default_value = "vad/alerts")]
+    pub alert_mqtt_topic: String,
+
+    /// MQTT broker for the remote-administration control plane ("" = off)
+    #[arg(long, env = "ADMIN_MQTT_HOST", default_value = "")]
+    pub admin_mqtt_host: String,
+
+    /// MQTT broker port for the control plane
+    #[arg(long, default_value_t = 1883)]
+    pub admin_mqtt_port: u16,
+
+    /// Commands are read from `<prefix>/<instance>[/<tenant>]/cmd`,
+    /// replies go to `.../reply`
+    #[arg(long, default_value = "bridge")]
+    pub admin_mqtt_topic: String,
+
+    /// Control-plane broker username ("" = anonymous)
+    #[arg(long, env = "ADMIN_MQTT_USERNAME", default_value = "")]
+    pub admin_mqtt_username: String,
+
+    /// Control-plane broker password (or set ADMIN_MQTT_PASSWORD env var)
+    #[arg(long, env = "ADMIN_MQTT_PASSWORD", default_value = "")]
+    pub admin_mqtt_password: String,
+
+    /// Name of this bridge instance in control-plane topics and the
+    /// cluster ("" = host name)
+    #[arg(long, env = "INSTANCE_ID", default_value = "")]
+    pub instance_id: String,
+
+    /// Share persona, device overrides and the device registry with other
+    /// instances through this Redis ("" = single instance; needs
+    /// `--features cluster`)
+    #[arg(long, env = "CLUSTER_REDIS_URL", default_value = "")]
+    pub cluster_redis_url: String,
+
+    /// Redis key / channel prefix of the cluster (instances sharing it
+    /// form one cluster)
+    #[arg(long, default_value = "vad-bridge")]
+    pub cluster_namespace: String,
+
+    /// Keep device overrides, persona, sessions and transcripts in this
+    /// PostgreSQL database, shared by every instance using it ("" = off;
+    /// needs `--features postgres`)
+    #[arg(long, env = "POSTGRES_URL", default_value = "")]
+    pub postgres_url: String,
+
+    /// Pick up persona / device changes other instances made in
+    /// PostgreSQL every N seconds
+    #[arg(long, default_value_t = 5)]
+    pub postgres_sync_secs: u64,
+
+    /// Run as one side of a hot-standby pair: only the active side binds
+    /// sockets; the standby takes over when the active one goes silent
+    #[arg(long, value_enum, default_value_t = FailoverRole::Off)]
+    pub failover_role: FailoverRole,
+
+    /// Heartbeat address of the other side of the pair (HOST:PORT)
+    #[arg(long, default_value = "")]
+    pub failover_peer: String,
+
+    /// UDP port failover heartbeats are received on
+    #[arg(long, default_value_t = 9300)]
+    pub failover_port: u16,
+
+    /// Heartbeat period of the failover pair
+    #[arg(long, default_value_t = 500)]
+    pub failover_interval_ms: u64,
+
+    /// The peer is considered gone after this long without a heartbeat
+    #[arg(long, default_value_t = 3000)]
+    pub failover_timeout_ms: u64,
+
+    /// Executable run as `<hook> active` before taking over and
+    /// `<hook> standby` when standing down (e.g. moves the virtual IP)
+    #[arg(long, default_value = "")]
+    pub failover_hook: String,
+
+    /// Unix socket for zero-downtime restarts: take over the bound
+    /// sockets of the bridge listening here, then listen for the next
+    /// one ("" = off)
+    #[arg(long, default_value = "")]
+    pub handover_socket: String,
+
+    /// After handing its sockets over, wait at most N seconds for the
+    /// OpenAI session to go quiet before exiting
+    #[arg(long, default_value_t = 30)]
+    pub handover_drain_secs: u64,
+
+    /// Apply pending schema migrations to `--audio-save-dir` and
+    /// `--postgres-url` (every tenant's, with `--tenants-file`), then exit
+    #[arg(long)]
+    pub migrate_only: bool,
+
+    /// Exit at startup when a critical self-test check fails (ports,
+    /// save dir, models, OpenAI key) instead of starting partially
+    #[arg(long)]
+    pub strict_startup: bool,
+
     // ── OpenAI Realtime API ────────────────────────────────────────────
 
     /// Enable OpenAI Realtime API bridge (streams ESP audio to OpenAI and back)
@@ -124,6 +728,350 @@ You talk to kids aged 6 to 14. Your job is to be their fun robot buddy — chat,
 - Never pretend to be a real person."
     )]
     pub openai_instructions: String,
+
+    /// Read the session instructions from this file instead of
+    /// --openai-instructions.  Supports {{persona}}, {{device_name}},
+    /// {{battery}} and {{emotion}} placeholders (see `prompt.rs`).
+    #[arg(long, default_value = "")]
+    pub openai_instructions_file: String,
+
+    /// Mandatory safety rules prepended to every set of OpenAI
+    /// instructions.  The file must not be group/world-writable.
+    #[arg(long, default_value = "")]
+    pub safety_banner_file: String,
+
+    /// Expected SHA-256 (hex) of the trimmed safety banner ("" = no pin)
+    #[arg(long, default_value = "")]
+    pub safety_banner_sha256: String,
+
+    /// Append every OpenAI instructions change, recording policy change
+    /// and retention delete to this JSONL file ("" = log only)
+    #[arg(long, default_value = "")]
+    pub instructions_audit_log: String,
+
+    /// Transcribed turns remembered per device and re-injected after an
+    /// OpenAI reconnect or device switch (0 = one shared conversation)
+    #[arg(long, default_value_t = 10)]
+    pub conversation_history_turns: usize,
+
+    /// What OpenAI does with ESP audio: spoken replies (`conversation`)
+    /// or transcripts only (`transcribe`)
+    #[arg(long, value_enum, default_value_t = OpenAiMode::Conversation)]
+    pub openai_mode: OpenAiMode,
+
+    /// Model transcribing user speech (e.g. whisper-1, gpt-4o-transcribe)
+    #[arg(long, default_value = "whisper-1")]
+    pub openai_transcription_model: String,
+
+    /// Forward transcripts to this MQTT broker ("" = off)
+    #[arg(long, env = "TRANSCRIPT_MQTT_HOST", default_value = "")]
+    pub transcript_mqtt_host: String,
+
+    /// MQTT broker port for transcripts
+    #[arg(long, default_value_t = 1883)]
+    pub transcript_mqtt_port: u16,
+
+    /// Transcripts are published to `<prefix>/<device>`
+    #[arg(long, default_value = "vad/transcripts")]
+    pub transcript_mqtt_topic: String,
+
+    /// MQTT 5 message expiry of published alerts, events, transcripts and
+    /// admin replies: the broker drops what a consumer has not fetched within
+    /// N seconds (0 = never)
+    #[arg(long, default_value_t = 60)]
+    pub mqtt_expiry_secs: u32,
+
+    /// POST every transcript as JSON to this URL (repeatable)
+    #[arg(long)]
+    pub transcript_webhook: Vec<String>,
+
+    /// Send a WebSocket Ping to OpenAI every N seconds (0 = never)
+    #[arg(long, default_value_t = 15)]
+    pub openai_ping_secs: u64,
+
+    /// Reconnect the OpenAI WebSocket if nothing (events or Pongs) has
+    /// arrived for N seconds (0 = never)
+    #[arg(long, default_value_t = 45)]
+    pub openai_stale_secs: u64,
+
+    /// Strip silence before the first and after the last speech (local
+    /// energy VAD, `--audio-threshold`) from audio committed to OpenAI
+    #[arg(long)]
+    pub openai_trim_silence: bool,
+
+    /// Silence kept at each end when `--openai-trim-silence` is set (ms)
+    #[arg(long, default_value_t = 300)]
+    pub openai_trim_pad_ms: u64,
+
+    /// Directory of canned WAV clips playable via
+    /// `POST /devices/{device}/play/{clip}` ("" = none)
+    #[arg(long, default_value = "")]
+    pub clips_dir: String,
+
+    /// `POST /broadcast/say` reaches ESPs heard from within this many seconds
+    #[arg(long, default_value_t = 120)]
+    pub broadcast_online_secs: u64,
+
+    /// Firmware log lines kept per device for `GET /devices/{device}/telemetry`
+    #[arg(long, default_value_t = 200)]
+    pub telemetry_log_lines: usize,
+
+    /// An ESP is announced offline after N seconds without a heartbeat,
+    /// session control or audio
+    #[arg(long, default_value_t = 30)]
+    pub presence_offline_secs: u64,
+
+    /// Publish device online / offline events to this MQTT broker ("" = off)
+    #[arg(long, env = "PRESENCE_MQTT_HOST", default_value = "")]
+    pub presence_mqtt_host: String,
+
+    /// MQTT broker port for presence events
+    #[arg(long, default_value_t = 1883)]
+    pub presence_mqtt_port: u16,
+
+    /// Presence events are published (retained) to `<prefix>/<device>`
+    #[arg(long, default_value = "vad/presence")]
+    pub presence_mqtt_topic: String,
+
+    /// POST every device online / offline event as JSON to this URL (repeatable)
+    #[arg(long)]
+    pub presence_webhook: Vec<String>,
+
+    /// Write a daily summary (sessions, talk time, emotions, errors,
+    /// OpenAI cost) as `.json` + `.csv` here after every UTC midnight
+    /// ("" = not written)
+    #[arg(long, default_value = "")]
+    pub report_dir: String,
+
+    /// POST every daily summary as JSON to this URL (repeatable)
+    #[arg(long)]
+    pub report_webhook: Vec<String>,
+
+    /// OpenAI price of text input tokens for the daily report (USD per 1M)
+    #[arg(long, default_value_t = 0.6)]
+    pub openai_text_input_price: f64,
+
+    /// OpenAI price of text output tokens (USD per 1M)
+    #[arg(long, default_value_t = 2.4)]
+    pub openai_text_output_price: f64,
+
+    /// OpenAI price of audio input tokens (USD per 1M)
+    #[arg(long, default_value_t = 10.0)]
+    pub openai_audio_input_price: f64,
+
+    /// OpenAI price of audio output tokens (USD per 1M)
+    #[arg(long, default_value_t = 20.0)]
+    pub openai_audio_output_price: f64,
+
+    /// Firmware images pushed to ESPs via `POST /devices/{device}/ota`
+    /// (per-tenant subdirectory when --tenant-id is set)
+    #[arg(long, default_value = "firmware")]
+    pub firmware_dir: String,
+
+    /// Directory of voice command templates, `<command>/*.wav` with
+    /// command stop | volume_down | volume_up ("" = off)
+    #[arg(long, default_value = "")]
+    pub voice_commands_dir: String,
+
+    /// Mean frame distance under which an utterance matches a voice
+    /// command template (lower = stricter)
+    #[arg(long, default_value_t = 5.0)]
+    pub voice_command_threshold: f32,
+
+    /// Volume change per volume_down / volume_up command, in percentage
+    /// points
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub volume_step: u8,
+
+    /// Clip played when an ESP session ends but OpenAI is unreachable
+    /// ("" = stay silent)
+    #[arg(long, default_value = "wifi_sad")]
+    pub offline_clip: String,
+
+    /// Text-to-speech backend for `POST /devices/{device}/say`
+    /// (unset = no TTS)
+    #[arg(long, value_enum)]
+    pub tts_backend: Option<TtsKind>,
+
+    /// API key for the TTS backend (or set TTS_API_KEY env var)
+    #[arg(long, env = "TTS_API_KEY", default_value = "")]
+    pub tts_api_key: String,
+
+    /// TTS voice: Azure voice name or ElevenLabs voice id
+    /// ("" = backend default)
+    #[arg(long, default_value = "")]
+    pub tts_voice: String,
+
+    /// Azure Speech region for `--tts-backend azure`
+    #[arg(long, default_value = "eastus")]
+    pub azure_speech_region: String,
+
+    /// ElevenLabs model for `--tts-backend elevenlabs`
+    #[arg(long, default_value = "eleven_turbo_v2_5")]
+    pub elevenlabs_model: String,
+
+    /// `POST /devices/{device}/diagnostics` fails when the looped-back
+    /// test tone arrives later than this (ms)
+    #[arg(long, default_value_t = 400)]
+    pub diag_max_latency_ms: u64,
+
+    /// `POST /devices/{device}/diagnostics` fails when the looped-back
+    /// test tone is quieter than this (dBFS)
+    #[arg(long, default_value_t = -40.0, allow_negative_numbers = true)]
+    pub diag_min_level_dbfs: f32,
+}
+
+/// Top-level subcommands.
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Run the bridge: UDP receivers, VAD, REST API, OpenAI (default)
+    Serve(Box<Config>),
+    /// Forward UDP sensor packets to an MQTT broker (no VAD)
+    Gateway(GatewayArgs),
+    /// Generate synthetic sensor / audio traffic against a running bridge
+    Simulate(SimulateArgs),
+    /// Replay a recorded sensor-vector file (to a bridge, or offline through VAD)
+    Replay(ReplayArgs),
+    /// Check configuration, files, ports and credentials without starting
+    Validate(Box<Config>),
+    /// Fit the linear emotional weights from labeled recordings
+    Calibrate(CalibrateArgs),
+}
+
+/// Arguments for `calibrate`.
+#[derive(Args, Debug, Clone)]
+pub struct CalibrateArgs {
+    /// Labeled recording (.csv or .jsonl) with sensor vectors + target V/A/D
+    #[arg(long)]
+    pub input: String,
+
+    /// Output weights TOML (load with --emotion-weights)
+    #[arg(long, default_value = "emotion_weights.toml")]
+    pub output: String,
+
+    /// Persona active when the labels were recorded
+    #[arg(long, value_enum, default_value_t = PersonaTrait::Obedient)]
+    pub persona: PersonaTrait,
+
+    /// Ridge (L2) regularisation strength
+    #[arg(long, default_value_t = 1e-3)]
+    pub ridge: f64,
+}
+
+/// Arguments for `gateway`.
+#[derive(Args, Debug, Clone)]
+pub struct GatewayArgs {
+    /// UDP listen address
+    #[arg(long, default_value = "0.0.0.0")]
+    pub host: String,
+
+    /// UDP listen port (binary sensor packets)
+    #[arg(long, default_value_t = 9002)]
+    pub port: u16,
+
+    /// UDP receive buffer size (SO_RCVBUF)
+    #[arg(long, default_value_t = 4 * 1024 * 1024)]
+    pub recv_buf_size: usize,
+
+    /// Number of receiver tasks
+    #[arg(long, default_value_t = 2)]
+    pub recv_threads: usize,
+
+    /// MQTT broker host
+    #[arg(long, env = "MQTT_HOST", default_value = "127.0.0.1")]
+    pub mqtt_host: String,
+
+    /// MQTT broker port
+    #[arg(long, env = "MQTT_PORT", default_value_t = 1883)]
+    pub mqtt_port: u16,
+
+    /// MQTT client id
+    #[arg(long, default_value = "vad-sensor-bridge-gw")]
+    pub mqtt_client_id: String,
+
+    /// Topic prefix; packets go to `<prefix>/<sensor_id>`
+    #[arg(long, default_value = "vad/sensors")]
+    pub mqtt_topic_prefix: String,
+
+    /// Publish QoS (0, 1 or 2)
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=2))]
+    pub mqtt_qos: u8,
+
+    /// MQTT 5 message expiry of forwarded packets in seconds (0 = never)
+    #[arg(long, default_value_t = 0)]
+    pub mqtt_expiry_secs: u32,
+
+    /// Outgoing publish queue; packets are dropped (and counted) when full
+    #[arg(long, default_value_t = 65536)]
+    pub mqtt_queue: usize,
+
+    /// Stats logging interval in seconds (0 = disabled)
+    #[arg(long, default_value_t = 5)]
+    pub stats_interval_secs: u64,
+}
+
+/// What `simulate` sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SimulateMode {
+    /// 10-channel sensor vectors (emotional VAD)
+    Sensor,
+    /// 16 kHz s16 PCM tone bursts (audio VAD)
+    Audio,
+}
+
+/// Arguments for `simulate`.
+#[derive(Args, Debug, Clone)]
+pub struct SimulateArgs {
+    /// Bridge sensor port to send to
+    #[arg(long, default_value = "127.0.0.1:9002")]
+    pub target: String,
+
+    /// Packet kind to generate
+    #[arg(long, value_enum, default_value_t = SimulateMode::Sensor)]
+    pub mode: SimulateMode,
+
+    /// Number of simulated sensors (ids 1..=N)
+    #[arg(long, default_value_t = 1)]
+    pub sensors: u32,
+
+    /// Packets per second, per sensor
+    #[arg(long, default_value_t = 10.0)]
+    pub rate: f64,
+
+    /// Run time in seconds (0 = until interrupted)
+    #[arg(long, default_value_t = 10)]
+    pub duration_secs: u64,
+
+    /// Run on a simulated clock: send back-to-back instead of at --rate,
+    /// with virtual timestamps from the UNIX epoch (same packets every run)
+    #[arg(long, default_value_t = false)]
+    pub fast: bool,
+}
+
+/// Arguments for `replay`.
+#[derive(Args, Debug, Clone)]
+pub struct ReplayArgs {
+    /// Recording (.csv or .jsonl) of sensor vectors; V/A/D labels are ignored
+    #[arg(long)]
+    pub input: String,
+
+    /// Send rows as UDP packets to this bridge address instead of
+    /// evaluating offline
+    #[arg(long)]
+    pub target: Option<String>,
+
+    /// Packets per second when sending to `--target`; offline, the
+    /// simulated time between rows (smoother decay, reset gap)
+    #[arg(long, default_value_t = 10.0)]
+    pub rate: f64,
+
+    /// Persona for offline evaluation
+    #[arg(long, value_enum, default_value_t = PersonaTrait::Obedient)]
+    pub persona: PersonaTrait,
+
+    /// Linear weights TOML for offline evaluation (default: built-in)
+    #[arg(long, default_value = "")]
+    pub emotion_weights: String,
 }
 
 impl Config {
@@ -150,6 +1098,23 @@ impl Config {
     pub fn resolved_proc_threads(&self) -> usize {
         if self.proc_threads == 0 { num_cpus() } else { self.proc_threads }
     }
+
+    pub fn resolved_audio_proc_threads(&self) -> usize {
+        if self.audio_proc_threads == 0 { num_cpus() } else { self.audio_proc_threads }
+    }
+
+    pub fn resolved_instance_id(&self) -> String {
+        if !self.instance_id.is_empty() {
+            return self.instance_id.clone();
+        }
+        std::env
+            ::var("HOSTNAME")
+            .ok()
+            .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+            .map(|h| h.trim().to_string())
+            .filter(|h| !h.is_empty())
+            .unwrap_or_else(|| "vad-bridge".to_string())
+    }
 }
 
 fn num_cpus() -> usize {
@@ -0,0 +1,221 @@
+use serde::Serialize;
+use std::collections::{ HashMap, VecDeque };
+use std::sync::{ Arc, RwLock };
+use tokio::sync::broadcast;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Per-device conversation history
+// ─────────────────────────────────────────────────────────────────────
+//
+//  The OpenAI Realtime session is shared by every ESP client, and its
+//  server-side conversation is lost whenever the WebSocket reconnects.
+//  We keep the last N transcribed turns per device (keyed by the same
+//  name as `{{device_name}}`: MAC or UDP address) and replay them as
+//  `conversation.item.create` events when:
+//
+//    * the session reconnects (active device's history), or
+//    * a different device is wired to the session (the previous
+//      device's items are deleted first, so devices never hear each
+//      other's conversations).
+//
+//  `DELETE /devices/{id}/conversation` clears a device's history and,
+//  if it is the active device, the live OpenAI conversation too.
+
+/// Who said a turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+/// One transcribed utterance.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Turn {
+    pub role: Role,
+    pub text: String,
+    /// Who spoke a user turn, when speaker diarization recognised them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+}
+
+/// Thread-safe history store shared between the OpenAI reader, the ESP
+/// control handlers and the REST API.  Clone-friendly (Arc inside).
+#[derive(Clone)]
+pub struct ConversationStore {
+    max_turns: usize,
+    inner: Arc<RwLock<Inner>>,
+    resets: broadcast::Sender<String>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Device currently wired to the OpenAI session
+    active: Option<String>,
+    histories: HashMap<String, VecDeque<Turn>>,
+}
+
+impl ConversationStore {
+    /// Keep at most `max_turns` turns per device (0 = disabled).
+    pub fn new(max_turns: usize) -> Self {
+        let (resets, _) = broadcast::channel(16);
+        Self {
+            max_turns,
+            inner: Arc::new(RwLock::new(Inner::default())),
+            resets,
+        }
+    }
+
+    /// Whether history is kept at all.
+    pub fn enabled(&self) -> bool {
+        self.max_turns > 0
+    }
+
+    /// Mark `device` as wired to the session.  Returns `true` if this
+    /// is a different device from the previous one.
+    pub fn activate(&self, device: &str) -> bool {
+        let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        if inner.active.as_deref() == Some(device) {
+            return false;
+        }
+        inner.active = Some(device.to_string());
+        true
+    }
+
+    /// Append a turn to the active device's history (dropping the oldest
+    /// beyond `max_turns`), tagged with `speaker` if known.  No-op when
+    /// disabled or no device is wired.
+    pub fn record(&self, role: Role, text: &str, speaker: Option<&str>) {
+        let text = text.trim();
+        if !self.enabled() || text.is_empty() {
+            return;
+        }
+        let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let Some(device) = inner.active.clone() else {
+            return;
+        };
+        let history = inner.histories.entry(device).or_default();
+        history.push_back(Turn { role, text: text.to_string(), speaker: speaker.map(str::to_string) });
+        while history.len() > self.max_turns {
+            history.pop_front();
+        }
+    }
+
+    /// Replace `device`'s history with `turns` (oldest first, the last
+    /// `max_turns` kept), e.g. loaded from the shared store.
+    pub fn restore(&self, device: &str, turns: Vec<Turn>) {
+        if !self.enabled() {
+            return;
+        }
+        let skip = turns.len().saturating_sub(self.max_turns);
+        let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        inner.histories.insert(device.to_string(), turns.into_iter().skip(skip).collect());
+    }
+
+    /// History for `device`, oldest first.
+    pub fn history(&self, device: &str) -> Vec<Turn> {
+        let inner = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        inner.histories
+            .get(device)
+            .map(|h| h.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Replace the text of `device`'s turns that read exactly like one of
+    /// `texts` with `replacement`; returns how many.
+    pub fn redact(&self, device: &str, texts: &[String], replacement: &str) -> usize {
+        let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let Some(history) = inner.histories.get_mut(device) else {
+            return 0;
+        };
+        let mut redacted = 0;
+        for turn in history.iter_mut().filter(|t| texts.iter().any(|x| x.trim() == t.text)) {
+            turn.text = replacement.to_string();
+            redacted += 1;
+        }
+        redacted
+    }
+
+    /// History for the active device (empty if none is wired).
+    pub fn active_history(&self) -> Vec<Turn> {
+        let active = self.inner.read().unwrap_or_else(|e| e.into_inner()).active.clone();
+        active.map(|d| self.history(&d)).unwrap_or_default()
+    }
+
+    /// Forget `device`'s history and notify the live session.  Returns
+    /// `true` if there was anything to clear.
+    pub fn reset(&self, device: &str) -> bool {
+        let removed = {
+            let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+            inner.histories.remove(device).is_some_and(|h| !h.is_empty())
+        };
+        // No subscribers is fine
+        let _ = self.resets.send(device.to_string());
+        removed
+    }
+
+    /// Device currently wired to the session (tracked even when
+    /// history is disabled).
+    pub fn active_device(&self) -> Option<String> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner()).active.clone()
+    }
+
+    /// Whether `device` is the one wired to the session.
+    pub fn is_active(&self, device: &str) -> bool {
+        self.inner.read().unwrap_or_else(|e| e.into_inner()).active.as_deref() == Some(device)
+    }
+
+    /// Receive the device name of every [`reset`](Self::reset).
+    pub fn subscribe_resets(&self) -> broadcast::Receiver<String> {
+        self.resets.subscribe()
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_is_bounded_per_device() {
+        let store = ConversationStore::new(2);
+        store.record(Role::User, "dropped: nobody wired", None);
+        assert!(store.activate("a"));
+        assert!(!store.activate("a"));
+        store.record(Role::User, "one", None);
+        store.record(Role::Assistant, "two", None);
+        store.record(Role::User, "three", None);
+        store.record(Role::User, "   ", None);
+
+        store.activate("b");
+        store.record(Role::User, "hello b", None);
+
+        let a = store.history("a");
+        assert_eq!(a.len(), 2);
+        assert_eq!((a[0].role, a[0].text.as_str()), (Role::Assistant, "two"));
+        assert_eq!(store.active_history().len(), 1);
+    }
+
+    #[test]
+    fn test_reset_clears_and_notifies() {
+        let store = ConversationStore::new(4);
+        let mut resets = store.subscribe_resets();
+        store.activate("a");
+        store.record(Role::User, "hi", None);
+        assert!(store.reset("a"));
+        assert!(!store.reset("a"));
+        assert!(store.history("a").is_empty());
+        assert_eq!(resets.try_recv().unwrap(), "a");
+    }
+
+    #[test]
+    fn test_disabled_keeps_nothing() {
+        let store = ConversationStore::new(0);
+        store.activate("a");
+        store.record(Role::User, "hi", None);
+        assert!(store.history("a").is_empty());
+    }
+}
@@ -0,0 +1,523 @@
+use crate::conversation::ConversationStore;
+use crate::session_log::SessionLogs;
+use crate::speakers::Diarizer;
+use crate::store::SharedStore;
+use serde::{ Deserialize, Serialize };
+use std::io::Write;
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, Mutex };
+use tokio::io::{ AsyncRead, AsyncReadExt };
+use tokio::sync::mpsc;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Per-device data: export + erasure
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Everything the bridge keeps about one device (MAC or `ip:port`):
+//
+//    recordings     session WAVs, Ogg monitoring copies, cached
+//                   transcodes and JSONL session logs in
+//                   `--audio-save-dir`.  Their names carry the
+//                   device's IP, not its name, so each file is listed in
+//                   `<dir>/device_files.jsonl` as it is written:
+//                     {"device":"aa:bb:cc:dd:ee:ff","path":"recordings/esp_..wav"}
+//    memories       conversation history (the transcripts), speaker
+//                   voice prints and names
+//    session rows   recent session records (`GET /sessions`)
+//
+//  `GET /devices/{device}/export` zips all of it (stored, uncompressed):
+//  the files under `recordings/`, plus `conversation.json`,
+//  `speakers.json` and `sessions.json`.  The archive is streamed, one
+//  file at a time, so its size is not bounded by memory; without ZIP64
+//  it must stay under 4 GiB and 65535 entries, and a larger export is
+//  refused up front (or aborted, should a file grow meanwhile).  `DELETE /devices/{device}/data`
+//  deletes the files, drops them from the index and forgets the rest.
+//  A session still running keeps its row (and writes its WAV) until it
+//  ends.  With `--postgres-url` the device's stored sessions and
+//  transcripts are deleted too.  Transcripts already forwarded to MQTT /
+//  webhooks are out of the bridge's reach.
+
+/// File index name inside `--audio-save-dir`.
+pub const INDEX_FILE: &str = "device_files.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    device: String,
+    path: String,
+}
+
+/// Files written per device, persisted as [`INDEX_FILE`].
+/// Clone-friendly; `Default` keeps the index in memory only.
+#[derive(Clone, Default)]
+pub struct DeviceFiles {
+    index_path: Option<Arc<PathBuf>>,
+    entries: Arc<Mutex<Vec<IndexEntry>>>,
+}
+
+impl DeviceFiles {
+    /// Load the index in `dir` (empty if there is none yet).
+    pub fn load(dir: &str) -> Self {
+        let index_path = Path::new(dir).join(INDEX_FILE);
+        let entries: Vec<IndexEntry> = std::fs
+            ::read_to_string(&index_path)
+            .map(|text|
+                text
+                    .lines()
+                    .filter_map(|l| serde_json::from_str(l).ok())
+                    .collect()
+            )
+            .unwrap_or_default();
+        Self { index_path: Some(Arc::new(index_path)), entries: Arc::new(Mutex::new(entries)) }
+    }
+
+    /// Note that `path` holds data of `device`.
+    pub fn record(&self, device: &str, path: &str) {
+        let entry = IndexEntry { device: device.to_ascii_lowercase(), path: path.to_string() };
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(ref index_path) = self.index_path {
+            if let Err(e) = append_line(index_path, &entry) {
+                warn!(path = %index_path.display(), error = %e, "failed to update device file index");
+            }
+        }
+        entries.push(entry);
+    }
+
+    /// Files listed for `device`, oldest first.
+    pub fn files(&self, device: &str) -> Vec<String> {
+        let device = device.to_ascii_lowercase();
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .filter(|e| e.device == device)
+            .map(|e| e.path.clone())
+            .collect()
+    }
+
+    /// Device whose files include `path`, if it is listed.
+    pub fn device_of(&self, path: &str) -> Option<String> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .find(|e| e.path == path)
+            .map(|e| e.device.clone())
+    }
+
+    /// Every listed file as `(device, path)`, oldest first.
+    pub fn all(&self) -> Vec<(String, String)> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .map(|e| (e.device.clone(), e.path.clone()))
+            .collect()
+    }
+
+    /// Drop `device` from the index (rewritten in place); returns its files.
+    pub fn forget(&self, device: &str) -> Vec<String> {
+        let device = device.to_ascii_lowercase();
+        self.remove_where(|e| e.device == device)
+    }
+
+    /// Drop `paths` from the index (rewritten in place).
+    pub fn forget_paths(&self, paths: &[String]) {
+        self.remove_where(|e| paths.contains(&e.path));
+    }
+
+    fn remove_where(&self, pred: impl Fn(&IndexEntry) -> bool) -> Vec<String> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let (removed, kept): (Vec<IndexEntry>, Vec<IndexEntry>) = entries.drain(..).partition(|e| pred(e));
+        *entries = kept;
+        if let Some(ref index_path) = self.index_path {
+            if let Err(e) = rewrite(index_path, &entries) {
+                warn!(path = %index_path.display(), error = %e, "failed to rewrite device file index");
+            }
+        }
+        removed
+            .into_iter()
+            .map(|e| e.path)
+            .collect()
+    }
+}
+
+fn append_line(path: &Path, entry: &IndexEntry) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)
+}
+
+/// Replace the index with `entries` (temp file + rename).
+fn rewrite(path: &Path, entries: &[IndexEntry]) -> std::io::Result<()> {
+    let tmp = path.with_extension("jsonl.tmp");
+    let mut text = String::new();
+    for entry in entries {
+        text.push_str(&serde_json::to_string(entry)?);
+        text.push('\n');
+    }
+    std::fs::write(&tmp, text)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// What `DELETE /devices/{device}/data` removed.
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgeReport {
+    pub device: String,
+    /// Files deleted from disk
+    pub files: usize,
+    /// Listed files that were already gone
+    pub missing_files: usize,
+    pub sessions: usize,
+    pub turns: usize,
+    pub speakers: usize,
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    /// The archive would need ZIP64
+    TooLarge { entries: usize, bytes: u64 },
+    Failed(String),
+}
+
+impl std::fmt::Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportError::TooLarge { entries, bytes } =>
+                write!(f, "export of {entries} files / {bytes} bytes exceeds the ZIP limits (65535 files, 4 GiB)"),
+            ExportError::Failed(e) => write!(f, "export failed: {e}"),
+        }
+    }
+}
+
+/// Chunks of a streamed export; an `Err` aborts the download.
+pub type ExportStream = mpsc::Receiver<std::io::Result<Vec<u8>>>;
+
+/// Export / erasure over every per-device store.  Clone-friendly.
+#[derive(Clone)]
+pub struct DeviceData {
+    files: DeviceFiles,
+    session_logs: SessionLogs,
+    conversations: ConversationStore,
+    speakers: Diarizer,
+    store: SharedStore,
+}
+
+impl DeviceData {
+    pub fn new(files: DeviceFiles, session_logs: SessionLogs, conversations: ConversationStore, speakers: Diarizer) -> Self {
+        Self { files, session_logs, conversations, speakers, store: SharedStore::disabled() }
+    }
+
+    /// Also erase devices from `store`.
+    pub fn with_store(mut self, store: SharedStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// ZIP archive of everything stored about `device`, streamed.
+    pub async fn export(&self, device: &str) -> Result<ExportStream, ExportError> {
+        let device = device.to_ascii_lowercase();
+        let json = |value: serde_json::Result<Vec<u8>>| value.map_err(|e| ExportError::Failed(e.to_string()));
+        let documents = vec![
+            ("conversation.json".to_string(), json(serde_json::to_vec_pretty(&self.conversations.history(&device)))?),
+            ("speakers.json".to_string(), json(serde_json::to_vec_pretty(&self.speakers.list(&device)))?),
+            ("sessions.json".to_string(), json(serde_json::to_vec_pretty(&self.session_logs.records(&device)))?)
+        ];
+        let mut files = Vec::new();
+        for path in self.files.files(&device) {
+            let name = Path::new(&path)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone());
+            match tokio::fs::metadata(&path).await {
+                Ok(meta) => files.push((format!("recordings/{name}"), path, meta.len())),
+                Err(e) => warn!(path = %path, error = %e, "export: skipping unreadable file"),
+            }
+        }
+        let sizes: Vec<(&str, u64)> = documents
+            .iter()
+            .map(|(name, data)| (name.as_str(), data.len() as u64))
+            .chain(files.iter().map(|(name, _, len)| (name.as_str(), *len)))
+            .collect();
+        check_zip_limits(&sizes)?;
+        info!(device = %device, entries = sizes.len(), bytes = zip_len(&sizes), "📦 device data export started");
+
+        let (tx, rx) = mpsc::channel(4);
+        tokio::spawn(async move {
+            let mut zip = ZipWriter::new(tx.clone());
+            let written = async {
+                for (name, data) in &documents {
+                    zip.add(name, data.as_slice()).await?;
+                }
+                for (name, path, _) in &files {
+                    zip.add(name, tokio::fs::File::open(path).await?).await?;
+                }
+                zip.finish().await
+            };
+            if let Err(e) = written.await {
+                warn!(device = %device, error = %e, "device data export aborted");
+                let _ = tx.send(Err(e)).await;
+            }
+        });
+        Ok(rx)
+    }
+
+    /// Delete everything stored about `device`.
+    pub async fn purge(&self, device: &str) -> PurgeReport {
+        let device = device.to_ascii_lowercase();
+        let (mut files, mut missing_files) = (0, 0);
+        for path in self.files.forget(&device) {
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => {
+                    files += 1;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    missing_files += 1;
+                }
+                Err(e) => warn!(path = %path, error = %e, "purge: failed to delete file"),
+            }
+        }
+        let turns = self.conversations.history(&device).len();
+        self.conversations.reset(&device);
+        self.store.forget(&device);
+        let report = PurgeReport {
+            files,
+            missing_files,
+            sessions: self.session_logs.forget(&device),
+            turns,
+            speakers: self.speakers.forget(&device),
+            device,
+        };
+        info!(
+            device = %report.device,
+            files = report.files,
+            sessions = report.sessions,
+            turns = report.turns,
+            speakers = report.speakers,
+            "🗑️ device data purged"
+        );
+        report
+    }
+}
+
+// ── ZIP (stored entries, no compression) ─────────────────────────────
+//
+//  Each entry is a local header (flag bit 3: CRC and sizes follow the
+//  data), the data and a data descriptor, so files are read once, in
+//  chunks, while the archive is sent.
+
+/// 1980-01-01 00:00 in MS-DOS format.
+const DOS_DATE: u16 = (1 << 5) | 1;
+/// Largest size or offset without ZIP64.
+const ZIP_MAX: u64 = u32::MAX as u64;
+/// Bytes read per chunk of a streamed file.
+const ZIP_CHUNK: usize = 64 * 1024;
+
+/// Archive size for `entries` (name, size).
+fn zip_len(entries: &[(&str, u64)]) -> u64 {
+    let per_entry: u64 = entries
+        .iter()
+        .map(|(name, size)| 30 + 16 + 46 + 2 * (name.len() as u64) + size)
+        .sum();
+    per_entry + 22
+}
+
+/// Refuse archives that would need ZIP64.
+fn check_zip_limits(entries: &[(&str, u64)]) -> Result<(), ExportError> {
+    let bytes = zip_len(entries);
+    if entries.len() > (u16::MAX as usize) || bytes > ZIP_MAX {
+        return Err(ExportError::TooLarge { entries: entries.len(), bytes });
+    }
+    Ok(())
+}
+
+/// Streaming writer of a stored ZIP archive into an [`ExportStream`].
+struct ZipWriter {
+    out: mpsc::Sender<std::io::Result<Vec<u8>>>,
+    offset: u64,
+    central: Vec<u8>,
+    entries: u16,
+}
+
+impl ZipWriter {
+    fn new(out: mpsc::Sender<std::io::Result<Vec<u8>>>) -> Self {
+        Self { out, offset: 0, central: Vec::new(), entries: 0 }
+    }
+
+    /// Send `bytes`, failing once the archive outgrows 4 GiB.
+    async fn send(&mut self, bytes: Vec<u8>) -> std::io::Result<()> {
+        self.offset += bytes.len() as u64;
+        if self.offset > ZIP_MAX {
+            return Err(std::io::Error::other("archive grew past 4 GiB while streaming"));
+        }
+        self.out.send(Ok(bytes)).await.map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    /// Append `name`, reading its data from `data` to the end.
+    async fn add(&mut self, name: &str, mut data: impl AsyncRead + Unpin) -> std::io::Result<()> {
+        self.entries = self.entries.checked_add(1).ok_or_else(|| std::io::Error::other("more than 65535 entries"))?;
+        let offset = self.offset as u32;
+        let mut header = 0x04034b50u32.to_le_bytes().to_vec();
+        header.extend_from_slice(&file_fields(name, 0, 0));
+        header.extend_from_slice(name.as_bytes());
+        self.send(header).await?;
+
+        let (mut crc, mut size) = (!0u32, 0u64);
+        loop {
+            let mut chunk = vec![0u8; ZIP_CHUNK];
+            let n = data.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            chunk.truncate(n);
+            crc = crc32_update(crc, &chunk);
+            size += n as u64;
+            self.send(chunk).await?;
+        }
+        let (crc, size) = (!crc, size as u32);
+
+        // Data descriptor
+        let mut descriptor = 0x08074b50u32.to_le_bytes().to_vec();
+        descriptor.extend_from_slice(&crc.to_le_bytes());
+        descriptor.extend_from_slice(&size.to_le_bytes());
+        descriptor.extend_from_slice(&size.to_le_bytes());
+        self.send(descriptor).await?;
+
+        // Central directory record
+        self.central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        self.central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        self.central.extend_from_slice(&file_fields(name, size, crc));
+        self.central.extend_from_slice(&[0u8; 10]); // comment length, disk, internal + external attributes
+        self.central.extend_from_slice(&offset.to_le_bytes());
+        self.central.extend_from_slice(name.as_bytes());
+        Ok(())
+    }
+
+    /// Write the central directory and its end record.
+    async fn finish(mut self) -> std::io::Result<()> {
+        let central_offset = self.offset as u32;
+        let central = std::mem::take(&mut self.central);
+        let mut end = 0x06054b50u32.to_le_bytes().to_vec();
+        end.extend_from_slice(&[0u8; 4]); // disk numbers
+        end.extend_from_slice(&self.entries.to_le_bytes());
+        end.extend_from_slice(&self.entries.to_le_bytes());
+        end.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        end.extend_from_slice(&central_offset.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.send(central).await?;
+        self.send(end).await
+    }
+}
+
+/// Fields shared by local and central headers: version needed .. extra length.
+fn file_fields(name: &str, size: u32, crc: u32) -> Vec<u8> {
+    let mut f = Vec::with_capacity(26);
+    f.extend_from_slice(&20u16.to_le_bytes()); // version needed
+    f.extend_from_slice(&0x0808u16.to_le_bytes()); // flags: data descriptor, UTF-8 names
+    f.extend_from_slice(&0u16.to_le_bytes()); // method: stored
+    f.extend_from_slice(&0u16.to_le_bytes()); // time
+    f.extend_from_slice(&DOS_DATE.to_le_bytes());
+    f.extend_from_slice(&crc.to_le_bytes());
+    f.extend_from_slice(&size.to_le_bytes()); // compressed
+    f.extend_from_slice(&size.to_le_bytes()); // uncompressed
+    f.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    f.extend_from_slice(&0u16.to_le_bytes()); // extra length
+    f
+}
+
+/// Running CRC-32 (IEEE), as ZIP uses: start at `!0`, invert the result.
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn collect(mut stream: ExportStream) -> Vec<u8> {
+        let mut out = Vec::new();
+        while let Some(chunk) = stream.recv().await {
+            out.extend(chunk.unwrap());
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn test_zip_layout() {
+        assert_eq!(!crc32_update(!0, b"123456789"), 0xcbf43926);
+
+        let (tx, rx) = mpsc::channel(4);
+        let reader = tokio::spawn(collect(rx));
+        let mut zip = ZipWriter::new(tx);
+        zip.add("a.txt", &b"hello"[..]).await.unwrap();
+        // Longer than one read chunk
+        let big = vec![7u8; ZIP_CHUNK + 10];
+        zip.add("b/c.bin", big.as_slice()).await.unwrap();
+        zip.finish().await.unwrap();
+        let zip = reader.await.unwrap();
+        assert_eq!(zip.len() as u64, zip_len(&[("a.txt", 5), ("b/c.bin", big.len() as u64)]));
+
+        assert_eq!(&zip[..4], b"PK\x03\x04");
+        assert_eq!(&zip[30..35], b"a.txt");
+        assert_eq!(&zip[35..40], b"hello");
+        // Data descriptor: CRC and size of "hello"
+        assert_eq!(&zip[40..44], b"PK\x07\x08");
+        assert_eq!(u32::from_le_bytes(zip[44..48].try_into().unwrap()), !crc32_update(!0, b"hello"));
+        assert_eq!(u32::from_le_bytes(zip[48..52].try_into().unwrap()), 5);
+        // End of central directory: 2 entries, central directory right after the data
+        let eocd = &zip[zip.len() - 22..];
+        assert_eq!(&eocd[..4], b"PK\x05\x06");
+        assert_eq!(u16::from_le_bytes([eocd[10], eocd[11]]), 2);
+        let cd_offset = u32::from_le_bytes(eocd[16..20].try_into().unwrap()) as usize;
+        assert_eq!(cd_offset, (30 + 5 + 5 + 16) + (30 + 7 + big.len() + 16));
+        // Second central record: real size and the second local header's offset
+        let second = cd_offset + 46 + 5;
+        assert_eq!(&zip[second..second + 4], b"PK\x01\x02");
+        assert_eq!(u32::from_le_bytes(zip[second + 24..second + 28].try_into().unwrap()) as usize, big.len());
+        assert_eq!(u32::from_le_bytes(zip[second + 42..second + 46].try_into().unwrap()), 30 + 5 + 5 + 16);
+    }
+
+    #[test]
+    fn test_zip_limits() {
+        assert!(check_zip_limits(&[("a.wav", 1 << 20)]).is_ok());
+        let huge = [("a.wav", ZIP_MAX / 2), ("b.wav", ZIP_MAX / 2)];
+        assert!(matches!(check_zip_limits(&huge), Err(ExportError::TooLarge { entries: 2, .. })));
+        let many = vec![("x", 0u64); 70_000];
+        assert!(check_zip_limits(&many).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_index_survives_reload_and_purge_deletes_files() {
+        let dir = std::env::temp_dir().join(format!("vad-device-data-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let wav = dir.join("esp_10_0_0_7_20260101_200000.wav");
+        std::fs::write(&wav, b"RIFF").unwrap();
+
+        let files = DeviceFiles::load(dir.to_str().unwrap());
+        files.record("AA:BB:CC:DD:EE:FF", wav.to_str().unwrap());
+        files.record("10.0.0.9:5000", "elsewhere.wav");
+        let files = DeviceFiles::load(dir.to_str().unwrap());
+        assert_eq!(files.files("aa:bb:cc:dd:ee:ff"), [wav.to_str().unwrap()]);
+
+        let data = DeviceData::new(files.clone(), SessionLogs::new(None, DeviceFiles::default()), ConversationStore::new(8), Diarizer::default());
+        let zip = collect(data.export("aa:bb:cc:dd:ee:ff").await.unwrap()).await;
+        assert!(zip.windows(4).any(|w| w == b"RIFF"));
+
+        let report = data.purge("aa:bb:cc:dd:ee:ff").await;
+        assert_eq!((report.files, report.missing_files), (1, 0));
+        assert!(!wav.exists());
+        let reloaded = DeviceFiles::load(dir.to_str().unwrap());
+        assert!(reloaded.files("aa:bb:cc:dd:ee:ff").is_empty());
+        assert_eq!(reloaded.files("10.0.0.9:5000"), ["elsewhere.wav"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,355 @@
+use crate::devices::{ DeviceProfile, Location };
+use crate::persona::PersonaTrait;
+use serde::{ Deserialize, Serialize };
+use std::collections::HashMap;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Bulk device import (provisioning manifests)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  `POST /devices/import` provisions a whole site in one request.  The
+//  manifest is either CSV (`Content-Type: text/csv`, header row, any
+//  column order) or a JSON array of objects with the same fields:
+//
+//    mac        required; `aa:bb:cc:dd:ee:ff`, `AA-BB-...` or bare hex
+//    name       display name (≤ 64 chars)
+//    tenant     must be this bridge's tenant (multi-tenant directory:
+//               required, picks the tenant's registry)
+//    persona    obedient | mischievous | cute | stubborn
+//    sensor_id  sensor id of the device's sensor packets
+//    site       site (building) id, lower-cased; `a-z 0-9 - _ .`
+//    room       room within the site (needs a site)
+//
+//  Every row is checked before anything is stored: one bad row (or a
+//  MAC / sensor id listed twice) rejects the whole manifest with the
+//  offending rows, so a half-provisioned site never exists.  Rows are
+//  numbered by CSV line or 1-based JSON array position.
+
+/// Longest display name accepted.
+const MAX_NAME_CHARS: usize = 64;
+
+/// Columns a CSV manifest may have.
+const COLUMNS: [&str; 7] = ["mac", "name", "tenant", "persona", "sensor_id", "site", "room"];
+
+/// One manifest entry as sent, before validation.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestRow {
+    pub mac: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub tenant: Option<String>,
+    #[serde(default)]
+    pub persona: Option<String>,
+    #[serde(default)]
+    pub sensor_id: Option<u32>,
+    #[serde(default)]
+    pub site: Option<String>,
+    #[serde(default)]
+    pub room: Option<String>,
+}
+
+/// A problem with one row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RowError {
+    pub row: usize,
+    pub error: String,
+}
+
+/// Why a manifest was rejected (served as the 400 body).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImportError {
+    pub error: String,
+    pub rows: Vec<RowError>,
+}
+
+impl ImportError {
+    fn whole(error: impl Into<String>) -> Self {
+        Self { error: error.into(), rows: Vec::new() }
+    }
+
+    fn rows(rows: Vec<RowError>) -> Self {
+        Self { error: format!("{} invalid row(s), nothing imported", rows.len()), rows }
+    }
+}
+
+/// Which tenants a manifest may name.
+pub enum TenantScope<'a> {
+    /// One bridge with this tenant id ("" = single tenant): rows may
+    /// omit the tenant or repeat this one
+    Bridge(&'a str),
+    /// The multi-tenant directory: every row names one of these
+    Directory(&'a [String]),
+}
+
+/// Parse a manifest body: CSV when `csv`, else a JSON array.  Returns
+/// rows with their row numbers.
+pub fn parse(body: &[u8], csv: bool) -> Result<Vec<(usize, ManifestRow)>, ImportError> {
+    let rows = if csv {
+        let text = std::str::from_utf8(body).map_err(|_| ImportError::whole("manifest is not UTF-8"))?;
+        parse_csv(text)?
+    } else {
+        let rows: Vec<ManifestRow> = serde_json
+            ::from_slice(body)
+            .map_err(|e| ImportError::whole(format!("invalid JSON manifest: {e}")))?;
+        rows.into_iter()
+            .enumerate()
+            .map(|(i, row)| (i + 1, row))
+            .collect()
+    };
+    if rows.is_empty() {
+        return Err(ImportError::whole("manifest has no devices"));
+    }
+    Ok(rows)
+}
+
+fn parse_csv(text: &str) -> Result<Vec<(usize, ManifestRow)>, ImportError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((header_line, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let columns: Vec<String> = split_csv_line(header)
+        .map_err(|error| ImportError::rows(vec![RowError { row: header_line, error }]))?
+        .into_iter()
+        .map(|c| c.trim().to_ascii_lowercase())
+        .collect();
+    for (i, column) in columns.iter().enumerate() {
+        let error = if !COLUMNS.contains(&column.as_str()) {
+            format!("unknown column {column:?} (expected {})", COLUMNS.join(", "))
+        } else if columns[..i].contains(column) {
+            format!("column {column:?} appears twice")
+        } else {
+            continue;
+        };
+        return Err(ImportError::rows(vec![RowError { row: header_line, error }]));
+    }
+    if !columns.iter().any(|c| c == "mac") {
+        return Err(ImportError::rows(vec![RowError { row: header_line, error: "no \"mac\" column".into() }]));
+    }
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for (line_no, line) in lines {
+        let cells = match split_csv_line(line) {
+            Ok(cells) if cells.len() == columns.len() => cells,
+            Ok(cells) => {
+                let error = format!("{} fields, header has {}", cells.len(), columns.len());
+                errors.push(RowError { row: line_no, error });
+                continue;
+            }
+            Err(error) => {
+                errors.push(RowError { row: line_no, error });
+                continue;
+            }
+        };
+        let mut row = ManifestRow::default();
+        for (column, cell) in columns.iter().zip(cells) {
+            let cell = cell.trim().to_string();
+            let value = (!cell.is_empty()).then_some(cell);
+            match column.as_str() {
+                "mac" => {
+                    row.mac = value.unwrap_or_default();
+                }
+                "name" => {
+                    row.name = value;
+                }
+                "tenant" => {
+                    row.tenant = value;
+                }
+                "persona" => {
+                    row.persona = value;
+                }
+                "site" => {
+                    row.site = value;
+                }
+                "room" => {
+                    row.room = value;
+                }
+                _ =>
+                    match value.map(|v| v.parse::<u32>().map_err(|_| v)).transpose() {
+                        Ok(id) => {
+                            row.sensor_id = id;
+                        }
+                        Err(v) => errors.push(RowError { row: line_no, error: format!("invalid sensor_id: {v}") }),
+                    }
+            }
+        }
+        rows.push((line_no, row));
+    }
+    if errors.is_empty() { Ok(rows) } else { Err(ImportError::rows(errors)) }
+}
+
+/// Split one CSV line into fields (RFC 4180 quoting, no line breaks
+/// inside fields).
+fn split_csv_line(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => {
+                quoted = false;
+            }
+            (false, '"') if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            (false, ',') => fields.push(std::mem::take(&mut field)),
+            (_, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".into());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+/// Normalise a MAC to lower-case `aa:bb:cc:dd:ee:ff` (None if invalid).
+pub fn normalize_mac(mac: &str) -> Option<String> {
+    let hex: String = mac
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ':' | '-' | '.'))
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if hex.len() != 12 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let octets: Vec<&str> = (0..6).map(|i| &hex[i * 2..i * 2 + 2]).collect();
+    Some(octets.join(":"))
+}
+
+/// Check every row.  The profiles (keyed by normalised MAC) come back
+/// only if all rows are valid; otherwise every problem is reported.
+pub fn validate(
+    rows: Vec<(usize, ManifestRow)>,
+    scope: &TenantScope
+) -> Result<Vec<(String, DeviceProfile)>, ImportError> {
+    let mut profiles = Vec::with_capacity(rows.len());
+    let mut errors = Vec::new();
+    let mut macs: HashMap<String, usize> = HashMap::new();
+    let mut sensors: HashMap<u32, usize> = HashMap::new();
+
+    for (row, entry) in rows {
+        let mut fail = |error: String| errors.push(RowError { row, error });
+
+        let Some(mac) = normalize_mac(&entry.mac) else {
+            fail(format!("invalid MAC: {:?}", entry.mac));
+            continue;
+        };
+        if let Some(first) = macs.insert(mac.clone(), row) {
+            fail(format!("MAC {mac} already listed in row {first}"));
+        }
+        if let Some(id) = entry.sensor_id {
+            if let Some(first) = sensors.insert(id, row) {
+                fail(format!("sensor_id {id} already listed in row {first}"));
+            }
+        }
+        let name = entry.name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+        if name.as_ref().is_some_and(|n| n.chars().count() > MAX_NAME_CHARS) {
+            fail(format!("name longer than {MAX_NAME_CHARS} characters"));
+        }
+        let persona = match entry.persona.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            None => None,
+            Some(p) =>
+                match <PersonaTrait as clap::ValueEnum>::from_str(p, true) {
+                    Ok(persona) => Some(persona),
+                    Err(_) => {
+                        fail(format!("unknown persona: {p}"));
+                        None
+                    }
+                }
+        };
+        let tenant = entry.tenant.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+        let tenant = match (scope, tenant) {
+            (TenantScope::Bridge(own), None) => (!own.is_empty()).then(|| own.to_string()),
+            (TenantScope::Bridge(own), Some(t)) if t == *own => Some(t),
+            (TenantScope::Bridge(""), Some(t)) => {
+                fail(format!("tenant {t:?} given, but this bridge has no tenants"));
+                None
+            }
+            (TenantScope::Bridge(own), Some(t)) => {
+                fail(format!("tenant {t:?} is not this bridge's tenant ({own})"));
+                None
+            }
+            (TenantScope::Directory(_), None) => {
+                fail("tenant required".into());
+                None
+            }
+            (TenantScope::Directory(ids), Some(t)) if ids.contains(&t) => Some(t),
+            (TenantScope::Directory(_), Some(t)) => {
+                fail(format!("unknown tenant: {t}"));
+                None
+            }
+        };
+        let location = Location { site: entry.site, room: entry.room }.normalize().unwrap_or_else(|e| {
+            fail(e);
+            Location::default()
+        });
+        profiles.push((mac, DeviceProfile { name, tenant, persona, sensor_id: entry.sensor_id, location }));
+    }
+
+    if errors.is_empty() { Ok(profiles) } else { Err(ImportError::rows(errors)) }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_manifest_validated_as_a_whole() {
+        let csv = "MAC,name,persona,sensor_id,site,room\n\
+                   AA-BB-CC-DD-EE-01,\"Robo, the first\",Cute,1,HQ,Lobby\n\
+                   \n\
+                   aabbccddee02,,,,,\n";
+        let rows = parse(csv.as_bytes(), true).unwrap();
+        let profiles = validate(rows, &TenantScope::Bridge("")).unwrap();
+        assert_eq!(profiles[0].0, "aa:bb:cc:dd:ee:01");
+        assert_eq!(profiles[0].1.name.as_deref(), Some("Robo, the first"));
+        assert_eq!(profiles[0].1.persona, Some(PersonaTrait::Cute));
+        assert_eq!(profiles[0].1.location.site.as_deref(), Some("hq"));
+        assert_eq!(profiles[1].1, DeviceProfile::default());
+
+        // Row numbers are CSV lines; every bad row is reported
+        let csv = "mac,tenant,sensor_id\naa:bb:cc:dd:ee:01,acme,1\nnot-a-mac,acme,2\nAA:BB:CC:DD:EE:01,acme,1\n";
+        let err = validate(parse(csv.as_bytes(), true).unwrap(), &TenantScope::Bridge("acme")).unwrap_err();
+        let rows: Vec<usize> = err.rows.iter().map(|r| r.row).collect();
+        assert_eq!(rows, [3, 4, 4], "{err:?}");
+
+        let err = parse(b"mac,colour\n", true).unwrap_err();
+        assert_eq!(err.rows[0].row, 1);
+        assert_eq!(split_csv_line("\"a\"\"b\",c").unwrap(), ["a\"b", "c"]);
+    }
+
+    #[test]
+    fn test_json_manifest_tenants() {
+        let json = br#"[{"mac": "aa:bb:cc:dd:ee:01", "tenant": "acme", "persona": "stubborn"},
+                        {"mac": "aa:bb:cc:dd:ee:02"}]"#;
+        let tenants = ["acme".to_string()];
+        let err = validate(parse(json, false).unwrap(), &TenantScope::Directory(&tenants)).unwrap_err();
+        assert_eq!(err.rows, [RowError { row: 2, error: "tenant required".into() }]);
+
+        let err = validate(parse(json, false).unwrap(), &TenantScope::Bridge("")).unwrap_err();
+        assert_eq!(err.rows[0].row, 1);
+
+        let profiles = validate(parse(json, false).unwrap(), &TenantScope::Bridge("acme")).unwrap();
+        assert!(profiles.iter().all(|(_, p)| p.tenant.as_deref() == Some("acme")));
+        assert!(parse(b"[]", false).is_err());
+        assert!(parse(br#"[{"mac": "x", "colour": 1}]"#, false).is_err());
+    }
+}
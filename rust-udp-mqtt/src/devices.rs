@@ -0,0 +1,533 @@
+use crate::persona::PersonaTrait;
+use serde::{ Deserialize, Serialize };
+use std::collections::{ BTreeMap, HashMap };
+use std::sync::{ Arc, RwLock };
+use tokio::sync::broadcast;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Device registry
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Per-sensor settings keyed by `sensor_id`, editable over REST and read
+//  on the VAD hot path.  Entries are sparse: a sensor with no entry (or
+//  an entry with `None` fields) falls back to the global defaults.
+//
+//  The registry also tracks each ESP's playback output (volume / mute),
+//  keyed by device name (MAC or `ip:port`), as last sent by the bridge
+//  (`PUT /devices/{device}/volume`, spoken volume commands).  Devices
+//  never set are assumed to play at full volume, unmuted.
+//
+//  Groups ("classroom-a", "floor-2") name a set of sensors and ESPs so
+//  a fleet can be handled in one call: bulk persona assignment, group
+//  broadcasts and `?group=` filters on the REST API.  A group lists
+//  both key spaces — sensor ids for the VAD pipeline (thresholds,
+//  persona) and device names for the ESP audio side (sessions,
+//  broadcasts, telemetry).  Groups are kept in memory; a device may be
+//  in any number of them.
+//
+//  Provisioning profiles (`POST /devices/import`) describe ESPs by MAC:
+//  a display name, the tenant, a persona used for the device's OpenAI
+//  prompt and, optionally, the sensor id whose VAD persona it also sets.
+//
+//  Sensors and ESPs can be tagged with a location — a site (building)
+//  and a room in it — so dashboards organised by building can ask for
+//  `GET /sites/{site}/overview` instead of listing sensor ids.  Sensor
+//  tags live in `DeviceConfig` (`PUT /devices/{id}/location`), ESP tags
+//  in the provisioning profile.  Site ids are lower-cased.
+
+/// Active-detection thresholds, one per [`crate::vad::VadKind`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Thresholds {
+    /// Audio RMS energy above which `is_active` is set
+    pub audio: f64,
+    /// Emotional arousal above which `is_active` is set
+    pub arousal: f32,
+}
+
+/// Playback output of one ESP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct OutputLevel {
+    /// Percent, 0..=100
+    pub volume: u8,
+    pub muted: bool,
+}
+
+impl Default for OutputLevel {
+    fn default() -> Self {
+        Self { volume: 100, muted: false }
+    }
+}
+
+/// Settings stored for one device.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceConfig {
+    /// Audio energy threshold override (None = global default)
+    #[serde(default)]
+    pub audio_threshold: Option<f64>,
+    /// Arousal threshold override (None = global default)
+    #[serde(default)]
+    pub arousal_threshold: Option<f32>,
+    /// Persona override (None = the global `PUT /persona` one)
+    #[serde(default)]
+    pub persona: Option<PersonaTrait>,
+    /// Where the sensor is
+    #[serde(flatten)]
+    pub location: Location,
+}
+
+/// Site + room tags of a sensor or ESP.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
+}
+
+/// Sensors and ESPs tagged with one site, each with its room.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Site {
+    pub sensors: BTreeMap<u32, Option<String>>,
+    pub devices: BTreeMap<String, Option<String>>,
+}
+
+/// A named set of devices, as sent to and served by the REST API.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceGroup {
+    /// Sensor ids (VAD pipeline)
+    #[serde(default)]
+    pub sensors: Vec<u32>,
+    /// ESP device names (MAC or `ip:port`)
+    #[serde(default)]
+    pub devices: Vec<String>,
+}
+
+/// Provisioning record of one ESP, keyed by MAC.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DeviceProfile {
+    pub name: Option<String>,
+    pub tenant: Option<String>,
+    pub persona: Option<PersonaTrait>,
+    /// Sensor id of the device's sensor packets (persona and location
+    /// applied there too)
+    pub sensor_id: Option<u32>,
+    #[serde(flatten)]
+    pub location: Location,
+}
+
+/// What an import changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ImportReport {
+    pub created: usize,
+    pub updated: usize,
+}
+
+impl DeviceGroup {
+    /// Whether `device` (case-insensitive) is in the group.
+    pub fn has_device(&self, device: &str) -> bool {
+        self.devices.iter().any(|d| d.eq_ignore_ascii_case(device))
+    }
+}
+
+/// Longest site id / room name accepted.
+const MAX_TAG_CHARS: usize = 64;
+
+impl Location {
+    /// Trim both tags (blank = none) and lower-case the site.  Site ids
+    /// are used in URLs, so only `a-z 0-9 - _ .` are allowed; a room
+    /// needs a site.
+    pub fn normalize(self) -> Result<Self, String> {
+        let tag = |t: Option<String>| t.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+        let site = tag(self.site).map(|s| s.to_ascii_lowercase());
+        let room = tag(self.room);
+        if let Some(ref site) = site {
+            if !site.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+                return Err(format!("site {site:?} may only contain a-z, 0-9, '-', '_' and '.'"));
+            }
+        }
+        if [&site, &room].into_iter().flatten().any(|t| t.chars().count() > MAX_TAG_CHARS) {
+            return Err(format!("site and room must be at most {MAX_TAG_CHARS} characters"));
+        }
+        if site.is_none() && room.is_some() {
+            return Err("a room needs a site".into());
+        }
+        Ok(Self { site, room })
+    }
+}
+
+impl Site {
+    /// Distinct rooms, sorted.
+    pub fn rooms(&self) -> Vec<String> {
+        let rooms: std::collections::BTreeSet<&String> = self.sensors
+            .values()
+            .chain(self.devices.values())
+            .flatten()
+            .collect();
+        rooms.into_iter().cloned().collect()
+    }
+
+    /// Only the members in `room` (case-insensitive).
+    pub fn in_room(self, room: &str) -> Self {
+        let here = |r: &Option<String>| r.as_ref().is_some_and(|r| r.eq_ignore_ascii_case(room));
+        Self {
+            sensors: self.sensors.into_iter().filter(|(_, r)| here(r)).collect(),
+            devices: self.devices.into_iter().filter(|(_, r)| here(r)).collect(),
+        }
+    }
+}
+
+impl DeviceConfig {
+    /// Check overrides are in range; returns a human-readable error.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(t) = self.audio_threshold {
+            if !t.is_finite() || t < 0.0 {
+                return Err(format!("audio_threshold must be ≥ 0, got {t}"));
+            }
+        }
+        if let Some(t) = self.arousal_threshold {
+            if !(0.0..=1.0).contains(&t) {
+                return Err(format!("arousal_threshold must be in [0, 1], got {t}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Thread-safe registry shared between the API and VAD workers.
+#[derive(Clone)]
+pub struct DeviceRegistry {
+    defaults: Thresholds,
+    inner: Arc<RwLock<HashMap<u32, DeviceConfig>>>,
+    /// Output levels by lower-cased device name
+    outputs: Arc<RwLock<HashMap<String, OutputLevel>>>,
+    /// Groups by lower-cased name
+    groups: Arc<RwLock<BTreeMap<String, DeviceGroup>>>,
+    /// Provisioning profiles by lower-cased MAC
+    profiles: Arc<RwLock<BTreeMap<String, DeviceProfile>>>,
+    /// Local changes, for cluster sync
+    changes: broadcast::Sender<(u32, DeviceConfig)>,
+}
+
+impl DeviceRegistry {
+    pub fn new(defaults: Thresholds) -> Self {
+        Self {
+            defaults,
+            inner: Arc::new(RwLock::new(HashMap::new())),
+            outputs: Arc::default(),
+            groups: Arc::default(),
+            profiles: Arc::default(),
+            changes: broadcast::channel(64).0,
+        }
+    }
+
+    /// Every `set_thresholds` on this instance (not `apply_remote`).
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<(u32, DeviceConfig)> {
+        self.changes.subscribe()
+    }
+
+    /// Adopt overrides set on another cluster instance without
+    /// announcing them again.
+    pub fn apply_remote(&self, sensor_id: u32, config: DeviceConfig) {
+        let mut map = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        map.insert(sensor_id, config);
+    }
+
+    /// Global thresholds used when a device has no override.
+    pub fn defaults(&self) -> Thresholds {
+        self.defaults
+    }
+
+    /// Effective thresholds for a sensor (overrides merged over defaults).
+    pub fn thresholds(&self, sensor_id: u32) -> Thresholds {
+        let map = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        match map.get(&sensor_id) {
+            Some(cfg) =>
+                Thresholds {
+                    audio: cfg.audio_threshold.unwrap_or(self.defaults.audio),
+                    arousal: cfg.arousal_threshold.unwrap_or(self.defaults.arousal),
+                },
+            None => self.defaults,
+        }
+    }
+
+    pub fn get(&self, sensor_id: u32) -> Option<DeviceConfig> {
+        self.inner
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&sensor_id)
+            .cloned()
+    }
+
+    /// All registered devices, sorted by sensor_id.
+    pub fn list(&self) -> Vec<(u32, DeviceConfig)> {
+        let map = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        let mut out: Vec<_> = map
+            .iter()
+            .map(|(id, cfg)| (*id, cfg.clone()))
+            .collect();
+        out.sort_by_key(|(id, _)| *id);
+        out
+    }
+
+    /// Replace a device's threshold overrides.
+    pub fn set_thresholds(
+        &self,
+        sensor_id: u32,
+        audio: Option<f64>,
+        arousal: Option<f32>
+    ) -> DeviceConfig {
+        let mut map = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let cfg = map.entry(sensor_id).or_default();
+        cfg.audio_threshold = audio;
+        cfg.arousal_threshold = arousal;
+        let cfg = cfg.clone();
+        drop(map);
+        // No subscribers is fine
+        let _ = self.changes.send((sensor_id, cfg.clone()));
+        cfg
+    }
+
+    /// Persona override of a sensor, if any.
+    pub fn persona(&self, sensor_id: u32) -> Option<PersonaTrait> {
+        let map = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        map.get(&sensor_id).and_then(|cfg| cfg.persona)
+    }
+
+    /// Replace a sensor's persona override.
+    pub fn set_persona(&self, sensor_id: u32, persona: Option<PersonaTrait>) -> DeviceConfig {
+        let mut map = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let cfg = map.entry(sensor_id).or_default();
+        cfg.persona = persona;
+        let cfg = cfg.clone();
+        drop(map);
+        let _ = self.changes.send((sensor_id, cfg.clone()));
+        cfg
+    }
+
+    /// Replace a sensor's location (already normalised).
+    pub fn set_location(&self, sensor_id: u32, location: Location) -> DeviceConfig {
+        let mut map = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let cfg = map.entry(sensor_id).or_default();
+        cfg.location = location;
+        let cfg = cfg.clone();
+        drop(map);
+        let _ = self.changes.send((sensor_id, cfg.clone()));
+        cfg
+    }
+
+    /// Every site with its tagged sensors and ESPs, by site id.
+    pub fn sites(&self) -> BTreeMap<String, Site> {
+        let mut sites: BTreeMap<String, Site> = BTreeMap::new();
+        {
+            let map = self.inner.read().unwrap_or_else(|e| e.into_inner());
+            for (id, cfg) in map.iter() {
+                if let Some(ref site) = cfg.location.site {
+                    sites.entry(site.clone()).or_default().sensors.insert(*id, cfg.location.room.clone());
+                }
+            }
+        }
+        let profiles = self.profiles.read().unwrap_or_else(|e| e.into_inner());
+        for (mac, profile) in profiles.iter() {
+            if let Some(ref site) = profile.location.site {
+                sites.entry(site.clone()).or_default().devices.insert(mac.clone(), profile.location.room.clone());
+            }
+        }
+        sites
+    }
+
+    /// One site (case-insensitive); None if nothing is tagged with it.
+    pub fn site(&self, site: &str) -> Option<Site> {
+        self.sites().remove(&site.to_ascii_lowercase())
+    }
+
+    /// All groups, sorted by name.
+    pub fn groups(&self) -> Vec<(String, DeviceGroup)> {
+        let map = self.groups.read().unwrap_or_else(|e| e.into_inner());
+        map.iter()
+            .map(|(name, group)| (name.clone(), group.clone()))
+            .collect()
+    }
+
+    /// One group (case-insensitive).
+    pub fn group(&self, name: &str) -> Option<DeviceGroup> {
+        let map = self.groups.read().unwrap_or_else(|e| e.into_inner());
+        map.get(&name.to_ascii_lowercase()).cloned()
+    }
+
+    /// Create or replace a group.  Members are de-duplicated and sorted,
+    /// device names lower-cased.
+    pub fn set_group(&self, name: &str, mut group: DeviceGroup) -> Result<DeviceGroup, String> {
+        let name = name.trim().to_ascii_lowercase();
+        if name.is_empty() {
+            return Err("group name must not be empty".into());
+        }
+        for device in &mut group.devices {
+            *device = device.trim().to_ascii_lowercase();
+        }
+        if group.devices.iter().any(String::is_empty) {
+            return Err("device names must not be empty".into());
+        }
+        group.sensors.sort_unstable();
+        group.sensors.dedup();
+        group.devices.sort();
+        group.devices.dedup();
+        let mut map = self.groups.write().unwrap_or_else(|e| e.into_inner());
+        map.insert(name, group.clone());
+        Ok(group)
+    }
+
+    /// Delete a group; false if there is none by that name.
+    pub fn remove_group(&self, name: &str) -> bool {
+        let mut map = self.groups.write().unwrap_or_else(|e| e.into_inner());
+        map.remove(&name.to_ascii_lowercase()).is_some()
+    }
+
+    /// Provisioning profile of `device` (case-insensitive).
+    pub fn profile(&self, device: &str) -> Option<DeviceProfile> {
+        let map = self.profiles.read().unwrap_or_else(|e| e.into_inner());
+        map.get(&device.to_ascii_lowercase()).cloned()
+    }
+
+    /// All provisioning profiles, sorted by MAC.
+    pub fn profiles(&self) -> Vec<(String, DeviceProfile)> {
+        let map = self.profiles.read().unwrap_or_else(|e| e.into_inner());
+        map.iter()
+            .map(|(mac, profile)| (mac.clone(), profile.clone()))
+            .collect()
+    }
+
+    /// Upsert validated profiles in one step: readers see either none or
+    /// all of them.  Profiles with a sensor id also set its persona and
+    /// location, when given.
+    pub fn import(&self, rows: Vec<(String, DeviceProfile)>) -> ImportReport {
+        let mut report = ImportReport::default();
+        let mut personas = Vec::new();
+        {
+            let mut profiles = self.profiles.write().unwrap_or_else(|e| e.into_inner());
+            let mut configs = self.inner.write().unwrap_or_else(|e| e.into_inner());
+            for (mac, profile) in rows {
+                if let Some(id) = profile.sensor_id {
+                    let located = profile.location.site.is_some();
+                    if profile.persona.is_some() || located {
+                        let cfg = configs.entry(id).or_default();
+                        if profile.persona.is_some() {
+                            cfg.persona = profile.persona;
+                        }
+                        if located {
+                            cfg.location = profile.location.clone();
+                        }
+                        personas.push((id, cfg.clone()));
+                    }
+                }
+                match profiles.insert(mac.to_ascii_lowercase(), profile) {
+                    Some(_) => report.updated += 1,
+                    None => report.created += 1,
+                }
+            }
+        }
+        for change in personas {
+            let _ = self.changes.send(change);
+        }
+        report
+    }
+
+    /// Last output level sent to `device` (case-insensitive).
+    pub fn output(&self, device: &str) -> OutputLevel {
+        let map = self.outputs.read().unwrap_or_else(|e| e.into_inner());
+        map.get(&device.to_ascii_lowercase()).copied().unwrap_or_default()
+    }
+
+    /// Record the output level sent to `device`.
+    pub fn set_output(&self, device: &str, level: OutputLevel) {
+        let mut map = self.outputs.write().unwrap_or_else(|e| e.into_inner());
+        map.insert(device.to_ascii_lowercase(), level);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULTS: Thresholds = Thresholds { audio: 30.0, arousal: 0.35 };
+
+    #[test]
+    fn test_overrides_merge_over_defaults() {
+        let reg = DeviceRegistry::new(DEFAULTS);
+        assert_eq!(reg.thresholds(7), DEFAULTS);
+
+        reg.set_thresholds(7, Some(120.0), None);
+        assert_eq!(reg.thresholds(7), Thresholds { audio: 120.0, arousal: 0.35 });
+        assert_eq!(reg.thresholds(8), DEFAULTS);
+
+        reg.set_thresholds(7, None, None);
+        assert_eq!(reg.thresholds(7), DEFAULTS);
+    }
+
+    #[test]
+    fn test_validate_ranges() {
+        assert!(DeviceConfig { audio_threshold: Some(-1.0), ..Default::default() }.validate().is_err());
+        assert!(DeviceConfig { arousal_threshold: Some(1.5), ..Default::default() }.validate().is_err());
+        assert!(
+            (DeviceConfig { audio_threshold: Some(50.0), arousal_threshold: Some(0.5), ..Default::default() })
+                .validate()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_groups_and_persona_overrides() {
+        let reg = DeviceRegistry::new(DEFAULTS);
+        let group = DeviceGroup {
+            sensors: vec![3, 1, 3],
+            devices: vec!["AA:BB:CC:DD:EE:01".into(), " aa:bb:cc:dd:ee:01".into()],
+        };
+        let group = reg.set_group("Classroom-A", group).unwrap();
+        assert_eq!(group.sensors, [1, 3]);
+        assert_eq!(group.devices, ["aa:bb:cc:dd:ee:01"]);
+        assert_eq!(reg.group("classroom-a"), Some(group.clone()));
+        assert!(group.has_device("AA:bb:CC:dd:EE:01"));
+        assert!(reg.set_group(" ", DeviceGroup::default()).is_err());
+
+        reg.set_thresholds(1, Some(80.0), None);
+        reg.set_persona(1, Some(PersonaTrait::Cute));
+        assert_eq!(reg.persona(1), Some(PersonaTrait::Cute));
+        assert_eq!(reg.thresholds(1).audio, 80.0, "persona kept the threshold override");
+        reg.set_thresholds(1, None, None);
+        assert_eq!(reg.persona(1), Some(PersonaTrait::Cute), "thresholds kept the persona override");
+        assert_eq!(reg.persona(3), None);
+
+        let profile = DeviceProfile { sensor_id: Some(3), persona: Some(PersonaTrait::Stubborn), ..Default::default() };
+        let report = reg.import(vec![("AA:BB:CC:DD:EE:01".into(), profile.clone())]);
+        assert_eq!(report, ImportReport { created: 1, updated: 0 });
+        assert_eq!(reg.import(vec![("aa:bb:cc:dd:ee:01".into(), profile.clone())]).updated, 1);
+        assert_eq!(reg.profile("aa:BB:cc:dd:ee:01"), Some(profile));
+        assert_eq!(reg.persona(3), Some(PersonaTrait::Stubborn), "imported persona reaches the sensor");
+
+        let lobby = Location { site: Some("hq".into()), room: Some("Lobby".into()) };
+        let located = DeviceProfile { sensor_id: Some(5), location: lobby.clone(), ..Default::default() };
+        reg.import(vec![("aa:bb:cc:dd:ee:02".into(), located)]);
+        reg.set_location(7, Location { site: Some("hq".into()), room: None });
+        let site = reg.site("HQ").unwrap();
+        assert_eq!(site.sensors, BTreeMap::from([(5, Some("Lobby".into())), (7, None)]));
+        assert_eq!(site.devices.keys().collect::<Vec<_>>(), ["aa:bb:cc:dd:ee:02"]);
+        assert_eq!(site.rooms(), ["Lobby"]);
+        assert_eq!(site.in_room("lobby").sensors.len(), 1);
+        assert_eq!(reg.get(5).unwrap().location, lobby, "imported location reaches the sensor");
+        let bad = |site: &str, room: Option<&str>| {
+            Location { site: Some(site.into()), room: room.map(Into::into) }.normalize().is_err()
+        };
+        assert!(bad("main hall", None) && bad(" ", Some("lobby")));
+        assert_eq!(
+            Location { site: Some(" HQ ".into()), room: Some(" ".into()) }.normalize(),
+            Ok(Location { site: Some("hq".into()), room: None })
+        );
+
+        assert!(reg.remove_group("CLASSROOM-A"));
+        assert!(!reg.remove_group("classroom-a"));
+        assert!(reg.groups().is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+use crate::audio_window::{ AUDIO_SAMPLE_RATE, DBFS_FLOOR };
+use crate::capture::CapturedSocket;
+use crate::clips::{ send_paced, PlayError };
+use crate::downlink_pacing::DownlinkPacer;
+use crate::esp_audio_protocol::{ build_control, CTRL_LOOPBACK_END, CTRL_LOOPBACK_START };
+use dashmap::DashMap;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+use tokio::sync::{ mpsc, oneshot };
+use tracing::info;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Test-tone / loopback audio path check
+// ─────────────────────────────────────────────────────────────────────
+//
+//  `POST /devices/{device}/diagnostics` checks the whole audio path of
+//  one ESP in a single call:
+//
+//    1. CTRL_LOOPBACK_START — the ESP streams its mic back as AUDIO_UP
+//       (or raw PCM) without a session
+//    2. a 1 kHz, -6 dBFS test tone is paced out as AUDIO_DOWN
+//    3. mic audio from that address is diverted from the session
+//       pipeline into the test until `LISTEN_TAIL` after the tone
+//    4. CTRL_LOOPBACK_END
+//
+//  Latency is the time from the first tone packet to the first mic
+//  chunk at or above `--diag-min-level-dbfs`; level is the loudest
+//  chunk heard.  The device passes when the tone came back loud enough
+//  within `--diag-max-latency-ms`.
+
+/// Test tone frequency (well inside any speaker / mic passband).
+const TONE_HZ: f64 = 1_000.0;
+
+/// Test tone length.
+const TONE_DURATION: Duration = Duration::from_secs(1);
+
+/// Test tone peak amplitude (-6 dBFS).
+const TONE_AMPLITUDE: f64 = 0.5;
+
+/// How long to keep listening after the last tone packet.
+const LISTEN_TAIL: Duration = Duration::from_millis(1_500);
+
+/// Addresses under test → where their mic audio goes instead of the
+/// session pipeline.
+pub type LoopbackTaps = Arc<DashMap<SocketAddr, mpsc::UnboundedSender<(Instant, Vec<u8>)>>>;
+
+/// Pass/fail thresholds (`--diag-max-latency-ms`, `--diag-min-level-dbfs`).
+#[derive(Debug, Clone, Copy)]
+pub struct Criteria {
+    pub max_latency: Duration,
+    pub min_level_dbfs: f32,
+}
+
+/// Result of one loopback run.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsReport {
+    pub device: String,
+    pub addr: String,
+    pub passed: bool,
+    /// First tone packet → first loud mic chunk (None = tone never heard)
+    pub latency_ms: Option<u64>,
+    /// Loudest mic chunk heard
+    pub level_dbfs: f32,
+    /// Mic audio received during the test
+    pub captured_ms: u64,
+    pub packets: usize,
+    pub max_latency_ms: u64,
+    pub min_level_dbfs: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+/// A request from the API to test `device` (MAC or `ip:port`).
+pub struct DiagnosticsRequest {
+    pub device: String,
+    pub reply: oneshot::Sender<Result<DiagnosticsReport, PlayError>>,
+}
+
+/// API handle for running diagnostics on the UDP side.  Clone-friendly.
+#[derive(Clone)]
+pub struct Diagnostics {
+    requests: mpsc::Sender<DiagnosticsRequest>,
+}
+
+impl Diagnostics {
+    pub fn new() -> (Self, mpsc::Receiver<DiagnosticsRequest>) {
+        let (requests, rx) = mpsc::channel(8);
+        (Self { requests }, rx)
+    }
+
+    /// Run the loopback test on `device`; resolves when it is finished.
+    pub async fn run(&self, device: &str) -> Result<DiagnosticsReport, PlayError> {
+        let (reply, rx) = oneshot::channel();
+        let req = DiagnosticsRequest { device: device.to_string(), reply };
+        self.requests.send(req).await.map_err(|_| PlayError::Unavailable)?;
+        rx.await.map_err(|_| PlayError::Unavailable)?
+    }
+}
+
+/// Play the test tone to `addr` with its mic looped back into `taps`,
+/// and judge what came back.
+pub async fn run_loopback(
+    socket: &CapturedSocket,
+    addr: SocketAddr,
+    taps: &LoopbackTaps,
+    criteria: Criteria
+) -> DiagnosticsReport {
+    let (tap, mut rx) = mpsc::unbounded_channel();
+    taps.insert(addr, tap);
+    info!(esp = %addr, "🩺 audio diagnostics started");
+
+    let _ = socket.send_to(&build_control(0, CTRL_LOOPBACK_START, 0), addr).await;
+    let started = Instant::now();
+    send_paced(socket, addr, &test_tone(), &DownlinkPacer::default()).await;
+    tokio::time::sleep(LISTEN_TAIL).await;
+    let _ = socket.send_to(&build_control(0, CTRL_LOOPBACK_END, 0), addr).await;
+    taps.remove(&addr);
+
+    let mut captured = Vec::new();
+    while let Ok((at, pcm)) = rx.try_recv() {
+        captured.push((at.saturating_duration_since(started), pcm));
+    }
+    let report = analyze(&captured, criteria, addr);
+    info!(
+        esp = %addr,
+        passed = report.passed,
+        latency_ms = ?report.latency_ms,
+        level_dbfs = format!("{:.1}", report.level_dbfs),
+        "🩺 audio diagnostics finished"
+    );
+    report
+}
+
+/// 16 kHz s16 LE sine at `TONE_HZ` for `TONE_DURATION`.
+fn test_tone() -> Vec<u8> {
+    let samples = ((AUDIO_SAMPLE_RATE as f64) * TONE_DURATION.as_secs_f64()) as usize;
+    (0..samples)
+        .flat_map(|n| {
+            let t = (n as f64) / (AUDIO_SAMPLE_RATE as f64);
+            let s = TONE_AMPLITUDE * (2.0 * std::f64::consts::PI * TONE_HZ * t).sin();
+            ((s * (i16::MAX as f64)) as i16).to_le_bytes()
+        })
+        .collect()
+}
+
+/// RMS level of s16 LE PCM in dBFS.
+fn chunk_dbfs(pcm: &[u8]) -> f32 {
+    let samples = pcm.len() / 2;
+    if samples == 0 {
+        return DBFS_FLOOR;
+    }
+    let sum_sq: f64 = pcm
+        .chunks_exact(2)
+        .map(|s| (i16::from_le_bytes([s[0], s[1]]) as f64).powi(2))
+        .sum();
+    let rms = (sum_sq / (samples as f64)).sqrt();
+    if rms <= 0.0 {
+        return DBFS_FLOOR;
+    }
+    ((20.0 * (rms / 32_768.0).log10()) as f32).max(DBFS_FLOOR)
+}
+
+/// Judge mic chunks (offset from the first tone packet, PCM).
+fn analyze(captured: &[(Duration, Vec<u8>)], criteria: Criteria, addr: SocketAddr) -> DiagnosticsReport {
+    let levels: Vec<(Duration, f32)> = captured
+        .iter()
+        .map(|(at, pcm)| (*at, chunk_dbfs(pcm)))
+        .collect();
+    let level_dbfs = levels
+        .iter()
+        .map(|&(_, db)| db)
+        .fold(DBFS_FLOOR, f32::max);
+    let latency = levels
+        .iter()
+        .find(|&&(_, db)| db >= criteria.min_level_dbfs)
+        .map(|&(at, _)| at);
+    let captured_bytes: usize = captured
+        .iter()
+        .map(|(_, pcm)| pcm.len())
+        .sum();
+
+    let failure = if captured.is_empty() {
+        Some("no mic audio received — loopback not supported or device offline".to_string())
+    } else if latency.is_none() {
+        Some(format!("tone not heard: loudest chunk {level_dbfs:.1} dBFS < {:.1} dBFS", criteria.min_level_dbfs))
+    } else if latency > Some(criteria.max_latency) {
+        Some(format!("latency {} ms > {} ms", latency.unwrap_or_default().as_millis(), criteria.max_latency.as_millis()))
+    } else {
+        None
+    };
+
+    DiagnosticsReport {
+        device: String::new(),
+        addr: addr.to_string(),
+        passed: failure.is_none(),
+        latency_ms: latency.map(|d| d.as_millis() as u64),
+        level_dbfs,
+        captured_ms: ((captured_bytes as u64) * 1000) / ((AUDIO_SAMPLE_RATE as u64) * 2),
+        packets: captured.len(),
+        max_latency_ms: criteria.max_latency.as_millis() as u64,
+        min_level_dbfs: criteria.min_level_dbfs,
+        failure,
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CRITERIA: Criteria = Criteria {
+        max_latency: Duration::from_millis(400),
+        min_level_dbfs: -40.0,
+    };
+
+    fn addr() -> SocketAddr {
+        "10.0.0.9:5000".parse().unwrap()
+    }
+
+    #[test]
+    fn test_tone_level() {
+        let tone = test_tone();
+        assert_eq!(tone.len(), 32_000);
+        // Sine RMS = peak / √2 → -6 dBFS peak ≈ -9 dBFS RMS
+        assert!((chunk_dbfs(&tone) + 9.0).abs() < 0.2);
+        assert_eq!(chunk_dbfs(&[0; 64]), DBFS_FLOOR);
+    }
+
+    #[test]
+    fn test_analyze_pass_and_failures() {
+        let quiet = vec![0u8; 1400];
+        let loud = test_tone()[..1400].to_vec();
+        let heard = [
+            (Duration::from_millis(100), quiet.clone()),
+            (Duration::from_millis(180), loud.clone()),
+        ];
+        let report = analyze(&heard, CRITERIA, addr());
+        assert!(report.passed, "{report:?}");
+        assert_eq!(report.latency_ms, Some(180));
+        assert_eq!(report.packets, 2);
+
+        let late = [(Duration::from_millis(900), loud)];
+        assert!(analyze(&late, CRITERIA, addr()).failure.unwrap().contains("latency"));
+
+        let silent = [(Duration::from_millis(100), quiet)];
+        let report = analyze(&silent, CRITERIA, addr());
+        assert_eq!(report.latency_ms, None);
+        assert!(!report.passed);
+
+        assert!(!analyze(&[], CRITERIA, addr()).passed);
+    }
+}
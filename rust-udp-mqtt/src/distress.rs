@@ -0,0 +1,279 @@
+use crate::clock::{ self, SharedClock };
+use crate::config::Config;
+use crate::events::{ SensorEvent, SensorEventKind, Snippet };
+use crate::vad::{ VadKind, VadResult };
+use std::collections::{ HashMap, VecDeque };
+use std::sync::Mutex;
+use std::time::{ Duration, Instant };
+use tokio::sync::mpsc;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Emotional distress alarm (opt-in)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  A child starting to cry shows up in the emotional V/A/D stream as
+//  valence falling fast while arousal is high.  With `--distress-alarm`
+//  the VAD workers feed every emotional result here; a sensor raises a
+//  priority `distress` event when, within `--distress-window-secs`:
+//
+//    valence fell by at least     --distress-valence-drop
+//    and is now at or below       --distress-max-valence
+//    while arousal is at least    --distress-min-arousal
+//
+//  then stays quiet for `--distress-cooldown-secs`.  The event goes to
+//  `/events/ws` like sensor events (`"priority": true`) and is POSTed to
+//  every `--distress-webhook`.  Its `snippet` names the window that
+//  triggered it: the seq range and a `GET /devices/{id}/emotions` query
+//  covering it.
+//
+//  The alarm is a hint for a caregiver, not a diagnosis; every parameter
+//  is a flag so a deployment can tune it against its own data.
+
+/// Validated `--distress-*` parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistressParams {
+    pub window: Duration,
+    pub valence_drop: f32,
+    pub max_valence: f32,
+    pub min_arousal: f32,
+    pub cooldown: Duration,
+}
+
+impl DistressParams {
+    /// Parameters from the flags; None without `--distress-alarm`.
+    pub fn from_config(config: &Config) -> anyhow::Result<Option<Self>> {
+        if !config.distress_alarm {
+            return Ok(None);
+        }
+        let params = Self {
+            window: Duration::from_secs(config.distress_window_secs),
+            valence_drop: config.distress_valence_drop,
+            max_valence: config.distress_max_valence,
+            min_arousal: config.distress_min_arousal,
+            cooldown: Duration::from_secs(config.distress_cooldown_secs),
+        };
+        if params.window.is_zero() {
+            anyhow::bail!("--distress-window-secs must be at least 1");
+        }
+        if !(params.valence_drop > 0.0 && params.valence_drop <= 1.0) {
+            anyhow::bail!("--distress-valence-drop must be in (0, 1], got {}", params.valence_drop);
+        }
+        for (flag, value) in [
+            ("--distress-max-valence", params.max_valence),
+            ("--distress-min-arousal", params.min_arousal),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                anyhow::bail!("{flag} must be in [0, 1], got {value}");
+            }
+        }
+        Ok(Some(params))
+    }
+}
+
+/// One emotional sample kept for the window.
+struct Sample {
+    at: Instant,
+    at_ms: u64,
+    seq: u64,
+    valence: f32,
+}
+
+#[derive(Default)]
+struct SensorState {
+    samples: VecDeque<Sample>,
+    last_fired: Option<Instant>,
+}
+
+/// Distress detector shared across VAD workers.
+pub struct DistressDetector {
+    params: DistressParams,
+    state: Mutex<HashMap<u32, SensorState>>,
+    /// Webhook delivery (None = no `--distress-webhook`)
+    webhooks: Option<mpsc::Sender<SensorEvent>>,
+    clock: SharedClock,
+}
+
+impl DistressDetector {
+    pub fn new(params: DistressParams, clock: SharedClock) -> Self {
+        Self { params, state: Mutex::default(), webhooks: None, clock }
+    }
+
+    /// Detector for the `--distress-*` flags, with its webhook delivery
+    /// task; None unless `--distress-alarm` is set.
+    pub fn from_config(config: &Config, clock: SharedClock) -> anyhow::Result<Option<Self>> {
+        let Some(params) = DistressParams::from_config(config)? else {
+            return Ok(None);
+        };
+        let mut detector = Self::new(params, clock);
+        let webhooks = config.distress_webhook.clone();
+        info!(
+            window_secs = params.window.as_secs(),
+            valence_drop = %params.valence_drop,
+            max_valence = %params.max_valence,
+            min_arousal = %params.min_arousal,
+            webhooks = webhooks.len(),
+            "🚨 distress alarm enabled"
+        );
+        if webhooks.is_empty() {
+            return Ok(Some(detector));
+        }
+
+        let http = reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?;
+        let (tx, mut rx) = mpsc::channel::<SensorEvent>(64);
+        tokio::spawn(async move {
+            while let Some(ev) = rx.recv().await {
+                let body = match serde_json::to_vec(&ev) {
+                    Ok(b) => b,
+                    Err(_) => continue,
+                };
+                for url in &webhooks {
+                    match
+                        http
+                            .post(url)
+                            .header("Content-Type", "application/json")
+                            .body(body.clone())
+                            .send().await
+                    {
+                        Ok(resp) if resp.status().is_success() => {}
+                        Ok(resp) => warn!(url = %url, status = %resp.status(), "distress webhook rejected"),
+                        Err(e) => warn!(url = %url, error = %e, "distress webhook failed"),
+                    }
+                }
+            }
+        });
+        detector.webhooks = Some(tx);
+        Ok(Some(detector))
+    }
+
+    /// Feed one VAD result; returns the distress event if it fired.
+    /// `value` is the valence drop over the window.
+    pub fn observe(&self, result: &VadResult) -> Option<SensorEvent> {
+        if result.kind != VadKind::Emotional {
+            return None;
+        }
+        let now = self.clock.now();
+        let now_ms = clock::unix_millis(self.clock.as_ref());
+        let p = self.params;
+        let mut map = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let state = map.entry(result.sensor_id).or_default();
+
+        while state.samples.front().is_some_and(|s| now.saturating_duration_since(s.at) > p.window) {
+            state.samples.pop_front();
+        }
+        state.samples.push_back(Sample { at: now, at_ms: now_ms, seq: result.seq, valence: result.valence });
+
+        if result.valence > p.max_valence || result.arousal < p.min_arousal {
+            return None;
+        }
+        if state.last_fired.is_some_and(|t| now.saturating_duration_since(t) < p.cooldown) {
+            return None;
+        }
+        let peak = state.samples.iter().max_by(|a, b| a.valence.total_cmp(&b.valence))?;
+        let drop = peak.valence - result.valence;
+        if drop < p.valence_drop {
+            return None;
+        }
+
+        let snippet = Snippet {
+            from_seq: peak.seq,
+            to_seq: result.seq,
+            from_ms: peak.at_ms,
+            to_ms: now_ms,
+            // Emotion history buckets are whole seconds
+            emotions: format!(
+                "/devices/{}/emotions?from={}&to={}&resolution=1s",
+                result.sensor_id,
+                (peak.at_ms / 1000) * 1000,
+                (now_ms / 1000 + 1) * 1000
+            ),
+        };
+        // A new alarm needs a new drop, not the tail of this one
+        state.samples.clear();
+        state.last_fired = Some(now);
+        Some(SensorEvent {
+            sensor_id: result.sensor_id,
+            seq: result.seq,
+            kind: SensorEventKind::Distress,
+            value: drop,
+            timestamp_ms: now_ms,
+            tenant: String::new(),
+            priority: true,
+            snippet: Some(snippet),
+        })
+    }
+
+    /// Queue `ev` for the webhooks (never blocks).
+    pub fn notify(&self, ev: &SensorEvent) {
+        let Some(ref tx) = self.webhooks else {
+            return;
+        };
+        if tx.try_send(ev.clone()).is_err() {
+            warn!(sensor_id = ev.sensor_id, "distress webhook queue full — dropping event");
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimClock;
+    use std::sync::Arc;
+
+    fn emotional(seq: u64, valence: f32, arousal: f32) -> VadResult {
+        VadResult {
+            sensor_id: 7,
+            seq,
+            kind: VadKind::Emotional,
+            is_active: true,
+            energy: 0.0,
+            threshold: 0.0,
+            dbfs: -96.0,
+            zcr: 0.0,
+            band_ratio: 0.0,
+            valence,
+            arousal,
+            dominance: 0.3,
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn test_fast_drop_with_high_arousal_fires_once() {
+        let sim = SimClock::starting_at(std::time::UNIX_EPOCH);
+        let params = DistressParams {
+            window: Duration::from_secs(5),
+            valence_drop: 0.3,
+            max_valence: 0.35,
+            min_arousal: 0.6,
+            cooldown: Duration::from_secs(60),
+        };
+        let det = DistressDetector::new(params, Arc::new(sim.clone()));
+        let step = |seq, v, a| {
+            sim.advance(Duration::from_secs(1));
+            det.observe(&emotional(seq, v, a))
+        };
+
+        // A slow slide (0.25 per window) and a fast drop while calm: nothing
+        for seq in 0..14 {
+            assert!(step(seq, 0.8 - 0.05 * (seq as f32), 0.8).is_none(), "seq {seq}");
+        }
+        assert!(step(14, 0.7, 0.2).is_none());
+        assert!(step(15, 0.2, 0.2).is_none(), "low arousal");
+
+        let ev = step(16, 0.2, 0.9).expect("fast drop with high arousal");
+        assert_eq!((ev.kind, ev.seq, ev.priority), (SensorEventKind::Distress, 16, true));
+        assert!((ev.value - 0.5).abs() < 1e-6);
+        let snippet = ev.snippet.unwrap();
+        assert_eq!((snippet.from_seq, snippet.to_seq), (14, 16));
+        assert_eq!(snippet.emotions, "/devices/7/emotions?from=15000&to=18000&resolution=1s");
+
+        // Cooldown, even for a fresh drop
+        assert!(step(17, 0.9, 0.9).is_none());
+        assert!(step(18, 0.1, 0.9).is_none());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::config::Config;
+use dashmap::DashMap;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::info;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Adaptive AUDIO_DOWN pacing (per-device congestion control)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Every AUDIO_DOWN packet to a device takes the next send slot of that
+//  device's link; slots are `pace × chunk duration` apart, shared by
+//  OpenAI replies, clips and TTS.
+//
+//  Legacy ESPs may append a link report to their HEARTBEAT:
+//
+//    [rtt_ms u16][loss_permille u16]    (little-endian)
+//
+//  rtt_ms is the ESP's own heartbeat round trip and loss_permille the
+//  AUDIO_DOWN sequence gaps it saw since its previous report.  Each report
+//  updates a smoothed RTT (TCP's 1/8 gain) and loss rate (1/4 gain), then:
+//
+//    degraded   (loss > LOSS_LIMIT or srtt > --downlink-rtt-limit-ms)
+//               pace ×= BACKOFF, up to MAX_PACE: packets spread out,
+//               speech arrives late but whole
+//    healthy    pace -= RECOVERY, down to MIN_PACE: the ESP's playback
+//               buffer is filled ahead of real time again
+//
+//  Without `--downlink-pacing` OpenAI audio is sent as it arrives and
+//  clips / TTS at exactly real time, as before; reports are still
+//  tracked for `GET /downlink`.  Devices that never report keep the
+//  starting pace.
+
+/// Fastest pace: twice real time.
+const MIN_PACE: f64 = 0.5;
+/// Slowest pace: 1.5× the audio's duration.
+const MAX_PACE: f64 = 1.5;
+/// Multiplicative back-off per degraded report.
+const BACKOFF: f64 = 1.5;
+/// Additive recovery per healthy report.
+const RECOVERY: f64 = 0.05;
+/// Smoothed downlink loss above which the link counts as degraded.
+const LOSS_LIMIT: f64 = 0.02;
+
+/// Per-device AUDIO_DOWN pacing.  Clone-friendly; `Default` paces at
+/// real time and ignores reports (diagnostics tones).
+#[derive(Clone, Default)]
+pub struct DownlinkPacer {
+    enabled: bool,
+    rtt_limit: Duration,
+    links: Arc<DashMap<SocketAddr, Link>>,
+}
+
+struct Link {
+    srtt_ms: Option<f64>,
+    loss: f64,
+    pace: f64,
+    reports: u64,
+    /// Earliest time the next AUDIO_DOWN may leave
+    next_slot: Option<Instant>,
+}
+
+/// One device's link, as served by `GET /downlink`.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkSummary {
+    pub addr: SocketAddr,
+    pub srtt_ms: Option<f64>,
+    pub loss: f64,
+    /// Gap between packets as a multiple of their audio duration
+    pub pace: f64,
+    pub reports: u64,
+}
+
+impl Link {
+    fn new() -> Self {
+        Self { srtt_ms: None, loss: 0.0, pace: MIN_PACE, reports: 0, next_slot: None }
+    }
+}
+
+impl DownlinkPacer {
+    pub fn from_config(config: &Config) -> Self {
+        if config.downlink_pacing {
+            info!(rtt_limit_ms = config.downlink_rtt_limit_ms, "📶 adaptive AUDIO_DOWN pacing enabled");
+        }
+        Self {
+            enabled: config.downlink_pacing,
+            rtt_limit: Duration::from_millis(config.downlink_rtt_limit_ms),
+            links: Arc::default(),
+        }
+    }
+
+    /// Whether OpenAI audio is paced at all (`--downlink-pacing`).
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Record a heartbeat link report from `addr`.
+    pub fn report(&self, addr: SocketAddr, rtt_ms: u16, loss_permille: u16) {
+        let mut link = self.links.entry(addr).or_insert_with(Link::new);
+        let rtt = rtt_ms as f64;
+        let srtt = link.srtt_ms.map_or(rtt, |s| s * 0.875 + rtt * 0.125);
+        link.srtt_ms = Some(srtt);
+        link.loss = link.loss * 0.75 + ((loss_permille.min(1000) as f64) / 1000.0) * 0.25;
+        link.reports += 1;
+
+        let degraded = link.loss > LOSS_LIMIT || srtt > (self.rtt_limit.as_millis() as f64);
+        let pace = if degraded {
+            (link.pace * BACKOFF).min(MAX_PACE)
+        } else {
+            (link.pace - RECOVERY).max(MIN_PACE)
+        };
+        if degraded && pace > link.pace {
+            info!(
+                esp = %addr,
+                srtt_ms = srtt as u64,
+                loss = format!("{:.3}", link.loss),
+                pace = format!("{:.2}", pace),
+                "📶 downlink degraded — slowing AUDIO_DOWN"
+            );
+        }
+        link.pace = pace;
+    }
+
+    /// Gap between AUDIO_DOWN packets of `chunk` duration to `addr`.
+    pub fn gap(&self, addr: SocketAddr, chunk: Duration) -> Duration {
+        if !self.enabled {
+            return chunk;
+        }
+        let pace = self.links.get(&addr).map_or(MIN_PACE, |l| l.pace);
+        chunk.mul_f64(pace)
+    }
+
+    /// Wait for `addr`'s next send slot, then reserve the one after it
+    /// (`chunk` of audio later, scaled by the link's pace).
+    pub async fn pace(&self, addr: SocketAddr, chunk: Duration) {
+        let gap = self.gap(addr, chunk);
+        let slot = {
+            let mut link = self.links.entry(addr).or_insert_with(Link::new);
+            let now = Instant::now();
+            let slot = link.next_slot.filter(|s| *s > now).unwrap_or(now);
+            link.next_slot = Some(slot + gap);
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+
+    /// Every device seen, by address.
+    pub fn list(&self) -> Vec<LinkSummary> {
+        let mut list: Vec<LinkSummary> = self.links
+            .iter()
+            .map(|l| LinkSummary {
+                addr: *l.key(),
+                srtt_ms: l.srtt_ms,
+                loss: l.loss,
+                pace: if self.enabled { l.pace } else { 1.0 },
+                reports: l.reports,
+            })
+            .collect();
+        list.sort_by_key(|l| l.addr);
+        list
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pacer() -> DownlinkPacer {
+        DownlinkPacer { enabled: true, rtt_limit: Duration::from_millis(250), links: Arc::default() }
+    }
+
+    #[test]
+    fn test_backs_off_and_recovers() {
+        let pacer = pacer();
+        let addr: SocketAddr = "10.0.0.7:5000".parse().unwrap();
+        let chunk = Duration::from_secs(1);
+        assert_eq!(pacer.gap(addr, chunk), Duration::from_millis(500));
+
+        // 10 % loss: backs off to the slowest pace
+        for _ in 0..4 {
+            pacer.report(addr, 40, 100);
+        }
+        assert_eq!(pacer.gap(addr, chunk), Duration::from_millis(1500));
+
+        // Clean reports bring it back down once the loss average settles
+        for _ in 0..6 {
+            pacer.report(addr, 40, 0);
+        }
+        let gap = pacer.gap(addr, chunk);
+        assert!(gap < Duration::from_millis(1500) && gap > Duration::from_millis(500), "{gap:?}");
+
+        // High RTT alone also counts as degraded
+        let slow: SocketAddr = "10.0.0.8:5000".parse().unwrap();
+        pacer.report(slow, 400, 0);
+        assert_eq!(pacer.gap(slow, chunk), Duration::from_millis(750));
+        assert!(!DownlinkPacer::default().enabled());
+        assert_eq!(DownlinkPacer::default().gap(slow, chunk), chunk);
+    }
+
+    #[tokio::test]
+    async fn test_pace_spaces_packets() {
+        let pacer = pacer();
+        let addr: SocketAddr = "10.0.0.7:5000".parse().unwrap();
+        let started = Instant::now();
+        for _ in 0..4 {
+            pacer.pace(addr, Duration::from_millis(40)).await;
+        }
+        // First packet immediately, then 20 ms apart
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(60), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(200), "{elapsed:?}");
+    }
+}
@@ -0,0 +1,384 @@
+use crate::clock::{ self, SharedClock };
+use crate::transport_udp::{ prompt_mode, PromptMode };
+use crate::vad::{ VadKind, VadResult };
+use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::{ BTreeMap, VecDeque };
+use std::sync::Arc;
+use std::time::Duration;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Per-device emotion timeline
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Every emotional VadResult is folded into a 1-second bucket per
+//  device (sample count + V/A/D sums), kept for
+//  `--emotion-history-hours`.  `GET /devices/{id}/emotions` reads a
+//  range back, averaged down to the requested resolution:
+//
+//    ?from=<unix ms | RFC 3339>&to=<...>&resolution=<secs | 30s | 5m | 1h>
+//
+//  `from` / `to` default to the last hour; without `resolution` the
+//  range is cut into at most `AUTO_POINTS` points.  Each point carries
+//  the averaged V/A/D and the emotion label of that average (the same
+//  classification that picks the OpenAI prompt style); `labels` gives
+//  the share of samples per label over the whole range.
+//  `GET /sites/{site}/overview` pools a site's sensors into one timeline.
+//
+//  History is in memory only — it does not survive a restart.
+
+/// Target number of points when no resolution is given.
+const AUTO_POINTS: u64 = 500;
+
+/// Most points one query may return.
+const MAX_POINTS: u64 = 10_000;
+
+/// Default query range.
+const DEFAULT_RANGE_MS: u64 = 3_600_000;
+
+/// One second of samples for one device.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    second: u64,
+    samples: u32,
+    valence: f32,
+    arousal: f32,
+    dominance: f32,
+}
+
+/// One downsampled point of a timeline.
+#[derive(Debug, Clone, Serialize)]
+pub struct EmotionPoint {
+    /// Start of the point's interval (unix ms)
+    pub t_ms: u64,
+    pub samples: u32,
+    pub valence: f32,
+    pub arousal: f32,
+    pub dominance: f32,
+    pub label: PromptMode,
+}
+
+/// Response of `GET /devices/{id}/emotions` (and of a site overview,
+/// without `device`).
+#[derive(Debug, Clone, Serialize)]
+pub struct EmotionTimeline {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<u32>,
+    pub from_ms: u64,
+    pub to_ms: u64,
+    pub resolution_secs: u64,
+    pub points: Vec<EmotionPoint>,
+    /// Share of samples per label over the range
+    pub labels: BTreeMap<PromptMode, f32>,
+}
+
+/// Raw query parameters, as strings.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct EmotionQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub resolution: Option<String>,
+}
+
+/// Emotion history of every device.  Clone-friendly (Arc inside).
+#[derive(Clone)]
+pub struct EmotionHistory {
+    devices: Arc<DashMap<u32, VecDeque<Bucket>>>,
+    /// How long buckets are kept (zero = history disabled)
+    retention: Duration,
+    clock: SharedClock,
+}
+
+impl EmotionHistory {
+    pub fn new(retention: Duration, clock: SharedClock) -> Self {
+        Self { devices: Arc::new(DashMap::new()), retention, clock }
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.retention.is_zero()
+    }
+
+    /// Sensors with history.
+    pub fn devices(&self) -> Vec<u32> {
+        let mut devices: Vec<u32> = self.devices
+            .iter()
+            .map(|e| *e.key())
+            .collect();
+        devices.sort_unstable();
+        devices
+    }
+
+    /// Fold an emotional `result` into its device's current bucket.
+    pub fn record(&self, result: &VadResult) {
+        if !self.enabled() || result.kind != VadKind::Emotional {
+            return;
+        }
+        let second = clock::unix_millis(self.clock.as_ref()) / 1000;
+        let oldest = second.saturating_sub(self.retention.as_secs());
+        let mut buckets = self.devices.entry(result.sensor_id).or_default();
+
+        // VAD workers race, so a sample may land a second behind the newest
+        let pos = buckets
+            .iter()
+            .rposition(|b| b.second <= second)
+            .map_or(0, |i| i + 1);
+        match pos.checked_sub(1).and_then(|i| buckets.get_mut(i)) {
+            Some(bucket) if bucket.second == second => {
+                bucket.samples += 1;
+                bucket.valence += result.valence;
+                bucket.arousal += result.arousal;
+                bucket.dominance += result.dominance;
+            }
+            _ => {
+                buckets.insert(pos, Bucket {
+                    second,
+                    samples: 1,
+                    valence: result.valence,
+                    arousal: result.arousal,
+                    dominance: result.dominance,
+                });
+            }
+        }
+        while buckets.front().is_some_and(|b| b.second < oldest) {
+            buckets.pop_front();
+        }
+    }
+
+    /// Drop expired buckets and devices with nothing left; returns how
+    /// many devices were removed.
+    pub fn evict_expired(&self) -> usize {
+        let now = clock::unix_millis(self.clock.as_ref()) / 1000;
+        let oldest = now.saturating_sub(self.retention.as_secs());
+        let before = self.devices.len();
+        self.devices.retain(|_, buckets| {
+            while buckets.front().is_some_and(|b| b.second < oldest) {
+                buckets.pop_front();
+            }
+            !buckets.is_empty()
+        });
+        before - self.devices.len()
+    }
+
+    /// Timeline of `device` for `query` (parameter errors are returned as
+    /// a message for a 400).
+    pub fn query(&self, device: u32, query: &EmotionQuery) -> Result<EmotionTimeline, String> {
+        let timeline = self.query_combined(&[device], query)?;
+        Ok(EmotionTimeline { device: Some(device), ..timeline })
+    }
+
+    /// Timeline of several devices pooled together: every point averages
+    /// all their samples in its interval.
+    pub fn query_combined(&self, devices: &[u32], query: &EmotionQuery) -> Result<EmotionTimeline, String> {
+        let now_ms = clock::unix_millis(self.clock.as_ref());
+        let to_ms = match &query.to {
+            Some(s) => parse_time(s).map_err(|e| format!("invalid `to`: {e}"))?,
+            None => now_ms,
+        };
+        let from_ms = match &query.from {
+            Some(s) => parse_time(s).map_err(|e| format!("invalid `from`: {e}"))?,
+            None => to_ms.saturating_sub(DEFAULT_RANGE_MS),
+        };
+        if from_ms >= to_ms {
+            return Err("`from` must be before `to`".into());
+        }
+        let span_secs = (to_ms - from_ms).div_ceil(1000);
+        let resolution_secs = match &query.resolution {
+            Some(s) => parse_resolution(s).map_err(|e| format!("invalid `resolution`: {e}"))?,
+            None => span_secs.div_ceil(AUTO_POINTS).max(1),
+        };
+        if span_secs.div_ceil(resolution_secs) > MAX_POINTS {
+            return Err(format!("range / resolution gives more than {MAX_POINTS} points"));
+        }
+
+        let mut points: Vec<EmotionPoint> = Vec::new();
+        let mut label_samples: BTreeMap<PromptMode, u64> = BTreeMap::new();
+        let mut in_range: Vec<Bucket> = Vec::new();
+        for device in devices {
+            if let Some(buckets) = self.devices.get(device) {
+                in_range.extend(buckets.iter().filter(|b| b.second * 1000 >= from_ms && b.second * 1000 < to_ms));
+            }
+        }
+        if devices.len() > 1 {
+            in_range.sort_by_key(|b| b.second);
+        }
+        let first = from_ms / 1000;
+        let mut current: Option<(u64, Bucket)> = None;
+        for b in &in_range {
+            let slot = (b.second - first) / resolution_secs;
+            match current.as_mut() {
+                Some((s, acc)) if *s == slot => {
+                    acc.samples += b.samples;
+                    acc.valence += b.valence;
+                    acc.arousal += b.arousal;
+                    acc.dominance += b.dominance;
+                }
+                _ => {
+                    if let Some((s, acc)) = current.replace((slot, *b)) {
+                        points.push(point(from_ms, resolution_secs, s, &acc, &mut label_samples));
+                    }
+                }
+            }
+        }
+        if let Some((s, acc)) = current {
+            points.push(point(from_ms, resolution_secs, s, &acc, &mut label_samples));
+        }
+
+        let total: u64 = label_samples.values().sum();
+        let labels = label_samples
+            .into_iter()
+            .map(|(label, n)| (label, (n as f32) / (total as f32)))
+            .collect();
+        Ok(EmotionTimeline { device: None, from_ms, to_ms, resolution_secs, points, labels })
+    }
+}
+
+/// Average a downsampled slot and count its samples towards its label.
+fn point(
+    from_ms: u64,
+    resolution_secs: u64,
+    slot: u64,
+    acc: &Bucket,
+    label_samples: &mut BTreeMap<PromptMode, u64>
+) -> EmotionPoint {
+    let n = acc.samples.max(1) as f32;
+    let (valence, arousal, dominance) = (acc.valence / n, acc.arousal / n, acc.dominance / n);
+    let label = prompt_mode(valence, arousal, dominance);
+    *label_samples.entry(label).or_default() += acc.samples as u64;
+    EmotionPoint {
+        t_ms: (from_ms / 1000 + slot * resolution_secs) * 1000,
+        samples: acc.samples,
+        valence,
+        arousal,
+        dominance,
+        label,
+    }
+}
+
+/// Unix milliseconds or an RFC 3339 timestamp.
+pub(crate) fn parse_time(s: &str) -> Result<u64, String> {
+    if let Ok(ms) = s.parse::<u64>() {
+        return Ok(ms);
+    }
+    let t = chrono::DateTime::parse_from_rfc3339(s).map_err(|_| format!("{s:?} is neither unix ms nor RFC 3339"))?;
+    u64::try_from(t.timestamp_millis()).map_err(|_| format!("{s:?} is before 1970"))
+}
+
+/// Seconds, optionally suffixed `s`, `m` or `h`.
+fn parse_resolution(s: &str) -> Result<u64, String> {
+    let (digits, unit) = match s.char_indices().last() {
+        Some((i, 's')) => (&s[..i], 1),
+        Some((i, 'm')) => (&s[..i], 60),
+        Some((i, 'h')) => (&s[..i], 3600),
+        _ => (s, 1),
+    };
+    match digits.parse::<u64>() {
+        Ok(n) if n > 0 => Ok(n * unit),
+        _ => Err(format!("{s:?} (expected e.g. 10, 30s, 5m, 1h)")),
+    }
+}
+
+/// Periodically drop expired history (no-op when history is disabled).
+pub async fn evict_expired_loop(history: EmotionHistory) {
+    if !history.enabled() {
+        return;
+    }
+    loop {
+        history.clock.sleep(Duration::from_secs(60)).await;
+        let evicted = history.evict_expired();
+        if evicted > 0 {
+            tracing::info!(evicted, "🧹 evicted expired emotion history");
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimClock;
+
+    fn emotional(sensor_id: u32, valence: f32, arousal: f32, dominance: f32) -> VadResult {
+        VadResult {
+            sensor_id,
+            seq: 0,
+            kind: VadKind::Emotional,
+            is_active: arousal > 0.35,
+            energy: 0.0,
+            threshold: 0.0,
+            dbfs: -96.0,
+            zcr: 0.0,
+            band_ratio: 0.0,
+            valence,
+            arousal,
+            dominance,
+            trace: None,
+        }
+    }
+
+    fn history(sim: &SimClock, hours: u64) -> EmotionHistory {
+        EmotionHistory::new(Duration::from_secs(hours * 3600), Arc::new(sim.clone()))
+    }
+
+    fn query(from: &str, to: &str, resolution: Option<&str>) -> EmotionQuery {
+        EmotionQuery {
+            from: Some(from.into()),
+            to: Some(to.into()),
+            resolution: resolution.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_downsampling_and_labels() {
+        let sim = SimClock::starting_at(std::time::UNIX_EPOCH);
+        let history = history(&sim, 1);
+        // 10 s energetic, then 10 s calm, 2 samples per second
+        for i in 0..20 {
+            let (v, a) = if i < 10 { (0.9, 0.9) } else { (0.45, 0.1) };
+            history.record(&emotional(3, v, a, 0.5));
+            history.record(&emotional(3, v, a, 0.5));
+            if i < 10 {
+                history.record(&emotional(4, 0.45, 0.1, 0.5));
+            }
+            sim.advance(Duration::from_secs(1));
+        }
+
+        let timeline = history.query(3, &query("0", "20000", Some("5s"))).unwrap();
+        assert_eq!(timeline.resolution_secs, 5);
+        assert_eq!(timeline.points.len(), 4);
+        assert_eq!(timeline.points[1].t_ms, 5_000);
+        assert_eq!(timeline.points[0].samples, 10);
+        assert_eq!(timeline.points[0].label, PromptMode::Energetic);
+        assert_eq!(timeline.points[3].label, PromptMode::Calm);
+        assert_eq!(timeline.labels[&PromptMode::Energetic], 0.5);
+
+        // Auto resolution, RFC 3339 bounds, and an unknown device
+        let rfc = query("1970-01-01T00:00:00Z", "1970-01-01T00:00:20Z", None);
+        assert_eq!(history.query(3, &rfc).unwrap().points.len(), 20);
+        assert!(history.query(9, &rfc).unwrap().points.is_empty());
+
+        // Sensors pooled: one sample of sensor 4 per second joins sensor 3's
+        let pooled = history.query_combined(&[3, 4], &query("0", "20000", Some("5s"))).unwrap();
+        assert_eq!(pooled.device, None);
+        assert_eq!(pooled.points.iter().map(|p| p.samples).collect::<Vec<_>>(), [15, 15, 10, 10]);
+        assert!((pooled.points[0].arousal - (10.0 * 0.9 + 5.0 * 0.1) / 15.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_retention_and_bad_params() {
+        let sim = SimClock::starting_at(std::time::UNIX_EPOCH);
+        let history = history(&sim, 1);
+        history.record(&emotional(1, 0.5, 0.5, 0.5));
+        sim.advance(Duration::from_secs(2 * 3600));
+        assert_eq!(history.evict_expired(), 1);
+
+        for (from, to, resolution) in [("5", "1", None), ("x", "1", None), ("0", "10", Some("0m"))] {
+            assert!(history.query(1, &query(from, to, resolution)).is_err());
+        }
+        assert!(history.query(1, &query("0", "86400000", Some("1s"))).is_err());
+        assert_eq!(parse_resolution("5m"), Ok(300));
+        assert_eq!(parse_resolution("2h"), Ok(7200));
+    }
+}
@@ -0,0 +1,57 @@
+//! Learned emotional model (ONNX Runtime).
+//!
+//! Input:  `[1, EMOTION_FEATURES + 4]` f32 — the 20 smoothed level/rate
+//!         features from `vad.rs` followed by a one-hot persona embedding
+//!         in `PersonaTrait::index()` order.
+//! Output: first output tensor, ≥ 3 f32 values — valence, arousal,
+//!         dominance (clamped to \[0, 1\] by the caller).
+//!
+//! ONNX Runtime is loaded dynamically at startup (`ORT_DYLIB_PATH` or the
+//! system library search path), so the binary itself stays static.
+
+use crate::persona::PersonaTrait;
+use crate::vad::EMOTION_FEATURES;
+use ort::session::Session;
+use ort::value::Tensor;
+use std::sync::Mutex;
+
+/// Width of the model input: features + one-hot persona.
+pub const MODEL_INPUTS: usize = EMOTION_FEATURES + PersonaTrait::ALL.len();
+
+/// A loaded ONNX emotion model, shared across VAD workers.
+pub struct OnnxEmotionModel {
+    session: Mutex<Session>,
+}
+
+impl OnnxEmotionModel {
+    /// Load a model from disk.
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let session = Session::builder()
+            .map_err(|e| anyhow::anyhow!("ONNX Runtime init failed: {e}"))?
+            .commit_from_file(path)
+            .map_err(|e| anyhow::anyhow!("failed to load ONNX model {path}: {e}"))?;
+        Ok(Self { session: Mutex::new(session) })
+    }
+
+    /// Run one inference; returns the raw (unclamped) V/A/D triple.
+    pub fn infer(
+        &self,
+        features: &[f32; EMOTION_FEATURES],
+        persona: PersonaTrait
+    ) -> anyhow::Result<(f32, f32, f32)> {
+        let mut input = Vec::with_capacity(MODEL_INPUTS);
+        input.extend_from_slice(features);
+        for p in PersonaTrait::ALL {
+            input.push(if p == persona { 1.0 } else { 0.0 });
+        }
+
+        let tensor = Tensor::from_array(([1usize, MODEL_INPUTS], input))?;
+        let mut session = self.session.lock().unwrap_or_else(|e| e.into_inner());
+        let outputs = session.run(ort::inputs![tensor])?;
+        let (_shape, data) = outputs[0].try_extract_tensor::<f32>()?;
+        if data.len() < 3 {
+            anyhow::bail!("ONNX model returned {} values, expected 3 (V/A/D)", data.len());
+        }
+        Ok((data[0], data[1], data[2]))
+    }
+}
@@ -0,0 +1,133 @@
+// ─────────────────────────────────────────────────────────────────────
+//  Library error type
+// ─────────────────────────────────────────────────────────────────────
+//
+//  The embedding surface — `bridge::serve` / `serve_instance`, the
+//  loopback transport, `spawn_openai_session` — returns `BridgeError`,
+//  so embedders can match on what failed instead of string-matching an
+//  anyhow chain:
+//
+//    Transport   sockets, the REST listener, a transport that stopped
+//    Protocol    wire data the bridge cannot accept
+//    OpenAi      the OpenAI Realtime connection
+//    Storage     files and directories (recordings, clips, templates)
+//    Config      invalid flags or configuration files
+//
+//  The building blocks underneath keep returning anyhow; their failures
+//  are classified where the bridge calls them (`ResultExt`), with the
+//  original error kept as `source()`.
+
+/// Underlying cause of a [`BridgeError`].
+pub type Source = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// What a bridge operation failed on.
+#[derive(Debug, thiserror::Error)]
+pub enum BridgeError {
+    /// Sockets, the REST listener, or a transport that stopped
+    #[error("transport: {context}")]
+    Transport {
+        context: String,
+        #[source]
+        source: Option<Source>,
+    },
+    /// Wire data the bridge cannot accept
+    #[error("protocol: {context}")]
+    Protocol {
+        context: String,
+    },
+    /// The OpenAI Realtime connection
+    #[error("openai: {context}")]
+    OpenAi {
+        context: String,
+        #[source]
+        source: Option<Source>,
+    },
+    /// Files and directories
+    #[error("storage: {context}")]
+    Storage {
+        context: String,
+        #[source]
+        source: Source,
+    },
+    /// Invalid flags or configuration files
+    #[error("config: {context}")]
+    Config {
+        context: String,
+        #[source]
+        source: Source,
+    },
+}
+
+/// `Result` of the library surface.
+pub type Result<T, E = BridgeError> = std::result::Result<T, E>;
+
+impl BridgeError {
+    /// A transport failure without an underlying error (e.g. a channel
+    /// whose other side is gone).
+    pub fn transport(context: impl Into<String>) -> Self {
+        BridgeError::Transport { context: context.into(), source: None }
+    }
+
+    /// Wire data refused before it reached the pipeline.
+    pub fn protocol(context: impl Into<String>) -> Self {
+        BridgeError::Protocol { context: context.into() }
+    }
+
+    /// An OpenAI failure without an underlying error.
+    pub fn openai(context: impl Into<String>) -> Self {
+        BridgeError::OpenAi { context: context.into(), source: None }
+    }
+}
+
+/// Classify the error of a fallible building block.
+pub trait ResultExt<T> {
+    fn transport(self, context: impl Into<String>) -> Result<T>;
+    fn openai(self, context: impl Into<String>) -> Result<T>;
+    fn storage(self, context: impl Into<String>) -> Result<T>;
+    fn config(self, context: impl Into<String>) -> Result<T>;
+}
+
+impl<T, E: Into<Source>> ResultExt<T> for std::result::Result<T, E> {
+    fn transport(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|e| BridgeError::Transport { context: context.into(), source: Some(e.into()) })
+    }
+
+    fn openai(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|e| BridgeError::OpenAi { context: context.into(), source: Some(e.into()) })
+    }
+
+    fn storage(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|e| BridgeError::Storage { context: context.into(), source: e.into() })
+    }
+
+    fn config(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|e| BridgeError::Config { context: context.into(), source: e.into() })
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::{ serve_instance, Transport };
+    use crate::config::Cli;
+    use crate::transport_loopback::loopback;
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn test_embedders_can_match_failure_kinds() {
+        let config = Cli::parse_from(["vad-sensor-bridge", "--sensor-schemas", "/nonexistent/schemas.toml"]).serve;
+        let (handle, transport) = loopback(4);
+        let err = serve_instance(config, None, Transport::Loopback(transport)).await.unwrap_err();
+        assert!(matches!(err, BridgeError::Config { .. }), "{err}");
+        // The building block's error stays reachable as the source
+        assert!(std::error::Error::source(&err).unwrap().to_string().contains("schemas.toml"));
+
+        // The instance is gone, so the handle's transport has stopped
+        let sent = handle.send_vector(1, 0, Default::default()).await;
+        assert!(matches!(sent, Err(BridgeError::Transport { source: None, .. })));
+    }
+}
@@ -1,16 +1,20 @@
-/// ESP32 ↔ Server UDP Audio Protocol
-///
-/// Packet format (4-byte header + variable payload):
-/// ```text
-/// ┌─────────────┬──────────┬──────────┬────────────────┐
-/// │ Byte 0-1    │ Byte 2   │ Byte 3   │ Byte 4..N      │
-/// │ Seq Num     │ Type     │ Flags    │ Payload         │
-/// │ (uint16 LE) │ (uint8)  │ (uint8)  │ (up to 1400B)  │
-/// └─────────────┴──────────┴──────────┴────────────────┘
-/// ```
-///
-/// Audio format: 16-bit LE PCM, 16 kHz, mono.
-/// 1400 B payload = 700 samples = 43.75 ms per packet.
+//! ESP32 ↔ Server UDP Audio Protocol
+//!
+//! Packet format (4-byte header + variable payload):
+//! ```text
+//! ┌─────────────┬──────────┬──────────┬────────────────┐
+//! │ Byte 0-1    │ Byte 2   │ Byte 3   │ Byte 4..N      │
+//! │ Seq Num     │ Type     │ Flags    │ Payload         │
+//! │ (uint16 LE) │ (uint8)  │ (uint8)  │ (up to 1400B)  │
+//! └─────────────┴──────────┴──────────┴────────────────┘
+//! ```
+//!
+//! Audio format: 16-bit LE PCM, 16 kHz, mono.
+//! 1400 B payload = 700 samples = 43.75 ms per packet.
+//!
+//! Multi-mic devices may declare a channel count in SESSION_START
+//! (`[CTRL_SESSION_START, channels]`); their AUDIO_UP payloads are then
+//! interleaved frames of that many samples.
 
 // ═══════════════════════════════════════════════════════════════════════
 //  Constants
@@ -34,6 +38,10 @@ pub const PKT_AUDIO_DOWN: u8 = 0x02;
 pub const PKT_CONTROL: u8 = 0x03;
 /// Bidirectional: keep-alive / RTT measurement.
 pub const PKT_HEARTBEAT: u8 = 0x04;
+/// Bidirectional: OTA firmware transfer (first payload byte = `OTA_*`).
+pub const PKT_OTA: u8 = 0x05;
+/// ESP → Server: log line or telemetry counters (first payload byte = `TELEMETRY_*`).
+pub const PKT_TELEMETRY: u8 = 0x06;
 
 // ── Flags (bitfield in byte 3) ─────────────────────────────────────────
 
@@ -46,7 +54,8 @@ pub const FLAG_URGENT: u8 = 0x04;
 
 // ── Control Commands (first byte of payload when type == PKT_CONTROL) ──
 
-/// ESP → Server: wake word detected, begin session.
+/// ESP → Server: wake word detected, begin session; optional second
+/// payload byte = channel count (absent / 0 = mono).
 pub const CTRL_SESSION_START: u8 = 0x01;
 /// ESP → Server: user stopped speaking (timeout / silence).
 pub const CTRL_SESSION_END: u8 = 0x02;
@@ -54,12 +63,45 @@ pub const CTRL_SESSION_END: u8 = 0x02;
 pub const CTRL_STREAM_START: u8 = 0x03;
 /// Server → ESP: finished sending audio response.
 pub const CTRL_STREAM_END: u8 = 0x04;
-/// Bidirectional: acknowledge a control message.
+/// Bidirectional: acknowledge a control message.  Server ACKs carry
+/// `[CTRL_ACK, acked_cmd, status]` (see `ACK_*`).
 pub const CTRL_ACK: u8 = 0x05;
 /// Bidirectional: abort current session.
 pub const CTRL_CANCEL: u8 = 0x06;
 /// Server → ESP: server is ready for audio.
 pub const CTRL_SERVER_READY: u8 = 0x07;
+/// Server → ESP: stream the microphone back (no session) for diagnostics.
+pub const CTRL_LOOPBACK_START: u8 = 0x08;
+/// Server → ESP: stop the diagnostics mic loopback.
+pub const CTRL_LOOPBACK_END: u8 = 0x09;
+/// Server → ESP: a request was refused; payload
+/// `[CTRL_ERROR, refused_cmd, code]`.
+pub const CTRL_ERROR: u8 = 0x0a;
+/// Server → ESP: set playback volume; payload `[CTRL_SET_VOLUME, volume]`
+/// with `volume` in percent (0..=100).
+pub const CTRL_SET_VOLUME: u8 = 0x0b;
+/// Server → ESP: mute / unmute playback; payload `[CTRL_MUTE, 1 | 0]`.
+pub const CTRL_MUTE: u8 = 0x0c;
+
+// ── ACK status (third payload byte of a server `CTRL_ACK`) ──
+
+/// Done: for SESSION_END the audio was saved and committed, for CANCEL
+/// the session was discarded.
+pub const ACK_OK: u8 = 0x00;
+/// No session was receiving; nothing to end or cancel.
+pub const ACK_NO_SESSION: u8 = 0x01;
+/// SESSION_END accepted but no audio had arrived; nothing saved.
+pub const ACK_NO_AUDIO: u8 = 0x02;
+/// SESSION_END accepted but the session WAV could not be written.
+pub const ACK_SAVE_FAILED: u8 = 0x03;
+
+// ── Error codes (third payload byte of `CTRL_ERROR`) ──
+
+/// SESSION_START refused: a session is already receiving
+/// (`--duplicate-start-policy reject`).
+pub const ERR_SESSION_ACTIVE: u8 = 0x01;
+/// SESSION_START refused: more channels declared than `MAX_CHANNELS`.
+pub const ERR_BAD_CHANNELS: u8 = 0x02;
 
 // ═══════════════════════════════════════════════════════════════════════
 //  Parsed Packet
@@ -90,7 +132,7 @@ impl EspPacket {
         let payload = buf[ESP_HEADER_SIZE..].to_vec();
 
         // Validate known packet type
-        if !matches!(pkt_type, PKT_AUDIO_UP | PKT_AUDIO_DOWN | PKT_CONTROL | PKT_HEARTBEAT) {
+        if !matches!(pkt_type, PKT_AUDIO_UP | PKT_AUDIO_DOWN | PKT_CONTROL | PKT_HEARTBEAT | PKT_OTA | PKT_TELEMETRY) {
             return None;
         }
 
@@ -128,6 +170,11 @@ impl EspPacket {
             None
         }
     }
+
+    /// Channel count declared by a SESSION_START (1 when absent or 0).
+    pub fn start_channels(&self) -> u16 {
+        self.payload.get(1).map_or(1, |&c| (c as u16).max(1))
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════
@@ -149,16 +196,214 @@ pub fn build_control(seq_num: u16, cmd: u8, flags: u8) -> Vec<u8> {
     build_packet(seq_num, PKT_CONTROL, flags, &[cmd])
 }
 
+/// Build an ACK for control command `cmd` (payload =
+/// `[CTRL_ACK, cmd, status]`).  Firmware that only checks the first
+/// payload byte still sees a plain ACK.
+pub fn build_ack(seq_num: u16, cmd: u8, status: u8) -> Vec<u8> {
+    build_packet(seq_num, PKT_CONTROL, 0, &[CTRL_ACK, cmd, status])
+}
+
+/// Build an error control packet refusing command `cmd` (payload =
+/// `[CTRL_ERROR, cmd, code]`).
+pub fn build_error(seq_num: u16, cmd: u8, code: u8) -> Vec<u8> {
+    build_packet(seq_num, PKT_CONTROL, 0, &[CTRL_ERROR, cmd, code])
+}
+
+/// Build a volume setting (payload = `[CTRL_SET_VOLUME, volume]`).
+pub fn build_set_volume(seq_num: u16, volume: u8) -> Vec<u8> {
+    build_packet(seq_num, PKT_CONTROL, 0, &[CTRL_SET_VOLUME, volume.min(100)])
+}
+
+/// Build a mute switch (payload = `[CTRL_MUTE, 1 | 0]`).
+pub fn build_mute(seq_num: u16, muted: bool) -> Vec<u8> {
+    build_packet(seq_num, PKT_CONTROL, 0, &[CTRL_MUTE, muted as u8])
+}
+
 /// Build a heartbeat response mirroring the incoming sequence number.
 pub fn build_heartbeat(seq_num: u16) -> Vec<u8> {
     build_packet(seq_num, PKT_HEARTBEAT, 0, &[])
 }
 
+/// Link report an ESP may append to its HEARTBEAT: `(rtt_ms,
+/// loss_permille)` from payload `[rtt_ms u16][loss_permille u16]` (LE).
+pub fn parse_link_report(payload: &[u8]) -> Option<(u16, u16)> {
+    if payload.len() < 4 {
+        return None;
+    }
+    Some((u16::from_le_bytes([payload[0], payload[1]]), u16::from_le_bytes([payload[2], payload[3]])))
+}
+
 /// Build an audio-down packet (type = `PKT_AUDIO_DOWN`).
 pub fn build_audio_down(seq_num: u16, flags: u8, pcm: &[u8]) -> Vec<u8> {
     build_packet(seq_num, PKT_AUDIO_DOWN, flags, pcm)
 }
 
+// ═══════════════════════════════════════════════════════════════════════
+//  OTA Firmware Transfer (type == PKT_OTA)
+// ═══════════════════════════════════════════════════════════════════════
+//
+// Pull-based: the server offers an image, the ESP asks for it chunk by
+// chunk at the offset it needs next, so a lost packet is simply asked for
+// again and an interrupted transfer resumes from wherever the ESP's flash
+// got to.  All integers are little-endian.
+//
+//   Server → ESP  OFFER    [0x01][size u32][crc32 u32][chunk u16][name_len u8][name]
+//   ESP → Server  REQUEST  [0x02][offset u32]
+//   Server → ESP  CHUNK    [0x03][offset u32][crc32 of data u32][data]
+//   ESP → Server  DONE     [0x04][status u8]          0 = image CRC ok, staged
+//   both          ABORT    [0x05][reason u8]          see OTA_ABORT_*
+//
+// CRC-32 is IEEE 802.3 (zlib / esp_rom_crc32_le).
+
+/// Server → ESP: firmware image available.
+pub const OTA_OFFER: u8 = 0x01;
+/// ESP → Server: send the chunk starting at `offset`.
+pub const OTA_REQUEST: u8 = 0x02;
+/// Server → ESP: one chunk of the image.
+pub const OTA_CHUNK: u8 = 0x03;
+/// ESP → Server: whole image received (status 0) or failed.
+pub const OTA_DONE: u8 = 0x04;
+/// Bidirectional: stop the transfer (ESP: decline the offer).
+pub const OTA_ABORT: u8 = 0x05;
+
+/// Abort reason: no transfer offered to this device.
+pub const OTA_ABORT_UNKNOWN: u8 = 0x01;
+/// Abort reason: requested offset is past the end of the image.
+pub const OTA_ABORT_BAD_OFFSET: u8 = 0x02;
+/// Abort reason: the transfer was replaced or cancelled.
+pub const OTA_ABORT_CANCELLED: u8 = 0x03;
+
+/// Bytes in front of the data of an OTA_CHUNK payload.
+pub const OTA_CHUNK_HEADER: usize = 9;
+
+/// An OTA message from the ESP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtaMessage {
+    Request {
+        offset: u32,
+    },
+    Done {
+        status: u8,
+    },
+    Abort {
+        reason: u8,
+    },
+}
+
+impl OtaMessage {
+    /// Parse the payload of an ESP → Server `PKT_OTA` packet.
+    pub fn parse(payload: &[u8]) -> Option<Self> {
+        match *payload {
+            [OTA_REQUEST, a, b, c, d, ..] => Some(OtaMessage::Request { offset: u32::from_le_bytes([a, b, c, d]) }),
+            [OTA_DONE, status, ..] => Some(OtaMessage::Done { status }),
+            [OTA_ABORT, reason, ..] => Some(OtaMessage::Abort { reason }),
+            _ => None,
+        }
+    }
+}
+
+/// CRC-32 (IEEE, reflected, poly 0xEDB88320).
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Build an OTA_OFFER packet (`name` is cut to 255 bytes).
+pub fn build_ota_offer(seq_num: u16, size: u32, crc: u32, chunk_size: u16, name: &str) -> Vec<u8> {
+    let name = &name.as_bytes()[..name.len().min(255)];
+    let mut payload = Vec::with_capacity(12 + name.len());
+    payload.push(OTA_OFFER);
+    payload.extend_from_slice(&size.to_le_bytes());
+    payload.extend_from_slice(&crc.to_le_bytes());
+    payload.extend_from_slice(&chunk_size.to_le_bytes());
+    payload.push(name.len() as u8);
+    payload.extend_from_slice(name);
+    build_packet(seq_num, PKT_OTA, 0, &payload)
+}
+
+/// Build an OTA_CHUNK packet carrying `data` from `offset`.
+pub fn build_ota_chunk(seq_num: u16, offset: u32, data: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(OTA_CHUNK_HEADER + data.len());
+    payload.push(OTA_CHUNK);
+    payload.extend_from_slice(&offset.to_le_bytes());
+    payload.extend_from_slice(&crc32(data).to_le_bytes());
+    payload.extend_from_slice(data);
+    build_packet(seq_num, PKT_OTA, 0, &payload)
+}
+
+/// Build an OTA_ABORT packet.
+pub fn build_ota_abort(seq_num: u16, reason: u8) -> Vec<u8> {
+    build_packet(seq_num, PKT_OTA, 0, &[OTA_ABORT, reason])
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Device Telemetry (type == PKT_TELEMETRY)
+// ═══════════════════════════════════════════════════════════════════════
+//
+// Fire-and-forget, ESP → Server only; nothing is acknowledged.  Integers
+// are little-endian.
+//
+//   LOG       [0x01][level u8][text]        ESP-IDF level: 1 error, 2 warn,
+//                                           3 info, 4 debug, 5 verbose;
+//                                           text UTF-8, no newline needed
+//   COUNTERS  [0x02]([id u8][value i32])*   any number of TELEMETRY_COUNTER_*
+//                                           records (unknown ids are kept)
+
+/// A firmware log line.
+pub const TELEMETRY_LOG: u8 = 0x01;
+/// A batch of counter values.
+pub const TELEMETRY_COUNTERS: u8 = 0x02;
+
+/// Counter: free heap (bytes).
+pub const TELEMETRY_COUNTER_HEAP_FREE: u8 = 0x01;
+/// Counter: Wi-Fi RSSI (dBm).
+pub const TELEMETRY_COUNTER_RSSI: u8 = 0x02;
+/// Counter: battery voltage (mV).
+pub const TELEMETRY_COUNTER_BATTERY_MV: u8 = 0x03;
+/// Counter: seconds since boot.
+pub const TELEMETRY_COUNTER_UPTIME_S: u8 = 0x04;
+
+/// A telemetry message from the ESP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TelemetryMessage {
+    Log {
+        level: u8,
+        text: String,
+    },
+    /// (counter id, value) pairs in wire order
+    Counters(Vec<(u8, i32)>),
+}
+
+impl TelemetryMessage {
+    /// Parse the payload of a `PKT_TELEMETRY` packet.  Counters must be
+    /// whole 5-byte records.
+    pub fn parse(payload: &[u8]) -> Option<Self> {
+        match payload {
+            [TELEMETRY_LOG, level, text @ ..] =>
+                Some(TelemetryMessage::Log {
+                    level: *level,
+                    text: String::from_utf8_lossy(text).trim_end().to_string(),
+                }),
+            [TELEMETRY_COUNTERS, records @ ..] if records.len() % 5 == 0 =>
+                Some(
+                    TelemetryMessage::Counters(
+                        records
+                            .chunks_exact(5)
+                            .map(|r| (r[0], i32::from_le_bytes([r[1], r[2], r[3], r[4]])))
+                            .collect()
+                    )
+                ),
+            _ => None,
+        }
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════
 //  Session State Machine
 // ═══════════════════════════════════════════════════════════════════════
@@ -214,19 +459,18 @@ pub struct EspSession {
     pub audio_packets: u32,
     /// Total audio bytes received this session.
     pub audio_bytes: u64,
-    /// Accumulated raw PCM buffer for the session.
-    pub audio_buffer: Vec<u8>,
     /// Number of detected sequence gaps (lost packets).
     pub packets_lost: u32,
+    /// Interleaved channels per AUDIO_UP frame (declared in SESSION_START).
+    pub channels: u16,
     /// Timestamp when the session entered `Receiving`.
     pub started_at: std::time::Instant,
 }
 
 impl EspSession {
-    /// Create a new idle session for the given client address.
-    ///
-    /// Pre-allocates ~30 s of 16 kHz/16-bit/mono audio (960 kB).
-    pub fn new(addr: std::net::SocketAddr) -> Self {
+    /// Create a new idle session for the given client address at `now`
+    /// (pipeline clock).
+    pub fn new(addr: std::net::SocketAddr, now: std::time::Instant) -> Self {
         EspSession {
             state: SessionState::Idle,
             addr,
@@ -235,9 +479,9 @@ impl EspSession {
             last_recv_seq: 0,
             audio_packets: 0,
             audio_bytes: 0,
-            audio_buffer: Vec::with_capacity(16_000 * 2 * 30),
             packets_lost: 0,
-            started_at: std::time::Instant::now(),
+            channels: 1,
+            started_at: now,
         }
     }
 
@@ -248,7 +492,7 @@ impl EspSession {
         s
     }
 
-    /// Record an incoming audio packet: append payload, detect gaps.
+    /// Record an incoming audio packet: count it, detect gaps.
     pub fn record_audio(&mut self, seq: u16, payload: &[u8]) {
         if self.audio_packets > 0 {
             let expected = self.last_recv_seq.wrapping_add(1);
@@ -260,27 +504,27 @@ impl EspSession {
         self.last_recv_seq = seq;
         self.audio_packets += 1;
         self.audio_bytes += payload.len() as u64;
-        self.audio_buffer.extend_from_slice(payload);
     }
 
-    /// Reset all counters and transition to `Idle`.
-    pub fn reset(&mut self) {
+    /// Reset all counters and transition to `Idle`; the session restarts
+    /// at `now`.
+    pub fn reset(&mut self, now: std::time::Instant) {
         self.state = SessionState::Idle;
         self.audio_packets = 0;
         self.audio_bytes = 0;
-        self.audio_buffer.clear();
         self.packets_lost = 0;
-        self.started_at = std::time::Instant::now();
+        self.channels = 1;
+        self.started_at = now;
     }
 
-    /// Wall-clock duration since the session started receiving.
-    pub fn elapsed(&self) -> std::time::Duration {
-        self.started_at.elapsed()
+    /// Duration from the session start until `now`.
+    pub fn elapsed(&self, now: std::time::Instant) -> std::time::Duration {
+        now.saturating_duration_since(self.started_at)
     }
 
-    /// Estimated audio duration in seconds (16 kHz, 16-bit, mono).
+    /// Estimated audio duration in seconds (16 kHz, 16-bit, `channels`).
     pub fn audio_duration_secs(&self) -> f64 {
-        (self.audio_bytes as f64) / (16_000.0 * 2.0)
+        (self.audio_bytes as f64) / (16_000.0 * 2.0 * (self.channels as f64))
     }
 }
 
@@ -311,6 +555,8 @@ pub const NOTIFY_CMD_STOP: u8 = 0x50;
 pub const NOTIFY_CMD_SERVER_READY: u8 = 0x52;
 /// Server → ESP: acknowledge.
 pub const NOTIFY_CMD_ACK: u8 = 0x53;
+/// Server → ESP: START refused, a session is already receiving.
+pub const NOTIFY_CMD_ERROR: u8 = 0x54;
 
 /// Fixed size of a notification packet.
 pub const NOTIFY_PACKET_SIZE: usize = 14;
@@ -404,18 +650,23 @@ impl NotifyPacket {
 
     /// Format the MAC address as a colon-separated hex string.
     pub fn mac_str(&self) -> String {
-        format!(
-            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
-            self.mac[0],
-            self.mac[1],
-            self.mac[2],
-            self.mac[3],
-            self.mac[4],
-            self.mac[5]
-        )
+        format_mac(&self.mac)
     }
 }
 
+/// Format a MAC address as a colon-separated lowercase hex string.
+pub fn format_mac(mac: &[u8; 6]) -> String {
+    format!(
+        "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        mac[0],
+        mac[1],
+        mac[2],
+        mac[3],
+        mac[4],
+        mac[5]
+    )
+}
+
 /// Compute the XOR checksum for a notification packet (all bytes except
 /// the checksum position itself).
 pub fn compute_notify_checksum(buf: &[u8]) -> u8 {
@@ -464,6 +715,10 @@ pub const S2D_FOOTER_1: u8 = 0xF5;
 pub const S2D_CMD_AUDIO_SETTINGS: u8 = 0x5A;
 /// CMD: stop playback.
 pub const S2D_CMD_STOP: u8 = 0x0A;
+/// CMD: set playback volume (percent).
+pub const S2D_CMD_SET_VOLUME: u8 = 0x0B;
+/// CMD: mute (1) / unmute (0) playback.
+pub const S2D_CMD_MUTE: u8 = 0x0C;
 
 /// CRC-8 with polynomial 0x07.
 ///
@@ -532,3 +787,89 @@ pub fn build_s2d_stop() -> [u8; 8] {
     f[7] = S2D_FOOTER_1;
     f
 }
+
+/// Build a set-volume frame (CMD 0x0B) — 9 bytes.
+///
+/// Tells the device to play at `volume` percent:
+///   `[0xB0][0xAA][0x00][0x02][0x0B][volume][CRC][0xFF][0xF5]`
+pub fn build_s2d_set_volume(volume: u8) -> [u8; 9] {
+    build_s2d_value(S2D_CMD_SET_VOLUME, volume.min(100))
+}
+
+/// Build a mute frame (CMD 0x0C) — 9 bytes.
+///
+///   `[0xB0][0xAA][0x00][0x02][0x0C][1 | 0][CRC][0xFF][0xF5]`
+pub fn build_s2d_mute(muted: bool) -> [u8; 9] {
+    build_s2d_value(S2D_CMD_MUTE, muted as u8)
+}
+
+/// A frame of `cmd` with a one-byte value.
+fn build_s2d_value(cmd: u8, value: u8) -> [u8; 9] {
+    let payload_len: u16 = 2; // CMD + value
+    let mut f = [0u8; 9];
+    f[0] = S2D_HEADER_0;
+    f[1] = S2D_HEADER_1;
+    f[2] = (payload_len >> 8) as u8;
+    f[3] = (payload_len & 0xFF) as u8;
+    f[4] = cmd;
+    f[5] = value;
+    f[6] = crc8_s2d(&f);
+    f[7] = S2D_FOOTER_0;
+    f[8] = S2D_FOOTER_1;
+    f
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const PKT_TYPES: [u8; 6] = [PKT_AUDIO_UP, PKT_AUDIO_DOWN, PKT_CONTROL, PKT_HEARTBEAT, PKT_OTA, PKT_TELEMETRY];
+    const ESP_NOTIFY_CMDS: [u8; 4] = [NOTIFY_CMD_START, NOTIFY_CMD_STOP, NOTIFY_CMD_SERVER_READY, NOTIFY_CMD_ACK];
+
+    proptest! {
+        #[test]
+        fn test_build_packet_parses_back(
+            seq in any::<u16>(),
+            pkt_type in prop_oneof![proptest::sample::select(&PKT_TYPES[..]), any::<u8>()],
+            flags in any::<u8>(),
+            payload in proptest::collection::vec(any::<u8>(), 0..=ESP_MAX_PAYLOAD + 8)
+        ) {
+            let buf = build_packet(seq, pkt_type, flags, &payload);
+            let parsed = EspPacket::parse(&buf);
+            if !PKT_TYPES.contains(&pkt_type) || payload.len() > ESP_MAX_PAYLOAD {
+                prop_assert!(parsed.is_none());
+            } else {
+                let pkt = parsed.unwrap();
+                prop_assert_eq!((pkt.seq_num, pkt.pkt_type, pkt.flags, pkt.payload), (seq, pkt_type, flags, payload));
+            }
+        }
+
+        #[test]
+        fn test_build_notify_packet_parses_back(
+            cmd in prop_oneof![proptest::sample::select(&ESP_NOTIFY_CMDS[..]), any::<u8>()],
+            mac in any::<[u8; 6]>(),
+            prefixed in any::<bool>(),
+            audio in proptest::collection::vec(any::<u8>(), 0..64)
+        ) {
+            // Optional FF F5 prefix and trailing PCM, as the firmware sends
+            let mut buf = if prefixed { vec![NOTIFY_END_0, NOTIFY_END_1] } else { Vec::new() };
+            buf.extend_from_slice(&build_notify_packet(cmd, &mac));
+            buf.extend_from_slice(&audio);
+
+            match NotifyPacket::parse(&buf) {
+                // Server → ESP commands (e.g. ERROR) are never accepted
+                None => prop_assert!(!ESP_NOTIFY_CMDS.contains(&cmd)),
+                Some(parsed) => {
+                    prop_assert!(ESP_NOTIFY_CMDS.contains(&cmd));
+                    prop_assert_eq!((parsed.packet.cmd, parsed.packet.mac), (cmd, mac));
+                    prop_assert_eq!(&buf[parsed.header_end..], &audio[..]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use crate::clock::{ self, SharedClock };
+use crate::config::Config;
+use crate::mqtt_health::{ self, MqttHealth };
+use crate::sensor::SensorVector;
+use rumqttc::v5::mqttbytes::QoS;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+use tokio::sync::broadcast;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Discrete sensor events
+// ─────────────────────────────────────────────────────────────────────
+//
+//  The V/A/D stream is continuous; consumers that only care about "did
+//  the robot just fall?" shouldn't have to re-derive that from floats.
+//  The detector watches individual raw channels with hysteresis:
+//
+//    fires  when value rises  ≥ on   (and the channel was re-armed)
+//    re-arms when value drops ≤ off
+//
+//  plus a per-kind cooldown so a bouncing signal can't spam events.
+//
+//    Event            Channel        on     off    cooldown
+//    FallDetected     fall_event     0.60   0.30    5 s
+//    PickedUp         lifted         0.60   0.30    5 s
+//    NewFaceSeen      unknown_face   0.60   0.30   10 s
+//    BatteryCritical  battery_low    0.90   0.80   60 s
+//
+//  `Distress` events come from the V/A/D stream instead (see
+//  `distress`) and carry a snippet reference to the emotion window
+//  behind them; `Cry`/`Scream`/`GlassBreak`/`Silence` come from the
+//  audio-event classifier (see `audio_events`).  All but `Silence` of
+//  those are priority events.
+//
+//  Every event goes to the `/events/ws` subscribers and, when set up,
+//  to `<--event-mqtt-topic>/[<tenant>/]<sensor id>/<kind>` (QoS 1) and
+//  every `--event-webhook`.  Debounce state of sensors not seen for
+//  `--smoother-evict-secs` is dropped.
+
+/// Kind of discrete event extracted from the sensor vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorEventKind {
+    FallDetected,
+    PickedUp,
+    NewFaceSeen,
+    BatteryCritical,
+    /// Fast valence drop with high arousal (`--distress-alarm`)
+    Distress,
+    /// Audio-event classifier (`--audio-event-model-path`)
+    Cry,
+    Scream,
+    GlassBreak,
+    Silence,
+}
+
+impl SensorEventKind {
+    /// Kinds watching a raw channel, in definition order.
+    pub const ALL: [SensorEventKind; 4] = [
+        SensorEventKind::FallDetected,
+        SensorEventKind::PickedUp,
+        SensorEventKind::NewFaceSeen,
+        SensorEventKind::BatteryCritical,
+    ];
+
+    /// `(on, off, cooldown)` hysteresis parameters for this kind.
+    fn params(self) -> (f32, f32, Duration) {
+        match self {
+            SensorEventKind::FallDetected => (0.6, 0.3, Duration::from_secs(5)),
+            SensorEventKind::PickedUp => (0.6, 0.3, Duration::from_secs(5)),
+            SensorEventKind::NewFaceSeen => (0.6, 0.3, Duration::from_secs(10)),
+            SensorEventKind::BatteryCritical => (0.9, 0.8, Duration::from_secs(60)),
+            | SensorEventKind::Distress
+            | SensorEventKind::Cry
+            | SensorEventKind::Scream
+            | SensorEventKind::GlassBreak
+            | SensorEventKind::Silence => unreachable!("{self} events are not channel-driven"),
+        }
+    }
+
+    /// Raw channel value this kind watches.
+    fn channel(self, sv: &SensorVector) -> f32 {
+        match self {
+            SensorEventKind::FallDetected => sv.fall_event,
+            SensorEventKind::PickedUp => sv.lifted,
+            SensorEventKind::NewFaceSeen => sv.unknown_face,
+            SensorEventKind::BatteryCritical => sv.battery_low,
+            | SensorEventKind::Distress
+            | SensorEventKind::Cry
+            | SensorEventKind::Scream
+            | SensorEventKind::GlassBreak
+            | SensorEventKind::Silence => unreachable!("{self} events are not channel-driven"),
+        }
+    }
+}
+
+impl fmt::Display for SensorEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SensorEventKind::FallDetected => write!(f, "fall_detected"),
+            SensorEventKind::PickedUp => write!(f, "picked_up"),
+            SensorEventKind::NewFaceSeen => write!(f, "new_face_seen"),
+            SensorEventKind::BatteryCritical => write!(f, "battery_critical"),
+            SensorEventKind::Distress => write!(f, "distress"),
+            SensorEventKind::Cry => write!(f, "cry"),
+            SensorEventKind::Scream => write!(f, "scream"),
+            SensorEventKind::GlassBreak => write!(f, "glass_break"),
+            SensorEventKind::Silence => write!(f, "silence"),
+        }
+    }
+}
+
+/// A debounced event, as delivered to subscribers.
+#[derive(Debug, Clone, Serialize)]
+pub struct SensorEvent {
+    pub sensor_id: u32,
+    pub seq: u64,
+    pub kind: SensorEventKind,
+    /// Channel value that triggered the event.
+    pub value: f32,
+    /// Wall-clock time of detection (ms since UNIX epoch).
+    pub timestamp_ms: u64,
+    /// Tenant of the ingress port (omitted in single-tenant mode).
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tenant: String,
+    /// Needs a person's attention now (omitted when false).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub priority: bool,
+    /// Samples behind the event, where it has any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<Snippet>,
+}
+
+/// Reference to the stretch of a sensor's stream behind an event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Snippet {
+    pub from_seq: u64,
+    pub to_seq: u64,
+    pub from_ms: u64,
+    pub to_ms: u64,
+    /// `GET` path of the emotion timeline covering it
+    pub emotions: String,
+}
+
+/// Fan-out channel for detected events (WebSocket subscribers etc.).
+pub type EventBus = broadcast::Sender<SensorEvent>;
+
+/// Create an event bus with room for `capacity` undelivered events per
+/// subscriber; slow subscribers lag rather than block the pipeline.
+pub fn event_bus(capacity: usize) -> EventBus {
+    broadcast::channel(capacity).0
+}
+
+/// Forward every event on `bus` to `--event-mqtt-host` and the
+/// `--event-webhook` URLs; does nothing when neither is set.
+pub fn spawn_forwarder(config: &Config, health: &MqttHealth, bus: &EventBus) -> anyhow::Result<()> {
+    let mqtt = if config.event_mqtt_host.is_empty() {
+        None
+    } else {
+        let opts = mqtt_health::client_options("events", &config.tenant_id, &config.event_mqtt_host, config.event_mqtt_port);
+        let (client, _) = mqtt_health::spawn_client(opts, 64, health, "events", |_, _| {});
+        let props = crate::mqtt5::json("sensor_event", &config.tenant_id, config.mqtt_expiry_secs);
+        Some((client, config.event_mqtt_topic.clone(), props))
+    };
+    let webhooks = config.event_webhook.clone();
+    if mqtt.is_none() && webhooks.is_empty() {
+        return Ok(());
+    }
+
+    let http = reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?;
+    info!(
+        mqtt = ?mqtt.as_ref().map(|(_, topic, _)| topic),
+        webhooks = webhooks.len(),
+        "⚡ sensor event forwarding enabled"
+    );
+    let mut rx = bus.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let ev = match rx.recv().await {
+                Ok(ev) => ev,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "sensor event forwarding fell behind — events dropped");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let body = match serde_json::to_vec(&ev) {
+                Ok(b) => b,
+                Err(_) => continue,
+            };
+            if let Some((ref client, ref prefix, ref props)) = mqtt {
+                let topic = event_topic(prefix, &ev);
+                if let Err(e) = client.publish_with_properties(topic, QoS::AtLeastOnce, false, body.clone(), props.clone()).await {
+                    warn!(error = %e, "failed to queue sensor event for MQTT");
+                }
+            }
+            for url in &webhooks {
+                match
+                    http
+                        .post(url)
+                        .header("Content-Type", "application/json")
+                        .body(body.clone())
+                        .send().await
+                {
+                    Ok(resp) if resp.status().is_success() => {}
+                    Ok(resp) => warn!(url = %url, status = %resp.status(), "event webhook rejected"),
+                    Err(e) => warn!(url = %url, error = %e, "event webhook failed"),
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// MQTT topic of `ev`: `<prefix>/[<tenant>/]<sensor id>/<kind>`.
+fn event_topic(prefix: &str, ev: &SensorEvent) -> String {
+    if ev.tenant.is_empty() {
+        format!("{prefix}/{}/{}", ev.sensor_id, ev.kind)
+    } else {
+        format!("{prefix}/{}/{}/{}", ev.tenant, ev.sensor_id, ev.kind)
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Detector
+// ─────────────────────────────────────────────────────────────────────
+
+/// Per-sensor, per-kind debounce state.
+#[derive(Debug, Clone, Copy)]
+struct KindState {
+    armed: bool,
+    last_fired: Option<Instant>,
+}
+
+impl Default for KindState {
+    fn default() -> Self {
+        Self { armed: true, last_fired: None }
+    }
+}
+
+/// Debounce state of one sensor.
+struct SensorState {
+    kinds: [KindState; SensorEventKind::ALL.len()],
+    last_seen: Instant,
+}
+
+/// Thread-safe event detector shared across VAD workers.
+pub struct EventDetector {
+    state: Mutex<HashMap<u32, SensorState>>,
+    clock: SharedClock,
+}
+
+impl Default for EventDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventDetector {
+    pub fn new() -> Self {
+        Self::with_clock(clock::system())
+    }
+
+    /// Detector timing cooldowns and event timestamps on `clock`.
+    pub fn with_clock(clock: SharedClock) -> Self {
+        Self {
+            state: Mutex::new(HashMap::new()),
+            clock,
+        }
+    }
+
+    /// Feed one raw sensor vector; returns any events that fired.
+    /// Non-finite channels are ignored: they neither fire nor re-arm.
+    pub fn observe(&self, sensor_id: u32, seq: u64, sv: &SensorVector) -> Vec<SensorEvent> {
+        let now = self.clock.now();
+        let mut events = Vec::new();
+        let mut map = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let sensor = map.entry(sensor_id).or_insert_with(|| SensorState { kinds: Default::default(), last_seen: now });
+        sensor.last_seen = now;
+
+        for (kind, st) in SensorEventKind::ALL.iter().zip(sensor.kinds.iter_mut()) {
+            let (on, off, cooldown) = kind.params();
+            let value = kind.channel(sv);
+            // NaN fails every comparison below; the vector is raw here
+            if !value.is_finite() {
+                continue;
+            }
+
+            if value <= off {
+                st.armed = true;
+                continue;
+            }
+            if !st.armed || value < on {
+                continue;
+            }
+            if st.last_fired.is_some_and(|t| now.duration_since(t) < cooldown) {
+                continue;
+            }
+
+            st.armed = false;
+            st.last_fired = Some(now);
+            events.push(SensorEvent {
+                sensor_id,
+                seq,
+                kind: *kind,
+                value,
+                timestamp_ms: clock::unix_millis(self.clock.as_ref()),
+                tenant: String::new(),
+                priority: false,
+                snippet: None,
+            });
+        }
+
+        events
+    }
+
+    /// Drop debounce state of sensors not seen within `max_age`.
+    ///
+    /// Returns the number of evicted sensors.
+    pub fn evict_stale(&self, max_age: Duration) -> usize {
+        let now = self.clock.now();
+        let mut map = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let before = map.len();
+        map.retain(|_, sensor| now.duration_since(sensor.last_seen) < max_age);
+        before - map.len()
+    }
+}
+
+/// Background sweep evicting idle sensors' debounce state.
+pub async fn evict_stale_loop(detector: Arc<EventDetector>, max_age_secs: u64) {
+    if max_age_secs == 0 {
+        std::future::pending::<()>().await;
+        return;
+    }
+
+    let max_age = Duration::from_secs(max_age_secs);
+    let period = clock::eviction_period(max_age_secs);
+    loop {
+        detector.clock.sleep(period).await;
+        let evicted = detector.evict_stale(max_age);
+        if evicted > 0 {
+            info!(evicted, "🧹 evicted stale sensor event state");
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fall(v: f32) -> SensorVector {
+        SensorVector { fall_event: v, ..Default::default() }
+    }
+
+    #[test]
+    fn test_fires_once_on_rising_edge() {
+        let det = EventDetector::new();
+        assert!(det.observe(1, 0, &fall(0.1)).is_empty());
+
+        let ev = det.observe(1, 1, &fall(0.9));
+        assert_eq!(ev.len(), 1);
+        assert_eq!(ev[0].kind, SensorEventKind::FallDetected);
+
+        // Held high → no repeat
+        assert!(det.observe(1, 2, &fall(0.9)).is_empty());
+    }
+
+    #[test]
+    fn test_hysteresis_and_cooldown() {
+        let det = EventDetector::new();
+        assert_eq!(det.observe(1, 0, &fall(0.9)).len(), 1);
+
+        // Dipping between off and on does not re-arm
+        assert!(det.observe(1, 1, &fall(0.5)).is_empty());
+        assert!(det.observe(1, 2, &fall(0.9)).is_empty());
+
+        // Re-armed below off, but still inside the cooldown window
+        assert!(det.observe(1, 3, &fall(0.0)).is_empty());
+        assert!(det.observe(1, 4, &fall(0.9)).is_empty());
+    }
+
+    #[test]
+    fn test_independent_per_sensor() {
+        let det = EventDetector::new();
+        assert_eq!(det.observe(1, 0, &fall(0.9)).len(), 1);
+        assert_eq!(det.observe(2, 0, &fall(0.9)).len(), 1);
+    }
+
+    #[test]
+    fn test_evict_stale_forgets_debounce() {
+        let sim = clock::SimClock::default();
+        let det = EventDetector::with_clock(Arc::new(sim.clone()));
+        assert_eq!(det.observe(1, 0, &fall(0.9)).len(), 1);
+        sim.advance(Duration::from_secs(30));
+        assert_eq!(det.observe(2, 0, &fall(0.0)).len(), 0);
+        sim.advance(Duration::from_secs(30));
+
+        assert_eq!(det.evict_stale(Duration::from_secs(45)), 1);
+        assert_eq!(det.state.lock().unwrap().len(), 1);
+        // Sensor 1 starts over: armed, no cooldown
+        assert_eq!(det.observe(1, 1, &fall(0.9)).len(), 1);
+    }
+
+    #[test]
+    fn test_event_topic() {
+        let mut ev = det_event();
+        assert_eq!(event_topic("vad/events", &ev), "vad/events/42/fall_detected");
+        ev.tenant = "acme".into();
+        assert_eq!(event_topic("vad/events", &ev), "vad/events/acme/42/fall_detected");
+    }
+
+    fn det_event() -> SensorEvent {
+        EventDetector::new().observe(42, 7, &fall(0.9)).remove(0)
+    }
+
+    #[test]
+    fn test_battery_critical_threshold() {
+        let det = EventDetector::new();
+        let low = SensorVector { battery_low: 0.85, ..Default::default() };
+        assert!(det.observe(1, 0, &low).is_empty());
+        let crit = SensorVector { battery_low: 0.95, ..Default::default() };
+        let ev = det.observe(1, 1, &crit);
+        assert_eq!(ev.len(), 1);
+        assert_eq!(ev[0].kind, SensorEventKind::BatteryCritical);
+    }
+
+    #[test]
+    fn test_non_finite_values_never_fire() {
+        let det = EventDetector::new();
+        for v in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            let sv = SensorVector { battery_low: v, ..fall(v) };
+            assert!(det.observe(1, 0, &sv).is_empty(), "{v}");
+        }
+        // ... and leave the debounce state alone
+        assert_eq!(det.observe(1, 1, &fall(0.9)).len(), 1);
+        assert!(det.observe(1, 2, &fall(f32::NAN)).is_empty());
+        assert!(det.observe(1, 3, &fall(0.9)).is_empty());
+    }
+}
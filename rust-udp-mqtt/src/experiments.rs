@@ -0,0 +1,580 @@
+use crate::conversation::Role;
+use crate::devices::{ DeviceConfig, DeviceRegistry, Thresholds };
+use crate::persona::PersonaTrait;
+use crate::vad::{ VadKind, VadResult };
+use serde::{ Deserialize, Serialize };
+use std::collections::{ BTreeMap, HashSet };
+use std::sync::{ Arc, Mutex };
+
+// ─────────────────────────────────────────────────────────────────────
+//  A/B experiments
+// ─────────────────────────────────────────────────────────────────────
+//
+//  `--experiments-file experiments.toml` splits the fleet into variants
+//  of the pipeline settings, so a threshold or prompt change can be
+//  judged on production numbers before it is rolled out:
+//
+//    [[experiment]]
+//    name = "arousal-threshold"
+//    [[experiment.variant]]
+//    name = "control"                # the first variant is the baseline
+//    [[experiment.variant]]
+//    name = "sensitive"
+//    weight = 1                      # share of devices (default 1)
+//    arousal_threshold = 0.30        # audio_threshold, persona and
+//                                    # prompt may be varied as well
+//
+//  A device is bucketed by hashing the experiment name (and `salt`)
+//  with its sensor id, so it stays in one variant across restarts and
+//  instances.  An ESP counts as the sensor of its provisioning profile
+//  (`sensor_id`), so both sides of one robot share a variant; ESPs
+//  without one are bucketed by name.  Renaming or re-salting an
+//  experiment reshuffles the fleet.
+//
+//  Variant settings take precedence over device overrides and the
+//  global defaults:
+//
+//    audio_threshold / arousal_threshold   VAD `is_active` thresholds
+//    persona                               VAD persona and the prompt's
+//                                          `{{persona}}`
+//    prompt                                text appended to the OpenAI
+//                                          instructions
+//
+//  Each setting may be varied by one experiment only, so results are
+//  never confounded by another experiment on the same knob.
+//
+//  Transcripts and session records carry the device's variants
+//  (`"experiments": {"arousal-threshold": "sensitive"}`), and each
+//  variant accumulates VAD results, sessions and transcripts, served
+//  side by side by `GET /experiments`.
+
+/// `experiments.toml` contents.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExperimentsFile {
+    #[serde(rename = "experiment", default)]
+    experiments: Vec<ExperimentSpec>,
+}
+
+/// One `[[experiment]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExperimentSpec {
+    pub name: String,
+    /// Mixed into the bucketing hash (change to reshuffle devices)
+    #[serde(default)]
+    pub salt: String,
+    #[serde(rename = "variant", default)]
+    pub variants: Vec<VariantSpec>,
+}
+
+/// One `[[experiment.variant]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VariantSpec {
+    pub name: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    pub audio_threshold: Option<f64>,
+    pub arousal_threshold: Option<f32>,
+    pub persona: Option<PersonaTrait>,
+    pub prompt: Option<String>,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// What one variant has seen so far.
+#[derive(Debug, Default)]
+struct Accum {
+    /// Bucketing units (sensor ids / ESP names) seen in this variant
+    units: HashSet<Unit>,
+    packets: u64,
+    active: u64,
+    emotional: u64,
+    sum_valence: f64,
+    sum_arousal: f64,
+    sessions: u64,
+    user_transcripts: u64,
+    assistant_transcripts: u64,
+}
+
+struct Experiment {
+    spec: ExperimentSpec,
+    total_weight: u64,
+    stats: Mutex<Vec<Accum>>,
+}
+
+/// What a device is bucketed by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Unit {
+    Sensor(u32),
+    /// Lower-cased ESP name without a provisioned sensor id
+    Device(String),
+}
+
+/// `GET /experiments` entry.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExperimentReport {
+    pub name: String,
+    pub variants: Vec<VariantReport>,
+}
+
+/// Results of one variant.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VariantReport {
+    pub name: String,
+    pub weight: u32,
+    /// Sensors / ESPs seen
+    pub devices: usize,
+    pub packets: u64,
+    /// Share of VAD results with `is_active`
+    pub active_rate: Option<f64>,
+    /// Means over emotional VAD results
+    pub mean_valence: Option<f64>,
+    pub mean_arousal: Option<f64>,
+    pub sessions: u64,
+    pub user_transcripts: u64,
+    pub assistant_transcripts: u64,
+    /// Difference from the first (control) variant, where both have data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vs_control: Option<Delta>,
+}
+
+/// Variant minus control.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Delta {
+    pub active_rate: Option<f64>,
+    pub mean_valence: Option<f64>,
+    pub mean_arousal: Option<f64>,
+}
+
+/// Running experiments.  Clone-friendly; the disabled default runs
+/// none.
+#[derive(Clone, Default)]
+pub struct Experiments {
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    experiments: Vec<Experiment>,
+    /// Provisioning profiles (an ESP's sensor id)
+    devices: DeviceRegistry,
+}
+
+impl Experiments {
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    /// Load `--experiments-file` ("" = none).
+    pub fn load(path: &str, devices: DeviceRegistry) -> anyhow::Result<Self> {
+        if path.is_empty() {
+            return Ok(Self::disabled());
+        }
+        let text = std::fs
+            ::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read experiments file {path}: {e}"))?;
+        let file: ExperimentsFile = toml
+            ::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("invalid experiments file {path}: {e}"))?;
+        let experiments = Self::new(file.experiments, devices)?;
+        if let Some(ref inner) = experiments.inner {
+            for e in &inner.experiments {
+                let variants: Vec<&str> = e.spec.variants
+                    .iter()
+                    .map(|v| v.name.as_str())
+                    .collect();
+                tracing::info!(experiment = %e.spec.name, variants = %variants.join(" / "), "🧪 experiment running");
+            }
+        }
+        Ok(experiments)
+    }
+
+    /// Run `specs` (checked first).
+    pub fn new(specs: Vec<ExperimentSpec>, devices: DeviceRegistry) -> anyhow::Result<Self> {
+        validate(&specs)?;
+        if specs.is_empty() {
+            return Ok(Self::disabled());
+        }
+        let experiments = specs
+            .into_iter()
+            .map(|spec| Experiment {
+                total_weight: spec.variants
+                    .iter()
+                    .map(|v| v.weight as u64)
+                    .sum(),
+                stats: Mutex::new(spec.variants.iter().map(|_| Accum::default()).collect()),
+                spec,
+            })
+            .collect();
+        Ok(Self { inner: Some(Arc::new(Inner { experiments, devices })) })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Variant of `unit` in every experiment, as (experiment, variant).
+    fn assigned<'a>(&'a self, unit: &'a Unit) -> impl Iterator<Item = (&'a Experiment, usize)> + 'a {
+        self.inner
+            .iter()
+            .flat_map(|inner| &inner.experiments)
+            .map(move |e| (e, e.variant_of(unit)))
+    }
+
+    fn variant_setting<T>(&self, unit: &Unit, get: impl Fn(&VariantSpec) -> Option<T>) -> Option<T> {
+        self.assigned(unit).find_map(|(e, v)| get(&e.spec.variants[v]))
+    }
+
+    /// An ESP's bucketing unit: its provisioned sensor, else its name.
+    fn device_unit(&self, device: &str) -> Unit {
+        let sensor_id = self.inner.as_ref().and_then(|inner| inner.devices.profile(device)?.sensor_id);
+        match sensor_id {
+            Some(id) => Unit::Sensor(id),
+            None => Unit::Device(device.to_ascii_lowercase()),
+        }
+    }
+
+    /// `base` thresholds of `sensor_id` with its variants' overrides.
+    pub fn thresholds(&self, sensor_id: u32, base: Thresholds) -> Thresholds {
+        if self.inner.is_none() {
+            return base;
+        }
+        let unit = Unit::Sensor(sensor_id);
+        Thresholds {
+            audio: self.variant_setting(&unit, |v| v.audio_threshold).unwrap_or(base.audio),
+            arousal: self.variant_setting(&unit, |v| v.arousal_threshold).unwrap_or(base.arousal),
+        }
+    }
+
+    /// VAD persona of `sensor_id`'s variant, if it sets one.
+    pub fn persona(&self, sensor_id: u32) -> Option<PersonaTrait> {
+        self.inner.as_ref()?;
+        self.variant_setting(&Unit::Sensor(sensor_id), |v| v.persona)
+    }
+
+    /// Prompt persona of `device`'s variant, if it sets one.
+    pub fn device_persona(&self, device: &str) -> Option<PersonaTrait> {
+        self.inner.as_ref()?;
+        self.variant_setting(&self.device_unit(device), |v| v.persona)
+    }
+
+    /// Prompt text `device`'s variants add ("" = none).
+    pub fn device_prompt(&self, device: &str) -> String {
+        if self.inner.is_none() {
+            return String::new();
+        }
+        let unit = self.device_unit(device);
+        self.assigned(&unit)
+            .filter_map(|(e, v)| e.spec.variants[v].prompt.as_deref())
+            .map(|p| format!("\n\n{}", p.trim()))
+            .collect()
+    }
+
+    /// `device`'s variant in every experiment, by experiment name.
+    pub fn device_variants(&self, device: &str) -> BTreeMap<String, String> {
+        if self.inner.is_none() {
+            return BTreeMap::new();
+        }
+        let unit = self.device_unit(device);
+        self.assigned(&unit)
+            .map(|(e, v)| (e.spec.name.clone(), e.spec.variants[v].name.clone()))
+            .collect()
+    }
+
+    /// Count a VAD result towards its sensor's variants.
+    pub fn record_vad(&self, result: &VadResult) {
+        self.record(Unit::Sensor(result.sensor_id), |acc| {
+            acc.packets += 1;
+            if result.is_active {
+                acc.active += 1;
+            }
+            if result.kind == VadKind::Emotional {
+                acc.emotional += 1;
+                acc.sum_valence += result.valence as f64;
+                acc.sum_arousal += result.arousal as f64;
+            }
+        });
+    }
+
+    /// Count an ESP session towards `device`'s variants.
+    pub fn record_session(&self, device: &str) {
+        if self.inner.is_some() {
+            self.record(self.device_unit(device), |acc| acc.sessions += 1);
+        }
+    }
+
+    /// Count a transcript towards `device`'s variants.
+    pub fn record_transcript(&self, device: &str, role: Role) {
+        if self.inner.is_some() {
+            self.record(self.device_unit(device), |acc| {
+                match role {
+                    Role::User => acc.user_transcripts += 1,
+                    Role::Assistant => acc.assistant_transcripts += 1,
+                }
+            });
+        }
+    }
+
+    fn record(&self, unit: Unit, update: impl Fn(&mut Accum)) {
+        for (e, v) in self.assigned(&unit) {
+            let mut stats = e.stats.lock().unwrap_or_else(|e| e.into_inner());
+            let acc = &mut stats[v];
+            if !acc.units.contains(&unit) {
+                acc.units.insert(unit.clone());
+            }
+            update(acc);
+        }
+    }
+
+    /// Every experiment with its variants' results side by side.
+    pub fn report(&self) -> Vec<ExperimentReport> {
+        self.inner
+            .iter()
+            .flat_map(|inner| &inner.experiments)
+            .map(|e| {
+                let stats = e.stats.lock().unwrap_or_else(|e| e.into_inner());
+                let mut variants: Vec<VariantReport> = e.spec.variants
+                    .iter()
+                    .zip(stats.iter())
+                    .map(|(spec, acc)| VariantReport {
+                        name: spec.name.clone(),
+                        weight: spec.weight,
+                        devices: acc.units.len(),
+                        packets: acc.packets,
+                        active_rate: ratio(acc.active as f64, acc.packets),
+                        mean_valence: ratio(acc.sum_valence, acc.emotional),
+                        mean_arousal: ratio(acc.sum_arousal, acc.emotional),
+                        sessions: acc.sessions,
+                        user_transcripts: acc.user_transcripts,
+                        assistant_transcripts: acc.assistant_transcripts,
+                        vs_control: None,
+                    })
+                    .collect();
+                let control = variants[0].clone();
+                let diff = |a: Option<f64>, b: Option<f64>| Some(a? - b?);
+                for variant in variants.iter_mut().skip(1) {
+                    variant.vs_control = Some(Delta {
+                        active_rate: diff(variant.active_rate, control.active_rate),
+                        mean_valence: diff(variant.mean_valence, control.mean_valence),
+                        mean_arousal: diff(variant.mean_arousal, control.mean_arousal),
+                    });
+                }
+                ExperimentReport { name: e.spec.name.clone(), variants }
+            })
+            .collect()
+    }
+}
+
+impl Experiment {
+    /// Deterministic variant of `unit`, weighted.
+    fn variant_of(&self, unit: &Unit) -> usize {
+        let mut point = bucket_hash(&self.spec.name, &self.spec.salt, unit) % self.total_weight;
+        for (i, v) in self.spec.variants.iter().enumerate() {
+            if point < (v.weight as u64) {
+                return i;
+            }
+            point -= v.weight as u64;
+        }
+        self.spec.variants.len() - 1
+    }
+}
+
+fn ratio(sum: f64, n: u64) -> Option<f64> {
+    (n > 0).then(|| sum / (n as f64))
+}
+
+/// FNV-1a over the experiment name, salt and unit: stable across
+/// processes and builds, unlike `std`'s hasher.
+fn bucket_hash(name: &str, salt: &str, unit: &Unit) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for b in bytes {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    feed(name.as_bytes());
+    feed(b"/");
+    feed(salt.as_bytes());
+    match unit {
+        Unit::Sensor(id) => {
+            feed(b"/s");
+            feed(&id.to_le_bytes());
+        }
+        Unit::Device(name) => {
+            feed(b"/d");
+            feed(name.as_bytes());
+        }
+    }
+    hash
+}
+
+/// Names, weights and settings in range; each setting varied by one
+/// experiment at most.
+fn validate(specs: &[ExperimentSpec]) -> anyhow::Result<()> {
+    let mut names = HashSet::new();
+    let mut varied: BTreeMap<&str, &str> = BTreeMap::new();
+    for e in specs {
+        if e.name.trim().is_empty() {
+            anyhow::bail!("experiment name must not be empty");
+        }
+        if !names.insert(e.name.as_str()) {
+            anyhow::bail!("duplicate experiment {}", e.name);
+        }
+        if e.variants.len() < 2 {
+            anyhow::bail!("experiment {} needs at least two variants", e.name);
+        }
+        let mut variants = HashSet::new();
+        for v in &e.variants {
+            if v.name.trim().is_empty() || !variants.insert(v.name.as_str()) {
+                anyhow::bail!("experiment {}: variant names must be non-empty and unique", e.name);
+            }
+            let overrides = DeviceConfig {
+                audio_threshold: v.audio_threshold,
+                arousal_threshold: v.arousal_threshold,
+                ..Default::default()
+            };
+            overrides.validate().map_err(|err| anyhow::anyhow!("experiment {} variant {}: {err}", e.name, v.name))?;
+        }
+        if e.variants.iter().all(|v| v.weight == 0) {
+            anyhow::bail!("experiment {}: at least one variant needs a weight above 0", e.name);
+        }
+        let settings = [
+            ("audio_threshold", e.variants.iter().any(|v| v.audio_threshold.is_some())),
+            ("arousal_threshold", e.variants.iter().any(|v| v.arousal_threshold.is_some())),
+            ("persona", e.variants.iter().any(|v| v.persona.is_some())),
+            ("prompt", e.variants.iter().any(|v| v.prompt.is_some())),
+        ];
+        for (setting, _) in settings.into_iter().filter(|(_, set)| *set) {
+            if let Some(other) = varied.insert(setting, &e.name) {
+                anyhow::bail!("experiments {other} and {} both vary {setting}", e.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::DeviceProfile;
+
+    const DEFAULTS: Thresholds = Thresholds { audio: 30.0, arousal: 0.35 };
+
+    fn parse(text: &str) -> anyhow::Result<Vec<ExperimentSpec>> {
+        Ok(toml::from_str::<ExperimentsFile>(text)?.experiments)
+    }
+
+    fn emotional(sensor_id: u32, arousal: f32, is_active: bool) -> VadResult {
+        VadResult {
+            sensor_id,
+            seq: 0,
+            kind: VadKind::Emotional,
+            is_active,
+            energy: 0.0,
+            threshold: 0.0,
+            dbfs: -96.0,
+            zcr: 0.0,
+            band_ratio: 0.0,
+            valence: 0.5,
+            arousal,
+            dominance: 0.0,
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn test_buckets_are_stable_weighted_and_applied() {
+        let specs = parse(
+            r#"
+            [[experiment]]
+            name = "arousal"
+            [[experiment.variant]]
+            name = "control"
+            weight = 3
+            [[experiment.variant]]
+            name = "sensitive"
+            arousal_threshold = 0.2
+            persona = "cute"
+            "#
+        ).unwrap();
+        let devices = DeviceRegistry::new(DEFAULTS);
+        let experiments = Experiments::new(specs, devices.clone()).unwrap();
+
+        // Deterministic, and roughly by weight
+        let sensitive: Vec<u32> = (0..1000)
+            .filter(|&id| experiments.thresholds(id, DEFAULTS).arousal == 0.2)
+            .collect();
+        assert!((150..350).contains(&sensitive.len()), "{} of 1000 in the 1/4 variant", sensitive.len());
+        let id = sensitive[0];
+        let control = (0..1000).find(|id| !sensitive.contains(id)).unwrap();
+        assert_eq!(experiments.thresholds(id, DEFAULTS), Thresholds { audio: 30.0, arousal: 0.2 });
+        assert_eq!(experiments.thresholds(control, DEFAULTS), DEFAULTS);
+        assert_eq!(experiments.persona(id), Some(PersonaTrait::Cute));
+        assert_eq!(experiments.persona(control), None);
+
+        // An ESP provisioned with that sensor shares its variant
+        devices.import(vec![("aa:bb".into(), DeviceProfile { sensor_id: Some(id), ..Default::default() })]);
+        assert_eq!(experiments.device_variants("AA:BB")["arousal"], "sensitive");
+        assert_eq!(experiments.device_persona("aa:bb"), Some(PersonaTrait::Cute));
+
+        // Results are reported per variant, against the control
+        experiments.record_vad(&emotional(id, 0.3, true));
+        experiments.record_vad(&emotional(control, 0.3, false));
+        experiments.record_vad(&emotional(control, 0.1, false));
+        experiments.record_session("aa:bb");
+        experiments.record_transcript("aa:bb", Role::User);
+        let report = experiments.report();
+        let [ref ctl, ref var] = report[0].variants[..] else { panic!("two variants") };
+        assert_eq!((ctl.devices, ctl.packets, ctl.active_rate), (1, 2, Some(0.0)));
+        assert!((ctl.mean_arousal.unwrap() - 0.2).abs() < 1e-6);
+        assert_eq!((var.devices, var.packets, var.sessions, var.user_transcripts), (1, 1, 1, 1));
+        let delta = var.vs_control.unwrap();
+        assert_eq!(delta.active_rate, Some(1.0));
+        assert!((delta.mean_arousal.unwrap() - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_prompt_variants_and_validation() {
+        let specs = parse(
+            r#"
+            [[experiment]]
+            name = "prompt"
+            [[experiment.variant]]
+            name = "control"
+            [[experiment.variant]]
+            name = "short"
+            weight = 0
+            [[experiment.variant]]
+            name = "chatty"
+            weight = 1000
+            prompt = "Ask a follow-up question."
+            "#
+        ).unwrap();
+        let experiments = Experiments::new(specs, DeviceRegistry::new(DEFAULTS)).unwrap();
+        // Weight 1 of 1001 in control: nearly every device is chatty
+        let chatty = (0..50)
+            .map(|i| format!("10.0.0.{i}:4000"))
+            .filter(|d| experiments.device_prompt(d) == "\n\nAsk a follow-up question.")
+            .count();
+        assert!(chatty >= 45, "{chatty}");
+        assert!(!experiments.device_variants("10.0.0.1:4000").values().any(|v| v == "short"));
+        assert!(Experiments::disabled().device_prompt("x").is_empty());
+
+        let bad = |text: &str| Experiments::new(parse(text).unwrap(), DeviceRegistry::new(DEFAULTS)).err().unwrap().to_string();
+        let one = "[[experiment]]\nname = \"a\"\n[[experiment.variant]]\nname = \"x\"";
+        assert!(bad(one).contains("at least two variants"));
+        let range = "[[experiment]]\nname = \"a\"\n[[experiment.variant]]\nname = \"x\"\n[[experiment.variant]]\nname = \"y\"\narousal_threshold = 2.0";
+        assert!(bad(range).contains("arousal_threshold"));
+        let valid = range.replace("2.0", "0.2");
+        let twice = format!("{valid}\n{}", valid.replace("\"a\"", "\"b\""));
+        assert!(bad(&twice).contains("experiments a and b both vary arousal_threshold"));
+    }
+}
@@ -0,0 +1,362 @@
+use crate::config::Config;
+use crate::systemd;
+use serde::{ Deserialize, Serialize };
+use std::sync::{ Arc, Mutex, Weak };
+use std::time::{ Duration, Instant };
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tracing::{ error, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Hot-standby failover pair
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Two bridges, usually on two gateways, serve one virtual identity: a
+//  floating IP the robots talk to, moved by `--failover-hook` (e.g. an
+//  `ip addr add` / keepalived-style script).  Each sends a heartbeat
+//  datagram to the other every `--failover-interval-ms` on
+//  `--failover-port`:
+//
+//    {"instance":"gw-a","role":"primary","active":true}
+//
+//  Only the active one binds sockets, connects to brokers and loads
+//  state; the standby heartbeats and waits:
+//
+//    - at startup a `primary` takes over at once if the peer answers as
+//      a standby, and either role takes over after
+//      `--failover-timeout-ms` without hearing the peer
+//    - an active peer is never preempted, so a recovered primary comes
+//      back as the standby
+//    - a standby takes over once the active peer has been silent for
+//      `--failover-timeout-ms`: it runs `<hook> active`, then starts
+//      like a fresh bridge, loading persona, device overrides and
+//      conversations from the shared store (`--postgres-url` /
+//      `--cluster-redis-url`; without one it starts from its flags)
+//    - if both end up active (a healed partition), the `standby` role
+//      (between equal roles, the higher instance id) runs
+//      `<hook> standby` and exits non-zero; its supervisor restarts it
+//      as the standby
+//
+//  Robots lose service for the timeout plus a startup, a few seconds.
+//  While waiting the standby reports ready and pings the systemd
+//  watchdog, so `Type=notify` units work on both sides.
+
+/// Limit on one hook run.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Which side of the pair this instance prefers to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum FailoverRole {
+    /// No failover pair (default)
+    Off,
+    /// Takes over first when both start together
+    Primary,
+    /// Takes over when the primary goes silent
+    Standby,
+}
+
+/// One heartbeat datagram.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Heartbeat {
+    instance: String,
+    role: FailoverRole,
+    active: bool,
+}
+
+/// Last heartbeat from the peer.
+#[derive(Debug, Clone)]
+struct Peer {
+    instance: String,
+    role: FailoverRole,
+    active: bool,
+    heard: Instant,
+}
+
+/// Membership in a failover pair.  Clone-friendly; a disabled handle
+/// (no `--failover-role`) is always active.
+#[derive(Clone)]
+pub struct Failover {
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    instance: String,
+    role: FailoverRole,
+    hook: String,
+    interval: Duration,
+    timeout: Duration,
+    started: Instant,
+    active: watch::Sender<bool>,
+    peer: Mutex<Option<Peer>>,
+}
+
+impl Failover {
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    /// Bind the heartbeat port and start heartbeating (as standby) when
+    /// `--failover-role` is set.
+    pub async fn from_config(config: &Config) -> anyhow::Result<Self> {
+        if config.failover_role == FailoverRole::Off {
+            return Ok(Self::disabled());
+        }
+        if config.failover_peer.is_empty() {
+            anyhow::bail!("--failover-role needs --failover-peer HOST:PORT");
+        }
+        if config.failover_interval_ms == 0 || config.failover_timeout_ms < 2 * config.failover_interval_ms {
+            anyhow::bail!("--failover-timeout-ms must be at least twice --failover-interval-ms (> 0)");
+        }
+        let socket = UdpSocket::bind((config.host.as_str(), config.failover_port)).await?;
+        let failover = Self::start(
+            socket,
+            config.failover_peer.clone(),
+            config.resolved_instance_id(),
+            config.failover_role,
+            config.failover_hook.clone(),
+            Duration::from_millis(config.failover_interval_ms),
+            Duration::from_millis(config.failover_timeout_ms)
+        );
+        info!(
+            role = ?config.failover_role,
+            peer = %config.failover_peer,
+            port = config.failover_port,
+            "🔀 failover pair: standing by for the peer"
+        );
+        Ok(failover)
+    }
+
+    fn start(
+        socket: UdpSocket,
+        peer: String,
+        instance: String,
+        role: FailoverRole,
+        hook: String,
+        interval: Duration,
+        timeout: Duration
+    ) -> Self {
+        let inner = Arc::new(Inner {
+            instance,
+            role,
+            hook,
+            interval,
+            timeout,
+            started: Instant::now(),
+            active: watch::channel(false).0,
+            peer: Mutex::new(None),
+        });
+        let socket = Arc::new(socket);
+        tokio::spawn(send_loop(Arc::downgrade(&inner), socket.clone(), peer));
+        tokio::spawn(recv_loop(Arc::downgrade(&inner), socket));
+        Self { inner: Some(inner) }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.inner.as_ref().is_none_or(|inner| *inner.active.borrow())
+    }
+
+    /// Resolve once this instance is the active one (at once when
+    /// disabled), after running `<hook> active`.
+    pub async fn wait_active(&self) {
+        let Some(ref inner) = self.inner else {
+            return;
+        };
+        let mut reported = false;
+        let watchdog = systemd::watchdog_interval().is_some();
+        loop {
+            if should_promote(inner.role, inner.peer.lock().unwrap().as_ref(), inner.started, Instant::now(), inner.timeout) {
+                break;
+            }
+            if !reported && Instant::now().duration_since(inner.started) >= inner.timeout {
+                let peer = inner.peer.lock().unwrap().as_ref().map(|p| p.instance.clone()).unwrap_or_default();
+                info!(peer = %peer, "🔀 peer is active — this instance is the standby");
+                systemd::notify(&format!("READY=1\nSTATUS=standby (active: {peer})"));
+                reported = true;
+            }
+            if watchdog {
+                systemd::notify("WATCHDOG=1");
+            }
+            tokio::time::sleep(inner.interval).await;
+        }
+        let peer = inner.peer.lock().unwrap().clone();
+        warn!(
+            role = ?inner.role,
+            peer = ?peer.as_ref().map(|p| &p.instance),
+            "🔀 taking over as the active bridge"
+        );
+        run_hook(&inner.hook, "active").await;
+        inner.active.send_replace(true);
+    }
+
+    /// Resolve when this active instance must yield to an active peer
+    /// (never when disabled), after running `<hook> standby`.
+    pub async fn demoted(&self) {
+        let Some(ref inner) = self.inner else {
+            return std::future::pending().await;
+        };
+        loop {
+            tokio::time::sleep(inner.interval).await;
+            let peer = inner.peer.lock().unwrap().clone();
+            if
+                *inner.active.borrow() &&
+                should_yield(inner.role, &inner.instance, peer.as_ref(), Instant::now(), inner.timeout)
+            {
+                error!(peer = ?peer.map(|p| p.instance), "🔀 peer is active too — standing down");
+                inner.active.send_replace(false);
+                run_hook(&inner.hook, "standby").await;
+                return;
+            }
+        }
+    }
+}
+
+async fn send_loop(inner: Weak<Inner>, socket: Arc<UdpSocket>, peer: String) {
+    loop {
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        let heartbeat = Heartbeat {
+            instance: inner.instance.clone(),
+            role: inner.role,
+            active: *inner.active.borrow(),
+        };
+        let interval = inner.interval;
+        drop(inner);
+        if let Ok(bytes) = serde_json::to_vec(&heartbeat) {
+            if let Err(e) = socket.send_to(&bytes, peer.as_str()).await {
+                tracing::debug!(peer = %peer, error = %e, "failover heartbeat not sent");
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn recv_loop(inner: Weak<Inner>, socket: Arc<UdpSocket>) {
+    let mut buf = [0u8; 512];
+    loop {
+        let Ok(Ok((len, _))) = tokio::time::timeout(Duration::from_secs(1), socket.recv_from(&mut buf)).await else {
+            if inner.strong_count() == 0 {
+                return;
+            }
+            continue;
+        };
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        let Ok(heartbeat) = serde_json::from_slice::<Heartbeat>(&buf[..len]) else {
+            continue;
+        };
+        if heartbeat.instance == inner.instance {
+            continue;
+        }
+        let mut peer = inner.peer.lock().unwrap();
+        if peer.as_ref().is_none_or(|p| p.instance != heartbeat.instance || p.active != heartbeat.active) {
+            info!(peer = %heartbeat.instance, role = ?heartbeat.role, active = heartbeat.active, "🔀 failover peer");
+            if heartbeat.role == inner.role {
+                warn!(role = ?inner.role, "both sides of the failover pair have the same --failover-role");
+            }
+        }
+        *peer = Some(Peer {
+            instance: heartbeat.instance,
+            role: heartbeat.role,
+            active: heartbeat.active,
+            heard: Instant::now(),
+        });
+    }
+}
+
+/// Run `<hook> <state>` (no-op without a hook); failures are logged.
+async fn run_hook(hook: &str, state: &str) {
+    if hook.is_empty() {
+        return;
+    }
+    let run = tokio::process::Command::new(hook).arg(state).kill_on_drop(true).status();
+    match tokio::time::timeout(HOOK_TIMEOUT, run).await {
+        Ok(Ok(status)) if status.success() => info!(hook = %hook, state, "🔀 failover hook ran"),
+        Ok(Ok(status)) => warn!(hook = %hook, state, %status, "failover hook failed"),
+        Ok(Err(e)) => warn!(hook = %hook, state, error = %e, "failover hook did not start"),
+        Err(_) => warn!(hook = %hook, state, "failover hook timed out"),
+    }
+}
+
+/// Whether a waiting instance takes over now.
+fn should_promote(role: FailoverRole, peer: Option<&Peer>, started: Instant, now: Instant, timeout: Duration) -> bool {
+    match peer {
+        Some(p) if now.duration_since(p.heard) < timeout => !p.active && role == FailoverRole::Primary,
+        Some(_) => true,
+        None => now.duration_since(started) >= timeout,
+    }
+}
+
+/// Whether an active instance yields to an active peer.
+fn should_yield(role: FailoverRole, instance: &str, peer: Option<&Peer>, now: Instant, timeout: Duration) -> bool {
+    match peer {
+        Some(p) if p.active && now.duration_since(p.heard) < timeout => {
+            if p.role == role { instance > p.instance.as_str() } else { role == FailoverRole::Standby }
+        }
+        _ => false,
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(role: FailoverRole, active: bool, heard: Instant) -> Peer {
+        Peer { instance: "gw-b".into(), role, active, heard }
+    }
+
+    #[test]
+    fn test_promote_and_yield_rules() {
+        use FailoverRole::*;
+        let t0 = Instant::now();
+        let timeout = Duration::from_secs(3);
+        let later = t0 + Duration::from_secs(1);
+        let gone = t0 + Duration::from_secs(5);
+
+        // Nothing heard: either role waits out the timeout
+        assert!(!should_promote(Primary, None, t0, later, timeout));
+        assert!(should_promote(Standby, None, t0, gone, timeout));
+        // Both standing by: the primary goes first
+        assert!(should_promote(Primary, Some(&peer(Standby, false, t0)), t0, later, timeout));
+        assert!(!should_promote(Standby, Some(&peer(Primary, false, t0)), t0, later, timeout));
+        // An active peer is not preempted until it goes silent
+        assert!(!should_promote(Primary, Some(&peer(Standby, true, t0)), t0, later, timeout));
+        assert!(should_promote(Standby, Some(&peer(Primary, true, t0)), t0, gone, timeout));
+
+        // Both active: the standby role yields, equal roles by instance id
+        assert!(should_yield(Standby, "gw-a", Some(&peer(Primary, true, t0)), later, timeout));
+        assert!(!should_yield(Primary, "gw-a", Some(&peer(Standby, true, t0)), later, timeout));
+        assert!(should_yield(Primary, "gw-c", Some(&peer(Primary, true, t0)), later, timeout));
+        assert!(!should_yield(Primary, "gw-a", Some(&peer(Primary, true, t0)), later, timeout));
+        assert!(!should_yield(Standby, "gw-a", Some(&peer(Primary, true, t0)), gone, timeout));
+        assert!(!should_yield(Standby, "gw-a", Some(&peer(Primary, false, t0)), later, timeout));
+    }
+
+    #[tokio::test]
+    async fn test_standby_takes_over_when_primary_goes_silent() {
+        let a = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let (a_addr, b_addr) = (a.local_addr().unwrap().to_string(), b.local_addr().unwrap().to_string());
+        let interval = Duration::from_millis(20);
+        let timeout = Duration::from_millis(150);
+        let primary = Failover::start(a, b_addr, "gw-a".into(), FailoverRole::Primary, String::new(), interval, timeout);
+        let standby = Failover::start(b, a_addr, "gw-b".into(), FailoverRole::Standby, String::new(), interval, timeout);
+
+        tokio::time::timeout(Duration::from_secs(2), primary.wait_active()).await.unwrap();
+        assert!(primary.is_active());
+        let waiting = tokio::time::timeout(Duration::from_millis(400), standby.wait_active()).await;
+        assert!(waiting.is_err(), "standby must not take over from a live primary");
+        assert!(!standby.is_active());
+
+        drop(primary);
+        tokio::time::timeout(Duration::from_secs(3), standby.wait_active()).await.unwrap();
+        assert!(standby.is_active());
+        assert!(Failover::disabled().is_active());
+    }
+}
@@ -0,0 +1,184 @@
+//! `gateway` subcommand — pure UDP → MQTT forwarder.
+//!
+//! Receives binary sensor packets on UDP and republishes each datagram,
+//! unchanged, to `<topic-prefix>/<sensor_id>` on an MQTT broker.  No VAD,
+//! persona or OpenAI machinery is started; packets are only parsed far
+//! enough to validate the header and route by `sensor_id`.
+//!
+//! The topic layout matches `bench/load_gen.py --transport mqtt` and the
+//! C bridge's `vad/sensors/+` subscription, so a gateway can sit in front
+//! of either.
+
+use crate::config::GatewayArgs;
+use crate::mqtt_health::{ self, MqttHealth };
+use crate::sensor::SensorPacket;
+use crate::stats::{ self, Stats };
+use rumqttc::v5::mqttbytes::v5::{ Packet, PublishProperties };
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::{ AsyncClient, Event, MqttOptions };
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tracing::{ debug, info, warn };
+
+/// Run the gateway until the process is stopped.
+pub async fn run(args: &GatewayArgs) -> anyhow::Result<()> {
+    let listen = format!("{}:{}", args.host, args.port);
+    let qos = match args.mqtt_qos {
+        0 => QoS::AtMostOnce,
+        1 => QoS::AtLeastOnce,
+        _ => QoS::ExactlyOnce,
+    };
+
+    let mut opts = MqttOptions::new(&args.mqtt_client_id, &args.mqtt_host, args.mqtt_port);
+    opts.set_keep_alive(Duration::from_secs(30));
+
+    info!(
+        listen = %listen,
+        broker = format!("{}:{}", args.mqtt_host, args.mqtt_port),
+        topic = format!("{}/<sensor_id>", args.mqtt_topic_prefix),
+        qos = args.mqtt_qos,
+        "🚀 UDP → MQTT gateway starting"
+    );
+
+    // Standalone: nothing serves /readyz, the link only feeds the logs
+    let (client, _) = mqtt_health::spawn_client(opts, args.mqtt_queue, &MqttHealth::default(), "gateway", |_, event| {
+        if matches!(event, Event::Incoming(Packet::ConnAck(_))) {
+            info!("✅ MQTT broker connected");
+        }
+    });
+
+    let stats = Stats::new();
+    let stats_clone = stats.clone();
+    let stats_interval = args.stats_interval_secs;
+    tokio::spawn(async move {
+        stats::stats_reporter(
+            stats_clone,
+            stats_interval,
+            String::new(),
+            crate::clock::system(),
+            crate::anomaly::AnomalyDetector::disabled(),
+            stats::StatsSinks::stdout()
+        ).await;
+    });
+
+    let socket = Arc::new(
+        crate::transport_udp::bind_reuseport(&listen, args.recv_buf_size).await?
+    );
+
+    let mut handles = Vec::new();
+    for i in 0..args.recv_threads.max(1) {
+        let socket = socket.clone();
+        let client = client.clone();
+        let stats = stats.clone();
+        let prefix = args.mqtt_topic_prefix.clone();
+        let props = crate::mqtt5::binary("sensor-packet", "", args.mqtt_expiry_secs);
+        handles.push(
+            tokio::spawn(async move {
+                forward_loop(i, socket, client, prefix, qos, props, stats).await;
+            })
+        );
+    }
+
+    for h in handles {
+        h.await?;
+    }
+    Ok(())
+}
+
+async fn forward_loop(
+    thread_id: usize,
+    socket: Arc<UdpSocket>,
+    client: AsyncClient,
+    prefix: String,
+    qos: QoS,
+    props: PublishProperties,
+    stats: Arc<Stats>
+) {
+    debug!(thread = thread_id, "gateway receiver started");
+    let mut buf = vec![0u8; 65536];
+
+    loop {
+        let (len, src) = match socket.recv_from(&mut buf).await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(thread = thread_id, error = %e, "UDP recv error");
+                stats.record_recv_error();
+                continue;
+            }
+        };
+        stats.record_recv(len);
+
+        let Some(topic) = topic_for(&prefix, &buf[..len]) else {
+            debug!(src = %src, bytes = len, "dropping malformed sensor packet");
+            stats.record_parse_error();
+            continue;
+        };
+
+        // A full client queue (broker down or slow) drops, never blocks
+        match client.try_publish_with_properties(topic, qos, false, buf[..len].to_vec(), props.clone()) {
+            Ok(()) => stats.record_processed(false),
+            Err(_) => stats.record_channel_drop(),
+        }
+    }
+}
+
+/// Topic a datagram is published to, `<prefix>/<sensor_id>`; None for
+/// a malformed sensor packet.
+fn topic_for(prefix: &str, datagram: &[u8]) -> Option<String> {
+    let pkt = SensorPacket::parse(datagram)?;
+    Some(format!("{}/{}", prefix, pkt.sensor_id))
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::DATA_TYPE_SENSOR_VECTOR;
+
+    fn datagram(sensor_id: u32) -> Vec<u8> {
+        SensorPacket::new(sensor_id, DATA_TYPE_SENSOR_VECTOR, vec![0; 40]).to_binary()
+    }
+
+    #[test]
+    fn test_topic_by_sensor_id() {
+        assert_eq!(topic_for("vad/sensors", &datagram(42)).as_deref(), Some("vad/sensors/42"));
+        assert_eq!(topic_for("vad/sensors", &datagram(42)[..20]), None);
+        // Payload shorter than payload_len
+        let mut bad = datagram(7);
+        bad.truncate(bad.len() - 1);
+        assert_eq!(topic_for("p", &bad), None);
+    }
+
+    #[tokio::test]
+    async fn test_forward_loop_counts_parse_errors_and_drops() {
+        let socket = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let addr = socket.local_addr().unwrap();
+        // Room for one publish; the event loop is never polled, so the
+        // second valid packet finds the queue full
+        let (client, _eventloop) = AsyncClient::new(MqttOptions::new("test", "127.0.0.1", 1), 1);
+        let stats = Stats::new();
+        let props = crate::mqtt5::binary("sensor-packet", "", 0);
+        let task = tokio::spawn(forward_loop(0, socket, client, "vad/sensors".into(), QoS::AtMostOnce, props, stats.clone()));
+
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        for dgram in [b"junk".to_vec(), datagram(1), datagram(2)] {
+            sender.send_to(&dgram, addr).await.unwrap();
+        }
+        for _ in 0..200 {
+            let t = stats.totals();
+            if t.parse_errors + t.processed + t.channel_drops == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        task.abort();
+
+        let totals = stats.totals();
+        assert_eq!(totals.recv_packets, 3);
+        assert_eq!((totals.parse_errors, totals.processed, totals.channel_drops), (1, 1, 1));
+    }
+}
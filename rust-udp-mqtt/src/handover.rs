@@ -0,0 +1,387 @@
+use crate::transport_openai::OpenAiHealth;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::os::fd::{ AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd };
+use std::os::unix::net::{ UnixListener, UnixStream };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+use tokio::net::{ TcpListener, UdpSocket };
+use tokio::sync::Notify;
+use tracing::{ debug, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Zero-downtime restart (socket handover)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Every UDP port (audio / sensor / test, RTP / SIP) and the REST API
+//  listener is taken from one of these before it is bound afresh:
+//
+//    systemd   socket activation (`LISTEN_FDS`, a `.socket` unit): the
+//              sockets systemd bound, matched by local address
+//    previous  with `--handover-socket PATH`, the bridge being replaced.
+//              The new process connects to PATH at startup and receives
+//              the old one's bound sockets (SCM_RIGHTS).  It then
+//              listens on PATH itself for the next upgrade.
+//
+//  After handing its sockets over, the old process stops receiving
+//  (and accepting API connections) at once; the new one reads the
+//  same sockets, so no datagram is lost in between.  The old process
+//  keeps its OpenAI session until it has been quiet for DRAIN_QUIET
+//  (replies still streaming to ESPs go out through its copy of the
+//  audio socket), at most `--handover-drain-secs`, then exits.
+//
+//  ESP sessions open in the old process are not moved: a robot mid-
+//  utterance finishes its reply from the old process and its next
+//  SESSION_START lands in the new one (conversation history follows
+//  through `--postgres-url`).
+
+/// The old process's OpenAI session counts as drained after this long
+/// without a frame.
+const DRAIN_QUIET: Duration = Duration::from_secs(3);
+
+/// Most sockets one handover carries.
+const MAX_FDS: usize = 32;
+
+/// Limit on connecting to / answering a handover peer.
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// First file descriptor passed by systemd.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Sockets this process inherited and bound.  Clone-friendly; the
+/// default inherited nothing.
+#[derive(Clone, Default)]
+pub struct Sockets {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Inherited and not yet claimed by a bind
+    inherited: Mutex<Vec<OwnedFd>>,
+    /// Copies of every bound socket, for the next handover
+    bound: Mutex<Vec<OwnedFd>>,
+    handed_over: AtomicBool,
+    handed_over_notify: Notify,
+}
+
+impl std::fmt::Debug for Sockets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sockets")
+            .field("inherited", &self.inner.inherited.lock().unwrap().len())
+            .field("bound", &self.inner.bound.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl Sockets {
+    /// Sockets from systemd, then from the bridge listening on
+    /// `handover_socket` ("" = none).
+    pub async fn inherit(handover_socket: &str) -> Self {
+        let sockets = Self::default();
+        let mut fds = from_systemd();
+        if !fds.is_empty() {
+            info!(sockets = fds.len(), "♻️ sockets inherited from systemd");
+        }
+        if !handover_socket.is_empty() {
+            let path = handover_socket.to_string();
+            match tokio::task::spawn_blocking(move || request(&path)).await {
+                Ok(Ok(received)) => {
+                    info!(sockets = received.len(), socket = %handover_socket, "♻️ sockets handed over by the previous bridge");
+                    fds.extend(received);
+                }
+                Ok(Err(e)) => debug!(socket = %handover_socket, error = %e, "no bridge to take over from"),
+                Err(e) => warn!(error = %e, "handover request failed"),
+            }
+        }
+        *sockets.inner.inherited.lock().unwrap() = fds;
+        sockets
+    }
+
+    /// An inherited socket of `udp` / TCP type bound to `addr` is waiting.
+    pub fn is_inherited(&self, addr: &str, udp: bool) -> bool {
+        let Ok(addr) = addr.parse::<SocketAddr>() else {
+            return false;
+        };
+        self.inner.inherited
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|fd| matches(fd.as_fd(), addr, udp))
+    }
+
+    /// The UDP socket for `addr`: inherited, else from `bind`.
+    pub async fn udp<F, Fut, E>(&self, addr: &str, bind: F) -> anyhow::Result<UdpSocket>
+        where F: FnOnce() -> Fut, Fut: Future<Output = Result<UdpSocket, E>>, E: Into<anyhow::Error>
+    {
+        let socket = match self.take(addr, true) {
+            Some(fd) => {
+                let socket = std::net::UdpSocket::from(fd);
+                socket.set_nonblocking(true)?;
+                debug!(addr = %addr, "♻️ inherited UDP socket");
+                UdpSocket::from_std(socket)?
+            }
+            None => bind().await.map_err(Into::into)?,
+        };
+        self.keep(socket.as_fd())?;
+        Ok(socket)
+    }
+
+    /// The TCP listener for `addr`: inherited, else bound.
+    pub async fn tcp(&self, addr: SocketAddr) -> anyhow::Result<TcpListener> {
+        let listener = match self.take(&addr.to_string(), false) {
+            Some(fd) => {
+                let listener = std::net::TcpListener::from(fd);
+                listener.set_nonblocking(true)?;
+                debug!(addr = %addr, "♻️ inherited TCP listener");
+                TcpListener::from_std(listener)?
+            }
+            None => TcpListener::bind(addr).await?,
+        };
+        self.keep(listener.as_fd())?;
+        Ok(listener)
+    }
+
+    fn take(&self, addr: &str, udp: bool) -> Option<OwnedFd> {
+        let addr = addr.parse::<SocketAddr>().ok()?;
+        let mut inherited = self.inner.inherited.lock().unwrap();
+        let i = inherited.iter().position(|fd| matches(fd.as_fd(), addr, udp))?;
+        Some(inherited.swap_remove(i))
+    }
+
+    fn keep(&self, fd: BorrowedFd<'_>) -> io::Result<()> {
+        self.inner.bound.lock().unwrap().push(fd.try_clone_to_owned()?);
+        Ok(())
+    }
+
+    /// Hand every bound socket to the next bridge connecting to `path`
+    /// (removed and re-created), once.
+    pub fn listen(&self, path: &str) -> anyhow::Result<()> {
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path).map_err(|e| anyhow::anyhow!("--handover-socket {path}: {e}"))?;
+        let sockets = self.clone();
+        let path = path.to_string();
+        std::thread::Builder::new().name("handover".into()).spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream.and_then(|stream| {
+                    stream.set_write_timeout(Some(PEER_TIMEOUT))?;
+                    let bound = sockets.inner.bound.lock().unwrap();
+                    let fds: Vec<BorrowedFd<'_>> = bound.iter().map(|fd| fd.as_fd()).collect();
+                    send_fds(&stream, &fds)
+                });
+                match result {
+                    Ok(()) => {
+                        info!(socket = %path, "♻️ sockets handed to the next bridge — draining");
+                        sockets.inner.handed_over.store(true, Ordering::Relaxed);
+                        sockets.inner.handed_over_notify.notify_waiters();
+                        return;
+                    }
+                    Err(e) => warn!(socket = %path, error = %e, "handover failed"),
+                }
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Resolve once the sockets were handed to the next bridge.
+    pub async fn handed_over(&self) {
+        loop {
+            let notified = self.inner.handed_over_notify.notified();
+            if self.inner.handed_over.load(Ordering::Relaxed) {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Wait until the OpenAI session has been quiet for DRAIN_QUIET, or
+/// `max` passed.
+pub async fn drain(openai: &OpenAiHealth, max: Duration) {
+    let started = Instant::now();
+    loop {
+        let snapshot = openai.snapshot();
+        let quiet = !snapshot.enabled ||
+            !snapshot.connected ||
+            snapshot.last_event_age_ms.is_none_or(|age| age >= DRAIN_QUIET.as_millis() as u64);
+        if quiet || started.elapsed() >= max {
+            info!(drained_ms = started.elapsed().as_millis() as u64, quiet, "♻️ drained");
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// Whether `fd` is a socket of the wanted type bound to `addr`.
+fn matches(fd: BorrowedFd<'_>, addr: SocketAddr, udp: bool) -> bool {
+    let socket = socket2::SockRef::from(&fd);
+    let wanted = if udp { socket2::Type::DGRAM } else { socket2::Type::STREAM };
+    socket.r#type().is_ok_and(|t| t == wanted) &&
+        socket
+            .local_addr()
+            .ok()
+            .and_then(|a| a.as_socket())
+            .is_some_and(|a| a == addr)
+}
+
+/// `LISTEN_FDS` passed to this process by systemd.
+fn from_systemd() -> Vec<OwnedFd> {
+    let for_us = std::env
+        ::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id());
+    let count = std::env
+        ::var("LISTEN_FDS")
+        .ok()
+        .and_then(|n| n.trim().parse::<RawFd>().ok())
+        .unwrap_or(0);
+    if !for_us || count <= 0 {
+        return Vec::new();
+    }
+    // SAFETY: systemd passes `count` open descriptors from fd 3 on, and
+    // nothing else in this process owns them
+    (LISTEN_FDS_START..LISTEN_FDS_START + count).map(|fd| unsafe { OwnedFd::from_raw_fd(fd) }).collect()
+}
+
+/// Ask the bridge listening on `path` for its sockets.
+fn request(path: &str) -> io::Result<Vec<OwnedFd>> {
+    let stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(PEER_TIMEOUT))?;
+    recv_fds(&stream)
+}
+
+fn send_fds(stream: &UnixStream, fds: &[BorrowedFd<'_>]) -> io::Result<()> {
+    if fds.len() > MAX_FDS {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} sockets, at most {MAX_FDS}", fds.len())));
+    }
+    let payload = [fds.len() as u8];
+    let mut iov = libc::iovec { iov_base: payload.as_ptr() as *mut libc::c_void, iov_len: payload.len() };
+    let data_len = std::mem::size_of_val(fds) as u32;
+    // SAFETY: CMSG_SPACE only computes a size
+    let space = (unsafe { libc::CMSG_SPACE(data_len) }) as usize;
+    let mut control = vec![0u64; space.div_ceil(8)];
+    // SAFETY: msghdr is plain data; all-zero is a valid empty message
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    if !fds.is_empty() {
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = space as _;
+        // SAFETY: the control buffer is aligned and CMSG_SPACE(data_len)
+        // bytes long, so the first header and its data fit
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(data_len) as _;
+            let data = libc::CMSG_DATA(cmsg).cast::<RawFd>();
+            for (i, fd) in fds.iter().enumerate() {
+                data.add(i).write_unaligned(fd.as_raw_fd());
+            }
+        }
+    }
+    // SAFETY: msg points at live buffers for the duration of the call
+    if (unsafe { libc::sendmsg(stream.as_raw_fd(), &msg, 0) }) < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn recv_fds(stream: &UnixStream) -> io::Result<Vec<OwnedFd>> {
+    let mut payload = [0u8; 1];
+    let mut iov = libc::iovec { iov_base: payload.as_mut_ptr().cast(), iov_len: payload.len() };
+    // SAFETY: CMSG_SPACE only computes a size
+    let space = (unsafe { libc::CMSG_SPACE((MAX_FDS * std::mem::size_of::<RawFd>()) as u32) }) as usize;
+    let mut control = vec![0u64; space.div_ceil(8)];
+    // SAFETY: msghdr is plain data; all-zero is a valid empty message
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = space as _;
+    #[cfg(target_os = "linux")]
+    let flags = libc::MSG_CMSG_CLOEXEC;
+    #[cfg(not(target_os = "linux"))]
+    let flags = 0;
+    // SAFETY: msg points at live buffers for the duration of the call
+    let n = unsafe { libc::recvmsg(stream.as_raw_fd(), &mut msg, flags) };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if n == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "closed without sockets"));
+    }
+    let mut fds = Vec::new();
+    // SAFETY: the kernel filled `control` with well-formed headers, and
+    // each SCM_RIGHTS descriptor is now owned by this process
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let len = (*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                let data = libc::CMSG_DATA(cmsg).cast::<RawFd>();
+                for i in 0..len / std::mem::size_of::<RawFd>() {
+                    fds.push(OwnedFd::from_raw_fd(data.add(i).read_unaligned()));
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+        warn!(received = fds.len(), "handover truncated — some sockets were lost");
+    }
+    Ok(fds)
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sockets_handed_to_the_next_process() {
+        let path = std::env::temp_dir().join(format!("vad-handover-{}.sock", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        // The old bridge binds its ports and listens for a successor
+        let old = Sockets::default();
+        let udp = old.udp("127.0.0.1:0", || UdpSocket::bind("127.0.0.1:0")).await.unwrap();
+        let api = old.tcp("127.0.0.1:0".parse().unwrap()).await.unwrap();
+        let (udp_addr, api_addr) = (udp.local_addr().unwrap(), api.local_addr().unwrap());
+        old.listen(path).unwrap();
+
+        // The new one receives them instead of binding
+        let new = Sockets::inherit(path).await;
+        tokio::time::timeout(Duration::from_secs(2), old.handed_over()).await.unwrap();
+        assert!(new.is_inherited(&udp_addr.to_string(), true));
+        assert!(!new.is_inherited(&udp_addr.to_string(), false));
+        let taken = new.udp(&udp_addr.to_string(), || async { Err(anyhow::anyhow!("must not bind")) }).await.unwrap();
+        let listener = new.tcp(api_addr).await.unwrap();
+        assert_eq!(listener.local_addr().unwrap(), api_addr);
+
+        // Datagrams sent after the handover reach the new process
+        drop(udp);
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.send_to(b"ping", udp_addr).await.unwrap();
+        let mut buf = [0u8; 8];
+        let (n, _) = tokio::time::timeout(Duration::from_secs(2), taken.recv_from(&mut buf)).await.unwrap().unwrap();
+        assert_eq!(&buf[..n], b"ping");
+
+        // Nothing to take over from: bind as usual
+        let none = Sockets::inherit("/definitely/not/a/bridge.sock").await;
+        assert!(!none.is_inherited(&udp_addr.to_string(), true));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_drain_ends_when_openai_is_quiet() {
+        let off = OpenAiHealth::new(false, Duration::ZERO, Duration::ZERO);
+        tokio::time::timeout(Duration::from_secs(1), drain(&off, Duration::from_secs(30))).await.unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+//! VAD sensor bridge as a library: the full pipeline (`bridge`) plus
+//! every building block the `vad-sensor-bridge` binary is made of.
+//!
+//! Embedders and integration tests can run a real bridge without any
+//! sockets via `bridge::Transport::Loopback` (see `transport_loopback`).
+
+pub mod admin;
+pub mod anomaly;
+pub mod api;
+pub mod api_limits;
+pub mod audio_analysis;
+pub mod audio_events;
+#[cfg(feature = "onnx")]
+pub mod audio_events_onnx;
+pub mod audio_routes;
+pub mod audio_window;
+pub mod bridge;
+pub mod bridge_openai;
+pub mod broadcast;
+pub mod calibrate;
+pub mod capture;
+pub mod clips;
+pub mod clock;
+pub mod cluster;
+#[cfg(feature = "cluster")]
+pub mod cluster_redis;
+pub mod config;
+pub mod conversation;
+pub mod device_data;
+pub mod device_import;
+pub mod devices;
+pub mod diagnostics;
+pub mod distress;
+pub mod downlink_pacing;
+pub mod emotion_history;
+#[cfg(feature = "onnx")]
+pub mod emotion_onnx;
+pub mod error;
+pub mod esp_audio_protocol;
+pub mod events;
+pub mod experiments;
+pub mod failover;
+pub mod gateway;
+pub mod handover;
+pub mod mel;
+pub mod migrations;
+pub mod monitor_audio;
+pub mod mqtt5;
+pub mod mqtt_health;
+pub mod pcm;
+pub mod ota;
+pub mod persona;
+pub mod presence;
+pub mod prompt;
+pub mod quiet_hours;
+pub mod recorder;
+#[cfg(feature = "parquet")]
+pub mod recorder_parquet;
+pub mod recording_policy;
+pub mod recordings;
+pub mod redaction;
+pub mod replay;
+pub mod reports;
+pub mod safety;
+pub mod self_test;
+pub mod sensor;
+pub mod sensor_sanitize;
+pub mod sensor_schema;
+pub mod sensor_smoother;
+pub mod session_log;
+pub mod shard;
+pub mod silence_trim;
+pub mod simulate;
+pub mod sound_levels;
+#[cfg(feature = "onnx")]
+pub mod speaker_onnx;
+pub mod speakers;
+pub mod start_policy;
+pub mod stats;
+pub mod store;
+#[cfg(feature = "postgres")]
+pub mod store_postgres;
+pub mod subscriptions;
+pub mod systemd;
+pub mod telemetry;
+pub mod tenants;
+pub mod trace;
+pub mod transcripts;
+pub mod transport_local;
+pub mod transport_loopback;
+pub mod transport_openai;
+pub mod transport_rtp;
+pub mod transport_serial;
+pub mod transport_sip;
+pub mod transport_udp;
+pub mod transport_zmq;
+pub mod tts;
+pub mod udp_batch;
+pub mod uring;
+pub mod vad;
+pub mod vad_response;
+pub mod vad_shadow;
+pub mod validate;
+pub mod voice_commands;
+pub mod volume;
+pub mod wav_writer;
+#[cfg(any(test, feature = "fuzz"))]
+pub mod wire_check;
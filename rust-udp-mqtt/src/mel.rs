@@ -0,0 +1,82 @@
+// ─────────────────────────────────────────────────────────────────────
+//  Log mel-band energies of 16 kHz s16 frames
+// ─────────────────────────────────────────────────────────────────────
+//
+//  The coarse spectrum shared by the speaker voice prints (`speakers.rs`)
+//  and the voice-command templates (`voice_commands.rs`): per 25 ms
+//  frame, the log power of `bands` mel-spaced bands, each the mean of a
+//  Hann-windowed DFT at three frequencies across the band.  No FFT crate
+//  needed; the DFT basis is built once per `MelBands`.
+
+/// 25 ms analysis frames, 10 ms hop (16 kHz).
+pub const FRAME: usize = 400;
+pub const HOP: usize = 160;
+
+const SAMPLE_RATE: f32 = 16_000.0;
+/// DFT probe frequencies per band.
+const PROBES: usize = 3;
+
+/// Mel filterbank over `lo_hz .. hi_hz`.
+pub struct MelBands {
+    /// Windowed (cos, sin) per probe frequency, PROBES per band
+    basis: Vec<Vec<(f32, f32)>>,
+}
+
+impl MelBands {
+    pub fn new(bands: usize, lo_hz: f32, hi_hz: f32) -> Self {
+        let mel = |hz: f32| 2595.0 * (1.0 + hz / 700.0).log10();
+        let hz = |mel: f32| 700.0 * ((10f32).powf(mel / 2595.0) - 1.0);
+        let (lo, hi) = (mel(lo_hz), mel(hi_hz));
+        let edge = |i: usize| hz(lo + ((hi - lo) * (i as f32)) / (bands as f32));
+        let window = |n: usize| 0.5 - 0.5 * ((2.0 * std::f32::consts::PI * (n as f32)) / ((FRAME - 1) as f32)).cos();
+        let basis = (0..bands)
+            .flat_map(|b| {
+                let (start, end) = (edge(b), edge(b + 1));
+                (1..=PROBES).map(move |p| start + ((end - start) * (p as f32)) / ((PROBES + 1) as f32))
+            })
+            .map(|f| {
+                let w = (2.0 * std::f32::consts::PI * f) / SAMPLE_RATE;
+                (0..FRAME).map(|n| (window(n) * (w * (n as f32)).cos(), window(n) * (w * (n as f32)).sin())).collect()
+            })
+            .collect();
+        Self { basis }
+    }
+
+    pub fn bands(&self) -> usize {
+        self.basis.len() / PROBES
+    }
+
+    /// Log power per band of one FRAME-sample frame.
+    pub fn log_energies(&self, frame: &[i16]) -> Vec<f32> {
+        self.basis
+            .chunks(PROBES)
+            .map(|bins| {
+                let power: f32 =
+                    bins
+                        .iter()
+                        .map(|bin| {
+                            let (re, im) = frame
+                                .iter()
+                                .zip(bin)
+                                .fold((0f32, 0f32), |(re, im), (&s, &(c, si))| (re + (s as f32) * c, im - (s as f32) * si));
+                            re * re + im * im
+                        })
+                        .sum::<f32>() / (PROBES as f32);
+                (power + 1.0).ln()
+            })
+            .collect()
+    }
+}
+
+/// RMS of an s16 frame.
+pub fn rms(frame: &[i16]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    (
+        frame
+            .iter()
+            .map(|&s| (s as f32) * (s as f32))
+            .sum::<f32>() / (frame.len() as f32)
+    ).sqrt()
+}
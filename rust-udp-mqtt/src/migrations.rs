@@ -0,0 +1,177 @@
+use crate::config::Config;
+use crate::device_data::INDEX_FILE;
+use crate::recording_policy::POLICY_FILE;
+use serde::{ Deserialize, Serialize };
+use std::path::Path;
+use tracing::info;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Schema versioning + startup migrations
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Two kinds of persistent store carry a schema version, and both are
+//  migrated at startup, before anything reads them:
+//
+//    --audio-save-dir   device file index, recording policies, session
+//                       logs.  The version is kept in
+//                       `<dir>/schema_version.json`; a directory without
+//                       one predates versioning (version 0), one that
+//                       does not exist yet has nothing to migrate.  Each
+//                       step below runs once and the file is rewritten
+//                       after every step, so an interrupted upgrade
+//                       resumes.
+//    --postgres-url     the shared store's tables, migrated by the SQL
+//                       files in `migrations/postgres/` (embedded at build
+//                       time, see `store_postgres.rs`).
+//
+//  A store written by a newer bridge is refused rather than read: a
+//  downgrade must not half-understand (and then rewrite) newer data.
+//  `--migrate-only` applies the migrations and exits, for upgrading
+//  ahead of a rollout.
+
+/// Version file name inside `--audio-save-dir`.
+pub const VERSION_FILE: &str = "schema_version.json";
+
+/// One data-directory migration.
+type Step = fn(&Path) -> anyhow::Result<()>;
+
+/// Data-directory migrations in order; step `i` upgrades to version
+/// `i + 1`.
+const DATA_DIR_STEPS: &[(&str, Step)] = &[("repair device file index", repair_index)];
+
+/// Data-directory version this build writes.
+pub const DATA_DIR_VERSION: u32 = DATA_DIR_STEPS.len() as u32;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionStamp {
+    version: u32,
+    /// Bridge release that last migrated the directory
+    #[serde(default)]
+    bridge: String,
+}
+
+/// Version of the data in `dir` (0 = unversioned or empty).
+pub fn data_dir_version(dir: &Path) -> anyhow::Result<u32> {
+    let path = dir.join(VERSION_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(text) => {
+            let stamp: VersionStamp = serde_json
+                ::from_str(&text)
+                .map_err(|e| anyhow::anyhow!("invalid {}: {e}", path.display()))?;
+            Ok(stamp.version)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(e) => anyhow::bail!("failed to read {}: {e}", path.display()),
+    }
+}
+
+/// Bring `dir` to [`DATA_DIR_VERSION`]; returns the steps applied.
+pub fn migrate_data_dir(dir: &str) -> anyhow::Result<Vec<&'static str>> {
+    let dir = Path::new(dir);
+    if !dir.is_dir() {
+        // Nothing written yet; stamped on the first startup that finds it
+        return Ok(Vec::new());
+    }
+    let current = data_dir_version(dir)?;
+    if current > DATA_DIR_VERSION {
+        anyhow::bail!(
+            "{} is at schema version {current}, written by a newer bridge (this build supports {DATA_DIR_VERSION}); upgrade the bridge or use another --audio-save-dir",
+            dir.display()
+        );
+    }
+    let mut applied = Vec::new();
+    for (i, (name, step)) in DATA_DIR_STEPS.iter().enumerate().skip(current as usize) {
+        step(dir).map_err(|e| anyhow::anyhow!("migration {} ({name}) failed: {e}", i + 1))?;
+        stamp(dir, (i + 1) as u32)?;
+        applied.push(*name);
+    }
+    if !applied.is_empty() {
+        info!(dir = %dir.display(), from = current, to = DATA_DIR_VERSION, steps = ?applied, "📦 data directory migrated");
+    }
+    Ok(applied)
+}
+
+fn stamp(dir: &Path, version: u32) -> anyhow::Result<()> {
+    let stamp = VersionStamp { version, bridge: env!("CARGO_PKG_VERSION").to_string() };
+    let path = dir.join(VERSION_FILE);
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(&stamp)?)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Every persistent store of `config` (`--migrate-only`).
+pub async fn migrate(config: &Config) -> anyhow::Result<()> {
+    migrate_data_dir(&config.audio_save_dir)?;
+    if config.postgres_url.is_empty() {
+        return Ok(());
+    }
+    #[cfg(feature = "postgres")]
+    {
+        crate::store_postgres::migrate(&config.postgres_url).await?.close().await;
+        Ok(())
+    }
+    #[cfg(not(feature = "postgres"))]
+    anyhow::bail!("--postgres-url requires a build with `--features postgres`")
+}
+
+// ── Steps ────────────────────────────────────────────────────────────
+
+/// 1: a crash mid-append leaves a torn last line in the device file
+/// index, and one mid-rewrite leaves `.tmp` files behind.  Drop both so
+/// later rewrites start from clean files.
+fn repair_index(dir: &Path) -> anyhow::Result<()> {
+    let index = dir.join(INDEX_FILE);
+    if let Ok(text) = std::fs::read_to_string(&index) {
+        let kept: String = text
+            .lines()
+            .filter(|l| serde_json::from_str::<serde_json::Value>(l).is_ok_and(|v| v["device"].is_string()))
+            .map(|l| format!("{l}\n"))
+            .collect();
+        if kept != text {
+            let tmp = index.with_extension("jsonl.tmp");
+            std::fs::write(&tmp, kept)?;
+            std::fs::rename(&tmp, &index)?;
+        }
+    }
+    for leftover in [index.with_extension("jsonl.tmp"), dir.join(POLICY_FILE).with_extension("json.tmp")] {
+        match std::fs::remove_file(&leftover) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e.into());
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_dir_migrates_once_and_refuses_newer() {
+        let dir = std::env::temp_dir().join(format!("vad-migrations-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir_str = dir.to_str().unwrap();
+        let good = r#"{"device":"aa:bb:cc:dd:ee:ff","path":"esp_10_0_0_7_20261016_150012.wav"}"#;
+        std::fs::write(dir.join(INDEX_FILE), format!("{good}\n{{\"device\":\"aa:b")).unwrap();
+        std::fs::write(dir.join("recording_policies.json.tmp"), "{").unwrap();
+        assert_eq!(data_dir_version(&dir).unwrap(), 0);
+
+        assert_eq!(migrate_data_dir(dir_str).unwrap(), ["repair device file index"]);
+        assert_eq!(std::fs::read_to_string(dir.join(INDEX_FILE)).unwrap(), format!("{good}\n"));
+        assert!(!dir.join("recording_policies.json.tmp").exists());
+        assert_eq!(data_dir_version(&dir).unwrap(), DATA_DIR_VERSION);
+        assert!(migrate_data_dir(dir_str).unwrap().is_empty());
+
+        stamp(&dir, DATA_DIR_VERSION + 1).unwrap();
+        let err = migrate_data_dir(dir_str).unwrap_err().to_string();
+        assert!(err.contains("newer bridge"), "{err}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
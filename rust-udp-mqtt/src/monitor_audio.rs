@@ -0,0 +1,331 @@
+use crate::config::Config;
+use crate::pcm::Downmix;
+use crate::session_log::SessionLogs;
+use serde_json::json;
+use std::net::SocketAddr;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Monitoring copies of session audio (Ogg Opus)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  With `--monitor-opus-kbps N` (build with `--features opus`), every
+//  archived session WAV gets a low-bitrate copy next to it:
+//
+//    esp_<ip>_<YYYYmmdd_HHMMSS>.wav    16 kHz s16, archival
+//    esp_<ip>_<YYYYmmdd_HHMMSS>.opus   Ogg Opus at N kbps, for dashboards
+//                                      (multi-channel WAVs mixed to mono)
+//
+//  At 16 kbps a minute of speech is ~120 KB instead of ~1.9 MB.  Encoding
+//  reads the finished WAV back on the blocking pool, so it never delays
+//  the SESSION_END ACK.  The copy is attached to the session record and
+//  served by `GET /sessions/{id}/monitor`.
+//
+//  The Ogg framing (RFC 7845) is written here; only the encoder itself
+//  needs libopus.
+
+/// Opus frame: 20 ms of 16 kHz audio.
+#[cfg_attr(not(feature = "opus"), allow(dead_code))]
+const FRAME_SAMPLES: usize = 320;
+
+/// Granule positions always count 48 kHz samples.
+const GRANULE_PER_INPUT_SAMPLE: u64 = 48_000 / 16_000;
+
+/// Packets per Ogg page (~1 s): fewer pages, less framing overhead.
+const PACKETS_PER_PAGE: usize = 50;
+
+const OGG_BOS: u8 = 0x02;
+const OGG_EOS: u8 = 0x04;
+
+/// Encodes monitoring copies (a no-op when `--monitor-opus-kbps` is 0).
+/// Clone-friendly.
+#[derive(Clone)]
+pub struct MonitorAudio {
+    kbps: u32,
+}
+
+impl MonitorAudio {
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let kbps = config.monitor_opus_kbps;
+        if kbps != 0 {
+            #[cfg(not(feature = "opus"))]
+            anyhow::bail!("--monitor-opus-kbps requires a build with `--features opus`");
+            #[cfg(feature = "opus")]
+            if !(6..=128).contains(&kbps) {
+                anyhow::bail!("--monitor-opus-kbps must be 6..=128 (got {kbps})");
+            }
+        }
+        Ok(Self { kbps })
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.kbps != 0
+    }
+
+    /// Encode the archived `wav_path` next to it in the background, then
+    /// attach the copy to `src`'s session record.
+    pub fn spawn(&self, wav_path: &str, src: SocketAddr, session_logs: &SessionLogs) {
+        if !self.enabled() {
+            return;
+        }
+        let path = std::path::Path::new(wav_path).with_extension("opus").to_string_lossy().into_owned();
+        let wav_path = wav_path.to_string();
+        let kbps = self.kbps;
+        let session_logs = session_logs.clone();
+        tokio::spawn(async move {
+            let encoded = tokio::task::spawn_blocking(move || {
+                let wav = std::fs::read(&wav_path)?;
+                let channels = wav.get(22..24).map_or(1, |c| u16::from_le_bytes([c[0], c[1]]));
+                encode(&Downmix::default().apply(wav.get(44..).unwrap_or_default(), channels as usize), kbps)
+            }).await;
+            let ogg = match encoded {
+                Ok(Ok(ogg)) => ogg,
+                Ok(Err(e)) => {
+                    warn!(src = %src, error = %e, "failed to encode monitoring copy");
+                    return;
+                }
+                Err(e) => {
+                    warn!(src = %src, error = %e, "monitoring copy encoder panicked");
+                    return;
+                }
+            };
+            if let Err(e) = tokio::fs::write(&path, &ogg).await {
+                warn!(path = %path, error = %e, "failed to save monitoring copy");
+                return;
+            }
+            info!(path = %path, bytes = ogg.len(), kbps, "🎧 monitoring copy saved");
+            session_logs.monitor(src, &path, json!({ "path": path, "bytes": ogg.len(), "kbps": kbps }));
+        });
+    }
+}
+
+#[cfg(feature = "opus")]
+pub(crate) fn encode(pcm: &[u8], kbps: u32) -> anyhow::Result<Vec<u8>> {
+    use audiopus::coder::Encoder;
+    use audiopus::{ Application, Bitrate, Channels, SampleRate };
+
+    let mut encoder = Encoder::new(SampleRate::Hz16000, Channels::Mono, Application::Voip)?;
+    encoder.set_bitrate(Bitrate::BitsPerSecond((kbps * 1000) as i32))?;
+    let pre_skip = (encoder.lookahead()? as u64 * GRANULE_PER_INPUT_SAMPLE) as u16;
+
+    let samples: Vec<i16> = pcm
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+    let mut ogg = OggOpus::new(stream_serial(), pre_skip);
+    let mut packet = [0u8; 1275];
+    for chunk in samples.chunks(FRAME_SAMPLES) {
+        let mut frame = [0i16; FRAME_SAMPLES];
+        frame[..chunk.len()].copy_from_slice(chunk);
+        let len = encoder.encode(&frame, &mut packet)?;
+        ogg.push(&packet[..len], FRAME_SAMPLES as u64);
+    }
+    tracing::debug!(samples = samples.len(), kbps, "Opus monitoring copy encoded");
+    Ok(ogg.finish(samples.len() as u64))
+}
+
+#[cfg(not(feature = "opus"))]
+pub(crate) fn encode(_pcm: &[u8], _kbps: u32) -> anyhow::Result<Vec<u8>> {
+    anyhow::bail!("built without `--features opus`")
+}
+
+/// Ogg stream serial: only has to differ between chained streams.
+#[cfg_attr(not(feature = "opus"), allow(dead_code))]
+fn stream_serial() -> u32 {
+    chrono::Utc::now().timestamp_subsec_nanos()
+}
+
+// ── Ogg Opus container ───────────────────────────────────────────────
+
+/// Ogg Opus stream writer (mono, RFC 7845): ID + comment header pages,
+/// then audio packets grouped `PACKETS_PER_PAGE` to a page.
+#[cfg_attr(not(feature = "opus"), allow(dead_code))]
+struct OggOpus {
+    out: Vec<u8>,
+    serial: u32,
+    page_seq: u32,
+    pre_skip: u16,
+    /// Lacing values + data of the page being filled
+    lacing: Vec<u8>,
+    data: Vec<u8>,
+    packets: usize,
+    /// Input samples covered by the packets written so far
+    samples: u64,
+}
+
+#[cfg_attr(not(feature = "opus"), allow(dead_code))]
+impl OggOpus {
+    fn new(serial: u32, pre_skip: u16) -> Self {
+        let mut ogg = Self {
+            out: Vec::new(),
+            serial,
+            page_seq: 0,
+            pre_skip,
+            lacing: Vec::new(),
+            data: Vec::new(),
+            packets: 0,
+            samples: 0,
+        };
+
+        let mut head = Vec::with_capacity(19);
+        head.extend_from_slice(b"OpusHead");
+        head.push(1); // version
+        head.push(1); // channels
+        head.extend_from_slice(&pre_skip.to_le_bytes());
+        head.extend_from_slice(&16_000u32.to_le_bytes()); // input rate
+        head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+        head.push(0); // mapping family: mono/stereo
+        ogg.add(&head);
+        ogg.flush(OGG_BOS, 0);
+
+        let vendor = concat!("vad-sensor-bridge ", env!("CARGO_PKG_VERSION"));
+        let mut tags = Vec::new();
+        tags.extend_from_slice(b"OpusTags");
+        tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        tags.extend_from_slice(vendor.as_bytes());
+        tags.extend_from_slice(&0u32.to_le_bytes()); // no user comments
+        ogg.add(&tags);
+        ogg.flush(0, 0);
+        ogg
+    }
+
+    /// Append one encoded packet covering `samples` input samples.
+    fn push(&mut self, packet: &[u8], samples: u64) {
+        if self.packets == PACKETS_PER_PAGE || self.lacing.len() + packet.len() / 255 + 1 > 255 {
+            self.flush(0, self.granule());
+        }
+        self.add(packet);
+        self.samples += samples;
+    }
+
+    /// Close the stream; `total_samples` trims the padding of the last
+    /// frame on playback.
+    fn finish(mut self, total_samples: u64) -> Vec<u8> {
+        self.samples = self.samples.min(total_samples);
+        let granule = self.granule();
+        self.flush(OGG_EOS, granule);
+        self.out
+    }
+
+    fn granule(&self) -> u64 {
+        self.pre_skip as u64 + self.samples * GRANULE_PER_INPUT_SAMPLE
+    }
+
+    fn add(&mut self, packet: &[u8]) {
+        // 255-byte segments, then a shorter one (possibly empty) ends it
+        self.lacing.extend(std::iter::repeat_n(255u8, packet.len() / 255));
+        self.lacing.push((packet.len() % 255) as u8);
+        self.data.extend_from_slice(packet);
+        self.packets += 1;
+    }
+
+    fn flush(&mut self, flags: u8, granule: u64) {
+        let start = self.out.len();
+        self.out.extend_from_slice(b"OggS");
+        self.out.push(0); // version
+        self.out.push(flags);
+        self.out.extend_from_slice(&granule.to_le_bytes());
+        self.out.extend_from_slice(&self.serial.to_le_bytes());
+        self.out.extend_from_slice(&self.page_seq.to_le_bytes());
+        self.out.extend_from_slice(&[0; 4]); // CRC, filled below
+        self.out.push(self.lacing.len() as u8);
+        self.out.append(&mut self.lacing);
+        self.out.append(&mut self.data);
+        let crc = ogg_crc(&self.out[start..]);
+        self.out[start + 22..start + 26].copy_from_slice(&crc.to_le_bytes());
+        self.page_seq += 1;
+        self.packets = 0;
+    }
+}
+
+/// Ogg page checksum: CRC-32, polynomial 0x04c11db7, MSB first, no
+/// reflection, zero init and no final XOR.
+fn ogg_crc(data: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for &byte in data {
+        crc ^= (byte as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (flags, granule, sequence, packets) of every page in `ogg`.
+    fn pages(ogg: &[u8]) -> Vec<(u8, u64, u32, Vec<Vec<u8>>)> {
+        let mut pages = Vec::new();
+        let mut pos = 0;
+        while pos < ogg.len() {
+            let page = &ogg[pos..];
+            assert_eq!(&page[..4], b"OggS");
+            let segments = page[26] as usize;
+            let lacing = &page[27..27 + segments];
+            let body_len: usize = lacing.iter().map(|&l| l as usize).sum();
+            let len = 27 + segments + body_len;
+
+            let mut copy = page[..len].to_vec();
+            copy[22..26].fill(0);
+            assert_eq!(ogg_crc(&copy).to_le_bytes(), page[22..26], "bad CRC");
+
+            let mut packets = Vec::new();
+            let mut current = Vec::new();
+            let mut body = &page[27 + segments..len];
+            for &l in lacing {
+                current.extend_from_slice(&body[..l as usize]);
+                body = &body[l as usize..];
+                if l < 255 {
+                    packets.push(std::mem::take(&mut current));
+                }
+            }
+            let granule = u64::from_le_bytes(page[6..14].try_into().unwrap());
+            let seq = u32::from_le_bytes(page[18..22].try_into().unwrap());
+            pages.push((page[5], granule, seq, packets));
+            pos += len;
+        }
+        pages
+    }
+
+    #[test]
+    fn test_ogg_opus_pages() {
+        let mut ogg = OggOpus::new(7, 312);
+        for i in 0..60u8 {
+            ogg.push(&[i; 40], FRAME_SAMPLES as u64);
+        }
+        ogg.push(&[0xAB; 600], FRAME_SAMPLES as u64); // spans 3 segments
+        let out = ogg.finish(61 * 320 - 100);
+
+        let pages = pages(&out);
+        assert_eq!(pages.len(), 4);
+        let (flags, granule, seq, ref packets) = pages[0];
+        assert_eq!((flags, granule, seq), (OGG_BOS, 0, 0));
+        assert_eq!(&packets[0][..8], b"OpusHead");
+        assert_eq!(u16::from_le_bytes([packets[0][10], packets[0][11]]), 312);
+        assert_eq!(&pages[1].3[0][..8], b"OpusTags");
+
+        // First audio page: 50 packets of 20 ms
+        assert_eq!(pages[2].3.len(), PACKETS_PER_PAGE);
+        assert_eq!(pages[2].1, 312 + 50 * 960);
+
+        // Last page: the rest, end-trimmed granule
+        let (flags, granule, seq, ref packets) = pages[3];
+        assert_eq!((flags, seq), (OGG_EOS, 3));
+        assert_eq!(granule, 312 + (61 * 320 - 100) * 3);
+        assert_eq!(packets.len(), 11);
+        assert_eq!(packets[10], vec![0xAB; 600]);
+    }
+
+    #[test]
+    fn test_disabled_without_bitrate() {
+        use clap::Parser;
+        let config = crate::config::Cli::parse_from(["vad-sensor-bridge"]).serve;
+        assert!(!MonitorAudio::from_config(&config).unwrap().enabled());
+    }
+}
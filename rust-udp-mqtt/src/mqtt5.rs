@@ -0,0 +1,69 @@
+use rumqttc::v5::mqttbytes::v5::PublishProperties;
+
+// ─────────────────────────────────────────────────────────────────────
+//  MQTT 5 message metadata
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Every broker connection speaks MQTT 5, so each message the bridge
+//  publishes says what it is without consumers parsing the topic:
+//
+//    content type   application/json | application/octet-stream
+//    user props     format=<alert|transcript|admin-reply|
+//                          subscription-reply|sensor-packet>
+//                   tenant=<tenant>       (multi-tenant instances only)
+//    expiry         --mqtt-expiry-secs    (0 = kept until delivered)
+//
+//  With an expiry the broker discards messages a slow or offline
+//  consumer has not fetched in time, instead of replaying a backlog of
+//  stale results when it catches up.
+
+/// Properties of a JSON message of kind `format`.
+pub fn json(format: &str, tenant: &str, expiry_secs: u32) -> PublishProperties {
+    PublishProperties {
+        // UTF-8 payload
+        payload_format_indicator: Some(1),
+        content_type: Some("application/json".into()),
+        ..binary(format, tenant, expiry_secs)
+    }
+}
+
+/// Properties of a binary message of kind `format`.
+pub fn binary(format: &str, tenant: &str, expiry_secs: u32) -> PublishProperties {
+    let mut user_properties = vec![("format".to_string(), format.to_string())];
+    if !tenant.is_empty() {
+        user_properties.push(("tenant".into(), tenant.into()));
+    }
+    PublishProperties {
+        content_type: Some("application/octet-stream".into()),
+        message_expiry_interval: (expiry_secs > 0).then_some(expiry_secs),
+        user_properties,
+        ..Default::default()
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_properties_carry_format_tenant_and_expiry() {
+        let props = json("alert", "acme", 30);
+        assert_eq!(props.content_type.as_deref(), Some("application/json"));
+        assert_eq!(props.payload_format_indicator, Some(1));
+        assert_eq!(props.message_expiry_interval, Some(30));
+        assert_eq!(
+            props.user_properties,
+            [("format".to_string(), "alert".to_string()), ("tenant".to_string(), "acme".to_string())]
+        );
+
+        // Single-tenant, no expiry
+        let props = binary("sensor-packet", "", 0);
+        assert_eq!(props.content_type.as_deref(), Some("application/octet-stream"));
+        assert_eq!((props.payload_format_indicator, props.message_expiry_interval), (None, None));
+        assert_eq!(props.user_properties.len(), 1);
+    }
+}
@@ -0,0 +1,213 @@
+use rumqttc::v5::mqttbytes::v5::Packet;
+use rumqttc::v5::{ AsyncClient, Event, MqttOptions };
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{ AtomicBool, AtomicU64, Ordering };
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+// ─────────────────────────────────────────────────────────────────────
+//  MQTT connection health
+// ─────────────────────────────────────────────────────────────────────
+//
+//  The bridge keeps up to six broker connections of its own:
+//
+//    admin         --admin-mqtt-host       control plane + presence
+//    alerts        --alert-mqtt-host       alert delivery / rule registration
+//    transcripts   --transcript-mqtt-host  transcript forwarding
+//    presence      --presence-mqtt-host    device online / offline events
+//    events        --event-mqtt-host       sensor events (falls, distress, ...)
+//    stats         --stats-mqtt-host       pipeline stats (`--stats-sink mqtt`)
+//
+//  Every connection is started with `spawn_client`, whose poll loop
+//  reports ConnAcks and connection errors to the link's `MqttLink` and
+//  retries a failed poll after a second; rumqttc reconnects on the next
+//  poll.  Links that subscribe do so on every ConnAck (from their event
+//  callback) because the sessions are not persistent.  `/readyz` lists every enabled link and is only ready
+//  while all of them are connected.
+
+/// Registry of the bridge's MQTT connections, shared with `/readyz`.
+/// Clone-friendly (Arc inside).
+#[derive(Clone, Default)]
+pub struct MqttHealth {
+    links: Arc<Mutex<BTreeMap<&'static str, MqttLink>>>,
+}
+
+/// State of one broker connection.  Clone-friendly (Arc inside).
+#[derive(Clone, Default)]
+pub struct MqttLink {
+    inner: Arc<LinkInner>,
+}
+
+#[derive(Default)]
+struct LinkInner {
+    connected: AtomicBool,
+    connects: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+/// `/readyz` view of one [`MqttLink`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LinkSnapshot {
+    pub connected: bool,
+    /// ConnAcks after the first one
+    pub reconnects: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// `/readyz` view of [`MqttHealth`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MqttSnapshot {
+    pub links: BTreeMap<&'static str, LinkSnapshot>,
+    /// Every enabled link is connected (true when none is enabled)
+    pub ready: bool,
+}
+
+impl MqttHealth {
+    /// Register the connection `name` (one of the links above).
+    pub fn link(&self, name: &'static str) -> MqttLink {
+        self.links.lock().unwrap().entry(name).or_default().clone()
+    }
+
+    pub fn snapshot(&self) -> MqttSnapshot {
+        let links: BTreeMap<_, _> = self.links
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, link)| (*name, link.snapshot()))
+            .collect();
+        let ready = links.values().all(|link| link.connected);
+        MqttSnapshot { links, ready }
+    }
+}
+
+impl MqttLink {
+    /// The broker acknowledged a (re)connect.
+    pub fn connected(&self) {
+        self.inner.connected.store(true, Ordering::Relaxed);
+        self.inner.connects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The event loop failed; rumqttc reconnects on the next poll.
+    pub fn disconnected(&self, error: &impl std::fmt::Display) {
+        self.inner.connected.store(false, Ordering::Relaxed);
+        *self.inner.last_error.lock().unwrap() = Some(error.to_string());
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.inner.connected.load(Ordering::Relaxed)
+    }
+
+    fn snapshot(&self) -> LinkSnapshot {
+        LinkSnapshot {
+            connected: self.is_connected(),
+            reconnects: self.inner.connects.load(Ordering::Relaxed).saturating_sub(1),
+            last_error: self.inner.last_error.lock().unwrap().clone(),
+        }
+    }
+}
+
+/// Options for the bridge's connection `name`: client id
+/// `vad-bridge-<name>[-<tenant>]` (ids must be unique per broker
+/// connection, so one per tenant) and a 30 s keep-alive.
+pub fn client_options(name: &str, tenant: &str, host: &str, port: u16) -> MqttOptions {
+    let client_id = if tenant.is_empty() { format!("vad-bridge-{name}") } else { format!("vad-bridge-{name}-{tenant}") };
+    let mut opts = MqttOptions::new(client_id, host, port);
+    opts.set_keep_alive(Duration::from_secs(30));
+    opts
+}
+
+/// Connect `opts` as link `name` of `health` and spawn its poll loop.
+/// Every event is passed to `on_event` with the client (to subscribe on
+/// ConnAck, or reply to a publish); `capacity` bounds the client's
+/// request queue.
+pub fn spawn_client(
+    opts: MqttOptions,
+    capacity: usize,
+    health: &MqttHealth,
+    name: &'static str,
+    mut on_event: impl FnMut(&AsyncClient, Event) + Send + 'static
+) -> (AsyncClient, JoinHandle<()>) {
+    let (client, mut eventloop) = AsyncClient::new(opts, capacity);
+    let link = health.link(name);
+    let poller = client.clone();
+    let task = tokio::spawn(async move {
+        loop {
+            match eventloop.poll().await {
+                Ok(event) => {
+                    if matches!(event, Event::Incoming(Packet::ConnAck(_))) {
+                        link.connected();
+                    }
+                    on_event(&poller, event);
+                }
+                Err(e) => {
+                    link.disconnected(&e);
+                    warn!(link = name, error = %e, "MQTT connection error — retrying in 1 s");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    });
+    (client, task)
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ready_tracks_every_link_across_reconnects() {
+        let health = MqttHealth::default();
+        assert!(health.snapshot().ready, "no MQTT configured");
+
+        let admin = health.link("admin");
+        let alerts = health.link("alerts");
+        admin.connected();
+        assert!(!health.snapshot().ready, "alerts never connected");
+        alerts.connected();
+        assert!(health.snapshot().ready);
+
+        admin.disconnected(&"connection refused");
+        let snapshot = health.snapshot();
+        assert!(!snapshot.ready);
+        assert!(snapshot.links["admin"].last_error.is_some());
+
+        admin.connected();
+        let snapshot = health.snapshot();
+        assert!(snapshot.ready);
+        assert_eq!((snapshot.links["admin"].reconnects, snapshot.links["alerts"].reconnects), (1, 0));
+        // Registering again hands out the same link
+        assert!(health.link("admin").is_connected());
+    }
+
+    #[test]
+    fn test_client_ids_are_per_tenant() {
+        assert_eq!(client_options("stats", "", "localhost", 1883).client_id(), "vad-bridge-stats");
+        assert_eq!(client_options("stats", "acme", "localhost", 1883).client_id(), "vad-bridge-stats-acme");
+    }
+
+    #[tokio::test]
+    async fn test_spawned_client_reports_connection_errors() {
+        // Nothing listens on a port we just released
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let health = MqttHealth::default();
+        let (_client, task) = spawn_client(client_options("events", "", "127.0.0.1", port), 8, &health, "events", |_, _| {});
+        for _ in 0..100 {
+            if health.snapshot().links["events"].last_error.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        task.abort();
+        let snapshot = health.snapshot();
+        assert!(!snapshot.ready);
+        assert!(snapshot.links["events"].last_error.is_some(), "{snapshot:?}");
+    }
+}
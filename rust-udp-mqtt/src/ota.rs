@@ -0,0 +1,504 @@
+use crate::clock::{ self, SharedClock };
+use crate::esp_audio_protocol::{
+    build_ota_abort,
+    build_ota_chunk,
+    build_ota_offer,
+    crc32,
+    OtaMessage,
+    OTA_ABORT_BAD_OFFSET,
+    OTA_ABORT_CANCELLED,
+    OTA_ABORT_UNKNOWN,
+};
+use dashmap::DashMap;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+use tokio::sync::{ mpsc, oneshot };
+use tracing::{ debug, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  OTA firmware delivery
+// ─────────────────────────────────────────────────────────────────────
+//
+//  The robots' only server contact is the bridge, so it also hosts their
+//  firmware.  Images are uploaded with `PUT /firmware/{name}` (stored as
+//  `<--firmware-dir>/<name>.bin`, reloaded at startup) and pushed with
+//  `POST /devices/{device}/ota` over the ESP audio port (`PKT_OTA`, see
+//  `esp_audio_protocol.rs`):
+//
+//    1. the bridge sends OTA_OFFER (size, CRC-32, chunk size, name),
+//       repeated every OFFER_RETRY until the ESP answers
+//    2. the ESP pulls OTA_REQUEST(offset) → OTA_CHUNK(offset, crc, data),
+//       re-requesting whatever got lost; a transfer interrupted by a
+//       reboot or Wi-Fi drop resumes when the image is offered again and
+//       the ESP asks for the offset it already has
+//    3. the ESP verifies the whole image's CRC-32 and answers OTA_DONE
+//
+//  One transfer per device; a new offer replaces the previous one.  A
+//  transfer the ESP stops pulling for STALL_TIMEOUT is marked failed.
+
+/// Largest accepted image (ESP32 OTA partitions are at most a few MB).
+pub const MAX_IMAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Image bytes per OTA_CHUNK (fits ESP_MAX_PAYLOAD with the header).
+pub const CHUNK_SIZE: usize = 1024;
+
+/// Offer repeat period while the ESP has not answered.
+const OFFER_RETRY: Duration = Duration::from_secs(2);
+
+/// Offers sent before giving up on a silent device.
+const OFFER_ATTEMPTS: u32 = 5;
+
+/// A transfer the ESP stopped pulling for this long has failed.
+const STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Why an OTA request was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OtaError {
+    InvalidName(String),
+    InvalidImage(String),
+    UnknownImage(String),
+    UnknownDevice(String),
+    /// The UDP side is not running (no receiver for requests)
+    Unavailable,
+    Io(String),
+}
+
+impl std::fmt::Display for OtaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OtaError::InvalidName(n) => write!(f, "invalid image name: {n:?} (letters, digits, '.', '-', '_')"),
+            OtaError::InvalidImage(e) => write!(f, "invalid image: {e}"),
+            OtaError::UnknownImage(n) => write!(f, "unknown image: {n}"),
+            OtaError::UnknownDevice(d) => write!(f, "unknown device: {d}"),
+            OtaError::Unavailable => write!(f, "audio transport not running"),
+            OtaError::Io(e) => write!(f, "firmware store: {e}"),
+        }
+    }
+}
+
+/// A hosted firmware image.
+#[derive(Debug, Clone, Serialize)]
+pub struct FirmwareInfo {
+    pub name: String,
+    pub size: usize,
+    pub crc32: u32,
+}
+
+#[derive(Clone)]
+struct Image {
+    info: FirmwareInfo,
+    data: Arc<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferState {
+    Offered,
+    Transferring,
+    Complete,
+    Failed,
+}
+
+/// Progress of one device's transfer (`GET /ota`).
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferStatus {
+    pub device: String,
+    pub addr: String,
+    pub image: String,
+    pub size: usize,
+    pub crc32: u32,
+    /// Bytes the ESP has confirmed (offset of its latest request)
+    pub offset: usize,
+    pub chunks_sent: u64,
+    /// Offset of the first request, when the ESP resumed a transfer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resumed_from: Option<usize>,
+    pub state: TransferState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub started_ms: u64,
+    pub updated_ms: u64,
+}
+
+struct Transfer {
+    status: TransferStatus,
+    data: Arc<Vec<u8>>,
+    offers_sent: u32,
+    last_activity: Instant,
+    /// Outgoing sequence number for this device's OTA packets
+    seq: u16,
+}
+
+impl Transfer {
+    fn next_seq(&mut self) -> u16 {
+        self.seq = self.seq.wrapping_add(1);
+        self.seq
+    }
+
+    fn offer(&mut self) -> Vec<u8> {
+        let seq = self.next_seq();
+        let s = &self.status;
+        build_ota_offer(seq, s.size as u32, s.crc32, CHUNK_SIZE as u16, &s.image)
+    }
+
+    fn fail(&mut self, error: String, now_ms: u64) {
+        warn!(device = %self.status.device, image = %self.status.image, error = %error, "📦 OTA transfer failed");
+        self.status.state = TransferState::Failed;
+        self.status.error = Some(error);
+        self.status.updated_ms = now_ms;
+    }
+}
+
+/// A request from the API to update `device` (MAC or `ip:port`).
+pub struct OtaRequest {
+    pub device: String,
+    pub image: String,
+    pub reply: oneshot::Sender<Result<TransferStatus, OtaError>>,
+}
+
+/// Firmware store + per-device transfers.  Clone-friendly (Arc inside).
+#[derive(Clone)]
+pub struct Ota {
+    dir: Arc<PathBuf>,
+    images: Arc<DashMap<String, Image>>,
+    transfers: Arc<DashMap<SocketAddr, Transfer>>,
+    requests: mpsc::Sender<OtaRequest>,
+    clock: SharedClock,
+}
+
+impl Ota {
+    /// Load every `*.bin` in `dir` (a missing directory is created on
+    /// the first upload).
+    pub fn load(dir: &str, clock: SharedClock) -> (Self, mpsc::Receiver<OtaRequest>) {
+        let images = DashMap::new();
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("bin") {
+                    continue;
+                }
+                let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                    continue;
+                };
+                match std::fs::read(&path) {
+                    Ok(data) if valid_name(name) && !data.is_empty() && data.len() <= MAX_IMAGE_BYTES => {
+                        let image = Image::new(name, data);
+                        debug!(image = name, size = image.info.size, "firmware image loaded");
+                        images.insert(name.to_string(), image);
+                    }
+                    Ok(_) => warn!(path = %path.display(), "skipping unusable firmware image"),
+                    Err(e) => warn!(path = %path.display(), error = %e, "skipping unreadable firmware image"),
+                }
+            }
+        }
+        if !images.is_empty() {
+            info!(images = images.len(), dir = %dir, "📦 firmware images loaded");
+        }
+
+        let (requests, rx) = mpsc::channel(8);
+        let ota = Self {
+            dir: Arc::new(PathBuf::from(dir)),
+            images: Arc::new(images),
+            transfers: Arc::default(),
+            requests,
+            clock,
+        };
+        (ota, rx)
+    }
+
+    /// Hosted images, sorted by name.
+    pub fn list(&self) -> Vec<FirmwareInfo> {
+        let mut list: Vec<_> = self.images
+            .iter()
+            .map(|i| i.info.clone())
+            .collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list
+    }
+
+    /// Store (or replace) image `name`.
+    pub async fn upload(&self, name: &str, data: Vec<u8>) -> Result<FirmwareInfo, OtaError> {
+        if !valid_name(name) {
+            return Err(OtaError::InvalidName(name.to_string()));
+        }
+        if data.is_empty() {
+            return Err(OtaError::InvalidImage("empty body".into()));
+        }
+        if data.len() > MAX_IMAGE_BYTES {
+            return Err(OtaError::InvalidImage(format!("{} bytes (max {MAX_IMAGE_BYTES})", data.len())));
+        }
+        tokio::fs::create_dir_all(self.dir.as_ref()).await.map_err(|e| OtaError::Io(e.to_string()))?;
+        let path = self.dir.join(format!("{name}.bin"));
+        tokio::fs::write(&path, &data).await.map_err(|e| OtaError::Io(e.to_string()))?;
+        let image = Image::new(name, data);
+        let info = image.info.clone();
+        self.images.insert(name.to_string(), image);
+        info!(image = %name, size = info.size, crc32 = format!("{:08x}", info.crc32), "📦 firmware image stored");
+        Ok(info)
+    }
+
+    /// Delete image `name` (transfers already running keep their copy).
+    pub async fn remove(&self, name: &str) -> Result<bool, OtaError> {
+        if self.images.remove(name).is_none() {
+            return Ok(false);
+        }
+        let path = self.dir.join(format!("{name}.bin"));
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+            Err(e) => Err(OtaError::Io(e.to_string())),
+        }
+    }
+
+    /// Ask the UDP side to offer `image` to `device` (API entry point).
+    pub async fn request(&self, device: &str, image: &str) -> Result<TransferStatus, OtaError> {
+        if !self.images.contains_key(image) {
+            return Err(OtaError::UnknownImage(image.to_string()));
+        }
+        let (reply, rx) = oneshot::channel();
+        let req = OtaRequest { device: device.to_string(), image: image.to_string(), reply };
+        self.requests.send(req).await.map_err(|_| OtaError::Unavailable)?;
+        rx.await.map_err(|_| OtaError::Unavailable)?
+    }
+
+    /// Every device's latest transfer, by device name.
+    pub fn transfers(&self) -> Vec<TransferStatus> {
+        let mut list: Vec<_> = self.transfers
+            .iter()
+            .map(|t| t.status.clone())
+            .collect();
+        list.sort_by(|a, b| a.device.cmp(&b.device));
+        list
+    }
+
+    /// Start offering `image` to `addr`; returns the status and the
+    /// OTA_OFFER packet to send.
+    pub fn begin(&self, addr: SocketAddr, device: &str, image: &str) -> Result<(TransferStatus, Vec<u8>), OtaError> {
+        let image = self.images
+            .get(image)
+            .map(|i| i.clone())
+            .ok_or_else(|| OtaError::UnknownImage(image.to_string()))?;
+        let now_ms = clock::unix_millis(self.clock.as_ref());
+        let mut transfer = Transfer {
+            status: TransferStatus {
+                device: device.to_string(),
+                addr: addr.to_string(),
+                image: image.info.name.clone(),
+                size: image.info.size,
+                crc32: image.info.crc32,
+                offset: 0,
+                chunks_sent: 0,
+                resumed_from: None,
+                state: TransferState::Offered,
+                error: None,
+                started_ms: now_ms,
+                updated_ms: now_ms,
+            },
+            data: image.data,
+            offers_sent: 1,
+            last_activity: self.clock.now(),
+            seq: 0,
+        };
+        let offer = transfer.offer();
+        let status = transfer.status.clone();
+        info!(device = %device, addr = %addr, image = %status.image, size = status.size, "📦 OTA offered");
+        self.transfers.insert(addr, transfer);
+        Ok((status, offer))
+    }
+
+    /// Handle an OTA packet from `addr`; returns the reply to send.
+    pub fn handle(&self, addr: SocketAddr, seq: u16, payload: &[u8]) -> Option<Vec<u8>> {
+        let Some(message) = OtaMessage::parse(payload) else {
+            debug!(src = %addr, "malformed OTA packet");
+            return None;
+        };
+        let now_ms = clock::unix_millis(self.clock.as_ref());
+        let Some(mut transfer) = self.transfers.get_mut(&addr) else {
+            return match message {
+                OtaMessage::Request { .. } => Some(build_ota_abort(seq, OTA_ABORT_UNKNOWN)),
+                _ => None,
+            };
+        };
+        if matches!(transfer.status.state, TransferState::Complete | TransferState::Failed) {
+            return match message {
+                OtaMessage::Request { .. } => Some(build_ota_abort(seq, OTA_ABORT_CANCELLED)),
+                _ => None,
+            };
+        }
+        transfer.last_activity = self.clock.now();
+        transfer.status.updated_ms = now_ms;
+        match message {
+            OtaMessage::Request { offset } => {
+                let offset = offset as usize;
+                if offset >= transfer.data.len() {
+                    transfer.fail(format!("device requested offset {offset} past the image end"), now_ms);
+                    return Some(build_ota_abort(seq, OTA_ABORT_BAD_OFFSET));
+                }
+                if transfer.status.state == TransferState::Offered {
+                    transfer.status.state = TransferState::Transferring;
+                    if offset > 0 {
+                        transfer.status.resumed_from = Some(offset);
+                        info!(device = %transfer.status.device, offset, "📦 OTA resumed");
+                    }
+                }
+                transfer.status.offset = offset;
+                transfer.status.chunks_sent += 1;
+                let end = (offset + CHUNK_SIZE).min(transfer.data.len());
+                let data = transfer.data.clone();
+                let seq = transfer.next_seq();
+                Some(build_ota_chunk(seq, offset as u32, &data[offset..end]))
+            }
+            OtaMessage::Done { status: 0 } => {
+                transfer.status.offset = transfer.status.size;
+                transfer.status.state = TransferState::Complete;
+                info!(
+                    device = %transfer.status.device,
+                    image = %transfer.status.image,
+                    chunks = transfer.status.chunks_sent,
+                    "📦 OTA complete — device verified the image"
+                );
+                None
+            }
+            OtaMessage::Done { status } => {
+                transfer.fail(format!("device reported error {status}"), now_ms);
+                None
+            }
+            OtaMessage::Abort { reason } => {
+                transfer.fail(format!("device aborted (reason {reason})"), now_ms);
+                None
+            }
+        }
+    }
+
+    /// Offers to repeat now (silent devices), failing transfers that
+    /// ran out of offers or stalled.  Call about once a second.
+    pub fn due_offers(&self) -> Vec<(SocketAddr, Vec<u8>)> {
+        let now = self.clock.now();
+        let now_ms = clock::unix_millis(self.clock.as_ref());
+        let mut due = Vec::new();
+        for mut entry in self.transfers.iter_mut() {
+            let addr = *entry.key();
+            let idle = now.saturating_duration_since(entry.last_activity);
+            match entry.status.state {
+                TransferState::Offered if idle >= OFFER_RETRY => {
+                    if entry.offers_sent >= OFFER_ATTEMPTS {
+                        entry.fail(format!("no answer to {OFFER_ATTEMPTS} offers"), now_ms);
+                        continue;
+                    }
+                    entry.offers_sent += 1;
+                    entry.last_activity = now;
+                    due.push((addr, entry.offer()));
+                }
+                TransferState::Transferring if idle >= STALL_TIMEOUT => {
+                    let offset = entry.status.offset;
+                    entry.fail(format!("stalled at offset {offset}"), now_ms);
+                }
+                _ => {}
+            }
+        }
+        due
+    }
+}
+
+impl Image {
+    fn new(name: &str, data: Vec<u8>) -> Self {
+        let info = FirmwareInfo { name: name.to_string(), size: data.len(), crc32: crc32(&data) };
+        Self { info, data: Arc::new(data) }
+    }
+}
+
+/// Image names double as file names: keep them boring.
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() &&
+        name.len() <= 64 &&
+        !name.starts_with('.') &&
+        name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimClock;
+    use crate::esp_audio_protocol::{ EspPacket, OTA_CHUNK, OTA_CHUNK_HEADER, OTA_DONE, OTA_REQUEST, PKT_OTA };
+
+    fn request(offset: u32) -> Vec<u8> {
+        let mut p = vec![OTA_REQUEST];
+        p.extend_from_slice(&offset.to_le_bytes());
+        p
+    }
+
+    /// (offset, data) of an OTA_CHUNK packet, checking its CRC.
+    fn chunk(packet: &[u8]) -> (u32, Vec<u8>) {
+        let pkt = EspPacket::parse(packet).unwrap();
+        assert_eq!((pkt.pkt_type, pkt.payload[0]), (PKT_OTA, OTA_CHUNK));
+        let p = &pkt.payload;
+        let offset = u32::from_le_bytes(p[1..5].try_into().unwrap());
+        let crc = u32::from_le_bytes(p[5..9].try_into().unwrap());
+        let data = p[OTA_CHUNK_HEADER..].to_vec();
+        assert_eq!(crc32(&data), crc);
+        (offset, data)
+    }
+
+    #[test]
+    fn test_crc32_and_names() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert!(valid_name("robot-v1.4.2_rc1"));
+        assert!(!valid_name("../etc/passwd"));
+        assert!(!valid_name(".hidden"));
+        assert!(!valid_name(""));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_with_loss_and_resume() {
+        let dir = std::env::temp_dir().join(format!("vad-ota-{}", std::process::id()));
+        let sim = SimClock::default();
+        let (ota, _rx) = Ota::load(dir.to_str().unwrap(), Arc::new(sim.clone()));
+        let image: Vec<u8> = (0..2500u32).map(|i| (i % 251) as u8).collect();
+        let info = ota.upload("fw-1.2", image.clone()).await.unwrap();
+        assert_eq!(info.size, 2500);
+        assert!(ota.upload("bad/name", vec![1]).await.is_err());
+
+        let addr: SocketAddr = "10.0.0.7:4000".parse().unwrap();
+        let (status, offer) = ota.begin(addr, "aa:bb", "fw-1.2").unwrap();
+        assert_eq!(status.state, TransferState::Offered);
+        assert_eq!(EspPacket::parse(&offer).unwrap().payload[0], crate::esp_audio_protocol::OTA_OFFER);
+
+        // Silent device: the offer is repeated
+        sim.advance(OFFER_RETRY);
+        assert_eq!(ota.due_offers().len(), 1);
+
+        // First chunk, then the ESP "reboots" and resumes at 1024
+        let (offset, data) = chunk(&ota.handle(addr, 1, &request(0)).unwrap());
+        assert_eq!((offset, data.len()), (0, CHUNK_SIZE));
+        let (_, again) = ota.begin(addr, "aa:bb", "fw-1.2").unwrap();
+        assert!(!again.is_empty());
+        let mut received = data;
+        while received.len() < image.len() {
+            let (offset, data) = chunk(&ota.handle(addr, 2, &request(received.len() as u32)).unwrap());
+            assert_eq!(offset as usize, received.len());
+            received.extend(data);
+        }
+        assert_eq!(received, image);
+        assert_eq!(ota.transfers()[0].resumed_from, Some(1024));
+
+        // Verified by the device; later requests are refused
+        assert!(ota.handle(addr, 3, &[OTA_DONE, 0]).is_none());
+        assert_eq!(ota.transfers()[0].state, TransferState::Complete);
+        assert!(ota.handle(addr, 4, &request(0)).is_some());
+
+        // Unknown device gets an abort
+        let other: SocketAddr = "10.0.0.8:4000".parse().unwrap();
+        assert_eq!(ota.handle(other, 9, &request(0)).unwrap(), build_ota_abort(9, OTA_ABORT_UNKNOWN));
+
+        assert!(ota.remove("fw-1.2").await.unwrap());
+        assert!(ota.list().is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
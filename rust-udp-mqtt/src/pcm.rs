@@ -0,0 +1,367 @@
+use std::borrow::Cow;
+
+// ─────────────────────────────────────────────────────────────────────
+//  PCM sample formats
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Everything downstream of ingress (VAD energy, WAV recording, the
+//  16↔24 kHz resampler, OpenAI upload) works on 16-bit LE PCM.  Devices
+//  that send other formats are normalised to s16 once, on arrival:
+//
+//    Code  Format  Bytes  Conversion to s16
+//    0     s16     2      as-is (zero-copy)
+//    1     s8      1      s << 8            (signed 8-bit)
+//    2     s24     3      top 16 bits       (packed LE, 3 bytes)
+//    3     s32     4      top 16 bits
+//    4     f32     4      round(x · 32767)  (clamped to ±1.0, NaN → 0)
+//
+//  Sensor-port packets carry the code in header byte 13 (previously
+//  reserved, so existing senders are implicitly s16).  The raw ESP audio
+//  port has no header and uses `--esp-sample-format`.  A trailing partial
+//  sample is dropped.
+
+/// Wire sample format of a PCM payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SampleFormat {
+    /// 16-bit signed LE (default)
+    #[default]
+    S16,
+    /// 8-bit signed
+    S8,
+    /// 24-bit signed LE, packed (3 bytes per sample)
+    S24,
+    /// 32-bit signed LE
+    S32,
+    /// 32-bit IEEE float LE, nominal range ±1.0
+    F32,
+}
+
+impl SampleFormat {
+    /// Decode the header code; `None` for unknown formats.
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(SampleFormat::S16),
+            1 => Some(SampleFormat::S8),
+            2 => Some(SampleFormat::S24),
+            3 => Some(SampleFormat::S32),
+            4 => Some(SampleFormat::F32),
+            _ => None,
+        }
+    }
+
+    /// Header code for this format.
+    pub fn code(self) -> u8 {
+        match self {
+            SampleFormat::S16 => 0,
+            SampleFormat::S8 => 1,
+            SampleFormat::S24 => 2,
+            SampleFormat::S32 => 3,
+            SampleFormat::F32 => 4,
+        }
+    }
+
+    /// Bytes per (mono) sample.
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            SampleFormat::S8 => 1,
+            SampleFormat::S16 => 2,
+            SampleFormat::S24 => 3,
+            SampleFormat::S32 | SampleFormat::F32 => 4,
+        }
+    }
+
+    /// Convert a payload in this format to 16-bit LE PCM.
+    pub fn to_s16le(self, data: &[u8]) -> Cow<'_, [u8]> {
+        if self == SampleFormat::S16 {
+            return Cow::Borrowed(data);
+        }
+        let n = data.len() / self.bytes_per_sample();
+        let mut out = Vec::with_capacity(n * 2);
+        for chunk in data.chunks_exact(self.bytes_per_sample()) {
+            let s: i16 = match self {
+                SampleFormat::S16 => i16::from_le_bytes([chunk[0], chunk[1]]),
+                SampleFormat::S8 => ((chunk[0] as i8) as i16) << 8,
+                SampleFormat::S24 => i16::from_le_bytes([chunk[1], chunk[2]]),
+                SampleFormat::S32 => i16::from_le_bytes([chunk[2], chunk[3]]),
+                SampleFormat::F32 => {
+                    let x = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+                    if x.is_nan() { 0 } else { (x.clamp(-1.0, 1.0) * 32767.0).round() as i16 }
+                }
+            };
+            out.extend_from_slice(&s.to_le_bytes());
+        }
+        Cow::Owned(out)
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Multi-channel input
+// ─────────────────────────────────────────────────────────────────────
+//
+//  A legacy ESP may declare a channel count in its SESSION_START; its
+//  AUDIO_UP payloads are then interleaved frames (L R L R … for stereo).
+//  Session WAVs keep every channel, while VAD and OpenAI get mono:
+//
+//    mix       average of all channels (a delay-free sum beam)
+//    loudest   per packet, the channel with the most energy
+//    channel   one fixed channel (`--mic-channel`)
+//
+//  A trailing partial frame is dropped.
+
+/// Most channels a session may declare.
+pub const MAX_CHANNELS: u16 = 8;
+
+/// How interleaved channels are reduced to mono.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum MixMode {
+    /// Average of all channels
+    #[default]
+    Mix,
+    /// Per packet, the channel with the most energy
+    Loudest,
+    /// One fixed channel
+    Channel,
+}
+
+/// Mono reduction of interleaved s16 audio (`--mic-mix`, `--mic-channel`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Downmix {
+    pub mode: MixMode,
+    /// Channel kept by `MixMode::Channel` (clamped to the last one)
+    pub channel: usize,
+}
+
+impl Downmix {
+    /// Reduce `pcm` (s16 LE, `channels` interleaved) to mono.
+    pub fn apply<'a>(&self, pcm: &'a [u8], channels: usize) -> Cow<'a, [u8]> {
+        if channels <= 1 {
+            return Cow::Borrowed(pcm);
+        }
+        let sample = |frame: &[u8], c: usize| i16::from_le_bytes([frame[c * 2], frame[c * 2 + 1]]);
+        let frames = pcm.chunks_exact(channels * 2);
+        let pick = match self.mode {
+            MixMode::Mix => None,
+            MixMode::Channel => Some(self.channel.min(channels - 1)),
+            MixMode::Loudest =>
+                (0..channels).max_by_key(|&c| {
+                    frames
+                        .clone()
+                        .map(|f| (sample(f, c) as i64).pow(2))
+                        .sum::<i64>()
+                }),
+        };
+        let mut out = Vec::with_capacity(pcm.len() / channels);
+        for frame in frames {
+            let s = match pick {
+                Some(c) => sample(frame, c),
+                None => ((0..channels).map(|c| sample(frame, c) as i32).sum::<i32>() / (channels as i32)) as i16,
+            };
+            out.extend_from_slice(&s.to_le_bytes());
+        }
+        Cow::Owned(out)
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  G.711 (telephony)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  PBXs and SIP phones all speak G.711 at 8 kHz: one byte per sample,
+//  a 13/14-bit linear range companded logarithmically.  μ-law (PCMU,
+//  RTP payload type 0) is used in North America and Japan, A-law (PCMA,
+//  type 8) elsewhere.  Conversion follows the ITU reference (Sun g711.c).
+
+/// G.711 companding law.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum G711 {
+    Ulaw,
+    Alaw,
+}
+
+impl G711 {
+    /// The law of a static RTP payload type.
+    pub fn from_payload_type(pt: u8) -> Option<Self> {
+        match pt {
+            0 => Some(G711::Ulaw),
+            8 => Some(G711::Alaw),
+            _ => None,
+        }
+    }
+
+    pub fn payload_type(self) -> u8 {
+        match self {
+            G711::Ulaw => 0,
+            G711::Alaw => 8,
+        }
+    }
+
+    /// SDP encoding name.
+    pub fn name(self) -> &'static str {
+        match self {
+            G711::Ulaw => "PCMU",
+            G711::Alaw => "PCMA",
+        }
+    }
+
+    /// G.711 bytes → s16le.
+    pub fn decode(self, data: &[u8]) -> Vec<u8> {
+        data.iter()
+            .flat_map(|&b| {
+                match self {
+                    G711::Ulaw => ulaw_to_linear(b),
+                    G711::Alaw => alaw_to_linear(b),
+                }.to_le_bytes()
+            })
+            .collect()
+    }
+
+    /// s16le → G.711 bytes (a trailing partial sample is dropped).
+    pub fn encode(self, pcm: &[u8]) -> Vec<u8> {
+        pcm.chunks_exact(2)
+            .map(|b| {
+                let s = i16::from_le_bytes([b[0], b[1]]);
+                match self {
+                    G711::Ulaw => linear_to_ulaw(s),
+                    G711::Alaw => linear_to_alaw(s),
+                }
+            })
+            .collect()
+    }
+}
+
+const ULAW_BIAS: i32 = 0x84;
+const ULAW_CLIP: i32 = 32635;
+
+fn linear_to_ulaw(sample: i16) -> u8 {
+    let mut s = sample as i32;
+    let sign = if s < 0 { 0x80 } else { 0 };
+    if s < 0 {
+        s = -s;
+    }
+    s = s.min(ULAW_CLIP) + ULAW_BIAS;
+    let exponent = 31 - ((s >> 7) as u32).leading_zeros() as i32;
+    let mantissa = (s >> (exponent + 3)) & 0x0f;
+    !((sign | (exponent << 4) | mantissa) as u8)
+}
+
+fn ulaw_to_linear(byte: u8) -> i16 {
+    let u = !byte as i32;
+    let exponent = (u >> 4) & 0x07;
+    let magnitude = ((((u & 0x0f) << 3) + ULAW_BIAS) << exponent) - ULAW_BIAS;
+    (if u & 0x80 != 0 { -magnitude } else { magnitude }) as i16
+}
+
+fn linear_to_alaw(sample: i16) -> u8 {
+    const SEGMENT_END: [i32; 8] = [0x1f, 0x3f, 0x7f, 0xff, 0x1ff, 0x3ff, 0x7ff, 0xfff];
+    let mut s = (sample as i32) >> 3;
+    let mask = if s >= 0 {
+        0xd5
+    } else {
+        s = -s - 1;
+        0x55
+    };
+    let Some(segment) = SEGMENT_END.iter().position(|&end| s <= end) else {
+        return 0x7f ^ mask;
+    };
+    let shift = if segment < 2 { 1 } else { segment };
+    (((segment << 4) as i32 | ((s >> shift) & 0x0f)) as u8) ^ mask
+}
+
+fn alaw_to_linear(byte: u8) -> i16 {
+    let a = (byte ^ 0x55) as i32;
+    let segment = (a & 0x70) >> 4;
+    let mut t = (a & 0x0f) << 4;
+    match segment {
+        0 => t += 8,
+        1 => t += 0x108,
+        _ => t = (t + 0x108) << (segment - 1),
+    }
+    (if a & 0x80 != 0 { t } else { -t }) as i16
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s16(bytes: &[u8]) -> Vec<i16> {
+        bytes
+            .chunks_exact(2)
+            .map(|c| i16::from_le_bytes([c[0], c[1]]))
+            .collect()
+    }
+
+    #[test]
+    fn test_s16_is_zero_copy() {
+        let data = [1u8, 2, 3, 4];
+        assert!(matches!(SampleFormat::S16.to_s16le(&data), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_integer_formats_keep_top_bits() {
+        assert_eq!(s16(&SampleFormat::S8.to_s16le(&[0x40, 0xc0])), vec![0x4000, -0x4000]);
+
+        let s24: Vec<u8> = [0x123456i32, -0x123456]
+            .iter()
+            .flat_map(|v| v.to_le_bytes()[..3].to_vec())
+            .collect();
+        assert_eq!(s16(&SampleFormat::S24.to_s16le(&s24)), vec![0x1234, -0x1235]);
+
+        let s32: Vec<u8> = [0x12345678i32, i32::MIN].iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(s16(&SampleFormat::S32.to_s16le(&s32)), vec![0x1234, i16::MIN]);
+    }
+
+    #[test]
+    fn test_f32_scales_and_clamps() {
+        let f: Vec<u8> = [0.5f32, -1.0, 2.0, f32::NAN].iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(s16(&SampleFormat::F32.to_s16le(&f)), vec![16384, -32767, 32767, 0]);
+    }
+
+    #[test]
+    fn test_partial_trailing_sample_dropped() {
+        assert_eq!(SampleFormat::F32.to_s16le(&[0u8; 6]).len(), 2);
+    }
+
+    #[test]
+    fn test_downmix_modes() {
+        let stereo: Vec<u8> = [100i16, -300, 200, -500, 7, 9]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let mix = |mode, channel| s16(&(Downmix { mode, channel }).apply(&stereo[..11], 2));
+        assert_eq!(mix(MixMode::Mix, 0), vec![-100, -150]);
+        assert_eq!(mix(MixMode::Loudest, 0), vec![-300, -500]);
+        assert_eq!(mix(MixMode::Channel, 0), vec![100, 200]);
+        assert_eq!(mix(MixMode::Channel, 5), vec![-300, -500]);
+        assert!(matches!(Downmix::default().apply(&stereo, 1), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_g711_round_trip_within_quantisation() {
+        assert_eq!(G711::Ulaw.encode(&[0, 0]), [0xff]);
+        assert_eq!(G711::Alaw.encode(&[0, 0]), [0xd5]);
+        let pcm: Vec<u8> = [0i16, 100, -100, 1000, -5000, 20000, i16::MIN, i16::MAX]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        for law in [G711::Ulaw, G711::Alaw] {
+            let back = s16(&law.decode(&law.encode(&pcm)));
+            for (orig, back) in s16(&pcm).into_iter().zip(back) {
+                // Logarithmic steps: error grows with the magnitude
+                let tolerance = (orig as i32).abs() / 16 + 16;
+                assert!((orig as i32 - back as i32).abs() <= tolerance, "{law:?}: {orig} → {back}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_code_round_trip() {
+        for code in 0..5 {
+            assert_eq!(SampleFormat::from_code(code).unwrap().code(), code);
+        }
+        assert_eq!(SampleFormat::from_code(9), None);
+    }
+}
@@ -1,7 +1,7 @@
 use serde::{ Deserialize, Serialize };
 use std::fmt;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{ broadcast, RwLock };
 
 // ─────────────────────────────────────────────────────────────────────
 //  Personality trait enum
@@ -12,7 +12,7 @@ use tokio::sync::RwLock;
 /// Each trait applies an additive delta to the base Valence / Arousal /
 /// Dominance weight vectors (including bias), shaping how the robot
 /// *feels* about the same sensor inputs.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
 #[serde(rename_all = "snake_case")]
 pub enum PersonaTrait {
     /// Calm, compliant — high dominance sensitivity, low arousal reactivity.
@@ -185,6 +185,8 @@ pub fn apply_deltas(base: &[f32; 11], delta: &[f32; 11]) -> [f32; 11] {
 #[derive(Clone)]
 pub struct PersonaState {
     inner: Arc<RwLock<PersonaTrait>>,
+    /// Local changes, for cluster sync
+    changes: broadcast::Sender<PersonaTrait>,
 }
 
 impl PersonaState {
@@ -192,9 +194,21 @@ impl PersonaState {
     pub fn new(initial: PersonaTrait) -> Self {
         Self {
             inner: Arc::new(RwLock::new(initial)),
+            changes: broadcast::channel(16).0,
         }
     }
 
+    /// Every `set` on this instance (not `apply_remote`).
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<PersonaTrait> {
+        self.changes.subscribe()
+    }
+
+    /// Adopt a persona set on another cluster instance without
+    /// announcing it again.
+    pub async fn apply_remote(&self, persona: PersonaTrait) {
+        *self.inner.write().await = persona;
+    }
+
     /// Read the current persona (non-blocking when no writer).
     pub async fn get(&self) -> PersonaTrait {
         *self.inner.read().await
@@ -203,6 +217,8 @@ impl PersonaState {
     /// Atomically replace the active persona.
     pub async fn set(&self, persona: PersonaTrait) {
         *self.inner.write().await = persona;
+        // No subscribers is fine
+        let _ = self.changes.send(persona);
     }
 
     /// Blocking read for sync contexts (VAD hot-path).
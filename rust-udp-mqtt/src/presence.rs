@@ -0,0 +1,335 @@
+use crate::anomaly::AnomalyDetector;
+use crate::clock::{ self, SharedClock };
+use crate::config::Config;
+use crate::mqtt_health::{ self, MqttHealth };
+use dashmap::DashMap;
+use rumqttc::v5::mqttbytes::QoS;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+use tokio::sync::{ broadcast, mpsc };
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Device presence (online / offline)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  An ESP is online while it is heard from — heartbeats, session
+//  control or audio — and goes offline once it has been silent for
+//  `--presence-offline-secs`.  That silence is the debounce: a device
+//  that drops a few heartbeats or reconnects from a new port does not
+//  flap.  Every transition is sent to:
+//
+//    * `GET /events/ws`  next to sensor events (`kind` = `device_online`
+//                        / `device_offline`)
+//    * MQTT              `<--presence-mqtt-topic>/[<tenant>/]<device>`,
+//                        retained, when `--presence-mqtt-host` is set
+//    * HTTP              POST to every `--presence-webhook` URL
+//
+//  Per device the tracker also keeps the time online since it was first
+//  seen; `GET /presence` serves the uptime percentage, and the number of
+//  online devices is the `online=` gauge on the `[STATS]` line.  MQTT /
+//  webhook delivery runs behind a bounded queue like transcripts.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceKind {
+    DeviceOnline,
+    DeviceOffline,
+}
+
+/// One online / offline transition.
+#[derive(Debug, Clone, Serialize)]
+pub struct PresenceEvent {
+    pub kind: PresenceKind,
+    /// MAC or `ip:port`
+    pub device: String,
+    pub addr: SocketAddr,
+    /// Time spent in the previous state (0 when first seen)
+    pub previous_ms: u64,
+    /// Share of the time since first seen the device was online
+    pub uptime_pct: f64,
+    pub timestamp_ms: u64,
+    /// Tenant of the bridge instance (omitted in single-tenant mode)
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tenant: String,
+}
+
+/// `GET /presence` entry.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DevicePresence {
+    pub device: String,
+    pub addr: SocketAddr,
+    pub online: bool,
+    /// Time in the current state
+    pub for_secs: u64,
+    /// Time since the device was first seen
+    pub tracked_secs: u64,
+    pub uptime_pct: f64,
+}
+
+struct Record {
+    addr: SocketAddr,
+    online: bool,
+    /// Start of the current state
+    since: Instant,
+    first_seen: Instant,
+    /// Online time before `since`
+    online_total: Duration,
+}
+
+impl Record {
+    fn uptime_pct(&self, now: Instant) -> f64 {
+        let tracked = now.saturating_duration_since(self.first_seen);
+        if tracked.is_zero() {
+            return 100.0;
+        }
+        let mut online = self.online_total;
+        if self.online {
+            online += now.saturating_duration_since(self.since);
+        }
+        ((online.as_secs_f64() / tracked.as_secs_f64()) * 100.0).min(100.0)
+    }
+}
+
+/// Presence tracker.  Clone-friendly (Arc inside).
+#[derive(Clone)]
+pub struct Presence {
+    offline_after: Duration,
+    records: Arc<DashMap<String, Record>>,
+    ws: broadcast::Sender<PresenceEvent>,
+    /// MQTT / webhook delivery (None = neither configured)
+    sink: Option<mpsc::Sender<PresenceEvent>>,
+    /// Watches each device's silence against its usual cadence
+    anomalies: AnomalyDetector,
+    tenant: String,
+    clock: SharedClock,
+}
+
+impl Presence {
+    pub fn new(offline_after: Duration, tenant: &str, clock: SharedClock) -> Self {
+        Self {
+            offline_after,
+            records: Arc::default(),
+            ws: broadcast::channel(256).0,
+            sink: None,
+            anomalies: AnomalyDetector::disabled(),
+            tenant: tenant.to_string(),
+            clock,
+        }
+    }
+
+    /// Also check every device's silence in `anomalies`.
+    pub fn with_anomalies(mut self, anomalies: AnomalyDetector) -> Self {
+        self.anomalies = anomalies;
+        self
+    }
+
+    /// Tracker for `--presence-offline-secs`, with the delivery task for
+    /// the configured MQTT broker / webhooks.
+    pub fn from_config(config: &Config, health: &MqttHealth, clock: SharedClock) -> anyhow::Result<Self> {
+        let mut presence = Self::new(Duration::from_secs(config.presence_offline_secs), &config.tenant_id, clock);
+        let mqtt = if config.presence_mqtt_host.is_empty() {
+            None
+        } else {
+            let opts = mqtt_health::client_options("presence", &config.tenant_id, &config.presence_mqtt_host, config.presence_mqtt_port);
+            let (client, _) = mqtt_health::spawn_client(opts, 64, health, "presence", |_, _| {});
+            // Retained, so never expired: the broker answers "is it online?"
+            let props = crate::mqtt5::json("presence", &config.tenant_id, 0);
+            Some((client, config.presence_mqtt_topic.clone(), props))
+        };
+        let webhooks = config.presence_webhook.clone();
+        if mqtt.is_none() && webhooks.is_empty() {
+            return Ok(presence);
+        }
+
+        let http = reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?;
+        info!(
+            mqtt = ?mqtt.as_ref().map(|(_, topic, _)| topic),
+            webhooks = webhooks.len(),
+            "📶 presence notifications enabled"
+        );
+        let (tx, mut rx) = mpsc::channel::<PresenceEvent>(256);
+        tokio::spawn(async move {
+            while let Some(ev) = rx.recv().await {
+                let body = match serde_json::to_vec(&ev) {
+                    Ok(b) => b,
+                    Err(_) => continue,
+                };
+                if let Some((ref client, ref prefix, ref props)) = mqtt {
+                    let topic = crate::transcripts::topic_for(prefix, &ev.tenant, &ev.device);
+                    if let Err(e) = client.publish_with_properties(topic, QoS::AtLeastOnce, true, body.clone(), props.clone()).await {
+                        warn!(error = %e, "failed to queue presence event for MQTT");
+                    }
+                }
+                for url in &webhooks {
+                    match
+                        http
+                            .post(url)
+                            .header("Content-Type", "application/json")
+                            .body(body.clone())
+                            .send().await
+                    {
+                        Ok(resp) if resp.status().is_success() => {}
+                        Ok(resp) => warn!(url = %url, status = %resp.status(), "presence webhook rejected"),
+                        Err(e) => warn!(url = %url, error = %e, "presence webhook failed"),
+                    }
+                }
+            }
+        });
+        presence.sink = Some(tx);
+        Ok(presence)
+    }
+
+    /// Transitions, for `GET /events/ws`.
+    pub fn subscribe(&self) -> broadcast::Receiver<PresenceEvent> {
+        self.ws.subscribe()
+    }
+
+    /// Update every device from when it was last heard (name, address,
+    /// instant) and announce the transitions.  A device listed twice
+    /// (reconnected from a new port) counts by its latest address.
+    /// Returns how many devices are online.
+    pub fn sweep(&self, heard: impl IntoIterator<Item = (String, SocketAddr, Instant)>) -> usize {
+        let now = self.clock.now();
+        let mut latest: HashMap<String, (SocketAddr, Instant)> = HashMap::new();
+        for (device, addr, at) in heard {
+            let entry = latest.entry(device).or_insert((addr, at));
+            if at > entry.1 {
+                *entry = (addr, at);
+            }
+        }
+
+        let mut online = 0;
+        for (device, (addr, last_heard)) in latest {
+            let is_online = now.saturating_duration_since(last_heard) < self.offline_after;
+            if is_online {
+                self.anomalies.observe_heard(&device, last_heard, now);
+            }
+            online += is_online as usize;
+            let event = match self.records.get_mut(&device) {
+                None if !is_online => continue,
+                None => {
+                    let record = Record { addr, online: true, since: last_heard, first_seen: last_heard, online_total: Duration::ZERO };
+                    let event = self.event(PresenceKind::DeviceOnline, &device, &record, Duration::ZERO, now);
+                    self.records.insert(device.clone(), record);
+                    event
+                }
+                Some(mut record) => {
+                    record.addr = addr;
+                    if record.online == is_online {
+                        continue;
+                    }
+                    // Date the change by the last packet, not this sweep:
+                    // that is when it went quiet (or, within a sweep, came back)
+                    let changed_at = last_heard;
+                    let kind = if is_online { PresenceKind::DeviceOnline } else { PresenceKind::DeviceOffline };
+                    let previous = changed_at.saturating_duration_since(record.since);
+                    if record.online {
+                        record.online_total += previous;
+                    }
+                    record.online = is_online;
+                    record.since = changed_at;
+                    self.event(kind, &device, &record, previous, now)
+                }
+            };
+            self.announce(event);
+        }
+        online
+    }
+
+    fn event(&self, kind: PresenceKind, device: &str, record: &Record, previous: Duration, now: Instant) -> PresenceEvent {
+        PresenceEvent {
+            kind,
+            device: device.to_string(),
+            addr: record.addr,
+            previous_ms: previous.as_millis() as u64,
+            uptime_pct: record.uptime_pct(now),
+            timestamp_ms: clock::unix_millis(self.clock.as_ref()),
+            tenant: self.tenant.clone(),
+        }
+    }
+
+    fn announce(&self, event: PresenceEvent) {
+        match event.kind {
+            PresenceKind::DeviceOnline => info!(device = %event.device, addr = %event.addr, "📶 device online"),
+            PresenceKind::DeviceOffline =>
+                info!(
+                    device = %event.device,
+                    addr = %event.addr,
+                    uptime_pct = format!("{:.1}", event.uptime_pct),
+                    "📴 device offline"
+                ),
+        }
+        if let Some(ref sink) = self.sink {
+            if sink.try_send(event.clone()).is_err() {
+                warn!(device = %event.device, "presence queue full — dropping event");
+            }
+        }
+        // No WebSocket clients is fine
+        let _ = self.ws.send(event);
+    }
+
+    /// Every device seen, sorted by name.
+    pub fn list(&self) -> Vec<DevicePresence> {
+        let now = self.clock.now();
+        let mut list: Vec<DevicePresence> = self.records
+            .iter()
+            .map(|r| DevicePresence {
+                device: r.key().clone(),
+                addr: r.addr,
+                online: r.online,
+                for_secs: now.saturating_duration_since(r.since).as_secs(),
+                tracked_secs: now.saturating_duration_since(r.first_seen).as_secs(),
+                uptime_pct: r.uptime_pct(now),
+            })
+            .collect();
+        list.sort_by(|a, b| a.device.cmp(&b.device));
+        list
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{ Clock, SimClock };
+
+    #[test]
+    fn test_transitions_debounced_and_uptime_tracked() {
+        let sim = SimClock::default();
+        let presence = Presence::new(Duration::from_secs(30), "", Arc::new(sim.clone()));
+        let mut events = presence.subscribe();
+        let addr: SocketAddr = "10.0.0.7:5000".parse().unwrap();
+        let heard = |at: Instant| vec![("aa:bb:cc:dd:ee:01".to_string(), addr, at)];
+
+        let start = sim.now();
+        assert_eq!(presence.sweep(heard(start)), 1);
+        assert_eq!(events.try_recv().unwrap().kind, PresenceKind::DeviceOnline);
+
+        // Heard 10 s ago: still online, nothing announced
+        sim.advance(Duration::from_secs(20));
+        assert_eq!(presence.sweep(heard(start + Duration::from_secs(10))), 1);
+        assert!(events.try_recv().is_err());
+
+        // 30 s of silence since the last heartbeat: offline from then
+        sim.advance(Duration::from_secs(20));
+        assert_eq!(presence.sweep(heard(start + Duration::from_secs(10))), 0);
+        let offline = events.try_recv().unwrap();
+        assert_eq!((offline.kind, offline.previous_ms), (PresenceKind::DeviceOffline, 10_000));
+
+        // Back 30 s later; 10 s online out of 40 s
+        sim.advance(Duration::from_secs(30));
+        presence.sweep(heard(sim.now()));
+        let online = events.try_recv().unwrap();
+        assert_eq!((online.kind, online.previous_ms), (PresenceKind::DeviceOnline, 60_000));
+        let list = presence.list();
+        assert!(list[0].online);
+        assert!((list[0].uptime_pct - 14.29).abs() < 0.01, "{list:?}");
+    }
+}
@@ -0,0 +1,312 @@
+use crate::config::Config;
+use crate::devices::DeviceRegistry;
+use crate::experiments::Experiments;
+use crate::persona::PersonaState;
+use dashmap::DashMap;
+use std::sync::{ Arc, RwLock };
+
+// ─────────────────────────────────────────────────────────────────────
+//  OpenAI instruction templates
+// ─────────────────────────────────────────────────────────────────────
+//
+//  The session prompt comes from `--openai-instructions-file` (or the
+//  inline `--openai-instructions` default) and may contain placeholders
+//  that are filled in whenever the prompt is (re)sent to OpenAI — at
+//  startup, when an ESP client is wired to the session, and when the
+//  emotional prompt mode changes:
+//
+//    {{persona}}      active persona (`obedient`, `cute`, ...); the
+//                     device's provisioned persona if it has one
+//    {{device_name}}  ESP MAC (notification protocol) or UDP address
+//    {{battery}}      latest battery level of the device's sensor (its
+//                     profile's `sensor_id`, or the only sensor reporting),
+//                     e.g. `72%`; `unknown` before its first sensor vector
+//    {{emotion}}      current prompt mode (`friendly`, `tired`, ...) or
+//                     `neutral` before the first emotional result
+//
+//  Whitespace inside the braces is allowed (`{{ persona }}`).  Unknown
+//  placeholders are left untouched; `validate` reports them.
+
+/// Placeholder names understood by [`render`].
+pub const PLACEHOLDERS: [&str; 4] = ["persona", "device_name", "battery", "emotion"];
+
+/// Values substituted into the template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptVars {
+    pub persona: String,
+    pub device_name: String,
+    pub battery: String,
+    pub emotion: String,
+}
+
+/// Replace `{{name}}` placeholders in `template` with `vars`.
+pub fn render(template: &str, vars: &PromptVars) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let raw = &rest[start..start + len + 2];
+        let value = match raw[2..raw.len() - 2].trim() {
+            "persona" => &vars.persona,
+            "device_name" => &vars.device_name,
+            "battery" => &vars.battery,
+            "emotion" => &vars.emotion,
+            _ => raw,
+        };
+        out.push_str(value);
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Placeholder names in `template` that [`render`] does not know.
+pub fn unknown_placeholders(template: &str) -> Vec<String> {
+    let mut unknown = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + len].trim();
+        if !PLACEHOLDERS.contains(&name) && !unknown.iter().any(|u| u == name) {
+            unknown.push(name.to_string());
+        }
+        rest = &rest[start + len + 2..];
+    }
+    unknown
+}
+
+/// The configured instruction template: the file if
+/// `--openai-instructions-file` is set, otherwise `--openai-instructions`.
+pub fn load_template(config: &Config) -> anyhow::Result<String> {
+    if config.openai_instructions_file.is_empty() {
+        return Ok(config.openai_instructions.clone());
+    }
+    std::fs
+        ::read_to_string(&config.openai_instructions_file)
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "failed to read instructions file {}: {e}",
+                config.openai_instructions_file
+            )
+        })
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Shared prompt state
+// ─────────────────────────────────────────────────────────────────────
+
+/// Mutable inputs to the template, shared between the VAD workers
+/// (battery), the response loop (emotion) and the ESP control handlers
+/// (device name).  Clone-friendly (Arc inside).
+#[derive(Clone)]
+pub struct PromptContext {
+    template: Arc<str>,
+    persona: PersonaState,
+    /// Provisioning profiles (a device's own persona)
+    devices: Option<DeviceRegistry>,
+    /// A/B variants of the wired device (persona, prompt additions)
+    experiments: Experiments,
+    /// Latest battery level in [0, 1] per sensor_id
+    battery: Arc<DashMap<u32, f32>>,
+    state: Arc<RwLock<PromptState>>,
+}
+
+#[derive(Default)]
+struct PromptState {
+    device_name: Option<String>,
+    emotion: Option<String>,
+    /// Emotional style block appended after the rendered template
+    emotion_suffix: String,
+}
+
+impl PromptContext {
+    pub fn new(template: String, persona: PersonaState) -> Self {
+        Self {
+            template: template.into(),
+            persona,
+            devices: None,
+            experiments: Experiments::disabled(),
+            battery: Arc::new(DashMap::new()),
+            state: Arc::new(RwLock::new(PromptState::default())),
+        }
+    }
+
+    /// Use the persona of the wired device's provisioning profile, when
+    /// it has one, instead of the global persona.
+    pub fn with_profiles(mut self, devices: DeviceRegistry) -> Self {
+        self.devices = Some(devices);
+        self
+    }
+
+    /// Apply the wired device's experiment variants: their persona wins
+    /// over the profile's, their prompt text is appended.
+    pub fn with_experiments(mut self, experiments: Experiments) -> Self {
+        self.experiments = experiments;
+        self
+    }
+
+    /// Record a sensor's latest `battery_low` reading (1.0 = empty).
+    pub fn observe_battery_low(&self, sensor_id: u32, battery_low: f32) {
+        self.battery.insert(sensor_id, (1.0 - battery_low).clamp(0.0, 1.0));
+    }
+
+    /// Battery level of `device`: its profile's sensor, or the only
+    /// sensor reporting when it has none.
+    fn battery_of(&self, device: Option<&str>) -> Option<f32> {
+        let sensor_id = device
+            .zip(self.devices.as_ref())
+            .and_then(|(device, devices)| devices.profile(device)?.sensor_id);
+        match sensor_id {
+            Some(id) => self.battery.get(&id).map(|level| *level),
+            None if self.battery.len() == 1 => self.battery.iter().next().map(|level| *level.value()),
+            None => None,
+        }
+    }
+
+    /// Name the device currently wired to the OpenAI session.
+    pub fn set_device(&self, name: String) {
+        self.state.write().unwrap_or_else(|e| e.into_inner()).device_name = Some(name);
+    }
+
+    /// Set the current emotion name and the style block appended to the
+    /// rendered template.
+    pub fn set_emotion(&self, name: String, suffix: String) {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        state.emotion = Some(name);
+        state.emotion_suffix = suffix;
+    }
+
+    /// Current values for every placeholder.
+    pub async fn vars(&self) -> PromptVars {
+        let device = self.state.read().unwrap_or_else(|e| e.into_inner()).device_name.clone();
+        let experiment_persona = device.as_deref().and_then(|device| self.experiments.device_persona(device));
+        let battery = self.battery_of(device.as_deref());
+        let profile_persona = device
+            .zip(self.devices.as_ref())
+            .and_then(|(device, devices)| devices.profile(&device)?.persona);
+        let persona = match experiment_persona.or(profile_persona) {
+            Some(persona) => persona.to_string(),
+            None => self.persona.get().await.to_string(),
+        };
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        PromptVars {
+            persona,
+            device_name: state.device_name.clone().unwrap_or_else(|| "unknown".into()),
+            battery: match battery {
+                Some(level) => format!("{:.0}%", level * 100.0),
+                None => "unknown".into(),
+            },
+            emotion: state.emotion.clone().unwrap_or_else(|| "neutral".into()),
+        }
+    }
+
+    /// Full instructions to send: rendered template + the device's
+    /// experiment prompts + emotional style.
+    pub async fn instructions(&self) -> String {
+        let vars = self.vars().await;
+        let (device, suffix) = {
+            let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+            (state.device_name.clone(), state.emotion_suffix.clone())
+        };
+        let variants = device.map(|device| self.experiments.device_prompt(&device)).unwrap_or_default();
+        format!("{}{variants}{suffix}", render(&self.template, &vars))
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persona::PersonaTrait;
+
+    fn vars() -> PromptVars {
+        PromptVars {
+            persona: "cute".into(),
+            device_name: "AA:BB".into(),
+            battery: "50%".into(),
+            emotion: "tired".into(),
+        }
+    }
+
+    #[test]
+    fn test_render_substitutes_known_placeholders() {
+        let out = render("I am {{persona}} on {{ device_name }} at {{battery}}, feeling {{emotion}}.", &vars());
+        assert_eq!(out, "I am cute on AA:BB at 50%, feeling tired.");
+    }
+
+    #[test]
+    fn test_render_keeps_unknown_and_unterminated() {
+        assert_eq!(render("{{nope}} {{persona", &vars()), "{{nope}} {{persona");
+        assert_eq!(unknown_placeholders("{{nope}} {{persona}} {{ nope }}"), vec!["nope"]);
+    }
+
+    #[tokio::test]
+    async fn test_context_instructions() {
+        let ctx = PromptContext::new(
+            "{{persona}}/{{device_name}}/{{battery}}/{{emotion}}".into(),
+            PersonaState::new(PersonaTrait::Obedient)
+        );
+        assert_eq!(ctx.instructions().await, "obedient/unknown/unknown/neutral");
+
+        ctx.observe_battery_low(7, 0.25);
+        ctx.set_device("10.0.0.5:4000".into());
+        ctx.set_emotion("calm".into(), "\n\nstay calm".into());
+        assert_eq!(ctx.instructions().await, "obedient/10.0.0.5:4000/75%/calm\n\nstay calm");
+
+        // A provisioned device speaks with its own persona
+        let devices = DeviceRegistry::new(crate::devices::Thresholds { audio: 30.0, arousal: 0.35 });
+        let profile = crate::devices::DeviceProfile { persona: Some(PersonaTrait::Cute), ..Default::default() };
+        devices.import(vec![("aa:bb:cc:dd:ee:01".into(), profile)]);
+        let ctx = ctx.with_profiles(devices);
+        assert!(ctx.instructions().await.starts_with("obedient/"));
+        ctx.set_device("AA:BB:CC:DD:EE:01".into());
+        assert!(ctx.instructions().await.starts_with("cute/AA:BB:CC:DD:EE:01/"));
+
+        // ... unless its experiment variant sets one, and a prompt
+        let variant = |name: &str, weight, persona, prompt: Option<&str>| crate::experiments::VariantSpec {
+            name: name.into(),
+            weight,
+            audio_threshold: None,
+            arousal_threshold: None,
+            persona,
+            prompt: prompt.map(str::to_string),
+        };
+        let spec = crate::experiments::ExperimentSpec {
+            name: "tone".into(),
+            salt: String::new(),
+            variants: vec![variant("control", 0, None, None), variant("playful", 1, Some(PersonaTrait::Mischievous), Some("Joke a lot."))],
+        };
+        let experiments = Experiments::new(vec![spec], DeviceRegistry::new(crate::devices::Thresholds { audio: 30.0, arousal: 0.35 }));
+        let ctx = ctx.with_experiments(experiments.unwrap());
+        assert_eq!(ctx.instructions().await, "mischievous/AA:BB:CC:DD:EE:01/75%/calm\n\nJoke a lot.\n\nstay calm");
+    }
+
+    #[tokio::test]
+    async fn test_battery_is_per_device() {
+        let devices = DeviceRegistry::new(crate::devices::Thresholds { audio: 30.0, arousal: 0.35 });
+        let profile = |sensor_id| crate::devices::DeviceProfile { sensor_id: Some(sensor_id), ..Default::default() };
+        devices.import(vec![("aa:bb:cc:dd:ee:01".into(), profile(1)), ("aa:bb:cc:dd:ee:02".into(), profile(2))]);
+        let ctx = PromptContext::new("{{battery}}".into(), PersonaState::new(PersonaTrait::Obedient)).with_profiles(devices);
+
+        // One sensor reporting: every device falls back to it
+        ctx.observe_battery_low(1, 0.1);
+        assert_eq!(ctx.instructions().await, "90%");
+
+        ctx.observe_battery_low(2, 0.6);
+        ctx.set_device("AA:BB:CC:DD:EE:02".into());
+        assert_eq!(ctx.instructions().await, "40%");
+        ctx.set_device("aa:bb:cc:dd:ee:01".into());
+        assert_eq!(ctx.instructions().await, "90%");
+        // No profile, several sensors: no way to tell
+        ctx.set_device("10.0.0.5:4000".into());
+        assert_eq!(ctx.instructions().await, "unknown");
+    }
+}
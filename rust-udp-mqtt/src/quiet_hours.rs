@@ -0,0 +1,225 @@
+use chrono::{ DateTime, FixedOffset, NaiveTime, Utc };
+use dashmap::{ DashMap, DashSet };
+use serde::{ Deserialize, Serialize };
+use std::sync::Arc;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Per-device quiet hours
+// ─────────────────────────────────────────────────────────────────────
+//
+//  A daily window (e.g. 21:00 → 07:00, crossing midnight) in the
+//  device's own timezone during which the bridge keeps the robot quiet
+//  whatever the cloud does:
+//
+//    proactive speech   `POST /devices/{device}/play` and `/say` are
+//                       refused (409); replies to the child still play
+//    volume             optional cap: sent as SET_VOLUME when the window
+//                       opens (and on any change inside it); the tracked
+//                       level is restored when it closes
+//    mute_audio         optional: every OpenAI reply and offline clip
+//                       AUDIO_DOWN to the device is dropped
+//
+//  `timezone` is `local` (the host's zone, DST-aware), `UTC` or a fixed
+//  offset such as `+05:30`.  Schedules are set over REST and kept in
+//  memory, keyed by device name (MAC or `ip:port`, case-insensitive).
+
+/// One device's quiet hours, as sent to and served by the REST API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietSchedule {
+    /// Start of the window, `HH:MM`
+    pub start: String,
+    /// End of the window, `HH:MM` (before `start` = crosses midnight)
+    pub end: String,
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// Output volume cap during the window (0-100; absent = unchanged)
+    #[serde(default)]
+    pub volume: Option<u8>,
+    /// Drop all reply AUDIO_DOWN during the window
+    #[serde(default)]
+    pub mute_audio: bool,
+}
+
+fn default_timezone() -> String {
+    "local".into()
+}
+
+/// A schedule's zone.
+enum Zone {
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl QuietSchedule {
+    /// Check the times, timezone and volume.
+    pub fn validate(&self) -> Result<(), String> {
+        let (start, end) = (parse_time(&self.start)?, parse_time(&self.end)?);
+        if start == end {
+            return Err("start and end must differ".into());
+        }
+        parse_zone(&self.timezone)?;
+        if self.volume.is_some_and(|v| v > 100) {
+            return Err("volume must be 0-100".into());
+        }
+        Ok(())
+    }
+
+    /// Whether `now` falls inside the window (false if invalid).
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        let (Ok(start), Ok(end), Ok(zone)) = (parse_time(&self.start), parse_time(&self.end), parse_zone(&self.timezone)) else {
+            return false;
+        };
+        let t = match zone {
+            Zone::Local => now.with_timezone(&chrono::Local).time(),
+            Zone::Fixed(offset) => now.with_timezone(&offset).time(),
+        };
+        if start < end { start <= t && t < end } else { t >= start || t < end }
+    }
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(|_| format!("{s:?} is not HH:MM"))
+}
+
+fn parse_zone(s: &str) -> Result<Zone, String> {
+    match s.trim() {
+        z if z.eq_ignore_ascii_case("local") => Ok(Zone::Local),
+        z if z.eq_ignore_ascii_case("utc") => Ok(Zone::Fixed(FixedOffset::east_opt(0).unwrap())),
+        z =>
+            z
+                .parse::<FixedOffset>()
+                .map(Zone::Fixed)
+                .map_err(|_| format!("{s:?} is not local, UTC or an offset like +05:30")),
+    }
+}
+
+/// Quiet-hours schedules for every device.  Clone-friendly.
+#[derive(Clone, Default)]
+pub struct QuietHours {
+    schedules: Arc<DashMap<String, QuietSchedule>>,
+    /// Devices currently held at their quiet volume cap
+    capped: Arc<DashSet<String>>,
+}
+
+impl QuietHours {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, device: &str) -> Option<QuietSchedule> {
+        self.schedules.get(&device.to_ascii_lowercase()).map(|s| s.clone())
+    }
+
+    /// Set (`Some`) or clear (`None`) `device`'s schedule.  Returns whether
+    /// it had one.
+    pub fn set(&self, device: &str, schedule: Option<QuietSchedule>) -> Result<bool, String> {
+        let key = device.to_ascii_lowercase();
+        match schedule {
+            Some(schedule) => {
+                schedule.validate()?;
+                Ok(self.schedules.insert(key, schedule).is_some())
+            }
+            None => Ok(self.schedules.remove(&key).is_some()),
+        }
+    }
+
+    /// `device`'s schedule if its window is open now.
+    pub fn active(&self, device: &str) -> Option<QuietSchedule> {
+        self.active_at(device, Utc::now())
+    }
+
+    pub fn active_at(&self, device: &str, now: DateTime<Utc>) -> Option<QuietSchedule> {
+        self.get(device).filter(|s| s.is_active_at(now))
+    }
+
+    /// Whether reply audio to `device` is muted right now.
+    pub fn mutes_audio(&self, device: &str) -> bool {
+        self.active(device).is_some_and(|s| s.mute_audio)
+    }
+
+    /// `device`'s volume cap right now.
+    pub fn volume_cap(&self, device: &str) -> Option<u8> {
+        self.active(device).and_then(|s| s.volume)
+    }
+
+    /// Devices whose cap must be sent (`Some(cap)`: window opened) or
+    /// lifted (`None`: window closed or schedule cleared) at `now`.
+    /// Confirm each with [`Self::mark_capped`] once sent.
+    pub fn volume_transitions(&self, now: DateTime<Utc>) -> Vec<(String, Option<u8>)> {
+        let mut due: Vec<(String, Option<u8>)> = self.schedules
+            .iter()
+            .filter_map(|s| {
+                let cap = s.volume.filter(|_| s.is_active_at(now))?;
+                (!self.capped.contains(s.key())).then(|| (s.key().clone(), Some(cap)))
+            })
+            .collect();
+        due.extend(
+            self.capped
+                .iter()
+                .filter(|device| self.active_at(device.key(), now).and_then(|s| s.volume).is_none())
+                .map(|device| (device.key().clone(), None))
+        );
+        due
+    }
+
+    pub fn mark_capped(&self, device: &str, capped: bool) {
+        let key = device.to_ascii_lowercase();
+        if capped {
+            self.capped.insert(key);
+        } else {
+            self.capped.remove(&key);
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc)
+    }
+
+    fn bedtime() -> QuietSchedule {
+        QuietSchedule {
+            start: "21:00".into(),
+            end: "07:00".into(),
+            timezone: "+02:00".into(),
+            volume: Some(20),
+            mute_audio: false,
+        }
+    }
+
+    #[test]
+    fn test_window_crosses_midnight_in_device_zone() {
+        let s = bedtime();
+        assert!(s.is_active_at(at("2026-01-01T19:30:00Z"))); // 21:30 local
+        assert!(s.is_active_at(at("2026-01-02T04:59:00Z"))); // 06:59 local
+        assert!(!s.is_active_at(at("2026-01-02T05:00:00Z"))); // 07:00 local
+        assert!(!s.is_active_at(at("2026-01-01T12:00:00Z")));
+
+        assert!(QuietSchedule { timezone: "Mars/Olympus".into(), ..bedtime() }.validate().is_err());
+        assert!(QuietSchedule { end: "21:00".into(), ..bedtime() }.validate().is_err());
+        assert!(QuietSchedule { start: "9pm".into(), ..bedtime() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_volume_transitions() {
+        let quiet = QuietHours::new();
+        quiet.set("AA:BB:CC:DD:EE:FF", Some(bedtime())).unwrap();
+        let night = at("2026-01-01T20:00:00Z");
+
+        assert_eq!(quiet.volume_transitions(night), [("aa:bb:cc:dd:ee:ff".to_string(), Some(20))]);
+        quiet.mark_capped("aa:bb:cc:dd:ee:ff", true);
+        assert!(quiet.volume_transitions(night).is_empty());
+
+        // Morning (or the schedule going away) lifts the cap
+        assert_eq!(quiet.volume_transitions(at("2026-01-02T08:00:00Z")), [("aa:bb:cc:dd:ee:ff".to_string(), None)]);
+        quiet.set("aa:bb:cc:dd:ee:ff", None).unwrap();
+        assert_eq!(quiet.volume_transitions(night), [("aa:bb:cc:dd:ee:ff".to_string(), None)]);
+    }
+}
@@ -0,0 +1,347 @@
+use crate::calibrate::CHANNEL_NAMES;
+use crate::clock::{ self, SharedClock };
+use crate::config::Config;
+use crate::sensor::{ SensorPacket, SensorVector, DATA_TYPE_SENSOR_VECTOR, SENSOR_VECTOR_LEN };
+use crate::trace::TraceId;
+use crate::vad::VadResult;
+use std::io::{ BufWriter, Write };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+use tokio::sync::mpsc;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Sensor data recorder
+// ─────────────────────────────────────────────────────────────────────
+//
+//  With `--record-dir`, every parsed sensor vector is appended — raw
+//  channels as received plus the V/A/D the engine computed — to
+//  rotating files for offline analysis / weight retraining:
+//
+//    <record-dir>/sensors_[<tenant>_]<YYYYmmdd_HHMMSS>.csv|.parquet
+//
+//  Columns: t_ms, sensor_id, seq, timestamp_us, battery_low …
+//  motion_energy, valence, arousal, dominance, is_active, trace (the
+//  packet's correlation id, see `trace.rs`).  CSV files
+//  load directly into `replay` (and into `calibrate` once the V/A/D
+//  columns hold human labels).
+//
+//  A new file starts every `--record-rotate-secs`.  Rows are handed to
+//  a blocking writer thread through a bounded channel; when the disk
+//  can't keep up rows are dropped (and counted) rather than stalling
+//  the VAD workers.  Parquet (`--features parquet`) is written in row
+//  groups and only readable once its file is closed — on rotation or
+//  shutdown.
+
+/// Rows buffered between the VAD workers and the writer.
+const CHANNEL_CAPACITY: usize = 8192;
+
+/// File format for `--record-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RecordFormat {
+    Csv,
+    /// Needs a build with `--features parquet`
+    Parquet,
+}
+
+impl RecordFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            RecordFormat::Csv => "csv",
+            RecordFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// One recorded sensor vector.
+#[derive(Debug, Clone, Copy)]
+pub struct Record {
+    pub t_ms: u64,
+    pub sensor_id: u32,
+    pub seq: u64,
+    pub timestamp_us: u64,
+    pub sensors: [f32; SENSOR_VECTOR_LEN],
+    pub valence: f32,
+    pub arousal: f32,
+    pub dominance: f32,
+    pub is_active: bool,
+    pub trace: Option<TraceId>,
+}
+
+/// Column names, in file order.
+pub fn columns() -> Vec<&'static str> {
+    let mut cols = vec!["t_ms", "sensor_id", "seq", "timestamp_us"];
+    cols.extend(CHANNEL_NAMES);
+    cols.extend(["valence", "arousal", "dominance", "is_active", "trace"]);
+    cols
+}
+
+/// Handle used by the VAD workers.  Clone-friendly.
+#[derive(Clone)]
+pub struct SensorRecorder {
+    tx: mpsc::Sender<Record>,
+    dropped: Arc<AtomicU64>,
+    clock: SharedClock,
+}
+
+impl SensorRecorder {
+    /// Start the recorder if `--record-dir` is set.
+    pub fn from_config(config: &Config, clock: SharedClock) -> anyhow::Result<Option<Self>> {
+        if config.record_dir.is_empty() {
+            return Ok(None);
+        }
+        #[cfg(not(feature = "parquet"))]
+        if config.record_format == RecordFormat::Parquet {
+            anyhow::bail!("--record-format parquet requires a build with `--features parquet`");
+        }
+        std::fs
+            ::create_dir_all(&config.record_dir)
+            .map_err(|e| anyhow::anyhow!("failed to create {}: {e}", config.record_dir))?;
+
+        let files = Rotation {
+            dir: config.record_dir.clone(),
+            prefix: if config.tenant_id.is_empty() {
+                "sensors".to_string()
+            } else {
+                format!("sensors_{}", config.tenant_id)
+            },
+            format: config.record_format,
+            every: Duration::from_secs(config.record_rotate_secs.max(1)),
+            clock: clock.clone(),
+        };
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let dropped_writer = dropped.clone();
+        tokio::task::spawn_blocking(move || write_loop(files, rx, dropped_writer));
+        info!(
+            dir = %config.record_dir,
+            format = ?config.record_format,
+            rotate_secs = config.record_rotate_secs,
+            "🗃️  sensor recorder enabled"
+        );
+        Ok(Some(Self { tx, dropped, clock }))
+    }
+
+    /// Record `packet` with its VAD `result` (sensor vectors only).
+    pub fn record(&self, packet: &SensorPacket, result: &VadResult) {
+        if packet.data_type != DATA_TYPE_SENSOR_VECTOR {
+            return;
+        }
+        let Some(sv) = SensorVector::from_payload(&packet.payload) else {
+            return;
+        };
+        let record = Record {
+            t_ms: clock::unix_millis(self.clock.as_ref()),
+            sensor_id: packet.sensor_id,
+            seq: packet.seq,
+            timestamp_us: packet.timestamp_us,
+            sensors: sv.as_array(),
+            valence: result.valence,
+            arousal: result.arousal,
+            dominance: result.dominance,
+            is_active: result.is_active,
+            trace: packet.trace,
+        };
+        if self.tx.try_send(record).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Writer
+// ─────────────────────────────────────────────────────────────────────
+
+/// An open recording file.
+pub(crate) trait Sink: Send {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()>;
+    /// Push buffered rows to disk (called whenever the channel drains).
+    fn flush(&mut self) -> anyhow::Result<()>;
+    /// Finish and close the file.
+    fn close(self: Box<Self>) -> anyhow::Result<()>;
+}
+
+/// Where and how often to start new files.
+struct Rotation {
+    dir: String,
+    prefix: String,
+    format: RecordFormat,
+    every: Duration,
+    clock: SharedClock,
+}
+
+impl Rotation {
+    fn open(&self) -> anyhow::Result<(String, Box<dyn Sink>)> {
+        let ts = chrono::DateTime::<chrono::Local>::from(self.clock.system_now()).format("%Y%m%d_%H%M%S");
+        let path = format!("{}/{}_{ts}.{}", self.dir, self.prefix, self.format.extension());
+        let file = std::fs::File::create(&path).map_err(|e| anyhow::anyhow!("failed to create {path}: {e}"))?;
+        let sink: Box<dyn Sink> = match self.format {
+            RecordFormat::Csv => Box::new(CsvSink::new(file)?),
+            #[cfg(feature = "parquet")]
+            RecordFormat::Parquet => Box::new(crate::recorder_parquet::ParquetSink::new(file)?),
+            #[cfg(not(feature = "parquet"))]
+            RecordFormat::Parquet => unreachable!("rejected in SensorRecorder::from_config"),
+        };
+        info!(path = %path, "🗃️  sensor recording started");
+        Ok((path, sink))
+    }
+}
+
+fn write_loop(files: Rotation, mut rx: mpsc::Receiver<Record>, dropped: Arc<AtomicU64>) {
+    let mut current: Option<(String, Box<dyn Sink>, Instant)> = None;
+    while let Some(first) = rx.blocking_recv() {
+        let mut next = Some(first);
+        while let Some(record) = next {
+            let now = files.clock.now();
+            if current.as_ref().is_some_and(|(_, _, opened)| now.duration_since(*opened) >= files.every) {
+                close(current.take());
+            }
+            if current.is_none() {
+                match files.open() {
+                    Ok((path, sink)) => {
+                        current = Some((path, sink, now));
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "sensor recorder stopped");
+                        return;
+                    }
+                }
+            }
+            if let Some((path, sink, _)) = current.as_mut() {
+                if let Err(e) = sink.write(&record) {
+                    warn!(path = %path, error = %e, "sensor recorder write failed");
+                }
+            }
+            next = rx.try_recv().ok();
+        }
+        if let Some((path, sink, _)) = current.as_mut() {
+            if let Err(e) = sink.flush() {
+                warn!(path = %path, error = %e, "sensor recorder flush failed");
+            }
+        }
+        let lost = dropped.swap(0, Ordering::Relaxed);
+        if lost > 0 {
+            warn!(dropped = lost, "sensor recorder fell behind — rows dropped");
+        }
+    }
+    close(current);
+}
+
+fn close(current: Option<(String, Box<dyn Sink>, Instant)>) {
+    if let Some((path, sink, _)) = current {
+        match sink.close() {
+            Ok(()) => info!(path = %path, "🗃️  sensor recording closed"),
+            Err(e) => warn!(path = %path, error = %e, "failed to close sensor recording"),
+        }
+    }
+}
+
+/// Plain CSV with a header row.
+struct CsvSink {
+    out: BufWriter<std::fs::File>,
+}
+
+impl CsvSink {
+    fn new(file: std::fs::File) -> anyhow::Result<Self> {
+        let mut out = BufWriter::new(file);
+        writeln!(out, "{}", columns().join(","))?;
+        Ok(Self { out })
+    }
+}
+
+impl Sink for CsvSink {
+    fn write(&mut self, r: &Record) -> anyhow::Result<()> {
+        write!(self.out, "{},{},{},{}", r.t_ms, r.sensor_id, r.seq, r.timestamp_us)?;
+        for v in r.sensors {
+            write!(self.out, ",{v}")?;
+        }
+        write!(self.out, ",{},{},{},{},", r.valence, r.arousal, r.dominance, r.is_active as u8)?;
+        match r.trace {
+            Some(trace) => writeln!(self.out, "{trace}")?,
+            None => writeln!(self.out)?,
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        Ok(self.out.flush()?)
+    }
+
+    fn close(mut self: Box<Self>) -> anyhow::Result<()> {
+        Ok(self.out.flush()?)
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimClock;
+
+    fn record(sensor_id: u32, seq: u64) -> Record {
+        Record {
+            t_ms: 1_000 + seq,
+            sensor_id,
+            seq,
+            timestamp_us: 0,
+            sensors: [0.5; SENSOR_VECTOR_LEN],
+            valence: 0.25,
+            arousal: 0.75,
+            dominance: 0.5,
+            is_active: true,
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn test_csv_rows_and_rotation() {
+        let dir = std::env::temp_dir().join(format!("vad-recorder-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let sim = SimClock::starting_at(std::time::UNIX_EPOCH);
+        let files = Rotation {
+            dir: dir.to_str().unwrap().to_string(),
+            prefix: "sensors".into(),
+            format: RecordFormat::Csv,
+            every: Duration::from_secs(60),
+            clock: Arc::new(sim.clone()),
+        };
+        let (tx, rx) = mpsc::channel(16);
+        tx.try_send(record(1, 0)).unwrap();
+        tx.try_send(record(1, 1)).unwrap();
+        let writer = std::thread::spawn(move || write_loop(files, rx, Arc::default()));
+
+        // Give the writer the first two rows, then rotate
+        while tx.capacity() < 16 {
+            std::thread::yield_now();
+        }
+        std::thread::sleep(Duration::from_millis(20));
+        sim.advance(Duration::from_secs(61));
+        tx.try_send(record(2, 0)).unwrap();
+        drop(tx);
+        writer.join().unwrap();
+
+        let mut paths: Vec<_> = std::fs
+            ::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        paths.sort();
+        let texts: Vec<String> = paths
+            .iter()
+            .map(|p| std::fs::read_to_string(p).unwrap())
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(texts.len(), 2);
+        let lines: Vec<&str> = texts[0].lines().collect();
+        assert_eq!(lines[0], columns().join(","));
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("1000,1,0,0,0.5,"));
+        assert!(lines[1].ends_with(",0.25,0.75,0.5,1,"));
+        assert_eq!(texts[1].lines().count(), 2);
+    }
+}
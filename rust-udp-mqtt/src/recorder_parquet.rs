@@ -0,0 +1,152 @@
+//! Parquet sink for the sensor recorder (`--record-format parquet`).
+//!
+//! Rows are buffered and written as one row group per `ROW_GROUP_ROWS`
+//! (or whenever the recorder flushes with at least that many pending);
+//! the footer is written on close, so a file becomes readable once the
+//! recorder rotates away from it or shuts down.
+
+use crate::recorder::{ columns, Record, Sink };
+use crate::sensor::SENSOR_VECTOR_LEN;
+use arrow_array::{ ArrayRef, BooleanArray, Float32Array, RecordBatch, StringArray, UInt32Array, UInt64Array };
+use arrow_schema::{ DataType, Field, Schema };
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::sync::Arc;
+
+/// Rows per Parquet row group.
+const ROW_GROUP_ROWS: usize = 16_384;
+
+pub struct ParquetSink {
+    writer: ArrowWriter<std::fs::File>,
+    schema: Arc<Schema>,
+    pending: Vec<Record>,
+}
+
+impl ParquetSink {
+    pub fn new(file: std::fs::File) -> anyhow::Result<Self> {
+        let fields: Vec<Field> = columns()
+            .into_iter()
+            .map(|name| {
+                let ty = match name {
+                    "t_ms" | "seq" | "timestamp_us" => DataType::UInt64,
+                    "sensor_id" => DataType::UInt32,
+                    "is_active" => DataType::Boolean,
+                    "trace" => DataType::Utf8,
+                    _ => DataType::Float32,
+                };
+                Field::new(name, ty, name == "trace")
+            })
+            .collect();
+        let schema = Arc::new(Schema::new(fields));
+        let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+        let writer = ArrowWriter::try_new(file, schema.clone(), Some(props))?;
+        Ok(Self { writer, schema, pending: Vec::with_capacity(ROW_GROUP_ROWS) })
+    }
+
+    fn write_pending(&mut self) -> anyhow::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let rows = std::mem::take(&mut self.pending);
+        let u64s = |f: fn(&Record) -> u64| -> ArrayRef {
+            Arc::new(UInt64Array::from_iter_values(rows.iter().map(f)))
+        };
+        let f32s = |f: &dyn Fn(&Record) -> f32| -> ArrayRef {
+            Arc::new(Float32Array::from_iter_values(rows.iter().map(f)))
+        };
+
+        let mut arrays: Vec<ArrayRef> = vec![
+            u64s(|r| r.t_ms),
+            Arc::new(UInt32Array::from_iter_values(rows.iter().map(|r| r.sensor_id))),
+            u64s(|r| r.seq),
+            u64s(|r| r.timestamp_us)
+        ];
+        for ch in 0..SENSOR_VECTOR_LEN {
+            arrays.push(f32s(&(|r: &Record| r.sensors[ch])));
+        }
+        arrays.push(f32s(&(|r: &Record| r.valence)));
+        arrays.push(f32s(&(|r: &Record| r.arousal)));
+        arrays.push(f32s(&(|r: &Record| r.dominance)));
+        arrays.push(Arc::new(rows.iter().map(|r| Some(r.is_active)).collect::<BooleanArray>()));
+        arrays.push(Arc::new(rows.iter().map(|r| r.trace.map(|t| t.to_string())).collect::<StringArray>()));
+
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays)?;
+        self.writer.write(&batch)?;
+        self.writer.flush()?;
+        self.pending = rows;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl Sink for ParquetSink {
+    fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        self.pending.push(*record);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        // Small row groups bloat Parquet files: only cut full ones
+        if self.pending.len() >= ROW_GROUP_ROWS {
+            self.write_pending()?;
+        }
+        Ok(())
+    }
+
+    fn close(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.write_pending()?;
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn test_parquet_round_trip() {
+        let path = std::env::temp_dir().join(format!("vad-recorder-{}.parquet", std::process::id()));
+        let mut sink = Box::new(ParquetSink::new(std::fs::File::create(&path).unwrap()).unwrap());
+        for seq in 0..3 {
+            let record = Record {
+                t_ms: 1_000 + seq,
+                sensor_id: 4,
+                seq,
+                timestamp_us: 0,
+                sensors: [0.5; SENSOR_VECTOR_LEN],
+                valence: 0.25,
+                arousal: 0.75,
+                dominance: 0.5,
+                is_active: seq == 2,
+                trace: None,
+            };
+            sink.write(&record).unwrap();
+        }
+        sink.flush().unwrap();
+        sink.close().unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(|b| b.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 3);
+        assert_eq!(batches[0].num_columns(), columns().len());
+        let active = batches[0]
+            .column_by_name("is_active")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<BooleanArray>()
+            .unwrap();
+        assert!(!active.value(0) && active.value(2));
+    }
+}
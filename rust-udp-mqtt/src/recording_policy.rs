@@ -0,0 +1,309 @@
+use crate::clock::SharedClock;
+use crate::config::Config;
+use crate::device_data::DeviceFiles;
+use crate::safety::SafetyPolicy;
+use dashmap::DashMap;
+use serde::{ Deserialize, Serialize };
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Recording consent policies
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Not every household consents to audio retention.  Each device (MAC
+//  or `ip:port`, as in `{{device_name}}`) has a recording policy:
+//
+//    record     session WAV, monitoring copy, JSONL session log and
+//               transcripts kept; with `retention_days` > 0, the
+//               device's files are deleted once that old
+//    metadata   session timing only: the JSONL session log (packets,
+//               VAD, OpenAI event types) without transcript text; no
+//               WAV, no conversation history, transcripts not
+//               forwarded
+//    never      nothing written for the device's sessions at all
+//
+//  The policy is read at SESSION_START, before the WAV is opened, and
+//  again for every transcript.  Audio still streams to OpenAI — the
+//  robot answers either way.  Files kept before a policy change stay
+//  until retention or `DELETE /devices/{id}/data` removes them.
+//
+//  `--recording-policy` / `--recording-retention-days` set the default;
+//  per-device overrides are set over REST and persisted in
+//  `<--audio-save-dir>/recording_policies.json`.  Every change and every
+//  retention delete is written to the audit log (`safety.rs`).
+
+/// Override file name inside `--audio-save-dir`.
+pub const POLICY_FILE: &str = "recording_policies.json";
+
+/// How often retention is enforced.
+const SWEEP_EVERY: Duration = Duration::from_secs(3600);
+
+/// What is kept of a device's sessions.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    Serialize,
+    Deserialize,
+    clap::ValueEnum
+)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingMode {
+    #[default]
+    Record,
+    Metadata,
+    Never,
+}
+
+/// A device's recording policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RecordingPolicy {
+    pub mode: RecordingMode,
+    /// Days recorded files are kept (0 = until erased)
+    #[serde(default)]
+    pub retention_days: u32,
+}
+
+impl RecordingPolicy {
+    /// Session WAV (and the copies made from it)
+    pub fn keeps_audio(&self) -> bool {
+        self.mode == RecordingMode::Record
+    }
+
+    /// Conversation history, forwarded and logged transcript text
+    pub fn keeps_transcripts(&self) -> bool {
+        self.mode == RecordingMode::Record
+    }
+
+    /// JSONL session log
+    pub fn keeps_session_log(&self) -> bool {
+        self.mode != RecordingMode::Never
+    }
+}
+
+/// Default policy plus per-device overrides.  Clone-friendly.
+#[derive(Clone)]
+pub struct RecordingPolicies {
+    default: RecordingPolicy,
+    overrides: Arc<DashMap<String, RecordingPolicy>>,
+    /// Override file (None = memory only)
+    path: Option<Arc<PathBuf>>,
+    /// Serialises rewrites of `path`
+    saving: Arc<Mutex<()>>,
+    files: DeviceFiles,
+    audit: Arc<SafetyPolicy>,
+    clock: SharedClock,
+}
+
+impl RecordingPolicies {
+    /// Policies kept in memory only.
+    pub fn new(default: RecordingPolicy, files: DeviceFiles, audit: Arc<SafetyPolicy>, clock: SharedClock) -> Self {
+        Self {
+            default,
+            overrides: Arc::default(),
+            path: None,
+            saving: Arc::default(),
+            files,
+            audit,
+            clock,
+        }
+    }
+
+    /// The configured default plus the overrides saved in
+    /// `--audio-save-dir`.
+    pub fn load(
+        config: &Config,
+        files: DeviceFiles,
+        audit: Arc<SafetyPolicy>,
+        clock: SharedClock
+    ) -> anyhow::Result<Self> {
+        let default = RecordingPolicy { mode: config.recording_policy, retention_days: config.recording_retention_days };
+        let path = Path::new(&config.audio_save_dir).join(POLICY_FILE);
+        let saved: BTreeMap<String, RecordingPolicy> = match std::fs::read_to_string(&path) {
+            Ok(text) =>
+                serde_json
+                    ::from_str(&text)
+                    .map_err(|e| anyhow::anyhow!("invalid {}: {e}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => anyhow::bail!("failed to read {}: {e}", path.display()),
+        };
+        if default.mode != RecordingMode::Record || default.retention_days > 0 || !saved.is_empty() {
+            info!(
+                default = ?default.mode,
+                retention_days = default.retention_days,
+                overrides = saved.len(),
+                "🔏 recording policies loaded"
+            );
+        }
+        let policies = Self { path: Some(Arc::new(path)), ..Self::new(default, files, audit, clock) };
+        for (device, policy) in saved {
+            policies.overrides.insert(device, policy);
+        }
+        Ok(policies)
+    }
+
+    /// Policy for `device` (case-insensitive).
+    pub fn policy(&self, device: &str) -> RecordingPolicy {
+        self.override_for(device).unwrap_or(self.default)
+    }
+
+    pub fn override_for(&self, device: &str) -> Option<RecordingPolicy> {
+        self.overrides.get(&device.to_ascii_lowercase()).map(|p| *p)
+    }
+
+    /// Set (`Some`) or clear (`None`) `device`'s override, persist and
+    /// audit it.
+    pub fn set(&self, device: &str, policy: Option<RecordingPolicy>) -> anyhow::Result<()> {
+        let key = device.to_ascii_lowercase();
+        let previous = self.policy(&key);
+        match policy {
+            Some(policy) => {
+                self.overrides.insert(key.clone(), policy);
+            }
+            None => {
+                self.overrides.remove(&key);
+            }
+        }
+        self.save()?;
+        let now = self.policy(&key);
+        info!(device = %key, mode = ?now.mode, retention_days = now.retention_days, "🔏 recording policy updated");
+        self.audit.audit_event(
+            "recording_policy",
+            json!({ "device": key, "previous": previous, "policy": now, "override": policy.is_some() })
+        );
+        Ok(())
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        let _saving = self.saving.lock().unwrap_or_else(|e| e.into_inner());
+        let overrides: BTreeMap<String, RecordingPolicy> = self.overrides
+            .iter()
+            .map(|e| (e.key().clone(), *e.value()))
+            .collect();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(&overrides)?)?;
+        std::fs::rename(&tmp, path.as_ref())?;
+        Ok(())
+    }
+
+    /// Delete indexed files older than their device's retention; returns
+    /// how many were removed.
+    pub fn sweep(&self) -> usize {
+        let now = self.clock.system_now();
+        let mut expired: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (device, path) in self.files.all() {
+            let policy = self.policy(&device);
+            if policy.retention_days == 0 {
+                continue;
+            }
+            let max_age = Duration::from_secs(u64::from(policy.retention_days) * 86_400);
+            let age = std::fs
+                ::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok());
+            match age {
+                Some(age) if age < max_age => {}
+                // Gone already: only the index entry is left
+                None if Path::new(&path).exists() => {}
+                _ => expired.entry(device).or_default().push(path),
+            }
+        }
+        let mut removed = 0;
+        for (device, paths) in expired {
+            for path in &paths {
+                if let Err(e) = std::fs::remove_file(path) {
+                    if e.kind() != std::io::ErrorKind::NotFound {
+                        warn!(path = %path, error = %e, "failed to delete expired recording");
+                    }
+                }
+            }
+            self.files.forget_paths(&paths);
+            info!(device = %device, files = paths.len(), "🗑️ recordings past retention deleted");
+            self.audit.audit_event("recording_retention", json!({ "device": device, "files": paths }));
+            removed += paths.len();
+        }
+        removed
+    }
+}
+
+/// Enforce retention every hour.
+pub async fn sweep_loop(policies: RecordingPolicies) {
+    loop {
+        policies.clock.sleep(SWEEP_EVERY).await;
+        let policies = policies.clone();
+        if let Err(e) = tokio::task::spawn_blocking(move || policies.sweep()).await {
+            warn!(error = %e, "recording retention sweep failed");
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimClock;
+    use clap::Parser;
+
+    #[test]
+    fn test_overrides_persist_and_retention_deletes() {
+        let dir = std::env::temp_dir().join(format!("vad-recording-policy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir_str = dir.to_str().unwrap();
+        let config = crate::config::Cli::parse_from([
+            "vad-sensor-bridge",
+            "--audio-save-dir",
+            dir_str,
+            "--recording-policy",
+            "metadata",
+        ]).serve;
+        let files = DeviceFiles::load(dir_str);
+        let sim = SimClock::starting_at(std::time::SystemTime::now());
+        let audit = Arc::new(SafetyPolicy::default());
+
+        let policies = RecordingPolicies::load(&config, files.clone(), audit.clone(), Arc::new(sim.clone())).unwrap();
+        assert!(!policies.policy("AA:BB:CC:DD:EE:FF").keeps_audio());
+        assert!(policies.policy("10.0.0.7:5000").keeps_session_log());
+        let keep_week = RecordingPolicy { mode: RecordingMode::Record, retention_days: 7 };
+        policies.set("AA:BB:CC:DD:EE:FF", Some(keep_week)).unwrap();
+        policies.set("10.0.0.7:5000", Some(RecordingPolicy { mode: RecordingMode::Never, retention_days: 0 })).unwrap();
+        policies.set("10.0.0.7:5000", None).unwrap();
+
+        let reloaded = RecordingPolicies::load(&config, files.clone(), audit, Arc::new(sim.clone())).unwrap();
+        assert_eq!(reloaded.policy("aa:bb:cc:dd:ee:ff"), keep_week);
+        assert_eq!(reloaded.override_for("10.0.0.7:5000"), None);
+        assert_eq!(serde_json::to_value(keep_week).unwrap(), json!({"mode": "record", "retention_days": 7}));
+
+        // Only the device with a retention loses its files, once they age
+        let wav = dir.join("esp_10_0_0_9_20261016_150012.wav");
+        let other = dir.join("esp_10_0_0_7_20261016_150012.wav");
+        std::fs::write(&wav, b"RIFF").unwrap();
+        std::fs::write(&other, b"RIFF").unwrap();
+        files.record("aa:bb:cc:dd:ee:ff", wav.to_str().unwrap());
+        files.record("10.0.0.7:5000", other.to_str().unwrap());
+        assert_eq!(reloaded.sweep(), 0);
+        sim.advance(Duration::from_secs(8 * 86_400));
+        assert_eq!(reloaded.sweep(), 1);
+        assert!(!wav.exists() && other.exists());
+        assert!(files.files("aa:bb:cc:dd:ee:ff").is_empty());
+        assert_eq!(files.files("10.0.0.7:5000").len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
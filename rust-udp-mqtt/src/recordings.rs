@@ -0,0 +1,780 @@
+use crate::config::Config;
+use crate::device_data::DeviceFiles;
+use crate::pcm::Downmix;
+use crate::transport_openai::resample;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tracing::{ debug, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Session recordings, transcoded on demand
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Session WAVs are 16 kHz s16, possibly multi-channel — fine for an
+//  archive, awkward for a browser.  `GET /recordings/{id}` serves one,
+//  where `id` is the WAV's file stem in `--audio-save-dir`
+//  (`esp_<ip>_<YYYYmmdd_HHMMSS>`, the name in the session log's
+//  `wav_saved` line):
+//
+//    ?format=wav    (default) the WAV as stored; with `rate`, mono
+//    ?format=flac   lossless FLAC, mono (encoded here, no library)
+//    ?format=ogg    Ogg Opus, mono 16 kHz input (`--features opus`);
+//                   `rate` does not apply
+//    ?format=mp3    not available: no MP3 encoder in this build (415)
+//    ?rate=N        resample to N Hz (8000..=48000)
+//
+//  Transcodes are cached in `<dir>/transcoded/<id>.<rate>.<ext>` and
+//  reused while newer than their WAV.  Past `--transcode-cache-mb` the
+//  least recently served are deleted.  Each cached file is listed under
+//  its recording's device in the device file index, so device export
+//  and erasure cover it.
+
+/// Rate of stored session WAVs.
+const WAV_RATE: u32 = 16_000;
+const WAV_HEADER_LEN: usize = 44;
+
+/// Cache directory inside `--audio-save-dir`.
+const CACHE_DIR: &str = "transcoded";
+
+/// Bitrate of Ogg transcodes when `--monitor-opus-kbps` is off.
+const DEFAULT_OPUS_KBPS: u32 = 24;
+
+/// Output container / codec of `GET /recordings/{id}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    #[default]
+    Wav,
+    Flac,
+    Ogg,
+    Mp3,
+}
+
+impl AudioFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "audio/wav",
+            AudioFormat::Flac => "audio/flac",
+            AudioFormat::Ogg => "audio/ogg",
+            AudioFormat::Mp3 => "audio/mpeg",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Ogg => "opus",
+            AudioFormat::Mp3 => "mp3",
+        }
+    }
+}
+
+/// Query of `GET /recordings/{id}`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TranscodeQuery {
+    pub format: Option<AudioFormat>,
+    pub rate: Option<u32>,
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    /// Not a recording file stem
+    BadId(String),
+    NotFound(String),
+    BadRate(u32),
+    /// Malformed request body
+    Invalid(String),
+    /// The format can't be produced by this build
+    Unsupported(&'static str),
+    Failed(String),
+}
+
+impl std::fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordingError::BadId(id) => write!(f, "invalid recording id: {id:?}"),
+            RecordingError::NotFound(id) => write!(f, "unknown recording: {id}"),
+            RecordingError::BadRate(rate) => write!(f, "rate must be 8000..=48000 Hz, got {rate}"),
+            RecordingError::Invalid(e) => write!(f, "{e}"),
+            RecordingError::Unsupported(why) => write!(f, "{why}"),
+            RecordingError::Failed(e) => write!(f, "transcoding failed: {e}"),
+        }
+    }
+}
+
+/// Serves session WAVs from `--audio-save-dir`.  Clone-friendly.
+#[derive(Clone)]
+pub struct Recordings {
+    dir: Arc<str>,
+    /// Cache budget (0 = transcode every request, cache nothing)
+    cache_bytes: u64,
+    opus_kbps: u32,
+    files: DeviceFiles,
+    /// One transcode at a time: they are CPU-bound, and a burst of
+    /// requests for the same recording then hits the cache
+    busy: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Recordings {
+    pub fn new(dir: &str, cache_mb: u64, opus_kbps: u32, files: DeviceFiles) -> Self {
+        Self {
+            dir: Arc::from(dir),
+            cache_bytes: cache_mb * 1024 * 1024,
+            opus_kbps: if opus_kbps == 0 { DEFAULT_OPUS_KBPS } else { opus_kbps },
+            files,
+            busy: Arc::default(),
+        }
+    }
+
+    pub fn from_config(config: &Config, files: DeviceFiles) -> Self {
+        Self::new(&config.audio_save_dir, config.transcode_cache_mb, config.monitor_opus_kbps, files)
+    }
+
+    /// Path of recording `id`, if it is a valid id of an existing WAV.
+    pub async fn wav_path(&self, id: &str) -> Result<String, RecordingError> {
+        let valid = !id.is_empty() && id.len() <= 128 && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+        if !valid {
+            return Err(RecordingError::BadId(id.to_string()));
+        }
+        let wav_path = format!("{}/{id}.wav", self.dir);
+        match tokio::fs::metadata(&wav_path).await {
+            Ok(meta) if meta.is_file() => Ok(wav_path),
+            _ => Err(RecordingError::NotFound(id.to_string())),
+        }
+    }
+
+    /// Hold off transcoding (while a recording is rewritten).
+    pub async fn lock(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.busy.lock().await
+    }
+
+    /// Delete every cached transcode of `id`; returns how many.
+    pub fn forget_cached(&self, id: &str) -> usize {
+        let Ok(entries) = std::fs::read_dir(format!("{}/{CACHE_DIR}", self.dir)) else {
+            return 0;
+        };
+        let prefix = format!("{id}.");
+        entries
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().starts_with(&prefix))
+            .filter(|e| std::fs::remove_file(e.path()).is_ok())
+            .count()
+    }
+
+    /// Recording `id` in the requested format.
+    pub async fn get(&self, id: &str, query: &TranscodeQuery) -> Result<(AudioFormat, Vec<u8>), RecordingError> {
+        let wav_path = self.wav_path(id).await?;
+        let wav_modified = tokio::fs
+            ::metadata(&wav_path).await
+            .and_then(|m| m.modified())
+            .map_err(|_| RecordingError::NotFound(id.to_string()))?;
+        if let Some(rate) = query.rate {
+            if !(8_000..=48_000).contains(&rate) {
+                return Err(RecordingError::BadRate(rate));
+            }
+        }
+
+        let format = query.format.unwrap_or_default();
+        let rate = match format {
+            AudioFormat::Wav if query.rate.is_none() => {
+                let wav = tokio::fs::read(&wav_path).await.map_err(|e| RecordingError::Failed(e.to_string()))?;
+                return Ok((format, wav));
+            }
+            AudioFormat::Mp3 => {
+                return Err(RecordingError::Unsupported("mp3 encoding is not available in this build; use format=ogg or flac"));
+            }
+            AudioFormat::Ogg if !cfg!(feature = "opus") => {
+                return Err(RecordingError::Unsupported("ogg needs a build with `--features opus`; use format=flac"));
+            }
+            // Opus is encoded from the 16 kHz input; players decode at 48 kHz
+            AudioFormat::Ogg => WAV_RATE,
+            _ => query.rate.unwrap_or(WAV_RATE),
+        };
+
+        let _busy = self.busy.lock().await;
+        let cache_path = format!("{}/{CACHE_DIR}/{id}.{rate}.{}", self.dir, format.extension());
+        if self.cache_bytes > 0 {
+            if let Some(data) = read_cached(&cache_path, wav_modified).await {
+                debug!(id, path = %cache_path, "transcode cache hit");
+                return Ok((format, data));
+            }
+        }
+
+        let kbps = self.opus_kbps;
+        let source = wav_path.clone();
+        let encoded = tokio::task
+            ::spawn_blocking(move || transcode(&std::fs::read(&source)?, format, rate, kbps)).await
+            .map_err(|e| RecordingError::Failed(e.to_string()))?
+            .map_err(|e| RecordingError::Failed(e.to_string()))?;
+        info!(id, format = format.extension(), rate, bytes = encoded.len(), "🎼 recording transcoded");
+
+        if self.cache_bytes > 0 {
+            self.store(&wav_path, &cache_path, &encoded).await;
+        }
+        Ok((format, encoded))
+    }
+
+    /// Write a transcode to the cache, list it under the recording's
+    /// device and evict past the budget.
+    async fn store(&self, wav_path: &str, cache_path: &str, data: &[u8]) {
+        let part = format!("{cache_path}.part");
+        let written = async {
+            tokio::fs::create_dir_all(format!("{}/{CACHE_DIR}", self.dir)).await?;
+            tokio::fs::write(&part, data).await?;
+            tokio::fs::rename(&part, cache_path).await
+        }.await;
+        if let Err(e) = written {
+            warn!(path = %cache_path, error = %e, "failed to cache transcode");
+            return;
+        }
+        if let Some(device) = self.files.device_of(wav_path) {
+            if !self.files.files(&device).iter().any(|p| p == cache_path) {
+                self.files.record(&device, cache_path);
+            }
+        }
+        let dir = format!("{}/{CACHE_DIR}", self.dir);
+        let budget = self.cache_bytes;
+        let evicted = tokio::task::spawn_blocking(move || evict(Path::new(&dir), budget)).await.unwrap_or(0);
+        if evicted > 0 {
+            debug!(evicted, "evicted cached transcodes");
+        }
+    }
+}
+
+/// A cached transcode newer than its WAV; serving it marks it recently
+/// used.
+async fn read_cached(path: &str, wav_modified: SystemTime) -> Option<Vec<u8>> {
+    let modified = tokio::fs::metadata(path).await.ok()?.modified().ok()?;
+    if modified < wav_modified {
+        return None;
+    }
+    let data = tokio::fs::read(path).await.ok()?;
+    if let Ok(file) = std::fs::File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+    Some(data)
+}
+
+/// Delete the least recently used files in `dir` until it fits `budget`
+/// bytes; returns how many were deleted.
+fn evict(dir: &Path, budget: u64) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut files: Vec<(SystemTime, u64, std::path::PathBuf)> = entries
+        .flatten()
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            (meta.is_file() && !e.path().to_string_lossy().ends_with(".part")).then(|| (
+                meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                meta.len(),
+                e.path(),
+            ))
+        })
+        .collect();
+    let mut total: u64 = files
+        .iter()
+        .map(|(_, len, _)| len)
+        .sum();
+    files.sort();
+    let mut evicted = 0;
+    for (_, len, path) in files {
+        if total <= budget {
+            break;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            total -= len;
+            evicted += 1;
+        }
+    }
+    evicted
+}
+
+/// Encode a stored session WAV as mono `format` at `rate` Hz.
+fn transcode(wav: &[u8], format: AudioFormat, rate: u32, opus_kbps: u32) -> anyhow::Result<Vec<u8>> {
+    if wav.len() < WAV_HEADER_LEN || &wav[0..4] != b"RIFF" {
+        anyhow::bail!("not a session WAV");
+    }
+    let channels = u16::from_le_bytes([wav[22], wav[23]]).max(1);
+    let mono = Downmix::default().apply(&wav[WAV_HEADER_LEN..], channels as usize);
+    let pcm = if rate == WAV_RATE { mono.into_owned() } else { resample(&mono, WAV_RATE as u64, rate as u64) };
+    match format {
+        AudioFormat::Wav => {
+            let mut out = Vec::with_capacity(WAV_HEADER_LEN + pcm.len());
+            out.extend_from_slice(&wav_header(pcm.len() as u32, rate));
+            out.extend_from_slice(&pcm);
+            Ok(out)
+        }
+        AudioFormat::Flac => {
+            let samples: Vec<i16> = pcm
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]))
+                .collect();
+            Ok(encode_flac(&samples, rate))
+        }
+        AudioFormat::Ogg => crate::monitor_audio::encode(&pcm, opus_kbps),
+        AudioFormat::Mp3 => anyhow::bail!("no MP3 encoder"),
+    }
+}
+
+/// 44-byte header of a mono s16 WAV at `rate` Hz.
+fn wav_header(data_len: u32, rate: u32) -> [u8; 44] {
+    let mut h = crate::wav_writer::wav_header(data_len, 1);
+    h[24..28].copy_from_slice(&rate.to_le_bytes());
+    h[28..32].copy_from_slice(&(rate * 2).to_le_bytes());
+    h
+}
+
+// ── FLAC (fixed predictors, Rice-coded residuals) ────────────────────
+
+/// Samples per FLAC frame.
+const FLAC_BLOCK: usize = 4096;
+
+/// A FLAC stream of mono 16-bit `samples` at `rate` Hz.  Each frame
+/// uses the best fixed predictor (order 0-2) with one Rice partition, or
+/// verbatim samples when that would be larger.  No MD5 (allowed by the
+/// format: decoders skip the check).
+pub fn encode_flac(samples: &[i16], rate: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(samples.len() + 64);
+    out.extend_from_slice(b"fLaC");
+
+    // STREAMINFO, the last (only) metadata block
+    let mut info = BitWriter::default();
+    info.put(1, 1);
+    info.put(0, 7);
+    info.put(34, 24);
+    info.put(FLAC_BLOCK as u64, 16);
+    info.put(FLAC_BLOCK as u64, 16);
+    info.put(0, 24); // min frame size: unknown
+    info.put(0, 24); // max frame size: unknown
+    info.put(rate as u64, 20);
+    info.put(0, 3); // channels - 1
+    info.put(15, 5); // bits per sample - 1
+    info.put(samples.len() as u64, 36);
+    info.put(0, 64); // MD5: unknown
+    info.put(0, 64);
+    out.extend_from_slice(&info.finish());
+
+    for (number, block) in samples.chunks(FLAC_BLOCK).enumerate() {
+        let mut frame = BitWriter::default();
+        frame.put(0x3ffe, 14); // sync code 0b11111111111110
+        frame.put(0, 1);
+        frame.put(0, 1); // fixed block size: frame numbers
+        frame.put(0b0111, 4); // block size: 16 bits at the end of the header
+        frame.put(0b0000, 4); // sample rate: from STREAMINFO
+        frame.put(0b0000, 4); // mono
+        frame.put(0b100, 3); // 16 bits per sample
+        frame.put(0, 1);
+        put_utf8(&mut frame, number as u64);
+        frame.put((block.len() - 1) as u64, 16);
+        let header = frame.bytes();
+        frame.put(crc8(&header) as u64, 8);
+        put_subframe(&mut frame, block);
+        let mut bytes = frame.finish();
+        let crc = crc16(&bytes);
+        bytes.extend_from_slice(&crc.to_be_bytes());
+        out.extend_from_slice(&bytes);
+    }
+    out
+}
+
+/// Residuals of the fixed predictor of `order` (the first `order`
+/// samples are warm-up, not predicted).
+fn fixed_residuals(block: &[i16], order: usize) -> Vec<i32> {
+    (order..block.len())
+        .map(|i| {
+            let x = |k: usize| block[i - k] as i32;
+            match order {
+                0 => x(0),
+                1 => x(0) - x(1),
+                _ => x(0) - 2 * x(1) + x(2),
+            }
+        })
+        .collect()
+}
+
+/// Rice parameter for `residuals` and the bits they'd take with it.
+fn rice_cost(residuals: &[i32]) -> (u32, u64) {
+    let folded = |r: i32| ((r << 1) ^ (r >> 31)) as u32 as u64;
+    let sum: u64 = residuals
+        .iter()
+        .map(|&r| folded(r))
+        .sum();
+    let mean = sum / (residuals.len().max(1) as u64);
+    let k = (64 - mean.leading_zeros()).saturating_sub(1).min(14);
+    let bits = residuals
+        .iter()
+        .map(|&r| (folded(r) >> k) + 1 + (k as u64))
+        .sum();
+    (k, bits)
+}
+
+fn put_subframe(w: &mut BitWriter, block: &[i16]) {
+    let verbatim_bits = (block.len() as u64) * 16;
+    let best = (0..=2usize)
+        .filter(|&order| order < block.len())
+        .map(|order| {
+            let residuals = fixed_residuals(block, order);
+            let (k, bits) = rice_cost(&residuals);
+            (bits + (order as u64) * 16 + 10, order, k, residuals)
+        })
+        .min_by_key(|(bits, ..)| *bits);
+    match best {
+        Some((bits, order, k, residuals)) if bits < verbatim_bits => {
+            w.put(0, 1);
+            w.put(0b001000 | (order as u64), 6); // FIXED
+            w.put(0, 1); // no wasted bits
+            for &s in &block[..order] {
+                w.put((s as u16) as u64, 16);
+            }
+            w.put(0b00, 2); // Rice, 4-bit parameters
+            w.put(0, 4); // partition order 0
+            w.put(k as u64, 4);
+            for r in residuals {
+                let u = ((r << 1) ^ (r >> 31)) as u32 as u64;
+                let q = u >> k;
+                for _ in 0..q {
+                    w.put(0, 1);
+                }
+                w.put(1, 1);
+                w.put(u & ((1u64 << k) - 1), k);
+            }
+        }
+        _ => {
+            w.put(0, 1);
+            w.put(0b000001, 6); // VERBATIM
+            w.put(0, 1);
+            for &s in block {
+                w.put((s as u16) as u64, 16);
+            }
+        }
+    }
+}
+
+/// Frame number in FLAC's extended UTF-8 coding.
+fn put_utf8(w: &mut BitWriter, n: u64) {
+    if n < 0x80 {
+        w.put(n, 8);
+        return;
+    }
+    let extra = match n {
+        0x80..0x800 => 1,
+        0x800..0x1_0000 => 2,
+        0x1_0000..0x20_0000 => 3,
+        0x20_0000..0x400_0000 => 4,
+        _ => 5,
+    };
+    let lead_mask = (0xff00u64 >> (extra + 1)) & 0xff;
+    w.put(lead_mask | (n >> (6 * extra)), 8);
+    for i in (0..extra).rev() {
+        w.put(0x80 | ((n >> (6 * i)) & 0x3f), 8);
+    }
+}
+
+/// CRC-8, polynomial 0x07, zero init (frame headers).
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// CRC-16, polynomial 0x8005, zero init (whole frames).
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// MSB-first bit writer.
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    /// Append the low `n` (≤ 32) bits of `value`.
+    fn put(&mut self, value: u64, n: u32) {
+        if n > 32 {
+            self.put(value >> 32, n - 32);
+            self.put(value & 0xffff_ffff, 32);
+            return;
+        }
+        if n == 0 {
+            return;
+        }
+        self.acc = (self.acc << n) | (value & ((1u64 << n) - 1));
+        self.bits += n;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.out.push((self.acc >> self.bits) as u8);
+        }
+        self.acc &= (1u64 << self.bits) - 1;
+    }
+
+    /// Whole bytes written so far.
+    fn bytes(&self) -> Vec<u8> {
+        self.out.clone()
+    }
+
+    /// Pad to a byte boundary with zeros and return the bytes.
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            let pad = 8 - self.bits;
+            self.put(0, pad);
+        }
+        self.out
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flac_stream_layout() {
+        // 1.5 frames of a slow ramp: fixed order 1-2 predicts it well
+        let samples: Vec<i16> = (0..6144).map(|i| ((i % 2000) as i16) - 1000).collect();
+        let flac = encode_flac(&samples, 8000);
+        assert_eq!(&flac[..4], b"fLaC");
+        assert_eq!(flac[4], 0x80, "last metadata block, STREAMINFO");
+        assert_eq!(&flac[5..8], &[0, 0, 34]);
+        let info = &flac[8..42];
+        let packed = u64::from_be_bytes(info[10..18].try_into().unwrap());
+        assert_eq!(packed >> 44, 8000, "sample rate");
+        assert_eq!((packed >> 36) & 0xff, 15, "mono, 16 bits");
+        assert_eq!(packed & 0xf_ffff_ffff, 6144, "total samples");
+        assert!(flac.len() < samples.len(), "compressed: {} bytes", flac.len());
+
+        // Two frames, each with a valid header CRC-8 and frame CRC-16
+        let frames = &flac[42..];
+        let second = (1..frames.len() - 1).find(|&i| frames[i] == 0xff && frames[i + 1] == 0xf8 && crc16(&frames[..i]) == 0).unwrap();
+        for (frame, number, len) in [(&frames[..second], 0u8, 4096u16), (&frames[second..], 1, 2048)] {
+            assert_eq!(frame[4], number);
+            assert_eq!(u16::from_be_bytes([frame[5], frame[6]]), len - 1);
+            assert_eq!(crc8(&frame[..7]), frame[7]);
+            assert_eq!(crc16(frame), 0);
+        }
+
+        let mut w = BitWriter::default();
+        put_utf8(&mut w, 0x7ff);
+        put_utf8(&mut w, 1172);
+        assert_eq!(w.finish(), [0xdf, 0xbf, 0xd2, 0x94]);
+    }
+
+    /// MSB-first bit reader for [`decode_flac`].
+    struct BitReader<'a> {
+        data: &'a [u8],
+        bit: usize,
+    }
+
+    impl BitReader<'_> {
+        fn bits(&mut self, n: u32) -> u64 {
+            (0..n).fold(0, |acc, _| {
+                let bit = (self.data[self.bit / 8] >> (7 - (self.bit % 8))) & 1;
+                self.bit += 1;
+                (acc << 1) | (bit as u64)
+            })
+        }
+
+        fn signed16(&mut self) -> i32 {
+            self.bits(16) as u16 as i16 as i32
+        }
+
+        fn unary(&mut self) -> u64 {
+            let mut zeros = 0;
+            while self.bits(1) == 0 {
+                zeros += 1;
+            }
+            zeros
+        }
+
+        fn byte_pos(&self) -> usize {
+            self.bit / 8
+        }
+    }
+
+    /// Decoder for the subset [`encode_flac`] writes (mono 16-bit,
+    /// 16-bit block sizes, VERBATIM / FIXED subframes, one Rice
+    /// partition), checking every frame number and CRC on the way.
+    fn decode_flac(flac: &[u8]) -> (u32, Vec<i16>) {
+        assert_eq!(&flac[..4], b"fLaC");
+        let mut r = BitReader {
+            data: flac,
+            bit: 8 * 8,
+        };
+        r.bits(32 + 48); // block sizes, frame sizes
+        let rate = r.bits(20) as u32;
+        assert_eq!(r.bits(3 + 5), 15, "mono, 16 bits");
+        let total = r.bits(36) as usize;
+        r.bit = 42 * 8;
+
+        let mut samples: Vec<i32> = Vec::new();
+        let mut number = 0;
+        while r.byte_pos() < flac.len() {
+            let start = r.byte_pos();
+            assert_eq!(r.bits(14), 0x3ffe, "sync at byte {start}");
+            assert_eq!(r.bits(2), 0, "reserved + fixed block size");
+            assert_eq!(r.bits(4), 0b0111, "16-bit block size");
+            r.bits(4 + 4 + 3 + 1);
+            // Frame number, extended UTF-8
+            let lead = r.bits(8);
+            let extra = (lead as u8).leading_ones().saturating_sub(1);
+            let mut n = lead & (0x7f >> extra.min(6));
+            for _ in 0..extra {
+                assert_eq!(r.bits(2), 0b10);
+                n = (n << 6) | r.bits(6);
+            }
+            assert_eq!(n, number);
+            let len = (r.bits(16) as usize) + 1;
+            let crc = r.bits(8) as u8;
+            assert_eq!(crc8(&flac[start..r.byte_pos() - 1]), crc);
+
+            assert_eq!(r.bits(1), 0, "subframe padding");
+            let kind = r.bits(6);
+            assert_eq!(r.bits(1), 0, "wasted bits");
+            let block_start = samples.len();
+            if kind == 0b000001 {
+                samples.extend((0..len).map(|_| r.signed16()));
+            } else {
+                assert_eq!(kind & 0b111000, 0b001000, "FIXED subframe");
+                let order = (kind & 0b111) as usize;
+                samples.extend((0..order).map(|_| r.signed16()));
+                assert_eq!(r.bits(2 + 4), 0, "Rice, partition order 0");
+                let k = r.bits(4) as u32;
+                for _ in order..len {
+                    let u = (r.unary() << k) | r.bits(k);
+                    let residual = ((u >> 1) as i64 ^ -((u & 1) as i64)) as i32;
+                    let x = |back: usize| samples[samples.len() - back];
+                    let predicted = match order {
+                        0 => 0,
+                        1 => x(1),
+                        _ => 2 * x(1) - x(2),
+                    };
+                    samples.push(predicted + residual);
+                }
+            }
+            assert_eq!(samples.len() - block_start, len);
+            r.bit = r.bit.div_ceil(8) * 8;
+            r.bits(16);
+            assert_eq!(
+                crc16(&flac[start..r.byte_pos()]),
+                0,
+                "frame {number} CRC-16"
+            );
+            number += 1;
+        }
+        assert_eq!(samples.len(), total);
+        (
+            rate,
+            samples
+                .into_iter()
+                .map(|s| i16::try_from(s).expect("16-bit sample"))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_flac_round_trips() {
+        let mut seed = 0x2545_f491u32;
+        let mut noise = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as i16
+        };
+        let signals: Vec<(&str, Vec<i16>)> = vec![
+            (
+                "ramp",
+                (0..10_000)
+                    .map(|i| ((i % 3000) as i16) * 7 - 10_000)
+                    .collect(),
+            ),
+            // Full-scale steps: order-2 residuals up to 4 x 32767
+            (
+                "steps",
+                (0..9000)
+                    .map(|i| if (i / 100) % 2 == 0 { 32767 } else { -32767 })
+                    .collect(),
+            ),
+            (
+                "extremes",
+                [i16::MIN, i16::MAX, i16::MIN, 0, i16::MAX, -1, 1].repeat(700),
+            ),
+            ("noise", (0..5000).map(|_| noise()).collect()),
+            (
+                "sine",
+                (0..4097)
+                    .map(|i| (((i as f32) * 0.05).sin() * 20_000.0) as i16)
+                    .collect(),
+            ),
+            // 130 frames: two-byte UTF-8 frame numbers from frame 128 on
+            ("silence", vec![0; 130 * FLAC_BLOCK + 1]),
+            ("one", vec![-5]),
+        ];
+        for (name, samples) in signals {
+            let (rate, decoded) = decode_flac(&encode_flac(&samples, 16_000));
+            assert_eq!(rate, 16_000);
+            assert!(decoded == samples, "{name}: decoded samples differ");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transcodes_are_cached_and_listed_for_the_device() {
+        let dir = std::env::temp_dir().join(format!("vad-recordings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir_str = dir.to_str().unwrap();
+        let wav_path = format!("{dir_str}/esp_10_0_0_7_20261016_150012.wav");
+        // 0.1 s of stereo: left 1000, right 3000 → mono 2000
+        let pcm: Vec<u8> = [1000i16, 3000].repeat(1600).iter().flat_map(|s| s.to_le_bytes()).collect();
+        let mut wav = crate::wav_writer::wav_header(pcm.len() as u32, 2).to_vec();
+        wav.extend_from_slice(&pcm);
+        std::fs::write(&wav_path, &wav).unwrap();
+        let files = DeviceFiles::load(dir_str);
+        files.record("aa:bb:cc:dd:ee:ff", &wav_path);
+        let recordings = Recordings::new(dir_str, 1, 0, files.clone());
+        let id = "esp_10_0_0_7_20261016_150012";
+
+        let query = |format, rate| TranscodeQuery { format: Some(format), rate };
+        let (_, original) = recordings.get(id, &TranscodeQuery::default()).await.unwrap();
+        assert_eq!(original, wav);
+        let (format, resampled) = recordings.get(id, &query(AudioFormat::Wav, Some(8000))).await.unwrap();
+        assert_eq!(format.content_type(), "audio/wav");
+        assert_eq!(resampled.len(), 44 + 800 * 2);
+        assert_eq!(&resampled[44..46], &2000i16.to_le_bytes());
+
+        let (_, flac) = recordings.get(id, &query(AudioFormat::Flac, None)).await.unwrap();
+        let cached = format!("{dir_str}/transcoded/{id}.16000.flac");
+        assert_eq!(std::fs::read(&cached).unwrap(), flac);
+        assert_eq!(recordings.get(id, &query(AudioFormat::Flac, None)).await.unwrap().1, flac);
+        assert!(files.files("aa:bb:cc:dd:ee:ff").contains(&cached));
+
+        for (bad, status) in [
+            (recordings.get("../etc/passwd", &TranscodeQuery::default()).await, "invalid"),
+            (recordings.get("esp_missing", &TranscodeQuery::default()).await, "unknown"),
+            (recordings.get(id, &query(AudioFormat::Flac, Some(96_000))).await, "rate"),
+            (recordings.get(id, &query(AudioFormat::Mp3, None)).await, "mp3"),
+        ] {
+            assert!(bad.unwrap_err().to_string().contains(status), "{status}");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
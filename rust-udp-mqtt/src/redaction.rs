@@ -0,0 +1,403 @@
+use crate::conversation::ConversationStore;
+use crate::recordings::{ RecordingError, Recordings };
+use serde::{ Deserialize, Serialize };
+use serde_json::{ json, Value };
+use std::collections::{ BTreeSet, HashMap };
+use std::io::{ Read, Seek, SeekFrom };
+use std::path::Path;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Partial redaction of session recordings
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Erasure (`device_data.rs`) is all or nothing; a parent may instead
+//  ask for one sentence to go.  `POST /recordings/{id}/redact` takes
+//  time ranges into the recording and / or transcript text to find:
+//
+//    {"ranges": [{"from_ms": 12000, "to_ms": 15500}],
+//     "transcripts": ["our address is"], "reason": "parent request"}
+//
+//  and, in place:
+//
+//    audio        the ranges are overwritten with silence in the WAV
+//                 (every channel; length and offsets unchanged)
+//    transcripts  user utterances containing a `transcripts` entry
+//                 (case-insensitive), or overlapping a range, read
+//                 "[redacted]" in the JSONL session log (marked
+//                 `"redacted": true`) and in the device's conversation
+//                 history; a `redaction` line is appended to the log
+//    copies       the Opus monitoring copy and cached transcodes are
+//                 deleted (they would still hold the audio)
+//
+//  An utterance's audio span is taken from the session log: the
+//  `speech_started` / `speech_stopped` OpenAI events of the item whose
+//  transcription precedes its `transcript` line, widened by
+//  `SPAN_PAD_MS` on both sides since those events arrive a little
+//  after the speech itself.  Matching text therefore needs
+//  `--session-log`; without a log only explicit ranges apply.
+//  Transcripts already forwarded to MQTT / webhooks, and the live
+//  OpenAI conversation, are out of reach.
+
+/// Replacement text of redacted utterances.
+pub const REDACTED: &str = "[redacted]";
+
+/// Margin around an utterance's detected speech.
+const SPAN_PAD_MS: u64 = 500;
+
+const WAV_HEADER_LEN: u64 = 44;
+
+/// Audio ms per byte: 16 kHz s16, times the channel count.
+const BYTES_PER_MS_PER_CHANNEL: u64 = 32;
+
+/// A range of a recording, in ms from its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MsRange {
+    pub from_ms: u64,
+    pub to_ms: u64,
+}
+
+/// Body of `POST /recordings/{id}/redact`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RedactRequest {
+    #[serde(default)]
+    pub ranges: Vec<MsRange>,
+    /// Text of user utterances to silence (case-insensitive substring)
+    #[serde(default)]
+    pub transcripts: Vec<String>,
+    /// Recorded in the session log
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// What a redaction changed.
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactionReport {
+    pub id: String,
+    /// Silenced audio (merged, clamped to the recording)
+    pub ranges: Vec<MsRange>,
+    /// Utterances redacted in the session log
+    pub transcripts_redacted: usize,
+    /// `transcripts` entries that matched no utterance with audio timing
+    pub unmatched: Vec<String>,
+    pub conversation_turns_redacted: usize,
+    pub monitor_copy_removed: bool,
+    pub cached_transcodes_removed: usize,
+}
+
+/// A user utterance in a session log.
+struct Utterance {
+    /// Index of its `transcript` line
+    line: usize,
+    text: String,
+    span: Option<MsRange>,
+}
+
+/// Redact recording `id` as `req` asks.
+pub async fn redact(
+    recordings: &Recordings,
+    conversations: &ConversationStore,
+    id: &str,
+    req: &RedactRequest
+) -> Result<RedactionReport, RecordingError> {
+    if req.ranges.is_empty() && req.transcripts.iter().all(|t| t.trim().is_empty()) {
+        return Err(RecordingError::Invalid("nothing to redact: give `ranges` and / or `transcripts`".into()));
+    }
+    if let Some(r) = req.ranges.iter().find(|r| r.from_ms >= r.to_ms) {
+        return Err(RecordingError::Invalid(format!("range {}..{} ms is empty", r.from_ms, r.to_ms)));
+    }
+    let wav_path = recordings.wav_path(id).await?;
+    let _busy = recordings.lock().await;
+
+    let log_path = session_log_path(&wav_path);
+    let mut lines: Vec<Value> = match &log_path {
+        Some(path) =>
+            std::fs
+                ::read_to_string(path)
+                .map_err(|e| RecordingError::Failed(format!("{path}: {e}")))?
+                .lines()
+                .filter_map(|l| serde_json::from_str(l).ok())
+                .collect(),
+        None => Vec::new(),
+    };
+    let utterances = utterances(&lines);
+
+    // Audio ranges: explicit ones plus the spans of matching utterances
+    let mut ranges = req.ranges.clone();
+    let mut redact_lines = BTreeSet::new();
+    let mut unmatched = Vec::new();
+    for needle in req.transcripts.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        let needle_lower = needle.to_lowercase();
+        let mut timed = false;
+        for u in utterances.iter().filter(|u| u.text.to_lowercase().contains(&needle_lower)) {
+            redact_lines.insert(u.line);
+            if let Some(span) = u.span {
+                ranges.push(MsRange { from_ms: span.from_ms.saturating_sub(SPAN_PAD_MS), to_ms: span.to_ms + SPAN_PAD_MS });
+                timed = true;
+            }
+        }
+        if !timed {
+            unmatched.push(needle.to_string());
+        }
+    }
+    for u in &utterances {
+        if u.span.is_some_and(|s| req.ranges.iter().any(|r| r.from_ms < s.to_ms && s.from_ms < r.to_ms)) {
+            redact_lines.insert(u.line);
+        }
+    }
+    let ranges = merge(ranges);
+
+    let path = wav_path.clone();
+    let silenced = tokio::task
+        ::spawn_blocking(move || silence(Path::new(&path), &ranges)).await
+        .map_err(|e| RecordingError::Failed(e.to_string()))?
+        .map_err(|e| RecordingError::Failed(format!("{wav_path}: {e}")))?;
+
+    // Transcripts: session log (rewritten in place, so a log still open
+    // for appending keeps writing to the same file) + conversation history
+    let texts: Vec<String> = redact_lines
+        .iter()
+        .filter_map(|&i| lines[i]["text"].as_str().map(str::to_string))
+        .collect();
+    for &i in &redact_lines {
+        lines[i]["text"] = json!(REDACTED);
+        lines[i]["redacted"] = json!(true);
+    }
+    if let Some(ref log_path) = log_path {
+        lines.push(
+            json!({
+                "t_ms": chrono::Utc::now().timestamp_millis(),
+                "event": "redaction",
+                "ranges": silenced,
+                "transcripts": redact_lines.len(),
+                "reason": req.reason,
+            })
+        );
+        let text: String = lines
+            .iter()
+            .map(|l| format!("{l}\n"))
+            .collect();
+        std::fs::write(log_path, text).map_err(|e| RecordingError::Failed(format!("{log_path}: {e}")))?;
+    }
+    let device = lines
+        .iter()
+        .find_map(|l| (l["cmd"] == "session_start").then(|| l["device"].as_str()).flatten())
+        .map(str::to_string);
+    let conversation_turns_redacted = match device {
+        Some(ref device) if !texts.is_empty() => conversations.redact(device, &texts, REDACTED),
+        _ => 0,
+    };
+
+    // Derived copies still hold the audio
+    let monitor = Path::new(&wav_path).with_extension("opus");
+    let monitor_copy_removed = std::fs::remove_file(&monitor).is_ok();
+    let cached_transcodes_removed = recordings.forget_cached(id);
+
+    let report = RedactionReport {
+        id: id.to_string(),
+        ranges: silenced,
+        transcripts_redacted: redact_lines.len(),
+        unmatched,
+        conversation_turns_redacted,
+        monitor_copy_removed,
+        cached_transcodes_removed,
+    };
+    info!(
+        id,
+        ranges = report.ranges.len(),
+        transcripts = report.transcripts_redacted,
+        turns = report.conversation_turns_redacted,
+        reason = %req.reason,
+        "✂️ recording redacted"
+    );
+    if log_path.is_none() && !req.transcripts.is_empty() {
+        warn!(id, "no session log for this recording — transcript matches need --session-log");
+    }
+    Ok(report)
+}
+
+/// The JSONL session log written with the WAV at `wav_path`.  Both are
+/// named after the session start, but each reads the clock itself, so
+/// the log may be a second or two off.  A `-N` suffix (same-second
+/// sessions, see `wav_writer.rs`) is ignored.
+fn session_log_path(wav_path: &str) -> Option<String> {
+    let stem = wav_path.strip_suffix(".wav")?;
+    let stem = match stem.rsplit_once('-') {
+        Some((stem, n)) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => stem,
+        _ => stem,
+    };
+    let (prefix, ts) = stem.split_at(stem.len().checked_sub(15)?);
+    let ts = chrono::NaiveDateTime::parse_from_str(ts, "%Y%m%d_%H%M%S").ok()?;
+    [0i64, -1, 1, -2, 2]
+        .into_iter()
+        .map(|d| format!("{prefix}{}.jsonl", (ts + chrono::Duration::seconds(d)).format("%Y%m%d_%H%M%S")))
+        .find(|p| Path::new(p).is_file())
+}
+
+/// User utterances of a session log, with the audio span of each where
+/// the OpenAI events give one.
+fn utterances(lines: &[Value]) -> Vec<Utterance> {
+    let mut spans: HashMap<&str, (Option<u64>, Option<u64>)> = HashMap::new();
+    let mut transcribed: Option<&str> = None;
+    let mut out = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let elapsed = line["elapsed_ms"].as_u64();
+        match (line["event"].as_str(), line["type"].as_str(), line["item_id"].as_str()) {
+            (Some("openai"), Some("input_audio_buffer.speech_started"), Some(item)) => {
+                spans.entry(item).or_default().0 = elapsed;
+            }
+            (Some("openai"), Some("input_audio_buffer.speech_stopped"), Some(item)) => {
+                spans.entry(item).or_default().1 = elapsed;
+            }
+            (Some("openai"), Some("conversation.item.input_audio_transcription.completed"), Some(item)) => {
+                transcribed = Some(item);
+            }
+            (Some("transcript"), ..) if line["role"] == "user" => {
+                let span = transcribed
+                    .take()
+                    .and_then(|item| spans.get(item))
+                    .and_then(|&(from, to)| Some(MsRange { from_ms: from?, to_ms: to? }))
+                    .filter(|s| s.from_ms < s.to_ms);
+                if let Some(text) = line["text"].as_str() {
+                    out.push(Utterance { line: i, text: text.to_string(), span });
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Sort and merge overlapping or touching ranges.
+fn merge(mut ranges: Vec<MsRange>) -> Vec<MsRange> {
+    ranges.sort();
+    let mut out: Vec<MsRange> = Vec::with_capacity(ranges.len());
+    for r in ranges {
+        match out.last_mut() {
+            Some(last) if r.from_ms <= last.to_ms => {
+                last.to_ms = last.to_ms.max(r.to_ms);
+            }
+            _ => out.push(r),
+        }
+    }
+    out
+}
+
+/// Overwrite `ranges` of the WAV at `path` with silence; returns them
+/// clamped to its length.
+fn silence(path: &Path, ranges: &[MsRange]) -> std::io::Result<Vec<MsRange>> {
+    let mut file = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+    let mut header = [0u8; WAV_HEADER_LEN as usize];
+    file.read_exact(&mut header)?;
+    if &header[0..4] != b"RIFF" {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "not a session WAV"));
+    }
+    let channels = u16::from_le_bytes([header[22], header[23]]).max(1) as u64;
+    let per_ms = BYTES_PER_MS_PER_CHANNEL * channels;
+    let data_len = file.metadata()?.len().saturating_sub(WAV_HEADER_LEN);
+    let mut silenced = Vec::new();
+    for r in ranges {
+        let from = (r.from_ms * per_ms).min(data_len);
+        let to = (r.to_ms * per_ms).min(data_len);
+        if from >= to {
+            continue;
+        }
+        file.seek(SeekFrom::Start(WAV_HEADER_LEN + from))?;
+        std::io::copy(&mut std::io::repeat(0).take(to - from), &mut file)?;
+        silenced.push(MsRange { from_ms: r.from_ms, to_ms: to.div_ceil(per_ms) });
+    }
+    file.sync_all()?;
+    Ok(silenced)
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::Role;
+    use crate::device_data::DeviceFiles;
+
+    #[test]
+    fn test_session_log_of_suffixed_wav() {
+        let dir = std::env::temp_dir().join(format!("vad-redaction-log-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("esp_10_0_0_7_20261016_150012.jsonl");
+        std::fs::write(&log, b"{}\n").unwrap();
+        let wav = |name: &str| session_log_path(dir.join(name).to_str().unwrap());
+        let found = [wav("esp_10_0_0_7_20261016_150012.wav"), wav("esp_10_0_0_7_20261016_150012-2.wav")];
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(found, [Some(log.to_str().unwrap().to_string()), Some(log.to_str().unwrap().to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_redacts_ranges_and_matched_utterances() {
+        let dir = std::env::temp_dir().join(format!("vad-redaction-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("transcoded")).unwrap();
+        let dir_str = dir.to_str().unwrap();
+        let id = "esp_10_0_0_7_20261016_150012";
+
+        // 3 s of mono audio at 1000, a monitoring copy and a cached transcode
+        let mut wav = crate::wav_writer::wav_header(96_000, 1).to_vec();
+        wav.extend(1000i16.to_le_bytes().repeat(48_000));
+        std::fs::write(dir.join(format!("{id}.wav")), &wav).unwrap();
+        std::fs::write(dir.join(format!("{id}.opus")), b"OggS").unwrap();
+        std::fs::write(dir.join(format!("transcoded/{id}.16000.flac")), b"fLaC").unwrap();
+
+        // Session log a second later than the WAV name
+        let log = [
+            json!({"elapsed_ms":0,"event":"control","cmd":"session_start","device":"aa:bb:cc:dd:ee:ff"}),
+            json!({"elapsed_ms":400,"event":"openai","type":"input_audio_buffer.speech_started","item_id":"i1"}),
+            json!({"elapsed_ms":900,"event":"openai","type":"input_audio_buffer.speech_stopped","item_id":"i1"}),
+            json!({"elapsed_ms":1000,"event":"openai","type":"conversation.item.input_audio_transcription.completed","item_id":"i1"}),
+            json!({"elapsed_ms":1000,"event":"transcript","role":"user","text":"Our address is 12 Elm Street"}),
+            json!({"elapsed_ms":1500,"event":"transcript","role":"assistant","text":"Thanks!"}),
+            json!({"elapsed_ms":2400,"event":"openai","type":"input_audio_buffer.speech_started","item_id":"i2"}),
+            json!({"elapsed_ms":2600,"event":"openai","type":"input_audio_buffer.speech_stopped","item_id":"i2"}),
+            json!({"elapsed_ms":2700,"event":"openai","type":"conversation.item.input_audio_transcription.completed","item_id":"i2"}),
+            json!({"elapsed_ms":2700,"event":"transcript","role":"user","text":"I like trains"}),
+        ];
+        let log_path = dir.join("esp_10_0_0_7_20261016_150013.jsonl");
+        std::fs::write(&log_path, log.iter().map(|l| format!("{l}\n")).collect::<String>()).unwrap();
+
+        let conversations = ConversationStore::new(8);
+        conversations.activate("aa:bb:cc:dd:ee:ff");
+        conversations.record(Role::User, "Our address is 12 Elm Street", None);
+        conversations.record(Role::Assistant, "Thanks!", None);
+
+        let recordings = Recordings::new(dir_str, 16, 0, DeviceFiles::default());
+        let req = RedactRequest {
+            ranges: vec![MsRange { from_ms: 2500, to_ms: 9000 }],
+            transcripts: vec!["OUR ADDRESS".into(), "pizza".into()],
+            reason: "parent request".into(),
+        };
+        let report = redact(&recordings, &conversations, id, &req).await.unwrap();
+        assert_eq!(report.ranges, [MsRange { from_ms: 0, to_ms: 1400 }, MsRange { from_ms: 2500, to_ms: 3000 }]);
+        assert_eq!((report.transcripts_redacted, report.conversation_turns_redacted), (2, 1));
+        assert_eq!(report.unmatched, ["pizza"]);
+        assert!(report.monitor_copy_removed);
+        assert_eq!(report.cached_transcodes_removed, 1);
+
+        let wav = std::fs::read(dir.join(format!("{id}.wav"))).unwrap();
+        let sample = |ms: usize| i16::from_le_bytes([wav[44 + ms * 32], wav[45 + ms * 32]]);
+        assert_eq!((sample(0), sample(1399), sample(1400), sample(2499), sample(2500), sample(2999)), (0, 0, 1000, 1000, 0, 0));
+
+        let log: Vec<Value> = std::fs
+            ::read_to_string(&log_path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!((log[4]["text"].as_str(), log[4]["redacted"].as_bool()), (Some(REDACTED), Some(true)));
+        assert_eq!(log[5]["text"], "Thanks!");
+        assert_eq!(log[9]["text"], REDACTED, "overlaps the explicit range");
+        assert_eq!((log[10]["event"].as_str(), log[10]["reason"].as_str()), (Some("redaction"), Some("parent request")));
+        assert_eq!(conversations.history("aa:bb:cc:dd:ee:ff")[0].text, REDACTED);
+
+        let empty = RedactRequest::default();
+        assert!(matches!(redact(&recordings, &conversations, id, &empty).await, Err(RecordingError::Invalid(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+//! `replay` subcommand — play a recorded sensor-vector file.
+//!
+//! Input is the same CSV / JSONL layout `calibrate` reads (V/A/D label
+//! columns are optional and ignored).
+//!
+//! * With `--target host:port`, rows are sent to a running bridge as
+//!   sensor packets at `--rate` packets/s, preserving file order.
+//! * Without a target, rows are run offline through the same smoother +
+//!   emotional VAD the bridge uses and each result is printed to stdout as
+//!   one JSON object per line — handy for diffing weight files.  Offline
+//!   runs use a `SimClock` advanced by 1/`--rate` per row, so smoother
+//!   timing matches a live run at that rate while finishing instantly,
+//!   and the output is identical on every run.
+
+use crate::calibrate::CHANNEL_NAMES;
+use crate::clock::{ self, SimClock, Ticker };
+use crate::config::ReplayArgs;
+use crate::pcm::SampleFormat;
+use crate::sensor::{ SensorPacket, SensorVector, DATA_TYPE_SENSOR_VECTOR, SENSOR_VECTOR_LEN };
+use crate::sensor_smoother::{ SensorSmoother, DEFAULT_RESET_GAP };
+use crate::stats::Stats;
+use crate::vad::{ self, EmotionEngine, EmotionEngineKind };
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+/// One recorded row (labels, if present, are ignored).
+#[derive(Debug, Clone, Deserialize)]
+struct Row {
+    #[serde(default)]
+    sensor_id: u32,
+    sensors: [f32; SENSOR_VECTOR_LEN],
+}
+
+/// Entry point for `vad-sensor-bridge replay`.
+pub async fn run(args: &ReplayArgs) -> anyhow::Result<()> {
+    let rows = load_rows(&args.input)?;
+    info!(input = %args.input, rows = rows.len(), "⏯️  replaying recording");
+
+    match &args.target {
+        Some(target) => send(&rows, target, args.rate).await,
+        None => evaluate(&rows, args).await,
+    }
+}
+
+async fn send(rows: &[Row], target: &str, rate: f64) -> anyhow::Result<()> {
+    if rate <= 0.0 {
+        anyhow::bail!("--rate must be > 0");
+    }
+    let socket = tokio::net::UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect(target).await?;
+
+    let mut tick = Ticker::new(clock::system(), Duration::from_secs_f64(1.0 / rate));
+    let mut seqs: HashMap<u32, u64> = HashMap::new();
+    for row in rows {
+        tick.tick().await;
+        let seq = seqs.entry(row.sensor_id).or_insert(0);
+        socket.send(&packet(row, *seq).to_binary()).await?;
+        *seq += 1;
+    }
+
+    info!(sent = rows.len(), target = %target, "✅ replay finished");
+    Ok(())
+}
+
+async fn evaluate(rows: &[Row], args: &ReplayArgs) -> anyhow::Result<()> {
+    if args.rate <= 0.0 {
+        anyhow::bail!("--rate must be > 0");
+    }
+    let engine = EmotionEngine::from_config(EmotionEngineKind::Linear, "", &args.emotion_weights, &Stats::new())?;
+    let sim = SimClock::starting_at(std::time::UNIX_EPOCH);
+    let smoother = SensorSmoother::with_clock(DEFAULT_RESET_GAP, Arc::new(sim.clone()));
+    let mut tick = Ticker::new(Arc::new(sim.clone()), Duration::from_secs_f64(1.0 / args.rate));
+    let window = crate::audio_window::AudioWindow::new(Duration::ZERO);
+    let mut seqs: HashMap<u32, u64> = HashMap::new();
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for row in rows {
+        tick.tick().await;
+        let seq = seqs.entry(row.sensor_id).or_insert(0);
+        let r = vad::process_packet_with(
+            &packet(row, *seq),
+            args.persona,
+            &smoother,
+            &window,
+            &engine,
+            vad::DEFAULT_THRESHOLDS
+        );
+        *seq += 1;
+        writeln!(
+            out,
+            "{}",
+            serde_json::json!({
+                "t_ms": sim.elapsed().as_millis() as u64,
+                "sensor_id": r.sensor_id,
+                "seq": r.seq,
+                "is_active": r.is_active,
+                "valence": r.valence,
+                "arousal": r.arousal,
+                "dominance": r.dominance,
+            })
+        )?;
+    }
+    Ok(())
+}
+
+fn packet(row: &Row, seq: u64) -> SensorPacket {
+    SensorPacket {
+        sensor_id: row.sensor_id,
+        timestamp_us: 0,
+        data_type: DATA_TYPE_SENSOR_VECTOR,
+        sample_format: SampleFormat::S16,
+        seq,
+        payload: SensorVector::from_array(&row.sensors).to_payload(),
+        tenant: Default::default(),
+        ingested_at: None,
+        trace: None,
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Loading
+// ─────────────────────────────────────────────────────────────────────
+
+fn load_rows(path: &str) -> anyhow::Result<Vec<Row>> {
+    let text = std::fs
+        ::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read {path}: {e}"))?;
+    if path.ends_with(".csv") {
+        parse_csv(&text)
+    } else {
+        text.lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(i, l)| {
+                serde_json::from_str(l).map_err(|e| anyhow::anyhow!("line {}: {e}", i + 1))
+            })
+            .collect()
+    }
+}
+
+fn parse_csv(text: &str) -> anyhow::Result<Vec<Row>> {
+    let mut lines = text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty());
+    let (_, header) = lines.next().ok_or_else(|| anyhow::anyhow!("empty CSV"))?;
+    let cols: Vec<&str> = header
+        .split(',')
+        .map(|c| c.trim())
+        .collect();
+    let col = |name: &str| cols.iter().position(|c| *c == name);
+
+    let mut channel_idx = [0usize; SENSOR_VECTOR_LEN];
+    for (i, name) in CHANNEL_NAMES.iter().enumerate() {
+        channel_idx[i] = col(name).ok_or_else(|| anyhow::anyhow!("CSV missing column `{name}`"))?;
+    }
+    let id_idx = col("sensor_id");
+
+    let mut out = Vec::new();
+    for (i, line) in lines {
+        let fields: Vec<&str> = line
+            .split(',')
+            .map(|f| f.trim())
+            .collect();
+        let num = |idx: usize| -> anyhow::Result<f32> {
+            fields
+                .get(idx)
+                .and_then(|f| f.parse().ok())
+                .ok_or_else(|| anyhow::anyhow!("line {}: bad value in column {}", i + 1, cols[idx]))
+        };
+        let mut sensors = [0.0f32; SENSOR_VECTOR_LEN];
+        for (c, &idx) in channel_idx.iter().enumerate() {
+            sensors[c] = num(idx)?;
+        }
+        out.push(Row {
+            sensor_id: id_idx.map(|idx| num(idx).map(|v| v as u32)).transpose()?.unwrap_or(0),
+            sensors,
+        });
+    }
+    Ok(out)
+}
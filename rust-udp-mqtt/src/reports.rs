@@ -0,0 +1,476 @@
+use crate::clock::{ self, SharedClock };
+use crate::config::Config;
+use crate::emotion_history::{ EmotionHistory, EmotionQuery };
+use crate::session_log::SessionSummary;
+use crate::transport_udp::PromptMode;
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Daily reports
+// ─────────────────────────────────────────────────────────────────────
+//
+//  One summary per tenant and UTC day, accumulated as sessions end and
+//  OpenAI events arrive (see `session_log.rs`):
+//
+//    sessions     ESP sessions ended that day, distinct devices, talk
+//                 time (session start → end) and OpenAI responses
+//    errors       OpenAI `error` events by code
+//    emotions     share of emotional VAD samples per label over the day
+//                 (from the emotion history, `--emotion-history-hours`)
+//    openai       tokens from every `response.done` usage, by text /
+//                 audio and input / output, priced with
+//                 `--openai-*-price` (USD per 1M tokens; cached input
+//                 tokens are priced like uncached ones)
+//
+//  Totals are also broken down per device.  Shortly after each UTC
+//  midnight the finished day is written to `--report-dir` as
+//  `daily_[<tenant>_]<date>.json` plus a `.csv` (one row per device and
+//  a `total` row) and POSTed to every `--report-webhook`.
+//  `GET /reports/daily?date=YYYY-MM-DD` serves today's running totals,
+//  or a finished day from `--report-dir`.  Totals are in memory until
+//  written: a restart loses the day so far.
+
+/// OpenAI token counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct TokenCounts {
+    pub text_in: u64,
+    pub audio_in: u64,
+    /// Input tokens served from the prompt cache (included above)
+    pub cached_in: u64,
+    pub text_out: u64,
+    pub audio_out: u64,
+}
+
+impl TokenCounts {
+    /// Counts of a `response.done` usage object.
+    fn from_usage(usage: &Value) -> Self {
+        let n = |v: &Value| v.as_u64().unwrap_or(0);
+        let (input, output) = (&usage["input_token_details"], &usage["output_token_details"]);
+        Self {
+            text_in: n(&input["text_tokens"]),
+            audio_in: n(&input["audio_tokens"]),
+            cached_in: n(&input["cached_tokens"]),
+            text_out: n(&output["text_tokens"]),
+            audio_out: n(&output["audio_tokens"]),
+        }
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.text_in += other.text_in;
+        self.audio_in += other.audio_in;
+        self.cached_in += other.cached_in;
+        self.text_out += other.text_out;
+        self.audio_out += other.audio_out;
+    }
+}
+
+/// OpenAI prices, USD per 1M tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenPrices {
+    pub text_in: f64,
+    pub text_out: f64,
+    pub audio_in: f64,
+    pub audio_out: f64,
+}
+
+impl TokenPrices {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            text_in: config.openai_text_input_price,
+            text_out: config.openai_text_output_price,
+            audio_in: config.openai_audio_input_price,
+            audio_out: config.openai_audio_output_price,
+        }
+    }
+
+    pub fn cost(&self, tokens: &TokenCounts) -> f64 {
+        ((tokens.text_in as f64) * self.text_in +
+            (tokens.text_out as f64) * self.text_out +
+            (tokens.audio_in as f64) * self.audio_in +
+            (tokens.audio_out as f64) * self.audio_out) /
+            1e6
+    }
+}
+
+/// One device's (or the whole day's) totals.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Totals {
+    pub sessions: u64,
+    pub talk_secs: f64,
+    pub responses: u64,
+    pub errors: u64,
+    pub tokens: TokenCounts,
+    pub cost_usd: f64,
+}
+
+impl Totals {
+    fn add(&mut self, other: &Self) {
+        self.sessions += other.sessions;
+        self.talk_secs += other.talk_secs;
+        self.responses += other.responses;
+        self.errors += other.errors;
+        self.tokens.add(&other.tokens);
+    }
+}
+
+/// Summary of one day.
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyReport {
+    /// UTC day, `YYYY-MM-DD`
+    pub date: String,
+    /// Tenant of the bridge instance (omitted in single-tenant mode)
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tenant: String,
+    pub instance: String,
+    /// Devices with a session or OpenAI event that day
+    pub devices: usize,
+    #[serde(flatten)]
+    pub totals: Totals,
+    /// OpenAI errors by code
+    pub error_codes: BTreeMap<String, u64>,
+    /// Share of emotional samples per label
+    pub emotions: BTreeMap<PromptMode, f32>,
+    pub per_device: BTreeMap<String, Totals>,
+}
+
+#[derive(Default)]
+struct Day {
+    devices: BTreeMap<String, Totals>,
+    error_codes: BTreeMap<String, u64>,
+}
+
+/// Daily report accumulator.  Clone-friendly; a disabled handle (no
+/// `--report-dir`, no `--report-webhook`) ignores everything.
+#[derive(Clone)]
+pub struct DailyReports {
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    days: Mutex<BTreeMap<NaiveDate, Day>>,
+    prices: TokenPrices,
+    dir: Option<PathBuf>,
+    webhooks: Vec<String>,
+    tenant: String,
+    instance: String,
+    emotions: EmotionHistory,
+    clock: SharedClock,
+}
+
+impl DailyReports {
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    pub fn new(
+        prices: TokenPrices,
+        dir: Option<&str>,
+        webhooks: Vec<String>,
+        tenant: &str,
+        instance: &str,
+        emotions: EmotionHistory,
+        clock: SharedClock
+    ) -> Self {
+        Self {
+            inner: Some(
+                Arc::new(Inner {
+                    days: Mutex::default(),
+                    prices,
+                    dir: dir.map(PathBuf::from),
+                    webhooks,
+                    tenant: tenant.to_string(),
+                    instance: instance.to_string(),
+                    emotions,
+                    clock,
+                })
+            ),
+        }
+    }
+
+    /// Reports of `--report-dir` / `--report-webhook` (disabled if neither).
+    pub fn from_config(config: &Config, emotions: EmotionHistory, clock: SharedClock) -> Self {
+        if config.report_dir.is_empty() && config.report_webhook.is_empty() {
+            return Self::disabled();
+        }
+        info!(dir = %config.report_dir, webhooks = config.report_webhook.len(), "📊 daily reports enabled");
+        Self::new(
+            TokenPrices::from_config(config),
+            Some(config.report_dir.as_str()).filter(|d| !d.is_empty()),
+            config.report_webhook.clone(),
+            &config.tenant_id,
+            &config.resolved_instance_id(),
+            emotions,
+            clock
+        )
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// An ESP session ended.
+    pub fn session(&self, session: &SessionSummary) {
+        let talk_ms = session.ended_ms.map_or(0, |end| (end - session.started_ms).max(0));
+        self.update(&session.device, |totals, _| {
+            totals.sessions += 1;
+            totals.talk_secs += (talk_ms as f64) / 1000.0;
+            totals.responses += session.responses as u64;
+        });
+    }
+
+    /// An OpenAI server event for `device`'s session: usage of
+    /// `response.done` and `error`s are counted.
+    pub fn openai(&self, device: &str, event: &Value) {
+        match event["type"].as_str() {
+            Some("response.done") if !event["response"]["usage"].is_null() => {
+                let tokens = TokenCounts::from_usage(&event["response"]["usage"]);
+                self.update(device, |totals, _| totals.tokens.add(&tokens));
+            }
+            Some("error") => {
+                let error = &event["error"];
+                let code = error["code"].as_str().or(error["type"].as_str()).unwrap_or("unknown").to_string();
+                self.update(device, |totals, codes| {
+                    totals.errors += 1;
+                    *codes.entry(code).or_default() += 1;
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn update(&self, device: &str, f: impl FnOnce(&mut Totals, &mut BTreeMap<String, u64>)) {
+        let Some(ref inner) = self.inner else {
+            return;
+        };
+        let today = inner.today();
+        let mut days = inner.days.lock().unwrap_or_else(|e| e.into_inner());
+        let day = days.entry(today).or_default();
+        f(day.devices.entry(device.to_ascii_lowercase()).or_default(), &mut day.error_codes);
+    }
+
+    /// Report of `date` (default today) from memory, else from
+    /// `--report-dir`.
+    pub fn report(&self, date: Option<NaiveDate>) -> Option<Value> {
+        let inner = self.inner.as_ref()?;
+        let date = date.unwrap_or_else(|| inner.today());
+        let days = inner.days.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(day) = days.get(&date) {
+            return serde_json::to_value(inner.build(date, day)).ok();
+        }
+        drop(days);
+        if date == inner.today() {
+            return serde_json::to_value(inner.build(date, &Day::default())).ok();
+        }
+        let path = inner.path(date, "json")?;
+        serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+    }
+
+    /// Take the days before today out of memory as reports.
+    pub fn finish_days(&self) -> Vec<DailyReport> {
+        let Some(ref inner) = self.inner else {
+            return Vec::new();
+        };
+        let today = inner.today();
+        let mut days = inner.days.lock().unwrap_or_else(|e| e.into_inner());
+        let current = days.split_off(&today);
+        let finished = std::mem::replace(&mut *days, current);
+        drop(days);
+        finished
+            .iter()
+            .map(|(date, day)| inner.build(*date, day))
+            .collect()
+    }
+}
+
+impl Inner {
+    fn today(&self) -> NaiveDate {
+        let ms = clock::unix_millis(self.clock.as_ref()) as i64;
+        chrono::DateTime::from_timestamp_millis(ms).unwrap_or_default().date_naive()
+    }
+
+    fn build(&self, date: NaiveDate, day: &Day) -> DailyReport {
+        let mut totals = Totals::default();
+        let per_device: BTreeMap<String, Totals> = day.devices
+            .iter()
+            .map(|(device, t)| {
+                totals.add(t);
+                (device.clone(), Totals { cost_usd: self.prices.cost(&t.tokens), ..t.clone() })
+            })
+            .collect();
+        totals.cost_usd = self.prices.cost(&totals.tokens);
+
+        let start_ms = date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp_millis();
+        let query = EmotionQuery {
+            from: Some(start_ms.to_string()),
+            to: Some((start_ms + 86_400_000).to_string()),
+            resolution: Some("1h".into()),
+        };
+        let emotions = self.emotions
+            .query_combined(&self.emotions.devices(), &query)
+            .map(|t| t.labels)
+            .unwrap_or_default();
+
+        DailyReport {
+            date: date.to_string(),
+            tenant: self.tenant.clone(),
+            instance: self.instance.clone(),
+            devices: per_device.len(),
+            totals,
+            error_codes: day.error_codes.clone(),
+            emotions,
+            per_device,
+        }
+    }
+
+    fn path(&self, date: NaiveDate, ext: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        let name = match self.tenant.as_str() {
+            "" => format!("daily_{date}.{ext}"),
+            tenant => format!("daily_{tenant}_{date}.{ext}"),
+        };
+        Some(dir.join(name))
+    }
+
+    fn write(&self, report: &DailyReport, date: NaiveDate) -> anyhow::Result<()> {
+        let (Some(json_path), Some(csv_path)) = (self.path(date, "json"), self.path(date, "csv")) else {
+            return Ok(());
+        };
+        if let Some(dir) = json_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&json_path, serde_json::to_string_pretty(report)?)?;
+        std::fs::write(&csv_path, to_csv(report))?;
+        info!(path = %json_path.display(), sessions = report.totals.sessions, "📊 daily report written");
+        Ok(())
+    }
+}
+
+/// One row per device plus a `total` row.
+pub fn to_csv(report: &DailyReport) -> String {
+    let mut csv = String::from(
+        "date,tenant,device,sessions,talk_secs,responses,errors,text_in,audio_in,text_out,audio_out,cost_usd\n"
+    );
+    let rows = report.per_device
+        .iter()
+        .map(|(device, t)| (device.as_str(), t))
+        .chain(std::iter::once(("total", &report.totals)));
+    for (device, t) in rows {
+        csv.push_str(
+            &format!(
+                "{},{},{},{},{:.1},{},{},{},{},{},{},{:.4}\n",
+                report.date,
+                report.tenant,
+                device,
+                t.sessions,
+                t.talk_secs,
+                t.responses,
+                t.errors,
+                t.tokens.text_in,
+                t.tokens.audio_in,
+                t.tokens.text_out,
+                t.tokens.audio_out,
+                t.cost_usd
+            )
+        );
+    }
+    csv
+}
+
+/// Write and send each finished day shortly after UTC midnight.
+pub async fn run(reports: DailyReports) {
+    let Some(inner) = reports.inner.clone() else {
+        return;
+    };
+    let http = reqwest::Client::builder().timeout(Duration::from_secs(10)).build().unwrap_or_default();
+    loop {
+        let into_day = clock::unix_millis(inner.clock.as_ref()) % 86_400_000;
+        // A few seconds past midnight, so sessions ending on the dot land
+        inner.clock.sleep(Duration::from_millis(86_400_000 - into_day + 5_000)).await;
+        for report in reports.finish_days() {
+            let Ok(date) = report.date.parse::<NaiveDate>() else {
+                continue;
+            };
+            if let Err(e) = inner.write(&report, date) {
+                warn!(date = %report.date, error = %e, "failed to write daily report");
+            }
+            for url in &inner.webhooks {
+                match http.post(url).json(&report).send().await {
+                    Ok(resp) if resp.status().is_success() => {}
+                    Ok(resp) => warn!(url = %url, status = %resp.status(), "daily report webhook rejected"),
+                    Err(e) => warn!(url = %url, error = %e, "daily report webhook failed"),
+                }
+            }
+        }
+    }
+}
+
+/// Parse `?date=YYYY-MM-DD`.
+pub fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|e| format!("invalid date {s:?} (want YYYY-MM-DD): {e}"))
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimClock;
+    use serde_json::json;
+
+    #[test]
+    fn test_day_totals_cost_and_rollover() {
+        let sim = SimClock::starting_at(std::time::UNIX_EPOCH + Duration::from_secs(1_792_022_400)); // 2026-10-15 00:00 UTC
+        let clock: SharedClock = Arc::new(sim.clone());
+        let emotions = EmotionHistory::new(Duration::ZERO, clock.clone());
+        let prices = TokenPrices { text_in: 1.0, text_out: 2.0, audio_in: 10.0, audio_out: 20.0 };
+        let reports = DailyReports::new(prices, None, vec!["http://127.0.0.1:9/".into()], "acme", "edge-1", emotions, clock);
+
+        let session = SessionSummary {
+            id: 1,
+            trace: crate::trace::TraceId::next(),
+            addr: "10.0.0.7:5000".parse().unwrap(),
+            device: "AA:BB:CC:DD:EE:FF".into(),
+            started_ms: 0,
+            ended_ms: Some(90_000),
+            monitor: false,
+            openai_events: 4,
+            responses: 2,
+            speech: Default::default(),
+        };
+        reports.session(&session);
+        let usage = json!({
+            "input_token_details": { "text_tokens": 100_000, "audio_tokens": 200_000, "cached_tokens": 50_000 },
+            "output_token_details": { "text_tokens": 10_000, "audio_tokens": 50_000 }
+        });
+        reports.openai("aa:bb:cc:dd:ee:ff", &json!({ "type": "response.done", "response": { "usage": usage } }));
+        reports.openai("10.0.0.9:5000", &json!({ "type": "error", "error": { "type": "server_error", "code": "rate_limit_exceeded" } }));
+
+        let today = reports.report(None).unwrap();
+        assert_eq!(today["date"], "2026-10-15");
+        assert_eq!(today["devices"], 2);
+        assert_eq!(today["sessions"], 1);
+        assert_eq!(today["talk_secs"], 90.0);
+        assert_eq!(today["error_codes"]["rate_limit_exceeded"], 1);
+        // 0.1 + 0.02 + 2.0 + 1.0
+        assert!((today["cost_usd"].as_f64().unwrap() - 3.12).abs() < 1e-9);
+        assert!(reports.finish_days().is_empty(), "today is still running");
+
+        sim.advance(Duration::from_secs(86_400));
+        let finished = reports.finish_days();
+        assert_eq!(finished.len(), 1);
+        let csv = to_csv(&finished[0]);
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv.contains("2026-10-15,acme,aa:bb:cc:dd:ee:ff,1,90.0,2,0,100000,200000,10000,50000,3.1200\n"));
+        assert!(csv.ends_with("2026-10-15,acme,total,1,90.0,2,1,100000,200000,10000,50000,3.1200\n"));
+        assert_eq!(reports.report(None).unwrap()["sessions"], 0);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::config::Config;
+use sha2::{ Digest, Sha256 };
+use std::io::Write;
+use std::sync::Mutex;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Safety banner + instructions audit
+// ─────────────────────────────────────────────────────────────────────
+//
+//  `--safety-banner-file` holds the deployment's mandatory rules (e.g.
+//  kid-safety).  It is read once at startup and prepended to every set
+//  of instructions the OpenAI session sends — the initial
+//  `session.update`, reconnects, device wiring, persona and emotional
+//  prompt changes — so no template, persona or operator edit can drop
+//  it.  The only way to change it is to change the file and restart.
+//
+//  The file must be "locked":
+//
+//    * not writable by group or others (unix permissions), and
+//    * if `--safety-banner-sha256` is set, its SHA-256 must match
+//
+//  otherwise startup (and `validate`) fails.
+//
+//  Every change of the effective instructions is audited: a log line
+//  with its SHA-256 and, with `--instructions-audit-log`, a JSONL record
+//  `{timestamp_ms, source, sha256, len, instructions}`.  Recording
+//  policy changes and retention deletes (`recording_policy.rs`) are
+//  audited in the same file.
+
+/// Loaded banner plus the audit sink.
+#[derive(Default)]
+pub struct SafetyPolicy {
+    banner: String,
+    audit: Option<Mutex<std::fs::File>>,
+    /// Hash of the last audited instructions (skip no-op updates)
+    last: Mutex<Option<String>>,
+}
+
+impl SafetyPolicy {
+    /// Load `--safety-banner-file` (checking it is locked) and open the
+    /// audit log.
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        let banner = if config.safety_banner_file.is_empty() {
+            String::new()
+        } else {
+            read_locked_banner(&config.safety_banner_file, &config.safety_banner_sha256)?
+        };
+        let audit = if config.instructions_audit_log.is_empty() {
+            None
+        } else {
+            let file = std::fs::OpenOptions
+                ::new()
+                .create(true)
+                .append(true)
+                .open(&config.instructions_audit_log)
+                .map_err(|e| {
+                    anyhow::anyhow!("failed to open audit log {}: {e}", config.instructions_audit_log)
+                })?;
+            Some(Mutex::new(file))
+        };
+        if !banner.is_empty() {
+            info!(
+                file = %config.safety_banner_file,
+                sha256 = %sha256_hex(banner.as_bytes()),
+                "🛡️ safety banner loaded"
+            );
+        }
+        Ok(Self { banner, audit, last: Mutex::default() })
+    }
+
+    /// `instructions` with the banner in front (unchanged if there is no
+    /// banner or it is already there).
+    pub fn enforce(&self, instructions: &str) -> String {
+        if self.banner.is_empty() || instructions.starts_with(&self.banner) {
+            return instructions.to_string();
+        }
+        format!("{}\n\n{}", self.banner, instructions)
+    }
+
+    /// Record that `instructions` (already enforced) are now in effect.
+    /// Repeats of the previous instructions are not logged.
+    pub fn audit(&self, source: &str, instructions: &str) {
+        let hash = sha256_hex(instructions.as_bytes());
+        {
+            let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+            if last.as_deref() == Some(hash.as_str()) {
+                return;
+            }
+            *last = Some(hash.clone());
+        }
+        info!(source = source, sha256 = %hash, len = instructions.len(), "🛡️ instructions changed");
+        self.audit_event(
+            source,
+            serde_json::json!({
+                "sha256": hash,
+                "len": instructions.len(),
+                "instructions": instructions,
+            })
+        );
+    }
+
+    /// Append `{timestamp_ms, source, ..fields}` (`fields` a JSON object)
+    /// to the audit log, if there is one.
+    pub fn audit_event(&self, source: &str, fields: serde_json::Value) {
+        let Some(ref file) = self.audit else {
+            return;
+        };
+        let mut record =
+            serde_json::json!({
+            "timestamp_ms": std::time::SystemTime
+                ::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            "source": source,
+        });
+        if let (Some(record), serde_json::Value::Object(fields)) = (record.as_object_mut(), fields) {
+            record.extend(fields);
+        }
+        let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = writeln!(file, "{record}") {
+            warn!(error = %e, "failed to write audit record");
+        }
+    }
+}
+
+/// Read the banner, refusing files others can modify or whose hash
+/// does not match the pin.
+pub fn read_locked_banner(path: &str, pinned_sha256: &str) -> anyhow::Result<String> {
+    let meta = std::fs
+        ::metadata(path)
+        .map_err(|e| anyhow::anyhow!("failed to read safety banner {path}: {e}"))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = meta.permissions().mode();
+        if mode & 0o022 != 0 {
+            anyhow::bail!("safety banner {path} is group/world-writable (mode {:o}); chmod go-w it", mode & 0o777);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = meta;
+
+    let banner = std::fs::read_to_string(path)?.trim().to_string();
+    if banner.is_empty() {
+        anyhow::bail!("safety banner {path} is empty");
+    }
+    let actual = sha256_hex(banner.as_bytes());
+    if !pinned_sha256.is_empty() && !actual.eq_ignore_ascii_case(pinned_sha256.trim()) {
+        anyhow::bail!("safety banner {path} sha256 {actual} does not match --safety-banner-sha256");
+    }
+    Ok(banner)
+}
+
+/// Lower-case hex SHA-256.
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enforce_prepends_once() {
+        let policy = SafetyPolicy { banner: "Never be rude.".into(), ..Default::default() };
+        let once = policy.enforce("You are a robot.");
+        assert_eq!(once, "Never be rude.\n\nYou are a robot.");
+        assert_eq!(policy.enforce(&once), once);
+        assert_eq!(SafetyPolicy::default().enforce("x"), "x");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_banner_must_be_locked_and_match_pin() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = std::env::temp_dir().join(format!("vad-banner-{}.txt", std::process::id()));
+        std::fs::write(&path, "Be kind.\n").unwrap();
+        let p = path.to_str().unwrap();
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o666)).unwrap();
+        assert!(read_locked_banner(p, "").is_err());
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o444)).unwrap();
+        assert_eq!(read_locked_banner(p, "").unwrap(), "Be kind.");
+        let pin = sha256_hex(b"Be kind.");
+        assert!(read_locked_banner(p, &pin.to_uppercase()).is_ok());
+        assert!(read_locked_banner(p, &"0".repeat(64)).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
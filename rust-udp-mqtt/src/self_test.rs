@@ -0,0 +1,395 @@
+use crate::config::Config;
+use crate::speakers::SpeakerEngineKind;
+use crate::stats::StatsSinkKind;
+use crate::validate;
+use crate::vad::EmotionEngineKind;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+use tracing::{ error, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Startup self-test
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Before a socket bridge instance wires anything up it checks what it
+//  is about to depend on, logs one line per check and a summary:
+//
+//    ports          the UDP audio / sensor / test ports and the REST API
+//                   port can be bound
+//    save dir       `--audio-save-dir` exists (or can be created) and is
+//                   writable
+//    models         every configured model / weights file is a readable,
+//                   non-empty file
+//    OpenAI         with `--openai-realtime`: the key is accepted and can
+//                   use `--openai-model` (GET /v1/models/{model})
+//    MQTT brokers   every configured broker accepts a TCP connection
+//
+//  Every check but the brokers is critical.  The MQTT clients keep
+//  reconnecting in the background (and `/readyz` reports them), so an
+//  unreachable broker is a warning.  A critical failure keeps `/readyz`
+//  at 503 for the life of the process; with `--strict-startup` the
+//  bridge exits instead of starting partially.
+//
+//  Unlike `validate` this only probes the environment; flag ranges are
+//  rejected by the components themselves.
+
+/// Limit on each network probe.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Models endpoint the OpenAI key is checked against.
+const OPENAI_MODELS_URL: &str = "https://api.openai.com/v1/models";
+
+/// Outcome of one check.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub critical: bool,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+/// Readiness report of one instance (`/readyz`).  Clone-friendly.
+#[derive(Debug, Clone, Default)]
+pub struct SelfTestReport {
+    checks: Arc<Vec<CheckResult>>,
+}
+
+/// `/readyz` view of a [`SelfTestReport`].
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestSnapshot {
+    /// No critical check failed
+    pub ready: bool,
+    /// Failed checks (critical and warnings)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<CheckResult>,
+    pub passed: usize,
+}
+
+impl SelfTestReport {
+    pub fn checks(&self) -> &[CheckResult] {
+        &self.checks
+    }
+
+    /// Critical checks that failed.
+    pub fn critical_failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|c| c.critical && !c.ok)
+            .count()
+    }
+
+    pub fn ready(&self) -> bool {
+        self.critical_failures() == 0
+    }
+
+    pub fn snapshot(&self) -> SelfTestSnapshot {
+        let failed: Vec<CheckResult> = self.checks
+            .iter()
+            .filter(|c| !c.ok)
+            .cloned()
+            .collect();
+        SelfTestSnapshot { ready: self.ready(), passed: self.checks.len() - failed.len(), failed }
+    }
+
+    /// `--strict-startup`: fail when a critical check did.
+    pub fn enforce(&self) -> anyhow::Result<()> {
+        let failed: Vec<&str> = self.checks
+            .iter()
+            .filter(|c| c.critical && !c.ok)
+            .map(|c| c.name.as_str())
+            .collect();
+        if !failed.is_empty() {
+            anyhow::bail!("{} critical check(s) failed: {}", failed.len(), failed.join(", "));
+        }
+        Ok(())
+    }
+}
+
+/// Collects check results while the self-test runs.
+#[derive(Default)]
+struct Checks {
+    results: Vec<CheckResult>,
+}
+
+impl Checks {
+    fn record(&mut self, name: String, critical: bool, started: Instant, result: anyhow::Result<()>) {
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        match &result {
+            Ok(()) => info!(check = %name, elapsed_ms, "🩺 self-test passed"),
+            Err(e) if critical => error!(check = %name, error = %e, elapsed_ms, "🩺 self-test failed"),
+            Err(e) => warn!(check = %name, error = %e, elapsed_ms, "🩺 self-test failed (non-critical)"),
+        }
+        self.results.push(CheckResult {
+            name,
+            critical,
+            ok: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+            elapsed_ms,
+        });
+    }
+}
+
+/// Run every check that applies to `config`.  `udp` is false when a
+/// transport other than the UDP sockets feeds the instance.
+pub async fn run(config: &Config, udp: bool) -> SelfTestReport {
+    let started = Instant::now();
+    let mut checks = Checks::default();
+
+    // Ports
+    if udp {
+        for (name, port) in [
+            ("audio", config.audio_port),
+            ("sensor", config.sensor_port),
+            ("test", config.test_port),
+        ] {
+            let addr = format!("{}:{}", config.host, port);
+            let t = Instant::now();
+            // An inherited socket (systemd / handover) is bound already
+            let result = if config.sockets.is_inherited(&addr, true) {
+                Ok(())
+            } else {
+                std::net::UdpSocket
+                    ::bind(&addr)
+                    .map(|_| ())
+                    .map_err(Into::into)
+            };
+            checks.record(format!("UDP {name} port {addr}"), true, t, result);
+        }
+    }
+    let api_addr = format!("{}:{}", config.host, config.api_port);
+    let t = Instant::now();
+    let result = if config.sockets.is_inherited(&api_addr, false) {
+        Ok(())
+    } else {
+        tokio::net::TcpListener
+            ::bind(&api_addr).await
+            .map(|_| ())
+            .map_err(Into::into)
+    };
+    checks.record(format!("TCP API port {api_addr}"), true, t, result);
+
+    // Save dir
+    let t = Instant::now();
+    let result = validate::check_writable(&config.audio_save_dir);
+    checks.record(format!("audio save dir {}", config.audio_save_dir), true, t, result);
+
+    // Models
+    for (what, path) in model_files(config) {
+        let t = Instant::now();
+        checks.record(format!("{what} {path}"), true, t, check_file(path));
+    }
+
+    // OpenAI
+    if config.openai_realtime {
+        let t = Instant::now();
+        let result = check_openai(OPENAI_MODELS_URL, &config.openai_api_key, &config.openai_model).await;
+        checks.record(format!("OpenAI key + model {}", config.openai_model), true, t, result);
+    }
+
+    // MQTT brokers
+    for (name, host, port) in mqtt_brokers(config) {
+        let t = Instant::now();
+        let result = check_reachable(host, port).await;
+        checks.record(format!("{name} MQTT broker {host}:{port}"), false, t, result);
+    }
+
+    let report = SelfTestReport { checks: Arc::new(checks.results) };
+    let failed = report.checks().iter().filter(|c| !c.ok).count();
+    let critical = report.critical_failures();
+    let summary = format!("🩺 startup self-test: {} passed, {failed} failed", report.checks().len() - failed);
+    if critical > 0 {
+        error!(tenant = %config.tenant_id, critical, elapsed_ms = started.elapsed().as_millis() as u64, "{summary}");
+    } else {
+        info!(tenant = %config.tenant_id, elapsed_ms = started.elapsed().as_millis() as u64, "{summary}");
+    }
+    report
+}
+
+/// Model and weights files `config` loads.
+fn model_files(config: &Config) -> Vec<(&'static str, &str)> {
+    let mut files = Vec::new();
+    match config.emotion_engine {
+        EmotionEngineKind::Onnx => files.push(("emotion model", config.emotion_model_path.as_str())),
+        _ if !config.emotion_weights.is_empty() => files.push(("emotion weights", config.emotion_weights.as_str())),
+        _ => {}
+    }
+    match config.shadow_emotion_engine {
+        Some(EmotionEngineKind::Onnx) => {
+            files.push(("shadow emotion model", config.shadow_emotion_model_path.as_str()));
+        }
+        Some(_) if !config.shadow_emotion_weights.is_empty() => {
+            files.push(("shadow emotion weights", config.shadow_emotion_weights.as_str()));
+        }
+        _ => {}
+    }
+    if config.speaker_engine == SpeakerEngineKind::Onnx {
+        files.push(("speaker model", config.speaker_model_path.as_str()));
+    }
+    if !config.audio_event_model_path.is_empty() {
+        files.push(("audio-event model", config.audio_event_model_path.as_str()));
+    }
+    files
+}
+
+/// Broker connections `config` opens, by name.
+fn mqtt_brokers(config: &Config) -> Vec<(&'static str, &str, u16)> {
+    [
+        ("admin", config.admin_mqtt_host.as_str(), config.admin_mqtt_port),
+        ("alert", config.alert_mqtt_host.as_str(), config.alert_mqtt_port),
+        ("transcript", config.transcript_mqtt_host.as_str(), config.transcript_mqtt_port),
+        ("presence", config.presence_mqtt_host.as_str(), config.presence_mqtt_port),
+        ("event", config.event_mqtt_host.as_str(), config.event_mqtt_port),
+        (
+            "stats",
+            if config.stats_sink.contains(&StatsSinkKind::Mqtt) { config.stats_mqtt_host.as_str() } else { "" },
+            config.stats_mqtt_port,
+        ),
+    ]
+        .into_iter()
+        .filter(|(_, host, _)| !host.is_empty())
+        .collect()
+}
+
+/// `path` is a readable, non-empty file.
+fn check_file(path: &str) -> anyhow::Result<()> {
+    if path.is_empty() {
+        anyhow::bail!("no path configured");
+    }
+    let file = std::fs::File::open(path)?;
+    let meta = file.metadata()?;
+    if !meta.is_file() {
+        anyhow::bail!("not a file");
+    }
+    if meta.len() == 0 {
+        anyhow::bail!("empty file");
+    }
+    Ok(())
+}
+
+/// `host:port` accepts a TCP connection.
+async fn check_reachable(host: &str, port: u16) -> anyhow::Result<()> {
+    match tokio::time::timeout(PROBE_TIMEOUT, tokio::net::TcpStream::connect((host, port))).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e.into()),
+        Err(_) => anyhow::bail!("no answer within {} s", PROBE_TIMEOUT.as_secs()),
+    }
+}
+
+/// The key is accepted at `models_url` and may use `model`.
+async fn check_openai(models_url: &str, key: &str, model: &str) -> anyhow::Result<()> {
+    if key.trim().is_empty() {
+        anyhow::bail!("--openai-realtime set but no --openai-api-key / OPENAI_API_KEY");
+    }
+    let http = reqwest::Client::builder().timeout(PROBE_TIMEOUT).build()?;
+    let response = http
+        .get(format!("{models_url}/{model}"))
+        .bearer_auth(key.trim())
+        .send().await
+        .map_err(|e| anyhow::anyhow!("OpenAI unreachable: {e}"))?;
+    match response.status() {
+        s if s.is_success() => Ok(()),
+        reqwest::StatusCode::UNAUTHORIZED => anyhow::bail!("API key rejected (401)"),
+        reqwest::StatusCode::NOT_FOUND => anyhow::bail!("model not available to this key (404)"),
+        s => anyhow::bail!("unexpected HTTP {s}"),
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn test_report_flags_critical_failures_only() {
+        let dir = std::env::temp_dir().join(format!("vad-self-test-{}", std::process::id()));
+        let broker = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let broker_port = broker.local_addr().unwrap().port();
+        let closed_port = {
+            let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            l.local_addr().unwrap().port()
+        };
+        let config = crate::config::Cli::parse_from([
+            "vad-sensor-bridge",
+            "--host",
+            "127.0.0.1",
+            "--api-port",
+            "0",
+            "--audio-save-dir",
+            dir.to_str().unwrap(),
+            "--admin-mqtt-host",
+            "127.0.0.1",
+            "--admin-mqtt-port",
+            &broker_port.to_string(),
+            "--presence-mqtt-host",
+            "127.0.0.1",
+            "--presence-mqtt-port",
+            &closed_port.to_string(),
+        ]).serve;
+        let report = run(&config, false).await;
+        let names: Vec<&str> = report
+            .checks()
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert!(names.iter().all(|n| !n.starts_with("UDP")), "{names:?}");
+        assert!(report.checks().iter().any(|c| c.name.starts_with("admin MQTT") && c.ok));
+        let presence = report
+            .checks()
+            .iter()
+            .find(|c| c.name.starts_with("presence MQTT"))
+            .unwrap();
+        assert!(!presence.ok && !presence.critical);
+        assert!(report.ready());
+        assert!(report.enforce().is_ok());
+        assert_eq!(report.snapshot().failed.len(), 1);
+
+        let config = crate::config::Cli::parse_from([
+            "vad-sensor-bridge",
+            "--api-port",
+            "0",
+            "--audio-save-dir",
+            dir.to_str().unwrap(),
+            "--audio-event-model-path",
+            "/definitely/not/a/model.onnx",
+        ]).serve;
+        let report = run(&config, false).await;
+        assert!(!report.ready());
+        let err = report.enforce().unwrap_err().to_string();
+        assert!(err.contains("audio-event model"), "{err}");
+        assert!(!report.snapshot().ready);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_openai_key_checks() {
+        assert!(check_openai(OPENAI_MODELS_URL, " ", "gpt").await.unwrap_err().to_string().contains("no --openai-api-key"));
+
+        // Answers 401 to every request
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/models", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            use tokio::io::{ AsyncReadExt, AsyncWriteExt };
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket.write_all(b"HTTP/1.1 401 Unauthorized\r\ncontent-length: 0\r\n\r\n").await;
+            }
+        });
+        let err = check_openai(&url, "sk-bad", "gpt").await.unwrap_err().to_string();
+        assert!(err.contains("rejected"), "{err}");
+    }
+
+    #[test]
+    fn test_check_file() {
+        assert!(check_file("").is_err());
+        assert!(check_file("/definitely/not/here").is_err());
+        assert!(check_file(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")).is_ok());
+    }
+}
@@ -1,20 +1,37 @@
+use crate::pcm::SampleFormat;
+use crate::tenants::TenantId;
+use crate::trace::TraceId;
+use std::time::Instant;
+
 /// Raw sensor datagram layout (binary, packed, little-endian).
 ///
 /// Wire format (32 bytes fixed header + variable payload):
-///   [ sensor_id: u32 LE ][ timestamp_us: u64 LE ][ data_type: u8 ][ reserved: 3 bytes ]
-///   [ payload_len: u16 LE ][ reserved: 2 bytes ][ seq: u64 LE ][ padding: 4 bytes ]
-///   [ payload: payload_len bytes ]
+///   [ sensor_id: u32 LE ][ timestamp_us: u64 LE ][ data_type: u8 ][ sample_format: u8 ]
+///   [ reserved: 2 bytes ][ payload_len: u16 LE ][ reserved: 2 bytes ][ seq: u64 LE ]
+///   [ padding: 4 bytes ][ payload: payload_len bytes ]
 ///
 /// Data types:
-///   1 = 16-bit LE PCM audio (for audio RMS VAD)
+///   1 = PCM audio (for audio RMS VAD), format per `sample_format`
+///       (0 = s16 LE, 1 = s8, 2 = s24, 3 = s32, 4 = f32 — see `pcm.rs`)
 ///   2 = 10×f32 LE sensor vector (for emotional VAD: Valence-Arousal-Dominance)
 #[derive(Debug, Clone)]
 pub struct SensorPacket {
     pub sensor_id: u32,
     pub timestamp_us: u64,
     pub data_type: u8,
+    /// PCM sample format (audio only; ignored for sensor vectors)
+    pub sample_format: SampleFormat,
     pub seq: u64,
     pub payload: Vec<u8>,
+    /// Tenant that owns the ingress port (not on the wire; stamped by
+    /// the receiver, "" in single-tenant mode)
+    pub tenant: TenantId,
+    /// When the packet was queued for a VAD worker (not on the wire;
+    /// stamped by the shard sender, None before that)
+    pub ingested_at: Option<Instant>,
+    /// Correlation id (not on the wire; the ESP session's for its
+    /// audio, else stamped by the shard sender)
+    pub trace: Option<TraceId>,
 }
 
 /// Sensor data type: 16-bit LE PCM audio
@@ -84,6 +101,30 @@ impl SensorVector {
         })
     }
 
+    /// Build a vector from a `[f32; 10]` array in channel order.
+    pub fn from_array(v: &[f32; SENSOR_VECTOR_LEN]) -> Self {
+        SensorVector {
+            battery_low: v[0],
+            people_count: v[1],
+            known_face: v[2],
+            unknown_face: v[3],
+            fall_event: v[4],
+            lifted: v[5],
+            idle_time: v[6],
+            sound_energy: v[7],
+            voice_rate: v[8],
+            motion_energy: v[9],
+        }
+    }
+
+    /// Encode as a 40-byte LE payload.
+    pub fn to_payload(self) -> Vec<u8> {
+        self.as_array()
+            .iter()
+            .flat_map(|f| f.to_le_bytes())
+            .collect()
+    }
+
     /// Return the vector as a `[f32; 10]` array in channel order.
     #[inline]
     pub fn as_array(&self) -> [f32; SENSOR_VECTOR_LEN] {
@@ -106,6 +147,22 @@ impl SensorVector {
 pub const HEADER_SIZE: usize = 32;
 
 impl SensorPacket {
+    /// Packet with the default header (s16 audio, seq 0, no timestamp)
+    /// and nothing stamped yet; set the rest with struct update syntax.
+    pub fn new(sensor_id: u32, data_type: u8, payload: Vec<u8>) -> Self {
+        SensorPacket {
+            sensor_id,
+            timestamp_us: 0,
+            data_type,
+            sample_format: SampleFormat::default(),
+            seq: 0,
+            payload,
+            tenant: TenantId::default(),
+            ingested_at: None,
+            trace: None,
+        }
+    }
+
     /// Parse a binary sensor packet from raw bytes.
     #[inline]
     pub fn from_binary(buf: &[u8]) -> Option<Self> {
@@ -125,6 +182,9 @@ impl SensorPacket {
             buf[11],
         ]);
         let data_type = buf[12];
+        // Byte 13 was reserved before it carried the audio sample format;
+        // only audio validates it, so legacy vector senders keep working
+        let sample_format = if data_type == DATA_TYPE_AUDIO { SampleFormat::from_code(buf[13])? } else { SampleFormat::default() };
         let payload_len = u16::from_le_bytes([buf[16], buf[17]]) as usize;
         let seq = u64::from_le_bytes([
             buf[20],
@@ -144,11 +204,10 @@ impl SensorPacket {
         let payload = buf[HEADER_SIZE..HEADER_SIZE + payload_len].to_vec();
 
         Some(SensorPacket {
-            sensor_id,
             timestamp_us,
-            data_type,
+            sample_format,
             seq,
-            payload,
+            ..SensorPacket::new(sensor_id, data_type, payload)
         })
     }
 
@@ -157,4 +216,20 @@ impl SensorPacket {
     pub fn parse(buf: &[u8]) -> Option<Self> {
         Self::from_binary(buf)
     }
+
+    /// Encode to the binary wire format (inverse of [`Self::from_binary`]).
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(HEADER_SIZE + self.payload.len());
+        buf.extend_from_slice(&self.sensor_id.to_le_bytes());
+        buf.extend_from_slice(&self.timestamp_us.to_le_bytes());
+        buf.push(self.data_type);
+        buf.push(self.sample_format.code());
+        buf.extend_from_slice(&[0u8; 2]);
+        buf.extend_from_slice(&(self.payload.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&[0u8; 2]);
+        buf.extend_from_slice(&self.seq.to_le_bytes());
+        buf.extend_from_slice(&[0u8; 4]);
+        buf.extend_from_slice(&self.payload);
+        buf
+    }
 }
@@ -0,0 +1,201 @@
+use crate::calibrate::CHANNEL_NAMES;
+use crate::sensor::SENSOR_VECTOR_LEN;
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::sync::Arc;
+use std::time::Duration;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Sensor input sanitization (before smoothing)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  One corrupted float must not pin valence to an extreme for an
+//  EMA-length of packets.  Every sensor vector goes through three steps
+//  per channel before the smoother sees it:
+//
+//    NaN / ±Inf     replaced by the channel's previous value (0 at first)
+//    out of [0,1]   clamped
+//    spike          a jump of more than SPIKE_JUMP from the previous
+//                   packet is held back for one packet: the output is
+//                   the median of the last three values, so a single-
+//                   sample spike never gets through while a real step
+//                   arrives one packet late
+//
+//  `fall_event` and `lifted` are impulses by nature and skip the spike
+//  filter.  Counters per channel are logged each stats interval.
+
+/// Jump between consecutive packets treated as a possible spike.
+const SPIKE_JUMP: f32 = 0.5;
+
+/// Channels whose real signal is a one-packet impulse (fall_event, lifted).
+const IMPULSE_CHANNELS: [usize; 2] = [4, 5];
+
+/// Last two inputs per channel (NaN and range already repaired), kept
+/// with a sensor's smoother state.
+#[derive(Debug, Clone, Default)]
+pub struct SanitizeHistory {
+    /// `[older, previous]`; `None` until the first packet
+    last: Option<[[f32; 2]; SENSOR_VECTOR_LEN]>,
+}
+
+/// Per-channel sanitization counters, shared across VAD workers.
+#[derive(Debug, Default)]
+pub struct SanitizeStats {
+    non_finite: [AtomicU64; SENSOR_VECTOR_LEN],
+    out_of_range: [AtomicU64; SENSOR_VECTOR_LEN],
+    spikes: [AtomicU64; SENSOR_VECTOR_LEN],
+}
+
+/// Counters over one reporting interval.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SanitizeSnapshot {
+    pub non_finite: [u64; SENSOR_VECTOR_LEN],
+    pub out_of_range: [u64; SENSOR_VECTOR_LEN],
+    pub spikes: [u64; SENSOR_VECTOR_LEN],
+}
+
+impl SanitizeStats {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Snapshot and reset counters.
+    pub fn snapshot_and_reset(&self) -> SanitizeSnapshot {
+        let take = |c: &[AtomicU64; SENSOR_VECTOR_LEN]| std::array::from_fn(|i| c[i].swap(0, Ordering::Relaxed));
+        SanitizeSnapshot {
+            non_finite: take(&self.non_finite),
+            out_of_range: take(&self.out_of_range),
+            spikes: take(&self.spikes),
+        }
+    }
+}
+
+/// Sanitize `sensors` in place against `history`, counting what was
+/// fixed in `stats`.
+pub fn sanitize(history: &mut SanitizeHistory, sensors: &mut [f32; SENSOR_VECTOR_LEN], stats: &SanitizeStats) {
+    let mut raw = [0.0f32; SENSOR_VECTOR_LEN];
+    for i in 0..SENSOR_VECTOR_LEN {
+        let mut v = sensors[i];
+        if !v.is_finite() {
+            stats.non_finite[i].fetch_add(1, Ordering::Relaxed);
+            v = history.last.map_or(0.0, |last| last[i][1]);
+        }
+        if !(0.0..=1.0).contains(&v) {
+            stats.out_of_range[i].fetch_add(1, Ordering::Relaxed);
+            v = v.clamp(0.0, 1.0);
+        }
+        raw[i] = v;
+    }
+
+    let Some(last) = history.last.as_mut() else {
+        history.last = Some(raw.map(|v| [v, v]));
+        *sensors = raw;
+        return;
+    };
+    for i in 0..SENSOR_VECTOR_LEN {
+        let [older, prev] = last[i];
+        let v = raw[i];
+        let out = if (v - prev).abs() > SPIKE_JUMP && !IMPULSE_CHANNELS.contains(&i) {
+            median3(older, prev, v)
+        } else {
+            v
+        };
+        if out != v {
+            stats.spikes[i].fetch_add(1, Ordering::Relaxed);
+        }
+        // History keeps the input, so a step that persists gets through
+        last[i] = [prev, v];
+        sensors[i] = out;
+    }
+}
+
+#[inline]
+fn median3(a: f32, b: f32, c: f32) -> f32 {
+    a.max(b).min(a.min(b).max(c))
+}
+
+/// Background reporter task: logs the channels fixed during each
+/// interval (silent when nothing was).
+pub async fn sanitize_reporter(stats: Arc<SanitizeStats>, interval_secs: u64) {
+    if interval_secs == 0 {
+        std::future::pending::<()>().await;
+        return;
+    }
+
+    let interval = Duration::from_secs(interval_secs);
+    loop {
+        tokio::time::sleep(interval).await;
+        let snap = stats.snapshot_and_reset();
+        if snap == SanitizeSnapshot::default() {
+            continue;
+        }
+        let per_channel = |counts: &[u64; SENSOR_VECTOR_LEN]| {
+            CHANNEL_NAMES.iter()
+                .zip(counts)
+                .filter(|(_, n)| **n > 0)
+                .map(|(name, n)| format!("{name}={n}"))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        tracing::warn!(
+            non_finite = %per_channel(&snap.non_finite),
+            out_of_range = %per_channel(&snap.out_of_range),
+            spikes = %per_channel(&snap.spikes),
+            "🧽 sensor input sanitized"
+        );
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOUND: usize = 7;
+    const FALL: usize = 4;
+
+    fn run(history: &mut SanitizeHistory, stats: &SanitizeStats, sound: f32, fall: f32) -> [f32; SENSOR_VECTOR_LEN] {
+        let mut s = [0.2f32; SENSOR_VECTOR_LEN];
+        s[SOUND] = sound;
+        s[FALL] = fall;
+        sanitize(history, &mut s, stats);
+        s
+    }
+
+    #[test]
+    fn test_nan_inf_and_range() {
+        let stats = SanitizeStats::default();
+        let mut h = SanitizeHistory::default();
+        assert_eq!(run(&mut h, &stats, f32::NAN, 0.0)[SOUND], 0.0);
+        assert_eq!(run(&mut h, &stats, 0.3, 0.0)[SOUND], 0.3);
+        // NaN holds the previous value; +Inf likewise, never 1.0
+        assert_eq!(run(&mut h, &stats, f32::NAN, 0.0)[SOUND], 0.3);
+        assert_eq!(run(&mut h, &stats, f32::INFINITY, 0.0)[SOUND], 0.3);
+        assert_eq!(run(&mut h, &stats, 0.4, -3.0)[FALL], 0.0);
+
+        let snap = stats.snapshot_and_reset();
+        assert_eq!((snap.non_finite[SOUND], snap.out_of_range[FALL]), (3, 1));
+        assert_eq!(stats.snapshot_and_reset(), SanitizeSnapshot::default());
+    }
+
+    #[test]
+    fn test_single_spike_filtered_steps_pass_late() {
+        let stats = SanitizeStats::default();
+        let mut h = SanitizeHistory::default();
+        run(&mut h, &stats, 0.1, 0.0);
+        run(&mut h, &stats, 0.1, 0.0);
+
+        // One-packet spike never reaches the smoother; impulses do
+        let out = run(&mut h, &stats, 0.95, 0.9);
+        assert_eq!((out[SOUND], out[FALL]), (0.1, 0.9));
+        assert_eq!(run(&mut h, &stats, 0.1, 0.0)[SOUND], 0.1);
+        run(&mut h, &stats, 0.1, 0.0);
+
+        // A real step is one packet late
+        assert_eq!(run(&mut h, &stats, 0.9, 0.0)[SOUND], 0.1);
+        assert_eq!(run(&mut h, &stats, 0.9, 0.0)[SOUND], 0.9);
+        assert_eq!(stats.snapshot_and_reset().spikes[SOUND], 2);
+    }
+}
@@ -0,0 +1,392 @@
+use crate::sensor::{ SensorPacket, DATA_TYPE_AUDIO, DATA_TYPE_SENSOR_VECTOR };
+use serde::{ Deserialize, Serialize };
+use std::collections::{ BTreeMap, HashMap };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::sync::{ Arc, RwLock };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Sensor data schemas (per data_type validation)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  A schema names the f32 LE channels of one `data_type` with their
+//  valid range and unit.  Every packet of a registered type is checked
+//  before VAD, events or recording see it:
+//
+//    in range              passed through
+//    out of range          clamp: value pulled into [min, max], packet
+//                          kept
+//                          reject: packet dropped
+//    NaN / ±Inf            clamp: left as is, packet kept
+//                          reject: packet dropped
+//    payload too short     packet dropped
+//
+//  A schema only knows the range, not what a missing reading should
+//  be: the sensor vector's non-finite channels are repaired with the
+//  previous value by `sensor_sanitize.rs`, which keeps per-sensor
+//  history.
+//
+//  Each violation is counted per channel (`GET /schemas`).  The
+//  10-channel emotional vector (data_type 2, every channel 0..1, clamp)
+//  is built in; `--sensor-schemas FILE` (TOML, `[[schema]]` entries) and
+//  `PUT /schemas/{data_type}` add or replace schemas.  Audio (type 1)
+//  is PCM and cannot have one.
+
+/// Most channels a schema may declare.
+const MAX_CHANNELS: usize = 64;
+
+/// One channel of a schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelSpec {
+    pub name: String,
+    pub min: f32,
+    pub max: f32,
+    #[serde(default)]
+    pub unit: String,
+}
+
+/// What an out-of-range packet gets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnViolation {
+    #[default]
+    Clamp,
+    Reject,
+}
+
+/// Channel layout of one data type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataSchema {
+    /// Taken from the URL for `PUT /schemas/{data_type}`
+    #[serde(default)]
+    pub data_type: u8,
+    #[serde(default)]
+    pub name: String,
+    pub channels: Vec<ChannelSpec>,
+    #[serde(default)]
+    pub on_violation: OnViolation,
+}
+
+impl DataSchema {
+    /// The built-in emotional sensor vector schema.
+    pub fn sensor_vector() -> Self {
+        let names = [
+            "battery_low",
+            "people_count",
+            "known_face",
+            "unknown_face",
+            "fall_event",
+            "lifted",
+            "idle_time",
+            "sound_energy",
+            "voice_rate",
+            "motion_energy",
+        ];
+        Self {
+            data_type: DATA_TYPE_SENSOR_VECTOR,
+            name: "emotional_vector".into(),
+            channels: names
+                .iter()
+                .map(|name| ChannelSpec { name: name.to_string(), min: 0.0, max: 1.0, unit: "normalised".into() })
+                .collect(),
+            on_violation: OnViolation::Clamp,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.data_type == DATA_TYPE_AUDIO {
+            return Err("data_type 1 is PCM audio and cannot have a schema".into());
+        }
+        if self.channels.is_empty() || self.channels.len() > MAX_CHANNELS {
+            return Err(format!("a schema needs 1-{MAX_CHANNELS} channels"));
+        }
+        for c in &self.channels {
+            if !c.min.is_finite() || !c.max.is_finite() || c.min > c.max {
+                return Err(format!("channel {:?}: need finite min <= max", c.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SchemaFile {
+    #[serde(default)]
+    schema: Vec<DataSchema>,
+}
+
+/// Outcome of [`SchemaRegistry::check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// No schema for the packet's data type
+    Unchecked,
+    Valid,
+    /// Out-of-range values were clamped in place
+    Clamped,
+    /// Drop the packet
+    Rejected,
+}
+
+/// A registered schema and its counters.
+struct Entry {
+    schema: DataSchema,
+    checked: AtomicU64,
+    clamped: AtomicU64,
+    rejected: AtomicU64,
+    /// Payloads shorter than the schema's channels
+    short: AtomicU64,
+    violations: Vec<AtomicU64>,
+}
+
+impl Entry {
+    fn new(schema: DataSchema) -> Self {
+        let violations = schema.channels
+            .iter()
+            .map(|_| AtomicU64::new(0))
+            .collect();
+        Self {
+            schema,
+            checked: AtomicU64::new(0),
+            clamped: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            short: AtomicU64::new(0),
+            violations,
+        }
+    }
+}
+
+/// A schema with its counters, as served by `GET /schemas`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaStatus {
+    #[serde(flatten)]
+    pub schema: DataSchema,
+    pub checked: u64,
+    pub clamped: u64,
+    pub rejected: u64,
+    pub short: u64,
+    /// Out-of-range values per channel name
+    pub violations: BTreeMap<String, u64>,
+}
+
+/// Registered schemas by data type.  Clone-friendly.
+#[derive(Clone)]
+pub struct SchemaRegistry {
+    schemas: Arc<RwLock<HashMap<u8, Arc<Entry>>>>,
+}
+
+impl Default for SchemaRegistry {
+    /// Just the built-in sensor vector schema.
+    fn default() -> Self {
+        let registry = Self { schemas: Arc::default() };
+        let _ = registry.set(DataSchema::sensor_vector());
+        registry
+    }
+}
+
+impl SchemaRegistry {
+    /// Built-in schemas plus those in the TOML file at `path` ("" = none).
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let registry = Self::default();
+        if path.is_empty() {
+            return Ok(registry);
+        }
+        let text = std::fs
+            ::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read sensor schemas {path}: {e}"))?;
+        let file: SchemaFile = toml
+            ::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("invalid sensor schemas {path}: {e}"))?;
+        for schema in file.schema {
+            let data_type = schema.data_type;
+            registry.set(schema).map_err(|e| anyhow::anyhow!("{path}: data_type {data_type}: {e}"))?;
+        }
+        Ok(registry)
+    }
+
+    /// Add or replace the schema of `schema.data_type` (counters restart).
+    pub fn set(&self, schema: DataSchema) -> Result<(), String> {
+        schema.validate()?;
+        let mut schemas = self.schemas.write().unwrap_or_else(|e| e.into_inner());
+        schemas.insert(schema.data_type, Arc::new(Entry::new(schema)));
+        Ok(())
+    }
+
+    /// Stop validating `data_type`; returns whether it had a schema.
+    pub fn remove(&self, data_type: u8) -> bool {
+        self.schemas
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&data_type)
+            .is_some()
+    }
+
+    /// Schemas and counters, by data type.
+    pub fn list(&self) -> Vec<SchemaStatus> {
+        let schemas = self.schemas.read().unwrap_or_else(|e| e.into_inner());
+        let mut list: Vec<SchemaStatus> = schemas
+            .values()
+            .map(|e| SchemaStatus {
+                schema: e.schema.clone(),
+                checked: e.checked.load(Ordering::Relaxed),
+                clamped: e.clamped.load(Ordering::Relaxed),
+                rejected: e.rejected.load(Ordering::Relaxed),
+                short: e.short.load(Ordering::Relaxed),
+                violations: e.schema.channels
+                    .iter()
+                    .zip(&e.violations)
+                    .map(|(c, n)| (c.name.clone(), n.load(Ordering::Relaxed)))
+                    .collect(),
+            })
+            .collect();
+        list.sort_by_key(|s| s.schema.data_type);
+        list
+    }
+
+    /// Validate `packet` against its data type's schema, clamping its
+    /// payload in place when the schema says so.
+    pub fn check(&self, packet: &mut SensorPacket) -> Verdict {
+        let entry = {
+            let schemas = self.schemas.read().unwrap_or_else(|e| e.into_inner());
+            match schemas.get(&packet.data_type) {
+                Some(entry) => entry.clone(),
+                None => {
+                    return Verdict::Unchecked;
+                }
+            }
+        };
+        entry.checked.fetch_add(1, Ordering::Relaxed);
+        if packet.payload.len() < entry.schema.channels.len() * 4 {
+            entry.short.fetch_add(1, Ordering::Relaxed);
+            entry.rejected.fetch_add(1, Ordering::Relaxed);
+            return Verdict::Rejected;
+        }
+
+        let mut violated = false;
+        for (i, (spec, bytes)) in entry.schema.channels.iter().zip(packet.payload.chunks_exact_mut(4)).enumerate() {
+            let v = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            if v >= spec.min && v <= spec.max {
+                continue;
+            }
+            violated = true;
+            entry.violations[i].fetch_add(1, Ordering::Relaxed);
+            // Non-finite values have no nearest valid value: left as is
+            if entry.schema.on_violation == OnViolation::Clamp && v.is_finite() {
+                bytes.copy_from_slice(&v.clamp(spec.min, spec.max).to_le_bytes());
+            }
+        }
+        match (violated, entry.schema.on_violation) {
+            (false, _) => Verdict::Valid,
+            (true, OnViolation::Clamp) => {
+                entry.clamped.fetch_add(1, Ordering::Relaxed);
+                Verdict::Clamped
+            }
+            (true, OnViolation::Reject) => {
+                entry.rejected.fetch_add(1, Ordering::Relaxed);
+                Verdict::Rejected
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pcm::SampleFormat;
+    use crate::sensor::SensorVector;
+
+    fn packet(data_type: u8, payload: Vec<u8>) -> SensorPacket {
+        SensorPacket {
+            sensor_id: 1,
+            timestamp_us: 0,
+            data_type,
+            sample_format: SampleFormat::S16,
+            seq: 0,
+            payload,
+            tenant: Default::default(),
+            ingested_at: None,
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn test_builtin_schema_clamps_out_of_range_and_keeps_nan() {
+        let registry = SchemaRegistry::default();
+        let mut v = [0.5f32; 10];
+        v[7] = f32::NAN; // sound_energy
+        v[9] = 3.0; // motion_energy
+        let mut pkt = packet(DATA_TYPE_SENSOR_VECTOR, SensorVector::from_array(&v).to_payload());
+
+        assert_eq!(registry.check(&mut pkt), Verdict::Clamped);
+        let sv = SensorVector::from_payload(&pkt.payload).unwrap();
+        assert!(sv.sound_energy.is_nan());
+        assert_eq!((sv.motion_energy, sv.people_count), (1.0, 0.5));
+
+        let status = &registry.list()[0];
+        assert_eq!((status.checked, status.clamped), (1, 1));
+        assert_eq!(status.violations["sound_energy"], 1);
+        assert_eq!(status.violations["battery_low"], 0);
+
+        // Short payloads never reach VAD; audio is never checked
+        assert_eq!(registry.check(&mut packet(DATA_TYPE_SENSOR_VECTOR, vec![0; 8])), Verdict::Rejected);
+        assert_eq!(registry.check(&mut packet(DATA_TYPE_AUDIO, vec![0; 8])), Verdict::Unchecked);
+    }
+
+    #[test]
+    fn test_nan_through_schema_and_smoother_holds_previous_value() {
+        const SOUND: usize = 7;
+        let registry = SchemaRegistry::default();
+        let smoother = crate::sensor_smoother::SensorSmoother::new();
+        let reference = crate::sensor_smoother::SensorSmoother::new();
+        let persona = crate::persona::PersonaTrait::Obedient;
+        let mut v = [0.2f32; 10];
+        v[SOUND] = 0.6;
+
+        // What the bridge's VAD workers do: schema check, then smoothing
+        let run = |v: [f32; 10]| {
+            let mut pkt = packet(DATA_TYPE_SENSOR_VECTOR, SensorVector::from_array(&v).to_payload());
+            registry.check(&mut pkt);
+            let mut sensors = SensorVector::from_payload(&pkt.payload).unwrap().as_array();
+            smoother.smooth(1, &mut sensors, persona)
+        };
+        run(v);
+        v[SOUND] = f32::NAN;
+        let out = run(v);
+
+        // The sanitizer repaired it with the previous 0.6, not the schema's min
+        let mut steady = [0.2f32; 10];
+        steady[SOUND] = 0.6;
+        reference.smooth(1, &mut steady.clone(), persona);
+        assert_eq!(out, reference.smooth(1, &mut steady, persona));
+        assert_eq!(smoother.sanitize_stats().snapshot_and_reset().non_finite[SOUND], 1);
+        assert_eq!(registry.list()[0].violations["sound_energy"], 1);
+    }
+
+    #[test]
+    fn test_custom_schema_rejects() {
+        let registry = SchemaRegistry::default();
+        let schema = DataSchema {
+            data_type: 7,
+            name: "thermal".into(),
+            channels: vec![ChannelSpec { name: "temp_c".into(), min: -20.0, max: 60.0, unit: "°C".into() }],
+            on_violation: OnViolation::Reject,
+        };
+        registry.set(schema.clone()).unwrap();
+        assert_eq!(registry.check(&mut packet(7, 25f32.to_le_bytes().to_vec())), Verdict::Valid);
+        assert_eq!(registry.check(&mut packet(7, 99f32.to_le_bytes().to_vec())), Verdict::Rejected);
+
+        assert!(registry.set(DataSchema { data_type: DATA_TYPE_AUDIO, ..schema.clone() }).is_err());
+        assert!(
+            registry
+                .set(DataSchema {
+                    channels: vec![ChannelSpec { name: "x".into(), min: 1.0, max: 0.0, unit: String::new() }],
+                    ..schema
+                })
+                .is_err()
+        );
+        assert!(registry.remove(7));
+    }
+}
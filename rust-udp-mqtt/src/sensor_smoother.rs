@@ -1,6 +1,12 @@
+use crate::clock::{ self, SharedClock };
 use crate::persona::PersonaTrait;
+use crate::sensor::SENSOR_VECTOR_LEN;
+use crate::sensor_sanitize::{ self, SanitizeHistory, SanitizeStats };
+use crate::shard::shard_of;
+use serde::{ Deserialize, Serialize };
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
 
 // ─────────────────────────────────────────────────────────────────────
 //  Sensor Smoother — EMA-based idle-time decay
@@ -22,10 +28,38 @@ use std::sync::Mutex;
 //  Half-life in packets ≈ ln(2) / α  (continuous approximation).
 //
 //  All other channels are passed through unmodified.
+//
+//  Sanitization:  before any of this, NaN/Inf, out-of-range values and
+//              single-packet spikes are repaired (`sensor_sanitize.rs`),
+//              so one corrupted float cannot pin the EMA or the rates.
+//
+//  Rate of change:  alongside the levels, the smoother tracks a decaying
+//              envelope of |raw − prev_raw| per channel:
+//
+//                rate = max(|Δ|, DELTA_DECAY × prev_rate)
+//
+//              A sudden jump (people walk in, robot is shaken) produces a
+//              spike that fades over a handful of packets instead of a
+//              permanent step.  These feed the derivative half of the
+//              emotional weight vectors in `vad.rs`.
+//
+//  Lifecycle:  a sensor that goes quiet for longer than `reset_gap` starts
+//              from a fresh EMA on its next packet (a stale idle level from
+//              before the downtime must not colour the new session), and
+//              `evict_stale()` drops state for sensors that never come back.
+//
+//  Sharding:   state is striped by `shard_of(sensor_id)`, one stripe per
+//              VAD worker, so workers never contend for a lock.
 
 /// Index of the idle_time channel in the 10-element sensor vector.
 const IDLE_TIME_IDX: usize = 6;
 
+/// Per-packet decay of the rate-of-change envelope (~3 pkt half-life).
+const DELTA_DECAY: f32 = 0.8;
+
+/// Default silence after which a sensor's EMA is reset on its next packet.
+pub const DEFAULT_RESET_GAP: Duration = Duration::from_secs(300);
+
 /// Return the EMA alpha for idle_time given the active persona.
 ///
 /// Higher alpha → idle_time ramps up faster → robot gets sad sooner.
@@ -39,18 +73,62 @@ fn idle_alpha(persona: PersonaTrait) -> f32 {
     }
 }
 
-/// Per-sensor smoothing state.
+/// What the smoother remembers about one sensor between packets: the
+/// EMA of `idle_time` plus the raw previous vector and the
+/// rate-of-change envelope for every channel.
 ///
-/// Currently only tracks the EMA of `idle_time`; other channels are
-/// passed through.  Extensible to smooth additional channels if needed.
+/// Public so `POST /vad/evaluate` can take and hand back the state of
+/// a what-if sequence.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmootherState {
+    pub idle_time: f32,
+    /// Previous raw (unsmoothed) vector; `None` until the first packet.
+    pub prev_raw: Option<[f32; SENSOR_VECTOR_LEN]>,
+    /// Decaying |Δ| envelope per channel.
+    pub rate: [f32; SENSOR_VECTOR_LEN],
+}
+
+impl SmootherState {
+    /// Feed one (sanitized) vector: smooth `sensors` in place and return
+    /// the per-channel rate-of-change features.
+    pub fn step(&mut self, sensors: &mut [f32; SENSOR_VECTOR_LEN], persona: PersonaTrait) -> [f32; SENSOR_VECTOR_LEN] {
+        // Rate-of-change envelope on raw values
+        if let Some(prev) = self.prev_raw {
+            for i in 0..SENSOR_VECTOR_LEN {
+                let delta = (sensors[i] - prev[i]).abs();
+                self.rate[i] = delta.max(DELTA_DECAY * self.rate[i]).clamp(0.0, 1.0);
+            }
+        }
+        self.prev_raw = Some(*sensors);
+
+        // EMA update:  smoothed = α * raw + (1 − α) * prev
+        let alpha = idle_alpha(persona);
+        let raw_idle = sensors[IDLE_TIME_IDX];
+        self.idle_time = alpha * raw_idle + (1.0 - alpha) * self.idle_time;
+        sensors[IDLE_TIME_IDX] = self.idle_time;
+
+        self.rate
+    }
+}
+
+/// Per-sensor smoothing state plus its bookkeeping.
 #[derive(Debug, Clone)]
 struct SensorEma {
-    idle_time: f32,
+    model: SmootherState,
+    /// When this sensor last fed a packet through the smoother.
+    last_seen: Instant,
+    /// Recent inputs for the spike filter.
+    sanitize: SanitizeHistory,
 }
 
 impl SensorEma {
-    fn new() -> Self {
-        Self { idle_time: 0.0 }
+    fn new(now: Instant) -> Self {
+        Self {
+            model: SmootherState::default(),
+            last_seen: now,
+            sanitize: SanitizeHistory::default(),
+        }
     }
 }
 
@@ -59,47 +137,146 @@ impl SensorEma {
 /// Maintains an EMA state per `sensor_id` so each physical ESP32 device
 /// has its own independent idle-time ramp.
 pub struct SensorSmoother {
-    state: Mutex<HashMap<u32, SensorEma>>,
+    /// Per-sensor state, striped by [`shard_of`].
+    state: Vec<Mutex<HashMap<u32, SensorEma>>>,
+    /// Silence after which the EMA restarts from zero (reconnect).
+    reset_gap: Duration,
+    clock: SharedClock,
+    /// What input sanitization repaired, per channel.
+    sanitize_stats: Arc<SanitizeStats>,
+}
+
+impl Default for SensorSmoother {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SensorSmoother {
     pub fn new() -> Self {
+        Self::with_reset_gap(DEFAULT_RESET_GAP)
+    }
+
+    /// Create a smoother that resets a sensor's EMA when it reappears
+    /// after at least `reset_gap` without packets.
+    pub fn with_reset_gap(reset_gap: Duration) -> Self {
+        Self::with_clock(reset_gap, clock::system())
+    }
+
+    /// Like [`Self::with_reset_gap`], timing gaps on `clock`.
+    pub fn with_clock(reset_gap: Duration, clock: SharedClock) -> Self {
+        Self::with_shards(reset_gap, clock, 1)
+    }
+
+    /// Like [`Self::with_clock`], with the state split into `shards`
+    /// independently locked stripes (one per VAD worker).
+    pub fn with_shards(reset_gap: Duration, clock: SharedClock, shards: usize) -> Self {
         Self {
-            state: Mutex::new(HashMap::new()),
+            state: (0..shards.max(1)).map(|_| Mutex::new(HashMap::new())).collect(),
+            reset_gap,
+            clock,
+            sanitize_stats: SanitizeStats::new(),
         }
     }
 
-    /// Smooth a 10-element sensor array in-place.
+    /// Counters of the input sanitization stage.
+    pub fn sanitize_stats(&self) -> Arc<SanitizeStats> {
+        self.sanitize_stats.clone()
+    }
+
+    fn stripe(&self, sensor_id: u32) -> std::sync::MutexGuard<'_, HashMap<u32, SensorEma>> {
+        self.state[shard_of(sensor_id, self.state.len())].lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Smooth a 10-element sensor array in-place and return the per-channel
+    /// rate-of-change features (each clamped to \[0, 1\]).
+    ///
+    /// The input is sanitized first (NaN/Inf, range, spikes).
     ///
     /// Currently only the idle_time channel (index 6) is EMA-smoothed.
-    /// All other channels pass through unchanged.
+    /// All other channels pass through unchanged.  Rates are computed on
+    /// the raw values, so the first packet from a sensor has zero rate.
     ///
     /// The EMA alpha depends on the active persona: a Mischievous robot
     /// ramps idle faster (gets bored sooner), while a Stubborn one
     /// resists boredom for many more packets.
-    pub fn smooth(&self, sensor_id: u32, sensors: &mut [f32; 10], persona: PersonaTrait) {
-        let alpha = idle_alpha(persona);
-        let mut map = self.state.lock().unwrap_or_else(|e| e.into_inner());
-        let ema = map.entry(sensor_id).or_insert_with(SensorEma::new);
-
-        // EMA update:  smoothed = α * raw + (1 − α) * prev
-        let raw_idle = sensors[IDLE_TIME_IDX];
-        ema.idle_time = alpha * raw_idle + (1.0 - alpha) * ema.idle_time;
-        sensors[IDLE_TIME_IDX] = ema.idle_time;
+    ///
+    /// A sensor returning after `reset_gap` of silence is treated as a
+    /// reconnect and starts from a fresh EMA.
+    pub fn smooth(
+        &self,
+        sensor_id: u32,
+        sensors: &mut [f32; SENSOR_VECTOR_LEN],
+        persona: PersonaTrait
+    ) -> [f32; SENSOR_VECTOR_LEN] {
+        let now = self.clock.now();
+        let mut map = self.stripe(sensor_id);
+        let ema = map.entry(sensor_id).or_insert_with(|| SensorEma::new(now));
+        if now.duration_since(ema.last_seen) >= self.reset_gap {
+            tracing::debug!(sensor_id, "smoother state reset after reconnect gap");
+            *ema = SensorEma::new(now);
+        }
+        ema.last_seen = now;
+        sensor_sanitize::sanitize(&mut ema.sanitize, sensors, &self.sanitize_stats);
+        ema.model.step(sensors, persona)
     }
 
     /// Reset smoothing state for a specific sensor (e.g. on reconnect).
-    #[allow(dead_code)]
     pub fn reset_sensor(&self, sensor_id: u32) {
-        let mut map = self.state.lock().unwrap_or_else(|e| e.into_inner());
-        map.remove(&sensor_id);
+        self.stripe(sensor_id).remove(&sensor_id);
     }
 
     /// Reset all smoothing state.
     #[allow(dead_code)]
     pub fn reset_all(&self) {
-        let mut map = self.state.lock().unwrap_or_else(|e| e.into_inner());
-        map.clear();
+        for stripe in &self.state {
+            stripe
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clear();
+        }
+    }
+
+    /// Drop state for sensors not seen within `max_age`.
+    ///
+    /// Returns the number of evicted entries.
+    pub fn evict_stale(&self, max_age: Duration) -> usize {
+        let now = self.clock.now();
+        let mut evicted = 0;
+        for stripe in &self.state {
+            let mut map = stripe.lock().unwrap_or_else(|e| e.into_inner());
+            let before = map.len();
+            map.retain(|_, ema| now.duration_since(ema.last_seen) < max_age);
+            evicted += before - map.len();
+        }
+        evicted
+    }
+
+    /// Number of sensors currently tracked.
+    pub fn tracked_sensors(&self) -> usize {
+        self.state
+            .iter()
+            .map(|stripe| stripe.lock().unwrap_or_else(|e| e.into_inner()).len())
+            .sum()
+    }
+}
+
+/// Background task: periodically evict smoother state for sensors that
+/// have been silent longer than `max_age_secs` (0 = disabled).
+pub async fn evict_stale_loop(smoother: std::sync::Arc<SensorSmoother>, max_age_secs: u64) {
+    if max_age_secs == 0 {
+        std::future::pending::<()>().await;
+        return;
+    }
+
+    let max_age = Duration::from_secs(max_age_secs);
+    let period = clock::eviction_period(max_age_secs);
+    loop {
+        smoother.clock.sleep(period).await;
+        let evicted = smoother.evict_stale(max_age);
+        if evicted > 0 {
+            tracing::info!(evicted, remaining = smoother.tracked_sensors(), "🧹 evicted stale sensor smoother state");
+        }
     }
 }
 
@@ -235,4 +412,87 @@ mod tests {
             s2[IDLE_TIME_IDX]
         );
     }
+
+    #[test]
+    fn test_rate_spikes_then_decays() {
+        let smoother = SensorSmoother::new();
+        let mut s = [0.0f32; 10];
+        assert_eq!(smoother.smooth(1, &mut s, PersonaTrait::Obedient), [0.0; 10]);
+
+        // people_count jumps 0 → 0.8 (held one packet by the spike filter)
+        let mut s = [0.0f32; 10];
+        s[1] = 0.8;
+        assert_eq!(smoother.smooth(1, &mut s, PersonaTrait::Obedient)[1], 0.0);
+        let mut s = [0.0f32; 10];
+        s[1] = 0.8;
+        let spike = smoother.smooth(1, &mut s, PersonaTrait::Obedient);
+        assert!((spike[1] - 0.8).abs() < 1e-6, "rate={:.3} expected 0.8", spike[1]);
+        assert_eq!(spike[9], 0.0, "unchanged channel should have zero rate");
+
+        // Level holds → rate decays geometrically
+        let mut last = spike[1];
+        for _ in 0..10 {
+            let mut s = [0.0f32; 10];
+            s[1] = 0.8;
+            let r = smoother.smooth(1, &mut s, PersonaTrait::Obedient);
+            assert!(r[1] < last, "rate should decay, got {:.3} after {:.3}", r[1], last);
+            last = r[1];
+        }
+        assert!(last < 0.1, "rate={last:.3} should have mostly decayed");
+    }
+
+    #[test]
+    fn test_reconnect_after_gap_resets_ema() {
+        // Zero gap → every packet counts as a reconnect
+        let smoother = SensorSmoother::with_reset_gap(Duration::ZERO);
+        for _ in 0..50 {
+            let mut s = make_sensors(0.9);
+            smoother.smooth(1, &mut s, PersonaTrait::Obedient);
+        }
+        let mut s = make_sensors(0.9);
+        smoother.smooth(1, &mut s, PersonaTrait::Obedient);
+        assert!(
+            s[IDLE_TIME_IDX] < 0.05,
+            "idle={:.4} expected fresh EMA after reconnect gap",
+            s[IDLE_TIME_IDX]
+        );
+    }
+
+    #[test]
+    fn test_reset_gap_on_sim_clock() {
+        let sim = crate::clock::SimClock::default();
+        let smoother = SensorSmoother::with_clock(Duration::from_secs(60), std::sync::Arc::new(sim.clone()));
+        let ramp = |n| {
+            let mut s = make_sensors(0.9);
+            for _ in 0..n {
+                s = make_sensors(0.9);
+                smoother.smooth(1, &mut s, PersonaTrait::Obedient);
+            }
+            s[IDLE_TIME_IDX]
+        };
+        let ramped = ramp(50);
+        // 59 virtual seconds later the EMA carries on ...
+        sim.advance(Duration::from_secs(59));
+        assert!(ramp(1) > ramped);
+        // ... but a full gap counts as a reconnect
+        sim.advance(Duration::from_secs(60));
+        assert!(ramp(1) < 0.05);
+        assert_eq!(smoother.evict_stale(Duration::from_secs(1)), 0);
+        sim.advance(Duration::from_secs(1));
+        assert_eq!(smoother.evict_stale(Duration::from_secs(1)), 1);
+    }
+
+    #[test]
+    fn test_evict_stale() {
+        let smoother = SensorSmoother::with_shards(DEFAULT_RESET_GAP, clock::system(), 4);
+        let mut s = make_sensors(0.5);
+        for id in 1..=6 {
+            smoother.smooth(id, &mut s, PersonaTrait::Obedient);
+        }
+        smoother.reset_sensor(6);
+        assert_eq!(smoother.evict_stale(Duration::from_secs(60)), 0);
+        assert_eq!(smoother.tracked_sensors(), 5);
+        assert_eq!(smoother.evict_stale(Duration::ZERO), 5);
+        assert_eq!(smoother.tracked_sensors(), 0);
+    }
 }
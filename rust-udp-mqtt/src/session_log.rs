@@ -0,0 +1,681 @@
+use dashmap::DashMap;
+use crate::conversation::Role;
+use crate::device_data::DeviceFiles;
+use crate::experiments::Experiments;
+use crate::reports::DailyReports;
+use crate::store::SharedStore;
+use crate::trace::TraceId;
+use serde::Serialize;
+use serde_json::{ json, Value };
+use std::collections::{ BTreeMap, VecDeque };
+use std::net::SocketAddr;
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::{ debug, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Per-session JSONL event log
+// ─────────────────────────────────────────────────────────────────────
+//
+//  With `--session-log`, every ESP session gets a newline-delimited JSON
+//  file next to its WAV in `--audio-save-dir`:
+//
+//    esp_<ip>_<YYYYmmdd_HHMMSS>.jsonl
+//
+//  one object per line, e.g.
+//
+//    {"t_ms":1760600000123,"elapsed_ms":0,"event":"control","cmd":"session_start","device":"aa:bb:.."}
+//    {"t_ms":...,"elapsed_ms":44,"event":"audio","seq":0,"bytes":1400}
+//    {"t_ms":...,"elapsed_ms":180,"event":"loss","seq":3,"lost":1}
+//    {"t_ms":...,"elapsed_ms":350,"event":"vad","active":true,"energy":412.5}
+//    {"t_ms":...,"elapsed_ms":2900,"event":"openai","type":"response.created"}
+//    {"t_ms":...,"elapsed_ms":3100,"event":"transcript","role":"user","text":"..."}
+//    {"t_ms":...,"elapsed_ms":3120,"event":"wav_saved","path":"recordings/esp_..wav"}
+//
+//  `elapsed_ms` counts from SESSION_START.  Lines are written by one
+//  background task per session, so logging never blocks the receivers.
+//  OpenAI events go to the latest session of the currently wired ESP
+//  (audio deltas are skipped — they arrive every few ms and carry only
+//  audio), even after it ended: the reply to a session only starts once
+//  its audio is committed.  The file is closed when the session drops
+//  out of the recent history below.
+//
+//  Independently of `--session-log`, the last `HISTORY` sessions keep
+//  their significant OpenAI events (speech started/stopped, commits,
+//  responses with usage, errors) verbatim in memory for
+//  `GET /sessions/{id}` — the timeline behind "why did it answer twice".
+//
+//  Every file written for a session (this log, its WAV, its monitoring
+//  copy) is listed under the session's device in the device file index
+//  (`device_data.rs`), for export and erasure.
+//
+//  Each record also carries speech statistics (`SpeechStats`): the
+//  child's talk time from the session's audio VAD transitions, the
+//  robot's from the reply audio sent to the ESP, the number of turns
+//  (changes of speaker) and interruptions (the child starting to talk
+//  while reply audio is still playing).  Like the OpenAI timeline they
+//  keep growing after SESSION_END until the next session opens; the
+//  shared store (`--postgres-url`) gets them as of SESSION_END.
+
+/// Finished sessions kept for the sessions API.
+const HISTORY: usize = 32;
+
+/// OpenAI events kept per session (later ones are counted, not stored).
+const MAX_OPENAI_EVENTS: usize = 256;
+
+/// OpenAI event types stored in the session record.
+const SIGNIFICANT: &[&str] = &[
+    "input_audio_buffer.speech_started",
+    "input_audio_buffer.speech_stopped",
+    "input_audio_buffer.committed",
+    "response.created",
+    "response.done",
+    "error",
+];
+
+/// One session's log: the JSONL writer (with `--session-log`) and the
+/// in-memory record.
+struct SessionLog {
+    tx: Option<mpsc::UnboundedSender<String>>,
+    started: Instant,
+    /// VAD sensor id of the session's audio (see `esp_audio_to_sensor_packet`)
+    sensor_id: u32,
+    /// Last audio VAD state, for logging transitions only
+    vad_active: Option<bool>,
+    speech: SpeechTracker,
+    record: SessionRecord,
+}
+
+/// An ESP session and its OpenAI event timeline.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionRecord {
+    pub id: u64,
+    /// Correlation id of the session (its audio, OpenAI events and
+    /// transcripts carry it too)
+    pub trace: TraceId,
+    pub addr: SocketAddr,
+    pub device: String,
+    pub started_ms: i64,
+    /// None while the session is open
+    pub ended_ms: Option<i64>,
+    /// Ogg Opus monitoring copy (`--monitor-opus-kbps`)
+    pub monitor: Option<String>,
+    /// Significant OpenAI events dropped past `MAX_OPENAI_EVENTS`
+    pub openai_dropped: u64,
+    /// A/B variant of the device, by experiment (`--experiments-file`)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub experiments: BTreeMap<String, String>,
+    pub speech: SpeechStats,
+    pub openai: Vec<OpenAiEvent>,
+}
+
+/// Who talked how much in a session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SpeechStats {
+    /// Time the session's audio VAD was active
+    pub child_talk_ms: u64,
+    /// Reply audio sent to the ESP
+    pub robot_talk_ms: u64,
+    /// Changes of speaker; the first utterance counts as one
+    pub turns: u32,
+    /// Child speech starting while reply audio was still playing
+    pub interruptions: u32,
+}
+
+/// Derives [`SpeechStats`] from VAD transitions and reply audio.
+#[derive(Debug, Default)]
+struct SpeechTracker {
+    stats: SpeechStats,
+    /// Start of the child's current utterance
+    child_since: Option<Instant>,
+    /// When the reply audio sent so far has finished playing
+    robot_until: Option<Instant>,
+    last_speaker: Option<Role>,
+}
+
+impl SpeechTracker {
+    fn vad(&mut self, active: bool, now: Instant) {
+        match (active, self.child_since) {
+            (true, None) => {
+                self.child_since = Some(now);
+                if self.robot_until.is_some_and(|until| now < until) {
+                    self.stats.interruptions += 1;
+                }
+                self.turn(Role::User);
+            }
+            (false, Some(since)) => {
+                self.stats.child_talk_ms += millis(now - since);
+                self.child_since = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// `audio` of reply was sent; it is sent faster than real time, so
+    /// playback is assumed to continue where the previous chunk ends.
+    fn reply_audio(&mut self, audio: Duration, now: Instant) {
+        let playing = self.robot_until.filter(|&until| until > now);
+        if playing.is_none() {
+            self.turn(Role::Assistant);
+        }
+        self.robot_until = Some(playing.unwrap_or(now) + audio);
+        self.stats.robot_talk_ms += millis(audio);
+    }
+
+    fn turn(&mut self, speaker: Role) {
+        if self.last_speaker != Some(speaker) {
+            self.stats.turns += 1;
+            self.last_speaker = Some(speaker);
+        }
+    }
+
+    /// Stats with an utterance still in progress counted up to `now`.
+    fn stats(&self, now: Instant) -> SpeechStats {
+        let mut stats = self.stats;
+        if let Some(since) = self.child_since {
+            stats.child_talk_ms += millis(now - since);
+        }
+        stats
+    }
+}
+
+fn millis(d: Duration) -> u64 {
+    d.as_millis() as u64
+}
+
+/// One OpenAI server event, as received.
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenAiEvent {
+    pub t_ms: i64,
+    /// Since SESSION_START
+    pub elapsed_ms: u64,
+    pub event: Value,
+}
+
+/// `GET /sessions` entry: a record without its events.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionSummary {
+    pub id: u64,
+    pub trace: TraceId,
+    pub addr: SocketAddr,
+    pub device: String,
+    pub started_ms: i64,
+    pub ended_ms: Option<i64>,
+    pub monitor: bool,
+    pub openai_events: usize,
+    /// `response.created` events — more than one per session is the
+    /// "answered twice" case
+    pub responses: usize,
+    pub speech: SpeechStats,
+}
+
+/// Open session logs by ESP address plus the recent history.
+/// Clone-friendly.
+#[derive(Clone)]
+pub struct SessionLogs {
+    dir: Option<Arc<str>>,
+    open: Arc<DashMap<SocketAddr, SessionLog>>,
+    /// Ended sessions, oldest first
+    history: Arc<Mutex<VecDeque<SessionLog>>>,
+    next_id: Arc<AtomicU64>,
+    files: DeviceFiles,
+    /// Ended sessions are also written here (`--postgres-url`)
+    store: SharedStore,
+    /// Ended sessions and OpenAI usage / errors are counted here
+    reports: DailyReports,
+    /// Sessions are tagged with and counted per A/B variant
+    experiments: Experiments,
+}
+
+impl SessionLogs {
+    /// Logs written to `dir` (None = `--session-log` off; records are
+    /// still kept in memory); session files are listed in `files`.
+    pub fn new(dir: Option<&str>, files: DeviceFiles) -> Self {
+        Self {
+            dir: dir.map(Arc::from),
+            open: Arc::default(),
+            history: Arc::default(),
+            next_id: Arc::new(AtomicU64::new(1)),
+            files,
+            store: SharedStore::disabled(),
+            reports: DailyReports::disabled(),
+            experiments: Experiments::disabled(),
+        }
+    }
+
+    /// Also write every ended session to `store`.
+    pub fn with_store(mut self, store: SharedStore) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Also count ended sessions and OpenAI usage in `reports`.
+    pub fn with_reports(mut self, reports: DailyReports) -> Self {
+        self.reports = reports;
+        self
+    }
+
+    /// Tag every session with the device's A/B variants and count it
+    /// per variant.
+    pub fn with_experiments(mut self, experiments: Experiments) -> Self {
+        self.experiments = experiments;
+        self
+    }
+
+    /// Start a new log for the session at `addr`, closing any previous one.
+    /// `to_file: false` (recording policy `never`) keeps it in memory only.
+    pub fn open(&self, addr: SocketAddr, sensor_id: u32, device: &str, to_file: bool) {
+        let dir = self.dir.as_ref().filter(|_| to_file);
+        let tx = dir.map(|dir| {
+            let ts = chrono::Local::now().format("%Y%m%d_%H%M%S");
+            let ip = addr.ip().to_string().replace(['.', ':'], "_");
+            let path = format!("{dir}/esp_{ip}_{ts}.jsonl");
+            self.files.record(device, &path);
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(write_loop(path, rx));
+            tx
+        });
+        let record = SessionRecord {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            trace: TraceId::next(),
+            addr,
+            device: device.to_string(),
+            started_ms: chrono::Utc::now().timestamp_millis(),
+            ended_ms: None,
+            monitor: None,
+            openai_dropped: 0,
+            experiments: self.experiments.device_variants(device),
+            speech: SpeechStats::default(),
+            openai: Vec::new(),
+        };
+        self.experiments.record_session(device);
+        let log = SessionLog {
+            tx,
+            started: Instant::now(),
+            sensor_id,
+            vad_active: None,
+            speech: SpeechTracker::default(),
+            record,
+        };
+        if let Some(previous) = self.open.insert(addr, log) {
+            self.retire(previous);
+        }
+        let trace = self.trace(addr);
+        self.event(addr, "control", json!({ "cmd": "session_start", "device": device, "trace": trace }));
+    }
+
+    /// Trace id of the latest session of `addr`, open or ended.
+    pub fn trace(&self, addr: SocketAddr) -> Option<TraceId> {
+        let mut trace = None;
+        self.latest(addr, |log| {
+            trace = Some(log.record.trace);
+        });
+        trace
+    }
+
+    /// Append `event` with extra `fields` (a JSON object) to `addr`'s log.
+    pub fn event(&self, addr: SocketAddr, event: &str, fields: Value) {
+        if let Some(log) = self.open.get(&addr) {
+            log.write(event, fields);
+        }
+    }
+
+    /// Record an OpenAI server event for the latest session of `addr`,
+    /// open or ended.
+    pub fn openai(&self, addr: SocketAddr, event: &Value) {
+        self.latest(addr, |log| {
+            self.reports.openai(&log.record.device, event);
+            log.openai(event);
+        });
+    }
+
+    /// Attach the monitoring copy at `path` to the latest session of
+    /// `addr` (encoded after the session ended) and log `fields`.
+    pub fn monitor(&self, addr: SocketAddr, path: &str, fields: Value) {
+        self.latest(addr, |log| {
+            log.record.monitor = Some(path.to_string());
+            log.write("monitor_saved", fields);
+            self.files.record(&log.record.device, path);
+        });
+    }
+
+    /// Count `audio` of reply sent to `addr` as the robot talking in its
+    /// latest session.
+    pub fn reply_audio(&self, addr: SocketAddr, audio: Duration) {
+        self.latest(addr, |log| log.speech.reply_audio(audio, Instant::now()));
+    }
+
+    /// Log the session WAV of `addr` saved at `path`.
+    pub fn wav_saved(&self, addr: SocketAddr, path: &str) {
+        self.latest(addr, |log| {
+            log.write("wav_saved", json!({ "path": path }));
+            self.files.record(&log.record.device, path);
+        });
+    }
+
+    /// Run `f` on the open session of `addr`, else its newest ended one.
+    fn latest(&self, addr: SocketAddr, f: impl FnOnce(&mut SessionLog)) {
+        if let Some(mut log) = self.open.get_mut(&addr) {
+            f(&mut log);
+            return;
+        }
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(log) = history.iter_mut().rev().find(|log| log.record.addr == addr) {
+            f(log);
+        }
+    }
+
+    /// Record an audio VAD result for `sensor_id`; only state changes
+    /// are logged (and counted as the child's talk time).
+    pub fn vad(&self, sensor_id: u32, active: bool, energy: f64) {
+        for mut log in self.open.iter_mut() {
+            if log.sensor_id == sensor_id && log.vad_active != Some(active) {
+                log.vad_active = Some(active);
+                log.speech.vad(active, Instant::now());
+                log.write("vad", json!({ "active": active, "energy": energy }));
+            }
+        }
+    }
+
+    /// Log `fields` and close `addr`'s session (no-op if none is open).
+    /// Its OpenAI events keep being recorded until the next one opens.
+    pub fn close(&self, addr: SocketAddr, event: &str, fields: Value) {
+        if let Some((_, log)) = self.open.remove(&addr) {
+            log.write(event, fields);
+            self.retire(log);
+        }
+    }
+
+    fn retire(&self, mut log: SessionLog) {
+        log.record.ended_ms = Some(chrono::Utc::now().timestamp_millis());
+        // The utterance in progress ends with the session
+        log.speech.vad(false, Instant::now());
+        let summary = log.snapshot().summary();
+        self.reports.session(&summary);
+        self.store.session(summary);
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        history.push_back(log);
+        while history.len() > HISTORY {
+            history.pop_front();
+        }
+    }
+
+    /// Open and recent sessions, newest first.
+    pub fn list(&self) -> Vec<SessionSummary> {
+        let mut list: Vec<SessionSummary> = self.open
+            .iter()
+            .map(|log| log.snapshot().summary())
+            .collect();
+        let history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        list.extend(history.iter().map(|log| log.snapshot().summary()));
+        list.sort_by_key(|s| std::cmp::Reverse(s.id));
+        list
+    }
+
+    /// Records of `device`'s open and recent sessions, oldest first.
+    pub fn records(&self, device: &str) -> Vec<SessionRecord> {
+        let mut records: Vec<SessionRecord> = self.open
+            .iter()
+            .filter(|log| log.record.device.eq_ignore_ascii_case(device))
+            .map(|log| log.snapshot())
+            .collect();
+        let history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        records.extend(
+            history
+                .iter()
+                .filter(|log| log.record.device.eq_ignore_ascii_case(device))
+                .map(|log| log.snapshot())
+        );
+        records.sort_by_key(|r| r.id);
+        records
+    }
+
+    /// Drop `device`'s ended sessions (closing their logs); returns how
+    /// many.  An open session is left to finish.
+    pub fn forget(&self, device: &str) -> usize {
+        let mut history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let before = history.len();
+        history.retain(|log| !log.record.device.eq_ignore_ascii_case(device));
+        before - history.len()
+    }
+
+    /// Full record of session `id`, if still known.
+    pub fn get(&self, id: u64) -> Option<SessionRecord> {
+        if let Some(log) = self.open.iter().find(|log| log.record.id == id) {
+            return Some(log.snapshot());
+        }
+        let history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        history
+            .iter()
+            .find(|log| log.record.id == id)
+            .map(|log| log.snapshot())
+    }
+}
+
+impl SessionLog {
+    /// The record with its speech statistics as of now.
+    fn snapshot(&self) -> SessionRecord {
+        let mut record = self.record.clone();
+        record.speech = self.speech.stats(Instant::now());
+        record
+    }
+
+    fn write(&self, event: &str, fields: Value) {
+        if let Some(ref tx) = self.tx {
+            let _ = tx.send(line(self.started, event, fields));
+        }
+    }
+
+    fn openai(&mut self, event: &Value) {
+        let event_type = event["type"].as_str().unwrap_or("");
+        let mut fields = json!({ "type": event_type });
+        for key in ["response_id", "item_id", "error"] {
+            if !event[key].is_null() {
+                fields[key] = event[key].clone();
+            }
+        }
+        if !event["response"]["usage"].is_null() {
+            fields["usage"] = event["response"]["usage"].clone();
+        }
+        self.write("openai", fields);
+
+        if !SIGNIFICANT.contains(&event_type) {
+            return;
+        }
+        if self.record.openai.len() >= MAX_OPENAI_EVENTS {
+            self.record.openai_dropped += 1;
+            return;
+        }
+        self.record.openai.push(OpenAiEvent {
+            t_ms: chrono::Utc::now().timestamp_millis(),
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            event: event.clone(),
+        });
+    }
+}
+
+impl SessionRecord {
+    fn summary(&self) -> SessionSummary {
+        SessionSummary {
+            id: self.id,
+            trace: self.trace,
+            addr: self.addr,
+            device: self.device.clone(),
+            started_ms: self.started_ms,
+            ended_ms: self.ended_ms,
+            monitor: self.monitor.is_some(),
+            openai_events: self.openai.len(),
+            responses: self.openai
+                .iter()
+                .filter(|e| e.event["type"] == "response.created")
+                .count(),
+            speech: self.speech,
+        }
+    }
+}
+
+/// One JSONL line: timestamps, event name, then `fields`.
+fn line(started: Instant, event: &str, fields: Value) -> String {
+    let mut obj = json!({
+        "t_ms": chrono::Utc::now().timestamp_millis(),
+        "elapsed_ms": started.elapsed().as_millis() as u64,
+        "event": event,
+    });
+    if let (Some(obj), Value::Object(fields)) = (obj.as_object_mut(), fields) {
+        obj.extend(fields);
+    }
+    let mut s = obj.to_string();
+    s.push('\n');
+    s
+}
+
+/// Append lines to `path` until the session's sender is dropped.
+async fn write_loop(path: String, mut rx: mpsc::UnboundedReceiver<String>) {
+    if let Some(dir) = std::path::Path::new(&path).parent() {
+        let _ = tokio::fs::create_dir_all(dir).await;
+    }
+    let mut file = match tokio::fs::OpenOptions::new().create(true).append(true).open(&path).await {
+        Ok(f) => f,
+        Err(e) => {
+            warn!(path = %path, error = %e, "failed to open session log");
+            return;
+        }
+    };
+    debug!(path = %path, "📝 session log opened");
+    while let Some(line) = rx.recv().await {
+        // One write per line so a crash loses at most the current event
+        if let Err(e) = file.write_all(line.as_bytes()).await {
+            warn!(path = %path, error = %e, "failed to write session log");
+            return;
+        }
+    }
+    let _ = file.flush().await;
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_merges_fields() {
+        let l = line(Instant::now(), "audio", json!({ "seq": 3, "bytes": 1400 }));
+        assert!(l.ends_with('\n'));
+        let v: Value = serde_json::from_str(&l).unwrap();
+        assert_eq!(v["event"], "audio");
+        assert_eq!(v["seq"], 3);
+        assert!(v["t_ms"].as_i64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_session_log_written_and_closed() {
+        let dir = std::env::temp_dir().join(format!("vad-session-log-{}", std::process::id()));
+        let logs = SessionLogs::new(dir.to_str(), DeviceFiles::default());
+        let addr: SocketAddr = "10.0.0.7:5000".parse().unwrap();
+
+        logs.open(addr, 42, "aa:bb:cc:dd:ee:ff", true);
+        logs.vad(42, true, 500.0);
+        logs.vad(42, true, 510.0); // no transition
+        logs.vad(7, true, 500.0); // other sensor
+        logs.close(addr, "control", json!({ "cmd": "session_end" }));
+        logs.event(addr, "audio", json!({})); // after close: ignored
+
+        let mut text = String::new();
+        for _ in 0..50 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            if let Some(Ok(entry)) = std::fs::read_dir(&dir).ok().and_then(|mut d| d.next()) {
+                text = std::fs::read_to_string(entry.path()).unwrap();
+                if text.lines().count() == 3 {
+                    break;
+                }
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+        let events: Vec<Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let names: Vec<_> = events
+            .iter()
+            .map(|e| e["event"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["control", "vad", "control"]);
+        assert_eq!(events[0]["device"], "aa:bb:cc:dd:ee:ff");
+        assert_eq!(events[2]["cmd"], "session_end");
+    }
+
+    #[tokio::test]
+    async fn test_openai_timeline_follows_ended_session() {
+        let logs = SessionLogs::new(None, DeviceFiles::default());
+        let addr: SocketAddr = "10.0.0.7:5000".parse().unwrap();
+
+        logs.open(addr, 42, "aa:bb", true);
+        logs.openai(addr, &json!({ "type": "input_audio_buffer.speech_started" }));
+        logs.openai(addr, &json!({ "type": "conversation.item.created" })); // not significant
+        logs.close(addr, "control", json!({ "cmd": "session_end" }));
+        // The reply arrives after SESSION_END
+        logs.openai(addr, &json!({ "type": "response.created" }));
+        logs.openai(addr, &json!({ "type": "response.done", "response": { "usage": { "total_tokens": 9 } } }));
+
+        logs.open(addr, 42, "aa:bb", true);
+        logs.openai(addr, &json!({ "type": "error", "error": { "message": "boom" } }));
+
+        let list = logs.list();
+        assert_eq!(list.len(), 2);
+        assert_eq!((list[0].id, list[0].ended_ms), (2, None));
+        assert_eq!((list[1].openai_events, list[1].responses), (3, 1));
+
+        let first = logs.get(1).unwrap();
+        let types: Vec<_> = first.openai
+            .iter()
+            .map(|e| e.event["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(types, ["input_audio_buffer.speech_started", "response.created", "response.done"]);
+        assert_eq!(first.openai[2].event["response"]["usage"]["total_tokens"], 9);
+        assert_eq!(logs.get(2).unwrap().openai.len(), 1);
+        assert!(logs.get(3).is_none());
+    }
+
+    #[test]
+    fn test_speech_stats_talk_turns_interruptions() {
+        let t0 = Instant::now();
+        let at = |ms: u64| t0 + Duration::from_millis(ms);
+        let mut speech = SpeechTracker::default();
+
+        speech.vad(true, at(0));
+        speech.vad(false, at(1500));
+        // Reply arrives in two chunks, faster than it plays: 0..2000 ms
+        speech.reply_audio(Duration::from_millis(1200), at(2000));
+        speech.reply_audio(Duration::from_millis(800), at(2100));
+        // Child barges in 1 s into the reply
+        speech.vad(true, at(3000));
+        assert_eq!(speech.stats(at(3400)).child_talk_ms, 1900);
+        speech.vad(false, at(3500));
+        // A new reply after the old one finished playing
+        speech.reply_audio(Duration::from_millis(500), at(6000));
+
+        let stats = speech.stats(at(7000));
+        assert_eq!(stats, SpeechStats { child_talk_ms: 2000, robot_talk_ms: 2500, turns: 4, interruptions: 1 });
+    }
+
+    #[tokio::test]
+    async fn test_speech_stats_without_session_log() {
+        let logs = SessionLogs::new(None, DeviceFiles::default());
+        let addr: SocketAddr = "10.0.0.7:5000".parse().unwrap();
+
+        logs.open(addr, 42, "aa:bb", true);
+        logs.vad(42, true, 500.0);
+        logs.close(addr, "control", json!({ "cmd": "session_end" }));
+        logs.reply_audio(addr, Duration::from_millis(700));
+
+        let speech = logs.get(1).unwrap().speech;
+        assert_eq!((speech.robot_talk_ms, speech.turns, speech.interruptions), (700, 2, 0));
+        assert_eq!(logs.list()[0].speech, speech);
+    }
+}
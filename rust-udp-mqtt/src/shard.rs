@@ -0,0 +1,243 @@
+use crate::clock::SharedClock;
+use crate::sensor::{ SensorPacket, DATA_TYPE_AUDIO };
+use crate::trace::TraceId;
+use tokio::sync::mpsc;
+use std::time::Duration;
+use tokio::sync::mpsc::error::{ SendError, TrySendError };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Sensor sharding (receivers → VAD workers)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Every VAD worker owns one queue.  A packet goes to the queue of
+//  `shard_of(sensor_id)`, so all packets of one device are processed by
+//  the same worker, in arrival order — results of a device are never
+//  reordered by two workers racing each other.  The smoother stripes
+//  its per-sensor state the same way, so each worker only ever locks
+//  its own stripe.
+//
+//  A single busy device is bounded by one worker; the fleet spreads over
+//  all of them.
+//
+//  Lanes:  audio and sensor vectors have separate worker pools with
+//          their own queues (`--audio-proc-threads` /
+//          `--audio-channel-capacity` vs `--proc-threads` /
+//          `--channel-capacity`), so a burst of one kind never starves
+//          the other.  Sharding applies within each lane.
+//
+//  Packets are stamped with `ingested_at` as they are queued, so a
+//  worker can shed those that waited past `--packet-deadline-ms`
+//  (`is_stale`) instead of working through a backlog late.  Packets
+//  without a trace id (see `trace.rs`) get a fresh one here.
+
+/// Shard (worker index) that owns `sensor_id`.
+#[inline]
+pub fn shard_of(sensor_id: u32, shards: usize) -> usize {
+    // Fibonacci hashing: consecutive ids spread evenly
+    let hash = (sensor_id as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32;
+    (hash as usize) % shards.max(1)
+}
+
+/// Worker pool a packet is processed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    /// PCM audio (RMS VAD)
+    Audio,
+    /// Sensor vectors and any other data type (emotional VAD)
+    Sensor,
+}
+
+impl Lane {
+    pub fn of(packet: &SensorPacket) -> Self {
+        if packet.data_type == DATA_TYPE_AUDIO { Lane::Audio } else { Lane::Sensor }
+    }
+}
+
+impl std::fmt::Display for Lane {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lane::Audio => write!(f, "audio"),
+            Lane::Sensor => write!(f, "sensor"),
+        }
+    }
+}
+
+/// Size of one lane: its workers and each worker's queue.
+#[derive(Debug, Clone, Copy)]
+pub struct LaneConfig {
+    pub workers: usize,
+    pub capacity: usize,
+}
+
+/// Sending side of the per-shard queues of both lanes.  Clone-friendly.
+#[derive(Clone)]
+pub struct ShardedSender {
+    audio: Vec<mpsc::Sender<SensorPacket>>,
+    sensor: Vec<mpsc::Sender<SensorPacket>>,
+    clock: SharedClock,
+}
+
+/// One bounded queue per worker of each lane; packets are stamped on
+/// `clock` as they are queued.  Receivers come audio lane first.
+pub fn channels(
+    audio: LaneConfig,
+    sensor: LaneConfig,
+    clock: SharedClock
+) -> (ShardedSender, Vec<(Lane, mpsc::Receiver<SensorPacket>)>) {
+    let lane = |lane: Lane, config: LaneConfig| -> (Vec<_>, Vec<_>) {
+        (0..config.workers.max(1))
+            .map(|_| {
+                let (tx, rx) = mpsc::channel(config.capacity.max(1));
+                (tx, (lane, rx))
+            })
+            .unzip()
+    };
+    let (audio, mut receivers) = lane(Lane::Audio, audio);
+    let (sensor, sensor_rx) = lane(Lane::Sensor, sensor);
+    receivers.extend(sensor_rx);
+    (ShardedSender { audio, sensor, clock }, receivers)
+}
+
+/// Whether `packet` has waited longer than `deadline` since it was
+/// queued (never for a zero deadline or an unstamped packet).
+pub fn is_stale(packet: &SensorPacket, deadline: Duration, clock: &SharedClock) -> bool {
+    match packet.ingested_at {
+        Some(at) if !deadline.is_zero() => clock.now().saturating_duration_since(at) > deadline,
+        _ => false,
+    }
+}
+
+impl ShardedSender {
+    pub fn workers(&self, lane: Lane) -> usize {
+        self.lane(lane).len()
+    }
+
+    /// Packets queued and not yet taken by a worker, both lanes.
+    pub fn backlog(&self) -> usize {
+        self.audio
+            .iter()
+            .chain(&self.sensor)
+            .map(|tx| tx.max_capacity() - tx.capacity())
+            .sum()
+    }
+
+    fn lane(&self, lane: Lane) -> &[mpsc::Sender<SensorPacket>] {
+        match lane {
+            Lane::Audio => &self.audio,
+            Lane::Sensor => &self.sensor,
+        }
+    }
+
+    fn queue(&self, packet: &SensorPacket) -> &mpsc::Sender<SensorPacket> {
+        let lane = self.lane(Lane::of(packet));
+        &lane[shard_of(packet.sensor_id, lane.len())]
+    }
+
+    /// Queue without waiting (fails when the device's shard is full).
+    pub fn try_send(&self, mut packet: SensorPacket) -> Result<(), TrySendError<SensorPacket>> {
+        packet.ingested_at.get_or_insert(self.clock.now());
+        packet.trace.get_or_insert_with(TraceId::next);
+        self.queue(&packet).try_send(packet)
+    }
+
+    /// Queue, waiting for room in the device's shard.  The packet is
+    /// stamped before the wait: time blocked on a full shard counts.
+    pub async fn send(&self, mut packet: SensorPacket) -> Result<(), SendError<SensorPacket>> {
+        packet.ingested_at.get_or_insert(self.clock.now());
+        packet.trace.get_or_insert_with(TraceId::next);
+        self.queue(&packet).send(packet).await
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pcm::SampleFormat;
+    use crate::tenants::TenantId;
+
+    fn packet(sensor_id: u32, seq: u64) -> SensorPacket {
+        SensorPacket {
+            sensor_id,
+            timestamp_us: 0,
+            data_type: 0,
+            sample_format: SampleFormat::S16,
+            seq,
+            payload: Vec::new(),
+            tenant: TenantId::default(),
+            ingested_at: None,
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn test_shard_of_is_stable_and_spread() {
+        let mut used = [0usize; 4];
+        for id in 0..400 {
+            let shard = shard_of(id, 4);
+            assert_eq!(shard, shard_of(id, 4));
+            used[shard] += 1;
+        }
+        assert!(used.iter().all(|&n| n > 50), "uneven shards: {used:?}");
+        assert_eq!(shard_of(7, 1), 0);
+        assert_eq!(shard_of(7, 0), 0);
+    }
+
+    fn lane(workers: usize, capacity: usize) -> LaneConfig {
+        LaneConfig { workers, capacity }
+    }
+
+    #[tokio::test]
+    async fn test_device_packets_stay_in_order_on_one_shard() {
+        let (tx, mut rxs) = channels(lane(1, 1), lane(3, 64), crate::clock::system());
+        for seq in 0..10 {
+            for id in [1, 2, 3, 4] {
+                tx.send(packet(id, seq)).await.unwrap();
+            }
+        }
+        let mut seqs: std::collections::HashMap<u32, Vec<u64>> = Default::default();
+        for (shard, (lane, rx)) in rxs.iter_mut().skip(1).enumerate() {
+            assert_eq!(*lane, Lane::Sensor);
+            while let Ok(p) = rx.try_recv() {
+                assert_eq!(shard_of(p.sensor_id, 3), shard);
+                seqs.entry(p.sensor_id).or_default().push(p.seq);
+            }
+        }
+        for id in [1, 2, 3, 4] {
+            assert_eq!(seqs[&id], (0..10).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_full_audio_lane_does_not_block_sensors() {
+        let (tx, rxs) = channels(lane(1, 1), lane(2, 1), crate::clock::system());
+        assert_eq!((tx.workers(Lane::Audio), tx.workers(Lane::Sensor), rxs.len()), (1, 2, 3));
+        let audio = || SensorPacket { data_type: DATA_TYPE_AUDIO, ..packet(1, 0) };
+        tx.try_send(audio()).unwrap();
+        assert!(tx.try_send(audio()).is_err());
+        tx.try_send(packet(1, 0)).unwrap();
+        assert_eq!(tx.backlog(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_packets_are_stamped_and_go_stale() {
+        let sim = crate::clock::SimClock::default();
+        let clock: SharedClock = std::sync::Arc::new(sim.clone());
+        let (tx, mut rxs) = channels(lane(1, 8), lane(1, 8), clock.clone());
+        tx.send(packet(1, 0)).await.unwrap();
+        let p = rxs[1].1.recv().await.unwrap();
+        assert_eq!(p.ingested_at, Some(clock.now()));
+        assert!(p.trace.is_some());
+
+        let deadline = Duration::from_millis(200);
+        sim.advance(Duration::from_millis(200));
+        assert!(!is_stale(&p, deadline, &clock));
+        sim.advance(Duration::from_millis(1));
+        assert!(is_stale(&p, deadline, &clock));
+        assert!(!is_stale(&p, Duration::ZERO, &clock));
+        assert!(!is_stale(&packet(1, 1), deadline, &clock));
+    }
+}
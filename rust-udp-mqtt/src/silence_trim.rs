@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Head/tail silence trimming for OpenAI commits
+// ─────────────────────────────────────────────────────────────────────
+//
+//  ESP sessions often start with the wake-word tail and end with
+//  seconds of room noise before the user (or firmware timeout) sends
+//  SESSION_END.  All of it used to be streamed to OpenAI and committed,
+//  costing tokens and transcription latency.
+//
+//  With `--openai-trim-silence` each session's audio passes through a
+//  `SilenceTrimmer` on its way to `input_audio_buffer.append`.  Every
+//  chunk is classified with the same RMS energy test as the local audio
+//  VAD (`--audio-threshold`):
+//
+//    silent, before any speech  → held as pre-roll, only the last
+//                                 `pad` kept
+//    speech                     → held silence flushed, chunk forwarded
+//    silent, after speech       → held (may be a pause or the tail)
+//
+//  On SESSION_END the first `pad` of the held tail is forwarded and the
+//  rest dropped before the commit.  Streaming stays live: only silence
+//  is ever delayed.  The session WAV on disk is not trimmed.
+
+/// Bytes per second of 16 kHz s16 mono.
+const BYTES_PER_SEC: f64 = 16_000.0 * 2.0;
+
+/// Streaming trimmer for one ESP session.
+#[derive(Debug)]
+pub struct SilenceTrimmer {
+    threshold: f64,
+    pad_bytes: usize,
+    heard_speech: bool,
+    /// Silent chunks not forwarded yet (pre-roll, pause or tail)
+    held: VecDeque<Vec<u8>>,
+    held_bytes: usize,
+    trimmed_head: usize,
+    trimmed_tail: usize,
+}
+
+impl SilenceTrimmer {
+    /// `threshold` is an RMS level on 16-bit samples; `pad` is how much
+    /// silence to keep before the first and after the last speech.
+    pub fn new(threshold: f64, pad: Duration) -> Self {
+        Self {
+            threshold,
+            pad_bytes: ((pad.as_secs_f64() * BYTES_PER_SEC) as usize) & !1,
+            heard_speech: false,
+            held: VecDeque::new(),
+            held_bytes: 0,
+            trimmed_head: 0,
+            trimmed_tail: 0,
+        }
+    }
+
+    /// Feed one chunk of 16 kHz s16 LE PCM; returns the chunks to
+    /// forward now (possibly none).
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Vec<u8>> {
+        if rms(chunk) > self.threshold {
+            self.heard_speech = true;
+            let mut out: Vec<Vec<u8>> = self.held.drain(..).collect();
+            self.held_bytes = 0;
+            out.push(chunk.to_vec());
+            return out;
+        }
+
+        self.held.push_back(chunk.to_vec());
+        self.held_bytes += chunk.len();
+        if !self.heard_speech {
+            // Drop pre-roll older than `pad`
+            while let Some(front) = self.held.front() {
+                if self.held_bytes - front.len() < self.pad_bytes {
+                    break;
+                }
+                self.held_bytes -= front.len();
+                self.trimmed_head += front.len();
+                self.held.pop_front();
+            }
+        }
+        Vec::new()
+    }
+
+    /// Session over: returns the first `pad` of the held tail and drops
+    /// the rest (everything, if no speech was heard).
+    pub fn finish(&mut self) -> Vec<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut kept = 0;
+        for chunk in self.held.drain(..) {
+            if self.heard_speech && kept < self.pad_bytes {
+                kept += chunk.len();
+                out.push(chunk);
+            } else if self.heard_speech {
+                self.trimmed_tail += chunk.len();
+            } else {
+                self.trimmed_head += chunk.len();
+            }
+        }
+        self.held_bytes = 0;
+        out
+    }
+
+    /// Whether any chunk crossed the threshold.
+    pub fn heard_speech(&self) -> bool {
+        self.heard_speech
+    }
+
+    /// Silence dropped from the (head, tail) so far.
+    pub fn trimmed(&self) -> (Duration, Duration) {
+        let secs = |bytes: usize| Duration::from_secs_f64((bytes as f64) / BYTES_PER_SEC);
+        (secs(self.trimmed_head), secs(self.trimmed_tail))
+    }
+}
+
+/// RMS of 16-bit LE samples (0 for an empty chunk).
+fn rms(pcm: &[u8]) -> f64 {
+    let n = pcm.len() / 2;
+    if n == 0 {
+        return 0.0;
+    }
+    let sum_sq: f64 = pcm
+        .chunks_exact(2)
+        .map(|s| {
+            let v = i16::from_le_bytes([s[0], s[1]]) as f64;
+            v * v
+        })
+        .sum();
+    (sum_sq / (n as f64)).sqrt()
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 100 ms chunk of constant level.
+    fn chunk(level: i16) -> Vec<u8> {
+        (0..1600).flat_map(|_| level.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_trims_head_and_tail_keeping_pad() {
+        let mut t = SilenceTrimmer::new(30.0, Duration::from_millis(200));
+        let mut sent = 0;
+        for _ in 0..10 {
+            sent += t.push(&chunk(0)).len();
+        }
+        assert_eq!(sent, 0, "pre-roll held back");
+
+        // Speech releases the 200 ms pre-roll plus itself
+        assert_eq!(t.push(&chunk(1000)).len(), 3);
+
+        // A pause is forwarded once speech resumes
+        assert!(t.push(&chunk(0)).is_empty());
+        assert_eq!(t.push(&chunk(1000)).len(), 2);
+
+        for _ in 0..20 {
+            assert!(t.push(&chunk(5)).is_empty());
+        }
+        assert_eq!(t.finish().len(), 2);
+        assert!(t.heard_speech());
+        let (head, tail) = t.trimmed();
+        assert_eq!(head.as_millis(), 800);
+        assert_eq!(tail.as_millis(), 1800);
+    }
+
+    #[test]
+    fn test_only_silence_sends_nothing() {
+        let mut t = SilenceTrimmer::new(30.0, Duration::from_millis(300));
+        for _ in 0..5 {
+            assert!(t.push(&chunk(10)).is_empty());
+        }
+        assert!(t.finish().is_empty());
+        assert!(!t.heard_speech());
+        assert_eq!(t.trimmed().0.as_millis(), 500);
+    }
+}
@@ -0,0 +1,369 @@
+use crate::clock::{ self, SharedClock };
+use crate::emotion_history::parse_time;
+use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Ambient sound levels (long-term noise monitoring)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Facilities want to know when and where it gets loud, not what was
+//  said.  The VAD workers fold every audio packet into per-device
+//  energy sums (no audio is kept):
+//
+//    seconds   one energy sum per second, for the rolling level over
+//              `--sound-level-window-secs`
+//    hours     energy-averaged level of the hour plus its loudest and
+//              quietest second, kept for `--sound-level-days`
+//
+//  Levels are LAeq-style equivalent continuous levels — the mean energy
+//  of the samples in dBFS (0 = full scale, floor -96) — but unweighted:
+//  the bridge does not know the microphone's response, so values compare
+//  devices of one model and one device over time, not against dB(A)
+//  limits.  A second is folded into its hour once the next one starts
+//  (or after a minute of silence from the device).
+//
+//    GET /stats/sound-levels                     rolling level, every device
+//    GET /stats/sound-levels/{id}?from=&to=      hourly history (24 h default)
+//
+//  Like the emotion timeline, history is in memory only.
+
+/// Level of digital silence.
+const FLOOR_DB: f32 = -96.0;
+
+/// Default history range.
+const DEFAULT_RANGE_MS: u64 = 24 * 3_600_000;
+
+/// Audio energy of one second (or of an hour so far).
+#[derive(Debug, Clone, Copy, Default)]
+struct Energy {
+    /// Σ x² over samples scaled to ±1
+    sum: f64,
+    samples: u64,
+}
+
+impl Energy {
+    fn add(&mut self, other: Energy) {
+        self.sum += other.sum;
+        self.samples += other.samples;
+    }
+
+    fn db(&self) -> f32 {
+        if self.samples == 0 || self.sum <= 0.0 {
+            return FLOOR_DB;
+        }
+        let db = (10.0 * (self.sum / (self.samples as f64)).log10()) as f32;
+        round1(db.max(FLOOR_DB))
+    }
+}
+
+fn round1(db: f32) -> f32 {
+    (db * 10.0).round() / 10.0
+}
+
+/// One hour of one device.
+#[derive(Debug, Clone, Copy)]
+struct Hour {
+    hour: u64,
+    energy: Energy,
+    seconds: u32,
+    max_db: f32,
+    min_db: f32,
+}
+
+#[derive(Default)]
+struct DeviceLevels {
+    /// The second still receiving audio
+    open: Option<(u64, Energy)>,
+    /// Closed seconds within the rolling window, oldest first
+    seconds: VecDeque<(u64, Energy)>,
+    /// Oldest first
+    hours: VecDeque<Hour>,
+}
+
+impl DeviceLevels {
+    /// Fold the open second into the window and its hour.
+    fn close(&mut self) {
+        let Some((second, energy)) = self.open.take() else {
+            return;
+        };
+        self.seconds.push_back((second, energy));
+        let db = energy.db();
+        let hour = second / 3600;
+        match self.hours.back_mut() {
+            Some(h) if h.hour == hour => {
+                h.energy.add(energy);
+                h.seconds += 1;
+                h.max_db = h.max_db.max(db);
+                h.min_db = h.min_db.min(db);
+            }
+            _ => self.hours.push_back(Hour { hour, energy, seconds: 1, max_db: db, min_db: db }),
+        }
+    }
+
+    fn trim(&mut self, now_s: u64, window_secs: u64, retention_secs: u64) {
+        let oldest = now_s.saturating_sub(window_secs);
+        while self.seconds.front().is_some_and(|(s, _)| *s <= oldest) {
+            self.seconds.pop_front();
+        }
+        let oldest_hour = now_s.saturating_sub(retention_secs) / 3600;
+        while self.hours.front().is_some_and(|h| h.hour < oldest_hour) {
+            self.hours.pop_front();
+        }
+    }
+}
+
+/// Current level of one device, as listed by `GET /stats/sound-levels`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SoundLevelNow {
+    pub sensor_id: u32,
+    /// Level over the rolling window (None = no audio in it)
+    pub leq_db: Option<f32>,
+    pub window_secs: u64,
+    /// Level of the current hour so far
+    pub hour_leq_db: Option<f32>,
+}
+
+/// One hour of `GET /stats/sound-levels/{id}`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HourLevel {
+    /// Start of the hour (unix ms)
+    pub t_ms: u64,
+    pub leq_db: f32,
+    /// Loudest / quietest one-second level within the hour
+    pub max_db: f32,
+    pub min_db: f32,
+    /// Seconds with audio
+    pub seconds: u32,
+}
+
+/// Response of `GET /stats/sound-levels/{id}`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SoundLevelHistory {
+    pub sensor_id: u32,
+    pub from_ms: u64,
+    pub to_ms: u64,
+    /// Level over every hour in range (None = no audio)
+    pub leq_db: Option<f32>,
+    pub hours: Vec<HourLevel>,
+}
+
+/// Raw query parameters, as strings.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct SoundLevelQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// Sound levels of every device.  Clone-friendly (Arc inside).
+#[derive(Clone)]
+pub struct SoundLevels {
+    devices: Arc<DashMap<u32, DeviceLevels>>,
+    window: Duration,
+    /// How long hourly levels are kept (zero = monitoring disabled)
+    retention: Duration,
+    clock: SharedClock,
+}
+
+impl SoundLevels {
+    pub fn new(window: Duration, retention: Duration, clock: SharedClock) -> Self {
+        Self { devices: Arc::new(DashMap::new()), window, retention, clock }
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.retention.is_zero()
+    }
+
+    /// Fold one packet of s16 LE audio from `sensor_id` into its current
+    /// second.
+    pub fn record(&self, sensor_id: u32, pcm: &[u8]) {
+        if !self.enabled() {
+            return;
+        }
+        let mut energy = Energy::default();
+        for c in pcm.chunks_exact(2) {
+            let x = (i16::from_le_bytes([c[0], c[1]]) as f64) / 32768.0;
+            energy.sum += x * x;
+            energy.samples += 1;
+        }
+        if energy.samples == 0 {
+            return;
+        }
+        let now_s = clock::unix_millis(self.clock.as_ref()) / 1000;
+        let mut levels = self.devices.entry(sensor_id).or_default();
+        match levels.open.as_mut() {
+            // VAD workers race, so a packet may land a second late: it
+            // joins the open second rather than reopening a closed one
+            Some((second, open)) if *second >= now_s => open.add(energy),
+            _ => {
+                levels.close();
+                levels.open = Some((now_s, energy));
+            }
+        }
+        levels.trim(now_s, self.window.as_secs(), self.retention.as_secs());
+    }
+
+    /// Close seconds of devices that went quiet, drop expired hours and
+    /// devices with nothing left; returns how many devices were removed.
+    pub fn sweep(&self) -> usize {
+        let now_s = clock::unix_millis(self.clock.as_ref()) / 1000;
+        let before = self.devices.len();
+        self.devices.retain(|_, levels| {
+            if levels.open.is_some_and(|(second, _)| second + 60 <= now_s) {
+                levels.close();
+            }
+            levels.trim(now_s, self.window.as_secs(), self.retention.as_secs());
+            levels.open.is_some() || !levels.hours.is_empty()
+        });
+        before - self.devices.len()
+    }
+
+    /// Rolling level of every device, by sensor id.
+    pub fn list(&self) -> Vec<SoundLevelNow> {
+        let now_s = clock::unix_millis(self.clock.as_ref()) / 1000;
+        let window_secs = self.window.as_secs();
+        let mut out: Vec<SoundLevelNow> = self.devices
+            .iter()
+            .map(|entry| {
+                let levels = entry.value();
+                let open = levels.open.filter(|(s, _)| *s + window_secs > now_s);
+                let mut window = Energy::default();
+                for (_, e) in levels.seconds.iter().filter(|(s, _)| *s + window_secs > now_s).chain(open.iter()) {
+                    window.add(*e);
+                }
+                let mut hour = levels.hours
+                    .back()
+                    .filter(|h| h.hour == now_s / 3600)
+                    .map(|h| h.energy)
+                    .unwrap_or_default();
+                if let Some((_, e)) = levels.open.filter(|(s, _)| *s / 3600 == now_s / 3600) {
+                    hour.add(e);
+                }
+                SoundLevelNow {
+                    sensor_id: *entry.key(),
+                    leq_db: (window.samples > 0).then(|| window.db()),
+                    window_secs,
+                    hour_leq_db: (hour.samples > 0).then(|| hour.db()),
+                }
+            })
+            .collect();
+        out.sort_by_key(|l| l.sensor_id);
+        out
+    }
+
+    /// Hourly levels of `sensor_id` for `query` (parameter errors are
+    /// returned as a message for a 400).
+    pub fn history(&self, sensor_id: u32, query: &SoundLevelQuery) -> Result<SoundLevelHistory, String> {
+        let now_ms = clock::unix_millis(self.clock.as_ref());
+        let to_ms = match &query.to {
+            Some(s) => parse_time(s).map_err(|e| format!("invalid `to`: {e}"))?,
+            None => now_ms,
+        };
+        let from_ms = match &query.from {
+            Some(s) => parse_time(s).map_err(|e| format!("invalid `from`: {e}"))?,
+            None => to_ms.saturating_sub(DEFAULT_RANGE_MS),
+        };
+        if from_ms >= to_ms {
+            return Err("`from` must be before `to`".into());
+        }
+
+        let mut total = Energy::default();
+        let mut hours = Vec::new();
+        if let Some(levels) = self.devices.get(&sensor_id) {
+            // Every hour overlapping the range
+            for h in levels.hours.iter().filter(|h| (h.hour + 1) * 3_600_000 > from_ms && h.hour * 3_600_000 < to_ms) {
+                total.add(h.energy);
+                hours.push(HourLevel {
+                    t_ms: h.hour * 3_600_000,
+                    leq_db: h.energy.db(),
+                    max_db: h.max_db,
+                    min_db: h.min_db,
+                    seconds: h.seconds,
+                });
+            }
+        }
+        Ok(SoundLevelHistory {
+            sensor_id,
+            from_ms,
+            to_ms,
+            leq_db: (total.samples > 0).then(|| total.db()),
+            hours,
+        })
+    }
+}
+
+/// Periodically close idle seconds and drop expired levels (no-op when
+/// monitoring is disabled).
+pub async fn sweep_loop(levels: SoundLevels) {
+    if !levels.enabled() {
+        return;
+    }
+    loop {
+        levels.clock.sleep(Duration::from_secs(60)).await;
+        let evicted = levels.sweep();
+        if evicted > 0 {
+            tracing::info!(evicted, "🧹 evicted expired sound levels");
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimClock;
+
+    /// 100 ms of a constant sample `amplitude` (dBFS = 20·log10(amplitude / 32768)).
+    fn tone(amplitude: i16) -> Vec<u8> {
+        amplitude.to_le_bytes().repeat(1600)
+    }
+
+    #[test]
+    fn test_rolling_and_hourly_levels() {
+        let sim = SimClock::starting_at(std::time::UNIX_EPOCH);
+        let levels = SoundLevels::new(Duration::from_secs(60), Duration::from_secs(86_400), Arc::new(sim.clone()));
+
+        // Hour 0: 30 s at -20 dBFS (3277), then 30 s at -40 dBFS (328)
+        for s in 0..60 {
+            levels.record(4, &tone(if s < 30 { 3277 } else { 328 }));
+            sim.advance(Duration::from_secs(1));
+        }
+        let now = &levels.list()[0];
+        assert_eq!((now.sensor_id, now.window_secs), (4, 60));
+        // Energy average is dominated by the loud half: -20 - 10·log10(2)
+        assert_eq!(now.leq_db, Some(-23.0));
+
+        // Hour 1: 10 s at -40 dBFS; the device then goes quiet
+        sim.advance(Duration::from_secs(3600 - 60));
+        for _ in 0..10 {
+            levels.record(4, &tone(328));
+            sim.advance(Duration::from_secs(1));
+        }
+        sim.advance(Duration::from_secs(120));
+        assert_eq!(levels.sweep(), 0);
+        assert_eq!(levels.list()[0].leq_db, None, "quiet for longer than the window");
+
+        let query = SoundLevelQuery { from: Some("0".into()), to: Some("7200000".into()) };
+        let history = levels.history(4, &query).unwrap();
+        let hours: Vec<_> = history.hours
+            .iter()
+            .map(|h| (h.t_ms, h.leq_db, h.max_db, h.min_db, h.seconds))
+            .collect();
+        assert_eq!(hours, [
+            (0, -23.0, -20.0, -40.0, 60),
+            (3_600_000, -40.0, -40.0, -40.0, 10),
+        ]);
+        assert!(levels.history(4, &SoundLevelQuery { from: Some("5".into()), to: Some("5".into()) }).is_err());
+
+        // Past retention the device is forgotten
+        sim.advance(Duration::from_secs(86_400 + 3600));
+        assert_eq!(levels.sweep(), 1);
+        assert!(levels.list().is_empty());
+    }
+}
@@ -0,0 +1,47 @@
+//! Learned speaker-embedding model (ONNX Runtime).
+//!
+//! Input:  `[1, samples]` f32 — 16 kHz mono waveform scaled to ±1.0 (the
+//!         model must include its own feature front end, e.g. fbank).
+//! Output: first output tensor — the speaker embedding (any length;
+//!         compared by cosine similarity in `speakers.rs`).
+//!
+//! ONNX Runtime is loaded dynamically, as for `emotion_onnx.rs`.
+
+use ort::session::Session;
+use ort::value::Tensor;
+use std::sync::Mutex;
+
+/// A loaded ONNX speaker model, shared by every device.
+pub struct OnnxSpeakerModel {
+    session: Mutex<Session>,
+}
+
+impl OnnxSpeakerModel {
+    /// Load a model from disk.
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let session = Session::builder()
+            .map_err(|e| anyhow::anyhow!("ONNX Runtime init failed: {e}"))?
+            .commit_from_file(path)
+            .map_err(|e| anyhow::anyhow!("failed to load ONNX model {path}: {e}"))?;
+        Ok(Self { session: Mutex::new(session) })
+    }
+
+    /// Embed one utterance of 16 kHz s16 audio.
+    pub fn embed(&self, pcm: &[i16]) -> anyhow::Result<Vec<f32>> {
+        if pcm.is_empty() {
+            anyhow::bail!("no audio");
+        }
+        let input: Vec<f32> = pcm
+            .iter()
+            .map(|&s| (s as f32) / 32768.0)
+            .collect();
+        let tensor = Tensor::from_array(([1usize, input.len()], input))?;
+        let mut session = self.session.lock().unwrap_or_else(|e| e.into_inner());
+        let outputs = session.run(ort::inputs![tensor])?;
+        let (_shape, data) = outputs[0].try_extract_tensor::<f32>()?;
+        if data.is_empty() {
+            anyhow::bail!("ONNX speaker model returned an empty embedding");
+        }
+        Ok(data.to_vec())
+    }
+}
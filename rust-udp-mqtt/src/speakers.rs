@@ -0,0 +1,387 @@
+use crate::config::Config;
+use crate::mel::{ self, MelBands, FRAME, HOP };
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{ Arc, Mutex };
+use tracing::{ debug, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Speaker diarization (which child said that)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  With `--speaker-engine`, the first PROBE_SECS of every device session
+//  sent to OpenAI are also kept as a voice probe.  When the session is
+//  committed the probe is turned into a speaker embedding off the async
+//  runtime and matched (cosine similarity) against the speakers already
+//  heard on that device:
+//
+//    ≥ --speaker-threshold   same speaker; their centroid is updated
+//    below                   a new speaker `speaker_<n>` (at most
+//                            MAX_SPEAKERS per device, then the closest)
+//
+//  The label is held for the device until its next user transcript
+//  arrives, which is then tagged with it in the conversation history,
+//  forwarded transcripts and the session log.  Speakers can be named
+//  over REST (`PUT /devices/{device}/speakers/{id}`); later transcripts
+//  carry the name instead of `speaker_<n>`.
+//
+//  Engines:
+//
+//    spectral   built-in: mean log energies of BANDS mel-spaced bands over
+//               voiced frames, gain-normalised — a coarse voice timbre
+//               that separates a few children on one robot
+//    onnx       a speaker-embedding model (`--speaker-model-path`, needs
+//               `--features onnx`), see `speaker_onnx.rs`
+//
+//  Speakers are kept in memory per device name (MAC or `ip:port`).
+
+/// Seconds of session audio kept for the embedding.
+const PROBE_SECS: usize = 10;
+/// Speakers remembered per device.
+const MAX_SPEAKERS: usize = 8;
+
+const SAMPLE_RATE: f32 = 16_000.0;
+/// Mel-spaced bands of the spectral embedding (100 Hz – 7 kHz).
+const BANDS: usize = 24;
+/// Frames quieter than this RMS (s16) are treated as silence.
+const VOICED_RMS: f32 = 300.0;
+
+/// CLI selector for the speaker embedding engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SpeakerEngineKind {
+    /// No diarization (default)
+    #[default]
+    Off,
+    /// Built-in band-energy voice print
+    Spectral,
+    /// Speaker-embedding model from `--speaker-model-path` (needs the `onnx` feature)
+    Onnx,
+}
+
+enum SpeakerEngine {
+    Spectral,
+    #[cfg(feature = "onnx")]
+    Onnx(crate::speaker_onnx::OnnxSpeakerModel),
+}
+
+impl SpeakerEngine {
+    fn embed(&self, pcm: &[i16]) -> anyhow::Result<Vec<f32>> {
+        match self {
+            SpeakerEngine::Spectral => spectral_embedding(pcm).ok_or_else(|| anyhow::anyhow!("no voiced audio")),
+            #[cfg(feature = "onnx")]
+            SpeakerEngine::Onnx(model) => model.embed(pcm),
+        }
+    }
+}
+
+/// Per-device speaker registry plus the embedding engine.  Clone-friendly;
+/// a disabled diarizer hands out no probes.
+#[derive(Clone, Default)]
+pub struct Diarizer {
+    engine: Option<Arc<SpeakerEngine>>,
+    threshold: f32,
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    devices: HashMap<String, Vec<Speaker>>,
+    /// Label waiting for the device's next user transcript
+    pending: HashMap<String, String>,
+}
+
+struct Speaker {
+    id: u32,
+    name: Option<String>,
+    centroid: Vec<f32>,
+    utterances: u64,
+    last_seen_ms: i64,
+}
+
+impl Speaker {
+    fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| format!("speaker_{}", self.id))
+    }
+}
+
+/// One known speaker, as served by `GET /devices/{device}/speakers`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeakerSummary {
+    pub id: u32,
+    pub label: String,
+    pub name: Option<String>,
+    pub utterances: u64,
+    pub last_seen_ms: i64,
+}
+
+/// The start of one device session's audio, kept for the embedding.
+pub struct SpeakerProbe {
+    device: String,
+    pcm: Vec<i16>,
+}
+
+impl SpeakerProbe {
+    /// Append 16 kHz s16 LE mono audio (ignored beyond PROBE_SECS).
+    pub fn push(&mut self, pcm: &[u8]) {
+        let room = PROBE_SECS * (SAMPLE_RATE as usize) - self.pcm.len();
+        self.pcm.extend(
+            pcm
+                .chunks_exact(2)
+                .take(room)
+                .map(|c| i16::from_le_bytes([c[0], c[1]]))
+        );
+    }
+}
+
+impl Diarizer {
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let engine = match config.speaker_engine {
+            SpeakerEngineKind::Off => None,
+            SpeakerEngineKind::Spectral => Some(SpeakerEngine::Spectral),
+            #[cfg(feature = "onnx")]
+            SpeakerEngineKind::Onnx => {
+                if config.speaker_model_path.is_empty() {
+                    anyhow::bail!("--speaker-engine onnx requires --speaker-model-path");
+                }
+                Some(SpeakerEngine::Onnx(crate::speaker_onnx::OnnxSpeakerModel::load(&config.speaker_model_path)?))
+            }
+            #[cfg(not(feature = "onnx"))]
+            SpeakerEngineKind::Onnx => {
+                anyhow::bail!("--speaker-engine onnx requires a build with `--features onnx`")
+            }
+        };
+        if engine.is_some() {
+            info!(engine = ?config.speaker_engine, threshold = config.speaker_threshold, "🗣️ speaker diarization enabled");
+        }
+        Ok(Self {
+            engine: engine.map(Arc::new),
+            threshold: config.speaker_threshold,
+            inner: Arc::default(),
+        })
+    }
+
+    /// A fresh probe for a session of `device` (None when disabled).
+    pub fn probe(&self, device: &str) -> Option<SpeakerProbe> {
+        self.engine.as_ref()?;
+        Some(SpeakerProbe { device: device.to_string(), pcm: Vec::new() })
+    }
+
+    /// Embed a committed session's probe in the background and hold the
+    /// matched speaker's label for the device's next user transcript.
+    pub fn identify(&self, probe: SpeakerProbe) {
+        let Some(engine) = self.engine.clone() else {
+            return;
+        };
+        let diarizer = self.clone();
+        tokio::spawn(async move {
+            let SpeakerProbe { device, pcm } = probe;
+            match tokio::task::spawn_blocking(move || engine.embed(&pcm)).await {
+                Ok(Ok(embedding)) => {
+                    let label = diarizer.assign(&device, embedding);
+                    info!(device = %device, speaker = %label, "🗣️ speaker identified");
+                }
+                Ok(Err(e)) => debug!(device = %device, error = %e, "no speaker embedding for session"),
+                Err(e) => warn!(device = %device, error = %e, "speaker embedding panicked"),
+            }
+        });
+    }
+
+    /// Match `embedding` against `device`'s speakers; returns the label,
+    /// which is also held for the next transcript.
+    fn assign(&self, device: &str, embedding: Vec<f32>) -> String {
+        let embedding = normalize(embedding);
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let speakers = inner.devices.entry(device.to_string()).or_default();
+        let best = speakers
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (i, cosine(&s.centroid, &embedding)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let index = match best {
+            Some((i, score)) if score >= self.threshold || speakers.len() >= MAX_SPEAKERS => {
+                let s = &mut speakers[i];
+                // Running mean of the unit embeddings, re-normalised
+                let n = s.utterances as f32;
+                let merged = s.centroid
+                    .iter()
+                    .zip(&embedding)
+                    .map(|(c, e)| (c * n + e) / (n + 1.0))
+                    .collect();
+                s.centroid = normalize(merged);
+                i
+            }
+            _ => {
+                let id = speakers.iter().map(|s| s.id).max().unwrap_or(0) + 1;
+                speakers.push(Speaker { id, name: None, centroid: embedding, utterances: 0, last_seen_ms: now });
+                speakers.len() - 1
+            }
+        };
+        let speaker = &mut speakers[index];
+        speaker.utterances += 1;
+        speaker.last_seen_ms = now;
+        let label = speaker.label();
+        inner.pending.insert(device.to_string(), label.clone());
+        label
+    }
+
+    /// Speaker of `device`'s latest committed session, once (None if
+    /// disabled or not identified).
+    pub fn take_pending(&self, device: &str) -> Option<String> {
+        self.engine.as_ref()?;
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pending.remove(device)
+    }
+
+    /// Speakers heard on `device`, by id.
+    pub fn list(&self, device: &str) -> Vec<SpeakerSummary> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.devices
+            .get(device)
+            .map(|speakers| {
+                speakers
+                    .iter()
+                    .map(|s| SpeakerSummary {
+                        id: s.id,
+                        label: s.label(),
+                        name: s.name.clone(),
+                        utterances: s.utterances,
+                        last_seen_ms: s.last_seen_ms,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Forget every speaker of `device`; returns how many there were.
+    pub fn forget(&self, device: &str) -> usize {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.pending.remove(device);
+        inner.devices.remove(device).map_or(0, |speakers| speakers.len())
+    }
+
+    /// Name (`Some`) or un-name (`None`) speaker `id` of `device`.
+    /// Returns `false` if there is no such speaker.
+    pub fn rename(&self, device: &str, id: u32, name: Option<String>) -> bool {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(speaker) = inner.devices.get_mut(device).and_then(|s| s.iter_mut().find(|s| s.id == id)) else {
+            return false;
+        };
+        speaker.name = name.filter(|n| !n.trim().is_empty());
+        true
+    }
+}
+
+// ── Embedding helpers ────────────────────────────────────────────────
+
+fn normalize(mut v: Vec<f32>) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| {
+            *x /= norm;
+        });
+    }
+    v
+}
+
+/// Cosine similarity of two unit vectors.
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| x * y)
+        .sum()
+}
+
+/// Mean log band energies over voiced frames, with the mean across bands
+/// removed (loudness-independent).  None if nothing was voiced.
+fn spectral_embedding(pcm: &[i16]) -> Option<Vec<f32>> {
+    let bank = MelBands::new(BANDS, 100.0, 7_000.0);
+    let mut sums = [0f32; BANDS];
+    let mut voiced = 0usize;
+    for frame in pcm.windows(FRAME).step_by(HOP) {
+        if mel::rms(frame) < VOICED_RMS {
+            continue;
+        }
+        voiced += 1;
+        for (sum, e) in sums.iter_mut().zip(bank.log_energies(frame)) {
+            *sum += e;
+        }
+    }
+    if voiced == 0 {
+        return None;
+    }
+    let means: Vec<f32> = sums
+        .iter()
+        .map(|s| s / (voiced as f32))
+        .collect();
+    let avg = means.iter().sum::<f32>() / (BANDS as f32);
+    Some(
+        means
+            .into_iter()
+            .map(|m| m - avg)
+            .collect()
+    )
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A buzzy "voice": harmonics of `f0` with a formant-like peak.
+    fn voice(f0: f32, formant: f32, gain: f32) -> Vec<i16> {
+        (0..16_000)
+            .map(|n| {
+                let t = (n as f32) / SAMPLE_RATE;
+                let s: f32 = (1..20)
+                    .map(|k| {
+                        let f = f0 * (k as f32);
+                        let weight = 1.0 / (1.0 + ((f - formant) / 300.0).powi(2));
+                        weight * (2.0 * std::f32::consts::PI * f * t).sin()
+                    })
+                    .sum();
+                (s * gain) as i16
+            })
+            .collect()
+    }
+
+    fn diarizer() -> Diarizer {
+        Diarizer { engine: Some(Arc::new(SpeakerEngine::Spectral)), threshold: 0.8, inner: Arc::default() }
+    }
+
+    #[test]
+    fn test_spectral_embedding_is_a_voice_print() {
+        let a = normalize(spectral_embedding(&voice(220.0, 900.0, 3000.0)).unwrap());
+        let a_loud = normalize(spectral_embedding(&voice(220.0, 900.0, 9000.0)).unwrap());
+        let b = normalize(spectral_embedding(&voice(330.0, 2500.0, 3000.0)).unwrap());
+        assert!(cosine(&a, &a_loud) > 0.95, "{}", cosine(&a, &a_loud));
+        assert!(cosine(&a, &b) < 0.8, "{}", cosine(&a, &b));
+        assert!(spectral_embedding(&[0i16; 16_000]).is_none());
+    }
+
+    #[test]
+    fn test_assign_rename_and_pending() {
+        let diarizer = diarizer();
+        let a = spectral_embedding(&voice(220.0, 900.0, 3000.0)).unwrap();
+        let b = spectral_embedding(&voice(330.0, 2500.0, 3000.0)).unwrap();
+
+        assert_eq!(diarizer.assign("aa:bb", a.clone()), "speaker_1");
+        assert_eq!(diarizer.assign("aa:bb", b.clone()), "speaker_2");
+        assert_eq!(diarizer.take_pending("aa:bb").as_deref(), Some("speaker_2"));
+        assert_eq!(diarizer.take_pending("aa:bb"), None);
+
+        // Devices keep separate registries; names replace labels
+        assert_eq!(diarizer.assign("cc:dd", b), "speaker_1");
+        assert!(diarizer.rename("aa:bb", 1, Some("Mia".into())));
+        assert!(!diarizer.rename("aa:bb", 9, None));
+        assert_eq!(diarizer.assign("aa:bb", a), "Mia");
+        let list = diarizer.list("aa:bb");
+        assert_eq!((list.len(), list[0].utterances), (2, 2));
+        assert!(Diarizer::default().probe("aa:bb").is_none());
+    }
+}
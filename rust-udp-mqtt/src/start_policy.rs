@@ -0,0 +1,110 @@
+use dashmap::DashMap;
+use serde::{ Deserialize, Serialize };
+use std::sync::Arc;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Duplicate SESSION_START policy
+// ─────────────────────────────────────────────────────────────────────
+//
+//  What a SESSION_START / START means while the device's session is
+//  still Receiving:
+//
+//    resume    a retransmitted START (its SERVER_READY was lost): the
+//              session carries on — audio, WAV and OpenAI link kept —
+//              and SERVER_READY is sent again
+//    restart   the device began over: the audio so far is discarded
+//              and a fresh session starts
+//    reject    the START is refused with an error (CTRL_ERROR /
+//              ERR_SESSION_ACTIVE, or a 0x54 notification); the running
+//              session is untouched
+//
+//  `--duplicate-start-policy` sets the default; devices (MAC or
+//  `ip:port`, as in `{{device_name}}`) can override it over REST.
+
+/// Handling of a START while a session is already receiving.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    Serialize,
+    Deserialize,
+    clap::ValueEnum
+)]
+#[serde(rename_all = "lowercase")]
+pub enum StartPolicy {
+    Restart,
+    #[default]
+    Resume,
+    Reject,
+}
+
+impl StartPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StartPolicy::Restart => "restart",
+            StartPolicy::Resume => "resume",
+            StartPolicy::Reject => "reject",
+        }
+    }
+}
+
+/// Default policy plus per-device overrides.  Clone-friendly.
+#[derive(Clone)]
+pub struct StartPolicies {
+    default: StartPolicy,
+    overrides: Arc<DashMap<String, StartPolicy>>,
+}
+
+impl StartPolicies {
+    pub fn new(default: StartPolicy) -> Self {
+        Self { default, overrides: Arc::default() }
+    }
+
+    /// Policy for `device` (case-insensitive).
+    pub fn policy(&self, device: &str) -> StartPolicy {
+        self.override_for(device).unwrap_or(self.default)
+    }
+
+    pub fn override_for(&self, device: &str) -> Option<StartPolicy> {
+        self.overrides.get(&device.to_ascii_lowercase()).map(|p| *p)
+    }
+
+    /// Set (`Some`) or clear (`None`) `device`'s override.
+    pub fn set(&self, device: &str, policy: Option<StartPolicy>) {
+        let key = device.to_ascii_lowercase();
+        match policy {
+            Some(policy) => {
+                self.overrides.insert(key, policy);
+            }
+            None => {
+                self.overrides.remove(&key);
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_override_and_clear() {
+        let policies = StartPolicies::new(StartPolicy::Resume);
+        assert_eq!(policies.policy("AA:BB:CC:DD:EE:FF"), StartPolicy::Resume);
+
+        policies.set("AA:BB:CC:DD:EE:FF", Some(StartPolicy::Reject));
+        assert_eq!(policies.policy("aa:bb:cc:dd:ee:ff"), StartPolicy::Reject);
+        assert_eq!(policies.policy("10.0.0.7:5000"), StartPolicy::Resume);
+
+        policies.set("aa:bb:cc:dd:ee:ff", None);
+        assert_eq!(policies.override_for("aa:bb:cc:dd:ee:ff"), None);
+        assert_eq!(serde_json::to_string(&StartPolicy::Restart).unwrap(), "\"restart\"");
+    }
+}
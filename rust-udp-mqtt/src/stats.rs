@@ -1,6 +1,42 @@
+use crate::anomaly::AnomalyDetector;
+use crate::clock::{ self, SharedClock };
+use crate::config::Config;
+use crate::mqtt_health::{ self, MqttHealth };
+use rumqttc::v5::mqttbytes::v5::PublishProperties;
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::AsyncClient;
+use serde::Serialize;
 use std::sync::atomic::{ AtomicU64, Ordering };
-use std::sync::Arc;
+use std::sync::{ Arc, Mutex };
 use std::time::{ Duration, Instant };
+use tokio::io::AsyncWriteExt;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Pipeline stats reporting
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Counters only ever grow; readers keep their own baseline and look at
+//  the difference.  Every `--stats-interval-secs` the reporter takes the
+//  counts since its previous sample, feeds them to anomaly detection
+//  (`anomaly.rs`) and hands them to each `--stats-sink`:
+//
+//    stdout    the one-line `[STATS] ...` summary
+//    tracing   an `info` event with one field per value (JSON with a
+//              JSON log subscriber)
+//    file      one JSON object per line appended to `--stats-file`
+//    mqtt      the same JSON published to `--stats-mqtt-topic[/<tenant>]`
+//              on `--stats-mqtt-host` (QoS 0)
+//
+//  Each sink has its own period (`--stats-<sink>-secs`, 0 = every
+//  sample); samples are summed until it is due, so a 60 s file sink
+//  sees the counts of the whole minute.  stdout and tracing skip
+//  periods without any activity, file and mqtt always report.
+//
+//  `GET /stats` reads the counts since the last `POST /stats/reset`
+//  (or startup) without touching them; the reset only moves that
+//  baseline, so external pollers and the reporter never disturb each
+//  other.
 
 /// Lock-free performance counters — transport-agnostic
 #[derive(Debug)]
@@ -12,10 +48,26 @@ pub struct Stats {
     pub parse_errors: AtomicU64,
     pub recv_errors: AtomicU64,
     pub channel_drops: AtomicU64,
+    /// Packets skipped by VAD workers for missing `--packet-deadline-ms`
+    pub shed: AtomicU64,
+    /// ONNX emotion inferences that failed and fell back to linear weights
+    pub inference_errors: AtomicU64,
+    /// Gauge: sensor-port client addresses currently remembered
+    pub sensor_clients: AtomicU64,
+    /// Gauge: ESPs currently online (see `presence`)
+    pub esp_online: AtomicU64,
+    /// Totals and time of the last `POST /stats/reset`
+    api_baseline: Mutex<(StatsCounts, Instant, i64)>,
+    clock: SharedClock,
 }
 
 impl Stats {
     pub fn new() -> Arc<Self> {
+        Self::with_clock(clock::system())
+    }
+
+    /// Counters whose `GET /stats` period is timed on `clock`.
+    pub fn with_clock(clock: SharedClock) -> Arc<Self> {
         Arc::new(Self {
             recv_packets: AtomicU64::new(0),
             recv_bytes: AtomicU64::new(0),
@@ -24,6 +76,12 @@ impl Stats {
             parse_errors: AtomicU64::new(0),
             recv_errors: AtomicU64::new(0),
             channel_drops: AtomicU64::new(0),
+            shed: AtomicU64::new(0),
+            inference_errors: AtomicU64::new(0),
+            sensor_clients: AtomicU64::new(0),
+            esp_online: AtomicU64::new(0),
+            api_baseline: Mutex::new((StatsCounts::default(), clock.now(), clock::unix_millis(clock.as_ref()) as i64)),
+            clock,
         })
     }
 
@@ -56,79 +114,509 @@ impl Stats {
         self.channel_drops.fetch_add(1, Ordering::Relaxed);
     }
 
-    /// Snapshot and reset counters
-    pub fn snapshot_and_reset(&self, elapsed: Duration) -> StatsSnapshot {
-        let secs = elapsed.as_secs_f64().max(0.001);
+    #[inline(always)]
+    pub fn record_shed(&self) {
+        self.shed.fetch_add(1, Ordering::Relaxed);
+    }
 
-        let pkts = self.recv_packets.swap(0, Ordering::Relaxed);
-        let bytes = self.recv_bytes.swap(0, Ordering::Relaxed);
-        let proc = self.processed.swap(0, Ordering::Relaxed);
-        let active = self.vad_active.swap(0, Ordering::Relaxed);
-        let perr = self.parse_errors.swap(0, Ordering::Relaxed);
-        let rerr = self.recv_errors.swap(0, Ordering::Relaxed);
-        let drops = self.channel_drops.swap(0, Ordering::Relaxed);
+    #[inline(always)]
+    pub fn record_inference_error(&self) {
+        self.inference_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn set_sensor_clients(&self, n: usize) {
+        self.sensor_clients.store(n as u64, Ordering::Relaxed);
+    }
 
+    #[inline(always)]
+    pub fn set_esp_online(&self, n: usize) {
+        self.esp_online.store(n as u64, Ordering::Relaxed);
+    }
+
+    /// Counter totals since startup (nothing is reset).
+    pub fn totals(&self) -> StatsCounts {
+        StatsCounts {
+            recv_packets: self.recv_packets.load(Ordering::Relaxed),
+            recv_bytes: self.recv_bytes.load(Ordering::Relaxed),
+            processed: self.processed.load(Ordering::Relaxed),
+            vad_active: self.vad_active.load(Ordering::Relaxed),
+            parse_errors: self.parse_errors.load(Ordering::Relaxed),
+            recv_errors: self.recv_errors.load(Ordering::Relaxed),
+            channel_drops: self.channel_drops.load(Ordering::Relaxed),
+            shed: self.shed.load(Ordering::Relaxed),
+            inference_errors: self.inference_errors.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Snapshot of `counts` collected over `elapsed`, with the current gauges.
+    fn snapshot_of(&self, counts: &StatsCounts, elapsed: Duration) -> StatsSnapshot {
+        let secs = elapsed.as_secs_f64().max(0.001);
         StatsSnapshot {
-            recv_pps: (pkts as f64) / secs,
-            recv_mbps: ((bytes as f64) * 8.0) / (secs * 1_000_000.0),
-            proc_pps: (proc as f64) / secs,
-            vad_active: active,
-            parse_errors: perr,
-            recv_errors: rerr,
-            channel_drops: drops,
+            recv_pps: (counts.recv_packets as f64) / secs,
+            recv_packets: counts.recv_packets,
+            recv_mbps: ((counts.recv_bytes as f64) * 8.0) / (secs * 1_000_000.0),
+            proc_pps: (counts.processed as f64) / secs,
+            vad_active: counts.vad_active,
+            parse_errors: counts.parse_errors,
+            recv_errors: counts.recv_errors,
+            channel_drops: counts.channel_drops,
+            shed: counts.shed,
+            inference_errors: counts.inference_errors,
+            sensor_clients: self.sensor_clients.load(Ordering::Relaxed),
+            esp_online: self.esp_online.load(Ordering::Relaxed),
+        }
+    }
+
+    /// `GET /stats`: counts and rates since the last [`reset`](Self::reset).
+    pub fn live(&self) -> LiveStats {
+        let baseline = self.api_baseline.lock().unwrap_or_else(|e| e.into_inner());
+        self.live_of(&self.totals(), &baseline)
+    }
+
+    /// `POST /stats/reset`: start the `GET /stats` counts over; returns
+    /// the final view of the period that ended.  The reporter is not
+    /// affected.
+    pub fn reset(&self) -> LiveStats {
+        let mut baseline = self.api_baseline.lock().unwrap_or_else(|e| e.into_inner());
+        let totals = self.totals();
+        let ended = self.live_of(&totals, &baseline);
+        *baseline = (totals, self.clock.now(), clock::unix_millis(self.clock.as_ref()) as i64);
+        ended
+    }
+
+    fn live_of(&self, totals: &StatsCounts, (counts, at, at_ms): &(StatsCounts, Instant, i64)) -> LiveStats {
+        let elapsed = self.clock.now().saturating_duration_since(*at);
+        LiveStats {
+            since_ms: *at_ms,
+            elapsed_secs: elapsed.as_secs_f64(),
+            snapshot: self.snapshot_of(&totals.since(counts), elapsed),
         }
     }
 }
 
-#[derive(Debug)]
+/// Counter values: totals, or the difference of two totals.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StatsCounts {
+    pub recv_packets: u64,
+    pub recv_bytes: u64,
+    pub processed: u64,
+    pub vad_active: u64,
+    pub parse_errors: u64,
+    pub recv_errors: u64,
+    pub channel_drops: u64,
+    pub shed: u64,
+    pub inference_errors: u64,
+}
+
+impl StatsCounts {
+    fn add(&mut self, other: &Self) {
+        self.recv_packets += other.recv_packets;
+        self.recv_bytes += other.recv_bytes;
+        self.processed += other.processed;
+        self.vad_active += other.vad_active;
+        self.parse_errors += other.parse_errors;
+        self.recv_errors += other.recv_errors;
+        self.channel_drops += other.channel_drops;
+        self.shed += other.shed;
+        self.inference_errors += other.inference_errors;
+    }
+
+    /// Counts added since `earlier` totals were read.
+    pub fn since(&self, earlier: &Self) -> Self {
+        Self {
+            recv_packets: self.recv_packets.saturating_sub(earlier.recv_packets),
+            recv_bytes: self.recv_bytes.saturating_sub(earlier.recv_bytes),
+            processed: self.processed.saturating_sub(earlier.processed),
+            vad_active: self.vad_active.saturating_sub(earlier.vad_active),
+            parse_errors: self.parse_errors.saturating_sub(earlier.parse_errors),
+            recv_errors: self.recv_errors.saturating_sub(earlier.recv_errors),
+            channel_drops: self.channel_drops.saturating_sub(earlier.channel_drops),
+            shed: self.shed.saturating_sub(earlier.shed),
+            inference_errors: self.inference_errors.saturating_sub(earlier.inference_errors),
+        }
+    }
+}
+
+/// `GET /stats` view.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LiveStats {
+    /// Startup or the last `POST /stats/reset`, Unix ms
+    pub since_ms: i64,
+    pub elapsed_secs: f64,
+    #[serde(flatten)]
+    pub snapshot: StatsSnapshot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub struct StatsSnapshot {
     pub recv_pps: f64,
     pub recv_mbps: f64,
     pub proc_pps: f64,
+    pub recv_packets: u64,
     pub vad_active: u64,
     pub parse_errors: u64,
     pub recv_errors: u64,
     pub channel_drops: u64,
+    pub shed: u64,
+    pub inference_errors: u64,
+    pub sensor_clients: u64,
+    pub esp_online: u64,
+}
+
+impl StatsSnapshot {
+    fn has_activity(&self) -> bool {
+        self.recv_pps > 0.0 ||
+            self.proc_pps > 0.0 ||
+            self.vad_active > 0 ||
+            self.parse_errors > 0 ||
+            self.recv_errors > 0 ||
+            self.channel_drops > 0 ||
+            self.shed > 0 ||
+            self.inference_errors > 0
+    }
+}
+
+/// One report as written by the file and MQTT sinks.
+#[derive(Serialize)]
+struct StatsReport<'a> {
+    t_ms: i64,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    tenant: &'a str,
+    /// Length of the period the values cover
+    period_secs: f64,
+    #[serde(flatten)]
+    snapshot: &'a StatsSnapshot,
+}
+
+/// A stats destination (`--stats-sink`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatsSinkKind {
+    Stdout,
+    Tracing,
+    File,
+    Mqtt,
+}
+
+enum Output {
+    Stdout,
+    Tracing,
+    File(String),
+    Mqtt {
+        client: AsyncClient,
+        topic: String,
+        props: PublishProperties,
+    },
+}
+
+/// One sink and the samples it has not reported yet.
+struct Sink {
+    output: Output,
+    /// Report every this many samples
+    every: u64,
+    samples: u64,
+    pending: StatsCounts,
+    elapsed: Duration,
+}
+
+impl Sink {
+    fn new(output: Output, every: u64) -> Self {
+        Self { output, every: every.max(1), samples: 0, pending: StatsCounts::default(), elapsed: Duration::ZERO }
+    }
+
+    /// Add one sample; returns the summed counts and their period once
+    /// the sink is due.
+    fn push(&mut self, counts: &StatsCounts, elapsed: Duration) -> Option<(StatsCounts, Duration)> {
+        self.pending.add(counts);
+        self.elapsed += elapsed;
+        self.samples += 1;
+        if self.samples < self.every {
+            return None;
+        }
+        self.samples = 0;
+        Some((std::mem::take(&mut self.pending), std::mem::take(&mut self.elapsed)))
+    }
+
+    async fn report(&self, label: &str, tenant: &str, snap: &StatsSnapshot, period: Duration) {
+        match &self.output {
+            Output::Stdout if snap.has_activity() => {
+                println!(
+                    "[{}] {:.0} pps, {:.2} Mbps | VAD: {:.0} proc/s, {} active | errors: parse={} recv={} drops={} shed={} infer={} | clients={} online={}",
+                    label,
+                    snap.recv_pps,
+                    snap.recv_mbps,
+                    snap.proc_pps,
+                    snap.vad_active,
+                    snap.parse_errors,
+                    snap.recv_errors,
+                    snap.channel_drops,
+                    snap.shed,
+                    snap.inference_errors,
+                    snap.sensor_clients,
+                    snap.esp_online
+                );
+            }
+            Output::Tracing if snap.has_activity() => {
+                info!(
+                    tenant,
+                    period_secs = period.as_secs_f64(),
+                    recv_pps = snap.recv_pps,
+                    recv_mbps = snap.recv_mbps,
+                    proc_pps = snap.proc_pps,
+                    recv_packets = snap.recv_packets,
+                    vad_active = snap.vad_active,
+                    parse_errors = snap.parse_errors,
+                    recv_errors = snap.recv_errors,
+                    channel_drops = snap.channel_drops,
+                    shed = snap.shed,
+                    inference_errors = snap.inference_errors,
+                    sensor_clients = snap.sensor_clients,
+                    esp_online = snap.esp_online,
+                    "📊 stats"
+                );
+            }
+            Output::Stdout | Output::Tracing => {}
+            Output::File(path) => {
+                let mut line = report_json(tenant, snap, period);
+                line.push('\n');
+                let written = async {
+                    let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?;
+                    file.write_all(line.as_bytes()).await?;
+                    // tokio finishes the write in the background unless flushed
+                    file.flush().await
+                };
+                if let Err(e) = written.await {
+                    warn!(path = %path, error = %e, "failed to write stats file");
+                }
+            }
+            Output::Mqtt { client, topic, props } => {
+                let body = report_json(tenant, snap, period);
+                if let Err(e) = client.try_publish_with_properties(topic.clone(), QoS::AtMostOnce, false, body, props.clone()) {
+                    warn!(error = %e, "failed to queue stats for MQTT");
+                }
+            }
+        }
+    }
+}
+
+fn report_json(tenant: &str, snapshot: &StatsSnapshot, period: Duration) -> String {
+    let report = StatsReport {
+        t_ms: chrono::Utc::now().timestamp_millis(),
+        tenant,
+        period_secs: period.as_secs_f64(),
+        snapshot,
+    };
+    serde_json::to_string(&report).unwrap_or_default()
+}
+
+/// The configured stats sinks.
+pub struct StatsSinks {
+    sinks: Vec<Sink>,
+}
+
+impl StatsSinks {
+    /// Just the stdout line, every sample.
+    pub fn stdout() -> Self {
+        Self { sinks: vec![Sink::new(Output::Stdout, 1)] }
+    }
+
+    /// The `--stats-sink`s, with their periods in samples of
+    /// `--stats-interval-secs`.
+    pub fn from_config(config: &Config, health: &MqttHealth) -> anyhow::Result<Self> {
+        let interval = config.stats_interval_secs.max(1);
+        let every = |secs: u64| secs.div_ceil(interval);
+        let mut sinks = Vec::new();
+        for (i, &kind) in config.stats_sink.iter().enumerate() {
+            if config.stats_sink[..i].contains(&kind) {
+                continue;
+            }
+            let sink = match kind {
+                StatsSinkKind::Stdout => Sink::new(Output::Stdout, every(config.stats_stdout_secs)),
+                StatsSinkKind::Tracing => Sink::new(Output::Tracing, every(config.stats_tracing_secs)),
+                StatsSinkKind::File => {
+                    if config.stats_file.is_empty() {
+                        anyhow::bail!("--stats-sink file needs --stats-file");
+                    }
+                    if let Some(dir) = std::path::Path::new(&config.stats_file).parent() {
+                        std::fs::create_dir_all(dir).ok();
+                    }
+                    Sink::new(Output::File(config.stats_file.clone()), every(config.stats_file_secs))
+                }
+                StatsSinkKind::Mqtt => {
+                    if config.stats_mqtt_host.is_empty() {
+                        anyhow::bail!("--stats-sink mqtt needs --stats-mqtt-host");
+                    }
+                    let (client, topic) = stats_mqtt(config, health);
+                    let props = crate::mqtt5::json("stats", &config.tenant_id, config.mqtt_expiry_secs);
+                    Sink::new(Output::Mqtt { client, topic, props }, every(config.stats_mqtt_secs))
+                }
+            };
+            sinks.push(sink);
+        }
+        Ok(Self { sinks })
+    }
+}
+
+/// Client for `--stats-mqtt-host` (its poll loop spawned) and the topic.
+fn stats_mqtt(config: &Config, health: &MqttHealth) -> (AsyncClient, String) {
+    let topic = if config.tenant_id.is_empty() {
+        config.stats_mqtt_topic.clone()
+    } else {
+        format!("{}/{}", config.stats_mqtt_topic, config.tenant_id)
+    };
+    let opts = mqtt_health::client_options("stats", &config.tenant_id, &config.stats_mqtt_host, config.stats_mqtt_port);
+    let (client, _) = mqtt_health::spawn_client(opts, 16, health, "stats", |_, _| {});
+    (client, topic)
 }
 
-/// Background stats reporter task.
-pub async fn stats_reporter(stats: Arc<Stats>, interval_secs: u64) {
+/// Background stats reporter task.  `tenant` labels the reports in
+/// multi-tenant mode ("" = unlabeled); rates are per `clock` second.
+/// Every sample is also fed to `anomalies`.
+pub async fn stats_reporter(
+    stats: Arc<Stats>,
+    interval_secs: u64,
+    tenant: String,
+    clock: SharedClock,
+    anomalies: AnomalyDetector,
+    mut sinks: StatsSinks
+) {
+    let label = if tenant.is_empty() { "STATS".to_string() } else { format!("STATS {tenant}") };
     if interval_secs == 0 {
         std::future::pending::<()>().await;
         return;
     }
 
     let interval = Duration::from_secs(interval_secs);
-    let mut last = Instant::now();
+    let mut last = clock.now();
+    let mut last_totals = stats.totals();
 
     loop {
-        tokio::time::sleep(interval).await;
-        let now = Instant::now();
+        clock.sleep(interval).await;
+        let now = clock.now();
         let elapsed = now - last;
         last = now;
 
-        let snap = stats.snapshot_and_reset(elapsed);
-
-        // Only log when there's actual activity
-        let has_activity =
-            snap.recv_pps > 0.0 ||
-            snap.proc_pps > 0.0 ||
-            snap.vad_active > 0 ||
-            snap.parse_errors > 0 ||
-            snap.recv_errors > 0 ||
-            snap.channel_drops > 0;
-
-        if has_activity {
-            println!(
-                "[STATS] {:.0} pps, {:.2} Mbps | VAD: {:.0} proc/s, {} active | errors: parse={} recv={} drops={}",
-                snap.recv_pps,
-                snap.recv_mbps,
-                snap.proc_pps,
-                snap.vad_active,
-                snap.parse_errors,
-                snap.recv_errors,
-                snap.channel_drops
-            );
+        let totals = stats.totals();
+        let counts = totals.since(&last_totals);
+        last_totals = totals;
+        anomalies.observe_stats(&stats.snapshot_of(&counts, elapsed));
+
+        for sink in &mut sinks.sinks {
+            if let Some((counts, period)) = sink.push(&counts, elapsed) {
+                sink.report(&label, &tenant, &stats.snapshot_of(&counts, period), period).await;
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sink_sums_samples_until_due() {
+        let stats = Stats::new();
+        let mut sink = Sink::new(Output::Stdout, 3);
+        let mut reports = Vec::new();
+        let mut last = stats.totals();
+        for n in 1..=6u64 {
+            for _ in 0..n {
+                stats.record_recv(100);
+            }
+            stats.record_parse_error();
+            let totals = stats.totals();
+            if let Some((counts, period)) = sink.push(&totals.since(&last), Duration::from_secs(5)) {
+                reports.push(stats.snapshot_of(&counts, period));
+            }
+            last = totals;
+        }
+        assert_eq!(reports.len(), 2);
+        assert_eq!((reports[0].recv_packets, reports[0].parse_errors), (6, 3));
+        assert_eq!(reports[0].recv_pps, 6.0 / 15.0);
+        assert_eq!(reports[1].recv_packets, 15);
+    }
+
+    #[test]
+    fn test_live_view_reads_without_reset() {
+        let stats = Stats::new();
+        stats.record_recv(10);
+        stats.record_recv(10);
+        stats.record_shed();
+        assert_eq!(stats.live().snapshot.recv_packets, 2);
+        // Reading twice changes nothing, and neither does the reporter's read
+        assert_eq!(stats.live().snapshot.recv_packets, 2);
+        assert_eq!(stats.totals().recv_bytes, 20);
+
+        let ended = stats.reset();
+        assert_eq!((ended.snapshot.recv_packets, ended.snapshot.shed), (2, 1));
+        stats.record_recv(10);
+        let live = stats.live();
+        assert_eq!((live.snapshot.recv_packets, live.snapshot.shed), (1, 0));
+        assert!(live.since_ms >= ended.since_ms);
+        // Totals keep counting from startup
+        assert_eq!(stats.totals().recv_packets, 3);
+    }
+
+    #[test]
+    fn test_live_period_follows_the_clock() {
+        let sim = clock::SimClock::starting_at(std::time::UNIX_EPOCH + Duration::from_secs(1_000));
+        let stats = Stats::with_clock(Arc::new(sim.clone()));
+        for _ in 0..20 {
+            stats.record_recv(10);
+        }
+        sim.advance(Duration::from_secs(4));
+        let live = stats.live();
+        assert_eq!((live.since_ms, live.elapsed_secs), (1_000_000, 4.0));
+        assert_eq!(live.snapshot.recv_pps, 5.0);
+
+        let ended = stats.reset();
+        assert_eq!(ended.elapsed_secs, 4.0);
+        sim.advance(Duration::from_secs(1));
+        let live = stats.live();
+        assert_eq!((live.since_ms, live.elapsed_secs), (1_004_000, 1.0));
+        assert_eq!(live.snapshot.recv_packets, 0);
+    }
+
+    #[tokio::test]
+    async fn test_file_sink_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("vad-stats-{}.jsonl", std::process::id()));
+        let sink = Sink::new(Output::File(path.to_str().unwrap().into()), 1);
+        let stats = Stats::new();
+        stats.record_processed(true);
+        let snap = stats.snapshot_of(&stats.totals(), Duration::from_secs(2));
+        sink.report("STATS", "acme", &snap, Duration::from_secs(2)).await;
+        // Reported even without activity
+        let idle = stats.snapshot_of(&StatsCounts::default(), Duration::from_secs(2));
+        sink.report("STATS", "acme", &idle, Duration::from_secs(2)).await;
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0]["tenant"].as_str(), lines[0]["vad_active"].as_u64()), (Some("acme"), Some(1)));
+        assert_eq!((lines[0]["proc_pps"].as_f64(), lines[0]["period_secs"].as_f64()), (Some(0.5), Some(2.0)));
+        assert_eq!(lines[1]["vad_active"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_file_sink_line_is_on_disk_when_report_returns() {
+        let path = std::env::temp_dir().join(format!("vad-stats-flush-{}.jsonl", std::process::id()));
+        let sink = Sink::new(Output::File(path.to_str().unwrap().into()), 1);
+        let snap = Stats::new().snapshot_of(&StatsCounts::default(), Duration::from_secs(1));
+        for n in 1..=50 {
+            sink.report("STATS", "", &snap, Duration::from_secs(1)).await;
+            // Read at once: an unflushed write would still be in flight
+            let text = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(text.lines().count(), n);
+            assert!(text.ends_with('\n'));
         }
+        std::fs::remove_file(&path).unwrap();
     }
 }
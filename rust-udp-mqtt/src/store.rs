@@ -0,0 +1,258 @@
+use crate::config::Config;
+use crate::conversation::{ ConversationStore, Turn };
+use crate::devices::{ DeviceConfig, DeviceRegistry };
+use crate::persona::{ PersonaState, PersonaTrait };
+use crate::session_log::SessionSummary;
+use crate::transcripts::Transcript;
+use std::sync::Arc;
+use tokio::sync::{ broadcast, mpsc };
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Shared state store (PostgreSQL)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  With `--postgres-url` (build with `--features postgres`, see
+//  `store_postgres.rs`) several bridge instances keep one copy of the
+//  state that outlives them, in tables scoped by `--tenant-id`:
+//
+//    devices      per-sensor overrides (thresholds, persona assignment,
+//                 location), written on every change
+//    persona      the global persona, written on every change
+//    sessions     one row per ESP session when it closes (device,
+//                 instance, start / end, OpenAI response count)
+//    transcripts  every transcript published (see `transcripts.rs`)
+//
+//  At startup an instance loads the persona, the device overrides and
+//  each device's last `--conversation-history-turns` transcripts, so a
+//  device keeps its conversation when it moves to another instance.
+//  Every `--postgres-sync-secs` it picks up persona and device changes
+//  made by other instances.  Device erasure also deletes the device's
+//  sessions and transcripts.
+//
+//  Writes go through a bounded queue, so a slow database never stalls
+//  the audio path; while it is down writes are dropped with a warning
+//  and every instance keeps serving on its local state.
+
+/// Writes waiting for the database.
+const OUTBOX_CAPACITY: usize = 1024;
+
+/// One write to the store.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoreWrite {
+    Device {
+        sensor_id: u32,
+        config: DeviceConfig,
+    },
+    Persona {
+        persona: PersonaTrait,
+    },
+    Session {
+        session: SessionSummary,
+    },
+    Transcript {
+        transcript: Transcript,
+    },
+    /// Erase a device's sessions and transcripts
+    Forget {
+        device: String,
+    },
+}
+
+/// State loaded when an instance starts.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoreSnapshot {
+    pub persona: Option<PersonaTrait>,
+    pub devices: Vec<(u32, DeviceConfig)>,
+    /// Recent turns by device, oldest first
+    pub histories: Vec<(String, Vec<Turn>)>,
+}
+
+/// Handle to the shared store.  Clone-friendly; a disabled handle
+/// (no `--postgres-url`) ignores everything.
+#[derive(Clone)]
+pub struct SharedStore {
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    outbox: mpsc::Sender<StoreWrite>,
+    persona: PersonaState,
+    devices: DeviceRegistry,
+}
+
+impl SharedStore {
+    /// No shared store.
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    /// Connect to `--postgres-url` (creating the tables), load the shared
+    /// state into `persona`, `devices` and `conversations`, and start
+    /// syncing.  Disabled when the URL is empty.
+    pub async fn from_config(
+        config: &Config,
+        persona: PersonaState,
+        devices: DeviceRegistry,
+        conversations: ConversationStore
+    ) -> anyhow::Result<Self> {
+        if config.postgres_url.is_empty() {
+            return Ok(Self::disabled());
+        }
+        #[cfg(not(feature = "postgres"))]
+        {
+            let _ = (persona, devices, conversations);
+            anyhow::bail!("--postgres-url requires a build with `--features postgres`");
+        }
+        #[cfg(feature = "postgres")]
+        {
+            let backend = crate::store_postgres::PgStore::connect(
+                &config.postgres_url,
+                &config.tenant_id,
+                &config.resolved_instance_id()
+            ).await?;
+            let snapshot = backend.load(config.conversation_history_turns).await?;
+            let (store, outbox) = Self::new(persona, devices);
+            store.apply_snapshot(&snapshot, &conversations).await;
+            info!(
+                devices = snapshot.devices.len(),
+                histories = snapshot.histories.len(),
+                "🐘 shared state loaded from PostgreSQL"
+            );
+            let sync = std::time::Duration::from_secs(config.postgres_sync_secs.max(1));
+            tokio::spawn(crate::store_postgres::run(backend, outbox, store.clone(), sync));
+            store.spawn_local_tasks();
+            Ok(store)
+        }
+    }
+
+    #[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+    fn new(persona: PersonaState, devices: DeviceRegistry) -> (Self, mpsc::Receiver<StoreWrite>) {
+        let (outbox, rx) = mpsc::channel(OUTBOX_CAPACITY);
+        (Self { inner: Some(Arc::new(Inner { outbox, persona, devices })) }, rx)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Adopt `snapshot` without writing it back.
+    pub async fn apply_snapshot(&self, snapshot: &StoreSnapshot, conversations: &ConversationStore) {
+        self.apply_remote(snapshot.persona, &snapshot.devices).await;
+        for (device, turns) in &snapshot.histories {
+            conversations.restore(device, turns.clone());
+        }
+    }
+
+    /// Adopt persona / device changes made by other instances.
+    pub async fn apply_remote(&self, persona: Option<PersonaTrait>, devices: &[(u32, DeviceConfig)]) {
+        let Some(ref inner) = self.inner else {
+            return;
+        };
+        if let Some(persona) = persona {
+            if inner.persona.get().await != persona {
+                info!(persona = %persona, "🐘 persona changed in the shared store");
+                inner.persona.apply_remote(persona).await;
+            }
+        }
+        for (sensor_id, config) in devices {
+            inner.devices.apply_remote(*sensor_id, config.clone());
+        }
+    }
+
+    /// Forward local persona / device changes.
+    #[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+    fn spawn_local_tasks(&self) {
+        let Some(inner) = self.inner.clone() else {
+            return;
+        };
+        let store = self.clone();
+        let mut personas = inner.persona.subscribe_changes();
+        let mut devices = inner.devices.subscribe_changes();
+        tokio::spawn(async move {
+            loop {
+                let write = tokio::select! {
+                    persona = personas.recv() => match persona {
+                        Ok(persona) => StoreWrite::Persona { persona },
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    change = devices.recv() => match change {
+                        Ok((sensor_id, config)) => StoreWrite::Device { sensor_id, config },
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                };
+                store.send(write);
+            }
+        });
+    }
+
+    /// An ESP session closed on this instance.
+    pub fn session(&self, session: SessionSummary) {
+        self.send(StoreWrite::Session { session });
+    }
+
+    /// A transcript was published on this instance.
+    pub fn transcript(&self, transcript: &Transcript) {
+        self.send(StoreWrite::Transcript { transcript: transcript.clone() });
+    }
+
+    /// `device`'s data was erased.
+    pub fn forget(&self, device: &str) {
+        self.send(StoreWrite::Forget { device: device.to_string() });
+    }
+
+    fn send(&self, write: StoreWrite) {
+        let Some(ref inner) = self.inner else {
+            return;
+        };
+        if inner.outbox.try_send(write).is_err() {
+            warn!("shared store queue full — dropping write");
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::Role;
+    use crate::devices::Thresholds;
+
+    #[tokio::test]
+    async fn test_snapshot_applied_and_local_changes_queued() {
+        let persona = PersonaState::new(PersonaTrait::Obedient);
+        let devices = DeviceRegistry::new(Thresholds { audio: 500.0, arousal: 0.6 });
+        let conversations = ConversationStore::new(4);
+        let (store, mut outbox) = SharedStore::new(persona.clone(), devices.clone());
+
+        let config = DeviceConfig { audio_threshold: Some(900.0), ..Default::default() };
+        let turn = Turn { role: Role::User, text: "hello".into(), speaker: None };
+        let snapshot = StoreSnapshot {
+            persona: Some(PersonaTrait::Mischievous),
+            devices: vec![(7, config.clone())],
+            histories: vec![("aa:bb:cc:dd:ee:ff".into(), vec![turn.clone()])],
+        };
+        store.apply_snapshot(&snapshot, &conversations).await;
+        assert_eq!(persona.get().await, PersonaTrait::Mischievous);
+        assert_eq!(devices.get(7), Some(config));
+        assert_eq!(conversations.history("aa:bb:cc:dd:ee:ff"), [turn]);
+        assert!(outbox.try_recv().is_err(), "loaded state is not written back");
+
+        store.spawn_local_tasks();
+        tokio::task::yield_now().await;
+        devices.set_thresholds(3, Some(100.0), None);
+        store.forget("aa:bb:cc:dd:ee:ff");
+        let mut writes = Vec::new();
+        for _ in 0..2 {
+            writes.push(tokio::time::timeout(std::time::Duration::from_secs(1), outbox.recv()).await.unwrap().unwrap());
+        }
+        assert!(writes.iter().any(|w| matches!(w, StoreWrite::Device { sensor_id: 3, .. })));
+        assert!(writes.contains(&StoreWrite::Forget { device: "aa:bb:cc:dd:ee:ff".into() }));
+        assert!(!SharedStore::disabled().is_enabled());
+    }
+}
@@ -0,0 +1,264 @@
+//! PostgreSQL backend of the shared store (`--postgres-url`).
+//!
+//! Tables are created / upgraded on connect by the embedded migrations
+//! (see `migrations.rs`) and keyed by tenant, so one database serves
+//! every tenant.  Sessions and transcripts are append-only rows
+//! for reporting, e.g. turns per device and day:
+//!
+//! ```sql
+//! SELECT device, date_trunc('day', to_timestamp(at_ms / 1000.0)) AS day, count(*)
+//! FROM bridge_transcripts WHERE role = 'user' GROUP BY 1, 2 ORDER BY 1, 2;
+//! ```
+//!
+//! Device overrides and the persona carry `updated_ms` / `updated_by`,
+//! which the sync loop uses to pick up other instances' changes.
+
+use crate::conversation::{ Role, Turn };
+use crate::devices::DeviceConfig;
+use crate::persona::PersonaTrait;
+use crate::store::{ SharedStore, StoreSnapshot, StoreWrite };
+use sqlx::postgres::{ PgPool, PgPoolOptions };
+use sqlx::types::Json;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{ debug, info, warn };
+
+/// Embedded schema migrations (`migrations/postgres/NNNN_name.sql`),
+/// applied in order on connect and recorded in `_sqlx_migrations`.
+pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("migrations/postgres");
+
+/// Connect to `url` and apply pending migrations.  Fails if the database
+/// was migrated by a newer bridge (a migration this build lacks) or an
+/// applied migration was edited.  Concurrent instances serialise on the
+/// migrator's advisory lock.
+pub async fn migrate(url: &str) -> anyhow::Result<PgPool> {
+    let pool = PgPoolOptions::new()
+        .max_connections(4)
+        .acquire_timeout(Duration::from_secs(5))
+        .connect(url).await
+        .map_err(|e| anyhow::anyhow!("PostgreSQL connect failed: {e}"))?;
+    let before = applied(&pool).await;
+    MIGRATOR.run(&pool).await.map_err(|e| anyhow::anyhow!("PostgreSQL migration failed: {e}"))?;
+    let after = applied(&pool).await;
+    if after != before {
+        info!(from = before, to = after, "🐘 PostgreSQL schema migrated");
+    }
+    Ok(pool)
+}
+
+/// Newest applied migration (0 = none).
+async fn applied(pool: &PgPool) -> i64 {
+    sqlx
+        ::query_scalar::<_, Option<i64>>("SELECT max(version) FROM _sqlx_migrations WHERE success")
+        .fetch_one(pool).await
+        .ok()
+        .flatten()
+        .unwrap_or(0)
+}
+
+/// Connection pool scoped to one tenant and instance.
+pub struct PgStore {
+    pool: PgPool,
+    tenant: String,
+    instance: String,
+}
+
+impl PgStore {
+    /// Connect and apply pending migrations.
+    pub async fn connect(url: &str, tenant: &str, instance: &str) -> anyhow::Result<Self> {
+        let pool = migrate(url).await?;
+        info!(tenant = %tenant, instance = %instance, "🐘 PostgreSQL store connected");
+        Ok(Self { pool, tenant: tenant.to_string(), instance: instance.to_string() })
+    }
+
+    /// Persona, device overrides and each device's last `turns` turns.
+    pub async fn load(&self, turns: usize) -> anyhow::Result<StoreSnapshot> {
+        let (persona, _) = self.persona_since(-1, "").await?.unzip();
+        let devices = self.devices_since(-1, "").await?;
+        let rows: Vec<(String, String, String, Option<String>)> = sqlx
+            ::query_as(
+                "SELECT device, role, text, speaker FROM (
+                     SELECT *, row_number() OVER (PARTITION BY device ORDER BY at_ms DESC, id DESC) AS n
+                     FROM bridge_transcripts WHERE tenant = $1
+                 ) t WHERE n <= $2 ORDER BY device, at_ms, id"
+            )
+            .bind(&self.tenant)
+            .bind(turns as i64)
+            .fetch_all(&self.pool).await?;
+        let mut histories: Vec<(String, Vec<Turn>)> = Vec::new();
+        for (device, role, text, speaker) in rows {
+            let role = if role == "assistant" { Role::Assistant } else { Role::User };
+            let turn = Turn { role, text, speaker };
+            match histories.last_mut() {
+                Some((last, turns)) if *last == device => turns.push(turn),
+                _ => histories.push((device, vec![turn])),
+            }
+        }
+        Ok(StoreSnapshot { persona, devices: devices.into_iter().map(|(id, cfg, _)| (id, cfg)).collect(), histories })
+    }
+
+    /// The persona if it changed after `since_ms` by anyone but `skip`.
+    async fn persona_since(&self, since_ms: i64, skip: &str) -> anyhow::Result<Option<(PersonaTrait, i64)>> {
+        let row: Option<(String, i64)> = sqlx
+            ::query_as(
+                "SELECT persona, updated_ms FROM bridge_persona
+                 WHERE tenant = $1 AND updated_ms > $2 AND updated_by <> $3"
+            )
+            .bind(&self.tenant)
+            .bind(since_ms)
+            .bind(skip)
+            .fetch_optional(&self.pool).await?;
+        Ok(
+            row.and_then(|(persona, at)| {
+                serde_json::from_value(serde_json::Value::String(persona)).ok().map(|p| (p, at))
+            })
+        )
+    }
+
+    /// Device overrides changed after `since_ms` by anyone but `skip`.
+    async fn devices_since(&self, since_ms: i64, skip: &str) -> anyhow::Result<Vec<(u32, DeviceConfig, i64)>> {
+        let rows: Vec<(i64, Json<DeviceConfig>, i64)> = sqlx
+            ::query_as(
+                "SELECT sensor_id, config, updated_ms FROM bridge_devices
+                 WHERE tenant = $1 AND updated_ms > $2 AND updated_by <> $3 ORDER BY sensor_id"
+            )
+            .bind(&self.tenant)
+            .bind(since_ms)
+            .bind(skip)
+            .fetch_all(&self.pool).await?;
+        Ok(
+            rows
+                .into_iter()
+                .map(|(id, Json(cfg), at)| (id as u32, cfg, at))
+                .collect()
+        )
+    }
+
+    async fn write(&self, write: &StoreWrite) -> anyhow::Result<()> {
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        match write {
+            StoreWrite::Device { sensor_id, config } => {
+                sqlx
+                    ::query(
+                        "INSERT INTO bridge_devices (tenant, sensor_id, config, updated_ms, updated_by)
+                         VALUES ($1, $2, $3, $4, $5)
+                         ON CONFLICT (tenant, sensor_id) DO UPDATE
+                         SET config = EXCLUDED.config, updated_ms = EXCLUDED.updated_ms,
+                             updated_by = EXCLUDED.updated_by"
+                    )
+                    .bind(&self.tenant)
+                    .bind(i64::from(*sensor_id))
+                    .bind(Json(config))
+                    .bind(now_ms)
+                    .bind(&self.instance)
+                    .execute(&self.pool).await?;
+            }
+            StoreWrite::Persona { persona } => {
+                sqlx
+                    ::query(
+                        "INSERT INTO bridge_persona (tenant, persona, updated_ms, updated_by)
+                         VALUES ($1, $2, $3, $4)
+                         ON CONFLICT (tenant) DO UPDATE
+                         SET persona = EXCLUDED.persona, updated_ms = EXCLUDED.updated_ms,
+                             updated_by = EXCLUDED.updated_by"
+                    )
+                    .bind(&self.tenant)
+                    .bind(serde_json::to_value(persona)?.as_str().unwrap_or_default())
+                    .bind(now_ms)
+                    .bind(&self.instance)
+                    .execute(&self.pool).await?;
+            }
+            StoreWrite::Session { session } => {
+                sqlx
+                    ::query(
+                        "INSERT INTO bridge_sessions
+                         (tenant, instance, session_id, device, addr, started_ms, ended_ms, openai_events, responses, trace,
+                          child_talk_ms, robot_talk_ms, turns, interruptions)
+                         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)"
+                    )
+                    .bind(&self.tenant)
+                    .bind(&self.instance)
+                    .bind(session.id as i64)
+                    .bind(&session.device)
+                    .bind(session.addr.to_string())
+                    .bind(session.started_ms)
+                    .bind(session.ended_ms)
+                    .bind(session.openai_events as i64)
+                    .bind(session.responses as i64)
+                    .bind(session.trace.to_string())
+                    .bind(session.speech.child_talk_ms as i64)
+                    .bind(session.speech.robot_talk_ms as i64)
+                    .bind(session.speech.turns as i32)
+                    .bind(session.speech.interruptions as i32)
+                    .execute(&self.pool).await?;
+            }
+            StoreWrite::Transcript { transcript: t } => {
+                sqlx
+                    ::query(
+                        "INSERT INTO bridge_transcripts (tenant, instance, device, role, text, speaker, at_ms, trace)
+                         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+                    )
+                    .bind(&self.tenant)
+                    .bind(&self.instance)
+                    .bind(&t.device)
+                    .bind(match t.role {
+                        Role::User => "user",
+                        Role::Assistant => "assistant",
+                    })
+                    .bind(&t.text)
+                    .bind(&t.speaker)
+                    .bind(t.timestamp_ms as i64)
+                    .bind(t.trace.map(|trace| trace.to_string()))
+                    .execute(&self.pool).await?;
+            }
+            StoreWrite::Forget { device } => {
+                for table in ["bridge_sessions", "bridge_transcripts"] {
+                    sqlx
+                        ::query(&format!("DELETE FROM {table} WHERE tenant = $1 AND lower(device) = lower($2)"))
+                        .bind(&self.tenant)
+                        .bind(device)
+                        .execute(&self.pool).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write local changes and pick up other instances' until shutdown.
+pub async fn run(store: PgStore, mut outbox: mpsc::Receiver<StoreWrite>, shared: SharedStore, sync: Duration) {
+    let mut ticker = tokio::time::interval(sync);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut since_ms = chrono::Utc::now().timestamp_millis();
+    loop {
+        tokio::select! {
+            write = outbox.recv() => {
+                let Some(write) = write else {
+                    break;
+                };
+                if let Err(e) = store.write(&write).await {
+                    warn!(error = %e, "PostgreSQL write failed — dropping it");
+                }
+            }
+            _ = ticker.tick() => {
+                let changes = async {
+                    let persona = store.persona_since(since_ms, &store.instance).await?;
+                    let devices = store.devices_since(since_ms, &store.instance).await?;
+                    anyhow::Ok((persona, devices))
+                };
+                match changes.await {
+                    Ok((persona, devices)) => {
+                        let newest = persona.iter().map(|(_, at)| *at).chain(devices.iter().map(|(_, _, at)| *at)).max();
+                        if let Some(newest) = newest {
+                            debug!(devices = devices.len(), persona = persona.is_some(), "🐘 changes from other instances");
+                            let devices: Vec<(u32, DeviceConfig)> = devices.into_iter().map(|(id, cfg, _)| (id, cfg)).collect();
+                            shared.apply_remote(persona.map(|(p, _)| p), &devices).await;
+                            since_ms = since_ms.max(newest);
+                        }
+                    }
+                    Err(e) => warn!(error = %e, "PostgreSQL sync failed"),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,504 @@
+use crate::clock::{ self, SharedClock };
+use crate::config::Config;
+use crate::mqtt_health::{ self, MqttHealth };
+use crate::vad::{ VadKind, VadResult };
+use dashmap::DashMap;
+use rumqttc::v5::mqttbytes::v5::{ Packet, PublishProperties };
+use rumqttc::v5::mqttbytes::QoS;
+use rumqttc::v5::{ AsyncClient, Event };
+use serde::{ Deserialize, Serialize };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::sync::{ Arc, RwLock };
+use std::time::{ Duration, Instant };
+use tokio::sync::{ broadcast, mpsc };
+use tracing::{ debug, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Threshold-crossing subscriptions
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Clients register rules like "arousal above 0.8 on sensor 42 for 5 s"
+//  instead of polling the emotion timeline:
+//
+//    {"device": 42, "metric": "arousal", "op": "above", "threshold": 0.8,
+//     "for_secs": 5, "deliver": {"type": "webhook", "url": "http://..."}}
+//
+//  Every emotional VadResult is checked against every rule.  A rule
+//  triggers once its condition has held continuously for `for_secs` on
+//  a device, and clears (a second alert) when the condition stops
+//  holding; it then re-arms.  Without `device` a rule watches every
+//  sensor, each with its own hold timer.
+//
+//  Alerts are delivered to one of:
+//
+//    * `webhook` — HTTP POST of the alert JSON to `url`
+//    * `mqtt`    — `topic`, or `<--alert-mqtt-topic>/[<tenant>/]<id>`
+//    * `ws`      — every `GET /subscriptions/ws` client
+//
+//  Rules are managed over REST (`/subscriptions`) or, with
+//  `--alert-mqtt-host`, by publishing a rule to `<prefix>/subscribe` or
+//  an id to `<prefix>/unsubscribe`; the outcome is published to
+//  `<prefix>/subscribed`.  Rules live in memory only.
+//
+//  Delivery runs on its own task behind a bounded queue (like
+//  transcript forwarding), so a slow webhook never stalls VAD.
+
+/// Most rules one bridge instance accepts.
+pub const MAX_SUBSCRIPTIONS: usize = 256;
+
+/// Longest hold time a rule may ask for.
+const MAX_FOR_SECS: f64 = 3600.0;
+
+/// V/A/D channel a rule watches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    Valence,
+    Arousal,
+    Dominance,
+}
+
+impl Metric {
+    fn of(self, result: &VadResult) -> f32 {
+        match self {
+            Metric::Valence => result.valence,
+            Metric::Arousal => result.arousal,
+            Metric::Dominance => result.dominance,
+        }
+    }
+}
+
+/// Direction of the threshold crossing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Comparison {
+    #[serde(alias = ">")]
+    Above,
+    #[serde(alias = "<")]
+    Below,
+}
+
+impl Comparison {
+    fn holds(self, value: f32, threshold: f32) -> bool {
+        match self {
+            Comparison::Above => value > threshold,
+            Comparison::Below => value < threshold,
+        }
+    }
+}
+
+/// Where a rule's alerts go.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Delivery {
+    Webhook {
+        url: String,
+    },
+    Mqtt {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        topic: Option<String>,
+    },
+    Ws,
+}
+
+/// A rule as registered by a client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    /// Sensor to watch (omitted = every sensor)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<u32>,
+    pub metric: Metric,
+    pub op: Comparison,
+    pub threshold: f32,
+    /// How long the condition must hold before the rule triggers
+    #[serde(default)]
+    pub for_secs: f64,
+    pub deliver: Delivery,
+}
+
+/// A registered rule.
+#[derive(Debug, Clone, Serialize)]
+pub struct Subscription {
+    pub id: u64,
+    #[serde(flatten)]
+    pub rule: Rule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    Triggered,
+    Cleared,
+}
+
+/// One delivered alert.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub subscription: u64,
+    pub sensor_id: u32,
+    pub seq: u64,
+    pub state: AlertState,
+    pub metric: Metric,
+    pub value: f32,
+    pub threshold: f32,
+    /// How long the condition had held
+    pub held_ms: u64,
+    pub timestamp_ms: u64,
+    /// Tenant of the bridge instance (omitted in single-tenant mode)
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tenant: String,
+}
+
+/// Hold timer of one rule on one sensor.
+#[derive(Debug, Clone, Copy, Default)]
+struct Hold {
+    since: Option<Instant>,
+    fired: bool,
+}
+
+/// Rule registry + evaluator.  Clone-friendly (Arc inside).
+#[derive(Clone)]
+pub struct Subscriptions {
+    rules: Arc<RwLock<Vec<Subscription>>>,
+    holds: Arc<DashMap<(u64, u32), Hold>>,
+    next_id: Arc<AtomicU64>,
+    alerts: mpsc::Sender<(Alert, Delivery)>,
+    ws: broadcast::Sender<Alert>,
+    mqtt_enabled: bool,
+    tenant: String,
+    clock: SharedClock,
+}
+
+impl Subscriptions {
+    fn new(
+        tenant: &str,
+        mqtt_enabled: bool,
+        clock: SharedClock
+    ) -> (Self, mpsc::Receiver<(Alert, Delivery)>) {
+        let (alerts, rx) = mpsc::channel(256);
+        let subs = Self {
+            rules: Arc::default(),
+            holds: Arc::default(),
+            next_id: Arc::new(AtomicU64::new(1)),
+            alerts,
+            ws: broadcast::channel(256).0,
+            mqtt_enabled,
+            tenant: tenant.to_string(),
+            clock,
+        };
+        (subs, rx)
+    }
+
+    /// Start the alert delivery task (and, with `--alert-mqtt-host`, the
+    /// MQTT connection that also accepts rule registrations).
+    pub fn from_config(config: &Config, clock: SharedClock, health: &MqttHealth) -> anyhow::Result<Self> {
+        let mqtt_enabled = !config.alert_mqtt_host.is_empty();
+        let (subs, rx) = Self::new(&config.tenant_id, mqtt_enabled, clock);
+        let prefix = config.alert_mqtt_topic.clone();
+
+        let mqtt = if mqtt_enabled {
+            let opts = mqtt_health::client_options("alerts", &config.tenant_id, &config.alert_mqtt_host, config.alert_mqtt_port);
+            let props = crate::mqtt5::json("subscription-reply", &config.tenant_id, config.mqtt_expiry_secs);
+            let (topics, rules) = (prefix.clone(), subs.clone());
+            let (client, _) = mqtt_health::spawn_client(opts, 64, health, "alerts", move |client, event| {
+                on_mqtt_event(&rules, client, event, &topics, &props)
+            });
+            info!(
+                broker = format!("{}:{}", config.alert_mqtt_host, config.alert_mqtt_port),
+                topic = %prefix,
+                "🔔 alert MQTT enabled"
+            );
+            Some(client)
+        } else {
+            None
+        };
+
+        let http = reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?;
+        let ws = subs.ws.clone();
+        let tenant = config.tenant_id.clone();
+        let props = crate::mqtt5::json("alert", &tenant, config.mqtt_expiry_secs);
+        tokio::spawn(deliver_loop(rx, http, mqtt.map(|client| (client, props)), ws, prefix, tenant));
+        Ok(subs)
+    }
+
+    /// Register `rule`; errors are returned as a message for a 400.
+    pub fn add(&self, rule: Rule) -> Result<Subscription, String> {
+        self.validate(&rule)?;
+        let mut rules = self.rules.write().unwrap_or_else(|e| e.into_inner());
+        if rules.len() >= MAX_SUBSCRIPTIONS {
+            return Err(format!("at most {MAX_SUBSCRIPTIONS} subscriptions"));
+        }
+        let sub = Subscription { id: self.next_id.fetch_add(1, Ordering::Relaxed), rule };
+        rules.push(sub.clone());
+        info!(id = sub.id, rule = ?sub.rule, "🔔 subscription added");
+        Ok(sub)
+    }
+
+    /// Remove subscription `id`; false if there was none.
+    pub fn remove(&self, id: u64) -> bool {
+        let mut rules = self.rules.write().unwrap_or_else(|e| e.into_inner());
+        let before = rules.len();
+        rules.retain(|s| s.id != id);
+        let removed = rules.len() < before;
+        drop(rules);
+        if removed {
+            self.holds.retain(|(sub, _), _| *sub != id);
+            info!(id, "🔕 subscription removed");
+        }
+        removed
+    }
+
+    pub fn list(&self) -> Vec<Subscription> {
+        self.rules
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Alerts of `ws` subscriptions.
+    pub fn subscribe_ws(&self) -> broadcast::Receiver<Alert> {
+        self.ws.subscribe()
+    }
+
+    fn validate(&self, rule: &Rule) -> Result<(), String> {
+        if !rule.threshold.is_finite() {
+            return Err("threshold must be a number".into());
+        }
+        if !(0.0..=MAX_FOR_SECS).contains(&rule.for_secs) {
+            return Err(format!("for_secs must be within 0..={MAX_FOR_SECS}"));
+        }
+        match &rule.deliver {
+            Delivery::Webhook { url } if !(url.starts_with("http://") || url.starts_with("https://")) => {
+                Err(format!("webhook url must be http(s): {url:?}"))
+            }
+            Delivery::Mqtt { .. } if !self.mqtt_enabled => {
+                Err("mqtt delivery needs --alert-mqtt-host".into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check an emotional `result` against every rule.
+    pub fn observe(&self, result: &VadResult) {
+        if result.kind != VadKind::Emotional {
+            return;
+        }
+        let rules = self.rules.read().unwrap_or_else(|e| e.into_inner());
+        if rules.is_empty() {
+            return;
+        }
+        let now = self.clock.now();
+        for sub in rules.iter() {
+            let rule = &sub.rule;
+            if rule.device.is_some_and(|d| d != result.sensor_id) {
+                continue;
+            }
+            let value = rule.metric.of(result);
+            let mut hold = self.holds.entry((sub.id, result.sensor_id)).or_default();
+            let (state, since) = if rule.op.holds(value, rule.threshold) {
+                let since = *hold.since.get_or_insert(now);
+                if hold.fired || now.duration_since(since).as_secs_f64() < rule.for_secs {
+                    continue;
+                }
+                hold.fired = true;
+                (AlertState::Triggered, since)
+            } else {
+                let was = std::mem::take(&mut *hold);
+                if !was.fired {
+                    continue;
+                }
+                (AlertState::Cleared, was.since.unwrap_or(now))
+            };
+            drop(hold);
+            let held = now.duration_since(since);
+            let alert = Alert {
+                subscription: sub.id,
+                sensor_id: result.sensor_id,
+                seq: result.seq,
+                state,
+                metric: rule.metric,
+                value,
+                threshold: rule.threshold,
+                held_ms: held.as_millis() as u64,
+                timestamp_ms: clock::unix_millis(self.clock.as_ref()),
+                tenant: self.tenant.clone(),
+            };
+            debug!(subscription = sub.id, sensor_id = result.sensor_id, state = ?state, "🔔 alert");
+            if self.alerts.try_send((alert, rule.deliver.clone())).is_err() {
+                warn!(subscription = sub.id, "alert queue full — dropping alert");
+            }
+        }
+    }
+}
+
+/// Deliver queued alerts to their webhook / MQTT topic / WebSocket clients.
+async fn deliver_loop(
+    mut rx: mpsc::Receiver<(Alert, Delivery)>,
+    http: reqwest::Client,
+    mqtt: Option<(AsyncClient, PublishProperties)>,
+    ws: broadcast::Sender<Alert>,
+    prefix: String,
+    tenant: String
+) {
+    while let Some((alert, deliver)) = rx.recv().await {
+        match deliver {
+            Delivery::Ws => {
+                // No WebSocket clients is fine
+                let _ = ws.send(alert);
+            }
+            Delivery::Webhook { url } => {
+                match http.post(&url).json(&alert).send().await {
+                    Ok(resp) if resp.status().is_success() => {}
+                    Ok(resp) => warn!(url = %url, status = %resp.status(), "alert webhook rejected"),
+                    Err(e) => warn!(url = %url, error = %e, "alert webhook failed"),
+                }
+            }
+            Delivery::Mqtt { topic } => {
+                let Some((ref client, ref props)) = mqtt else {
+                    continue;
+                };
+                let topic = topic.unwrap_or_else(|| alert_topic(&prefix, &tenant, alert.subscription));
+                let body = match serde_json::to_vec(&alert) {
+                    Ok(b) => b,
+                    Err(_) => continue,
+                };
+                if let Err(e) = client.publish_with_properties(topic, QoS::AtLeastOnce, false, body, props.clone()).await {
+                    warn!(error = %e, "failed to queue alert for MQTT");
+                }
+            }
+        }
+    }
+}
+
+/// Default MQTT topic for alerts of subscription `id`.
+fn alert_topic(prefix: &str, tenant: &str, id: u64) -> String {
+    if tenant.is_empty() { format!("{prefix}/{id}") } else { format!("{prefix}/{tenant}/{id}") }
+}
+
+/// One event of the alert MQTT connection: (re)subscribe on ConnAck,
+/// handle rule registrations on `<prefix>/subscribe` /
+/// `<prefix>/unsubscribe`.
+fn on_mqtt_event(subs: &Subscriptions, client: &AsyncClient, event: Event, prefix: &str, props: &PublishProperties) {
+    let subscribe = format!("{prefix}/subscribe");
+    let unsubscribe = format!("{prefix}/unsubscribe");
+    let replies = format!("{prefix}/subscribed");
+    match event {
+        Event::Incoming(Packet::ConnAck(_)) => {
+            // (Re)subscribe on every connect: the session is not persistent
+            for topic in [&subscribe, &unsubscribe] {
+                if let Err(e) = client.try_subscribe(topic.as_str(), QoS::AtLeastOnce) {
+                    warn!(topic = %topic, error = %e, "failed to subscribe");
+                }
+            }
+        }
+        Event::Incoming(Packet::Publish(msg)) => {
+            let reply = if msg.topic == subscribe {
+                serde_json
+                    ::from_slice::<Rule>(&msg.payload)
+                    .map_err(|e| e.to_string())
+                    .and_then(|rule| subs.add(rule))
+                    .map(|sub| serde_json::json!(sub))
+            } else if msg.topic == unsubscribe {
+                match std::str::from_utf8(&msg.payload).ok().and_then(|s| s.trim().parse::<u64>().ok()) {
+                    Some(id) if subs.remove(id) => Ok(serde_json::json!({ "removed": id })),
+                    Some(id) => Err(format!("no subscription {id}")),
+                    None => Err("payload must be a subscription id".into()),
+                }
+            } else {
+                return;
+            };
+            let body = reply.unwrap_or_else(|error| serde_json::json!({ "error": error })).to_string();
+            if let Err(e) = client.try_publish_with_properties(replies.as_str(), QoS::AtLeastOnce, false, body, props.clone()) {
+                warn!(error = %e, "failed to queue subscription reply");
+            }
+        }
+        _ => {}
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimClock;
+
+    fn emotional(sensor_id: u32, seq: u64, arousal: f32) -> VadResult {
+        VadResult {
+            sensor_id,
+            seq,
+            kind: VadKind::Emotional,
+            is_active: arousal > 0.35,
+            energy: 0.0,
+            threshold: 0.0,
+            dbfs: -96.0,
+            zcr: 0.0,
+            band_ratio: 0.0,
+            valence: 0.5,
+            arousal,
+            dominance: 0.5,
+            trace: None,
+        }
+    }
+
+    fn rule(device: Option<u32>, for_secs: f64) -> Rule {
+        serde_json
+            ::from_value(
+                serde_json::json!({
+                "device": device, "metric": "arousal", "op": ">", "threshold": 0.8,
+                "for_secs": for_secs, "deliver": {"type": "ws"}
+            })
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_hold_trigger_and_clear() {
+        let sim = SimClock::default();
+        let (subs, mut rx) = Subscriptions::new("", false, Arc::new(sim.clone()));
+        let id = subs.add(rule(Some(7), 5.0)).unwrap().id;
+
+        // Other devices and short spikes don't trigger
+        subs.observe(&emotional(8, 0, 0.9));
+        subs.observe(&emotional(7, 0, 0.9));
+        sim.advance(Duration::from_secs(3));
+        subs.observe(&emotional(7, 1, 0.5));
+        subs.observe(&emotional(7, 2, 0.9));
+        sim.advance(Duration::from_secs(4));
+        subs.observe(&emotional(7, 3, 0.9));
+        assert!(rx.try_recv().is_err());
+
+        // Held for 5 s → one trigger, then one clear
+        sim.advance(Duration::from_secs(1));
+        subs.observe(&emotional(7, 4, 0.95));
+        subs.observe(&emotional(7, 5, 0.95));
+        subs.observe(&emotional(7, 6, 0.2));
+        let (triggered, deliver) = rx.try_recv().unwrap();
+        assert_eq!((triggered.subscription, triggered.seq, triggered.state), (id, 4, AlertState::Triggered));
+        assert_eq!(triggered.held_ms, 5_000);
+        assert_eq!(deliver, Delivery::Ws);
+        let (cleared, _) = rx.try_recv().unwrap();
+        assert_eq!((cleared.seq, cleared.state), (6, AlertState::Cleared));
+        assert!(rx.try_recv().is_err());
+
+        assert!(subs.remove(id));
+        assert!(!subs.remove(id));
+        assert!(subs.holds.is_empty());
+    }
+
+    #[test]
+    fn test_validation() {
+        let (subs, _rx) = Subscriptions::new("", false, Arc::new(SimClock::default()));
+        let mut bad = rule(None, -1.0);
+        assert!(subs.add(bad.clone()).is_err());
+        bad.for_secs = 0.0;
+        bad.deliver = Delivery::Mqtt { topic: None };
+        assert!(subs.add(bad.clone()).unwrap_err().contains("--alert-mqtt-host"));
+        bad.deliver = Delivery::Webhook { url: "ftp://x".into() };
+        assert!(subs.add(bad).is_err());
+        assert!(subs.list().is_empty());
+        assert_eq!(alert_topic("vad/alerts", "acme", 3), "vad/alerts/acme/3");
+    }
+}
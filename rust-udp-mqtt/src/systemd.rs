@@ -0,0 +1,289 @@
+use crate::clock::SharedClock;
+use crate::shard::ShardedSender;
+use crate::stats::Stats;
+use serde::Serialize;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::{ Arc, Mutex };
+use std::time::{ Duration, Instant };
+use tokio::task::AbortHandle;
+use tracing::{ error, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  systemd integration (sd_notify + watchdog)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Under a `Type=notify` unit systemd passes `NOTIFY_SOCKET`, and with
+//  `WatchdogSec=` also `WATCHDOG_USEC`.  The bridge then sends:
+//
+//    READY=1       once every instance (one per tenant) has its
+//                  transport up
+//    WATCHDOG=1    every half `WATCHDOG_USEC`, but only while every
+//                  instance is live
+//    STATUS=...    a one-line state for `systemctl status`
+//
+//  An instance is live while none of its socket tasks (UDP receive
+//  loops, response sender, RTP / SIP) has exited and its worker queues
+//  are not wedged: packets queued, yet for `--watchdog-stall-secs` no
+//  worker processed or shed one and the backlog did not shrink.  Pings
+//  also stop when the runtime itself hangs, because they are sent from
+//  a task on it.  Once pings stop systemd kills and restarts the bridge
+//  after `WatchdogSec` (with `Restart=on-failure`); a stall that clears
+//  first resumes them.
+//
+//  Without `NOTIFY_SOCKET` nothing is sent.  `/readyz` reports the same
+//  liveness either way.
+
+/// Liveness of one bridge instance.  Clone-friendly.
+#[derive(Clone)]
+pub struct Liveness {
+    inner: Arc<LivenessInner>,
+}
+
+struct LivenessInner {
+    tenant: String,
+    queues: ShardedSender,
+    stats: Arc<Stats>,
+    stall: Duration,
+    clock: SharedClock,
+    started: AtomicBool,
+    tasks: Mutex<Vec<AbortHandle>>,
+    progress: Mutex<Progress>,
+}
+
+/// Worker progress as of the last check.
+struct Progress {
+    /// Packets processed + shed
+    done: u64,
+    backlog: usize,
+    /// Last time the workers were seen idle or making progress
+    since: Instant,
+}
+
+/// `/readyz` view of a [`Liveness`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LivenessSnapshot {
+    pub live: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub problem: Option<String>,
+}
+
+impl Liveness {
+    pub fn new(tenant: &str, queues: ShardedSender, stats: Arc<Stats>, stall: Duration, clock: SharedClock) -> Self {
+        let progress = Progress { done: 0, backlog: 0, since: clock.now() };
+        Self {
+            inner: Arc::new(LivenessInner {
+                tenant: tenant.to_string(),
+                queues,
+                stats,
+                stall,
+                clock,
+                started: AtomicBool::new(false),
+                tasks: Mutex::new(Vec::new()),
+                progress: Mutex::new(progress),
+            }),
+        }
+    }
+
+    /// The transport is up; `tasks` must run for the life of the
+    /// instance.
+    pub fn started(&self, tasks: impl IntoIterator<Item = AbortHandle>) {
+        self.inner.tasks.lock().unwrap().extend(tasks);
+        self.inner.started.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.inner.started.load(Ordering::Relaxed)
+    }
+
+    /// Why the instance is not live, if it is not.
+    pub fn check(&self) -> Result<(), String> {
+        let exited = self.inner.tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|t| t.is_finished())
+            .count();
+        if exited > 0 {
+            return Err(format!("{exited} socket task(s) exited"));
+        }
+
+        let stats = &self.inner.stats;
+        let done = stats.processed.load(Ordering::Relaxed) + stats.shed.load(Ordering::Relaxed);
+        let backlog = self.inner.queues.backlog();
+        let now = self.inner.clock.now();
+        let mut progress = self.inner.progress.lock().unwrap();
+        let moving = backlog == 0 || done != progress.done || backlog < progress.backlog;
+        progress.done = done;
+        progress.backlog = backlog;
+        if moving {
+            progress.since = now;
+            return Ok(());
+        }
+        let stalled = now.saturating_duration_since(progress.since);
+        if stalled >= self.inner.stall {
+            return Err(format!("worker queues wedged: {backlog} packet(s) queued, none taken for {} s", stalled.as_secs()));
+        }
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> LivenessSnapshot {
+        let problem = self.check().err();
+        LivenessSnapshot { live: problem.is_none(), problem }
+    }
+}
+
+/// Send `state` to systemd; false when not started with `NOTIFY_SOCKET`.
+pub fn notify(state: &str) -> bool {
+    let Ok(path) = std::env::var("NOTIFY_SOCKET") else {
+        return false;
+    };
+    match send(&path, state) {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(socket = %path, error = %e, "sd_notify failed");
+            false
+        }
+    }
+}
+
+fn send(path: &str, state: &str) -> std::io::Result<()> {
+    let socket = std::os::unix::net::UnixDatagram::unbound()?;
+    #[cfg(target_os = "linux")]
+    if let Some(name) = path.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        socket.send_to_addr(state.as_bytes(), &addr)?;
+        return Ok(());
+    }
+    socket.send_to(state.as_bytes(), path)?;
+    Ok(())
+}
+
+/// Ping interval for `WatchdogSec=` (half of it), if set for this
+/// process.
+pub fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.trim().parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.trim().parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+/// Report readiness once every instance started, then ping the
+/// watchdog while all of them are live.  Returns at once without
+/// `NOTIFY_SOCKET`.
+pub async fn supervise(instances: Vec<Liveness>) {
+    if std::env::var_os("NOTIFY_SOCKET").is_none() {
+        return;
+    }
+    while !instances.iter().all(Liveness::is_started) {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    notify(&format!("READY=1\nSTATUS=serving {} instance(s)", instances.len()));
+    let Some(interval) = watchdog_interval() else {
+        info!("🐧 systemd notified (ready, no watchdog)");
+        return;
+    };
+    info!(interval_ms = interval.as_millis() as u64, "🐧 systemd notified (ready), pinging the watchdog");
+    let mut ticker = tokio::time::interval(interval);
+    let mut failing = false;
+    loop {
+        ticker.tick().await;
+        let problems: Vec<String> = instances
+            .iter()
+            .filter_map(|l| l.check().err().map(|e| if l.inner.tenant.is_empty() { e } else { format!("[{}] {e}", l.inner.tenant) }))
+            .collect();
+        if problems.is_empty() {
+            if failing {
+                info!("🐧 bridge live again, resuming watchdog pings");
+                notify(&format!("STATUS=serving {} instance(s)", instances.len()));
+                failing = false;
+            }
+            notify("WATCHDOG=1");
+        } else if !failing {
+            let status = problems.join("; ");
+            error!(problems = %status, "🐧 bridge not live — withholding watchdog pings");
+            notify(&format!("STATUS=not live: {status}"));
+            failing = true;
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimClock;
+    use crate::sensor::SensorPacket;
+    use crate::shard::{ self, LaneConfig };
+
+    fn packet(seq: u64) -> SensorPacket {
+        SensorPacket {
+            sensor_id: 1,
+            timestamp_us: 0,
+            data_type: 0,
+            sample_format: crate::pcm::SampleFormat::S16,
+            seq,
+            payload: Vec::new(),
+            tenant: Default::default(),
+            ingested_at: None,
+            trace: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wedged_queue_and_exited_task() {
+        let sim = SimClock::default();
+        let clock: SharedClock = Arc::new(sim.clone());
+        let lane = LaneConfig { workers: 1, capacity: 8 };
+        let (tx, mut rxs) = shard::channels(lane, lane, clock.clone());
+        let stats = Stats::new();
+        let liveness = Liveness::new("", tx.clone(), stats.clone(), Duration::from_secs(10), clock);
+        assert!(!liveness.is_started());
+
+        // Idle queues are live however long nothing happens
+        sim.advance(Duration::from_secs(60));
+        assert!(liveness.check().is_ok());
+
+        // Queued but untouched: wedged after the stall limit
+        tx.try_send(packet(0)).unwrap();
+        tx.try_send(packet(1)).unwrap();
+        assert!(liveness.check().is_ok());
+        sim.advance(Duration::from_secs(9));
+        assert!(liveness.check().is_ok());
+        sim.advance(Duration::from_secs(1));
+        let err = liveness.check().unwrap_err();
+        assert!(err.contains("wedged: 2 packet(s)"), "{err}");
+        assert!(!liveness.snapshot().live);
+
+        // A worker takes one: live again
+        rxs[1].1.try_recv().unwrap();
+        assert!(liveness.check().is_ok());
+        sim.advance(Duration::from_secs(10));
+        stats.record_processed(false);
+        assert!(liveness.check().is_ok());
+
+        let task = tokio::spawn(async {});
+        liveness.started([task.abort_handle()]);
+        assert!(liveness.is_started());
+        task.await.unwrap();
+        assert_eq!(liveness.check().unwrap_err(), "1 socket task(s) exited");
+    }
+
+    #[test]
+    fn test_notify_datagram() {
+        let path = std::env::temp_dir().join(format!("vad-notify-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+        send(path.to_str().unwrap(), "READY=1").unwrap();
+        let mut buf = [0u8; 64];
+        let n = listener.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
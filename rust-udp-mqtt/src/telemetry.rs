@@ -0,0 +1,205 @@
+use crate::esp_audio_protocol::{
+    TelemetryMessage,
+    TELEMETRY_COUNTER_BATTERY_MV,
+    TELEMETRY_COUNTER_HEAP_FREE,
+    TELEMETRY_COUNTER_RSSI,
+    TELEMETRY_COUNTER_UPTIME_S,
+};
+use dashmap::DashMap;
+use serde::{ Deserialize, Serialize };
+use std::collections::{ BTreeMap, VecDeque };
+use std::sync::Arc;
+use tracing::{ debug, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Firmware logs + telemetry counters
+// ─────────────────────────────────────────────────────────────────────
+//
+//  ESPs send their log lines and counters (free heap, RSSI, battery mV,
+//  uptime) as `PKT_TELEMETRY` packets on the audio port, so field
+//  problems can be read off the bridge instead of a serial console.
+//  Per device (MAC or `ip:port`) the bridge keeps, in memory:
+//
+//    logs       the last `--telemetry-log-lines` lines
+//    counters   the latest value of every counter, and the last
+//               `COUNTER_HISTORY` reports for trends (battery drain,
+//               heap leaks)
+//
+//  `GET /devices/{device}/telemetry?lines=N` serves both.  Device error
+//  and warning lines are also logged by the bridge.
+
+/// Counter reports kept per device.
+const COUNTER_HISTORY: usize = 240;
+
+/// One firmware log line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogLine {
+    /// Arrival time (Unix ms)
+    pub at_ms: u64,
+    pub level: &'static str,
+    pub text: String,
+}
+
+/// Counter values at a point in time, by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Counters {
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub values: BTreeMap<String, i32>,
+}
+
+/// What `GET /devices/{device}/telemetry` returns.
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryReport {
+    pub device: String,
+    /// Latest value of every counter (`at_ms` = last report)
+    pub counters: Counters,
+    /// Counter reports, oldest first
+    pub history: Vec<Counters>,
+    /// Log lines, oldest first
+    pub logs: Vec<LogLine>,
+}
+
+/// `GET /devices/{device}/telemetry` query.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TelemetryQuery {
+    /// Only the last N log lines
+    pub lines: Option<usize>,
+}
+
+#[derive(Default)]
+struct DeviceRecord {
+    logs: VecDeque<LogLine>,
+    latest: Counters,
+    history: VecDeque<Counters>,
+}
+
+/// Telemetry of every device.  Clone-friendly.
+#[derive(Clone)]
+pub struct DeviceTelemetry {
+    log_lines: usize,
+    devices: Arc<DashMap<String, DeviceRecord>>,
+}
+
+/// Name of an ESP-IDF log level.
+fn level_name(level: u8) -> &'static str {
+    match level {
+        1 => "error",
+        2 => "warn",
+        4 => "debug",
+        5 => "verbose",
+        _ => "info",
+    }
+}
+
+/// Name of a `TELEMETRY_COUNTER_*` id.
+fn counter_name(id: u8) -> String {
+    match id {
+        TELEMETRY_COUNTER_HEAP_FREE => "heap_free".into(),
+        TELEMETRY_COUNTER_RSSI => "rssi_dbm".into(),
+        TELEMETRY_COUNTER_BATTERY_MV => "battery_mv".into(),
+        TELEMETRY_COUNTER_UPTIME_S => "uptime_s".into(),
+        id => format!("counter_{id}"),
+    }
+}
+
+impl DeviceTelemetry {
+    /// Keep `log_lines` log lines per device.
+    pub fn new(log_lines: usize) -> Self {
+        Self { log_lines, devices: Arc::default() }
+    }
+
+    /// Store a message from `device` received at `at_ms` (Unix ms).
+    pub fn record(&self, device: &str, message: TelemetryMessage, at_ms: u64) {
+        let mut record = self.devices.entry(device.to_ascii_lowercase()).or_default();
+        match message {
+            TelemetryMessage::Log { level, text } => {
+                let level = level_name(level);
+                match level {
+                    "error" | "warn" => warn!(device = %device, level, "📟 {text}"),
+                    _ => debug!(device = %device, level, "📟 {text}"),
+                }
+                if self.log_lines == 0 {
+                    return;
+                }
+                if record.logs.len() == self.log_lines {
+                    record.logs.pop_front();
+                }
+                record.logs.push_back(LogLine { at_ms, level, text });
+            }
+            TelemetryMessage::Counters(values) => {
+                let report = Counters {
+                    at_ms,
+                    values: values.into_iter().map(|(id, v)| (counter_name(id), v)).collect(),
+                };
+                record.latest.at_ms = at_ms;
+                record.latest.values.extend(report.values.clone());
+                if record.history.len() == COUNTER_HISTORY {
+                    record.history.pop_front();
+                }
+                record.history.push_back(report);
+            }
+        }
+    }
+
+    /// Latest counters of `device` (None if it never sent any).
+    pub fn counters(&self, device: &str) -> Option<Counters> {
+        let record = self.devices.get(&device.to_ascii_lowercase())?;
+        (!record.history.is_empty()).then(|| record.latest.clone())
+    }
+
+    /// Everything stored for `device` (None if it never sent any).
+    pub fn report(&self, device: &str, query: &TelemetryQuery) -> Option<TelemetryReport> {
+        let record = self.devices.get(&device.to_ascii_lowercase())?;
+        let skip = query.lines.map_or(0, |n| record.logs.len().saturating_sub(n));
+        Some(TelemetryReport {
+            device: device.to_string(),
+            counters: record.latest.clone(),
+            history: record.history.iter().cloned().collect(),
+            logs: record.logs.iter().skip(skip).cloned().collect(),
+        })
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logs_and_counters_per_device() {
+        let telemetry = DeviceTelemetry::new(2);
+        let log = |text: &str| TelemetryMessage::parse(&[&[1u8, 2][..], text.as_bytes()].concat()).unwrap();
+        for (i, text) in ["boot\n", "wifi up", "i2s underrun"].into_iter().enumerate() {
+            telemetry.record("AA:BB:CC:DD:EE:01", log(text), i as u64);
+        }
+        // heap 51200, battery 3700 mV, then a newer battery reading
+        let mut counters = vec![2u8];
+        counters.extend([1u8, 0x00, 0xc8, 0, 0, 3, 0x74, 0x0e, 0, 0]);
+        telemetry.record("aa:bb:cc:dd:ee:01", TelemetryMessage::parse(&counters).unwrap(), 10);
+        telemetry.record("aa:bb:cc:dd:ee:01", TelemetryMessage::Counters(vec![(3, 3650), (9, -1)]), 20);
+        assert_eq!(TelemetryMessage::parse(&[2, 1, 0, 0]), None, "partial counter record");
+
+        let report = telemetry.report("aa:bb:cc:dd:ee:01", &TelemetryQuery::default()).unwrap();
+        let texts: Vec<&str> = report.logs.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, ["wifi up", "i2s underrun"], "oldest line dropped");
+        assert_eq!(report.logs[0].level, "warn");
+        assert_eq!(report.counters.at_ms, 20);
+        assert_eq!(
+            report.counters.values,
+            BTreeMap::from([
+                ("battery_mv".to_string(), 3650),
+                ("counter_9".to_string(), -1),
+                ("heap_free".to_string(), 51_200),
+            ])
+        );
+        assert_eq!(report.history.len(), 2);
+
+        let last = telemetry.report("AA:BB:CC:DD:EE:01", &TelemetryQuery { lines: Some(1) }).unwrap();
+        assert_eq!(last.logs.len(), 1);
+        assert!(telemetry.report("10.0.0.9:5000", &TelemetryQuery::default()).is_none());
+    }
+}
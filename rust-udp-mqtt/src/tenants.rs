@@ -0,0 +1,236 @@
+use crate::config::Config;
+use crate::persona::PersonaTrait;
+use serde::{ Deserialize, Serialize };
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Multi-tenant port ranges
+// ─────────────────────────────────────────────────────────────────────
+//
+//  `--tenants-file tenants.toml` runs one fully isolated bridge per
+//  tenant inside a single process: each gets its own UDP audio / sensor
+//  / test ports, REST API port, ESP sessions, persona, smoother state,
+//  device registry, OpenAI session (and API key) and recordings dir.
+//  Nothing is shared except the tokio runtime.
+//
+//    port_range = "9001-9020"        # optional pool for tenants
+//                                    # without explicit ports
+//    [[tenant]]
+//    id = "acme"
+//    openai_api_key = "sk-..."       # optional overrides ↓
+//    openai_instructions_file = "prompts/acme.txt"
+//    persona = "cute"
+//
+//    [[tenant]]
+//    id = "globex"
+//    audio_port = 9101
+//    sensor_port = 9102
+//    test_port = 9103
+//    api_port = 8090
+//
+//  Tenants without ports take consecutive audio/sensor/test triples
+//  from `port_range` in file order.  Unset API ports are `--api-port`
+//  + 1, + 2, ... in file order.  Unset `audio_save_dir` is
+//  `<--audio-save-dir>/<id>`.  Every other setting comes from the
+//  command line.
+//
+//  The tenant id is also a data dimension: UDP receivers stamp it on
+//  every `SensorPacket` by ingress port, it labels stats lines, sensor
+//  events and logs, and the base `--api-port` serves
+//  `GET /tenants`, a cross-tenant `POST /devices/import` plus every
+//  tenant's REST API under `/tenants/{id}/...`.
+
+/// Tenant a packet / device / recording belongs to ("" = single tenant).
+pub type TenantId = Arc<str>;
+
+/// One tenant as listed by `GET /tenants`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TenantInfo {
+    pub id: String,
+    pub audio_port: u16,
+    pub sensor_port: u16,
+    pub test_port: u16,
+    pub api_port: u16,
+    pub audio_save_dir: String,
+}
+
+impl TenantInfo {
+    pub fn of(config: &Config) -> Self {
+        Self {
+            id: config.tenant_id.clone(),
+            audio_port: config.audio_port,
+            sensor_port: config.sensor_port,
+            test_port: config.test_port,
+            api_port: config.api_port,
+            audio_save_dir: config.audio_save_dir.clone(),
+        }
+    }
+}
+
+/// `tenants.toml` contents.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TenantsFile {
+    port_range: Option<String>,
+    #[serde(rename = "tenant", default)]
+    tenants: Vec<TenantSpec>,
+}
+
+/// One `[[tenant]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantSpec {
+    pub id: String,
+    pub audio_port: Option<u16>,
+    pub sensor_port: Option<u16>,
+    pub test_port: Option<u16>,
+    pub api_port: Option<u16>,
+    pub openai_api_key: Option<String>,
+    pub openai_instructions_file: Option<String>,
+    pub persona: Option<PersonaTrait>,
+    pub audio_save_dir: Option<String>,
+}
+
+/// Read `path` and build one `Config` per tenant from `base`.
+pub fn load(path: &str, base: &Config) -> anyhow::Result<Vec<Config>> {
+    let text = std::fs
+        ::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read tenants file {path}: {e}"))?;
+    let file: TenantsFile = toml
+        ::from_str(&text)
+        .map_err(|e| anyhow::anyhow!("invalid tenants file {path}: {e}"))?;
+    resolve(&file, base)
+}
+
+fn resolve(file: &TenantsFile, base: &Config) -> anyhow::Result<Vec<Config>> {
+    if file.tenants.is_empty() {
+        anyhow::bail!("tenants file has no [[tenant]] entries");
+    }
+    let mut pool = file.port_range.as_deref().map(parse_range).transpose()?.into_iter().flatten();
+
+    let mut ids = HashSet::new();
+    // The base API port serves the `/tenants` directory
+    let mut ports = HashSet::from([base.api_port]);
+    let mut configs = Vec::with_capacity(file.tenants.len());
+    for (i, spec) in file.tenants.iter().enumerate() {
+        if spec.id.is_empty() || !spec.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            anyhow::bail!("tenant id {:?} must be non-empty [A-Za-z0-9_-]", spec.id);
+        }
+        if !ids.insert(spec.id.clone()) {
+            anyhow::bail!("duplicate tenant id {}", spec.id);
+        }
+
+        let mut cfg = base.clone();
+        cfg.tenant_id = spec.id.clone();
+        (cfg.audio_port, cfg.sensor_port, cfg.test_port) = match (spec.audio_port, spec.sensor_port) {
+            (Some(audio), Some(sensor)) => (audio, sensor, spec.test_port.unwrap_or(sensor.wrapping_add(1))),
+            (None, None) => {
+                let mut take = || {
+                    pool.next().ok_or_else(|| anyhow::anyhow!("port_range exhausted at tenant {}", spec.id))
+                };
+                (take()?, take()?, take()?)
+            }
+            _ => anyhow::bail!("tenant {}: set both audio_port and sensor_port, or neither", spec.id),
+        };
+        cfg.api_port = spec.api_port.unwrap_or(base.api_port.saturating_add((i as u16) + 1));
+        for port in [cfg.audio_port, cfg.sensor_port, cfg.test_port, cfg.api_port] {
+            if !ports.insert(port) {
+                anyhow::bail!("tenant {}: port {port} is already used by another tenant", spec.id);
+            }
+        }
+
+        if let Some(ref key) = spec.openai_api_key {
+            cfg.openai_api_key = key.clone();
+        }
+        if let Some(ref file) = spec.openai_instructions_file {
+            cfg.openai_instructions_file = file.clone();
+        }
+        if let Some(persona) = spec.persona {
+            cfg.persona = persona;
+        }
+        cfg.audio_save_dir = spec.audio_save_dir
+            .clone()
+            .unwrap_or_else(|| format!("{}/{}", base.audio_save_dir, spec.id));
+        configs.push(cfg);
+    }
+    Ok(configs)
+}
+
+/// `"9001-9020"` → 9001..=9020.
+fn parse_range(range: &str) -> anyhow::Result<std::ops::RangeInclusive<u16>> {
+    let (lo, hi) = range
+        .split_once('-')
+        .ok_or_else(|| anyhow::anyhow!("port_range must look like 9001-9020, got {range:?}"))?;
+    let (lo, hi): (u16, u16) = (lo.trim().parse()?, hi.trim().parse()?);
+    if lo > hi {
+        anyhow::bail!("port_range {range:?} is empty");
+    }
+    Ok(lo..=hi)
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn base() -> Config {
+        crate::config::Cli::parse_from(["vad-sensor-bridge"]).serve
+    }
+
+    #[test]
+    fn test_ports_from_range_and_overrides() {
+        let file: TenantsFile = toml
+            ::from_str(
+                r#"
+                port_range = "9101-9106"
+                [[tenant]]
+                id = "acme"
+                persona = "cute"
+                openai_api_key = "sk-acme"
+                [[tenant]]
+                id = "globex"
+                [[tenant]]
+                id = "initech"
+                audio_port = 9201
+                sensor_port = 9202
+                api_port = 8099
+                "#
+            )
+            .unwrap();
+        let cfgs = resolve(&file, &base()).unwrap();
+        let ports: Vec<_> = cfgs
+            .iter()
+            .map(|c| (c.tenant_id.as_str(), c.audio_port, c.sensor_port, c.test_port, c.api_port))
+            .collect();
+        assert_eq!(ports, [
+            ("acme", 9101, 9102, 9103, 8081),
+            ("globex", 9104, 9105, 9106, 8082),
+            ("initech", 9201, 9202, 9203, 8099),
+        ]);
+        assert_eq!(cfgs[0].persona, PersonaTrait::Cute);
+        assert_eq!(cfgs[0].openai_api_key, "sk-acme");
+        assert!(cfgs[1].audio_save_dir.ends_with("/globex"));
+    }
+
+    #[test]
+    fn test_rejects_bad_files() {
+        let bad = [
+            "[[tenant]]\nid = \"a\"",                                     // no ports, no range
+            "port_range = \"9101-9103\"\n[[tenant]]\nid = \"a\"\n[[tenant]]\nid = \"a\"", // dup id
+            "[[tenant]]\nid = \"a b\"\naudio_port = 1\nsensor_port = 2",   // bad id
+            "[[tenant]]\nid = \"a\"\naudio_port = 1\nsensor_port = 1",      // port clash
+            "[[tenant]]\nid = \"a\"\naudio_port = 1\nsensor_port = 2\napi_port = 8080", // base API port
+            "port_range = \"9\"",                                           // bad range
+        ];
+        for text in bad {
+            let parsed: Result<TenantsFile, _> = toml::from_str(text);
+            let result = parsed.map_err(anyhow::Error::from).and_then(|f| resolve(&f, &base()));
+            assert!(result.is_err(), "accepted: {text}");
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use serde::{ Serialize, Serializer };
+use std::hash::{ BuildHasher, Hasher };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::sync::OnceLock;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Correlation ids
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Every packet entering the pipeline gets a trace id, and so does
+//  every ESP session (one user utterance).  The session's id is also
+//  carried by its AUDIO_UP packets, so one utterance shares one id from
+//  the receiver to the database:
+//
+//    VAD worker      `packet{trace=…}` span around everything logged
+//                    for the packet (events, VAD results, distress)
+//    VAD responses   logged with the result's id
+//    OpenAI          `openai_event{trace=…}` span around the handling
+//                    of every server event (AUDIO_DOWN sends included)
+//                    for the wired ESP's session
+//    records         session records and JSONL logs, transcripts
+//                    (MQTT / webhooks / PostgreSQL) and `--record-dir`
+//                    rows carry a `trace` field / column
+//
+//  Ids are 64-bit, printed as 16 hex digits.  They count up from a
+//  random start per process, so ids of one process sort in the order
+//  they were handed out and two processes practically never collide.
+
+/// Correlation id of one packet or ESP session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TraceId(u64);
+
+impl TraceId {
+    /// A fresh id.
+    pub fn next() -> Self {
+        static START: OnceLock<u64> = OnceLock::new();
+        static COUNT: AtomicU64 = AtomicU64::new(0);
+        // RandomState is seeded randomly per process
+        let start = *START.get_or_init(|| std::collections::hash_map::RandomState::new().build_hasher().finish());
+        Self(start.wrapping_add(COUNT.fetch_add(1, Ordering::Relaxed)))
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for TraceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl std::str::FromStr for TraceId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(Self)
+    }
+}
+
+impl Serialize for TraceId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_are_unique_and_round_trip() {
+        let a = TraceId::next();
+        let b = TraceId::next();
+        assert_ne!(a, b);
+        let text = a.to_string();
+        assert_eq!(text.len(), 16);
+        assert_eq!(text.parse::<TraceId>().unwrap(), a);
+        assert_eq!(serde_json::to_string(&b).unwrap(), format!("\"{b}\""));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::config::Config;
+use crate::conversation::Role;
+use crate::experiments::Experiments;
+use crate::mqtt_health::{ self, MqttHealth };
+use crate::store::SharedStore;
+use crate::trace::TraceId;
+use rumqttc::v5::mqttbytes::QoS;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{ debug, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Transcript forwarding (MQTT / webhooks)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Every finished transcript from the OpenAI session (what the user
+//  said, and in conversation mode what the robot said) is forwarded as
+//  JSON to:
+//
+//    * MQTT   `<--transcript-mqtt-topic>/[<tenant>/]<device>` (QoS 1),
+//             when `--transcript-mqtt-host` is set
+//    * HTTP   POST to every `--transcript-webhook` URL
+//
+//  Together with `--openai-mode transcribe` this gives the listening /
+//  analytics half of the bridge without any spoken responses.  With
+//  `--postgres-url` every transcript is also stored (`store.rs`).
+//
+//  Delivery runs on its own task behind a bounded queue, so a slow
+//  broker or webhook never stalls the OpenAI reader; when the queue is
+//  full transcripts are dropped with a warning.
+
+/// One forwarded transcript.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Transcript {
+    /// MAC or `ip:port` of the ESP that was wired to the session
+    pub device: String,
+    pub role: Role,
+    pub text: String,
+    /// Speaker label of a user transcript (`--speaker-engine`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+    pub timestamp_ms: u64,
+    /// Tenant of the bridge instance (omitted in single-tenant mode)
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tenant: String,
+    /// Correlation id of the ESP session the transcript belongs to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TraceId>,
+    /// A/B variant of the device, by experiment (`--experiments-file`)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub experiments: BTreeMap<String, String>,
+}
+
+/// Handle the OpenAI reader publishes through.  Clone-friendly; a
+/// disabled sink (no MQTT host, no webhooks, no store) drops everything.
+#[derive(Clone)]
+pub struct TranscriptSink {
+    tx: Option<mpsc::Sender<Transcript>>,
+    tenant: String,
+    store: SharedStore,
+    experiments: Experiments,
+}
+
+impl TranscriptSink {
+    /// A sink that forwards nothing.
+    pub fn disabled() -> Self {
+        Self { tx: None, tenant: String::new(), store: SharedStore::disabled(), experiments: Experiments::disabled() }
+    }
+
+    /// Start the delivery task for the configured MQTT broker / webhooks;
+    /// transcripts are also written to `store`.
+    pub fn from_config(config: &Config, health: &MqttHealth, store: SharedStore) -> anyhow::Result<Self> {
+        let mqtt = if config.transcript_mqtt_host.is_empty() {
+            None
+        } else {
+            let opts = mqtt_health::client_options(
+                "transcripts",
+                &config.tenant_id,
+                &config.transcript_mqtt_host,
+                config.transcript_mqtt_port
+            );
+            let (client, _) = mqtt_health::spawn_client(opts, 64, health, "transcripts", |_, _| {});
+            let props = crate::mqtt5::json("transcript", &config.tenant_id, config.mqtt_expiry_secs);
+            Some((client, config.transcript_mqtt_topic.clone(), props))
+        };
+        let webhooks = config.transcript_webhook.clone();
+        if mqtt.is_none() && webhooks.is_empty() {
+            return Ok(Self { tx: None, tenant: config.tenant_id.clone(), store, experiments: Experiments::disabled() });
+        }
+
+        let http = reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?;
+        info!(
+            mqtt = ?mqtt.as_ref().map(|(_, topic, _)| topic),
+            webhooks = webhooks.len(),
+            "📝 transcript forwarding enabled"
+        );
+        let (tx, mut rx) = mpsc::channel::<Transcript>(256);
+        tokio::spawn(async move {
+            while let Some(t) = rx.recv().await {
+                let body = match serde_json::to_vec(&t) {
+                    Ok(b) => b,
+                    Err(_) => continue,
+                };
+                if let Some((ref client, ref prefix, ref props)) = mqtt {
+                    let topic = topic_for(prefix, &t.tenant, &t.device);
+                    if let Err(e) = client.publish_with_properties(topic, QoS::AtLeastOnce, false, body.clone(), props.clone()).await {
+                        warn!(error = %e, "failed to queue transcript for MQTT");
+                    }
+                }
+                for url in &webhooks {
+                    match
+                        http
+                            .post(url)
+                            .header("Content-Type", "application/json")
+                            .body(body.clone())
+                            .send().await
+                    {
+                        Ok(resp) if resp.status().is_success() => {}
+                        Ok(resp) => warn!(url = %url, status = %resp.status(), "transcript webhook rejected"),
+                        Err(e) => warn!(url = %url, error = %e, "transcript webhook failed"),
+                    }
+                }
+                debug!(device = %t.device, "transcript forwarded");
+            }
+        });
+        Ok(Self { tx: Some(tx), tenant: config.tenant_id.clone(), store, experiments: Experiments::disabled() })
+    }
+
+    /// Tag transcripts with the device's A/B variants and count them
+    /// per variant.
+    pub fn with_experiments(mut self, experiments: Experiments) -> Self {
+        self.experiments = experiments;
+        self
+    }
+
+    /// Queue a transcript for delivery (never blocks), tagged with
+    /// `trace`, the session's correlation id.
+    pub fn publish(&self, device: &str, role: Role, text: &str, speaker: Option<&str>, trace: Option<TraceId>) {
+        self.experiments.record_transcript(device, role);
+        if self.tx.is_none() && !self.store.is_enabled() {
+            return;
+        }
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let t = Transcript {
+            device: device.to_string(),
+            role,
+            text: text.to_string(),
+            speaker: speaker.map(str::to_string),
+            timestamp_ms: std::time::SystemTime
+                ::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            tenant: self.tenant.clone(),
+            trace,
+            experiments: self.experiments.device_variants(device),
+        };
+        self.store.transcript(&t);
+        let Some(ref tx) = self.tx else {
+            return;
+        };
+        if tx.try_send(t).is_err() {
+            warn!(device = %device, "transcript queue full — dropping transcript");
+        }
+    }
+}
+
+/// MQTT topic for `device` (under `tenant` if set): MAC colons and
+/// address separators are not topic-safe everywhere, so `:` / `.`
+/// become `-`.
+pub(crate) fn topic_for(prefix: &str, tenant: &str, device: &str) -> String {
+    let device: String = device
+        .chars()
+        .map(|c| if c == ':' || c == '.' { '-' } else { c })
+        .collect();
+    if tenant.is_empty() {
+        format!("{prefix}/{device}")
+    } else {
+        format!("{prefix}/{tenant}/{device}")
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_for_device() {
+        assert_eq!(topic_for("vad/transcripts", "", "aa:bb:cc:dd:ee:ff"), "vad/transcripts/aa-bb-cc-dd-ee-ff");
+        assert_eq!(topic_for("t", "", "10.0.0.7:5000"), "t/10-0-0-7-5000");
+        assert_eq!(topic_for("t", "acme", "10.0.0.7:5000"), "t/acme/10-0-0-7-5000");
+    }
+
+    #[tokio::test]
+    async fn test_publish_queues_trimmed_text() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let sink = TranscriptSink { tx: Some(tx), tenant: String::new(), store: SharedStore::disabled(), experiments: Experiments::disabled() };
+        let trace = TraceId::next();
+        sink.publish("dev", Role::User, "  hello  ", Some("Maya"), Some(trace));
+        sink.publish("dev", Role::User, "   ", None, None);
+        let t = rx.recv().await.unwrap();
+        assert_eq!((t.device.as_str(), t.role, t.text.as_str()), ("dev", Role::User, "hello"));
+        assert_eq!(t.speaker.as_deref(), Some("Maya"));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(t.trace, Some(trace));
+        assert!(t.experiments.is_empty());
+
+        TranscriptSink::disabled().publish("dev", Role::User, "dropped", None, None);
+    }
+}
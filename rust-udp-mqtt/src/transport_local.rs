@@ -0,0 +1,236 @@
+use crate::config::Config;
+use crate::sensor::{ SensorPacket, HEADER_SIZE };
+use crate::shard::ShardedSender;
+use crate::stats::Stats;
+use crate::tenants::TenantId;
+use std::sync::Arc;
+use tokio::io::{ AsyncRead, AsyncReadExt };
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Local ingest (Unix domain sockets / stdin)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Sidecar processes on the same host can feed sensor packets to the
+//  pipeline without going through loopback UDP:
+//
+//    --uds-dgram PATH   Unix datagram socket, one packet per datagram
+//    --uds-stream PATH  Unix stream socket, any number of connections
+//    --stdin            the bridge's standard input
+//
+//  Streams carry the C bridge's TCP framing:
+//
+//    [ len: u32 LE ][ sensor packet: len bytes ] ...
+//
+//  A length outside HEADER_SIZE..=MAX_FRAME loses the framing, so the
+//  connection is closed (counted as a parse error).  Datagrams are
+//  dropped when the pipeline is full, like UDP; streams wait instead.
+//  These inputs only ingest: VAD results leave through the usual
+//  outputs (event stream, alerts, the sensor's UDP address if it has
+//  one), not back over the socket.  They run next to the UDP or ZeroMQ
+//  transport.
+
+/// Largest framed packet: header plus a full u16 payload.
+pub const MAX_FRAME: usize = HEADER_SIZE + u16::MAX as usize;
+
+/// Queues packets from local producers into the VAD workers.
+#[derive(Clone)]
+struct LocalIngress {
+    tx: ShardedSender,
+    stats: Arc<Stats>,
+    tenant: TenantId,
+}
+
+impl LocalIngress {
+    /// Parse and queue one packet; `wait` applies backpressure instead
+    /// of dropping.  False once the pipeline has stopped.
+    async fn handle(&self, data: &[u8], wait: bool) -> bool {
+        self.stats.record_recv(data.len());
+        let Some(mut packet) = SensorPacket::parse(data) else {
+            self.stats.record_parse_error();
+            return true;
+        };
+        // Tenant is decided by the ingress, never by the packet
+        packet.tenant = self.tenant.clone();
+        if wait {
+            return self.tx.send(packet).await.is_ok();
+        }
+        if self.tx.try_send(packet).is_err() {
+            self.stats.record_channel_drop();
+        }
+        true
+    }
+}
+
+/// Start the configured local inputs (no-op when none is set).
+pub fn spawn(config: &Config, tx: ShardedSender, stats: Arc<Stats>) -> anyhow::Result<()> {
+    let ingress = LocalIngress { tx, stats, tenant: TenantId::from(config.tenant_id.as_str()) };
+    if !config.uds_dgram.is_empty() || !config.uds_stream.is_empty() {
+        #[cfg(unix)]
+        unix::spawn(config, &ingress)?;
+        #[cfg(not(unix))]
+        anyhow::bail!("--uds-dgram / --uds-stream need a Unix platform");
+    }
+    if config.stdin {
+        info!(tenant = %config.tenant_id, "📥 reading length-prefixed sensor packets from stdin");
+        tokio::spawn(async move {
+            match read_frames(tokio::io::stdin(), &ingress).await {
+                Ok(()) => info!("stdin closed — no more local packets"),
+                Err(e) => warn!(error = %e, "stdin ingest stopped"),
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Read `[len][packet]` frames until EOF.
+async fn read_frames<R: AsyncRead + Unpin>(mut reader: R, ingress: &LocalIngress) -> std::io::Result<()> {
+    let mut len_buf = [0u8; 4];
+    let mut buf = vec![0u8; MAX_FRAME];
+    loop {
+        match reader.read_exact(&mut len_buf).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        let len = u32::from_le_bytes(len_buf) as usize;
+        if !(HEADER_SIZE..=MAX_FRAME).contains(&len) {
+            ingress.stats.record_parse_error();
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("frame length {len}")));
+        }
+        reader.read_exact(&mut buf[..len]).await?;
+        if !ingress.handle(&buf[..len], true).await {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::{ read_frames, LocalIngress, MAX_FRAME };
+    use crate::config::Config;
+    use std::os::unix::fs::FileTypeExt;
+    use tokio::net::{ UnixDatagram, UnixListener };
+    use tracing::{ debug, info, warn };
+
+    pub fn spawn(config: &Config, ingress: &LocalIngress) -> anyhow::Result<()> {
+        if !config.uds_dgram.is_empty() {
+            remove_stale(&config.uds_dgram);
+            let socket = UnixDatagram::bind(&config.uds_dgram)?;
+            info!(path = %config.uds_dgram, tenant = %config.tenant_id, "📥 Unix datagram ingest bound");
+            tokio::spawn(dgram_loop(socket, ingress.clone()));
+        }
+        if !config.uds_stream.is_empty() {
+            remove_stale(&config.uds_stream);
+            let listener = UnixListener::bind(&config.uds_stream)?;
+            info!(path = %config.uds_stream, tenant = %config.tenant_id, "📥 Unix stream ingest bound");
+            tokio::spawn(accept_loop(listener, ingress.clone()));
+        }
+        Ok(())
+    }
+
+    /// A socket file left by a previous run would make `bind` fail;
+    /// anything that is not a socket is left alone.
+    fn remove_stale(path: &str) {
+        if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    async fn dgram_loop(socket: UnixDatagram, ingress: LocalIngress) {
+        let mut buf = vec![0u8; MAX_FRAME];
+        loop {
+            match socket.recv(&mut buf).await {
+                Ok(len) => {
+                    ingress.handle(&buf[..len], false).await;
+                }
+                Err(e) => {
+                    warn!(error = %e, "Unix datagram recv error");
+                    ingress.stats.record_recv_error();
+                }
+            }
+        }
+    }
+
+    async fn accept_loop(listener: UnixListener, ingress: LocalIngress) {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!(error = %e, "Unix stream accept error");
+                    continue;
+                }
+            };
+            let ingress = ingress.clone();
+            tokio::spawn(async move {
+                debug!("local producer connected");
+                if let Err(e) = read_frames(stream, &ingress).await {
+                    warn!(error = %e, "local producer dropped");
+                }
+            });
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::Cli;
+    use crate::sensor::{ SensorVector, DATA_TYPE_SENSOR_VECTOR };
+    use crate::shard::{ self, Lane, LaneConfig };
+    use clap::Parser;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{ UnixDatagram, UnixStream };
+
+    fn packet(sensor_id: u32) -> Vec<u8> {
+        SensorPacket {
+            data_type: DATA_TYPE_SENSOR_VECTOR,
+            sensor_id,
+            payload: SensorVector::default().to_payload(),
+            ..SensorPacket::parse(&[0u8; HEADER_SIZE]).unwrap()
+        }.to_binary()
+    }
+
+    #[tokio::test]
+    async fn test_datagrams_and_framed_streams_reach_the_workers() {
+        let dir = std::env::temp_dir().join(format!("vad-local-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dgram = dir.join("dgram.sock").display().to_string();
+        let stream = dir.join("stream.sock").display().to_string();
+        let config = Cli::parse_from(["vad-sensor-bridge", "--uds-dgram", &dgram, "--uds-stream", &stream]).serve;
+
+        let lane = LaneConfig { workers: 1, capacity: 8 };
+        let (tx, mut rxs) = shard::channels(lane, lane, crate::clock::system());
+        let stats = Stats::new();
+        spawn(&config, tx, stats.clone()).unwrap();
+        let (_, mut sensor_rx) = rxs.pop().unwrap();
+        assert_eq!(rxs[0].0, Lane::Audio);
+
+        let client = UnixDatagram::unbound().unwrap();
+        client.send_to(&packet(1), &dgram).await.unwrap();
+        assert_eq!(sensor_rx.recv().await.unwrap().sensor_id, 1);
+
+        // Two frames written in one go, then one with a broken length
+        let mut conn = UnixStream::connect(&stream).await.unwrap();
+        let mut bytes = Vec::new();
+        for id in [2, 3] {
+            let pkt = packet(id);
+            bytes.extend((pkt.len() as u32).to_le_bytes());
+            bytes.extend(pkt);
+        }
+        bytes.extend(3u32.to_le_bytes());
+        conn.write_all(&bytes).await.unwrap();
+        assert_eq!(sensor_rx.recv().await.unwrap().sensor_id, 2);
+        assert_eq!(sensor_rx.recv().await.unwrap().sensor_id, 3);
+
+        // The broken frame closes the connection
+        let mut rest = Vec::new();
+        let _ = conn.read_to_end(&mut rest).await;
+        assert_eq!(stats.totals().parse_errors, 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
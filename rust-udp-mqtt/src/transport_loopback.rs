@@ -0,0 +1,181 @@
+use crate::clock::{ self, SharedClock };
+use crate::error::{ BridgeError, Result };
+use crate::pcm::SampleFormat;
+use crate::sensor::{ SensorPacket, SensorVector, DATA_TYPE_SENSOR_VECTOR };
+use crate::shard::ShardedSender;
+use crate::stats::Stats;
+use crate::tenants::TenantId;
+use crate::vad::{ VadKind, VadResult };
+use crate::vad_response::VadResponsePacket;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+// ─────────────────────────────────────────────────────────────────────
+//  In-process loopback transport
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Runs the real pipeline (VAD workers, smoother, events, persona,
+//  device thresholds) with an in-memory handle in place of the UDP
+//  sockets, for crates embedding the bridge and for integration tests:
+//
+//    let (mut handle, transport) = transport_loopback::loopback(64);
+//    tokio::spawn(bridge::serve_instance(config, None, Transport::Loopback(transport)));
+//    handle.send_vector(1, 0, vector).await?;
+//    let response = handle.recv().await;
+//
+//  Packets are stamped with the instance's tenant, like the UDP
+//  receivers do.  As on UDP, only sensor-vector (emotional) results are
+//  answered; responses are never coalesced.  Responses of one sensor
+//  come back in send order (with `--proc-threads 1`, of all sensors).  The REST API and the ESP audio /
+//  OpenAI side are not started.  Dropping the handle stops the instance.
+
+/// Embedder side: inject packets, receive VAD responses.
+pub struct LoopbackHandle {
+    packets: mpsc::Sender<SensorPacket>,
+    responses: mpsc::UnboundedReceiver<VadResponsePacket>,
+}
+
+/// Bridge side, passed as `bridge::Transport::Loopback`.
+pub struct LoopbackTransport {
+    packets: mpsc::Receiver<SensorPacket>,
+    responses: mpsc::UnboundedSender<VadResponsePacket>,
+    clock: SharedClock,
+}
+
+impl LoopbackTransport {
+    /// Clock the bridge instance runs on.
+    pub fn clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+}
+
+/// A connected handle / transport pair on the wall clock; at most
+/// `capacity` injected packets wait for the pipeline before `send`
+/// blocks.
+pub fn loopback(capacity: usize) -> (LoopbackHandle, LoopbackTransport) {
+    loopback_with_clock(capacity, clock::system())
+}
+
+/// Like [`loopback`], with the whole instance (smoother, events, audio
+/// windows, stats) running on `clock` — pass a `SimClock` for
+/// deterministic, faster-than-real-time runs.
+pub fn loopback_with_clock(capacity: usize, clock: SharedClock) -> (LoopbackHandle, LoopbackTransport) {
+    let (packets_tx, packets_rx) = mpsc::channel(capacity.max(1));
+    let (responses_tx, responses_rx) = mpsc::unbounded_channel();
+    (
+        LoopbackHandle { packets: packets_tx, responses: responses_rx },
+        LoopbackTransport { packets: packets_rx, responses: responses_tx, clock },
+    )
+}
+
+impl LoopbackHandle {
+    /// Inject one packet, as if it had arrived on the sensor port.
+    /// Payloads the wire format cannot carry (over 65535 bytes) are
+    /// refused.
+    pub async fn send(&self, packet: SensorPacket) -> Result<()> {
+        if packet.payload.len() > u16::MAX as usize {
+            return Err(BridgeError::protocol(format!("payload of {} bytes exceeds the wire format", packet.payload.len())));
+        }
+        self.packets
+            .send(packet)
+            .await
+            .map_err(|_| BridgeError::transport("loopback bridge stopped"))
+    }
+
+    /// Inject a sensor vector for `sensor_id`.
+    pub async fn send_vector(&self, sensor_id: u32, seq: u64, vector: SensorVector) -> Result<()> {
+        self.send(SensorPacket {
+            sensor_id,
+            timestamp_us: 0,
+            data_type: DATA_TYPE_SENSOR_VECTOR,
+            sample_format: SampleFormat::S16,
+            seq,
+            payload: vector.to_payload(),
+            tenant: TenantId::default(),
+            ingested_at: None,
+            trace: None,
+        }).await
+    }
+
+    /// Next VAD response (None once the bridge has stopped).
+    pub async fn recv(&mut self) -> Option<VadResponsePacket> {
+        self.responses.recv().await
+    }
+
+    /// Next VAD response if one is ready.
+    pub fn try_recv(&mut self) -> Option<VadResponsePacket> {
+        self.responses.try_recv().ok()
+    }
+}
+
+/// Pump injected packets into the VAD workers and their results back to
+/// the handle until the handle is dropped.
+pub async fn run(
+    transport: LoopbackTransport,
+    tx: ShardedSender,
+    mut vad_rx: mpsc::Receiver<VadResult>,
+    stats: Arc<Stats>,
+    tenant: TenantId
+) -> Result<()> {
+    let LoopbackTransport { mut packets, responses, .. } = transport;
+    loop {
+        tokio::select! {
+            packet = packets.recv() => {
+                let Some(mut packet) = packet else {
+                    break;
+                };
+                packet.tenant = tenant.clone();
+                stats.record_recv(packet.payload.len());
+                // Backpressure instead of dropping: loopback runs are
+                // expected to be lossless
+                if tx.send(packet).await.is_err() {
+                    break;
+                }
+            }
+            Some(result) = vad_rx.recv() => {
+                if result.kind == VadKind::Audio {
+                    continue;
+                }
+                if responses.send(VadResponsePacket::from_vad_result(&result)).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    debug!(tenant = %tenant, "loopback transport stopped");
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::{ serve_instance, Transport };
+    use crate::config::Cli;
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn test_loopback_runs_real_pipeline() {
+        let config = Cli::parse_from(["vad-sensor-bridge", "--proc-threads", "1"]).serve;
+        let (mut handle, transport) = loopback(16);
+        let bridge = tokio::spawn(serve_instance(config, None, Transport::Loopback(transport)));
+
+        let calm = SensorVector { known_face: 0.9, idle_time: 0.8, ..SensorVector::default() };
+        let alarmed = SensorVector { fall_event: 1.0, lifted: 1.0, motion_energy: 1.0, ..calm };
+        handle.send_vector(7, 0, calm).await.unwrap();
+        handle.send_vector(7, 1, alarmed).await.unwrap();
+
+        let first = handle.recv().await.unwrap();
+        let second = handle.recv().await.unwrap();
+        assert_eq!((first.sensor_id, first.seq), (7, 0));
+        assert_eq!((second.sensor_id, second.seq), (7, 1));
+        assert!(second.arousal > first.arousal);
+
+        drop(handle);
+        bridge.await.unwrap().unwrap();
+    }
+}
@@ -1,23 +1,23 @@
-/// OpenAI Realtime WebSocket bridge.
-///
-/// Connects to the OpenAI Realtime API via WebSocket and provides a
-/// bidirectional audio channel:
-///
-/// ```text
-///  ESP (16 kHz PCM)           Rust Server            OpenAI (24 kHz PCM)
-///  ───────────────── ──UDP──▶ ┌──────────────┐ ──WS──▶ ┌───────────┐
-///    AUDIO_UP chunks          │  resample    │         │  Realtime  │
-///                             │  16→24 kHz   │         │   API      │
-///                             │  + base64    │         │           │
-///  ◀──UDP── ──────── ◀─────── │  resample    │ ◀──WS── │           │
-///    AUDIO_DOWN chunks        │  24→16 kHz   │         └───────────┘
-///                             └──────────────┘
-/// ```
-///
-/// Audio format notes:
-///   - ESP protocol: 16-bit LE PCM, 16 kHz, mono
-///   - OpenAI Realtime: 16-bit LE PCM, 24 kHz, mono
-///   - We resample using linear interpolation (good enough for voice)
+//! OpenAI Realtime WebSocket bridge.
+//!
+//! Connects to the OpenAI Realtime API via WebSocket and provides a
+//! bidirectional audio channel:
+//!
+//! ```text
+//!  ESP (16 kHz PCM)           Rust Server            OpenAI (24 kHz PCM)
+//!  ───────────────── ──UDP──▶ ┌──────────────┐ ──WS──▶ ┌───────────┐
+//!    AUDIO_UP chunks          │  resample    │         │  Realtime  │
+//!                             │  16→24 kHz   │         │   API      │
+//!                             │  + base64    │         │           │
+//!  ◀──UDP── ──────── ◀─────── │  resample    │ ◀──WS── │           │
+//!    AUDIO_DOWN chunks        │  24→16 kHz   │         └───────────┘
+//!                             └──────────────┘
+//! ```
+//!
+//! Audio format notes:
+//!   - ESP protocol: 16-bit LE PCM, 16 kHz, mono
+//!   - OpenAI Realtime: 16-bit LE PCM, 24 kHz, mono
+//!   - We resample using linear interpolation (good enough for voice)
 
 use base64::Engine as _;
 use base64::engine::general_purpose::STANDARD as BASE64;
@@ -25,13 +25,25 @@ use futures_util::{ SinkExt, StreamExt };
 use serde_json::{ json, Value };
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::net::UdpSocket;
+use std::sync::atomic::{ AtomicBool, AtomicU64, Ordering };
+use std::time::Duration;
 use tokio::sync::{ mpsc, RwLock };
 use tokio_tungstenite::tungstenite;
-use tracing::{ debug, error, info, warn };
+use tracing::{ debug, error, info, warn, Instrument };
 
+use crate::capture::CapturedSocket;
 use crate::config::Config;
+use crate::conversation::{ ConversationStore, Role, Turn };
+use crate::downlink_pacing::DownlinkPacer;
+use crate::error::{ BridgeError, ResultExt };
 use crate::esp_audio_protocol::*;
+use crate::quiet_hours::QuietHours;
+use crate::recording_policy::RecordingPolicies;
+use crate::safety::SafetyPolicy;
+use crate::session_log::SessionLogs;
+use crate::silence_trim::SilenceTrimmer;
+use crate::speakers::Diarizer;
+use crate::transcripts::TranscriptSink;
 
 // ═══════════════════════════════════════════════════════════════════════
 //  Public types
@@ -39,6 +51,10 @@ use crate::esp_audio_protocol::*;
 
 /// Handle to a running OpenAI Realtime session.
 ///
+/// The WebSocket is supervised: if it drops, a new one is opened (with
+/// exponential backoff), the current instructions are re-sent and the
+/// active device's conversation history is re-injected.
+///
 /// Drop or call `close()` to shut down the WebSocket connection.
 pub struct OpenAiSession {
     /// Send raw 16 kHz PCM chunks here; they'll be resampled + forwarded.
@@ -47,17 +63,176 @@ pub struct OpenAiSession {
     pub control_tx: mpsc::Sender<tungstenite::Message>,
     /// The currently-active ESP client address (reader sends AUDIO_DOWN here).
     pub active_esp: Arc<RwLock<Option<SocketAddr>>>,
-    /// Join handle for the reader (response.audio.delta → ESP).
-    reader_handle: tokio::task::JoinHandle<()>,
-    /// Join handle for the writer (audio_tx → input_audio_buffer.append).
-    writer_handle: tokio::task::JoinHandle<()>,
+    /// Active addresses whose replies are tapped instead of sent as
+    /// AUDIO_DOWN (SIP calls).
+    pub reply_taps: ReplyTaps,
+    /// Latest session instructions, safety banner included (re-sent
+    /// after a reconnect).
+    instructions: Arc<RwLock<String>>,
+    /// Mandatory banner prepended to every instructions update.
+    safety: Arc<SafetyPolicy>,
+    /// Ids of the items in the live OpenAI conversation, oldest first.
+    items: ItemList,
+    /// Per-device history (switched when a different device is wired).
+    conversations: ConversationStore,
+    /// Connection liveness (also read by `/readyz`).
+    health: OpenAiHealth,
+    /// In-flight response tracking (for CTRL_CANCEL).
+    response: Arc<ResponseGate>,
+    /// `--openai-mode` (transcribe = never ask for a response).
+    mode: OpenAiMode,
+    /// `(threshold, pad)` for head/tail silence trimming, if enabled.
+    trim: Option<(f64, Duration)>,
+    /// Supervisor task: (re)connects and runs the reader / writer.
+    task: tokio::task::JoinHandle<()>,
+}
+
+/// Address → channel that gets its spoken replies (16 kHz s16le chunks)
+/// instead of AUDIO_DOWN packets, for devices that do not speak the ESP
+/// protocol.
+pub type ReplyTaps = Arc<dashmap::DashMap<SocketAddr, mpsc::UnboundedSender<Vec<u8>>>>;
+
+/// Conversation item ids, shared between the reader and control paths.
+type ItemList = Arc<std::sync::Mutex<Vec<String>>>;
+
+/// Tracks whether a response is being generated, and whether the ESP
+/// cancelled it.  Audio deltas of a cancelled response that are already
+/// in flight are dropped until the next `response.created`.
+#[derive(Default)]
+struct ResponseGate {
+    in_flight: AtomicBool,
+    cancelled: AtomicBool,
+}
+
+impl ResponseGate {
+    /// `response.created`: a new response starts, un-muted.
+    fn started(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+        self.in_flight.store(true, Ordering::Relaxed);
+    }
+
+    /// `response.done` or a dropped connection.
+    fn finished(&self) {
+        self.in_flight.store(false, Ordering::Relaxed);
+    }
+
+    /// Mute the current response.  Returns `true` if one was in flight
+    /// (i.e. `response.cancel` is worth sending).
+    fn cancel(&self) -> bool {
+        let was = self.in_flight.swap(false, Ordering::Relaxed);
+        if was {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+        was
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Liveness of the Realtime WebSocket, shared with `/readyz`.
+///
+/// Every received frame (events, Pong replies to our keepalive Pings)
+/// refreshes `last_event`; a connection silent for longer than
+/// `stale_after` is torn down by the watchdog and reconnected.
+/// Clone-friendly (Arc inside).
+#[derive(Clone)]
+pub struct OpenAiHealth {
+    inner: Arc<HealthInner>,
+}
+
+struct HealthInner {
+    enabled: bool,
+    ping_every: Duration,
+    stale_after: Duration,
+    connected: AtomicBool,
+    /// Unix ms of the last received frame (0 = never)
+    last_event_ms: AtomicU64,
+    reconnects: AtomicU64,
+}
+
+/// `/readyz` view of [`OpenAiHealth`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct HealthSnapshot {
+    pub enabled: bool,
+    pub connected: bool,
+    /// Milliseconds since the last frame from OpenAI (None = never)
+    pub last_event_age_ms: Option<u64>,
+    pub reconnects: u64,
+    pub ready: bool,
+}
+
+impl OpenAiHealth {
+    /// `enabled` = `--openai-realtime`; zero durations disable the
+    /// keepalive / watchdog.
+    pub fn new(enabled: bool, ping_every: Duration, stale_after: Duration) -> Self {
+        Self {
+            inner: Arc::new(HealthInner {
+                enabled,
+                ping_every,
+                stale_after,
+                connected: AtomicBool::new(false),
+                last_event_ms: AtomicU64::new(0),
+                reconnects: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.inner.connected.load(Ordering::Relaxed)
+    }
+
+    fn set_connected(&self, connected: bool) {
+        self.inner.connected.store(connected, Ordering::Relaxed);
+    }
+
+    fn record_reconnect(&self) {
+        self.inner.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that a frame was just received.
+    fn touch(&self) {
+        self.inner.last_event_ms.store(unix_ms(), Ordering::Relaxed);
+    }
+
+    fn last_event_age(&self) -> Option<Duration> {
+        match self.inner.last_event_ms.load(Ordering::Relaxed) {
+            0 => None,
+            at => Some(Duration::from_millis(unix_ms().saturating_sub(at))),
+        }
+    }
+
+    /// Connected and (if the watchdog is on) heard from recently.
+    fn is_stale(&self) -> bool {
+        !self.inner.stale_after.is_zero() &&
+            self.last_event_age().is_none_or(|age| age > self.inner.stale_after)
+    }
+
+    pub fn snapshot(&self) -> HealthSnapshot {
+        let connected = self.is_connected();
+        HealthSnapshot {
+            enabled: self.inner.enabled,
+            connected,
+            last_event_age_ms: self.last_event_age().map(|a| a.as_millis() as u64),
+            reconnects: self.inner.reconnects.load(Ordering::Relaxed),
+            ready: !self.inner.enabled || (connected && !self.is_stale()),
+        }
+    }
+}
+
+fn unix_ms() -> u64 {
+    std::time::SystemTime
+        ::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
 }
 
 impl OpenAiSession {
     /// Gracefully shut down the session.
     pub fn close(&self) {
-        self.reader_handle.abort();
-        self.writer_handle.abort();
+        self.task.abort();
     }
 
     /// Clear the OpenAI input audio buffer (discard un-committed audio).
@@ -87,8 +262,25 @@ impl OpenAiSession {
         info!("🗣️ response.create sent to OpenAI");
     }
 
-    /// Update the session instructions (prompt) on the fly.
+    /// Stop the response currently being spoken: send `response.cancel`
+    /// and drop any of its audio deltas still arriving.  No-op when no
+    /// response is in flight.
+    pub async fn cancel_response(&self) {
+        if !self.response.cancel() {
+            debug!("no OpenAI response in flight — nothing to cancel");
+            return;
+        }
+        let event = json!({"type": "response.cancel"}).to_string();
+        let _ = self.control_tx.send(tungstenite::Message::Text(event)).await;
+        info!("🛑 response.cancel sent to OpenAI");
+    }
+
+    /// Update the session instructions (prompt) on the fly.  The safety
+    /// banner is always prepended, whatever the caller passes.
     pub async fn update_instructions(&self, instructions: &str) {
+        let instructions = &self.safety.enforce(instructions);
+        self.safety.audit("update", instructions);
+        *self.instructions.write().await = instructions.to_string();
         let event =
             json!({
             "type": "session.update",
@@ -100,6 +292,39 @@ impl OpenAiSession {
         info!(len = instructions.len(), "🧭 session.update sent (instructions)");
     }
 
+    /// Record `device` as the one talking.  If it differs from the
+    /// previous device, the live conversation is replaced with its own
+    /// history so devices never hear each other's conversations.
+    pub async fn activate_device(&self, device: &str) {
+        let switched = self.conversations.activate(device);
+        if !self.conversations.enabled() || !switched {
+            return;
+        }
+        delete_items(&self.items, &self.control_tx).await;
+        let history = self.conversations.history(device);
+        for turn in &history {
+            let _ = self.control_tx.send(tungstenite::Message::Text(history_item(turn))).await;
+        }
+        info!(device = %device, turns = history.len(), "💬 conversation switched to device history");
+    }
+
+    /// A fresh trimmer for one ESP session (None = forward everything).
+    pub fn silence_trimmer(&self) -> Option<SilenceTrimmer> {
+        self.trim.map(|(threshold, pad)| SilenceTrimmer::new(threshold, pad))
+    }
+
+    /// Whether ESP audio should get a spoken response (false in
+    /// `--openai-mode transcribe`).
+    pub fn responds(&self) -> bool {
+        self.mode == OpenAiMode::Conversation
+    }
+
+    /// Whether the WebSocket is currently connected (false while the
+    /// supervisor is reconnecting).
+    pub fn is_connected(&self) -> bool {
+        self.health.is_connected()
+    }
+
     /// Set the active ESP client that receives audio responses.
     pub async fn set_active_esp(&self, addr: SocketAddr) {
         *self.active_esp.write().await = Some(addr);
@@ -117,39 +342,183 @@ impl OpenAiSession {
 //  Session spawner
 // ═══════════════════════════════════════════════════════════════════════
 
+/// First delay before reconnecting a dropped WebSocket.
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+/// Upper bound for the reconnect backoff.
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+type WsStream = tokio_tungstenite::WebSocketStream<
+    tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>
+>;
+
+/// What the session does with ESP audio (`--openai-mode`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OpenAiMode {
+    /// Spoken replies streamed back as AUDIO_DOWN (default)
+    Conversation,
+    /// Transcripts only: no responses are generated
+    Transcribe,
+}
+
+/// Everything needed to (re)open the WebSocket.
+struct Connection {
+    api_key: String,
+    model: String,
+    voice: String,
+    mode: OpenAiMode,
+    transcription_model: String,
+}
+
 /// Open a new OpenAI Realtime WebSocket session and return a handle.
 ///
-/// * `config`       — server configuration (API key, model, voice, etc.)
-/// * `esp_addr`     — the ESP client's UDP address (for sending audio back)
-/// * `audio_socket` — shared audio UDP socket (for sending AUDIO_DOWN)
+/// * `config`        — server configuration (API key, model, voice, etc.)
+/// * `instructions`  — rendered session instructions (see `prompt.rs`)
+/// * `esp_addr`      — the ESP client's UDP address (for sending audio back)
+/// * `audio_socket`  — shared audio UDP socket (for sending AUDIO_DOWN)
+/// * `conversations` — per-device history (recorded + re-injected)
+/// * `transcripts`   — where finished transcripts are forwarded
+/// * `safety`        — banner prepended to every set of instructions
+/// * `session_logs`  — per-session JSONL logs (OpenAI events → wired ESP)
+/// * `recording`     — recording policies (transcripts kept or withheld)
+/// * `downlink`      — per-device AUDIO_DOWN pacing (`--downlink-pacing`)
+/// * `speakers`      — speaker labels for user transcripts
+/// * `quiet`         — quiet hours (replies dropped when `mute_audio`)
 ///
 /// The returned [`OpenAiSession`] has an `audio_tx` sender: push 16 kHz
 /// PCM chunks into it and they'll be streamed to OpenAI in real time.
 /// Response audio is automatically resampled to 16 kHz and sent back to
 /// the ESP as `PKT_AUDIO_DOWN` packets.
+///
+/// The first connection is made before returning so startup failures
+/// reach the caller; later drops are reconnected in the background.
+#[allow(clippy::too_many_arguments)]
 pub async fn spawn_openai_session(
     config: &Config,
+    instructions: &str,
     active_esp: Arc<RwLock<Option<SocketAddr>>>,
-    audio_socket: Arc<UdpSocket>,
+    audio_socket: Arc<CapturedSocket>,
     save_debug_audio: bool,
-    audio_save_dir: &str
-) -> anyhow::Result<OpenAiSession> {
-    let api_key = config.openai_api_key.clone();
-    let model = config.openai_model.clone();
-    let voice = config.openai_voice.clone();
-    let instructions = config.openai_instructions.clone();
+    audio_save_dir: &str,
+    conversations: ConversationStore,
+    health: OpenAiHealth,
+    transcripts: TranscriptSink,
+    safety: Arc<SafetyPolicy>,
+    session_logs: SessionLogs,
+    recording: RecordingPolicies,
+    downlink: DownlinkPacer,
+    speakers: Diarizer,
+    quiet: QuietHours
+) -> crate::error::Result<OpenAiSession> {
+    let conn = Connection {
+        api_key: config.openai_api_key.clone(),
+        model: config.openai_model.clone(),
+        voice: config.openai_voice.clone(),
+        mode: config.openai_mode,
+        transcription_model: config.openai_transcription_model.clone(),
+    };
+
+    if conn.api_key.is_empty() {
+        return Err(BridgeError::openai("API key not set (use --openai-api-key or OPENAI_API_KEY env var)"));
+    }
 
-    if api_key.is_empty() {
-        anyhow::bail!("OpenAI API key not set (use --openai-api-key or OPENAI_API_KEY env var)");
+    let ws_stream = connect(&conn).await.openai("Realtime WebSocket connect")?;
+
+    // ── Internal channels ──────────────────────────────────────────────
+    //
+    //  audio_tx / audio_rx — transport_udp pushes 16 kHz PCM chunks
+    //  ws_msg_tx / ws_msg_rx — reader task injects control msgs (Pong)
+    //                          into the sink owned by the writer task
+    let (audio_tx, audio_rx) = mpsc::channel::<Vec<u8>>(512);
+    let (ws_msg_tx, ws_msg_rx) = mpsc::channel::<tungstenite::Message>(64);
+    let control_tx = ws_msg_tx.clone();
+    let instructions = safety.enforce(instructions);
+    safety.audit("startup", &instructions);
+    let instructions = Arc::new(RwLock::new(instructions));
+    let items: ItemList = Arc::default();
+    let response: Arc<ResponseGate> = Arc::default();
+    let reply_taps: ReplyTaps = Arc::default();
+
+    // ── History resets from the REST API ───────────────────────────────
+    if conversations.enabled() {
+        let mut resets = conversations.subscribe_resets();
+        let conversations = conversations.clone();
+        let items = items.clone();
+        let control_tx = control_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                match resets.recv().await {
+                    Ok(device) => {
+                        if conversations.is_active(&device) {
+                            delete_items(&items, &control_tx).await;
+                            info!(device = %device, "🧽 live OpenAI conversation reset");
+                        }
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
+                        continue;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                        break;
+                    }
+                }
+            }
+        });
     }
 
-    // ── Connect WebSocket ──────────────────────────────────────────────
-    let ws_url = format!("wss://api.openai.com/v1/realtime?model={}", model);
+    let reader = ReaderCtx {
+        ws_msg_tx,
+        active_esp: active_esp.clone(),
+        reply_taps: reply_taps.clone(),
+        audio_socket,
+        save_debug_audio,
+        debug_save_dir: format!("{}/debug", audio_save_dir),
+        conversations: conversations.clone(),
+        items: items.clone(),
+        health: health.clone(),
+        response: response.clone(),
+        transcripts,
+        session_logs,
+        recording,
+        downlink,
+        speakers,
+        quiet,
+    };
+    let supervisor = Supervisor {
+        conn,
+        instructions: instructions.clone(),
+        conversations: conversations.clone(),
+        items: items.clone(),
+        health: health.clone(),
+        reader,
+    };
+    let task = tokio::spawn(supervisor.run(ws_stream, audio_rx, ws_msg_rx));
+
+    Ok(OpenAiSession {
+        audio_tx,
+        control_tx,
+        active_esp,
+        reply_taps,
+        instructions,
+        safety,
+        items,
+        conversations,
+        health,
+        response,
+        mode: config.openai_mode,
+        trim: config.openai_trim_silence.then(|| {
+            (config.audio_threshold, Duration::from_millis(config.openai_trim_pad_ms))
+        }),
+        task,
+    })
+}
+
+/// Connect the Realtime WebSocket.
+async fn connect(conn: &Connection) -> anyhow::Result<WsStream> {
+    let ws_url = format!("wss://api.openai.com/v1/realtime?model={}", conn.model);
 
     let request = tungstenite::http::Request
         ::builder()
         .uri(&ws_url)
-        .header("Authorization", format!("Bearer {}", api_key))
+        .header("Authorization", format!("Bearer {}", conn.api_key))
         .header("OpenAI-Beta", "realtime=v1")
         .header("Host", "api.openai.com")
         .header("Connection", "Upgrade")
@@ -163,371 +532,640 @@ pub async fn spawn_openai_session(
         .map_err(|e| { anyhow::anyhow!("Failed to connect to OpenAI Realtime API: {}", e) })?;
 
     info!(
-        model = %model, voice = %voice,
+        model = %conn.model, voice = %conn.voice,
         status = %response.status(),
         "OpenAI Realtime WebSocket connected (persistent session)"
     );
+    Ok(ws_stream)
+}
 
-    // Split into independent read / write halves
-    let (mut ws_sink, mut ws_reader) = ws_stream.split();
-
-    // ── Send session.update ────────────────────────────────────────────
-    let session_update =
-        json!({
+/// Full `session.update` sent on every (re)connect.
+/// Initial `session.update`.  In transcribe mode server_vad still
+/// segments turns (so each gets a transcript) but never responds.
+fn session_update(conn: &Connection, instructions: &str) -> String {
+    let transcribe = conn.mode == OpenAiMode::Transcribe;
+    json!({
         "type": "session.update",
         "session": {
             "instructions": instructions,
-            "modalities": ["audio", "text"],
-            "voice": voice,
+            "modalities": if transcribe { json!(["text"]) } else { json!(["audio", "text"]) },
+            "voice": conn.voice,
             "input_audio_format": "pcm16",
             "output_audio_format": "pcm16",
             "input_audio_transcription": {
-                "model": "whisper-1"
+                "model": conn.transcription_model
             },
             "turn_detection": {
                 "type": "server_vad",
                 "threshold": 0.5,
                 "prefix_padding_ms": 300,
-                "silence_duration_ms": 500
+                "silence_duration_ms": 500,
+                "create_response": !transcribe
             }
         }
-    });
+    }).to_string()
+}
 
-    let session_update_str = session_update.to_string();
-    info!(payload = %session_update_str, "session.update payload");
+/// Record a finished transcript in the wired device's history, forward
+/// it and add it to that ESP's session log.  User turns are tagged with
+/// the speaker diarization heard in the session, if any.  Devices whose
+/// recording policy keeps no transcripts only get a text-less log line.
+fn publish_transcript(ctx: &ReaderCtx, esp: Option<SocketAddr>, role: Role, text: &str) {
+    let device = ctx.conversations.active_device();
+    let speaker = match (role, &device) {
+        (Role::User, Some(device)) => ctx.speakers.take_pending(device),
+        _ => None,
+    };
+    if device.as_deref().is_some_and(|d| !ctx.recording.policy(d).keeps_transcripts()) {
+        if let Some(esp) = esp {
+            ctx.session_logs.event(esp, "transcript", json!({ "role": role, "withheld": true }));
+        }
+        return;
+    }
+    ctx.conversations.record(role, text, speaker.as_deref());
+    if let Some(device) = device {
+        let trace = esp.and_then(|esp| ctx.session_logs.trace(esp));
+        ctx.transcripts.publish(&device, role, text, speaker.as_deref(), trace);
+    }
+    if let Some(esp) = esp {
+        let mut event = json!({ "role": role, "text": text });
+        if let Some(speaker) = speaker {
+            event["speaker"] = json!(speaker);
+        }
+        ctx.session_logs.event(esp, "transcript", event);
+    }
+}
 
-    ws_sink
-        .send(tungstenite::Message::Text(session_update_str)).await
-        .map_err(|e| anyhow::anyhow!("Failed to send session.update: {}", e))?;
+/// `conversation.item.create` replaying one remembered turn.
+fn history_item(turn: &Turn) -> String {
+    let (role, content_type) = match turn.role {
+        Role::User => ("user", "input_text"),
+        Role::Assistant => ("assistant", "text"),
+    };
+    json!({
+        "type": "conversation.item.create",
+        "item": {
+            "type": "message",
+            "role": role,
+            "content": [{ "type": content_type, "text": turn.text }]
+        }
+    }).to_string()
+}
 
-    info!(voice = %voice, "session.update sent (server_vad)");
+/// Delete every tracked item from the live conversation.
+async fn delete_items(items: &ItemList, control_tx: &mpsc::Sender<tungstenite::Message>) {
+    let ids = std::mem::take(&mut *items.lock().unwrap_or_else(|e| e.into_inner()));
+    for id in &ids {
+        let event = json!({"type": "conversation.item.delete", "item_id": id}).to_string();
+        let _ = control_tx.send(tungstenite::Message::Text(event)).await;
+    }
+    debug!(items = ids.len(), "conversation items deleted");
+}
 
-    // ── Internal channels ──────────────────────────────────────────────
-    //
-    //  audio_tx / audio_rx — transport_udp pushes 16 kHz PCM chunks
-    //  ws_msg_tx / ws_msg_rx — reader task injects control msgs (Pong)
-    //                          into the sink owned by the writer task
-    let (audio_tx, mut audio_rx) = mpsc::channel::<Vec<u8>>(512);
-    let (ws_msg_tx, mut ws_msg_rx) = mpsc::channel::<tungstenite::Message>(64);
-    let control_tx = ws_msg_tx.clone();
+// ═══════════════════════════════════════════════════════════════════════
+//  Supervisor — one WebSocket at a time, reconnect on drop
+// ═══════════════════════════════════════════════════════════════════════
 
-    // ── Writer task ────────────────────────────────────────────────────
-    //  Merges two sources into the single WS sink:
-    //    1. audio chunks  → resample 16→24 kHz → base64 → append event
-    //    2. control msgs  → forwarded as-is (e.g. Pong)
-    let writer_handle = tokio::spawn(async move {
-        info!("OpenAI writer task started");
-        let mut audio_chunks_sent: u64 = 0;
-        loop {
-            tokio::select! {
-                biased;
+struct Supervisor {
+    conn: Connection,
+    health: OpenAiHealth,
+    instructions: Arc<RwLock<String>>,
+    conversations: ConversationStore,
+    items: ItemList,
+    reader: ReaderCtx,
+}
 
-                Some(msg) = ws_msg_rx.recv() => {
-                    if let Err(e) = ws_sink.send(msg).await {
-                        error!("WS control send error: {}", e);
-                        break;
-                    }
-                }
+impl Supervisor {
+    async fn run(
+        self,
+        first: WsStream,
+        mut audio_rx: mpsc::Receiver<Vec<u8>>,
+        mut ws_msg_rx: mpsc::Receiver<tungstenite::Message>
+    ) {
+        let mut pending = Some(first);
+        let mut backoff = RECONNECT_MIN;
+        let mut state = ReaderState::default();
 
-                Some(pcm_16k) = audio_rx.recv() => {
-                    let pcm_16k_len = pcm_16k.len();
-                    let pcm_24k = resample_16k_to_24k(&pcm_16k);
-                    let pcm_24k_len = pcm_24k.len();
-                    let b64 = BASE64.encode(&pcm_24k);
-
-                    debug!(
-                        pcm_16k_bytes = pcm_16k_len,
-                        pcm_24k_bytes = pcm_24k_len,
-                        b64_len = b64.len(),
-                        "sending input_audio_buffer.append to OpenAI"
-                    );
-
-                    let event = json!({
-                        "type": "input_audio_buffer.append",
-                        "audio": b64,
-                    });
-
-                    if let Err(e) = ws_sink
-                        .send(tungstenite::Message::Text(event.to_string()))
-                        .await
-                    {
-                        error!("WS audio send error: {}", e);
-                        break;
+        loop {
+            let ws_stream = match pending.take() {
+                Some(s) => s,
+                None =>
+                    match connect(&self.conn).await {
+                        Ok(s) => {
+                            backoff = RECONNECT_MIN;
+                            self.health.record_reconnect();
+                            s
+                        }
+                        Err(e) => {
+                            warn!(
+                                error = %e,
+                                retry_in_secs = backoff.as_secs(),
+                                "OpenAI reconnect failed"
+                            );
+                            tokio::time::sleep(backoff).await;
+                            backoff = (backoff * 2).min(RECONNECT_MAX);
+                            continue;
+                        }
                     }
-                    audio_chunks_sent += 1;
-                }
+            };
 
-                else => {
-                    info!("OpenAI writer: all channels closed");
-                    break;
-                },
+            let closed = self.serve(ws_stream, &mut audio_rx, &mut ws_msg_rx, &mut state).await;
+            self.health.set_connected(false);
+            if closed {
+                info!("OpenAI writer: all channels closed");
+                break;
             }
+            warn!(retry_in_secs = backoff.as_secs(), "⚠️ OpenAI connection lost — reconnecting");
+            tokio::time::sleep(backoff).await;
         }
-        info!(audio_chunks_sent = audio_chunks_sent, "OpenAI writer task exiting");
-    });
-
-    // ── Reader task ────────────────────────────────────────────────────
-    //  Reads server events from the WS; when we get audio deltas
-    //  we decode + resample 24→16 kHz + packetise as AUDIO_DOWN.
-    let active_esp_reader = active_esp.clone();
-    let debug_save_dir = format!("{}/debug", audio_save_dir);
-    let reader_handle = tokio::spawn(async move {
-        info!(
-            save_debug_audio = save_debug_audio,
-            "👂 OpenAI reader task started (persistent session)"
-        );
-        let mut out_seq: u16 = 0;
-        let mut total_audio_deltas: u64 = 0;
-        let mut total_audio_bytes_to_esp: u64 = 0;
-
-        // Debug audio accumulator (only active when --save-debug-audio is set)
-        let mut response_audio_buf: Vec<u8> = Vec::new();
-        let mut response_count: u64 = 0;
-        if save_debug_audio {
-            tokio::fs::create_dir_all(&debug_save_dir).await.ok();
-            info!(dir = %debug_save_dir, "🔴 debug audio saving enabled");
-        }
-
-        while let Some(msg_result) = ws_reader.next().await {
-            let msg = match msg_result {
-                Ok(m) => m,
-                Err(e) => {
-                    error!("WS read error: {}", e);
-                    break;
-                }
-            };
+    }
 
-            let text = match &msg {
-                tungstenite::Message::Text(t) => {
-                    // Log a truncated preview of every text frame
-                    let preview: String = t.chars().take(200).collect();
-                    debug!(len = t.len(), preview = %preview, "WS text frame received");
-                    t.clone()
-                }
-                tungstenite::Message::Close(frame) => {
-                    info!(frame = ?frame, "OpenAI WebSocket closed by server");
-                    break;
-                }
-                tungstenite::Message::Ping(data) => {
-                    debug!(len = data.len(), "WS Ping received");
-                    let _ = ws_msg_tx.send(tungstenite::Message::Pong(data.clone())).await;
-                    continue;
-                }
-                tungstenite::Message::Binary(data) => {
-                    debug!(len = data.len(), "WS Binary frame received (unexpected)");
-                    continue;
-                }
-                other => {
-                    debug!(msg_type = ?other, "WS unknown frame type");
-                    continue;
-                }
-            };
+    /// Configure one connection and pump it until it drops.  Returns
+    /// `true` when the input channels are closed (shutdown).
+    async fn serve(
+        &self,
+        ws_stream: WsStream,
+        audio_rx: &mut mpsc::Receiver<Vec<u8>>,
+        ws_msg_rx: &mut mpsc::Receiver<tungstenite::Message>,
+        state: &mut ReaderState
+    ) -> bool {
+        // Split into independent read / write halves
+        let (mut ws_sink, ws_reader) = ws_stream.split();
+
+        // A new connection starts with an empty server-side conversation
+        self.items
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        self.reader.response.finished();
+
+        // ── Send session.update ────────────────────────────────────────
+        let session_update_str = session_update(&self.conn, &self.instructions.read().await);
+        info!(payload = %session_update_str, "session.update payload");
+        if let Err(e) = ws_sink.send(tungstenite::Message::Text(session_update_str)).await {
+            error!("Failed to send session.update: {}", e);
+            return false;
+        }
+        info!(voice = %self.conn.voice, mode = ?self.conn.mode, "session.update sent (server_vad)");
+
+        // ── Re-inject the active device's history ──────────────────────
+        let history = self.conversations.active_history();
+        for turn in &history {
+            if let Err(e) = ws_sink.send(tungstenite::Message::Text(history_item(turn))).await {
+                error!("Failed to re-inject conversation history: {}", e);
+                return false;
+            }
+        }
+        if !history.is_empty() {
+            info!(turns = history.len(), "💬 conversation history re-injected");
+        }
+        self.health.touch();
+        self.health.set_connected(true);
+
+        tokio::select! {
+            closed = write_loop(&mut ws_sink, audio_rx, ws_msg_rx, self.health.inner.ping_every) => closed,
+            _ = read_loop(ws_reader, &self.reader, state) => false,
+            _ = watchdog(&self.health) => {
+                warn!(
+                    stale_secs = self.health.inner.stale_after.as_secs(),
+                    "🐕 OpenAI WebSocket silent — tearing down half-open connection"
+                );
+                false
+            }
+        }
+    }
+}
 
-            let event: Value = match serde_json::from_str(&text) {
-                Ok(v) => v,
-                Err(e) => {
-                    warn!("Failed to parse OpenAI event: {}", e);
-                    continue;
+/// Resolve once the connection has been silent for too long (never if
+/// the watchdog is disabled).
+async fn watchdog(health: &OpenAiHealth) {
+    if health.inner.stale_after.is_zero() {
+        return std::future::pending().await;
+    }
+    let mut tick = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        tick.tick().await;
+        if health.is_stale() {
+            return;
+        }
+    }
+}
+
+// ── Writer ─────────────────────────────────────────────────────────────
+//  Merges three sources into the single WS sink:
+//    1. audio chunks  → resample 16→24 kHz → base64 → append event
+//    2. control msgs  → forwarded as-is (e.g. Pong)
+//    3. keepalive     → a Ping every `ping_every` (0 = off)
+//  Queued audio goes out before control messages, so a commit sent
+//  right after the last chunk of a session never overtakes it.
+//  Returns `true` if the channels closed, `false` on a send error.
+async fn write_loop(
+    ws_sink: &mut futures_util::stream::SplitSink<WsStream, tungstenite::Message>,
+    audio_rx: &mut mpsc::Receiver<Vec<u8>>,
+    ws_msg_rx: &mut mpsc::Receiver<tungstenite::Message>,
+    ping_every: Duration
+) -> bool {
+    info!("OpenAI writer task started");
+    let mut audio_chunks_sent: u64 = 0;
+    let pinging = !ping_every.is_zero();
+    let mut ping = tokio::time::interval_at(
+        tokio::time::Instant::now() + ping_every,
+        if pinging { ping_every } else { Duration::from_secs(3600) }
+    );
+    let closed = loop {
+        tokio::select! {
+            biased;
+
+            _ = ping.tick(), if pinging => {
+                if let Err(e) = ws_sink.send(tungstenite::Message::Ping(Vec::new())).await {
+                    error!("WS keepalive ping error: {}", e);
+                    break false;
                 }
-            };
+                debug!("WS keepalive ping sent");
+            }
 
-            let event_type = event["type"].as_str().unwrap_or("");
-
-            match event_type {
-                // ── Lifecycle ─────────────────────────────────────
-                "session.created" => {
-                    let session_id = event["session"]["id"].as_str().unwrap_or("?");
-                    let model = event["session"]["model"].as_str().unwrap_or("?");
-                    info!(
-                        session_id = session_id,
-                        model = model,
-                        "OpenAI Realtime session created"
-                    );
-                    debug!(raw = %text, "session.created full payload");
+            Some(pcm_16k) = audio_rx.recv() => {
+                let pcm_16k_len = pcm_16k.len();
+                let pcm_24k = resample_16k_to_24k(&pcm_16k);
+                let pcm_24k_len = pcm_24k.len();
+                let b64 = BASE64.encode(&pcm_24k);
+
+                debug!(
+                    pcm_16k_bytes = pcm_16k_len,
+                    pcm_24k_bytes = pcm_24k_len,
+                    b64_len = b64.len(),
+                    "sending input_audio_buffer.append to OpenAI"
+                );
+
+                let event = json!({
+                    "type": "input_audio_buffer.append",
+                    "audio": b64,
+                });
+
+                if let Err(e) = ws_sink
+                    .send(tungstenite::Message::Text(event.to_string()))
+                    .await
+                {
+                    error!("WS audio send error: {}", e);
+                    break false;
                 }
-                "session.updated" => {
-                    info!("OpenAI session config confirmed");
-                    debug!(raw = %text, "session.updated full payload");
+                audio_chunks_sent += 1;
+            }
+
+            Some(msg) = ws_msg_rx.recv() => {
+                if let Err(e) = ws_sink.send(msg).await {
+                    error!("WS control send error: {}", e);
+                    break false;
                 }
+            }
 
-                // ── Audio response: stream back to ESP ────────────
-                "response.audio.delta" => {
-                    if let Some(b64) = event["delta"].as_str() {
-                        info!(b64_len = b64.len(), "🔊 response.audio.delta received from OpenAI");
-                        match BASE64.decode(b64) {
-                            Ok(pcm_24k) => {
-                                let pcm_16k = resample_24k_to_16k(&pcm_24k);
-                                let n_chunks = pcm_16k.chunks(ESP_MAX_PAYLOAD).len();
-
-                                if save_debug_audio {
-                                    response_audio_buf.extend_from_slice(&pcm_16k);
-                                }
+            else => break true,
+        }
+    };
+    info!(audio_chunks_sent = audio_chunks_sent, "OpenAI writer task exiting");
+    closed
+}
+
+// ── Reader ─────────────────────────────────────────────────────────────
+//  Reads server events from the WS; when we get audio deltas
+//  we decode + resample 24→16 kHz + packetise as AUDIO_DOWN.
+
+/// Shared handles the reader needs (constant across reconnects).
+struct ReaderCtx {
+    ws_msg_tx: mpsc::Sender<tungstenite::Message>,
+    active_esp: Arc<RwLock<Option<SocketAddr>>>,
+    reply_taps: ReplyTaps,
+    audio_socket: Arc<CapturedSocket>,
+    save_debug_audio: bool,
+    debug_save_dir: String,
+    conversations: ConversationStore,
+    items: ItemList,
+    health: OpenAiHealth,
+    response: Arc<ResponseGate>,
+    transcripts: TranscriptSink,
+    session_logs: SessionLogs,
+    recording: RecordingPolicies,
+    downlink: DownlinkPacer,
+    speakers: Diarizer,
+    quiet: QuietHours,
+}
+
+/// Reader counters / buffers (kept across reconnects).
+#[derive(Default)]
+struct ReaderState {
+    out_seq: u16,
+    total_audio_deltas: u64,
+    total_audio_bytes_to_esp: u64,
+    /// Debug audio accumulator (only used when --save-debug-audio is set)
+    response_audio_buf: Vec<u8>,
+    response_count: u64,
+}
+
+async fn read_loop(
+    mut ws_reader: futures_util::stream::SplitStream<WsStream>,
+    ctx: &ReaderCtx,
+    state: &mut ReaderState
+) {
+    info!(
+        save_debug_audio = ctx.save_debug_audio,
+        "👂 OpenAI reader task started (persistent session)"
+    );
+    if ctx.save_debug_audio {
+        tokio::fs::create_dir_all(&ctx.debug_save_dir).await.ok();
+        info!(dir = %ctx.debug_save_dir, "🔴 debug audio saving enabled");
+    }
+
+    while let Some(msg_result) = ws_reader.next().await {
+        let msg = match msg_result {
+            Ok(m) => m,
+            Err(e) => {
+                error!("WS read error: {}", e);
+                break;
+            }
+        };
+        ctx.health.touch();
+
+        let text = match &msg {
+            tungstenite::Message::Text(t) => {
+                // Log a truncated preview of every text frame
+                let preview: String = t.chars().take(200).collect();
+                debug!(len = t.len(), preview = %preview, "WS text frame received");
+                t.clone()
+            }
+            tungstenite::Message::Close(frame) => {
+                info!(frame = ?frame, "OpenAI WebSocket closed by server");
+                break;
+            }
+            tungstenite::Message::Ping(data) => {
+                debug!(len = data.len(), "WS Ping received");
+                let _ = ctx.ws_msg_tx.send(tungstenite::Message::Pong(data.clone())).await;
+                continue;
+            }
+            tungstenite::Message::Pong(_) => {
+                debug!("WS keepalive pong received");
+                continue;
+            }
+            tungstenite::Message::Binary(data) => {
+                debug!(len = data.len(), "WS Binary frame received (unexpected)");
+                continue;
+            }
+            other => {
+                debug!(msg_type = ?other, "WS unknown frame type");
+                continue;
+            }
+        };
+
+        let event: Value = match serde_json::from_str(&text) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Failed to parse OpenAI event: {}", e);
+                continue;
+            }
+        };
+
+        // Logged under the trace id of the wired ESP's session
+        let esp = *ctx.active_esp.read().await;
+        let trace = esp.and_then(|esp| ctx.session_logs.trace(esp));
+        let span = tracing::info_span!("openai_event", trace = trace.map(tracing::field::display));
+        handle_event(&event, &text, ctx, state).instrument(span).await;
+    }
+
+    info!(
+        total_audio_deltas = state.total_audio_deltas,
+        total_audio_bytes_to_esp = state.total_audio_bytes_to_esp,
+        "OpenAI reader task exiting"
+    );
+}
+
+async fn handle_event(event: &Value, text: &str, ctx: &ReaderCtx, state: &mut ReaderState) {
+    let event_type = event["type"].as_str().unwrap_or("");
+
+    // Session log: everything but the high-rate deltas
+    let esp = *ctx.active_esp.read().await;
+    if let Some(esp) = esp.filter(|_| !event_type.ends_with(".delta")) {
+        ctx.session_logs.openai(esp, event);
+    }
+
+    match event_type {
+        // ── Lifecycle ─────────────────────────────────────
+        "session.created" => {
+            let session_id = event["session"]["id"].as_str().unwrap_or("?");
+            let model = event["session"]["model"].as_str().unwrap_or("?");
+            info!(session_id = session_id, model = model, "OpenAI Realtime session created");
+            debug!(raw = %text, "session.created full payload");
+        }
+        "session.updated" => {
+            info!("OpenAI session config confirmed");
+            debug!(raw = %text, "session.updated full payload");
+        }
+
+        // ── Conversation items (tracked for history switches) ──────
+        "conversation.item.created" => {
+            if let Some(id) = event["item"]["id"].as_str() {
+                ctx.items
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push(id.to_string());
+                debug!(item_id = id, "conversation item created");
+            }
+        }
+        "conversation.item.deleted" => {
+            debug!(item_id = event["item_id"].as_str().unwrap_or("?"), "conversation item deleted");
+        }
+
+        // ── Audio response: stream back to ESP ────────────
+        "response.created" => {
+            ctx.response.started();
+            debug!(response_id = event["response"]["id"].as_str().unwrap_or("?"), "OpenAI response.created");
+        }
 
-                                let current_esp = { *active_esp_reader.read().await };
-                                if let Some(esp_addr) = current_esp {
-                                    info!(
-                                        pcm_24k_bytes = pcm_24k.len(),
-                                        pcm_16k_bytes = pcm_16k.len(),
-                                        n_chunks = n_chunks,
-                                        esp = %esp_addr,
-                                        seq = out_seq,
-                                        "📤 sending AUDIO_DOWN to ESP"
-                                    );
-
-                                    for chunk in pcm_16k.chunks(ESP_MAX_PAYLOAD) {
-                                        let pkt = build_audio_down(out_seq, 0, chunk);
-                                        out_seq = out_seq.wrapping_add(1);
-
-                                        match audio_socket.send_to(&pkt, esp_addr).await {
-                                            Ok(sent) => {
-                                                debug!(
-                                                    sent_bytes = sent,
-                                                    seq = out_seq.wrapping_sub(1),
-                                                    "AUDIO_DOWN packet sent"
-                                                );
-                                            }
-                                            Err(e) => {
-                                                warn!(
-                                                    error = %e,
-                                                    esp = %esp_addr,
-                                                    "failed to send AUDIO_DOWN to ESP"
-                                                );
-                                            }
-                                        }
+        "response.audio.delta" if ctx.response.is_cancelled() => {
+            debug!("dropping audio delta of cancelled response");
+        }
+
+        "response.audio.delta" if ctx.conversations.active_device().is_some_and(|d| ctx.quiet.mutes_audio(&d)) => {
+            debug!("dropping audio delta during quiet hours");
+        }
+
+        "response.audio.delta" => {
+            if let Some(b64) = event["delta"].as_str() {
+                info!(b64_len = b64.len(), "🔊 response.audio.delta received from OpenAI");
+                match BASE64.decode(b64) {
+                    Ok(pcm_24k) => {
+                        let pcm_16k = resample_24k_to_16k(&pcm_24k);
+                        let n_chunks = pcm_16k.chunks(ESP_MAX_PAYLOAD).len();
+
+                        if ctx.save_debug_audio {
+                            state.response_audio_buf.extend_from_slice(&pcm_16k);
+                        }
+
+                        let current_esp = { *ctx.active_esp.read().await };
+                        if let Some(esp_addr) = current_esp {
+                            let secs = (pcm_16k.len() as f64) / (16_000.0 * 2.0);
+                            ctx.session_logs.reply_audio(esp_addr, Duration::from_secs_f64(secs));
+                        }
+                        let tap =current_esp.and_then(|addr| ctx.reply_taps.get(&addr).map(|tap| tap.clone()));
+                        if let Some(tap) = tap {
+                            debug!(pcm_16k_bytes = pcm_16k.len(), "🔊 reply audio to tap");
+                            let _ = tap.send(pcm_16k);
+                        } else if let Some(esp_addr) = current_esp {
+                            info!(
+                                pcm_24k_bytes = pcm_24k.len(),
+                                pcm_16k_bytes = pcm_16k.len(),
+                                n_chunks = n_chunks,
+                                esp = %esp_addr,
+                                seq = state.out_seq,
+                                "📤 sending AUDIO_DOWN to ESP"
+                            );
+
+                            let chunks: Vec<(&[u8], Vec<u8>)> = pcm_16k
+                                .chunks(ESP_MAX_PAYLOAD)
+                                .map(|chunk| {
+                                    let pkt = build_audio_down(state.out_seq, 0, chunk);
+                                    state.out_seq = state.out_seq.wrapping_add(1);
+                                    (chunk, pkt)
+                                })
+                                .collect();
+                            if ctx.downlink.enabled() {
+                                // --downlink-pacing: one chunk at a time
+                                for (chunk, pkt) in &chunks {
+                                    let secs = (chunk.len() as f64) / (16_000.0 * 2.0);
+                                    ctx.downlink.pace(esp_addr, Duration::from_secs_f64(secs)).await;
+                                    if let Err(e) = ctx.audio_socket.send_to(pkt, esp_addr).await {
+                                        warn!(error = %e, esp = %esp_addr, "failed to send AUDIO_DOWN to ESP");
                                     }
-                                    total_audio_bytes_to_esp += pcm_16k.len() as u64;
-                                } else {
-                                    warn!(
-                                        pcm_bytes = pcm_16k.len(),
-                                        "⚠️ no active ESP client — dropping audio response!"
-                                    );
+                                }
+                            } else {
+                                // Blasted as received, one batch per delta
+                                let batch: Vec<(&[u8], SocketAddr)> = chunks
+                                    .iter()
+                                    .map(|(_, pkt)| (pkt.as_slice(), esp_addr))
+                                    .collect();
+                                for (_, e) in ctx.audio_socket.send_batch(&batch).await {
+                                    warn!(error = %e, esp = %esp_addr, "failed to send AUDIO_DOWN to ESP");
                                 }
                             }
-                            Err(e) => {
-                                warn!(b64_len = b64.len(), error = %e, "base64 decode error on audio delta");
-                            }
-                        }
-                        total_audio_deltas += 1;
-                    } else {
-                        warn!("response.audio.delta event has no 'delta' field");
-                    }
-                }
-
-                "response.audio.done" => {
-                    let current_esp = { *active_esp_reader.read().await };
-                    info!(
-                        esp = ?current_esp,
-                        total_deltas = total_audio_deltas,
-                        total_bytes_to_esp = total_audio_bytes_to_esp,
-                        "✅ OpenAI audio response complete"
-                    );
-
-                    // Save response audio as WAV when debug saving is enabled
-                    if save_debug_audio && !response_audio_buf.is_empty() {
-                        response_count += 1;
-                        let ts = std::time::SystemTime
-                            ::now()
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .unwrap_or_default()
-                            .as_secs();
-                        let wav_path = format!(
-                            "{}/openai_response_{}_{}.wav",
-                            debug_save_dir,
-                            response_count,
-                            ts
-                        );
-                        let audio_secs = (response_audio_buf.len() as f64) / (16_000.0 * 2.0);
-                        match write_wav_16k_mono(&wav_path, &response_audio_buf).await {
-                            Ok(()) =>
-                                info!(
-                                path = %wav_path,
-                                audio_secs = format!("{:.2}", audio_secs),
-                                "💾 saved OpenAI response audio"
-                            ),
-                            Err(e) => warn!(error = %e, "failed to save OpenAI response WAV"),
+                            debug!(packets = chunks.len(), "AUDIO_DOWN packets sent");
+                            state.total_audio_bytes_to_esp += pcm_16k.len() as u64;
+                        } else {
+                            warn!(
+                                pcm_bytes = pcm_16k.len(),
+                                "⚠️ no active ESP client — dropping audio response!"
+                            );
                         }
-                        response_audio_buf.clear();
                     }
-
-                    if let Some(esp_addr) = current_esp {
-                        let pkt = build_control(out_seq, CTRL_STREAM_END, 0);
-                        out_seq = out_seq.wrapping_add(1);
-                        let _ = audio_socket.send_to(&pkt, esp_addr).await;
+                    Err(e) => {
+                        warn!(b64_len = b64.len(), error = %e, "base64 decode error on audio delta");
                     }
                 }
+                state.total_audio_deltas += 1;
+            } else {
+                warn!("response.audio.delta event has no 'delta' field");
+            }
+        }
 
-                "response.done" => {
-                    let st = event["response"]["status"].as_str().unwrap_or("?");
-                    let usage = &event["response"]["usage"];
-                    info!(status = st, usage = %usage, "OpenAI response.done");
-                    debug!(raw = %text, "response.done full");
-                }
+        "response.audio.done" => {
+            let current_esp = { *ctx.active_esp.read().await };
+            info!(
+                esp = ?current_esp,
+                total_deltas = state.total_audio_deltas,
+                total_bytes_to_esp = state.total_audio_bytes_to_esp,
+                "✅ OpenAI audio response complete"
+            );
 
-                // ── VAD events ────────────────────────────────────
-                "input_audio_buffer.speech_started" => {
-                    info!("OpenAI VAD: speech started");
-                }
-                "input_audio_buffer.speech_stopped" => {
-                    info!("OpenAI VAD: speech stopped");
-                }
-                "input_audio_buffer.committed" => {
-                    info!("audio buffer committed");
+            // Save response audio as WAV when debug saving is enabled
+            if ctx.save_debug_audio && !state.response_audio_buf.is_empty() {
+                state.response_count += 1;
+                let ts = std::time::SystemTime
+                    ::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let wav_path = format!(
+                    "{}/openai_response_{}_{}.wav",
+                    ctx.debug_save_dir,
+                    state.response_count,
+                    ts
+                );
+                let audio_secs = (state.response_audio_buf.len() as f64) / (16_000.0 * 2.0);
+                match write_wav_16k_mono(&wav_path, &state.response_audio_buf).await {
+                    Ok(()) =>
+                        info!(
+                        path = %wav_path,
+                        audio_secs = format!("{:.2}", audio_secs),
+                        "💾 saved OpenAI response audio"
+                    ),
+                    Err(e) => warn!(error = %e, "failed to save OpenAI response WAV"),
                 }
+                state.response_audio_buf.clear();
+            }
 
-                // ── Transcripts ───────────────────────────────────
-                "response.audio_transcript.delta" => {
-                    if let Some(d) = event["delta"].as_str() {
-                        debug!(delta = d, "transcript delta");
-                    }
-                }
-                "response.audio_transcript.done" => {
-                    if let Some(t) = event["transcript"].as_str() {
-                        info!("\n╔══════════════════════════════════════════════╗");
-                        info!("║ 🤖 AI SAID: {}", t);
-                        info!("╚══════════════════════════════════════════════╝");
-                    }
-                }
-                "conversation.item.input_audio_transcription.completed" => {
-                    if let Some(t) = event["transcript"].as_str() {
-                        info!("\n┌──────────────────────────────────────────────┐");
-                        info!("│ 🎤 USER SAID: {}", t);
-                        info!("└──────────────────────────────────────────────┘");
-                    }
-                }
+            if let Some(esp_addr) = current_esp.filter(|addr| !ctx.reply_taps.contains_key(addr)) {
+                let pkt = build_control(state.out_seq, CTRL_STREAM_END, 0);
+                state.out_seq = state.out_seq.wrapping_add(1);
+                let _ = ctx.audio_socket.send_to(&pkt, esp_addr).await;
+            }
+        }
 
-                // ── Errors ────────────────────────────────────────
-                "error" => {
-                    let msg = event["error"]["message"].as_str().unwrap_or("unknown");
-                    let code = event["error"]["code"].as_str().unwrap_or("unknown");
-                    let etype = event["error"]["type"].as_str().unwrap_or("unknown");
-                    error!(
-                        code = code, error_type = etype, message = msg,
-                        raw = %text,
-                        "❌ OpenAI error"
-                    );
-                }
+        "response.done" => {
+            ctx.response.finished();
+            let st = event["response"]["status"].as_str().unwrap_or("?");
+            let usage = &event["response"]["usage"];
+            info!(status = st, usage = %usage, "OpenAI response.done");
+            debug!(raw = %text, "response.done full");
+        }
 
-                // everything else → log with full payload so we can spot unknown events
-                other => {
-                    info!(event_type = other, raw = %text, "unhandled OpenAI event");
-                }
+        // ── VAD events ────────────────────────────────────
+        "input_audio_buffer.speech_started" => {
+            info!("OpenAI VAD: speech started");
+        }
+        "input_audio_buffer.speech_stopped" => {
+            info!("OpenAI VAD: speech stopped");
+        }
+        "input_audio_buffer.committed" => {
+            info!("audio buffer committed");
+        }
+
+        // ── Transcripts ───────────────────────────────────
+        "response.audio_transcript.delta" => {
+            if let Some(d) = event["delta"].as_str() {
+                debug!(delta = d, "transcript delta");
+            }
+        }
+        "response.audio_transcript.done" => {
+            if let Some(t) = event["transcript"].as_str() {
+                info!("\n╔══════════════════════════════════════════════╗");
+                info!("║ 🤖 AI SAID: {}", t);
+                info!("╚══════════════════════════════════════════════╝");
+                publish_transcript(ctx, esp, Role::Assistant, t);
+            }
+        }
+        "conversation.item.input_audio_transcription.completed" => {
+            if let Some(t) = event["transcript"].as_str() {
+                info!("\n┌──────────────────────────────────────────────┐");
+                info!("│ 🎤 USER SAID: {}", t);
+                info!("└──────────────────────────────────────────────┘");
+                publish_transcript(ctx, esp, Role::User, t);
             }
         }
 
-        info!(
-            total_audio_deltas = total_audio_deltas,
-            total_audio_bytes_to_esp = total_audio_bytes_to_esp,
-            "OpenAI reader task exiting"
-        );
-    });
+        // ── Errors ────────────────────────────────────────
+        "error" => {
+            let msg = event["error"]["message"].as_str().unwrap_or("unknown");
+            let code = event["error"]["code"].as_str().unwrap_or("unknown");
+            let etype = event["error"]["type"].as_str().unwrap_or("unknown");
+            error!(
+                code = code, error_type = etype, message = msg,
+                raw = %text,
+                "❌ OpenAI error"
+            );
+        }
 
-    Ok(OpenAiSession {
-        audio_tx,
-        control_tx,
-        active_esp,
-        reader_handle,
-        writer_handle,
-    })
+        // everything else → log with full payload so we can spot unknown events
+        other => {
+            info!(event_type = other, raw = %text, "unhandled OpenAI event");
+        }
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════
@@ -582,7 +1220,7 @@ pub fn resample_24k_to_16k(pcm: &[u8]) -> Vec<u8> {
 }
 
 /// Generic linear-interpolation resampler for 16-bit LE PCM.
-fn resample(pcm: &[u8], from_rate: u64, to_rate: u64) -> Vec<u8> {
+pub fn resample(pcm: &[u8], from_rate: u64, to_rate: u64) -> Vec<u8> {
     let n_in = pcm.len() / 2;
     if n_in == 0 {
         return Vec::new();
@@ -663,6 +1301,78 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_session_update_transcribe_mode() {
+        let mut conn = Connection {
+            api_key: String::new(),
+            model: String::new(),
+            voice: "ash".into(),
+            mode: OpenAiMode::Conversation,
+            transcription_model: "gpt-4o-transcribe".into(),
+        };
+        let v: Value = serde_json::from_str(&session_update(&conn, "hi")).unwrap();
+        assert_eq!(v["session"]["turn_detection"]["create_response"], true);
+        assert_eq!(v["session"]["input_audio_transcription"]["model"], "gpt-4o-transcribe");
+
+        conn.mode = OpenAiMode::Transcribe;
+        let v: Value = serde_json::from_str(&session_update(&conn, "hi")).unwrap();
+        assert_eq!(v["session"]["turn_detection"]["create_response"], false);
+        assert_eq!(v["session"]["modalities"], json!(["text"]));
+    }
+
+    #[test]
+    fn test_response_gate_cancel() {
+        let gate = ResponseGate::default();
+        assert!(!gate.cancel(), "nothing in flight");
+        assert!(!gate.is_cancelled());
+
+        gate.started();
+        assert!(gate.cancel());
+        assert!(gate.is_cancelled());
+        assert!(!gate.cancel(), "already cancelled");
+
+        // Stays muted through response.done until the next response
+        gate.finished();
+        assert!(gate.is_cancelled());
+        gate.started();
+        assert!(!gate.is_cancelled());
+    }
+
+    #[test]
+    fn test_health_readiness() {
+        let off = OpenAiHealth::new(false, Duration::ZERO, Duration::from_secs(45));
+        assert!(off.snapshot().ready);
+
+        let health = OpenAiHealth::new(true, Duration::ZERO, Duration::from_secs(45));
+        assert!(!health.snapshot().ready);
+        health.set_connected(true);
+        assert!(health.is_stale(), "never heard from counts as stale");
+        health.touch();
+        let snap = health.snapshot();
+        assert!(snap.ready && snap.last_event_age_ms.is_some());
+
+        // Watchdog disabled: connected is enough
+        let lax = OpenAiHealth::new(true, Duration::ZERO, Duration::ZERO);
+        lax.set_connected(true);
+        assert!(lax.snapshot().ready);
+    }
+
+    #[test]
+    fn test_history_item_roles() {
+        let user: Value = serde_json
+            ::from_str(&history_item(&Turn { role: Role::User, text: "hi".into(), speaker: None }))
+            .unwrap();
+        assert_eq!(user["type"], "conversation.item.create");
+        assert_eq!(user["item"]["role"], "user");
+        assert_eq!(user["item"]["content"][0]["type"], "input_text");
+
+        let bot: Value = serde_json
+            ::from_str(&history_item(&Turn { role: Role::Assistant, text: "hello!".into(), speaker: None }))
+            .unwrap();
+        assert_eq!(bot["item"]["role"], "assistant");
+        assert_eq!(bot["item"]["content"][0]["text"], "hello!");
+    }
+
     #[test]
     fn test_resample_empty() {
         assert!(resample_16k_to_24k(&[]).is_empty());
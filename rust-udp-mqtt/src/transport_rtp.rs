@@ -0,0 +1,697 @@
+use crate::bridge_openai::{ AudioLink, RealtimeBridge };
+use crate::clock::{ self, SharedClock };
+use crate::config::Config;
+use crate::sensor::{ SensorPacket, DATA_TYPE_AUDIO };
+use crate::pcm::SampleFormat;
+use crate::recording_policy::RecordingPolicies;
+use crate::session_log::SessionLogs;
+use crate::shard::ShardedSender;
+use crate::stats::Stats;
+use crate::tenants::TenantId;
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+use tokio::net::UdpSocket;
+use tracing::{ debug, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  RTP / RTCP audio ingest (`--rtp-port`)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Standard RTP streams (RFC 3550) from SIP phones, media servers and
+//  WebRTC gateways, next to the ESP audio protocol:
+//
+//    --rtp-port N     RTP on N, RTCP on N+1 (rtcp-mux on N also works)
+//
+//  Payload types:
+//
+//    10 / 11          L16 44.1 kHz stereo / mono (static, RFC 3551)
+//    --rtp-l16-pt     L16 mono at --rtp-l16-rate (default 96, 16 kHz)
+//    --rtp-opus-pt    Opus (default 111; needs `--features opus`)
+//
+//  Every SSRC is one session: its audio (16 kHz s16 mono) goes to the
+//  audio VAD lane with the SSRC as sensor_id, and a session log is kept
+//  per stream.  A stream ends on RTCP BYE or after --rtp-idle-ms
+//  without packets.  Every RTCP_INTERVAL each live stream gets a
+//  receiver report (loss fraction, cumulative loss, jitter, LSR/DLSR)
+//  at its RTCP address, so senders can adapt to the loss they cause.
+//
+//  RTP has no AUDIO_DOWN path back to the sender, so streams are only
+//  attached to OpenAI in `--openai-mode transcribe`: the transcript
+//  and conversation history are kept, there is no spoken reply.
+
+/// How often each live stream gets a receiver report.
+pub const RTCP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Sample rate of the audio handed to VAD and OpenAI.
+const OUTPUT_RATE: u32 = 16_000;
+
+const RTP_VERSION: u8 = 2;
+const RTP_HEADER_SIZE: usize = 12;
+
+/// RTCP packet types (RFC 3550 §12.1)
+const RTCP_SR: u8 = 200;
+const RTCP_RR: u8 = 201;
+const RTCP_SDES: u8 = 202;
+const RTCP_BYE: u8 = 203;
+const SDES_CNAME: u8 = 1;
+
+/// One parsed RTP packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RtpPacket<'a> {
+    pub payload_type: u8,
+    pub marker: bool,
+    pub seq: u16,
+    pub timestamp: u32,
+    pub ssrc: u32,
+    pub payload: &'a [u8],
+}
+
+impl<'a> RtpPacket<'a> {
+    /// Parse an RTP datagram (CSRCs, header extension and padding are
+    /// skipped).  None for anything that is not RTP version 2.
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        if buf.len() < RTP_HEADER_SIZE || buf[0] >> 6 != RTP_VERSION {
+            return None;
+        }
+        let padding = buf[0] & 0x20 != 0;
+        let extension = buf[0] & 0x10 != 0;
+        let mut offset = RTP_HEADER_SIZE + 4 * (buf[0] & 0x0f) as usize;
+        if extension {
+            let words = buf.get(offset + 2..offset + 4)?;
+            offset += 4 + 4 * u16::from_be_bytes([words[0], words[1]]) as usize;
+        }
+        let mut end = buf.len();
+        if padding {
+            end = end.checked_sub(*buf.last()? as usize)?;
+        }
+        Some(RtpPacket {
+            payload_type: buf[1] & 0x7f,
+            marker: buf[1] & 0x80 != 0,
+            seq: u16::from_be_bytes([buf[2], buf[3]]),
+            timestamp: u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]),
+            ssrc: u32::from_be_bytes([buf[8], buf[9], buf[10], buf[11]]),
+            payload: buf.get(offset..end)?,
+        })
+    }
+
+    /// Serialise with a plain 12-byte header (no CSRCs, no extension).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(RTP_HEADER_SIZE + self.payload.len());
+        out.push(RTP_VERSION << 6);
+        out.push(((self.marker as u8) << 7) | (self.payload_type & 0x7f));
+        out.extend_from_slice(&self.seq.to_be_bytes());
+        out.extend_from_slice(&self.timestamp.to_be_bytes());
+        out.extend_from_slice(&self.ssrc.to_be_bytes());
+        out.extend_from_slice(self.payload);
+        out
+    }
+}
+
+/// Whether a datagram on the RTP port is multiplexed RTCP (RFC 5761).
+fn is_rtcp(buf: &[u8]) -> bool {
+    buf.len() >= 8 && (RTCP_SR..=204).contains(&buf[1])
+}
+
+/// Audio encoding of a payload type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// Big-endian 16-bit PCM
+    L16 {
+        rate: u32,
+        channels: u8,
+    },
+    Opus,
+}
+
+/// The dynamic payload types of `--rtp-l16-pt` / `--rtp-opus-pt`.
+#[derive(Debug, Clone, Copy)]
+pub struct PayloadTypes {
+    pub l16: u8,
+    pub l16_rate: u32,
+    pub opus: u8,
+}
+
+impl PayloadTypes {
+    pub fn from_config(config: &Config) -> Self {
+        PayloadTypes { l16: config.rtp_l16_pt, l16_rate: config.rtp_l16_rate, opus: config.rtp_opus_pt }
+    }
+
+    /// The encoding of `payload_type` (None = not accepted).
+    pub fn codec(&self, payload_type: u8) -> Option<Codec> {
+        match payload_type {
+            10 => Some(Codec::L16 { rate: 44_100, channels: 2 }),
+            11 => Some(Codec::L16 { rate: 44_100, channels: 1 }),
+            pt if pt == self.l16 => Some(Codec::L16 { rate: self.l16_rate, channels: 1 }),
+            pt if pt == self.opus => Some(Codec::Opus),
+            _ => None,
+        }
+    }
+}
+
+impl Codec {
+    /// RTP timestamp units per second.
+    fn clock_rate(self) -> u32 {
+        match self {
+            Codec::L16 { rate, .. } => rate,
+            Codec::Opus => 48_000,
+        }
+    }
+}
+
+/// L16 payload → 16 kHz s16le mono (first channel).
+pub fn decode_l16(payload: &[u8], rate: u32, channels: u8) -> Vec<u8> {
+    let frame = 2 * channels.max(1) as usize;
+    let pcm: Vec<u8> = payload
+        .chunks_exact(frame)
+        .flat_map(|f| i16::from_be_bytes([f[0], f[1]]).to_le_bytes())
+        .collect();
+    if rate == OUTPUT_RATE {
+        return pcm;
+    }
+    crate::transport_openai::resample(&pcm, rate as u64, OUTPUT_RATE as u64)
+}
+
+#[cfg(feature = "opus")]
+mod opus {
+    use audiopus::coder::Decoder as OpusDecoder;
+    use audiopus::packet::Packet;
+    use audiopus::{ Channels, MutSignals, SampleRate };
+
+    /// Opus → 16 kHz s16le mono (libopus resamples internally).
+    pub struct Decoder(OpusDecoder);
+
+    impl Decoder {
+        pub fn new() -> anyhow::Result<Self> {
+            Ok(Decoder(OpusDecoder::new(SampleRate::Hz16000, Channels::Mono)?))
+        }
+
+        pub fn decode(&mut self, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+            // 120 ms, the longest Opus packet
+            let mut pcm = [0i16; 1920];
+            let samples = self.0.decode(Some(Packet::try_from(payload)?), MutSignals::try_from(&mut pcm[..])?, false)?;
+            Ok(pcm[..samples].iter().flat_map(|s| s.to_le_bytes()).collect())
+        }
+    }
+}
+
+#[cfg(not(feature = "opus"))]
+mod opus {
+    /// Stand-in: Opus streams are refused without `--features opus`.
+    pub struct Decoder;
+
+    impl Decoder {
+        pub fn new() -> anyhow::Result<Self> {
+            anyhow::bail!("Opus RTP needs a build with `--features opus`")
+        }
+
+        pub fn decode(&mut self, _payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+            anyhow::bail!("built without `--features opus`")
+        }
+    }
+}
+
+// ── Reception statistics (RFC 3550 A.1, A.3, A.8) ────────────────────
+
+/// Sequence, loss and jitter bookkeeping of one SSRC.
+#[derive(Debug, Clone)]
+pub struct Reception {
+    base_seq: u32,
+    max_seq: u16,
+    /// Sequence wraparounds, shifted (count << 16)
+    cycles: u32,
+    received: u32,
+    expected_prior: u32,
+    received_prior: u32,
+    /// Previous relative transit time, in timestamp units
+    transit: Option<i64>,
+    /// Interarrival jitter estimate, in timestamp units
+    jitter: f64,
+    /// Middle 32 bits of the last SR's NTP timestamp, and when it came
+    last_sr: Option<(u32, Instant)>,
+}
+
+impl Reception {
+    pub fn new(seq: u16) -> Self {
+        Reception {
+            base_seq: seq as u32,
+            max_seq: seq,
+            cycles: 0,
+            received: 0,
+            expected_prior: 0,
+            received_prior: 0,
+            transit: None,
+            jitter: 0.0,
+            last_sr: None,
+        }
+    }
+
+    /// Record a packet; `arrival` is its arrival time in timestamp units.
+    pub fn update(&mut self, seq: u16, timestamp: u32, arrival: i64) {
+        let delta = seq.wrapping_sub(self.max_seq);
+        if delta != 0 && delta < 0x8000 {
+            if seq < self.max_seq {
+                self.cycles = self.cycles.wrapping_add(1 << 16);
+            }
+            self.max_seq = seq;
+        }
+        self.received += 1;
+
+        let transit = arrival - timestamp as i64;
+        if let Some(prev) = self.transit {
+            let d = (transit - prev).abs() as f64;
+            self.jitter += (d - self.jitter) / 16.0;
+        }
+        self.transit = Some(transit);
+    }
+
+    /// Extended highest sequence number received.
+    pub fn extended_max(&self) -> u32 {
+        self.cycles | self.max_seq as u32
+    }
+
+    pub fn expected(&self) -> u32 {
+        self.extended_max().wrapping_sub(self.base_seq).wrapping_add(1)
+    }
+
+    /// Packets lost so far (negative with duplicates).
+    pub fn lost(&self) -> i64 {
+        self.expected() as i64 - self.received as i64
+    }
+
+    pub fn jitter(&self) -> u32 {
+        self.jitter as u32
+    }
+
+    pub fn sender_report(&mut self, ntp_middle: u32, at: Instant) {
+        self.last_sr = Some((ntp_middle, at));
+    }
+
+    /// The 24-byte report block of `ssrc` (starts a new loss interval).
+    pub fn report_block(&mut self, ssrc: u32, now: Instant) -> [u8; 24] {
+        let expected = self.expected();
+        let expected_interval = expected.wrapping_sub(self.expected_prior);
+        let received_interval = self.received.wrapping_sub(self.received_prior);
+        self.expected_prior = expected;
+        self.received_prior = self.received;
+        let lost_interval = expected_interval as i64 - received_interval as i64;
+        let fraction = if expected_interval == 0 || lost_interval <= 0 {
+            0
+        } else {
+            ((lost_interval << 8) / expected_interval as i64) as u8
+        };
+        // 24-bit signed, clamped
+        let lost = (self.lost().clamp(-0x80_0000, 0x7f_ffff) as u32) & 0xff_ffff;
+        let (lsr, dlsr) = match self.last_sr {
+            // Delay since the SR in 1/65536 s
+            Some((lsr, at)) => (lsr, (now.saturating_duration_since(at).as_secs_f64() * 65536.0) as u32),
+            None => (0, 0),
+        };
+
+        let mut block = [0u8; 24];
+        block[0..4].copy_from_slice(&ssrc.to_be_bytes());
+        block[4..8].copy_from_slice(&(((fraction as u32) << 24) | lost).to_be_bytes());
+        block[8..12].copy_from_slice(&self.extended_max().to_be_bytes());
+        block[12..16].copy_from_slice(&self.jitter().to_be_bytes());
+        block[16..20].copy_from_slice(&lsr.to_be_bytes());
+        block[20..24].copy_from_slice(&dlsr.to_be_bytes());
+        block
+    }
+}
+
+/// Compound RTCP packet: RR with one report `block`, then SDES CNAME.
+pub fn receiver_report(reporter_ssrc: u32, block: &[u8; 24], cname: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(64);
+    // V=2, RC=1; length in 32-bit words minus one
+    out.extend([0x81, RTCP_RR, 0, 7]);
+    out.extend(reporter_ssrc.to_be_bytes());
+    out.extend(block);
+
+    let cname = &cname.as_bytes()[..cname.len().min(255)];
+    // SSRC + CNAME item + END, padded to a word
+    let len = (4 + 2 + cname.len() + 1).div_ceil(4) * 4;
+    out.extend([0x81, RTCP_SDES]);
+    out.extend(((len / 4) as u16).to_be_bytes());
+    out.extend(reporter_ssrc.to_be_bytes());
+    out.extend([SDES_CNAME, cname.len() as u8]);
+    out.extend(cname);
+    out.resize(out.len() + len - 4 - 2 - cname.len(), 0);
+    out
+}
+
+/// What a (compound) RTCP packet says about the streams.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct RtcpNews {
+    /// SSRC → middle 32 bits of its SR's NTP timestamp
+    sender_reports: Vec<(u32, u32)>,
+    byes: Vec<u32>,
+}
+
+fn parse_rtcp(mut buf: &[u8]) -> RtcpNews {
+    let mut news = RtcpNews::default();
+    while buf.len() >= 8 && buf[0] >> 6 == RTP_VERSION {
+        let len = 4 * (u16::from_be_bytes([buf[2], buf[3]]) as usize + 1);
+        let Some(packet) = buf.get(..len) else {
+            break;
+        };
+        match packet[1] {
+            RTCP_SR if packet.len() >= 20 => {
+                let ssrc = u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]);
+                let ntp_middle = u32::from_be_bytes([packet[10], packet[11], packet[12], packet[13]]);
+                news.sender_reports.push((ssrc, ntp_middle));
+            }
+            RTCP_BYE => {
+                let count = (packet[0] & 0x1f) as usize;
+                news.byes.extend(
+                    packet[4..]
+                        .chunks_exact(4)
+                        .take(count)
+                        .map(|s| u32::from_be_bytes([s[0], s[1], s[2], s[3]]))
+                );
+            }
+            _ => {}
+        }
+        buf = &buf[len..];
+    }
+    news
+}
+
+// ── Receiver ─────────────────────────────────────────────────────────
+
+/// One SSRC's session.
+struct RtpStream {
+    /// Where its RTP comes from (the OpenAI / session-log key)
+    addr: SocketAddr,
+    /// Where its receiver reports go
+    rtcp_addr: SocketAddr,
+    codec: Codec,
+    opus: Option<opus::Decoder>,
+    reception: Reception,
+    started: Instant,
+    last_packet: Instant,
+    audio_bytes: u64,
+    openai: Option<AudioLink>,
+}
+
+/// Everything the RTP receiver feeds.
+pub struct RtpIngest {
+    pub tx: ShardedSender,
+    pub stats: Arc<Stats>,
+    pub realtime: RealtimeBridge,
+    pub session_logs: SessionLogs,
+    pub recording_policies: RecordingPolicies,
+    pub clock: SharedClock,
+}
+
+struct Receiver {
+    payload_types: PayloadTypes,
+    ingest: RtpIngest,
+    tenant: TenantId,
+    /// Our SSRC in receiver reports
+    reporter_ssrc: u32,
+    idle: Duration,
+    /// Attach streams to OpenAI (transcription mode only)
+    transcribe: bool,
+    streams: HashMap<u32, RtpStream>,
+}
+
+/// Bind `--rtp-port` (and the RTCP port above it) and start receiving;
+/// None when RTP is off.
+pub async fn spawn(config: &Config, ingest: RtpIngest) -> anyhow::Result<Option<tokio::task::JoinHandle<()>>> {
+    if config.rtp_port == 0 {
+        return Ok(None);
+    }
+    let bind = |port: u16| {
+        let addr = format!("{}:{port}", config.host);
+        async move { config.sockets.udp(&addr, || UdpSocket::bind((config.host.as_str(), port))).await }
+    };
+    let rtp = bind(config.rtp_port).await?;
+    let rtcp = bind(config.rtp_port + 1).await?;
+    let transcribe = ingest.realtime.enabled() && !ingest.realtime.responds();
+    if ingest.realtime.responds() {
+        info!("RTP streams are not sent to OpenAI in conversation mode (no return path for replies)");
+    }
+    info!(
+        rtp = %rtp.local_addr()?,
+        rtcp = %rtcp.local_addr()?,
+        l16_pt = config.rtp_l16_pt,
+        opus_pt = config.rtp_opus_pt,
+        tenant = %config.tenant_id,
+        "📞 RTP ingest bound"
+    );
+    let receiver = Receiver {
+        payload_types: PayloadTypes::from_config(config),
+        tenant: TenantId::from(config.tenant_id.as_str()),
+        reporter_ssrc: std::process::id().rotate_left(16) ^ config.rtp_port as u32,
+        idle: Duration::from_millis(config.rtp_idle_ms),
+        transcribe,
+        streams: HashMap::new(),
+        ingest,
+    };
+    Ok(Some(tokio::spawn(receiver.run(rtp, rtcp))))
+}
+
+impl Receiver {
+    async fn run(mut self, rtp: UdpSocket, rtcp: UdpSocket) {
+        let mut rtp_buf = vec![0u8; 65_536];
+        let mut rtcp_buf = vec![0u8; 2048];
+        let clock = self.ingest.clock.clone();
+        let mut last_reports = clock.now();
+        loop {
+            tokio::select! {
+                received = rtp.recv_from(&mut rtp_buf) => match received {
+                    Ok((len, src)) if is_rtcp(&rtp_buf[..len]) => self.on_rtcp(&rtp_buf[..len], src).await,
+                    Ok((len, src)) => self.on_rtp(&rtp_buf[..len], src).await,
+                    Err(e) => {
+                        warn!(error = %e, "RTP recv error");
+                        self.ingest.stats.record_recv_error();
+                    }
+                },
+                received = rtcp.recv_from(&mut rtcp_buf) => match received {
+                    Ok((len, src)) => self.on_rtcp(&rtcp_buf[..len], src).await,
+                    Err(e) => debug!(error = %e, "RTCP recv error"),
+                },
+                _ = clock.sleep(Duration::from_millis(250)) => {}
+            }
+
+            let now = clock.now();
+            let idle: Vec<u32> = self.streams
+                .iter()
+                .filter(|(_, s)| now.saturating_duration_since(s.last_packet) >= self.idle)
+                .map(|(ssrc, _)| *ssrc)
+                .collect();
+            for ssrc in idle {
+                self.end(ssrc, "rtp_idle").await;
+            }
+            if now.saturating_duration_since(last_reports) >= RTCP_INTERVAL {
+                last_reports = now;
+                self.send_reports(&rtcp, now).await;
+            }
+        }
+    }
+
+    async fn on_rtp(&mut self, data: &[u8], src: SocketAddr) {
+        let stats = self.ingest.stats.clone();
+        stats.record_recv(data.len());
+        let Some(packet) = RtpPacket::parse(data) else {
+            stats.record_parse_error();
+            return;
+        };
+        let Some(codec) = self.payload_types.codec(packet.payload_type) else {
+            debug!(src = %src, pt = packet.payload_type, "RTP payload type not accepted");
+            stats.record_parse_error();
+            return;
+        };
+        if !self.streams.contains_key(&packet.ssrc) {
+            self.start(&packet, codec, src).await;
+        }
+        let now = self.ingest.clock.now();
+        let Some(stream) = self.streams.get_mut(&packet.ssrc) else {
+            return;
+        };
+        stream.last_packet = now;
+        let arrival = (now.saturating_duration_since(stream.started).as_secs_f64() * codec.clock_rate() as f64) as i64;
+        stream.reception.update(packet.seq, packet.timestamp, arrival);
+
+        let pcm = match (codec, stream.opus.as_mut()) {
+            (Codec::L16 { rate, channels }, _) => decode_l16(packet.payload, rate, channels),
+            (Codec::Opus, Some(decoder)) =>
+                match decoder.decode(packet.payload) {
+                    Ok(pcm) => pcm,
+                    Err(e) => {
+                        debug!(ssrc = packet.ssrc, error = %e, "Opus packet not decoded");
+                        stats.record_parse_error();
+                        return;
+                    }
+                }
+            (Codec::Opus, None) => {
+                stats.record_parse_error();
+                return;
+            }
+        };
+        if pcm.is_empty() {
+            return;
+        }
+        stream.audio_bytes += pcm.len() as u64;
+        if let Some(ref mut openai) = stream.openai {
+            openai.forward(stream.addr, &pcm);
+        }
+        let sensor_pkt = SensorPacket {
+            sensor_id: packet.ssrc,
+            timestamp_us: clock::unix_micros(self.ingest.clock.as_ref()),
+            data_type: DATA_TYPE_AUDIO,
+            sample_format: SampleFormat::S16,
+            seq: stream.reception.extended_max() as u64,
+            payload: pcm,
+            tenant: self.tenant.clone(),
+            ingested_at: None,
+            trace: None,
+        };
+        if self.ingest.tx.try_send(sensor_pkt).is_err() {
+            stats.record_channel_drop();
+        }
+    }
+
+    async fn start(&mut self, packet: &RtpPacket<'_>, codec: Codec, src: SocketAddr) {
+        let opus = match codec {
+            Codec::Opus =>
+                match opus::Decoder::new() {
+                    Ok(decoder) => Some(decoder),
+                    Err(e) => {
+                        warn!(src = %src, ssrc = packet.ssrc, error = %e, "Opus RTP stream refused");
+                        None
+                    }
+                }
+            Codec::L16 { .. } => None,
+        };
+        let device = format!("rtp-{:08x}", packet.ssrc);
+        let openai = if self.transcribe { self.ingest.realtime.attach(src, &device).await } else { None };
+        let keep_log = self.ingest.recording_policies.policy(&device).keeps_session_log();
+        self.ingest.session_logs.open(src, packet.ssrc, &device, keep_log);
+        self.ingest.session_logs.event(src, "rtp_start", json!({ "ssrc": packet.ssrc, "pt": packet.payload_type }));
+        info!(src = %src, ssrc = format!("{:08x}", packet.ssrc), codec = ?codec, openai = openai.is_some(), "📞 RTP stream started");
+        let now = self.ingest.clock.now();
+        self.streams.insert(packet.ssrc, RtpStream {
+            addr: src,
+            rtcp_addr: SocketAddr::new(src.ip(), src.port().wrapping_add(1)),
+            codec,
+            opus,
+            reception: Reception::new(packet.seq),
+            started: now,
+            last_packet: now,
+            audio_bytes: 0,
+            openai,
+        });
+    }
+
+    async fn end(&mut self, ssrc: u32, reason: &str) {
+        let Some(stream) = self.streams.remove(&ssrc) else {
+            return;
+        };
+        let secs = stream.audio_bytes as f64 / (2 * OUTPUT_RATE) as f64;
+        if stream.openai.is_some() && stream.audio_bytes > 0 {
+            self.ingest.realtime.finish(stream.addr, stream.openai, secs).await;
+        } else if stream.openai.is_some() {
+            self.ingest.realtime.cancel(stream.addr).await;
+        }
+        let fields = json!({
+            "ssrc": ssrc,
+            "packets": stream.reception.received,
+            "lost": stream.reception.lost(),
+            "jitter": stream.reception.jitter(),
+            "audio_secs": secs,
+        });
+        info!(src = %stream.addr, ssrc = format!("{ssrc:08x}"), reason, codec = ?stream.codec,
+              packets = stream.reception.received, lost = stream.reception.lost(), "📴 RTP stream ended");
+        self.ingest.session_logs.close(stream.addr, reason, fields);
+    }
+
+    async fn on_rtcp(&mut self, data: &[u8], src: SocketAddr) {
+        let news = parse_rtcp(data);
+        let now = self.ingest.clock.now();
+        for (ssrc, ntp_middle) in news.sender_reports {
+            if let Some(stream) = self.streams.get_mut(&ssrc) {
+                stream.reception.sender_report(ntp_middle, now);
+                // Reports go where the sender's RTCP comes from
+                stream.rtcp_addr = src;
+            }
+        }
+        for ssrc in news.byes {
+            self.end(ssrc, "rtp_bye").await;
+        }
+    }
+
+    async fn send_reports(&mut self, rtcp: &UdpSocket, now: Instant) {
+        for (ssrc, stream) in &mut self.streams {
+            let block = stream.reception.report_block(*ssrc, now);
+            let report = receiver_report(self.reporter_ssrc, &block, "vad-sensor-bridge");
+            if let Err(e) = rtcp.send_to(&report, stream.rtcp_addr).await {
+                debug!(addr = %stream.rtcp_addr, error = %e, "RTCP receiver report not sent");
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rtp(pt: u8, seq: u16, ssrc: u32, payload: &[u8]) -> Vec<u8> {
+        let mut buf = vec![0x80, pt];
+        buf.extend(seq.to_be_bytes());
+        buf.extend((seq as u32 * 160).to_be_bytes());
+        buf.extend(ssrc.to_be_bytes());
+        buf.extend(payload);
+        buf
+    }
+
+    #[test]
+    fn test_loss_and_jitter_reach_the_receiver_report() {
+        // CSRC, extension and padding are stripped
+        let mut buf = vec![0xb1, 96, 0, 1, 0, 0, 0, 0, 0, 0, 0, 7];
+        buf.extend([0; 4]); // one CSRC
+        buf.extend([0xbe, 0xde, 0, 1, 1, 2, 3, 4]); // one-word extension
+        buf.extend([0x12, 0x34, 0, 0, 3]); // payload, 3 padding bytes (count included)
+        let packet = RtpPacket::parse(&buf).unwrap();
+        assert_eq!((packet.ssrc, packet.payload), (7, &[0x12, 0x34][..]));
+        assert!(RtpPacket::parse(&[0x40; 12]).is_none(), "RTP v1");
+
+        // L16 is big-endian; the first channel of stereo is kept
+        assert_eq!(decode_l16(&[0x12, 0x34, 0x56, 0x78], 16_000, 2), [0x34, 0x12]);
+
+        // seq 65534, 65535, 1, 2 (0 lost) across the wraparound
+        let start = Instant::now();
+        let mut reception = Reception::new(65534);
+        for (i, seq) in [65534u16, 65535, 1, 2].into_iter().enumerate() {
+            let pkt = rtp(96, seq, 9, &[]);
+            let parsed = RtpPacket::parse(&pkt).unwrap();
+            // Every packet arrives 40 units later than its timestamp
+            reception.update(parsed.seq, parsed.timestamp, parsed.timestamp as i64 + 40 * i as i64);
+        }
+        assert_eq!((reception.extended_max(), reception.expected(), reception.lost()), (0x1_0002, 5, 1));
+        assert!(reception.jitter() > 0);
+
+        reception.sender_report(0xaabb_ccdd, start);
+        let block = reception.report_block(9, start + Duration::from_secs(1));
+        assert_eq!(block[4], 51, "fraction lost: 1 of 5 in 1/256ths");
+        assert_eq!(&block[5..8], &[0, 0, 1], "cumulative lost");
+        assert_eq!(u32::from_be_bytes(block[16..20].try_into().unwrap()), 0xaabb_ccdd);
+        assert_eq!(u32::from_be_bytes(block[20..24].try_into().unwrap()), 65536, "1 s since the SR");
+        // A new interval with nothing lost
+        assert_eq!(reception.report_block(9, start)[4], 0);
+
+        let report = receiver_report(1, &block, "bridge");
+        assert_eq!((report[1], report.len() % 4), (RTCP_RR, 0));
+        assert!(is_rtcp(&report));
+        let bye = [0x81, RTCP_BYE, 0, 1, 0, 0, 0, 9];
+        let mut compound = report.clone();
+        compound.extend(bye);
+        assert_eq!(parse_rtcp(&compound), RtcpNews { sender_reports: vec![], byes: vec![9] });
+    }
+}
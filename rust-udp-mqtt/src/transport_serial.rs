@@ -0,0 +1,362 @@
+use crate::config::Config;
+use crate::esp_audio_protocol::{ EspPacket, PKT_AUDIO_UP };
+use crate::pcm::SampleFormat;
+use crate::sensor::{ SensorPacket, DATA_TYPE_AUDIO };
+use crate::shard::ShardedSender;
+use crate::stats::Stats;
+use crate::tenants::TenantId;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{ Hash, Hasher };
+use std::sync::Arc;
+use tracing::debug;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Serial / UART transport (`--serial`, `--features serial`)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  For benchtop robots and wired installations where Wi-Fi is not
+//  allowed.  Each `--serial PATH[:BAUD][:sensor|esp]` opens one port
+//  (default 921600 baud, sensor packets) next to the UDP transport:
+//
+//    sensor   every frame is one binary sensor packet (the sensor-port
+//             wire format)
+//    esp      every frame is one ESP audio-protocol packet; AUDIO_UP
+//             payloads (in `--esp-sample-format`, mono) go to the audio
+//             VAD lane under a sensor_id hashed from the port path
+//
+//  A byte stream has no packet boundaries, so frames are SLIP-encoded
+//  (RFC 1055):
+//
+//    END 0xC0 terminates a frame; ESC 0xDB escapes it inside data
+//    (0xC0 → DB DC, 0xDB → DB DD)
+//
+//  Line noise or a reset node costs at most the frame in flight: the
+//  next END resynchronises.  A port that goes away (cable pulled, USB
+//  re-enumerated) is reopened every few seconds.  Like the local
+//  inputs, serial nodes only ingest — sessions, recordings and OpenAI
+//  stay on the UDP ESP port, and results leave through the usual
+//  outputs.  Packets are dropped when the pipeline is full: a UART
+//  cannot be paused.
+
+/// Baud rate when the spec does not name one.
+pub const DEFAULT_BAUD: u32 = 921_600;
+
+/// Largest decoded frame; longer ones are discarded as noise.
+pub const MAX_FRAME: usize = crate::transport_local::MAX_FRAME;
+
+const SLIP_END: u8 = 0xc0;
+const SLIP_ESC: u8 = 0xdb;
+const SLIP_ESC_END: u8 = 0xdc;
+const SLIP_ESC_ESC: u8 = 0xdd;
+
+/// What the frames on a port carry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialFraming {
+    Sensor,
+    Esp,
+}
+
+/// One `--serial` port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialSpec {
+    pub path: String,
+    pub baud: u32,
+    pub framing: SerialFraming,
+}
+
+impl SerialSpec {
+    /// Parse `PATH[:BAUD][:sensor|esp]`.
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let mut parts: Vec<&str> = spec.split(':').collect();
+        let framing = match parts[1..].last() {
+            Some(&"esp") => SerialFraming::Esp,
+            Some(&"sensor") => SerialFraming::Sensor,
+            _ => {
+                parts.push("sensor");
+                SerialFraming::Sensor
+            }
+        };
+        let (path, baud) = match parts[..] {
+            [path, _] => (path, DEFAULT_BAUD),
+            [path, baud, _] => (path, baud.parse().map_err(|_| anyhow::anyhow!("--serial {spec}: bad baud rate {baud:?}"))?),
+            _ => anyhow::bail!("--serial {spec}: expected PATH[:BAUD][:sensor|esp]"),
+        };
+        if path.is_empty() {
+            anyhow::bail!("--serial {spec}: missing port path");
+        }
+        let path = path.to_string();
+        Ok(SerialSpec { path, baud, framing })
+    }
+}
+
+/// Whether this build can open the configured ports.
+pub fn check(config: &Config) -> anyhow::Result<()> {
+    if !config.serial.is_empty() && !cfg!(feature = "serial") {
+        anyhow::bail!("--serial requires a build with `--features serial`");
+    }
+    for spec in &config.serial {
+        SerialSpec::parse(spec)?;
+    }
+    Ok(())
+}
+
+/// Incremental SLIP decoder.
+#[derive(Default)]
+pub struct SlipDecoder {
+    frame: Vec<u8>,
+    escaped: bool,
+    /// The frame in flight outgrew `MAX_FRAME`; skip to the next END
+    overflow: bool,
+}
+
+impl SlipDecoder {
+    /// Feed received bytes; `on_frame` sees every completed frame.
+    pub fn push(&mut self, bytes: &[u8], mut on_frame: impl FnMut(&[u8])) {
+        for &byte in bytes {
+            match byte {
+                SLIP_END => {
+                    // Back-to-back ENDs (line idle / resync) are empty frames
+                    if !self.overflow && !self.frame.is_empty() {
+                        on_frame(&self.frame);
+                    }
+                    self.frame.clear();
+                    self.escaped = false;
+                    self.overflow = false;
+                    continue;
+                }
+                SLIP_ESC if !self.escaped => {
+                    self.escaped = true;
+                    continue;
+                }
+                _ => {}
+            }
+            let byte = match (self.escaped, byte) {
+                (true, SLIP_ESC_END) => SLIP_END,
+                (true, SLIP_ESC_ESC) => SLIP_ESC,
+                // Protocol violation: keep the byte, the parser decides
+                (_, byte) => byte,
+            };
+            self.escaped = false;
+            if self.frame.len() == MAX_FRAME {
+                self.overflow = true;
+            } else if !self.overflow {
+                self.frame.push(byte);
+            }
+        }
+    }
+}
+
+/// SLIP-encode one frame (what a serial node sends).
+pub fn slip_encode(frame: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(frame.len() + 2);
+    out.push(SLIP_END);
+    for &byte in frame {
+        match byte {
+            SLIP_END => out.extend([SLIP_ESC, SLIP_ESC_END]),
+            SLIP_ESC => out.extend([SLIP_ESC, SLIP_ESC_ESC]),
+            byte => out.push(byte),
+        }
+    }
+    out.push(SLIP_END);
+    out
+}
+
+/// Turns the frames of one port into queued work.
+#[derive(Clone)]
+pub struct SerialIngress {
+    framing: SerialFraming,
+    /// Audio sensor_id of an `esp` port
+    sensor_id: u32,
+    sample_format: SampleFormat,
+    tx: ShardedSender,
+    stats: Arc<Stats>,
+    tenant: TenantId,
+    clock: crate::clock::SharedClock,
+}
+
+impl SerialIngress {
+    pub fn new(spec: &SerialSpec, config: &Config, tx: ShardedSender, stats: Arc<Stats>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        spec.path.hash(&mut hasher);
+        SerialIngress {
+            framing: spec.framing,
+            sensor_id: (hasher.finish() & 0xffff_ffff) as u32,
+            sample_format: config.esp_sample_format,
+            tx,
+            stats,
+            tenant: TenantId::from(config.tenant_id.as_str()),
+            clock: crate::clock::system(),
+        }
+    }
+
+    /// Parse and queue one decoded frame, dropping it when the pipeline
+    /// is full.
+    pub fn handle(&self, frame: &[u8]) {
+        self.stats.record_recv(frame.len());
+        let packet = match self.framing {
+            SerialFraming::Sensor => SensorPacket::parse(frame),
+            SerialFraming::Esp => {
+                let Some(esp) = EspPacket::parse(frame) else {
+                    self.stats.record_parse_error();
+                    return;
+                };
+                let Some(packet) = self.esp_audio(esp) else {
+                    return;
+                };
+                Some(packet)
+            }
+        };
+        let Some(mut packet) = packet else {
+            self.stats.record_parse_error();
+            return;
+        };
+        // Tenant is decided by the ingress, never by the packet
+        packet.tenant = self.tenant.clone();
+        if self.tx.try_send(packet).is_err() {
+            self.stats.record_channel_drop();
+        }
+    }
+
+    /// The audio-lane packet of an ESP frame (None for other types).
+    fn esp_audio(&self, esp: EspPacket) -> Option<SensorPacket> {
+        if esp.pkt_type != PKT_AUDIO_UP {
+            debug!(pkt_type = esp.pkt_type, "serial ESP packet ignored — only audio is ingested");
+            return None;
+        }
+        let payload = self.sample_format.to_s16le(&esp.payload).into_owned();
+        if payload.is_empty() {
+            return None;
+        }
+        Some(SensorPacket {
+            sensor_id: self.sensor_id,
+            timestamp_us: crate::clock::unix_micros(self.clock.as_ref()),
+            data_type: DATA_TYPE_AUDIO,
+            sample_format: SampleFormat::S16,
+            seq: esp.seq_num as u64,
+            payload,
+            tenant: self.tenant.clone(),
+            ingested_at: None,
+            trace: None,
+        })
+    }
+}
+
+#[cfg(feature = "serial")]
+pub use port::spawn;
+
+/// Without `--features serial`, [`check`] has already refused `--serial`.
+#[cfg(not(feature = "serial"))]
+pub fn spawn(_config: &Config, _tx: ShardedSender, _stats: Arc<Stats>) -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[cfg(feature = "serial")]
+mod port {
+    use super::{ SerialIngress, SerialSpec, SlipDecoder };
+    use crate::config::Config;
+    use crate::shard::ShardedSender;
+    use crate::stats::Stats;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio_serial::SerialPortBuilderExt;
+    use tracing::{ info, warn };
+
+    /// Delay before reopening a port that failed or went away.
+    const REOPEN_DELAY: Duration = Duration::from_secs(3);
+
+    /// Start one reader per `--serial` port (no-op when none is set).
+    pub fn spawn(config: &Config, tx: ShardedSender, stats: Arc<Stats>) -> anyhow::Result<()> {
+        for spec in &config.serial {
+            let spec = SerialSpec::parse(spec)?;
+            let ingress = SerialIngress::new(&spec, config, tx.clone(), stats.clone());
+            tokio::spawn(read_loop(spec, ingress));
+        }
+        Ok(())
+    }
+
+    async fn read_loop(spec: SerialSpec, ingress: SerialIngress) {
+        let mut buf = vec![0u8; 4096];
+        loop {
+            let mut port = match tokio_serial::new(&spec.path, spec.baud).open_native_async() {
+                Ok(port) => port,
+                Err(e) => {
+                    warn!(path = %spec.path, error = %e, "serial port unavailable — retrying");
+                    tokio::time::sleep(REOPEN_DELAY).await;
+                    continue;
+                }
+            };
+            info!(path = %spec.path, baud = spec.baud, framing = ?spec.framing,
+                  tenant = %ingress.tenant, "🔌 serial port open");
+            // A fresh decoder: whatever was in flight before is lost
+            let mut decoder = SlipDecoder::default();
+            loop {
+                match port.read(&mut buf).await {
+                    Ok(0) => {
+                        warn!(path = %spec.path, "serial port closed");
+                        break;
+                    }
+                    Ok(n) => decoder.push(&buf[..n], |frame| ingress.handle(frame)),
+                    Err(e) => {
+                        warn!(path = %spec.path, error = %e, "serial read error");
+                        ingress.stats.record_recv_error();
+                        break;
+                    }
+                }
+            }
+            tokio::time::sleep(REOPEN_DELAY).await;
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Cli;
+    use crate::sensor::{ SensorVector, DATA_TYPE_SENSOR_VECTOR, HEADER_SIZE };
+    use crate::shard::{ self, LaneConfig };
+    use clap::Parser;
+
+    #[test]
+    fn test_slip_frames_survive_noise_and_reach_the_workers() {
+        assert_eq!(
+            SerialSpec::parse("/dev/ttyUSB0:115200:esp").unwrap(),
+            SerialSpec { path: "/dev/ttyUSB0".into(), baud: 115_200, framing: SerialFraming::Esp }
+        );
+        assert_eq!(SerialSpec::parse("/dev/ttyACM1").unwrap().baud, DEFAULT_BAUD);
+        assert!(SerialSpec::parse("/dev/ttyACM1:fast").is_err());
+
+        let config = Cli::parse_from(["vad-sensor-bridge"]).serve;
+        let lane = LaneConfig { workers: 1, capacity: 8 };
+        let (tx, mut rxs) = shard::channels(lane, lane, crate::clock::system());
+        let stats = Stats::new();
+        let spec = SerialSpec::parse("/dev/ttyUSB0").unwrap();
+        let ingress = SerialIngress::new(&spec, &config, tx, stats.clone());
+        let (_, mut sensor_rx) = rxs.pop().unwrap();
+
+        // Sensor ids 0xC0 and 0xDB need escaping on the wire
+        let packet = |sensor_id| SensorPacket {
+            data_type: DATA_TYPE_SENSOR_VECTOR,
+            sensor_id,
+            payload: SensorVector::default().to_payload(),
+            ..SensorPacket::parse(&[0u8; HEADER_SIZE]).unwrap()
+        }.to_binary();
+        let mut wire = b"\x13\x37 boot noise".to_vec();
+        wire.extend(slip_encode(&packet(0xc0)));
+        wire.extend(slip_encode(&packet(0xdb)));
+
+        // Delivered one byte at a time, as a slow UART would
+        let mut decoder = SlipDecoder::default();
+        for byte in &wire {
+            decoder.push(std::slice::from_ref(byte), |frame| ingress.handle(frame));
+        }
+        assert_eq!(sensor_rx.try_recv().unwrap().sensor_id, 0xc0);
+        assert_eq!(sensor_rx.try_recv().unwrap().sensor_id, 0xdb);
+        // The noise before the first END was one unparseable frame
+        assert_eq!(stats.totals().parse_errors, 1);
+    }
+}
@@ -0,0 +1,873 @@
+use crate::bridge_openai::{ AudioLink, RealtimeBridge };
+use crate::capture::CapturedSocket;
+use crate::clock::SharedClock;
+use crate::config::Config;
+use crate::esp_audio_protocol::{ build_audio_down, build_control, CTRL_STREAM_END, ESP_MAX_PAYLOAD };
+use crate::pcm::G711;
+use crate::transport_openai::resample;
+use crate::transport_rtp::RtpPacket;
+use md5::{ Digest, Md5 };
+use std::collections::VecDeque;
+use std::net::{ IpAddr, SocketAddr };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tracing::{ debug, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  SIP endpoint (`--sip-port`) — phone the robot
+// ─────────────────────────────────────────────────────────────────────
+//
+//  A minimal SIP user agent (RFC 3261, UDP only) so support can dial in
+//  from any phone on the PBX:
+//
+//    REGISTER   to --sip-registrar as --sip-user (digest auth), renewed
+//               before --sip-register-secs run out
+//    INVITE     answered at once with G.711 (PCMU or PCMA, whichever
+//               the caller offers first); one call at a time, others
+//               get 486 Busy Here
+//    BYE        ends the call; so does 30 s without caller media (we
+//               send BYE then)
+//    OPTIONS    200 (PBX keepalives)
+//
+//  During a call the caller is the OpenAI Realtime session's active
+//  device (`sip:<caller>` in prompts and conversation history): their
+//  audio is resampled to 16 kHz and streamed in, server VAD takes turns,
+//  and the spoken replies come back over the call's RTP leg (20 ms
+//  frames from --sip-rtp-port) instead of AUDIO_DOWN.  With
+//  --sip-speaker the caller's voice is also played on that ESP's
+//  speaker, so support can talk through the robot.
+//
+//  With a registrar, INVITEs are only accepted from its address.
+//  Without one the bridge takes direct calls (sip:<user>@<ip>:<port>)
+//  from anyone who can reach the port — keep it on a private network.
+
+const USER_AGENT: &str = "vad-sensor-bridge";
+const ALLOW: &str = "INVITE, ACK, BYE, CANCEL, OPTIONS";
+
+/// Retry delay after a failed or unanswered REGISTER.
+const REGISTER_RETRY: Duration = Duration::from_secs(30);
+
+/// A call without caller media for this long is hung up.
+const MEDIA_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// G.711 runs at 8 kHz; one RTP frame is 20 ms.
+const CALL_RATE: u64 = 8_000;
+const FRAME_SAMPLES: usize = 160;
+const FRAME: Duration = Duration::from_millis(20);
+
+/// Maps a device name (MAC or `ip:port`) to its ESP audio address.
+pub type DeviceResolver = Arc<dyn Fn(&str) -> Option<SocketAddr> + Send + Sync>;
+
+/// What a call can reach.
+pub struct SipContext {
+    pub realtime: RealtimeBridge,
+    /// The ESP audio socket (AUDIO_DOWN to --sip-speaker)
+    pub audio_socket: Arc<CapturedSocket>,
+    pub resolve: DeviceResolver,
+    pub clock: SharedClock,
+}
+
+/// Whether the SIP flags make sense together.
+pub fn check(config: &Config) -> anyhow::Result<()> {
+    if config.sip_port != 0 && !config.openai_realtime && config.sip_speaker.is_empty() {
+        anyhow::bail!("--sip-port needs --openai-realtime or --sip-speaker, or calls would go nowhere");
+    }
+    Ok(())
+}
+
+// ── SIP messages ─────────────────────────────────────────────────────
+
+/// One SIP request or response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SipMessage {
+    /// Request line or status line
+    pub start: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// Full name of a compact header form (RFC 3261 §7.3.3).
+fn full_name(name: &str) -> &str {
+    match name {
+        "v" | "V" => "Via",
+        "f" | "F" => "From",
+        "t" | "T" => "To",
+        "i" | "I" => "Call-ID",
+        "m" | "M" => "Contact",
+        "l" | "L" => "Content-Length",
+        "c" | "C" => "Content-Type",
+        name => name,
+    }
+}
+
+impl SipMessage {
+    pub fn request(method: &str, uri: &str) -> Self {
+        SipMessage { start: format!("{method} {uri} SIP/2.0"), headers: Vec::new(), body: String::new() }
+    }
+
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let text = std::str::from_utf8(data).ok()?;
+        let (head, body) = text.split_once("\r\n\r\n").unwrap_or((text, ""));
+        let mut lines = head.split("\r\n");
+        let start = lines.next()?.trim().to_string();
+        if !start.ends_with("SIP/2.0") && !start.starts_with("SIP/2.0 ") {
+            return None;
+        }
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in lines {
+            // Folded continuation line
+            if line.starts_with([' ', '\t']) {
+                let (_, value) = headers.last_mut()?;
+                value.push(' ');
+                value.push_str(line.trim());
+                continue;
+            }
+            let (name, value) = line.split_once(':')?;
+            headers.push((full_name(name.trim()).to_string(), value.trim().to_string()));
+        }
+        Some(SipMessage { start, headers, body: body.to_string() })
+    }
+
+    /// The method of a request (None for responses).
+    pub fn method(&self) -> Option<&str> {
+        if self.start.starts_with("SIP/2.0 ") {
+            return None;
+        }
+        self.start.split(' ').next()
+    }
+
+    /// The status code of a response.
+    pub fn status(&self) -> Option<u16> {
+        self.start.strip_prefix("SIP/2.0 ")?.split(' ').next()?.parse().ok()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    pub fn headers_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers
+            .iter()
+            .filter(move |(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn with(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// CSeq number and method.
+    pub fn cseq(&self) -> Option<(u32, &str)> {
+        let (number, method) = self.header("CSeq")?.split_once(' ')?;
+        Some((number.trim().parse().ok()?, method.trim()))
+    }
+
+    /// A response to this request: Via, From, To (tagged with `to_tag`
+    /// unless it is a 100 or already tagged), Call-ID and CSeq copied.
+    pub fn response(&self, status: u16, reason: &str, to_tag: &str) -> SipMessage {
+        let mut response = SipMessage {
+            start: format!("SIP/2.0 {status} {reason}"),
+            headers: Vec::new(),
+            body: String::new(),
+        };
+        for via in self.headers_named("Via") {
+            response = response.with("Via", via);
+        }
+        for name in ["From", "To", "Call-ID", "CSeq"] {
+            let Some(mut value) = self.header(name).map(str::to_string) else {
+                continue;
+            };
+            if name == "To" && status > 100 && tag(&value).is_none() {
+                value = format!("{value};tag={to_tag}");
+            }
+            response = response.with(name, value);
+        }
+        response.with("Server", USER_AGENT)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = format!("{}\r\n", self.start);
+        for (name, value) in &self.headers {
+            out.push_str(&format!("{name}: {value}\r\n"));
+        }
+        out.push_str(&format!("Content-Length: {}\r\n\r\n", self.body.len()));
+        out.push_str(&self.body);
+        out.into_bytes()
+    }
+}
+
+/// The `tag` parameter of a From / To value.
+fn tag(value: &str) -> Option<&str> {
+    value
+        .split(';')
+        .skip(1)
+        .find_map(|param| param.trim().strip_prefix("tag="))
+}
+
+/// The URI of a name-addr (`"Bob" <sip:bob@host>;tag=1` → `sip:bob@host`).
+fn uri(value: &str) -> &str {
+    match (value.find('<'), value.find('>')) {
+        (Some(open), Some(close)) if open < close => &value[open + 1..close],
+        _ => value.split(';').next().unwrap_or(value).trim(),
+    }
+}
+
+/// The user part of a URI (`sip:bob@host` → `bob`).
+fn user_part(uri: &str) -> &str {
+    let uri = uri.trim_start_matches("sips:").trim_start_matches("sip:");
+    uri.split(['@', ';']).next().unwrap_or(uri)
+}
+
+/// A fresh random-looking token (tags, branches, Call-IDs, cnonces).
+fn token() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = sha2::Sha256::new();
+    sha2::Digest::update(&mut hasher, std::process::id().to_le_bytes());
+    sha2::Digest::update(&mut hasher, COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+    let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos();
+    sha2::Digest::update(&mut hasher, nanos.to_le_bytes());
+    sha2::Digest::finalize(hasher)[..8].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+// ── Digest authentication (RFC 2617, MD5) ────────────────────────────
+
+/// A `WWW-Authenticate` / `Proxy-Authenticate` challenge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: Option<String>,
+    /// The server offers `qop=auth`
+    pub qop_auth: bool,
+}
+
+impl Challenge {
+    pub fn parse(header: &str) -> Option<Self> {
+        let params = header.trim().strip_prefix("Digest")?;
+        let mut realm = None;
+        let mut nonce = None;
+        let mut opaque = None;
+        let mut qop_auth = false;
+        for param in split_params(params) {
+            let Some((name, value)) = param.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches('"').to_string();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "realm" => realm = Some(value),
+                "nonce" => nonce = Some(value),
+                "opaque" => opaque = Some(value),
+                "qop" => qop_auth = value.split(',').any(|q| q.trim() == "auth"),
+                "algorithm" if !value.eq_ignore_ascii_case("MD5") => return None,
+                _ => {}
+            }
+        }
+        Some(Challenge { realm: realm?, nonce: nonce?, opaque, qop_auth })
+    }
+
+    /// The `Authorization` value answering this challenge.
+    pub fn authorize(&self, user: &str, password: &str, method: &str, uri: &str, cnonce: &str, nc: u32) -> String {
+        let md5 = |s: String| format!("{:x}", Md5::digest(s.as_bytes()));
+        let ha1 = md5(format!("{user}:{}:{password}", self.realm));
+        let ha2 = md5(format!("{method}:{uri}"));
+        let nc = format!("{nc:08x}");
+        let mut value = format!("Digest username=\"{user}\", realm=\"{}\", nonce=\"{}\", uri=\"{uri}\"", self.realm, self.nonce);
+        if self.qop_auth {
+            let response = md5(format!("{ha1}:{}:{nc}:{cnonce}:auth:{ha2}", self.nonce));
+            value.push_str(&format!(", response=\"{response}\", qop=auth, nc={nc}, cnonce=\"{cnonce}\""));
+        } else {
+            let response = md5(format!("{ha1}:{}:{ha2}", self.nonce));
+            value.push_str(&format!(", response=\"{response}\""));
+        }
+        if let Some(ref opaque) = self.opaque {
+            value.push_str(&format!(", opaque=\"{opaque}\""));
+        }
+        value.push_str(", algorithm=MD5");
+        value
+    }
+}
+
+/// Split `a="x,y", b=z` on the commas outside quotes.
+fn split_params(params: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in params.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                out.push(params[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(params[start..].trim());
+    out
+}
+
+// ── SDP (RFC 4566) ───────────────────────────────────────────────────
+
+/// The audio stream a caller offers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdpOffer {
+    pub media: SocketAddr,
+    pub payload_types: Vec<u8>,
+}
+
+impl SdpOffer {
+    pub fn parse(body: &str) -> Option<Self> {
+        let mut ip: Option<IpAddr> = None;
+        let mut audio: Option<(u16, Vec<u8>)> = None;
+        for line in body.lines() {
+            if let Some(connection) = line.strip_prefix("c=") {
+                // c=IN IP4 192.0.2.1 (session or media level; the last wins)
+                ip = connection.split_whitespace().nth(2).and_then(|a| a.parse().ok()).or(ip);
+            } else if let Some(media) = line.strip_prefix("m=audio ") {
+                let mut fields = media.split_whitespace();
+                let port = fields.next()?.parse().ok()?;
+                let payload_types = fields.skip(1).filter_map(|pt| pt.parse().ok()).collect();
+                audio = Some((port, payload_types));
+            }
+        }
+        let (port, payload_types) = audio?;
+        Some(SdpOffer { media: SocketAddr::new(ip?, port), payload_types })
+    }
+
+    /// The first offered law we speak.
+    pub fn choose(&self) -> Option<G711> {
+        self.payload_types.iter().find_map(|&pt| G711::from_payload_type(pt))
+    }
+}
+
+/// Our answer: `law` at `ip:port`, 20 ms frames.
+pub fn sdp_answer(ip: IpAddr, port: u16, law: G711, session: u64) -> String {
+    let family = if ip.is_ipv4() { "IP4" } else { "IP6" };
+    let pt = law.payload_type();
+    format!(
+        "v=0\r\no=- {session} {session} IN {family} {ip}\r\ns={USER_AGENT}\r\nc=IN {family} {ip}\r\nt=0 0\r\n\
+         m=audio {port} RTP/AVP {pt}\r\na=rtpmap:{pt} {}/8000\r\na=ptime:20\r\na=sendrecv\r\n",
+        law.name()
+    )
+}
+
+// ── User agent ───────────────────────────────────────────────────────
+
+/// REGISTER state.
+struct Registration {
+    registrar: SocketAddr,
+    domain: String,
+    call_id: String,
+    from_tag: String,
+    cseq: u32,
+    /// Challenge answered in the last REGISTER (a second 401 = bad password)
+    answered: bool,
+    registered: bool,
+    next_at: Instant,
+}
+
+/// The call in progress.
+struct Call {
+    call_id: String,
+    local_tag: String,
+    /// The INVITE's From / To (dialog identity for our BYE)
+    remote_from: String,
+    local_to: String,
+    remote_contact: String,
+    signal: SocketAddr,
+    /// Our 200 OK, resent when the INVITE is retransmitted
+    answer: Vec<u8>,
+    law: G711,
+    /// Identifies the call towards OpenAI (the SDP media address)
+    key: SocketAddr,
+    /// Where the caller's media comes from (latched, for NAT)
+    media: SocketAddr,
+    last_media: Instant,
+    openai: Option<AudioLink>,
+    speaker: Option<(SocketAddr, u16)>,
+    /// Reply audio waiting to go out, 8 kHz s16le
+    outgoing: VecDeque<u8>,
+    rtp_seq: u16,
+    rtp_timestamp: u32,
+    ssrc: u32,
+    talking: bool,
+}
+
+struct UserAgent {
+    sip: UdpSocket,
+    rtp: UdpSocket,
+    /// Advertised in Via / Contact / SDP
+    public_ip: IpAddr,
+    sip_port: u16,
+    rtp_port: u16,
+    user: String,
+    password: String,
+    register_secs: u32,
+    registration: Option<Registration>,
+    speaker: String,
+    ctx: SipContext,
+    call: Option<Call>,
+}
+
+/// Bind `--sip-port` / `--sip-rtp-port`, start registering and answer
+/// calls; None when SIP is off.
+pub async fn spawn(config: &Config, ctx: SipContext) -> anyhow::Result<Option<tokio::task::JoinHandle<()>>> {
+    if config.sip_port == 0 {
+        return Ok(None);
+    }
+    let bind = |port: u16| {
+        let addr = format!("{}:{port}", config.host);
+        async move { config.sockets.udp(&addr, || UdpSocket::bind((config.host.as_str(), port))).await }
+    };
+    let sip = bind(config.sip_port).await?;
+    let rtp = bind(config.sip_rtp_port).await?;
+
+    let registrar = match config.sip_registrar.as_str() {
+        "" => None,
+        registrar => {
+            let target = if registrar.contains(':') { registrar.to_string() } else { format!("{registrar}:5060") };
+            let addr = tokio::net::lookup_host(&target).await?.next()
+                .ok_or_else(|| anyhow::anyhow!("--sip-registrar {registrar}: no address"))?;
+            Some((addr, registrar.split(':').next().unwrap_or(registrar).to_string()))
+        }
+    };
+    let public_ip = match config.sip_public_ip.as_str() {
+        "" => local_ip(registrar.as_ref().map(|(addr, _)| *addr), &config.host).await,
+        ip => ip.parse()?,
+    };
+    let now = ctx.clock.now();
+    let ua = UserAgent {
+        public_ip,
+        sip_port: sip.local_addr()?.port(),
+        rtp_port: rtp.local_addr()?.port(),
+        sip,
+        rtp,
+        user: config.sip_user.clone(),
+        password: config.sip_password.clone(),
+        register_secs: config.sip_register_secs,
+        registration: registrar.map(|(registrar, domain)| Registration {
+            registrar,
+            domain,
+            call_id: format!("{}@{public_ip}", token()),
+            from_tag: token(),
+            cseq: 0,
+            answered: false,
+            registered: false,
+            next_at: now,
+        }),
+        speaker: config.sip_speaker.clone(),
+        ctx,
+        call: None,
+    };
+    info!(
+        sip = %ua.sip.local_addr()?,
+        rtp = %ua.rtp.local_addr()?,
+        contact = %ua.contact(),
+        registrar = %config.sip_registrar,
+        "☎️ SIP endpoint bound"
+    );
+    Ok(Some(tokio::spawn(ua.run())))
+}
+
+/// The address the OS would use towards `peer` (or the bind host).
+async fn local_ip(peer: Option<SocketAddr>, host: &str) -> IpAddr {
+    if let Some(peer) = peer {
+        if let Ok(probe) = UdpSocket::bind("0.0.0.0:0").await {
+            if probe.connect(peer).await.is_ok() {
+                if let Ok(local) = probe.local_addr() {
+                    return local.ip();
+                }
+            }
+        }
+    }
+    match host.parse::<IpAddr>() {
+        Ok(ip) if !ip.is_unspecified() => ip,
+        _ => IpAddr::from([127, 0, 0, 1]),
+    }
+}
+
+impl UserAgent {
+    fn contact(&self) -> String {
+        format!("<sip:{}@{}:{}>", self.user, self.public_ip, self.sip_port)
+    }
+
+    fn via(&self) -> String {
+        format!("SIP/2.0/UDP {}:{};branch=z9hG4bK{};rport", self.public_ip, self.sip_port, token())
+    }
+
+    async fn run(mut self) {
+        let mut sip_buf = vec![0u8; 65_536];
+        let mut rtp_buf = vec![0u8; 2048];
+        let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        let mut frames = tokio::time::interval(FRAME);
+        frames.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                received = self.sip.recv_from(&mut sip_buf) => match received {
+                    Ok((len, src)) => match SipMessage::parse(&sip_buf[..len]) {
+                        Some(msg) if msg.method().is_some() => self.on_request(msg, src, &reply_tx).await,
+                        Some(msg) => self.on_response(msg).await,
+                        None => debug!(src = %src, len, "not a SIP message"),
+                    },
+                    Err(e) => warn!(error = %e, "SIP recv error"),
+                },
+                received = self.rtp.recv_from(&mut rtp_buf) => match received {
+                    Ok((len, src)) => self.on_media(&rtp_buf[..len], src).await,
+                    Err(e) => debug!(error = %e, "call RTP recv error"),
+                },
+                Some(pcm) = reply_rx.recv() => {
+                    if let Some(ref mut call) = self.call {
+                        call.outgoing.extend(resample(&pcm, 16_000, CALL_RATE));
+                    }
+                }
+                _ = frames.tick() => self.tick().await,
+            }
+        }
+    }
+
+    /// Every 20 ms: one reply frame out, registration and media timeouts.
+    async fn tick(&mut self) {
+        let now = self.ctx.clock.now();
+        if let Some(ref mut call) = self.call {
+            if call.outgoing.is_empty() {
+                call.talking = false;
+            } else {
+                let take = call.outgoing.len().min(FRAME_SAMPLES * 2);
+                let mut pcm: Vec<u8> = call.outgoing.drain(..take).collect();
+                pcm.resize(FRAME_SAMPLES * 2, 0);
+                let packet = RtpPacket {
+                    payload_type: call.law.payload_type(),
+                    // First frame of a talkspurt
+                    marker: !call.talking,
+                    seq: call.rtp_seq,
+                    timestamp: call.rtp_timestamp,
+                    ssrc: call.ssrc,
+                    payload: &call.law.encode(&pcm),
+                }.to_bytes();
+                call.talking = true;
+                call.rtp_seq = call.rtp_seq.wrapping_add(1);
+                let _ = self.rtp.send_to(&packet, call.media).await;
+            }
+            // The RTP clock runs through silence too
+            call.rtp_timestamp = call.rtp_timestamp.wrapping_add(FRAME_SAMPLES as u32);
+
+            if now.saturating_duration_since(call.last_media) >= MEDIA_TIMEOUT {
+                warn!(call_id = %call.call_id, "☎️ no caller media — hanging up");
+                self.send_bye().await;
+                self.end_call("media timeout").await;
+            }
+        }
+        if self.registration.as_ref().is_some_and(|r| now >= r.next_at) {
+            self.register(None).await;
+        }
+    }
+
+    // ── Registration ─────────────────────────────────────────────────
+
+    /// Send a REGISTER (answering `challenge` if given).
+    async fn register(&mut self, challenge: Option<(&str, Challenge)>) {
+        let contact = self.contact();
+        let via = self.via();
+        let Some(ref mut reg) = self.registration else {
+            return;
+        };
+        reg.cseq += 1;
+        reg.next_at = self.ctx.clock.now() + REGISTER_RETRY;
+        reg.answered = challenge.is_some();
+        let aor = format!("<sip:{}@{}>", self.user, reg.domain);
+        let request_uri = format!("sip:{}", reg.domain);
+        let mut msg = SipMessage::request("REGISTER", &request_uri)
+            .with("Via", via)
+            .with("Max-Forwards", "70")
+            .with("From", format!("{aor};tag={}", reg.from_tag))
+            .with("To", aor)
+            .with("Call-ID", reg.call_id.clone())
+            .with("CSeq", format!("{} REGISTER", reg.cseq))
+            .with("Contact", contact)
+            .with("Expires", self.register_secs.to_string())
+            .with("User-Agent", USER_AGENT);
+        if let Some((header, challenge)) = challenge {
+            let auth = challenge.authorize(&self.user, &self.password, "REGISTER", &request_uri, &token(), 1);
+            msg = msg.with(header, auth);
+        }
+        let registrar = reg.registrar;
+        if let Err(e) = self.sip.send_to(&msg.to_bytes(), registrar).await {
+            warn!(registrar = %registrar, error = %e, "REGISTER not sent");
+        }
+    }
+
+    async fn on_response(&mut self, msg: SipMessage) {
+        let (Some(status), Some((_, method))) = (msg.status(), msg.cseq()) else {
+            return;
+        };
+        if method != "REGISTER" {
+            debug!(status, method, "SIP response");
+            return;
+        }
+        let Some(ref mut reg) = self.registration else {
+            return;
+        };
+        if msg.header("Call-ID") != Some(reg.call_id.as_str()) {
+            return;
+        }
+        match status {
+            100..=199 => {}
+            200..=299 => {
+                // The registrar may shorten the expiry
+                let expires = msg
+                    .header("Contact")
+                    .and_then(|c| c.split(';').find_map(|p| p.trim().strip_prefix("expires=")?.parse().ok()))
+                    .or_else(|| msg.header("Expires")?.parse().ok())
+                    .unwrap_or(self.register_secs)
+                    .max(60);
+                if !reg.registered {
+                    info!(registrar = %reg.registrar, user = %self.user, expires, "☎️ registered with the PBX");
+                }
+                reg.registered = true;
+                // Renew at 80 % of the expiry
+                reg.next_at = self.ctx.clock.now() + Duration::from_secs(expires as u64 * 4 / 5);
+            }
+            401 | 407 if !reg.answered => {
+                let (challenge_header, auth_header) = if status == 401 {
+                    ("WWW-Authenticate", "Authorization")
+                } else {
+                    ("Proxy-Authenticate", "Proxy-Authorization")
+                };
+                match msg.header(challenge_header).and_then(Challenge::parse) {
+                    Some(challenge) => self.register(Some((auth_header, challenge))).await,
+                    None => warn!(status, "unsupported SIP auth challenge — retrying later"),
+                }
+            }
+            _ => {
+                warn!(status, reason = %msg.start, registrar = %reg.registrar, "☎️ REGISTER refused — retrying later");
+                reg.registered = false;
+            }
+        }
+    }
+
+    // ── Requests ─────────────────────────────────────────────────────
+
+    async fn on_request(&mut self, msg: SipMessage, src: SocketAddr, replies: &mpsc::UnboundedSender<Vec<u8>>) {
+        let method = msg.method().unwrap_or_default().to_string();
+        let call_id = msg.header("Call-ID").unwrap_or_default().to_string();
+        let in_call = self.call.as_ref().is_some_and(|c| c.call_id == call_id);
+        let local_tag = self.call.as_ref().map_or_else(token, |c| c.local_tag.clone());
+        match method.as_str() {
+            "INVITE" if in_call => {
+                // Retransmission: our 200 OK was lost
+                let answer = self.call.as_ref().map(|c| c.answer.clone()).unwrap_or_default();
+                let _ = self.sip.send_to(&answer, src).await;
+            }
+            "INVITE" => self.on_invite(msg, src, replies).await,
+            "ACK" => {}
+            "BYE" if in_call => {
+                self.respond(&msg, 200, "OK", &local_tag, src).await;
+                self.end_call("caller hung up").await;
+            }
+            "BYE" => self.respond(&msg, 481, "Call/Transaction Does Not Exist", &local_tag, src).await,
+            // The call was answered at once, so there is nothing to cancel
+            "CANCEL" => self.respond(&msg, 200, "OK", &local_tag, src).await,
+            "OPTIONS" => {
+                let response = msg.response(200, "OK", &local_tag).with("Allow", ALLOW);
+                let _ = self.sip.send_to(&response.to_bytes(), src).await;
+            }
+            _ => {
+                let response = msg.response(405, "Method Not Allowed", &local_tag).with("Allow", ALLOW);
+                let _ = self.sip.send_to(&response.to_bytes(), src).await;
+            }
+        }
+    }
+
+    async fn respond(&self, msg: &SipMessage, status: u16, reason: &str, tag: &str, to: SocketAddr) {
+        let _ = self.sip.send_to(&msg.response(status, reason, tag).to_bytes(), to).await;
+    }
+
+    async fn on_invite(&mut self, msg: SipMessage, src: SocketAddr, replies: &mpsc::UnboundedSender<Vec<u8>>) {
+        let local_tag = token();
+        if self.registration.as_ref().is_some_and(|r| r.registrar.ip() != src.ip()) {
+            warn!(src = %src, "☎️ INVITE not from the registrar — refused");
+            self.respond(&msg, 403, "Forbidden", &local_tag, src).await;
+            return;
+        }
+        if self.call.is_some() {
+            self.respond(&msg, 486, "Busy Here", &local_tag, src).await;
+            return;
+        }
+        let Some((offer, law)) = SdpOffer::parse(&msg.body).and_then(|o| Some((o.clone(), o.choose()?))) else {
+            warn!(src = %src, "☎️ INVITE without a G.711 offer — refused");
+            let response = msg.response(488, "Not Acceptable Here", &local_tag).with("Accept", "application/sdp");
+            let _ = self.sip.send_to(&response.to_bytes(), src).await;
+            return;
+        };
+        self.respond(&msg, 100, "Trying", &local_tag, src).await;
+
+        let remote_from = msg.header("From").unwrap_or_default().to_string();
+        let caller = user_part(uri(&remote_from)).to_string();
+        let device = format!("sip:{caller}");
+        let key = offer.media;
+
+        // The caller becomes the OpenAI session's active device
+        let openai = self.ctx.realtime.attach(key, &device).await;
+        if openai.is_some() {
+            self.ctx.realtime.tap_replies(key, replies.clone());
+        }
+        let speaker = match self.speaker.as_str() {
+            "" => None,
+            device => {
+                let addr = (self.ctx.resolve)(device);
+                if addr.is_none() {
+                    warn!(speaker = %device, "☎️ --sip-speaker not connected — call audio only goes to OpenAI");
+                }
+                addr.map(|addr| (addr, 0))
+            }
+        };
+
+        let session = crate::clock::unix_micros(self.ctx.clock.as_ref()) / 1_000_000;
+        let answer = msg
+            .response(200, "OK", &local_tag)
+            .with("Contact", self.contact())
+            .with("Allow", ALLOW)
+            .with("Content-Type", "application/sdp");
+        let answer = SipMessage { body: sdp_answer(self.public_ip, self.rtp_port, law, session), ..answer }.to_bytes();
+        let _ = self.sip.send_to(&answer, src).await;
+        info!(caller = %caller, media = %offer.media, codec = law.name(), openai = openai.is_some(),
+              speaker = ?speaker.map(|(addr, _)| addr), "☎️ call answered");
+
+        let now = self.ctx.clock.now();
+        self.call = Some(Call {
+            call_id: msg.header("Call-ID").unwrap_or_default().to_string(),
+            local_to: format!("{};tag={local_tag}", msg.header("To").unwrap_or_default()),
+            local_tag,
+            remote_contact: msg.header("Contact").map(uri).unwrap_or_default().to_string(),
+            remote_from,
+            signal: src,
+            answer,
+            law,
+            key,
+            media: offer.media,
+            last_media: now,
+            openai,
+            speaker,
+            outgoing: VecDeque::new(),
+            rtp_seq: 0,
+            rtp_timestamp: 0,
+            ssrc: std::process::id().rotate_left(8) ^ key.port() as u32,
+            talking: false,
+        });
+    }
+
+    // ── Media ────────────────────────────────────────────────────────
+
+    async fn on_media(&mut self, data: &[u8], src: SocketAddr) {
+        let Some(ref mut call) = self.call else {
+            return;
+        };
+        let Some(packet) = RtpPacket::parse(data) else {
+            return;
+        };
+        if G711::from_payload_type(packet.payload_type) != Some(call.law) {
+            // DTMF events, comfort noise, ...
+            return;
+        }
+        call.media = src;
+        call.last_media = self.ctx.clock.now();
+        let pcm = resample(&call.law.decode(packet.payload), CALL_RATE, 16_000);
+        if let Some(ref mut openai) = call.openai {
+            openai.forward(call.key, &pcm);
+        }
+        if let Some((addr, ref mut seq)) = call.speaker {
+            for chunk in pcm.chunks(ESP_MAX_PAYLOAD) {
+                let _ = self.ctx.audio_socket.send_to(&build_audio_down(*seq, 0, chunk), addr).await;
+                *seq = seq.wrapping_add(1);
+            }
+        }
+    }
+
+    /// Hang up our side of the call (RFC 3261 §15.1.1).
+    async fn send_bye(&mut self) {
+        let via = self.via();
+        let Some(ref call) = self.call else {
+            return;
+        };
+        let target = if call.remote_contact.is_empty() { uri(&call.remote_from).to_string() } else { call.remote_contact.clone() };
+        let bye = SipMessage::request("BYE", &target)
+            .with("Via", via)
+            .with("Max-Forwards", "70")
+            .with("From", call.local_to.clone())
+            .with("To", call.remote_from.clone())
+            .with("Call-ID", call.call_id.clone())
+            .with("CSeq", "1 BYE")
+            .with("User-Agent", USER_AGENT);
+        let _ = self.sip.send_to(&bye.to_bytes(), call.signal).await;
+    }
+
+    async fn end_call(&mut self, reason: &str) {
+        let Some(call) = self.call.take() else {
+            return;
+        };
+        self.ctx.realtime.untap_replies(call.key);
+        if call.openai.is_some() {
+            // Stops a reply in progress and detaches the caller
+            self.ctx.realtime.cancel(call.key).await;
+        }
+        if let Some((addr, seq)) = call.speaker {
+            let _ = self.ctx.audio_socket.send_to(&build_control(seq, CTRL_STREAM_END, 0), addr).await;
+        }
+        info!(call_id = %call.call_id, reason, "☎️ call ended");
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invite_answer_and_digest_auth() {
+        // RFC 2617 §3.5 example
+        let challenge = Challenge::parse(
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#
+        ).unwrap();
+        assert!(challenge.qop_auth);
+        let auth = challenge.authorize("Mufasa", "Circle Of Life", "GET", "/dir/index.html", "0a4f113b", 1);
+        assert!(auth.contains(r#"response="6629fae49393a05397450978507c4ef1""#), "{auth}");
+        assert!(auth.contains("nc=00000001"));
+
+        let invite = SipMessage::parse(
+            b"INVITE sip:robot@10.0.0.5:5060 SIP/2.0\r\n\
+              v: SIP/2.0/UDP 10.0.0.1:5060;branch=z9hG4bK1\r\n\
+              Via: SIP/2.0/UDP 10.0.0.9;branch=z9hG4bK2\r\n\
+              f: \"Support\" <sip:2001@pbx>;tag=abc\r\n\
+              To: <sip:robot@pbx>\r\n\
+              Call-ID: 42@pbx\r\n\
+              CSeq: 7 INVITE\r\n\
+              Contact: <sip:2001@10.0.0.1:5060>\r\n\
+              Content-Type: application/sdp\r\n\r\n\
+              v=0\r\nc=IN IP4 10.0.0.1\r\nm=audio 18000 RTP/AVP 9 8 0 101\r\n"
+        ).unwrap();
+        assert_eq!((invite.method(), invite.cseq()), (Some("INVITE"), Some((7, "INVITE"))));
+        assert_eq!(user_part(uri(invite.header("From").unwrap())), "2001");
+
+        // G.722 (9) is skipped, PCMA is the first law we speak
+        let offer = SdpOffer::parse(&invite.body).unwrap();
+        assert_eq!(offer.media, "10.0.0.1:18000".parse().unwrap());
+        assert_eq!(offer.choose(), Some(G711::Alaw));
+        let answer = sdp_answer("10.0.0.5".parse().unwrap(), 40000, G711::Alaw, 1);
+        assert!(answer.contains("m=audio 40000 RTP/AVP 8\r\na=rtpmap:8 PCMA/8000"));
+
+        let ok = SipMessage::parse(&invite.response(200, "OK", "xyz").to_bytes()).unwrap();
+        assert_eq!(ok.status(), Some(200));
+        assert_eq!(ok.headers_named("Via").count(), 2, "every Via is echoed");
+        assert_eq!(tag(ok.header("To").unwrap()), Some("xyz"));
+        assert_eq!(tag(ok.header("From").unwrap()), Some("abc"));
+        // A 100 Trying is never tagged
+        assert_eq!(tag(invite.response(100, "Trying", "xyz").header("To").unwrap()), None);
+    }
+}
@@ -1,21 +1,57 @@
+use crate::audio_routes::{ AudioRoutes, RouteError, RouteRequest };
+use crate::bridge_openai::{ AudioLink, Finish, RealtimeBridge };
+use crate::broadcast::{ self, BroadcastRequest };
+use crate::capture::{ CapturedSocket, PacketCapture };
+use crate::clips::{ ClipPlayer, PlayError, PlayRequest, Playback };
+use crate::clock::{ self, SharedClock };
+use crate::cluster::Cluster;
 use crate::config::Config;
+use crate::conversation::ConversationStore;
+use crate::diagnostics::{ self, DiagnosticsRequest, LoopbackTaps };
+use crate::downlink_pacing::DownlinkPacer;
 use crate::esp_audio_protocol::*;
+use crate::monitor_audio::MonitorAudio;
+use crate::ota::{ Ota, OtaError, OtaRequest };
+use crate::pcm::{ Downmix, SampleFormat, MAX_CHANNELS };
+use crate::presence::Presence;
+use crate::prompt::PromptContext;
+use crate::quiet_hours::QuietHours;
+use crate::safety::SafetyPolicy;
+use crate::session_log::SessionLogs;
 use crate::sensor::SensorPacket;
+use crate::sensor_smoother::SensorSmoother;
+use crate::shard::ShardedSender;
+use crate::speakers::Diarizer;
+use crate::recording_policy::{ RecordingPolicies, RecordingPolicy };
+use crate::start_policy::{ StartPolicies, StartPolicy };
 use crate::stats::Stats;
-use crate::transport_openai::OpenAiSession;
+use crate::telemetry::DeviceTelemetry;
+use crate::tenants::TenantId;
+use crate::trace::TraceId;
+use crate::udp_batch;
+use crate::transcripts::TranscriptSink;
+use crate::transport_openai::{ OpenAiHealth, OpenAiSession };
 use crate::vad::VadResult;
-use crate::vad_response::VadResponsePacket;
-use std::collections::HashMap;
+use crate::vad_response::{ Coalescer, ResponseOptions, VadResponsePacket };
+use crate::voice_commands::{ CommandSpotter, Detected, VoiceCommand, VoiceCommands };
+use crate::volume::{ self as output, OutputChange, OutputStatus, VolumeControl, VolumeError, VolumeRequest };
+use crate::wav_writer::{ WavRecorder, WavStream };
+use dashmap::DashMap;
+use serde_json::json;
+use std::borrow::Cow;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{ Hash, Hasher };
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::net::UdpSocket;
+use std::time::{ Duration, Instant };
 use tokio::sync::{ mpsc, RwLock };
 use tracing::{ debug, warn, info };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum PromptMode {
+/// Emotion label of a V/A/D triple — picks the OpenAI prompt style and
+/// labels the emotion timeline (`GET /devices/{id}/emotions`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptMode {
     Neutral,
     Calm,
     Energetic,
@@ -29,10 +65,11 @@ enum PromptMode {
 }
 
 fn prompt_mode_from_vad(result: &VadResult) -> PromptMode {
-    let v = result.valence;
-    let a = result.arousal;
-    let d = result.dominance;
+    prompt_mode(result.valence, result.arousal, result.dominance)
+}
 
+/// Classify valence `v`, arousal `a`, dominance `d` (all 0..1).
+pub fn prompt_mode(v: f32, a: f32, d: f32) -> PromptMode {
     // High arousal + low valence + high dominance → Angry
     if a > 0.6 && v < 0.4 && d > 0.4 {
         PromptMode::Angry
@@ -65,7 +102,8 @@ fn prompt_mode_from_vad(result: &VadResult) -> PromptMode {
     }
 }
 
-fn build_prompt_instructions(base: &str, mode: PromptMode, result: &VadResult) -> String {
+/// Emotional style block appended to the rendered session instructions.
+fn emotion_style(mode: PromptMode, result: &VadResult) -> String {
     let style = match mode {
         PromptMode::Neutral =>
             "You feel good and ready for anything! Speak in your upbeat, bubbly Zing voice — cheerful and full of life! Every word should sparkle with personality.",
@@ -90,25 +128,57 @@ fn build_prompt_instructions(base: &str, mode: PromptMode, result: &VadResult) -
     };
 
     format!(
-        "{base}\n\nYour current emotional state (from environmental sensors): valence={:.3}, arousal={:.3}, dominance={:.3}.\nYou MUST embody this emotion in your voice and responses. {style}",
+        "\n\nYour current emotional state (from environmental sensors): valence={:.3}, arousal={:.3}, dominance={:.3}.\nYou MUST embody this emotion in your voice and responses. {style}",
         result.valence,
         result.arousal,
         result.dominance
     )
 }
 
-/// Shared map of sensor_id → last-seen client address (for sensor port responses).
-type ClientMap = Arc<RwLock<HashMap<u32, SocketAddr>>>;
+/// Last-seen client address of one sensor (for sensor port responses).
+#[derive(Debug, Clone, Copy)]
+struct ClientEntry {
+    addr: SocketAddr,
+    last_seen: Instant,
+}
+
+/// Shared map of sensor_id → last-seen client entry.  Sharded so sensor
+/// receiver threads don't serialise on one lock.
+type ClientMap = Arc<DashMap<u32, ClientEntry>>;
 
 /// Per-ESP-client session data: protocol state + optional OpenAI bridge.
 struct EspSessionEntry {
     session: EspSession,
-    /// When OpenAI Realtime is active, this holds the audio sender.
-    openai_tx: Option<mpsc::Sender<Vec<u8>>>,
+    /// When OpenAI Realtime is active, the session's audio path to it.
+    openai: Option<AudioLink>,
+    /// The session's WAV, streamed to disk while receiving.
+    wav: Option<WavStream>,
+    /// Recording policy of the device, read at SESSION_START
+    recording: RecordingPolicy,
+    /// Voice command spotting (`--voice-commands-dir`), fresh per session
+    commands: Option<CommandSpotter>,
+    /// Last heartbeat, session control or audio from the device
+    last_heard: Instant,
+}
+
+impl EspSessionEntry {
+    fn new(src: SocketAddr, now: Instant) -> Self {
+        EspSessionEntry {
+            session: EspSession::new(src, now),
+            openai: None,
+            wav: None,
+            recording: RecordingPolicy::default(),
+            commands: None,
+            last_heard: now,
+        }
+    }
 }
 
-/// Shared map of ESP client address → session entry (for audio port sessions).
-type SessionMap = Arc<RwLock<HashMap<SocketAddr, EspSessionEntry>>>;
+/// Shared map of ESP client address → session entry (for audio port
+/// sessions).  Every audio packet mutates its entry, so the map is
+/// sharded: receiver threads only contend when they hit the same shard.
+/// Never hold an entry guard across an `.await`.
+type SessionMap = Arc<DashMap<SocketAddr, EspSessionEntry>>;
 
 /// Spawn UDP receiver tasks for dual ports: audio and sensor.
 ///
@@ -118,11 +188,42 @@ type SessionMap = Arc<RwLock<HashMap<SocketAddr, EspSessionEntry>>>;
 ///   pipeline for real-time voice-activity detection.
 /// * **Sensor port** – receives sensor-vector packets, remembers the sender
 ///   address, and later sends back VAD results once they are computed.
+///   A sensor reappearing from a new address resets its smoother state.
+#[allow(clippy::too_many_arguments)]
 pub async fn spawn_udp_receivers(
     config: &Config,
-    tx: mpsc::Sender<SensorPacket>,
+    tx: ShardedSender,
     vad_rx: mpsc::Receiver<VadResult>,
-    stats: Arc<Stats>
+    stats: Arc<Stats>,
+    smoother: Arc<SensorSmoother>,
+    prompt: PromptContext,
+    conversations: ConversationStore,
+    openai_health: OpenAiHealth,
+    transcripts: TranscriptSink,
+    safety: Arc<SafetyPolicy>,
+    clips: ClipPlayer,
+    clip_requests: mpsc::Receiver<PlayRequest>,
+    broadcast_requests: mpsc::Receiver<BroadcastRequest>,
+    diagnostics_requests: mpsc::Receiver<DiagnosticsRequest>,
+    ota: Ota,
+    ota_requests: mpsc::Receiver<OtaRequest>,
+    capture: PacketCapture,
+    cluster: Cluster,
+    session_logs: SessionLogs,
+    start_policies: StartPolicies,
+    recording_policies: RecordingPolicies,
+    downlink: DownlinkPacer,
+    speakers: Diarizer,
+    commands: VoiceCommands,
+    command_requests: mpsc::Receiver<Detected>,
+    volume: VolumeControl,
+    volume_requests: mpsc::Receiver<VolumeRequest>,
+    quiet: QuietHours,
+    routes: AudioRoutes,
+    route_requests: mpsc::Receiver<RouteRequest>,
+    telemetry: DeviceTelemetry,
+    presence: Presence,
+    clock: SharedClock
 ) -> anyhow::Result<Vec<tokio::task::JoinHandle<()>>> {
     let n_threads = config.resolved_recv_threads();
     let audio_addr = config.audio_addr();
@@ -133,23 +234,37 @@ pub async fn spawn_udp_receivers(
     let mut handles = Vec::with_capacity(n_threads * 2 + 2);
 
     // Bind sockets
-    let audio_socket = Arc::new(bind_reuseport(&audio_addr, recv_buf_size).await?);
-    let sensor_socket = Arc::new(bind_reuseport(&sensor_addr, recv_buf_size).await?);
-    let test_socket = Arc::new(bind_reuseport(&test_addr, recv_buf_size).await?);
+    let bind = |socket| CapturedSocket::new(socket, capture.clone()).map(Arc::new);
+    let sockets = &config.sockets;
+    let audio_socket = bind(sockets.udp(&audio_addr, || bind_reuseport(&audio_addr, recv_buf_size)).await?)?;
+    let sensor_socket = bind(sockets.udp(&sensor_addr, || bind_reuseport(&sensor_addr, recv_buf_size)).await?)?;
+    let test_socket = bind(sockets.udp(&test_addr, || bind_reuseport(&test_addr, recv_buf_size)).await?)?;
 
     info!(
         audio_addr = %audio_addr,
         sensor_addr = %sensor_addr,
         test_addr = %test_addr,
+        tenant = %config.tenant_id,
         "✅ UDP triple ports bound"
     );
 
     // Shared map so the response handler knows where to send VAD results
-    let client_map: ClientMap = Arc::new(RwLock::new(HashMap::new()));
+    let client_map: ClientMap = Arc::new(DashMap::new());
+    tokio::spawn(
+        evict_clients_loop(client_map.clone(), stats.clone(), config.client_evict_secs, clock.clone())
+    );
 
     // Shared session map for ESP audio clients
-    let sessions: SessionMap = Arc::new(RwLock::new(HashMap::new()));
-    let audio_save_dir = config.audio_save_dir.clone();
+    let sessions: SessionMap = Arc::new(DashMap::new());
+    let wavs = WavRecorder::new(&config.audio_save_dir, Duration::from_secs(config.wav_flush_secs));
+    let recovered = wavs.recover();
+    if recovered > 0 {
+        info!(recovered, dir = %config.audio_save_dir, "🩹 finished session WAVs interrupted by a crash");
+    }
+    let sample_format = config.esp_sample_format;
+    let downmix = Downmix { mode: config.mic_mix, channel: config.mic_channel };
+    let tenant = TenantId::from(config.tenant_id.as_str());
+    let monitor = MonitorAudio::from_config(config)?;
 
     // Spawn persistent OpenAI Realtime session once at startup
     // (avoids WebSocket handshake latency on every ESP SESSION_START)
@@ -158,11 +273,21 @@ pub async fn spawn_udp_receivers(
         info!("\u{1F916} Spawning persistent OpenAI Realtime session...");
         match
             crate::transport_openai::spawn_openai_session(
-                &config,
+                config,
+                &prompt.instructions().await,
                 active_esp,
                 audio_socket.clone(),
                 config.save_debug_audio,
-                &config.audio_save_dir
+                &config.audio_save_dir,
+                conversations.clone(),
+                openai_health,
+                transcripts,
+                safety,
+                session_logs.clone(),
+                recording_policies.clone(),
+                downlink.clone(),
+                speakers.clone(),
+                quiet.clone()
             ).await
         {
             Ok(session) => {
@@ -177,12 +302,19 @@ pub async fn spawn_udp_receivers(
     } else {
         None
     };
+    let realtime = RealtimeBridge::new(persistent_oai.clone(), prompt.clone(), speakers);
 
     // ── Response handler: forwards VAD results to sensor clients ───────
     let sensor_socket_resp = sensor_socket.clone();
     let client_map_resp = client_map.clone();
-    let base_instructions = config.openai_instructions.clone();
+    let prompt_resp = prompt.clone();
     let persistent_oai_resp = persistent_oai.clone();
+    let session_logs_resp = session_logs.clone();
+    let response_opts = ResponseOptions {
+        version: config.vad_response_version,
+        coalesce: std::time::Duration::from_millis(config.response_coalesce_ms),
+        coalesce_mode: config.response_coalesce_mode,
+    };
     let resp_handle = tokio::spawn(async move {
         if
             let Err(e) = vad_response_loop(
@@ -190,7 +322,9 @@ pub async fn spawn_udp_receivers(
                 sensor_socket_resp,
                 client_map_resp,
                 persistent_oai_resp,
-                base_instructions
+                prompt_resp,
+                response_opts,
+                session_logs_resp
             ).await
         {
             tracing::error!(error = %e, "VAD response handler failed");
@@ -198,14 +332,113 @@ pub async fn spawn_udp_receivers(
     });
     handles.push(resp_handle);
 
+    // ── Clip playback requests from the REST API ───────────────────────
+    tokio::spawn(
+        clip_request_loop(clip_requests, clips.clone(), audio_socket.clone(), sessions.clone())
+    );
+
+    // ── Broadcast announcements to every online ESP ──────────────────
+    tokio::spawn(
+        broadcast_request_loop(
+            broadcast_requests,
+            clips.clone(),
+            quiet.clone(),
+            audio_socket.clone(),
+            sessions.clone(),
+            Duration::from_secs(config.broadcast_online_secs),
+            clock.clone()
+        )
+    );
+
+    // ── Audio diagnostics (tone out, mic looped back) ─────────────────
+    let taps: LoopbackTaps = Arc::new(DashMap::new());
+    let criteria = diagnostics::Criteria {
+        max_latency: Duration::from_millis(config.diag_max_latency_ms),
+        min_level_dbfs: config.diag_min_level_dbfs,
+    };
+    tokio::spawn(
+        diagnostics_request_loop(
+            diagnostics_requests,
+            clips.clone(),
+            audio_socket.clone(),
+            sessions.clone(),
+            taps.clone(),
+            criteria
+        )
+    );
+
+    // ── OTA firmware offers (REST-triggered) + offer retries ─────────
+    tokio::spawn(ota_request_loop(ota_requests, ota.clone(), audio_socket.clone(), sessions.clone()));
+    tokio::spawn(ota_retry_loop(ota.clone(), audio_socket.clone(), clock.clone()));
+
+    // ── Session caps (--max-session-secs / --max-session-bytes) ─────
+    tokio::spawn(
+        session_limit_loop(
+            SessionLimits::from_config(config),
+            audio_socket.clone(),
+            sessions.clone(),
+            realtime.clone(),
+            clips.clone(),
+            session_logs.clone(),
+            monitor.clone(),
+            cluster.clone(),
+            clock.clone()
+        )
+    );
+
+    // ── Voice commands spotted in session audio (stop / volume) ──────
+    tokio::spawn(
+        voice_command_loop(
+            command_requests,
+            config.volume_step as i8,
+            volume.clone(),
+            quiet.clone(),
+            audio_socket.clone(),
+            sessions.clone(),
+            realtime.clone(),
+            clips.clone(),
+            session_logs.clone(),
+            cluster.clone(),
+            clock.clone()
+        )
+    );
+
+    // ── Volume / mute changes from the REST API ──────────────────────
+    tokio::spawn(
+        volume_request_loop(volume_requests, volume.clone(), quiet.clone(), audio_socket.clone(), sessions.clone())
+    );
+
+    // ── Quiet hours: volume cap on / off as windows open and close ───
+    tokio::spawn(quiet_hours_loop(quiet, volume, audio_socket.clone(), sessions.clone(), clock.clone()));
+
+    // ── Intercom routes from the REST API ────────────────────────────
+    tokio::spawn(route_request_loop(route_requests, routes.clone(), audio_socket.clone(), sessions.clone()));
+
+    // ── Device online / offline notifications ─────────────────────────
+    tokio::spawn(presence_loop(sessions.clone(), presence, stats.clone(), clock.clone()));
+
     // ── Audio receiver threads (ESP audio protocol) ───────────────────
     for i in 0..n_threads {
         let socket = audio_socket.clone();
         let tx = tx.clone();
         let stats = stats.clone();
         let sessions = sessions.clone();
-        let save_dir = audio_save_dir.clone();
-        let persistent_oai = persistent_oai.clone();
+        let wavs = wavs.clone();
+        let realtime = realtime.clone();
+        let clips = clips.clone();
+        let tenant = tenant.clone();
+        let taps = taps.clone();
+        let routes = routes.clone();
+        let session_logs = session_logs.clone();
+        let monitor = monitor.clone();
+        let start_policies = start_policies.clone();
+        let recording_policies = recording_policies.clone();
+        let downlink = downlink.clone();
+        let ota = ota.clone();
+        let cluster = cluster.clone();
+        let commands = commands.clone();
+        let telemetry = telemetry.clone();
+        let clock = clock.clone();
 
         handles.push(
             tokio::spawn(async move {
@@ -216,8 +449,24 @@ pub async fn spawn_udp_receivers(
                         tx,
                         stats,
                         sessions,
-                        save_dir,
-                        persistent_oai
+                        wavs,
+                        realtime,
+                        &clips,
+                        sample_format,
+                        downmix,
+                        tenant,
+                        taps,
+                        routes,
+                        session_logs,
+                        monitor,
+                        start_policies,
+                        recording_policies,
+                        downlink,
+                        ota,
+                        cluster,
+                        commands,
+                        telemetry,
+                        clock
                     ).await
                 {
                     tracing::error!(thread = i, error = %e, "ESP audio receiver failed");
@@ -227,21 +476,55 @@ pub async fn spawn_udp_receivers(
     }
 
     // ── Sensor receiver threads (track client, forward for VAD) ───────
+    let ingress = SensorIngress {
+        tx: tx.clone(),
+        stats: stats.clone(),
+        client_map: client_map.clone(),
+        smoother: smoother.clone(),
+        tenant: tenant.clone(),
+        cluster: cluster.clone(),
+        clock: clock.clone(),
+    };
     for i in 0..n_threads {
         let socket = sensor_socket.clone();
-        let tx = tx.clone();
-        let stats = stats.clone();
-        let cmap = client_map.clone();
+        let ingress = ingress.clone();
+
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        if config.udp_io == crate::uring::UdpIo::Uring {
+            handles.push(spawn_sensor_uring(i, socket, ingress));
+            continue;
+        }
 
         handles.push(
             tokio::spawn(async move {
-                if let Err(e) = sensor_recv_loop(i, socket, tx, stats, cmap).await {
+                if let Err(e) = sensor_recv_loop(i, socket, ingress).await {
                     tracing::error!(thread = i, error = %e, "UDP sensor receiver failed");
                 }
             })
         );
     }
 
+    // ── RTP / RTCP audio streams (--rtp-port) ─────────────────────────
+    let rtp = crate::transport_rtp::RtpIngest {
+        tx: tx.clone(),
+        stats: stats.clone(),
+        realtime: realtime.clone(),
+        session_logs: session_logs.clone(),
+        recording_policies: recording_policies.clone(),
+        clock: clock.clone(),
+    };
+    handles.extend(crate::transport_rtp::spawn(config, rtp).await?);
+
+    // ── SIP phone calls (--sip-port) ──────────────────────────────────
+    let sip_sessions = sessions.clone();
+    let sip = crate::transport_sip::SipContext {
+        realtime: realtime.clone(),
+        audio_socket: audio_socket.clone(),
+        resolve: Arc::new(move |device| device_addr(&sip_sessions, device)),
+        clock: clock.clone(),
+    };
+    handles.extend(crate::transport_sip::spawn(config, sip).await?);
+
     // ── Test receiver (accepts any data, checks if from known ESP) ────
     {
         let test_sock = test_socket.clone();
@@ -262,18 +545,36 @@ pub async fn spawn_udp_receivers(
 //  ESP Audio Protocol receiver — session lifecycle + WAV recording
 // ═══════════════════════════════════════════════════════════════════════
 
+#[allow(clippy::too_many_arguments)]
 async fn esp_audio_recv_loop(
     thread_id: usize,
-    socket: Arc<UdpSocket>,
-    tx: mpsc::Sender<SensorPacket>,
+    socket: Arc<CapturedSocket>,
+    tx: ShardedSender,
     stats: Arc<Stats>,
     sessions: SessionMap,
-    audio_save_dir: String,
-    persistent_oai: Option<Arc<OpenAiSession>>
+    wavs: WavRecorder,
+    realtime: RealtimeBridge,
+    clips: &ClipPlayer,
+    sample_format: SampleFormat,
+    downmix: Downmix,
+    tenant: TenantId,
+    taps: LoopbackTaps,
+    routes: AudioRoutes,
+    session_logs: SessionLogs,
+    monitor: MonitorAudio,
+    start_policies: StartPolicies,
+    recording_policies: RecordingPolicies,
+    downlink: DownlinkPacer,
+    ota: Ota,
+    cluster: Cluster,
+    commands: VoiceCommands,
+    telemetry: DeviceTelemetry,
+    clock: SharedClock
 ) -> anyhow::Result<()> {
-    debug!(thread = thread_id, "ESP audio receiver started");
+    debug!(thread = thread_id, format = ?sample_format, "ESP audio receiver started");
 
-    let mut buf = vec![0u8; ESP_HEADER_SIZE + ESP_MAX_PAYLOAD + 64];
+    // Wider formats need proportionally larger datagrams
+    let mut buf = vec![0u8; (ESP_HEADER_SIZE + ESP_MAX_PAYLOAD) * 2 + 64];
 
     loop {
         let (len, src) = match socket.recv_from(&mut buf).await {
@@ -321,8 +622,15 @@ async fn esp_audio_recv_loop(
                 &sessions,
                 &tx,
                 &stats,
-                &audio_save_dir,
-                &persistent_oai
+                &wavs,
+                &realtime,
+                clips,
+                &session_logs,
+                &monitor,
+                &start_policies,
+                &recording_policies,
+                &cluster,
+                &clock
             ).await;
 
             // If the same datagram contains audio data after the
@@ -336,7 +644,22 @@ async fn esp_audio_recv_loop(
                     bytes = trailing.len(),
                     "🔊 processing trailing audio from notification packet"
                 );
-                handle_raw_pcm_audio(thread_id, trailing, src, &sessions, &tx, &stats).await;
+                handle_raw_pcm_audio(
+                    thread_id,
+                    trailing,
+                    sample_format,
+                    downmix,
+                    src,
+                    &sessions,
+                    &tx,
+                    &stats,
+                    &tenant,
+                    &taps,
+                    &routes,
+                    &session_logs,
+                    &commands,
+                    &clock
+                ).await;
             }
             continue;
         }
@@ -347,7 +670,26 @@ async fn esp_audio_recv_loop(
                 PKT_HEARTBEAT => {
                     let reply = build_heartbeat(pkt.seq_num);
                     let _ = socket.send_to(&reply, src).await;
+                    // A heartbeat alone makes the device known (broadcasts)
+                    sessions.entry(src).or_insert_with(|| EspSessionEntry::new(src, clock.now())).last_heard = clock.now();
                     debug!(thread = thread_id, src = %src, seq = pkt.seq_num, "💓 heartbeat");
+                    if let Some((rtt_ms, loss_permille)) = parse_link_report(&pkt.payload) {
+                        downlink.report(src, rtt_ms, loss_permille);
+                    }
+                }
+                PKT_OTA => {
+                    if let Some(reply) = ota.handle(src, pkt.seq_num, &pkt.payload) {
+                        let _ = socket.send_to(&reply, src).await;
+                    }
+                }
+                PKT_TELEMETRY => {
+                    let Some(message) = TelemetryMessage::parse(&pkt.payload) else {
+                        stats.record_parse_error();
+                        continue;
+                    };
+                    let mac = sessions.get(&src).and_then(|e| e.session.mac);
+                    let device = mac.map_or_else(|| src.to_string(), |mac| format_mac(&mac));
+                    telemetry.record(&device, message, clock::unix_millis(clock.as_ref()));
                 }
                 PKT_CONTROL => {
                     if let Some(cmd) = pkt.control_cmd() {
@@ -360,8 +702,15 @@ async fn esp_audio_recv_loop(
                             &sessions,
                             &tx,
                             &stats,
-                            &audio_save_dir,
-                            &persistent_oai
+                            &wavs,
+                            &realtime,
+                            clips,
+                            &session_logs,
+                            &monitor,
+                            &start_policies,
+                            &recording_policies,
+                            &cluster,
+                            &clock
                         ).await;
                     }
                 }
@@ -369,10 +718,18 @@ async fn esp_audio_recv_loop(
                     handle_raw_pcm_audio(
                         thread_id,
                         &pkt.payload,
+                        sample_format,
+                        downmix,
                         src,
                         &sessions,
                         &tx,
-                        &stats
+                        &stats,
+                        &tenant,
+                        &taps,
+                        &routes,
+                        &session_logs,
+                        &commands,
+                        &clock
                     ).await;
                     // Legacy: if END flag is set, treat as SESSION_END
                     if pkt.is_end() {
@@ -385,8 +742,15 @@ async fn esp_audio_recv_loop(
                             &sessions,
                             &tx,
                             &stats,
-                            &audio_save_dir,
-                            &persistent_oai
+                            &wavs,
+                            &realtime,
+                            clips,
+                            &session_logs,
+                            &monitor,
+                            &start_policies,
+                            &recording_policies,
+                            &cluster,
+                            &clock
                         ).await;
                     }
                 }
@@ -399,50 +763,90 @@ async fn esp_audio_recv_loop(
         }
 
         // ── Raw PCM audio (no header — new-protocol ESPs) ──────────
-        handle_raw_pcm_audio(thread_id, &buf[..len], src, &sessions, &tx, &stats).await;
+        handle_raw_pcm_audio(
+            thread_id,
+            &buf[..len],
+            sample_format,
+            downmix,
+            src,
+            &sessions,
+            &tx,
+            &stats,
+            &tenant,
+            &taps,
+            &routes,
+            &session_logs,
+            &commands,
+            &clock
+        ).await;
     }
 }
 
 /// Handle a single ESP control command within a session context.
+#[allow(clippy::too_many_arguments)]
 async fn handle_esp_control(
     thread_id: usize,
     cmd: u8,
     pkt: &EspPacket,
     src: SocketAddr,
-    socket: &Arc<UdpSocket>,
+    socket: &Arc<CapturedSocket>,
     sessions: &SessionMap,
-    _tx: &mpsc::Sender<SensorPacket>,
+    _tx: &ShardedSender,
     _stats: &Arc<Stats>,
-    audio_save_dir: &str,
-    persistent_oai: &Option<Arc<OpenAiSession>>
+    wavs: &WavRecorder,
+    realtime: &RealtimeBridge,
+    clips: &ClipPlayer,
+    session_logs: &SessionLogs,
+    monitor: &MonitorAudio,
+    start_policies: &StartPolicies,
+    recording_policies: &RecordingPolicies,
+    cluster: &Cluster,
+    clock: &SharedClock
 ) {
     match cmd {
         // ── SESSION_START: create / reset session, reply SERVER_READY ─
         CTRL_SESSION_START => {
+            let device = src.to_string();
+            let channels = pkt.start_channels();
+            if channels > MAX_CHANNELS {
+                warn!(src = %src, channels, "SESSION_START declares too many channels — refused");
+                let reply = build_error(pkt.seq_num, CTRL_SESSION_START, ERR_BAD_CHANNELS);
+                let _ = socket.send_to(&reply, src).await;
+                return;
+            }
+            match duplicate_start(src, &device, sessions, start_policies, session_logs) {
+                Some(StartPolicy::Resume) => {
+                    let reply = build_control(pkt.seq_num, CTRL_SERVER_READY, 0);
+                    let _ = socket.send_to(&reply, src).await;
+                    return;
+                }
+                Some(StartPolicy::Reject) => {
+                    let reply = build_error(pkt.seq_num, CTRL_SESSION_START, ERR_SESSION_ACTIVE);
+                    let _ = socket.send_to(&reply, src).await;
+                    return;
+                }
+                Some(StartPolicy::Restart) | None => {}
+            }
+
             // Wire the persistent OpenAI session to this ESP client
             // (no WebSocket handshake — session was created at server start)
-            let openai_tx = if let Some(ref oai) = persistent_oai {
-                oai.set_active_esp(src).await;
-                oai.clear_input_buffer().await;
-                info!(src = %src, "🤖 wired ESP client to persistent OpenAI session");
-                Some(oai.audio_tx.clone())
-            } else {
-                debug!(src = %src, "OpenAI Realtime not enabled — skipping");
-                None
-            };
+            let openai = realtime.attach(src, &device).await;
+            let recording = recording_policies.policy(&device);
 
             {
-                let mut map = sessions.write().await;
-                let entry = map.entry(src).or_insert_with(|| EspSessionEntry {
-                    session: EspSession::new(src),
-                    openai_tx: None,
-                });
-                entry.session.reset();
+                let mut entry = sessions.entry(src).or_insert_with(|| EspSessionEntry::new(src, clock.now()));
+                entry.session.reset(clock.now());
+                entry.last_heard = clock.now();
                 entry.session.state = SessionState::Receiving;
-                let has_openai = openai_tx.is_some();
-                entry.openai_tx = openai_tx;
-                info!(src = %src, has_openai_tx = has_openai, "session entry updated");
+                entry.session.channels = channels;
+                let has_openai = openai.is_some();
+                entry.openai = openai;
+                entry.wav = recording.keeps_audio().then(|| wavs.start(src, channels));
+                entry.recording = recording;
+                info!(src = %src, has_openai_tx = has_openai, channels, "session entry updated");
             }
+            session_logs.open(src, esp_sensor_id(src), &device, recording.keeps_session_log());
+            cluster.session(src, &device, true);
 
             let reply = build_control(pkt.seq_num, CTRL_SERVER_READY, 0);
             let _ = socket.send_to(&reply, src).await;
@@ -452,104 +856,35 @@ async fn handle_esp_control(
 
         // ── SESSION_END: save WAV, send ACK, reset ──────────────────
         CTRL_SESSION_END => {
-            let session_data = {
-                let mut map = sessions.write().await;
-                if let Some(entry) = map.get_mut(&src) {
-                    if entry.session.state == SessionState::Receiving {
-                        entry.session.state = SessionState::Processing;
-                        // Disconnect from persistent OpenAI session
-                        // (WebSocket stays alive for the next ESP session)
-                        entry.openai_tx = None;
-                        Some((
-                            entry.session.audio_buffer.clone(),
-                            entry.session.audio_packets,
-                            entry.session.audio_bytes,
-                            entry.session.packets_lost,
-                            entry.session.elapsed(),
-                        ))
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            };
-
-            if let Some((audio_buf, pkts, bytes, lost, duration)) = session_data {
-                let audio_secs = (bytes as f64) / (16_000.0 * 2.0);
-                let elapsed_ms = duration.as_millis();
-                let elapsed_human = if elapsed_ms < 1_000 {
-                    format!("{}ms", elapsed_ms)
-                } else if elapsed_ms < 60_000 {
-                    format!("{:.1}s", duration.as_secs_f64())
-                } else {
-                    let mins = elapsed_ms / 60_000;
-                    let secs = ((elapsed_ms % 60_000) as f64) / 1000.0;
-                    format!("{}m {:.1}s", mins, secs)
-                };
-                info!(
-                    src = %src,
-                    packets = pkts,
-                    bytes = bytes,
-                    lost = lost,
-                    elapsed = %elapsed_human,
-                    audio_secs = format!("{:.1}", audio_secs),
-                    "📴 ESP session ended — START→STOP took {}", elapsed_human
-                );
-
-                // Only commit + trigger OpenAI response if real audio was received
-                if !audio_buf.is_empty() {
-                    if let Some(ref oai) = persistent_oai {
-                        oai.commit_input_buffer().await;
-                        oai.create_response().await;
-                        info!(src = %src, audio_secs = format!("{:.1}", audio_secs),
-                              "📝 committed OpenAI audio buffer + triggered response");
-                    }
-
-                    match save_session_wav(audio_save_dir, src, &audio_buf).await {
-                        Ok(path) => info!(path = %path, "💾 session audio saved"),
-                        Err(e) => warn!(error = %e, "failed to save session audio"),
-                    }
-                } else {
-                    info!(src = %src, "⏭️ session ended with no audio — skipping OpenAI commit");
-                }
-
-                // Send ACK
-                let reply = build_control(pkt.seq_num, CTRL_ACK, 0);
-                let _ = socket.send_to(&reply, src).await;
-
-                // Reset to idle
-                {
-                    let mut map = sessions.write().await;
-                    if let Some(entry) = map.get_mut(&src) {
-                        entry.session.reset();
-                        entry.openai_tx = None;
-                    }
-                }
-            } else {
-                // No active receiving session — still ACK
-                let reply = build_control(pkt.seq_num, CTRL_ACK, 0);
-                let _ = socket.send_to(&reply, src).await;
-            }
+            let outcome = end_session(
+                src,
+                socket,
+                sessions,
+                realtime,
+                clips,
+                session_logs,
+                monitor,
+                cluster,
+                clock,
+                EndReason::Device
+            ).await;
+            // ACK whether or not a session was receiving; the status
+            // tells the device which
+            let reply = build_ack(pkt.seq_num, CTRL_SESSION_END, outcome.ack_status());
+            let _ = socket.send_to(&reply, src).await;
         }
 
         // ── CANCEL: discard session, ACK ────────────────────────────
         CTRL_CANCEL => {
-            {
-                let mut map = sessions.write().await;
-                if let Some(entry) = map.get_mut(&src) {
-                    info!(src = %src, pkts = entry.session.audio_packets,
-                          "🚫 ESP session cancelled");
-                    entry.session.reset();
-                    entry.openai_tx = None;
-                }
-            }
-            // Detach from persistent OpenAI session + discard buffered audio
-            if let Some(ref oai) = persistent_oai {
-                oai.clear_active_esp().await;
-                oai.clear_input_buffer().await;
-            }
-            let reply = build_control(pkt.seq_num, CTRL_ACK, 0);
+            let active = discard_session(src, sessions, clock);
+            // Stop the reply this device is hearing, detach from the
+            // persistent OpenAI session and discard buffered audio
+            realtime.cancel(src).await;
+            clips.stop(src);
+            session_logs.close(src, "control", json!({ "cmd": "cancel" }));
+            cluster.session(src, "", false);
+            let status = if active { ACK_OK } else { ACK_NO_SESSION };
+            let reply = build_ack(pkt.seq_num, CTRL_CANCEL, status);
             let _ = socket.send_to(&reply, src).await;
         }
 
@@ -564,272 +899,684 @@ async fn handle_esp_control(
 // ═══════════════════════════════════════════════════════════════════════
 
 /// Handle a parsed notification packet (start / stop session).
+#[allow(clippy::too_many_arguments)]
 async fn handle_notify_cmd(
     thread_id: usize,
     notify: &NotifyPacket,
     src: SocketAddr,
-    socket: &Arc<UdpSocket>,
+    socket: &Arc<CapturedSocket>,
     sessions: &SessionMap,
-    _tx: &mpsc::Sender<SensorPacket>,
+    _tx: &ShardedSender,
     _stats: &Arc<Stats>,
-    audio_save_dir: &str,
-    persistent_oai: &Option<Arc<OpenAiSession>>
+    wavs: &WavRecorder,
+    realtime: &RealtimeBridge,
+    clips: &ClipPlayer,
+    session_logs: &SessionLogs,
+    monitor: &MonitorAudio,
+    start_policies: &StartPolicies,
+    recording_policies: &RecordingPolicies,
+    cluster: &Cluster,
+    clock: &SharedClock
 ) {
     let mac_str = notify.mac_str();
 
     match notify.cmd {
         // ── START: create/reset session, wire OpenAI, reply ────────
         NOTIFY_CMD_START => {
-            let openai_tx = if let Some(ref oai) = persistent_oai {
-                oai.set_active_esp(src).await;
-                oai.clear_input_buffer().await;
-                info!(src = %src, mac = %mac_str,
-                      "🤖 wired ESP client to persistent OpenAI session");
-                Some(oai.audio_tx.clone())
-            } else {
-                debug!(src = %src, "OpenAI Realtime not enabled — skipping");
-                None
-            };
+            match duplicate_start(src, &mac_str, sessions, start_policies, session_logs) {
+                // Notify STARTs get no SERVER_READY: nothing to repeat
+                Some(StartPolicy::Resume) => {
+                    return;
+                }
+                Some(StartPolicy::Reject) => {
+                    let reply = build_notify_packet(NOTIFY_CMD_ERROR, &notify.mac);
+                    let _ = socket.send_to(&reply, src).await;
+                    return;
+                }
+                Some(StartPolicy::Restart) | None => {}
+            }
+
+            let openai = realtime.attach(src, &mac_str).await;
+            let recording = recording_policies.policy(&mac_str);
 
             {
-                let mut map = sessions.write().await;
-                let entry = map.entry(src).or_insert_with(|| EspSessionEntry {
-                    session: EspSession::new(src),
-                    openai_tx: None,
-                });
-                entry.session.reset();
+                let mut entry = sessions.entry(src).or_insert_with(|| EspSessionEntry::new(src, clock.now()));
+                entry.session.reset(clock.now());
+                entry.last_heard = clock.now();
                 entry.session.state = SessionState::Receiving;
                 entry.session.mac = Some(notify.mac);
-                let has_openai = openai_tx.is_some();
-                entry.openai_tx = openai_tx;
+                let has_openai = openai.is_some();
+                entry.openai = openai;
+                entry.wav = recording.keeps_audio().then(|| wavs.start(src, 1));
+                entry.recording = recording;
                 info!(src = %src, has_openai_tx = has_openai, "session entry updated");
             }
 
+            session_logs.open(src, esp_sensor_id(src), &mac_str, recording.keeps_session_log());
+            cluster.session(src, &mac_str, true);
+
             info!(thread = thread_id, src = %src, mac = %mac_str,
                   "📞 ESP session started (notify)");
         }
 
         // ── STOP: save WAV, commit OpenAI, send ACK, reset ────────
         NOTIFY_CMD_STOP => {
-            let session_data = {
-                let mut map = sessions.write().await;
-                if let Some(entry) = map.get_mut(&src) {
-                    if entry.session.state == SessionState::Receiving {
-                        entry.session.state = SessionState::Processing;
-                        entry.openai_tx = None;
-                        Some((
-                            entry.session.audio_buffer.clone(),
-                            entry.session.audio_packets,
-                            entry.session.audio_bytes,
-                            entry.session.packets_lost,
-                            entry.session.elapsed(),
-                        ))
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            };
+            let outcome = end_session(
+                src,
+                socket,
+                sessions,
+                realtime,
+                clips,
+                session_logs,
+                monitor,
+                cluster,
+                clock,
+                EndReason::Device
+            ).await;
+            if outcome == EndOutcome::NoSession {
+                // No active session — this is a keep-alive STOP, ignore
+                debug!(thread = thread_id, src = %src, mac = %mac_str,
+                       "🔄 STOP keep-alive (no active session)");
+            }
+        }
 
-            if let Some((audio_buf, pkts, bytes, lost, duration)) = session_data {
-                let audio_secs = (bytes as f64) / (16_000.0 * 2.0);
-                let elapsed_ms = duration.as_millis();
-                let elapsed_human = if elapsed_ms < 1_000 {
-                    format!("{}ms", elapsed_ms)
-                } else if elapsed_ms < 60_000 {
-                    format!("{:.1}s", duration.as_secs_f64())
-                } else {
-                    let mins = elapsed_ms / 60_000;
-                    let secs = ((elapsed_ms % 60_000) as f64) / 1000.0;
-                    format!("{}m {:.1}s", mins, secs)
-                };
-                info!(
-                    src = %src,
-                    packets = pkts,
-                    bytes = bytes,
-                    lost = lost,
-                    elapsed = %elapsed_human,
-                    audio_secs = format!("{:.1}", audio_secs),
-                    "📴 ESP session ended (notify) — START→STOP took {}", elapsed_human
-                );
+        other => {
+            debug!(thread = thread_id, src = %src, cmd = other,
+                   "unknown notification command");
+        }
+    }
+}
 
-                // Only commit + trigger OpenAI response if real audio was received
-                if !audio_buf.is_empty() {
-                    if let Some(ref oai) = persistent_oai {
-                        oai.commit_input_buffer().await;
-                        oai.create_response().await;
-                        info!(src = %src, audio_secs = format!("{:.1}", audio_secs),
-                              "📝 committed OpenAI audio buffer + triggered response");
-                    }
+/// A START from `src` while its session is still Receiving: returns the
+/// device's policy (None = no session running, start normally).  For
+/// `restart` the running session's audio is discarded here; the discard
+/// runs in the background, which is safe because the next stream never
+/// shares its part file (see `wav_writer::create`).
+fn duplicate_start(
+    src: SocketAddr,
+    device: &str,
+    sessions: &SessionMap,
+    start_policies: &StartPolicies,
+    session_logs: &SessionLogs
+) -> Option<StartPolicy> {
+    let mut entry = sessions.get_mut(&src)?;
+    if entry.session.state != SessionState::Receiving {
+        return None;
+    }
+    let policy = start_policies.policy(device);
+    warn!(
+        src = %src,
+        device = %device,
+        policy = policy.as_str(),
+        packets = entry.session.audio_packets,
+        "🔁 SESSION_START while a session is receiving"
+    );
+    if policy == StartPolicy::Restart {
+        entry.openai = None;
+        if let Some(wav) = entry.wav.take() {
+            wav.discard();
+        }
+        drop(entry);
+        session_logs.close(src, "control", json!({ "cmd": "session_start", "duplicate": "restart" }));
+    } else {
+        drop(entry);
+        session_logs.event(src, "control", json!({ "cmd": "session_start", "duplicate": policy.as_str() }));
+    }
+    Some(policy)
+}
+
+/// Why a Receiving session was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EndReason {
+    /// SESSION_END / STOP from the device
+    Device,
+    /// `--max-session-secs` reached
+    MaxSecs,
+    /// `--max-session-bytes` reached
+    MaxBytes,
+}
+
+impl EndReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            EndReason::Device => "device",
+            EndReason::MaxSecs => "max_session_secs",
+            EndReason::MaxBytes => "max_session_bytes",
+        }
+    }
+}
+
+/// What `end_session` did, reported to the device in the SESSION_END ACK.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EndOutcome {
+    /// No session was receiving
+    NoSession,
+    /// Ended without any audio; nothing saved or committed
+    NoAudio,
+    /// Audio committed and its WAV saved
+    Saved,
+    /// Audio committed but the WAV could not be written
+    SaveFailed,
+    /// Audio committed; the device's recording policy keeps no WAV
+    NotRecorded,
+}
+
+impl EndOutcome {
+    fn ack_status(self) -> u8 {
+        match self {
+            EndOutcome::NoSession => ACK_NO_SESSION,
+            EndOutcome::NoAudio => ACK_NO_AUDIO,
+            EndOutcome::Saved | EndOutcome::NotRecorded => ACK_OK,
+            EndOutcome::SaveFailed => ACK_SAVE_FAILED,
+        }
+    }
+}
+
+/// Close `src`'s Receiving session: commit its audio to OpenAI (or play
+/// the offline clip), finish its WAV, close the session log and reset
+/// the entry.
+#[allow(clippy::too_many_arguments)]
+async fn end_session(
+    src: SocketAddr,
+    socket: &Arc<CapturedSocket>,
+    sessions: &SessionMap,
+    realtime: &RealtimeBridge,
+    clips: &ClipPlayer,
+    session_logs: &SessionLogs,
+    monitor: &MonitorAudio,
+    cluster: &Cluster,
+    clock: &SharedClock,
+    reason: EndReason
+) -> EndOutcome {
+    let session_data = {
+        if let Some(mut entry) = sessions.get_mut(&src) {
+            if entry.session.state == SessionState::Receiving {
+                entry.session.state = SessionState::Processing;
+                // Disconnect from persistent OpenAI session
+                // (WebSocket stays alive for the next ESP session)
+                Some((
+                    entry.openai.take(),
+                    entry.wav.take(),
+                    entry.recording,
+                    entry.session.audio_packets,
+                    entry.session.audio_bytes,
+                    entry.session.packets_lost,
+                    entry.session.elapsed(clock.now()),
+                    entry.session.audio_duration_secs(),
+                    entry.session.mac,
+                ))
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    };
+    let Some((openai, wav, recording, pkts, bytes, lost, duration, audio_secs, mac)) = session_data else {
+        return EndOutcome::NoSession;
+    };
 
-                    match save_session_wav(audio_save_dir, src, &audio_buf).await {
-                        Ok(path) => info!(path = %path, "💾 session audio saved"),
-                        Err(e) => warn!(error = %e, "failed to save session audio"),
+    session_logs.event(
+        src,
+        "control",
+        json!({
+            "cmd": "session_end",
+            "reason": reason.as_str(),
+            "packets": pkts,
+            "bytes": bytes,
+            "lost": lost,
+        })
+    );
+    let elapsed_ms = duration.as_millis();
+    let elapsed_human = if elapsed_ms < 1_000 {
+        format!("{}ms", elapsed_ms)
+    } else if elapsed_ms < 60_000 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        let mins = elapsed_ms / 60_000;
+        let secs = ((elapsed_ms % 60_000) as f64) / 1000.0;
+        format!("{}m {:.1}s", mins, secs)
+    };
+    info!(
+        src = %src,
+        reason = reason.as_str(),
+        packets = pkts,
+        bytes = bytes,
+        lost = lost,
+        elapsed = %elapsed_human,
+        audio_secs = format!("{:.1}", audio_secs),
+        "📴 ESP session ended — START→STOP took {}", elapsed_human
+    );
+
+    // Only commit + trigger OpenAI response if real audio was received
+    let outcome = if bytes > 0 {
+        if realtime.finish(src, openai, audio_secs).await == Finish::Offline {
+            let device = mac.map_or_else(|| src.to_string(), |mac| format_mac(&mac));
+            clips.play_offline(socket.clone(), src, &device);
+        }
+
+        match wav {
+            Some(wav) =>
+                match wav.finish().await {
+                    Ok(path) => {
+                        info!(path = %path, "💾 session audio saved");
+                        session_logs.wav_saved(src, &path);
+                        monitor.spawn(&path, src, session_logs);
+                        EndOutcome::Saved
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "failed to save session audio");
+                        EndOutcome::SaveFailed
                     }
-                } else {
-                    info!(src = %src, "⏭️ session ended with no audio — skipping OpenAI commit");
                 }
+            None if !recording.keeps_audio() => {
+                info!(src = %src, mode = ?recording.mode, "🔏 session audio not saved (recording policy)");
+                session_logs.event(src, "wav_skipped", json!({ "policy": recording.mode }));
+                EndOutcome::NotRecorded
+            }
+            None => EndOutcome::SaveFailed,
+        }
+    } else {
+        if let Some(wav) = wav {
+            wav.discard();
+        }
+        info!(src = %src, "⏭️ session ended with no audio — skipping OpenAI commit");
+        EndOutcome::NoAudio
+    };
+    session_logs.close(src, "session_closed", json!({}));
+    cluster.session(src, "", false);
 
-                {
-                    let mut map = sessions.write().await;
-                    if let Some(entry) = map.get_mut(&src) {
-                        entry.session.reset();
-                        entry.openai_tx = None;
+    // Reset to idle
+    if let Some(mut entry) = sessions.get_mut(&src) {
+        entry.session.reset(clock.now());
+        entry.openai = None;
+    }
+    outcome
+}
+
+/// Drop `src`'s session: audio, WAV and OpenAI link discarded, entry
+/// reset.  Returns `true` if it was receiving.
+fn discard_session(src: SocketAddr, sessions: &SessionMap, clock: &SharedClock) -> bool {
+    let Some(mut entry) = sessions.get_mut(&src) else {
+        return false;
+    };
+    let active = entry.session.state == SessionState::Receiving;
+    info!(src = %src, pkts = entry.session.audio_packets, "🚫 ESP session cancelled");
+    entry.session.reset(clock.now());
+    entry.openai = None;
+    entry.commands = None;
+    if let Some(wav) = entry.wav.take() {
+        wav.discard();
+    }
+    active
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Voice commands — spotted locally, acted on without OpenAI
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Act on every spotted command.  The session it was said in is dropped
+/// (so OpenAI never answers it), then:
+///
+/// * stop: the device's reply, clip or TTS is cancelled and it is told
+///   to stop playback (CANCEL control; S2D stop + STOP notification for
+///   notify-protocol devices)
+/// * volume: the tracked volume stepped by `±volume_step` and sent
+///   (see `volume.rs`), and the device told the session is over
+///   (SESSION_END control / STOP notification); a reply in progress
+///   keeps playing
+#[allow(clippy::too_many_arguments)]
+async fn voice_command_loop(
+    mut detections: mpsc::Receiver<Detected>,
+    volume_step: i8,
+    volume: VolumeControl,
+    quiet: QuietHours,
+    socket: Arc<CapturedSocket>,
+    sessions: SessionMap,
+    realtime: RealtimeBridge,
+    clips: ClipPlayer,
+    session_logs: SessionLogs,
+    cluster: Cluster,
+    clock: SharedClock
+) {
+    while let Some(d) = detections.recv().await {
+        let src = d.addr;
+        info!(
+            src = %src,
+            command = d.command.as_str(),
+            phrase = %d.phrase,
+            distance = format!("{:.2}", d.distance),
+            "🗝️ voice command"
+        );
+        let target = sessions.get_mut(&src).map(|mut entry| (entry.session.mac, entry.session.next_seq()));
+        let Some((mac, seq)) = target else {
+            continue;
+        };
+        if !discard_session(src, &sessions, &clock) {
+            // The session ended while the command was queued
+            continue;
+        }
+        session_logs.close(
+            src,
+            "voice_command",
+            json!({ "command": d.command, "phrase": d.phrase, "distance": d.distance })
+        );
+        cluster.session(src, "", false);
+
+        let mut notices: Vec<Vec<u8>> = Vec::with_capacity(2);
+        match d.command {
+            VoiceCommand::Stop => {
+                realtime.cancel(src).await;
+                clips.stop(src);
+                match mac {
+                    Some(mac) => {
+                        notices.push(build_s2d_stop().to_vec());
+                        notices.push(build_notify_packet(NOTIFY_CMD_STOP, &mac).to_vec());
                     }
+                    None => notices.push(build_control(seq, CTRL_CANCEL, 0)),
                 }
-            } else {
-                // No active session — this is a keep-alive STOP, ignore
-                debug!(thread = thread_id, src = %src, mac = %mac_str,
-                       "🔄 STOP keep-alive (no active session)");
+            }
+            VoiceCommand::VolumeDown | VoiceCommand::VolumeUp => {
+                realtime.discard(src).await;
+                let step = if d.command == VoiceCommand::VolumeDown { -volume_step } else { volume_step };
+                let device = mac.map_or_else(|| src.to_string(), |mac| format_mac(&mac));
+                let (_, packets) = volume.apply(&device, OutputChange::Step(step), mac, seq, quiet.volume_cap(&device));
+                notices.extend(packets);
+                notices.push(match mac {
+                    Some(mac) => build_notify_packet(NOTIFY_CMD_STOP, &mac).to_vec(),
+                    None => build_control(seq.wrapping_add(2), CTRL_SESSION_END, 0),
+                });
             }
         }
+        for notice in notices {
+            let _ = socket.send_to(&notice, src).await;
+        }
+    }
+}
 
-        other => {
-            debug!(thread = thread_id, src = %src, cmd = other,
-                   "unknown notification command");
+// ═══════════════════════════════════════════════════════════════════════
+//  Session caps — firmware that never sends SESSION_END
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Upper bounds on one Receiving session (`--max-session-secs`,
+/// `--max-session-bytes`; `None` = unlimited).
+#[derive(Debug, Clone, Copy, Default)]
+struct SessionLimits {
+    max_duration: Option<Duration>,
+    max_bytes: Option<u64>,
+}
+
+impl SessionLimits {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            max_duration: (config.max_session_secs > 0).then(|| Duration::from_secs(config.max_session_secs)),
+            max_bytes: (config.max_session_bytes > 0).then_some(config.max_session_bytes),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.max_duration.is_some() || self.max_bytes.is_some()
+    }
+
+    /// The cap `session` has run into at `now`, if any.
+    fn exceeded(&self, session: &EspSession, now: Instant) -> Option<EndReason> {
+        if session.state != SessionState::Receiving {
+            return None;
+        }
+        if self.max_bytes.is_some_and(|max| session.audio_bytes >= max) {
+            return Some(EndReason::MaxBytes);
+        }
+        if self.max_duration.is_some_and(|max| session.elapsed(now) >= max) {
+            return Some(EndReason::MaxSecs);
+        }
+        None
+    }
+}
+
+/// Twice a second, force-close sessions over a cap: their audio is
+/// saved and committed as on SESSION_END, then the device is told the
+/// session is over (SESSION_END control, or a STOP notification for
+/// notify-protocol devices).
+#[allow(clippy::too_many_arguments)]
+async fn session_limit_loop(
+    limits: SessionLimits,
+    socket: Arc<CapturedSocket>,
+    sessions: SessionMap,
+    realtime: RealtimeBridge,
+    clips: ClipPlayer,
+    session_logs: SessionLogs,
+    monitor: MonitorAudio,
+    cluster: Cluster,
+    clock: SharedClock
+) {
+    if !limits.enabled() {
+        return;
+    }
+    loop {
+        clock.sleep(Duration::from_millis(500)).await;
+        let now = clock.now();
+        let over: Vec<(SocketAddr, EndReason)> = sessions
+            .iter()
+            .filter_map(|entry| Some((*entry.key(), limits.exceeded(&entry.session, now)?)))
+            .collect();
+        for (src, reason) in over {
+            warn!(src = %src, reason = reason.as_str(), "⏱️ session over its cap — closing it");
+            let notice = sessions.get_mut(&src).map(|mut entry| {
+                match entry.session.mac {
+                    Some(mac) => build_notify_packet(NOTIFY_CMD_STOP, &mac).to_vec(),
+                    None => build_control(entry.session.next_seq(), CTRL_SESSION_END, 0),
+                }
+            });
+            let outcome = end_session(
+                src,
+                &socket,
+                &sessions,
+                &realtime,
+                &clips,
+                &session_logs,
+                &monitor,
+                &cluster,
+                &clock,
+                reason
+            ).await;
+            if let Some(notice) = notice.filter(|_| outcome != EndOutcome::NoSession) {
+                let _ = socket.send_to(&notice, src).await;
+            }
         }
     }
 }
 
 /// Handle raw PCM audio data (no protocol header).
+#[allow(clippy::too_many_arguments)]
 async fn handle_raw_pcm_audio(
     thread_id: usize,
     audio_data: &[u8],
+    sample_format: SampleFormat,
+    downmix: Downmix,
     src: SocketAddr,
     sessions: &SessionMap,
-    tx: &mpsc::Sender<SensorPacket>,
-    stats: &Arc<Stats>
+    tx: &ShardedSender,
+    stats: &Arc<Stats>,
+    tenant: &TenantId,
+    taps: &LoopbackTaps,
+    routes: &AudioRoutes,
+    session_logs: &SessionLogs,
+    commands: &VoiceCommands,
+    clock: &SharedClock
 ) {
+    // Normalise to s16 once so recording, resampling and VAD all agree
+    let audio_data = &sample_format.to_s16le(audio_data)[..];
     if audio_data.is_empty() {
         return;
     }
 
-    let (should_forward, openai_tx, seq) = {
-        let mut map = sessions.write().await;
-        if let Some(entry) = map.get_mut(&src) {
+    // Device under diagnostics: its mic audio belongs to the test
+    if let Some(tap) = taps.get(&src) {
+        let _ = tap.send((Instant::now(), audio_data.to_vec()));
+        return;
+    }
+
+    // Intercom: the device's mic also plays on its routed peer
+    if routes.is_routed(src) {
+        let channels = sessions.get(&src).map_or(1, |entry| entry.session.channels as usize);
+        if routes.forward(src, &downmix.apply(audio_data, channels)) {
+            return;
+        }
+    }
+
+    let (should_forward, seq, lost, mono, command) = {
+        if let Some(mut entry) = sessions.get_mut(&src) {
+            entry.last_heard = clock.now();
             if entry.session.state == SessionState::Receiving {
                 let seq = entry.session.audio_packets as u16;
+                // First audio of a session: fresh command spotter
+                if entry.session.audio_packets == 0 {
+                    entry.commands = commands.spotter();
+                }
+                let lost_before = entry.session.packets_lost;
                 entry.session.record_audio(seq, audio_data);
-                (true, entry.openai_tx.clone(), seq)
+                let lost = entry.session.packets_lost - lost_before;
+                // The WAV keeps every channel; VAD and OpenAI hear mono
+                if let Some(ref wav) = entry.wav {
+                    wav.write(audio_data);
+                }
+                let mono = downmix.apply(audio_data, entry.session.channels as usize);
+                if let Some(ref mut openai) = entry.openai {
+                    openai.forward(src, &mono);
+                }
+                let command = entry.commands.as_mut().and_then(|c| c.push(src, &mono));
+                (true, seq, lost, mono, command)
             } else {
                 debug!(src = %src, state = %entry.session.state,
                        "audio ignored — session not receiving");
-                (false, None, 0)
+                (false, 0, 0, Cow::Borrowed(audio_data), None)
             }
         } else {
             debug!(thread = thread_id, src = %src,
                    "audio from unknown source — no active session");
-            (false, None, 0)
+            (false, 0, 0, Cow::Borrowed(audio_data), None)
         }
     };
+    if let Some(command) = command {
+        commands.dispatch(command);
+    }
 
     if should_forward {
-        let sensor_pkt = esp_audio_to_sensor_packet(src, seq, audio_data);
-        if tx.try_send(sensor_pkt).is_err() {
-            stats.record_channel_drop();
+        session_logs.event(src, "audio", json!({ "seq": seq, "bytes": audio_data.len() }));
+        if lost > 0 {
+            session_logs.event(src, "loss", json!({ "seq": seq, "lost": lost }));
         }
 
-        if let Some(ref oai_tx) = openai_tx {
-            let payload_len = audio_data.len();
-            match oai_tx.try_send(audio_data.to_vec()) {
-                Ok(()) => {
-                    debug!(src = %src, bytes = payload_len,
-                           "audio forwarded to OpenAI tx");
-                }
-                Err(mpsc::error::TrySendError::Full(_)) => {
-                    warn!(src = %src,
-                          "OpenAI tx channel full — dropping audio chunk");
-                }
-                Err(mpsc::error::TrySendError::Closed(_)) => {
-                    debug!(src = %src,
-                          "OpenAI tx channel closed — session may have ended");
-                }
-            }
+        let timestamp_us = clock::unix_micros(clock.as_ref());
+        let trace = session_logs.trace(src);
+        let sensor_pkt = esp_audio_to_sensor_packet(src, seq, &mono, timestamp_us, tenant.clone(), trace);
+        if tx.try_send(sensor_pkt).is_err() {
+            stats.record_channel_drop();
         }
     }
 }
 
 // ═══════════════════════════════════════════════════════════════════════
-//  Helpers: SensorPacket bridge + WAV writer
+//  Helpers: SensorPacket bridge
 // ═══════════════════════════════════════════════════════════════════════
 
-/// Convert an ESP audio payload into a [`SensorPacket`] so it can travel
-/// through the existing VAD processing pipeline.
-fn esp_audio_to_sensor_packet(src: SocketAddr, seq_num: u16, payload: &[u8]) -> SensorPacket {
-    // Derive a stable sensor_id from the source address.
+/// Stable VAD sensor_id for an ESP's audio, derived from its address.
+fn esp_sensor_id(src: SocketAddr) -> u32 {
     let mut hasher = DefaultHasher::new();
     src.hash(&mut hasher);
-    let sensor_id = (hasher.finish() & 0xffff_ffff) as u32;
+    (hasher.finish() & 0xffff_ffff) as u32
+}
 
+/// Convert an ESP audio payload into a [`SensorPacket`] so it can travel
+/// through the existing VAD processing pipeline, traced as its session.
+fn esp_audio_to_sensor_packet(
+    src: SocketAddr,
+    seq_num: u16,
+    payload: &[u8],
+    timestamp_us: u64,
+    tenant: TenantId,
+    trace: Option<TraceId>
+) -> SensorPacket {
     SensorPacket {
-        sensor_id,
-        timestamp_us: std::time::SystemTime
-            ::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_micros() as u64,
+        sensor_id: esp_sensor_id(src),
+        timestamp_us,
         data_type: crate::sensor::DATA_TYPE_AUDIO,
+        sample_format: SampleFormat::S16,
         seq: seq_num as u64,
         payload: payload.to_vec(),
+        tenant,
+        ingested_at: None,
+        trace,
     }
 }
 
-/// Write the accumulated PCM buffer to a WAV file (16 kHz, 16-bit, mono).
-async fn save_session_wav(dir: &str, src: SocketAddr, pcm_data: &[u8]) -> anyhow::Result<String> {
-    if pcm_data.is_empty() {
-        anyhow::bail!("no audio data to save");
-    }
+// ═══════════════════════════════════════════════════════════════════════
+//  Sensor receiver — remembers client addr, forwards packet for VAD
+// ═══════════════════════════════════════════════════════════════════════
 
-    tokio::fs::create_dir_all(dir).await?;
+/// What a sensor receiver needs to turn a datagram into queued work.
+#[derive(Clone)]
+struct SensorIngress {
+    tx: ShardedSender,
+    stats: Arc<Stats>,
+    client_map: ClientMap,
+    smoother: Arc<SensorSmoother>,
+    tenant: TenantId,
+    cluster: Cluster,
+    clock: SharedClock,
+}
 
-    let now = chrono::Local::now();
-    let ts = now.format("%Y%m%d_%H%M%S").to_string();
-    let ip_str = src.ip().to_string().replace('.', "_").replace(':', "_");
-    let filename = format!("esp_{}_{}.wav", ip_str, ts);
-    let path = format!("{}/{}", dir, filename);
+impl SensorIngress {
+    /// Parse one datagram from `src`, remember the sender and queue the
+    /// packet for VAD.
+    fn handle(&self, thread_id: usize, data: &[u8], src: SocketAddr) {
+        self.stats.record_recv(data.len());
 
-    let data_len = pcm_data.len() as u32;
-    let sample_rate: u32 = 16_000;
-    let bits_per_sample: u16 = 16;
-    let channels: u16 = 1;
-    let byte_rate = sample_rate * ((bits_per_sample as u32) / 8) * (channels as u32);
-    let block_align = channels * (bits_per_sample / 8);
+        let mut packet = match SensorPacket::parse(data) {
+            Some(p) => p,
+            None => {
+                self.stats.record_parse_error();
+                return;
+            }
+        };
+        // Tenant is decided by the ingress port, never by the packet
+        packet.tenant = self.tenant.clone();
 
-    let mut wav = Vec::with_capacity(44 + pcm_data.len());
-    // RIFF header
-    wav.extend_from_slice(b"RIFF");
-    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
-    wav.extend_from_slice(b"WAVE");
-    // fmt sub-chunk
-    wav.extend_from_slice(b"fmt ");
-    wav.extend_from_slice(&(16u32).to_le_bytes()); // sub-chunk size
-    wav.extend_from_slice(&(1u16).to_le_bytes()); // PCM format
-    wav.extend_from_slice(&channels.to_le_bytes());
-    wav.extend_from_slice(&sample_rate.to_le_bytes());
-    wav.extend_from_slice(&byte_rate.to_le_bytes());
-    wav.extend_from_slice(&block_align.to_le_bytes());
-    wav.extend_from_slice(&bits_per_sample.to_le_bytes());
-    // data sub-chunk
-    wav.extend_from_slice(b"data");
-    wav.extend_from_slice(&data_len.to_le_bytes());
-    wav.extend_from_slice(pcm_data);
+        // Remember the sender so we can send VAD results back later
+        let entry = ClientEntry { addr: src, last_seen: self.clock.now() };
+        let prev_addr = self.client_map.insert(packet.sensor_id, entry).map(|prev| prev.addr);
+        if prev_addr.is_none() {
+            self.stats.set_sensor_clients(self.client_map.len());
+        }
 
-    tokio::fs::write(&path, &wav).await?;
-    Ok(path)
-}
+        // Same sensor_id from a new address → device reconnected
+        if let Some(prev) = prev_addr.filter(|prev| *prev != src) {
+            info!(
+                sensor_id = packet.sensor_id,
+                old = %prev,
+                new = %src,
+                "🔌 sensor reconnected — resetting smoother state"
+            );
+            self.smoother.reset_sensor(packet.sensor_id);
+        }
+        self.cluster.claim_sensor(packet.sensor_id, src);
 
-// ═══════════════════════════════════════════════════════════════════════
-//  Sensor receiver — remembers client addr, forwards packet for VAD
-// ═══════════════════════════════════════════════════════════════════════
+        debug!(
+            thread = thread_id,
+            sensor_id = packet.sensor_id,
+            seq = packet.seq,
+            data_type = packet.data_type,
+            src = %src,
+            "📊 sensor packet received"
+        );
 
-async fn sensor_recv_loop(
-    thread_id: usize,
-    socket: Arc<UdpSocket>,
-    tx: mpsc::Sender<SensorPacket>,
-    stats: Arc<Stats>,
-    client_map: ClientMap
-) -> anyhow::Result<()> {
+        if self.tx.try_send(packet).is_err() {
+            self.stats.record_channel_drop();
+        }
+    }
+}
+
+async fn sensor_recv_loop(thread_id: usize, socket: Arc<CapturedSocket>, ingress: SensorIngress) -> anyhow::Result<()> {
     debug!(thread = thread_id, "UDP sensor receiver started");
 
     let mut buf = vec![0u8; 65535];
@@ -839,90 +1586,420 @@ async fn sensor_recv_loop(
             Ok(v) => v,
             Err(e) => {
                 warn!(thread = thread_id, error = %e, "UDP sensor recv error");
-                stats.record_recv_error();
+                ingress.stats.record_recv_error();
                 continue;
             }
         };
+        ingress.handle(thread_id, &buf[..len], src);
+    }
+}
 
-        stats.record_recv(len);
+/// Run sensor receiver `thread_id` on its own OS thread around an
+/// io_uring (`--udp-io uring`), falling back to the epoll loop if the
+/// kernel refuses the ring.  The returned task ends with the thread.
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+fn spawn_sensor_uring(
+    thread_id: usize,
+    socket: Arc<CapturedSocket>,
+    ingress: SensorIngress
+) -> tokio::task::JoinHandle<()> {
+    let mut ring = match crate::uring::RecvRing::new(socket.as_raw_fd()) {
+        Ok(ring) => ring,
+        Err(e) => {
+            warn!(thread = thread_id, error = %e, "io_uring unavailable — sensor receiver on epoll");
+            return tokio::spawn(async move {
+                if let Err(e) = sensor_recv_loop(thread_id, socket, ingress).await {
+                    tracing::error!(thread = thread_id, error = %e, "UDP sensor receiver failed");
+                }
+            });
+        }
+    };
+    let stop = StopRingOnDrop(ring.stopper());
+    let (done_tx, done_rx) = tokio::sync::oneshot::channel::<()>();
+    let thread = std::thread::Builder::new().name(format!("uring-sensor-{thread_id}")).spawn(move || {
+        debug!(thread = thread_id, "UDP sensor receiver started (io_uring)");
+        let result = ring.run(
+            |data, src| {
+                socket.record_in(src, data);
+                ingress.handle(thread_id, data, src);
+                true
+            },
+            |e| {
+                warn!(thread = thread_id, error = %e, "UDP sensor recv error");
+                ingress.stats.record_recv_error();
+            }
+        );
+        if let Err(e) = result {
+            tracing::error!(thread = thread_id, error = %e, "UDP sensor receiver failed");
+        }
+        let _ = done_tx.send(());
+    });
+    if let Err(e) = thread {
+        tracing::error!(thread = thread_id, error = %e, "failed to start io_uring sensor receiver");
+    }
+    // Aborting this task (socket handover) stops the ring thread too, so
+    // it stops taking datagrams the next bridge should get
+    tokio::spawn(async move {
+        let _stop = stop;
+        let _ = done_rx.await;
+    })
+}
 
-        let packet = match SensorPacket::parse(&buf[..len]) {
-            Some(p) => p,
-            None => {
-                stats.record_parse_error();
-                continue;
+/// Stops an io_uring receiver when its waiter task is dropped.
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+struct StopRingOnDrop(crate::uring::RingStopper);
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+impl Drop for StopRingOnDrop {
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+/// Drop clients not heard from within `max_age`; returns how many.
+fn evict_stale_clients(map: &DashMap<u32, ClientEntry>, now: Instant, max_age: Duration) -> usize {
+    let before = map.len();
+    map.retain(|_, entry| now.duration_since(entry.last_seen) < max_age);
+    // Receivers may insert concurrently
+    before.saturating_sub(map.len())
+}
+
+/// Background task: periodically forget sensor-port clients silent for
+/// longer than `max_age_secs` (0 = disabled), keeping the gauge current.
+async fn evict_clients_loop(client_map: ClientMap, stats: Arc<Stats>, max_age_secs: u64, clock: SharedClock) {
+    if max_age_secs == 0 {
+        return;
+    }
+
+    let max_age = Duration::from_secs(max_age_secs);
+    let period = clock::eviction_period(max_age_secs);
+    loop {
+        clock.sleep(period).await;
+        let evicted = evict_stale_clients(&client_map, clock.now(), max_age);
+        let remaining = client_map.len();
+        stats.set_sensor_clients(remaining);
+        if evicted > 0 {
+            info!(evicted, remaining, "🧹 evicted stale sensor client addresses");
+        }
+    }
+}
+
+/// Background task: announce ESPs going online / offline from when each
+/// session was last heard, keeping the `online=` gauge current.
+async fn presence_loop(sessions: SessionMap, presence: Presence, stats: Arc<Stats>, clock: SharedClock) {
+    loop {
+        clock.sleep(Duration::from_secs(1)).await;
+        let heard: Vec<(String, SocketAddr, Instant)> = sessions
+            .iter()
+            .map(|entry| {
+                let name = entry.session.mac.map_or_else(|| entry.key().to_string(), |mac| format_mac(&mac));
+                (name, *entry.key(), entry.last_heard)
+            })
+            .collect();
+        stats.set_esp_online(presence.sweep(heard));
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Clip playback — REST requests resolved against ESP sessions
+// ═══════════════════════════════════════════════════════════════════════
+
+async fn clip_request_loop(
+    mut requests: mpsc::Receiver<PlayRequest>,
+    clips: ClipPlayer,
+    socket: Arc<CapturedSocket>,
+    sessions: SessionMap
+) {
+    while let Some(req) = requests.recv().await {
+        let result = match resolve_device(&sessions, &req.device).await {
+            Some(addr) =>
+                match req.what {
+                    Playback::Clip(clip) =>
+                        clips
+                            .play(socket.clone(), addr, &clip)
+                            .map(|duration| (addr, Some(duration)))
+                            .ok_or(PlayError::UnknownClip(clip)),
+                    Playback::Speech(text) =>
+                        clips.say(socket.clone(), addr, &text).map(|()| (addr, None)),
+                }
+            None => Err(PlayError::UnknownDevice(req.device)),
+        };
+        let _ = req.reply.send(result);
+    }
+}
+
+async fn broadcast_request_loop(
+    mut requests: mpsc::Receiver<BroadcastRequest>,
+    clips: ClipPlayer,
+    quiet: QuietHours,
+    socket: Arc<CapturedSocket>,
+    sessions: SessionMap,
+    online_within: Duration,
+    clock: SharedClock
+) {
+    while let Some(req) = requests.recv().await {
+        let now = clock.now();
+        let online: Vec<(String, SocketAddr)> = sessions
+            .iter()
+            .filter(|entry| now.saturating_duration_since(entry.last_heard) < online_within)
+            .map(|entry| {
+                let name = entry.session.mac.map_or_else(|| entry.key().to_string(), |mac| format_mac(&mac));
+                (name, *entry.key())
+            })
+            .collect();
+        broadcast::deliver(req, online, &clips, &quiet, &socket);
+    }
+}
+
+async fn diagnostics_request_loop(
+    mut requests: mpsc::Receiver<DiagnosticsRequest>,
+    clips: ClipPlayer,
+    socket: Arc<CapturedSocket>,
+    sessions: SessionMap,
+    taps: LoopbackTaps,
+    criteria: diagnostics::Criteria
+) {
+    while let Some(req) = requests.recv().await {
+        let Some(addr) = resolve_device(&sessions, &req.device).await else {
+            let _ = req.reply.send(Err(PlayError::UnknownDevice(req.device)));
+            continue;
+        };
+        // A clip playing now would mask the test tone
+        clips.stop(addr);
+        let (socket, taps) = (socket.clone(), taps.clone());
+        tokio::spawn(async move {
+            let report = diagnostics::run_loopback(&socket, addr, &taps, criteria).await;
+            let _ = req.reply.send(Ok(report));
+        });
+    }
+}
+
+async fn route_request_loop(
+    mut requests: mpsc::Receiver<RouteRequest>,
+    routes: AudioRoutes,
+    socket: Arc<CapturedSocket>,
+    sessions: SessionMap
+) {
+    while let Some(req) = requests.recv().await {
+        let from = resolve_device(&sessions, &req.spec.from).await;
+        let to = resolve_device(&sessions, &req.spec.to).await;
+        let result = match (from, to) {
+            (Some(from), Some(to)) => routes.open(req.spec, from, to, socket.clone()),
+            (None, _) => Err(RouteError::UnknownDevice(req.spec.from)),
+            (_, None) => Err(RouteError::UnknownDevice(req.spec.to)),
+        };
+        let _ = req.reply.send(result);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  OTA firmware — REST offers resolved against ESP sessions
+// ═══════════════════════════════════════════════════════════════════════
+
+async fn ota_request_loop(
+    mut requests: mpsc::Receiver<OtaRequest>,
+    ota: Ota,
+    socket: Arc<CapturedSocket>,
+    sessions: SessionMap
+) {
+    while let Some(req) = requests.recv().await {
+        let result = match resolve_device(&sessions, &req.device).await {
+            Some(addr) =>
+                match ota.begin(addr, &req.device, &req.image) {
+                    Ok((status, offer)) => {
+                        let _ = socket.send_to(&offer, addr).await;
+                        Ok(status)
+                    }
+                    Err(e) => Err(e),
+                }
+            None => Err(OtaError::UnknownDevice(req.device)),
+        };
+        let _ = req.reply.send(result);
+    }
+}
+
+async fn volume_request_loop(
+    mut requests: mpsc::Receiver<VolumeRequest>,
+    volume: VolumeControl,
+    quiet: QuietHours,
+    socket: Arc<CapturedSocket>,
+    sessions: SessionMap
+) {
+    while let Some(req) = requests.recv().await {
+        let result = match resolve_device(&sessions, &req.device).await {
+            Some(addr) => {
+                let target = sessions.get_mut(&addr).map(|mut entry| (entry.session.mac, entry.session.next_seq()));
+                let (mac, seq) = target.unwrap_or((None, 0));
+                let (level, packets) = volume.apply(&req.device, req.change, mac, seq, quiet.volume_cap(&req.device));
+                for packet in packets {
+                    let _ = socket.send_to(&packet, addr).await;
+                }
+                Ok(OutputStatus { device: req.device, addr: addr.to_string(), level })
             }
+            None => Err(VolumeError::UnknownDevice(req.device)),
         };
+        let _ = req.reply.send(result);
+    }
+}
 
-        // Remember the sender so we can send VAD results back later
-        {
-            let mut map = client_map.write().await;
-            map.insert(packet.sensor_id, src);
+/// Send a device's quiet-hours volume cap when its window opens and its
+/// tracked volume back when it closes.  Devices not connected are tried
+/// again on the next tick.
+async fn quiet_hours_loop(
+    quiet: QuietHours,
+    volume: VolumeControl,
+    socket: Arc<CapturedSocket>,
+    sessions: SessionMap,
+    clock: SharedClock
+) {
+    loop {
+        clock.sleep(Duration::from_secs(30)).await;
+        let now = chrono::DateTime::<chrono::Utc>::from(clock.system_now());
+        for (device, cap) in quiet.volume_transitions(now) {
+            let Some(addr) = resolve_device(&sessions, &device).await else {
+                continue;
+            };
+            let target = sessions.get_mut(&addr).map(|mut entry| (entry.session.mac, entry.session.next_seq()));
+            let (mac, seq) = target.unwrap_or((None, 0));
+            let tracked = volume.level(&device).volume;
+            let sent = cap.map_or(tracked, |cap| cap.min(tracked));
+            let _ = socket.send_to(&output::volume_packet(sent, mac, seq), addr).await;
+            quiet.mark_capped(&device, cap.is_some());
+            info!(device = %device, volume = sent, quiet = cap.is_some(), "🌙 quiet hours volume sent");
         }
+    }
+}
 
-        debug!(
-            thread = thread_id,
-            sensor_id = packet.sensor_id,
-            seq = packet.seq,
-            data_type = packet.data_type,
-            src = %src,
-            "📊 sensor packet received"
-        );
-
-        if tx.try_send(packet).is_err() {
-            stats.record_channel_drop();
+/// Repeat offers to devices that have not answered yet.
+async fn ota_retry_loop(ota: Ota, socket: Arc<CapturedSocket>, clock: SharedClock) {
+    loop {
+        clock.sleep(Duration::from_secs(1)).await;
+        for (addr, offer) in ota.due_offers() {
+            debug!(dst = %addr, "📦 repeating OTA offer");
+            let _ = socket.send_to(&offer, addr).await;
         }
     }
 }
 
+/// Map a device name (`ip:port`, or a MAC seen in a notification
+/// session) to its UDP address.
+async fn resolve_device(sessions: &SessionMap, device: &str) -> Option<SocketAddr> {
+    device_addr(sessions, device)
+}
+
+fn device_addr(sessions: &SessionMap, device: &str) -> Option<SocketAddr> {
+    if let Ok(addr) = device.parse() {
+        return Some(addr);
+    }
+    sessions
+        .iter()
+        .find(|entry| {
+            entry.session.mac.is_some_and(|mac| format_mac(&mac).eq_ignore_ascii_case(device))
+        })
+        .map(|entry| entry.session.addr)
+}
+
 // ═══════════════════════════════════════════════════════════════════════
 //  Response handler — sends VAD results back to sensor clients
 // ═══════════════════════════════════════════════════════════════════════
 
 async fn vad_response_loop(
     mut vad_rx: mpsc::Receiver<VadResult>,
-    sensor_socket: Arc<UdpSocket>,
+    sensor_socket: Arc<CapturedSocket>,
     client_map: ClientMap,
     persistent_oai: Option<Arc<OpenAiSession>>,
-    base_instructions: String
+    prompt: PromptContext,
+    opts: ResponseOptions,
+    session_logs: SessionLogs
 ) -> anyhow::Result<()> {
-    debug!("VAD response handler started");
+    debug!(
+        version = opts.version,
+        coalesce_ms = opts.coalesce.as_millis() as u64,
+        mode = ?opts.coalesce_mode,
+        "VAD response handler started"
+    );
 
     let mut last_mode: Option<PromptMode> = None;
+    let coalescing = !opts.coalesce.is_zero();
+    let mut coalescer = Coalescer::new(opts.coalesce_mode);
+    let mut flush = tokio::time::interval(
+        if coalescing { opts.coalesce } else { std::time::Duration::from_secs(3600) }
+    );
+    flush.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
-    while let Some(result) = vad_rx.recv().await {
-        // Only send VAD results back for sensor/emotional packets
-        if result.kind != crate::vad::VadKind::Audio {
+    loop {
+        let first = tokio::select! {
+            r = vad_rx.recv() => match r {
+                Some(r) => r,
+                None => break,
+            },
+            _ = flush.tick(), if coalescing => {
+                send_vad_responses(&coalescer.drain(), &sensor_socket, &client_map, opts.version).await;
+                continue;
+            }
+        };
+
+        // Take whatever else is already queued and answer it in one batch
+        let mut batch = vec![first];
+        while batch.len() < udp_batch::MAX_BATCH {
+            match vad_rx.try_recv() {
+                Ok(r) => batch.push(r),
+                Err(_) => break,
+            }
+        }
+
+        let mut outgoing = Vec::with_capacity(batch.len());
+        for result in batch {
+            // Only send VAD results back for sensor/emotional packets
+            if result.kind == crate::vad::VadKind::Audio {
+                session_logs.vad(result.sensor_id, result.is_active, result.energy);
+                continue;
+            }
             if let Some(ref oai) = persistent_oai {
                 let mode = prompt_mode_from_vad(&result);
                 if last_mode != Some(mode) {
-                    let instructions = build_prompt_instructions(&base_instructions, mode, &result);
-                    oai.update_instructions(&instructions).await;
-                    info!(mode = ?mode, "updated OpenAI prompt from emotional VAD");
+                    prompt.set_emotion(
+                        format!("{mode:?}").to_lowercase(),
+                        emotion_style(mode, &result)
+                    );
+                    oai.update_instructions(&prompt.instructions().await).await;
+                    info!(mode = ?mode, trace = result.trace.map(tracing::field::display), "updated OpenAI prompt from emotional VAD");
                     last_mode = Some(mode);
                 }
             }
 
-            let response = VadResponsePacket::from_vad_result(&result);
-            let bytes = response.to_bytes();
+            if coalescing {
+                coalescer.push(result);
+            } else {
+                outgoing.push(result);
+            }
+        }
+        send_vad_responses(&outgoing, &sensor_socket, &client_map, opts.version).await;
+    }
 
-            let dst = {
-                let map = client_map.read().await;
-                map.get(&result.sensor_id).copied()
-            };
+    // Don't lose the last partial window on shutdown
+    send_vad_responses(&coalescer.drain(), &sensor_socket, &client_map, opts.version).await;
 
-            if let Some(addr) = dst {
-                if let Err(e) = sensor_socket.send_to(&bytes, addr).await {
-                    warn!(error = %e, dst = %addr, "failed to send VAD response");
-                } else {
-                    debug!(
-                        sensor_id = result.sensor_id,
-                        seq = result.seq,
-                        dst = %addr,
-                        "📤 VAD result sent to sensor client"
-                    );
-                }
-            } else {
+    Ok(())
+}
+
+/// Encode results and send each to its sensor's last known address, as
+/// one batch.
+async fn send_vad_responses(
+    results: &[VadResult],
+    sensor_socket: &CapturedSocket,
+    client_map: &ClientMap,
+    version: u8
+) {
+    let mut sent = Vec::with_capacity(results.len());
+    let mut packets = Vec::with_capacity(results.len());
+    for result in results {
+        match client_map.get(&result.sensor_id).map(|entry| entry.addr) {
+            Some(addr) => {
+                packets.push((VadResponsePacket::from_vad_result(result).to_bytes(version), addr));
+                sent.push(result);
+            }
+            None => {
                 debug!(
                     sensor_id = result.sensor_id,
                     "no known client address for sensor, skipping response"
@@ -930,15 +2007,32 @@ async fn vad_response_loop(
             }
         }
     }
+    if packets.is_empty() {
+        return;
+    }
 
-    Ok(())
+    let batch: Vec<(&[u8], SocketAddr)> = packets
+        .iter()
+        .map(|(bytes, addr)| (bytes.as_slice(), *addr))
+        .collect();
+    let failed = sensor_socket.send_batch(&batch).await;
+    for (i, e) in &failed {
+        warn!(error = %e, dst = %packets[*i].1, trace = sent[*i].trace.map(tracing::field::display), "failed to send VAD response");
+    }
+    debug!(
+        sent = packets.len() - failed.len(),
+        failed = failed.len(),
+        first_seq = sent[0].seq,
+        first_trace = sent[0].trace.map(tracing::field::display),
+        "📤 VAD results sent to sensor clients"
+    );
 }
 
 // ═══════════════════════════════════════════════════════════════════════
 //  Test receiver — accepts any data, checks if source is a known ESP
 // ═══════════════════════════════════════════════════════════════════════
 
-async fn test_recv_loop(socket: Arc<UdpSocket>, sessions: SessionMap) -> anyhow::Result<()> {
+async fn test_recv_loop(socket: Arc<CapturedSocket>, sessions: SessionMap) -> anyhow::Result<()> {
     info!("🧪 Test port receiver started — waiting for any data");
 
     let mut buf = vec![0u8; 65535];
@@ -953,10 +2047,7 @@ async fn test_recv_loop(socket: Arc<UdpSocket>, sessions: SessionMap) -> anyhow:
         };
 
         // Check if this source IP has an active ESP audio session
-        let is_known_esp = {
-            let map = sessions.read().await;
-            map.keys().any(|addr| addr.ip() == src.ip())
-        };
+        let is_known_esp = sessions.iter().any(|entry| entry.key().ip() == src.ip());
 
         let hex_preview: String = buf[..len.min(64)]
             .iter()
@@ -996,7 +2087,7 @@ async fn test_recv_loop(socket: Arc<UdpSocket>, sessions: SessionMap) -> anyhow:
 //  Socket helpers
 // ═══════════════════════════════════════════════════════════════════════
 
-async fn bind_reuseport(addr: &str, recv_buf_size: usize) -> anyhow::Result<UdpSocket> {
+pub(crate) async fn bind_reuseport(addr: &str, recv_buf_size: usize) -> anyhow::Result<tokio::net::UdpSocket> {
     use std::net::SocketAddr;
     let parsed: SocketAddr = addr.parse()?;
 
@@ -1015,5 +2106,88 @@ async fn bind_reuseport(addr: &str, recv_buf_size: usize) -> anyhow::Result<UdpS
     socket.bind(&parsed.into())?;
 
     let std_socket: std::net::UdpSocket = socket.into();
-    Ok(UdpSocket::from_std(std_socket)?)
+    Ok(tokio::net::UdpSocket::from_std(std_socket)?)
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+//  Tests
+// ═══════════════════════════════════════════════════════════════════════
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evict_stale_clients() {
+        let now = Instant::now();
+        let addr: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+        let map = DashMap::new();
+        map.insert(1, ClientEntry { addr, last_seen: now - Duration::from_secs(120) });
+        map.insert(2, ClientEntry { addr, last_seen: now - Duration::from_secs(5) });
+
+        assert_eq!(evict_stale_clients(&map, now, Duration::from_secs(60)), 1);
+        assert!(map.contains_key(&2) && !map.contains_key(&1));
+        assert_eq!(evict_stale_clients(&map, now, Duration::from_secs(60)), 0);
+    }
+
+    #[test]
+    fn test_session_limits() {
+        let start = Instant::now();
+        let addr: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+        let mut session = EspSession::new(addr, start);
+        let limits = SessionLimits { max_duration: Some(Duration::from_secs(60)), max_bytes: Some(1000) };
+        let later = start + Duration::from_secs(61);
+
+        // Only Receiving sessions are capped
+        assert_eq!(limits.exceeded(&session, later), None);
+        session.state = SessionState::Receiving;
+        assert_eq!(limits.exceeded(&session, start), None);
+        assert_eq!(limits.exceeded(&session, later), Some(EndReason::MaxSecs));
+        session.record_audio(0, &[0u8; 1000]);
+        assert_eq!(limits.exceeded(&session, start), Some(EndReason::MaxBytes));
+        assert!(!SessionLimits::default().enabled());
+        assert_eq!(SessionLimits::default().exceeded(&session, later), None);
+    }
+
+    #[tokio::test]
+    async fn test_restart_within_the_same_second_keeps_the_new_wav() {
+        let dir = std::env::temp_dir().join(format!("vad-udp-restart-{}", std::process::id()));
+        let wavs = WavRecorder::new(dir.to_str().unwrap(), Duration::from_secs(1));
+        let src: SocketAddr = "10.0.0.7:5000".parse().unwrap();
+        let sessions: SessionMap = Arc::new(DashMap::new());
+        let mut entry = EspSessionEntry::new(src, Instant::now());
+        entry.session.state = SessionState::Receiving;
+        entry.wav = Some(wavs.start(src, 1));
+        entry.wav.as_ref().unwrap().write(&[1u8; 640]);
+        sessions.insert(src, entry);
+
+        // START again right away: the old WAV is discarded, a new one starts
+        let policies = StartPolicies::new(StartPolicy::Restart);
+        let logs = SessionLogs::new(None, crate::device_data::DeviceFiles::default());
+        let device = src.to_string();
+        assert_eq!(duplicate_start(src, &device, &sessions, &policies, &logs), Some(StartPolicy::Restart));
+        let wav = wavs.start(src, 1);
+        wav.write(&[2u8; 320]);
+        let path = wav.finish().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().flatten().map(|e| e.path()).collect();
+        let bytes = std::fs::read(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files, vec![std::path::PathBuf::from(&path)], "only the new WAV is left");
+        let bytes = bytes.unwrap();
+        assert_eq!((bytes.len(), bytes[44]), (44 + 320, 2));
+    }
+
+    #[test]
+    fn test_end_ack_status() {
+        let ack = build_ack(9, CTRL_SESSION_END, EndOutcome::Saved.ack_status());
+        let pkt = EspPacket::parse(&ack).unwrap();
+        assert_eq!(pkt.seq_num, 9);
+        assert_eq!(pkt.control_cmd(), Some(CTRL_ACK));
+        assert_eq!(&pkt.payload[..], &[CTRL_ACK, CTRL_SESSION_END, ACK_OK]);
+        assert_eq!(EndOutcome::NoSession.ack_status(), ACK_NO_SESSION);
+        assert_eq!(EndOutcome::NoAudio.ack_status(), ACK_NO_AUDIO);
+        assert_eq!(EndOutcome::SaveFailed.ack_status(), ACK_SAVE_FAILED);
+    }
 }
@@ -0,0 +1,196 @@
+use crate::config::Config;
+
+// ─────────────────────────────────────────────────────────────────────
+//  ZeroMQ transport (`--zmq-pull` / `--zmq-pub`, `--features zmq`)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  For labs that already pipe sensor data through ZeroMQ.  With
+//  `--zmq-pull` the instance takes its sensor packets from a PULL
+//  socket instead of the UDP ports and publishes the results on a PUB
+//  socket:
+//
+//    PULL  --zmq-pull   every frame is one binary sensor packet (the
+//                       sensor-port wire format)
+//    PUB   --zmq-pub    [topic, VAD response]: topic `vad/[<tenant>/]<id>`,
+//                       response in `--vad-response-version` format
+//
+//  Both sockets bind (`tcp://0.0.0.0:5557`, `ipc:///tmp/vad.pull`, ...);
+//  PUSH / SUB peers connect.  Unlike UDP, a full pipeline queue pushes
+//  back on the PULL socket (and so on the peers' high-water marks)
+//  instead of dropping packets.  Only sensor-vector results are
+//  published, as on UDP.  The REST API runs as usual; the ESP audio
+//  port and the OpenAI session are not started.
+//
+//  The sockets are ZeroMQ-compatible but implemented in pure Rust (the
+//  `zeromq` crate, no libzmq).
+
+/// Whether this build can serve the configured ZeroMQ endpoints.
+pub fn check(config: &Config) -> anyhow::Result<()> {
+    if !config.zmq_pull.is_empty() && !cfg!(feature = "zmq") {
+        anyhow::bail!("--zmq-pull requires a build with `--features zmq`");
+    }
+    if config.zmq_pull.is_empty() && !config.zmq_pub.is_empty() {
+        anyhow::bail!("--zmq-pub publishes the results of --zmq-pull packets; set both");
+    }
+    Ok(())
+}
+
+#[cfg(feature = "zmq")]
+pub use socket::run;
+
+#[cfg(not(feature = "zmq"))]
+pub async fn run(
+    _config: &Config,
+    _tx: crate::shard::ShardedSender,
+    _vad_rx: tokio::sync::mpsc::Receiver<crate::vad::VadResult>,
+    _stats: std::sync::Arc<crate::stats::Stats>
+) -> crate::error::Result<()> {
+    Err(crate::error::BridgeError::transport("built without `--features zmq`"))
+}
+
+#[cfg(feature = "zmq")]
+mod socket {
+    use crate::config::Config;
+    use crate::error::{ ResultExt, Result };
+    use crate::sensor::SensorPacket;
+    use crate::shard::ShardedSender;
+    use crate::stats::Stats;
+    use crate::tenants::TenantId;
+    use crate::vad::{ VadKind, VadResult };
+    use crate::vad_response::VadResponsePacket;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+    use tracing::{ debug, info, warn };
+    use zeromq::{ PubSocket, PullSocket, Socket, SocketRecv, SocketSend, ZmqMessage };
+
+    /// Bind the sockets, then pump PULL frames into the VAD workers and
+    /// their results out of PUB until the pipeline stops.
+    pub async fn run(
+        config: &Config,
+        tx: ShardedSender,
+        mut vad_rx: mpsc::Receiver<VadResult>,
+        stats: Arc<Stats>
+    ) -> Result<()> {
+        let mut pull = PullSocket::new();
+        pull.bind(&config.zmq_pull).await.transport(format!("ZeroMQ PULL {}", config.zmq_pull))?;
+        let mut publisher = match config.zmq_pub.as_str() {
+            "" => None,
+            endpoint => {
+                let mut socket = PubSocket::new();
+                socket.bind(endpoint).await.transport(format!("ZeroMQ PUB {endpoint}"))?;
+                Some(socket)
+            }
+        };
+        info!(pull = %config.zmq_pull, publish = %config.zmq_pub, tenant = %config.tenant_id, "🔌 ZeroMQ sockets bound");
+
+        let tenant = TenantId::from(config.tenant_id.as_str());
+        tokio::spawn(pull_loop(pull, tx, stats, tenant.clone()));
+
+        let version = config.vad_response_version;
+        while let Some(result) = vad_rx.recv().await {
+            let Some(ref mut socket) = publisher else {
+                continue;
+            };
+            if result.kind == VadKind::Audio {
+                continue;
+            }
+            let mut msg = ZmqMessage::from(topic(&tenant, result.sensor_id));
+            msg.push_back(VadResponsePacket::from_vad_result(&result).to_bytes(version).into());
+            // No subscribers is fine (PUB drops)
+            if let Err(e) = socket.send(msg).await {
+                warn!(error = %e, "ZeroMQ publish failed");
+            }
+        }
+        debug!(tenant = %tenant, "ZeroMQ transport stopped");
+        Ok(())
+    }
+
+    async fn pull_loop(mut pull: PullSocket, tx: ShardedSender, stats: Arc<Stats>, tenant: TenantId) {
+        loop {
+            let msg = match pull.recv().await {
+                Ok(msg) => msg,
+                Err(e) => {
+                    warn!(error = %e, "ZeroMQ recv error");
+                    stats.record_recv_error();
+                    continue;
+                }
+            };
+            for frame in msg.iter() {
+                stats.record_recv(frame.len());
+                let Some(mut packet) = SensorPacket::parse(frame) else {
+                    stats.record_parse_error();
+                    continue;
+                };
+                packet.tenant = tenant.clone();
+                // Backpressure instead of dropping: ZeroMQ peers queue
+                if tx.send(packet).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// PUB topic of `sensor_id`'s results.
+    fn topic(tenant: &TenantId, sensor_id: u32) -> String {
+        match &**tenant {
+            "" => format!("vad/{sensor_id}"),
+            tenant => format!("vad/{tenant}/{sensor_id}"),
+        }
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    //  Tests
+    // ─────────────────────────────────────────────────────────────────
+
+    #[cfg(test)]
+    mod tests {
+        use crate::bridge::{ serve_instance, Transport };
+        use crate::config::Cli;
+        use crate::sensor::{ SensorPacket, SensorVector, DATA_TYPE_SENSOR_VECTOR };
+        use crate::vad_response::VadResponsePacket;
+        use clap::Parser;
+        use std::time::Duration;
+        use zeromq::{ PushSocket, Socket, SocketRecv, SocketSend, SubSocket, ZmqMessage };
+
+        #[tokio::test]
+        async fn test_packets_in_over_pull_results_out_over_pub() {
+            let dir = std::env::temp_dir().join(format!("vad-zmq-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let pull = format!("ipc://{}", dir.join("pull").display());
+            let publish = format!("ipc://{}", dir.join("pub").display());
+            let config = Cli::parse_from([
+                "vad-sensor-bridge",
+                "--api-port", "0",
+                "--zmq-pull", &pull,
+                "--zmq-pub", &publish,
+            ]).serve;
+            let bridge = tokio::spawn(serve_instance(config, None, Transport::Zmq));
+            tokio::time::sleep(Duration::from_millis(300)).await;
+
+            let mut sub = SubSocket::new();
+            sub.connect(&publish).await.unwrap();
+            sub.subscribe("vad/").await.unwrap();
+            let mut push = PushSocket::new();
+            push.connect(&pull).await.unwrap();
+            // Let the subscription reach the publisher
+            tokio::time::sleep(Duration::from_millis(200)).await;
+
+            let packet = SensorPacket {
+                data_type: DATA_TYPE_SENSOR_VECTOR,
+                sensor_id: 9,
+                seq: 3,
+                payload: SensorVector { known_face: 1.0, ..SensorVector::default() }.to_payload(),
+                ..SensorPacket::parse(&[0u8; crate::sensor::HEADER_SIZE]).unwrap()
+            };
+            push.send(ZmqMessage::from(packet.to_binary())).await.unwrap();
+
+            let msg = tokio::time::timeout(Duration::from_secs(5), sub.recv()).await.unwrap().unwrap();
+            assert_eq!(msg.get(0).unwrap().as_ref(), b"vad/9");
+            let (response, _) = VadResponsePacket::from_bytes(msg.get(1).unwrap()).unwrap();
+            assert_eq!((response.sensor_id, response.seq), (9, 3));
+
+            bridge.abort();
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+}
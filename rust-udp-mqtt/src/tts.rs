@@ -0,0 +1,277 @@
+use crate::capture::CapturedSocket;
+use crate::config::Config;
+use crate::downlink_pacing::DownlinkPacer;
+use crate::esp_audio_protocol::{ build_audio_down, build_control, CTRL_STREAM_END, ESP_MAX_PAYLOAD };
+use std::net::SocketAddr;
+use std::time::Duration;
+use tracing::{ info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Text-to-speech backends
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Some deployments use a text LLM plus a separate TTS instead of the
+//  Realtime API.  With `--tts-backend` set, text sent to
+//  `POST /devices/{device}/say` is synthesized and streamed to the ESP
+//  as AUDIO_DOWN + STREAM_END, independent of the OpenAI session:
+//
+//    azure       Azure Speech REST (`--azure-speech-region`), SSML in,
+//                `raw-16khz-16bit-mono-pcm` out
+//    elevenlabs  ElevenLabs `text-to-speech/{voice}` with
+//                `output_format=pcm_16000` (`--elevenlabs-model`)
+//
+//  Both return the ESP's native 16 kHz s16 mono, so the response body
+//  is forwarded as it arrives, paced at real time like a clip: the
+//  first words play before synthesis of the rest has finished.
+
+/// Which TTS service `--tts-backend` selects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TtsKind {
+    Azure,
+    Elevenlabs,
+}
+
+/// Default Azure neural voice.
+const AZURE_DEFAULT_VOICE: &str = "en-US-JennyNeural";
+/// Default ElevenLabs voice id ("Rachel").
+const ELEVENLABS_DEFAULT_VOICE: &str = "21m00Tcm4TlvDq8ikWAM";
+
+/// A configured TTS backend.
+#[derive(Debug)]
+pub enum TtsEngine {
+    Azure {
+        client: reqwest::Client,
+        key: String,
+        region: String,
+        voice: String,
+    },
+    ElevenLabs {
+        client: reqwest::Client,
+        key: String,
+        voice: String,
+        model: String,
+    },
+}
+
+impl TtsEngine {
+    /// Build the engine selected by `--tts-backend` (None if unset).
+    pub fn from_config(config: &Config) -> anyhow::Result<Option<Self>> {
+        let Some(kind) = config.tts_backend else {
+            return Ok(None);
+        };
+        let key = config.tts_api_key.trim().to_string();
+        if key.is_empty() {
+            anyhow::bail!("--tts-backend {kind:?} set but no --tts-api-key / TTS_API_KEY");
+        }
+        let voice = |default: &str| {
+            if config.tts_voice.is_empty() { default.to_string() } else { config.tts_voice.clone() }
+        };
+        let client = reqwest::Client
+            ::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?;
+        Ok(
+            Some(match kind {
+                TtsKind::Azure =>
+                    TtsEngine::Azure {
+                        client,
+                        key,
+                        region: config.azure_speech_region.clone(),
+                        voice: voice(AZURE_DEFAULT_VOICE),
+                    },
+                TtsKind::Elevenlabs =>
+                    TtsEngine::ElevenLabs {
+                        client,
+                        key,
+                        voice: voice(ELEVENLABS_DEFAULT_VOICE),
+                        model: config.elevenlabs_model.clone(),
+                    },
+            })
+        )
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TtsEngine::Azure { .. } => "azure",
+            TtsEngine::ElevenLabs { .. } => "elevenlabs",
+        }
+    }
+
+    /// HTTP request that synthesizes `text` to 16 kHz s16 mono PCM.
+    fn request(&self, text: &str) -> reqwest::RequestBuilder {
+        match self {
+            TtsEngine::Azure { client, key, region, voice } => {
+                let ssml = format!(
+                    "<speak version='1.0' xml:lang='en-US'><voice name='{}'>{}</voice></speak>",
+                    xml_escape(voice),
+                    xml_escape(text)
+                );
+                client
+                    .post(format!("https://{region}.tts.speech.microsoft.com/cognitiveservices/v1"))
+                    .header("Ocp-Apim-Subscription-Key", key)
+                    .header("Content-Type", "application/ssml+xml")
+                    .header("X-Microsoft-OutputFormat", "raw-16khz-16bit-mono-pcm")
+                    .header("User-Agent", "vad-sensor-bridge")
+                    .body(ssml)
+            }
+            TtsEngine::ElevenLabs { client, key, voice, model } =>
+                client
+                    .post(format!("https://api.elevenlabs.io/v1/text-to-speech/{voice}/stream"))
+                    .query(&[("output_format", "pcm_16000")])
+                    .header("xi-api-key", key)
+                    .json(&serde_json::json!({ "text": text, "model_id": model })),
+        }
+    }
+
+    /// Synthesize `text` to 16 kHz s16 mono PCM in full (broadcasts:
+    /// one synthesis, many devices).
+    pub async fn synthesize(&self, text: &str) -> anyhow::Result<Vec<u8>> {
+        let resp = self.request(text).send().await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            anyhow::bail!("{} TTS returned {status}: {}", self.name(), body.trim());
+        }
+        let mut pcm = resp.bytes().await?.to_vec();
+        pcm.truncate(pcm.len() & !1);
+        info!(backend = self.name(), chars = text.chars().count(), bytes = pcm.len(), "🗣️ TTS synthesized");
+        Ok(pcm)
+    }
+
+    /// Synthesize `text` and stream it to `addr` as AUDIO_DOWN packets
+    /// paced by `downlink`, followed by STREAM_END.  Returns the audio
+    /// duration.
+    pub async fn speak(
+        &self,
+        socket: &CapturedSocket,
+        addr: SocketAddr,
+        text: &str,
+        downlink: &DownlinkPacer
+    ) -> anyhow::Result<Duration> {
+        let started = std::time::Instant::now();
+        let mut resp = self.request(text).send().await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            anyhow::bail!("{} TTS returned {status}: {}", self.name(), body.trim());
+        }
+
+        let chunk_time = Duration::from_secs_f64((ESP_MAX_PAYLOAD as f64) / (16_000.0 * 2.0));
+        let mut pending: Vec<u8> = Vec::with_capacity(ESP_MAX_PAYLOAD * 4);
+        let mut seq: u16 = 0;
+        let mut total = 0usize;
+        let mut first_audio = None;
+
+        let result = loop {
+            let body = match resp.chunk().await {
+                Ok(Some(bytes)) => bytes,
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            };
+            first_audio.get_or_insert_with(|| started.elapsed());
+            pending.extend_from_slice(&body);
+            while pending.len() >= ESP_MAX_PAYLOAD {
+                downlink.pace(addr, chunk_time).await;
+                let _ = socket.send_to(&build_audio_down(seq, 0, &pending[..ESP_MAX_PAYLOAD]), addr).await;
+                seq = seq.wrapping_add(1);
+                total += ESP_MAX_PAYLOAD;
+                pending.drain(..ESP_MAX_PAYLOAD);
+            }
+        };
+        // Flush whole samples left over, then end the stream even on error
+        pending.truncate(pending.len() & !1);
+        if !pending.is_empty() {
+            downlink.pace(addr, chunk_time.mul_f64((pending.len() as f64) / (ESP_MAX_PAYLOAD as f64))).await;
+            let _ = socket.send_to(&build_audio_down(seq, 0, &pending), addr).await;
+            seq = seq.wrapping_add(1);
+            total += pending.len();
+        }
+        let _ = socket.send_to(&build_control(seq, CTRL_STREAM_END, 0), addr).await;
+
+        let duration = Duration::from_secs_f64((total as f64) / (16_000.0 * 2.0));
+        match result {
+            Ok(()) => {
+                info!(
+                    backend = self.name(),
+                    esp = %addr,
+                    chars = text.chars().count(),
+                    first_audio_ms = first_audio.map(|d| d.as_millis() as u64),
+                    secs = format!("{:.1}", duration.as_secs_f64()),
+                    "🗣️ TTS playback finished"
+                );
+                Ok(duration)
+            }
+            Err(e) => {
+                warn!(backend = self.name(), esp = %addr, error = %e, "TTS stream interrupted");
+                Err(e.into())
+            }
+        }
+    }
+}
+
+/// Escape text for an SSML element / attribute.
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(kind: &str) -> TtsEngine {
+        let client = reqwest::Client::new();
+        match kind {
+            "azure" =>
+                TtsEngine::Azure {
+                    client,
+                    key: "k".into(),
+                    region: "westeurope".into(),
+                    voice: AZURE_DEFAULT_VOICE.into(),
+                },
+            _ =>
+                TtsEngine::ElevenLabs {
+                    client,
+                    key: "k".into(),
+                    voice: "v1".into(),
+                    model: "eleven_turbo_v2_5".into(),
+                },
+        }
+    }
+
+    #[test]
+    fn test_azure_request_is_escaped_ssml() {
+        let req = engine("azure").request("Tom & <Jerry>").build().unwrap();
+        assert_eq!(
+            req.url().as_str(),
+            "https://westeurope.tts.speech.microsoft.com/cognitiveservices/v1"
+        );
+        assert_eq!(req.headers()["X-Microsoft-OutputFormat"], "raw-16khz-16bit-mono-pcm");
+        let body = std::str::from_utf8(req.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert!(body.contains(">Tom &amp; &lt;Jerry&gt;</voice>"), "{body}");
+    }
+
+    #[test]
+    fn test_elevenlabs_request_asks_for_16k_pcm() {
+        let req = engine("elevenlabs").request("hi").build().unwrap();
+        assert_eq!(req.url().path(), "/v1/text-to-speech/v1/stream");
+        assert_eq!(req.url().query(), Some("output_format=pcm_16000"));
+        assert_eq!(req.headers()["xi-api-key"], "k");
+        let body: serde_json::Value = serde_json::from_slice(req.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(body["text"], "hi");
+    }
+}
@@ -0,0 +1,122 @@
+use std::io;
+use std::net::SocketAddr;
+use tokio::net::UdpSocket;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Batched UDP sends (sendmmsg)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  High-rate sensors with responses enabled, and OpenAI audio deltas cut
+//  into ~20 AUDIO_DOWN chunks each, make one `sendto` per datagram a
+//  noticeable share of the response path.  On Linux a batch goes out
+//  with one `sendmmsg` per MAX_BATCH datagrams; elsewhere it falls back
+//  to one `send_to` each.
+//
+//  A datagram the kernel refuses (e.g. unreachable destination) is
+//  reported and skipped; the rest of the batch is still sent.
+
+/// Most datagrams handed to one `sendmmsg` call.
+pub const MAX_BATCH: usize = 64;
+
+/// Send every `(payload, dst)` in order.  Returns the datagrams that
+/// failed, by index, with their error (empty = all sent).
+pub async fn send_batch(socket: &UdpSocket, packets: &[(&[u8], SocketAddr)]) -> Vec<(usize, io::Error)> {
+    let mut failed = Vec::new();
+    let mut next = 0;
+    while next < packets.len() {
+        let end = (next + MAX_BATCH).min(packets.len());
+        match send_some(socket, &packets[next..end]).await {
+            Ok(sent) => {
+                next += sent.max(1);
+            }
+            Err(e) => {
+                // The datagram at `next` was refused; skip it
+                failed.push((next, e));
+                next += 1;
+            }
+        }
+    }
+    failed
+}
+
+/// Send a prefix of `packets`; returns how many went out.
+#[cfg(target_os = "linux")]
+async fn send_some(socket: &UdpSocket, packets: &[(&[u8], SocketAddr)]) -> io::Result<usize> {
+    use std::os::fd::AsRawFd;
+    loop {
+        socket.writable().await?;
+        match socket.try_io(tokio::io::Interest::WRITABLE, || sendmmsg(socket.as_raw_fd(), packets)) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                continue;
+            }
+            result => {
+                return result;
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn send_some(socket: &UdpSocket, packets: &[(&[u8], SocketAddr)]) -> io::Result<usize> {
+    let (payload, dst) = packets[0];
+    socket.send_to(payload, dst).await.map(|_| 1)
+}
+
+#[cfg(target_os = "linux")]
+fn sendmmsg(fd: std::os::fd::RawFd, packets: &[(&[u8], SocketAddr)]) -> io::Result<usize> {
+    let addrs: Vec<socket2::SockAddr> = packets
+        .iter()
+        .map(|(_, dst)| socket2::SockAddr::from(*dst))
+        .collect();
+    let mut iovs: Vec<libc::iovec> = packets
+        .iter()
+        .map(|(payload, _)| libc::iovec { iov_base: payload.as_ptr() as *mut libc::c_void, iov_len: payload.len() })
+        .collect();
+    let mut msgs: Vec<libc::mmsghdr> = iovs
+        .iter_mut()
+        .zip(&addrs)
+        .map(|(iov, addr)| {
+            // SAFETY: all-zero is a valid mmsghdr (null pointers, zero
+            // lengths); this also clears musl's padding fields
+            let mut msg: libc::mmsghdr = unsafe { std::mem::zeroed() };
+            msg.msg_hdr.msg_name = addr.as_ptr() as *mut libc::c_void;
+            msg.msg_hdr.msg_namelen = addr.len();
+            msg.msg_hdr.msg_iov = iov;
+            msg.msg_hdr.msg_iovlen = 1;
+            msg
+        })
+        .collect();
+    // SAFETY: every header points into `addrs` / `iovs` / `packets`,
+    // which outlive the call; the kernel only writes `msg_len`
+    let sent = unsafe { libc::sendmmsg(fd, msgs.as_mut_ptr(), msgs.len() as _, 0) };
+    if sent < 0 { Err(io::Error::last_os_error()) } else { Ok(sent as usize) }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_batch_arrives_in_order_across_calls() {
+        let rx = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let tx = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let dst = rx.local_addr().unwrap();
+
+        let payloads: Vec<Vec<u8>> = (0..MAX_BATCH + 6).map(|i| vec![i as u8; 1 + (i % 7)]).collect();
+        let batch: Vec<(&[u8], SocketAddr)> = payloads
+            .iter()
+            .map(|p| (p.as_slice(), dst))
+            .collect();
+        assert!(send_batch(&tx, &batch).await.is_empty());
+
+        let mut buf = [0u8; 64];
+        for expected in &payloads {
+            let (len, src) = rx.recv_from(&mut buf).await.unwrap();
+            assert_eq!((&buf[..len], src), (expected.as_slice(), tx.local_addr().unwrap()));
+        }
+    }
+}
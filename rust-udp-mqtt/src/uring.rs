@@ -0,0 +1,558 @@
+// ─────────────────────────────────────────────────────────────────────
+//  io_uring sensor datapath (`--udp-io uring`, `--features io-uring`)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  The default datapath is tokio's: epoll readiness, then one
+//  `recvfrom` syscall per datagram.  With `--udp-io uring` each sensor
+//  receiver instead runs on its own OS thread around an io_uring with
+//  QUEUE_DEPTH RECVMSG requests always in flight; one `io_uring_enter`
+//  both re-arms the slots just drained and waits for the next
+//  completions, so a burst costs a handful of syscalls instead of one
+//  per packet.
+//
+//  The ring is a minimal hand-rolled binding over the raw syscalls
+//  (setup / mmap / enter) — just RECVMSG and POLL_ADD, no SQPOLL or
+//  registered buffers.  The socket is tokio's, so it is O_NONBLOCK (a
+//  dup would share that flag): a RECVMSG the kernel completes with
+//  EAGAIN instead of waiting is re-armed behind a POLL_ADD on the
+//  socket, never in a busy loop.  A `RingStopper` wakes the ring
+//  through an eventfd it polls, so the thread can be stopped while idle
+//  (socket handover).  Only the sensor port uses it: it is the high-rate,
+//  stateless path; the audio port's session handling stays on tokio.
+//  Needs Linux 5.6+; a kernel (or seccomp profile) without io_uring
+//  falls back to epoll with a warning.
+//
+//  `bench/bench_uring.sh` compares both datapaths (pps per CPU second).
+
+/// Receive datapath of the sensor port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum UdpIo {
+    /// tokio (epoll) — one recvfrom per datagram
+    Epoll,
+    /// io_uring RECVMSG ring per receiver thread (needs `--features io-uring`)
+    Uring,
+}
+
+/// Whether this build can serve `io`.
+pub fn check(io: UdpIo) -> anyhow::Result<()> {
+    if io == UdpIo::Uring && !cfg!(all(feature = "io-uring", target_os = "linux")) {
+        anyhow::bail!("--udp-io uring requires a Linux build with `--features io-uring`");
+    }
+    Ok(())
+}
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub use ring::{ RecvRing, RingStopper };
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod ring {
+    use std::io;
+    use std::net::SocketAddr;
+    use std::os::fd::{ AsRawFd, FromRawFd, OwnedFd, RawFd };
+    use std::sync::Arc;
+    use std::sync::atomic::{ AtomicU32, Ordering };
+
+    /// RECVMSG requests kept in flight per ring.
+    const QUEUE_DEPTH: u32 = 32;
+
+    /// Receive buffer per request (largest UDP payload).
+    const BUF_SIZE: usize = 65536;
+
+    /// SQ entries: every slot's request plus the stop poll.
+    const RING_ENTRIES: u32 = QUEUE_DEPTH * 2;
+
+    /// `user_data` bit of a slot's POLL_ADD (re-arm after EAGAIN).
+    const POLL_BIT: u64 = 1 << 32;
+
+    /// `user_data` of the stop eventfd's POLL_ADD.
+    const STOP: u64 = u64::MAX;
+
+    const IORING_OP_POLL_ADD: u8 = 6;
+    const IORING_OP_RECVMSG: u8 = 10;
+    const IORING_ENTER_GETEVENTS: u32 = 1;
+    const IORING_FEAT_SINGLE_MMAP: u32 = 1;
+    const IORING_OFF_SQ_RING: i64 = 0;
+    const IORING_OFF_CQ_RING: i64 = 0x800_0000;
+    const IORING_OFF_SQES: i64 = 0x1000_0000;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct SqringOffsets {
+        head: u32,
+        tail: u32,
+        ring_mask: u32,
+        ring_entries: u32,
+        flags: u32,
+        dropped: u32,
+        array: u32,
+        resv1: u32,
+        user_addr: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct CqringOffsets {
+        head: u32,
+        tail: u32,
+        ring_mask: u32,
+        ring_entries: u32,
+        overflow: u32,
+        cqes: u32,
+        flags: u32,
+        resv1: u32,
+        user_addr: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Params {
+        sq_entries: u32,
+        cq_entries: u32,
+        flags: u32,
+        sq_thread_cpu: u32,
+        sq_thread_idle: u32,
+        features: u32,
+        wq_fd: u32,
+        resv: [u32; 3],
+        sq_off: SqringOffsets,
+        cq_off: CqringOffsets,
+    }
+
+    #[repr(C)]
+    struct Sqe {
+        opcode: u8,
+        flags: u8,
+        ioprio: u16,
+        fd: i32,
+        off: u64,
+        addr: u64,
+        len: u32,
+        msg_flags: u32,
+        user_data: u64,
+        buf_index: u16,
+        personality: u16,
+        splice_fd_in: i32,
+        addr3: u64,
+        pad: u64,
+    }
+
+    #[repr(C)]
+    struct Cqe {
+        user_data: u64,
+        res: i32,
+        flags: u32,
+    }
+
+    /// One in-flight RECVMSG: its buffer and the headers pointing into it.
+    struct Slot {
+        buf: Box<[u8]>,
+        addr: libc::sockaddr_storage,
+        iov: libc::iovec,
+        msg: libc::msghdr,
+    }
+
+    /// A mapped region, unmapped on drop.
+    struct Mmap {
+        ptr: *mut u8,
+        len: usize,
+    }
+
+    impl Mmap {
+        fn new(fd: RawFd, len: usize, offset: i64) -> io::Result<Self> {
+            // SAFETY: fresh shared mapping of the ring fd; checked below
+            let ptr = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED | libc::MAP_POPULATE,
+                    fd,
+                    offset
+                )
+            };
+            if ptr == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { ptr: ptr as *mut u8, len })
+        }
+
+        /// Pointer to `T` at byte `offset` into the mapping.
+        fn at<T>(&self, offset: u32) -> *mut T {
+            debug_assert!((offset as usize) < self.len);
+            // SAFETY: offsets come from the kernel and lie in the mapping
+            unsafe { self.ptr.add(offset as usize) as *mut T }
+        }
+    }
+
+    impl Drop for Mmap {
+        fn drop(&mut self) {
+            // SAFETY: we own the mapping
+            unsafe {
+                libc::munmap(self.ptr as *mut libc::c_void, self.len);
+            }
+        }
+    }
+
+    /// An io_uring keeping QUEUE_DEPTH RECVMSG requests on one socket.
+    /// Lives on (and must stay on) the thread that runs it.
+    pub struct RecvRing {
+        ring_fd: RawFd,
+        socket: RawFd,
+        /// Written by `RingStopper::stop`; polled by the ring
+        stop: Arc<OwnedFd>,
+        /// SQ ring (and the CQ ring with IORING_FEAT_SINGLE_MMAP)
+        sq: Mmap,
+        cq: Option<Mmap>,
+        sqes: Mmap,
+        p: Params,
+        slots: Box<[Slot]>,
+        /// SQEs written but not yet submitted
+        pending: u32,
+        /// msg_flags of every RECVMSG (tests force MSG_DONTWAIT to get
+        /// the EAGAIN completions some kernels give non-blocking sockets)
+        recv_flags: u32,
+    }
+
+    // SAFETY: the raw pointers are into the ring's own mmaps and slot
+    // buffers; the ring is used by one thread at a time (`&mut self`)
+    unsafe impl Send for RecvRing {}
+
+    /// Makes a running [`RecvRing::run`] return, from any thread.
+    #[derive(Clone)]
+    pub struct RingStopper {
+        stop: Arc<OwnedFd>,
+    }
+
+    impl RingStopper {
+        /// Stop the ring (idempotent).  Datagrams it already received are
+        /// still handed to `on_datagram`.
+        pub fn stop(&self) {
+            let one = 1u64.to_ne_bytes();
+            // SAFETY: 8-byte write to the eventfd we co-own
+            unsafe {
+                libc::write(self.stop.as_raw_fd(), one.as_ptr() as *const libc::c_void, one.len());
+            }
+        }
+    }
+
+    impl RecvRing {
+        /// Set up a ring receiving from `socket` (not owned; must outlive
+        /// the ring).
+        pub fn new(socket: RawFd) -> io::Result<Self> {
+            // SAFETY: plain syscall; the fd is owned right away
+            let stop = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+            if stop < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: fresh fd, owned by nobody else
+            let stop = Arc::new(unsafe { OwnedFd::from_raw_fd(stop) });
+
+            let mut p = Params::default();
+            // SAFETY: io_uring_setup reads / writes `p` only
+            let ring_fd = unsafe { libc::syscall(libc::SYS_io_uring_setup, RING_ENTRIES, &mut p as *mut Params) };
+            if ring_fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let ring_fd = ring_fd as RawFd;
+            let close_on_err = |e: io::Error| {
+                // SAFETY: we own ring_fd
+                unsafe {
+                    libc::close(ring_fd);
+                }
+                e
+            };
+
+            let sq_len = (p.sq_off.array as usize) + (p.sq_entries as usize) * 4;
+            let cq_len = (p.cq_off.cqes as usize) + (p.cq_entries as usize) * std::mem::size_of::<Cqe>();
+            let single = p.features & IORING_FEAT_SINGLE_MMAP != 0;
+            let sq = Mmap::new(ring_fd, if single { sq_len.max(cq_len) } else { sq_len }, IORING_OFF_SQ_RING).map_err(
+                close_on_err
+            )?;
+            let cq = if single { None } else { Some(Mmap::new(ring_fd, cq_len, IORING_OFF_CQ_RING).map_err(close_on_err)?) };
+            let sqes = Mmap::new(ring_fd, (p.sq_entries as usize) * std::mem::size_of::<Sqe>(), IORING_OFF_SQES).map_err(
+                close_on_err
+            )?;
+
+            let slots = (0..QUEUE_DEPTH)
+                .map(|_| Slot {
+                    buf: vec![0u8; BUF_SIZE].into_boxed_slice(),
+                    // SAFETY: all-zero is valid for these C structs
+                    addr: unsafe { std::mem::zeroed() },
+                    iov: libc::iovec { iov_base: std::ptr::null_mut(), iov_len: 0 },
+                    msg: unsafe { std::mem::zeroed() },
+                })
+                .collect();
+            let mut ring = Self { ring_fd, socket, stop, sq, cq, sqes, p, slots, pending: 0, recv_flags: 0 };
+            for i in 0..QUEUE_DEPTH {
+                ring.arm(i as usize);
+            }
+            ring.push(IORING_OP_POLL_ADD, ring.stop.as_raw_fd(), 0, 0, libc::POLLIN as u32, STOP);
+            Ok(ring)
+        }
+
+        /// Handle that stops [`run`](Self::run) from another thread.
+        pub fn stopper(&self) -> RingStopper {
+            RingStopper { stop: self.stop.clone() }
+        }
+
+        fn cq_map(&self) -> &Mmap {
+            self.cq.as_ref().unwrap_or(&self.sq)
+        }
+
+        /// Queue a RECVMSG into slot `i` (submitted on the next enter).
+        fn arm(&mut self, i: usize) {
+            let slot = &mut self.slots[i];
+            slot.iov = libc::iovec { iov_base: slot.buf.as_mut_ptr() as *mut libc::c_void, iov_len: slot.buf.len() };
+            slot.msg.msg_name = &mut slot.addr as *mut _ as *mut libc::c_void;
+            slot.msg.msg_namelen = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+            slot.msg.msg_iov = &mut slot.iov;
+            slot.msg.msg_iovlen = 1;
+            let msg = &slot.msg as *const libc::msghdr as u64;
+            self.push(IORING_OP_RECVMSG, self.socket, msg, 1, self.recv_flags, i as u64);
+        }
+
+        /// Queue a POLL_ADD waiting for the socket to be readable before
+        /// slot `i` receives again.
+        fn arm_poll(&mut self, i: usize) {
+            self.push(IORING_OP_POLL_ADD, self.socket, 0, 0, libc::POLLIN as u32, POLL_BIT | (i as u64));
+        }
+
+        /// Queue one SQE (submitted on the next enter).  `op_flags` is
+        /// the msg_flags / poll_events union.
+        fn push(&mut self, opcode: u8, fd: RawFd, addr: u64, len: u32, op_flags: u32, user_data: u64) {
+            // SAFETY: head / tail / mask / array point into the SQ ring;
+            // only this thread produces SQEs
+            unsafe {
+                let tail_ptr = &*self.sq.at::<AtomicU32>(self.p.sq_off.tail);
+                let mask = *self.sq.at::<u32>(self.p.sq_off.ring_mask);
+                let tail = tail_ptr.load(Ordering::Relaxed);
+                let index = tail & mask;
+                let sqe = self.sqes.ptr.cast::<Sqe>().add(index as usize);
+                sqe.write(Sqe {
+                    opcode,
+                    flags: 0,
+                    ioprio: 0,
+                    fd,
+                    off: 0,
+                    addr,
+                    len,
+                    msg_flags: op_flags,
+                    user_data,
+                    buf_index: 0,
+                    personality: 0,
+                    splice_fd_in: 0,
+                    addr3: 0,
+                    pad: 0,
+                });
+                *self.sq.at::<u32>(self.p.sq_off.array).add(index as usize) = index;
+                tail_ptr.store(tail.wrapping_add(1), Ordering::Release);
+            }
+            self.pending += 1;
+        }
+
+        /// Submit pending SQEs and wait for at least one completion.
+        fn enter(&mut self) -> io::Result<()> {
+            loop {
+                // SAFETY: plain syscall on our ring fd
+                let ret = unsafe {
+                    libc::syscall(
+                        libc::SYS_io_uring_enter,
+                        self.ring_fd,
+                        self.pending,
+                        1u32,
+                        IORING_ENTER_GETEVENTS,
+                        std::ptr::null::<libc::sigset_t>(),
+                        0usize
+                    )
+                };
+                if ret >= 0 {
+                    self.pending -= (ret as u32).min(self.pending);
+                    return Ok(());
+                }
+                let e = io::Error::last_os_error();
+                if e.kind() != io::ErrorKind::Interrupted {
+                    return Err(e);
+                }
+            }
+        }
+
+        /// Receive until `on_datagram` returns false or the ring is
+        /// stopped.  Failed receives are passed to `on_error` and re-armed.
+        pub fn run(
+            &mut self,
+            mut on_datagram: impl FnMut(&[u8], SocketAddr) -> bool,
+            mut on_error: impl FnMut(io::Error)
+        ) -> io::Result<()> {
+            let mut done = Vec::with_capacity(QUEUE_DEPTH as usize);
+            loop {
+                self.enter()?;
+                let cq = self.cq_map();
+                // SAFETY: CQ head / tail / mask / cqes point into the CQ
+                // ring; only this thread consumes CQEs
+                let (head_ptr, tail, mask, cqes) = unsafe {
+                    (
+                        &*cq.at::<AtomicU32>(self.p.cq_off.head),
+                        (*cq.at::<AtomicU32>(self.p.cq_off.tail)).load(Ordering::Acquire),
+                        *cq.at::<u32>(self.p.cq_off.ring_mask),
+                        cq.at::<Cqe>(self.p.cq_off.cqes),
+                    )
+                };
+                let mut head = head_ptr.load(Ordering::Relaxed);
+                done.clear();
+                while head != tail {
+                    // SAFETY: `head & mask` indexes the CQE array
+                    let (user_data, res) = unsafe {
+                        let cqe = &*cqes.add((head & mask) as usize);
+                        (cqe.user_data, cqe.res)
+                    };
+                    head = head.wrapping_add(1);
+                    done.push((user_data, res));
+                }
+                head_ptr.store(head, Ordering::Release);
+
+                let mut keep_going = true;
+                for &(user_data, res) in &done {
+                    if user_data == STOP {
+                        keep_going = false;
+                        continue;
+                    }
+                    let i = (user_data & !POLL_BIT) as usize;
+                    if user_data & POLL_BIT != 0 {
+                        // Readable (or failed: the receive reports it)
+                        self.arm(i);
+                        continue;
+                    }
+                    if res == -libc::EAGAIN {
+                        // O_NONBLOCK socket, nothing queued: wait for data
+                        self.arm_poll(i);
+                        continue;
+                    } else if res < 0 {
+                        on_error(io::Error::from_raw_os_error(-res));
+                    } else if keep_going {
+                        let slot = &self.slots[i];
+                        // SAFETY: the kernel filled `addr` / `msg_namelen`
+                        let src = unsafe { socket2::SockAddr::new(slot.addr, slot.msg.msg_namelen) }.as_socket();
+                        if let Some(src) = src {
+                            keep_going = on_datagram(&slot.buf[..res as usize], src);
+                        }
+                    }
+                    self.arm(i);
+                }
+                if !keep_going {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    impl Drop for RecvRing {
+        fn drop(&mut self) {
+            // Closing the ring cancels the in-flight receives
+            // SAFETY: we own ring_fd
+            unsafe {
+                libc::close(self.ring_fd);
+            }
+        }
+    }
+
+    // ─────────────────────────────────────────────────────────────────
+    //  Tests
+    // ─────────────────────────────────────────────────────────────────
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::os::fd::AsRawFd;
+
+        #[test]
+        fn test_ring_receives_bursts_deeper_than_the_queue() {
+            let rx = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            let tx = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            // Like the bridge's tokio sockets
+            rx.set_nonblocking(true).unwrap();
+            let mut ring = match RecvRing::new(rx.as_raw_fd()) {
+                Ok(ring) => ring,
+                Err(e) => {
+                    eprintln!("io_uring unavailable here ({e}), skipping");
+                    return;
+                }
+            };
+            let n = (QUEUE_DEPTH as usize) * 3;
+            for i in 0..n {
+                tx.send_to(&(i as u32).to_le_bytes(), rx.local_addr().unwrap()).unwrap();
+            }
+            let mut got = Vec::new();
+            ring.run(
+                |data, src| {
+                    assert_eq!(src, tx.local_addr().unwrap());
+                    got.push(u32::from_le_bytes(data.try_into().unwrap()));
+                    got.len() < n
+                },
+                |e| panic!("{e}")
+            ).unwrap();
+            assert_eq!(got, (0..n as u32).collect::<Vec<_>>());
+        }
+
+        /// CPU time of the calling thread.
+        fn thread_cpu() -> std::time::Duration {
+            // SAFETY: getrusage fills the zeroed struct
+            let usage = unsafe {
+                let mut usage: libc::rusage = std::mem::zeroed();
+                libc::getrusage(libc::RUSAGE_THREAD, &mut usage);
+                usage
+            };
+            let tv = |t: libc::timeval| std::time::Duration::new(t.tv_sec as u64, (t.tv_usec as u32) * 1000);
+            tv(usage.ru_utime) + tv(usage.ru_stime)
+        }
+
+        #[test]
+        fn test_idle_ring_sleeps_and_stops() {
+            let rx = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            let tx = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            rx.set_nonblocking(true).unwrap();
+            let mut ring = match RecvRing::new(rx.as_raw_fd()) {
+                Ok(ring) => ring,
+                Err(e) => {
+                    eprintln!("io_uring unavailable here ({e}), skipping");
+                    return;
+                }
+            };
+            // Every re-armed receive completes with EAGAIN when idle
+            ring.recv_flags = libc::MSG_DONTWAIT as u32;
+            let stopper = ring.stopper();
+            let (got_tx, got_rx) = std::sync::mpsc::channel();
+            let thread = std::thread::spawn(move || {
+                ring.run(
+                    |data, _| {
+                        got_tx.send(data.to_vec()).unwrap();
+                        true
+                    },
+                    |e| panic!("{e}")
+                ).unwrap();
+                thread_cpu()
+            });
+
+            // Cycle every slot through a re-arm, idle, then still receiving
+            let n = (QUEUE_DEPTH as usize) * 2;
+            for _ in 0..n {
+                tx.send_to(b"burst", rx.local_addr().unwrap()).unwrap();
+            }
+            for _ in 0..n {
+                got_rx.recv_timeout(std::time::Duration::from_secs(2)).unwrap();
+            }
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            tx.send_to(b"late", rx.local_addr().unwrap()).unwrap();
+            assert_eq!(got_rx.recv_timeout(std::time::Duration::from_secs(2)).unwrap(), b"late");
+
+            stopper.stop();
+            let (done_tx, done_rx) = std::sync::mpsc::channel();
+            std::thread::spawn(move || done_tx.send(thread.join().unwrap()));
+            let cpu = done_rx.recv_timeout(std::time::Duration::from_secs(2)).expect("ring did not stop");
+            assert!(cpu < std::time::Duration::from_millis(100), "ring spun for {cpu:?}");
+            // Nothing reads the socket any more
+            tx.send_to(b"after", rx.local_addr().unwrap()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            assert!(got_rx.try_recv().is_err());
+        }
+    }
+}
@@ -1,13 +1,19 @@
+use crate::audio_window::{ AudioWindow, DBFS_FLOOR };
+use crate::devices::Thresholds;
 use crate::persona::{ PersonaTrait, apply_deltas, persona_weight_deltas };
-use crate::sensor::{ SensorPacket, SensorVector, DATA_TYPE_AUDIO, DATA_TYPE_SENSOR_VECTOR };
-use crate::sensor_smoother::SensorSmoother;
+use crate::sensor::{ SensorPacket, SensorVector, DATA_TYPE_SENSOR_VECTOR, SENSOR_VECTOR_LEN };
+use crate::sensor_smoother::{ SensorSmoother, SmootherState };
+use crate::stats::Stats;
+use crate::trace::TraceId;
+use serde::{ Deserialize, Serialize };
+use std::sync::Arc;
 
 // ─────────────────────────────────────────────────────────────────────
 //  Unified VAD result — can originate from audio OR emotional pipeline
 // ─────────────────────────────────────────────────────────────────────
 
 /// The kind of VAD computation that produced the result.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum VadKind {
     /// Energy-based audio voice-activity detection
     Audio,
@@ -15,7 +21,7 @@ pub enum VadKind {
     Emotional,
 }
 
-/// Unified result returned by [`process_packet`].
+/// Unified result returned by [`process_packet_with`].
 #[derive(Debug, Clone)]
 pub struct VadResult {
     pub sensor_id: u32,
@@ -29,10 +35,165 @@ pub struct VadResult {
     pub energy: f64,
     /// Audio-only: energy threshold used
     pub threshold: f64,
+    /// Audio-only: window level in dBFS (-96 for silence / emotional mode)
+    pub dbfs: f32,
+    /// Audio-only: zero crossings per sample pair
+    pub zcr: f32,
+    /// Audio-only: low/high band energy ratio (split at fs/4)
+    pub band_ratio: f32,
     /// Emotional-only: Valence–Arousal–Dominance triple (all 0 for audio mode)
     pub valence: f32,
     pub arousal: f32,
     pub dominance: f32,
+    /// Correlation id of the packet (see `trace.rs`)
+    pub trace: Option<TraceId>,
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Emotional engine selection
+// ─────────────────────────────────────────────────────────────────────
+
+/// CLI selector for the emotional V/A/D engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EmotionEngineKind {
+    /// Hand-tuned linear weights (default)
+    Linear,
+    /// Learned model loaded from `--emotion-model-path` (needs the `onnx` feature)
+    Onnx,
+}
+
+/// The engine that maps emotional features to V/A/D.
+pub enum EmotionEngine {
+    Linear(EmotionWeights),
+    #[cfg(feature = "onnx")]
+    Onnx(crate::emotion_onnx::OnnxEmotionModel, InferenceFailures),
+}
+
+/// Failed ONNX inferences.  Each one is counted in `Stats`
+/// (`inference_errors`), but only the 1st, 10th, 100th, … is logged, so a
+/// broken model does not flood the log at packet rate.
+#[cfg(feature = "onnx")]
+pub struct InferenceFailures {
+    count: std::sync::atomic::AtomicU64,
+    stats: Arc<Stats>,
+}
+
+#[cfg(feature = "onnx")]
+impl InferenceFailures {
+    fn new(stats: Arc<Stats>) -> Self {
+        Self { count: std::sync::atomic::AtomicU64::new(0), stats }
+    }
+
+    /// Count one failure; returns the running total if it is to be logged.
+    fn record(&self) -> Option<u64> {
+        self.stats.record_inference_error();
+        let n = self.count.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        (10u64.pow(n.ilog10()) == n).then_some(n)
+    }
+}
+
+impl Default for EmotionEngine {
+    fn default() -> Self {
+        EmotionEngine::Linear(EmotionWeights::default())
+    }
+}
+
+impl EmotionEngine {
+    /// Build the engine selected on the command line.
+    ///
+    /// `weights_path` (optional) replaces the built-in linear weights with
+    /// a TOML file produced by the `calibrate` subcommand.  ONNX inference
+    /// failures are counted in `stats`.
+    pub fn from_config(
+        kind: EmotionEngineKind,
+        model_path: &str,
+        weights_path: &str,
+        stats: &Arc<Stats>
+    ) -> anyhow::Result<Self> {
+        match kind {
+            EmotionEngineKind::Linear if weights_path.is_empty() => Ok(EmotionEngine::default()),
+            EmotionEngineKind::Linear =>
+                Ok(EmotionEngine::Linear(EmotionWeights::load(weights_path)?)),
+            #[cfg(feature = "onnx")]
+            EmotionEngineKind::Onnx => {
+                if model_path.is_empty() {
+                    anyhow::bail!("--emotion-engine onnx requires --emotion-model-path");
+                }
+                Ok(
+                    EmotionEngine::Onnx(
+                        crate::emotion_onnx::OnnxEmotionModel::load(model_path)?,
+                        InferenceFailures::new(stats.clone())
+                    )
+                )
+            }
+            #[cfg(not(feature = "onnx"))]
+            EmotionEngineKind::Onnx => {
+                let _ = (model_path, stats);
+                anyhow::bail!("--emotion-engine onnx requires a build with `--features onnx`")
+            }
+        }
+    }
+
+    /// Map one feature vector to a clamped V/A/D triple.
+    #[inline]
+    fn evaluate(&self, features: &[f32; EMOTION_FEATURES], persona: PersonaTrait) -> (f32, f32, f32) {
+        match self {
+            EmotionEngine::Linear(weights) => linear_vad(features, persona, weights),
+            #[cfg(feature = "onnx")]
+            EmotionEngine::Onnx(model, failures) =>
+                match model.infer(features, persona) {
+                    Ok((v, a, d)) => (v.clamp(0.0, 1.0), a.clamp(0.0, 1.0), d.clamp(0.0, 1.0)),
+                    Err(e) => {
+                        if let Some(failures) = failures.record() {
+                            tracing::warn!(
+                                error = %e,
+                                failures,
+                                "ONNX emotion inference failed — using linear weights (next log at 10× the failures)"
+                            );
+                        }
+                        linear_vad(features, persona, &EmotionWeights::default())
+                    }
+                }
+        }
+    }
+
+    /// The linear weights with `persona`'s deltas applied — what the
+    /// engine actually multiplies features by.  `None` for ONNX, which
+    /// has no weight vectors.
+    pub fn persona_weights(&self, persona: PersonaTrait) -> Option<EmotionWeights> {
+        match self {
+            EmotionEngine::Linear(weights) => Some(weights.with_persona(persona)),
+            #[cfg(feature = "onnx")]
+            EmotionEngine::Onnx(..) => None,
+        }
+    }
+
+    /// V/A/D for a sensor vector held steady: levels as given, every
+    /// rate of change zero.  Touches no smoother state, so it can be
+    /// called from anywhere (`POST /persona/preview`).
+    pub fn preview(&self, sensors: &[f32; SENSOR_VECTOR_LEN], persona: PersonaTrait) -> (f32, f32, f32) {
+        let mut features = [0.0f32; EMOTION_FEATURES];
+        features[..SENSOR_VECTOR_LEN].copy_from_slice(sensors);
+        self.evaluate(&features, persona)
+    }
+
+    /// V/A/D for one raw sensor vector outside the packet path: the
+    /// vector takes one smoother step from `state` (advancing it), then
+    /// goes through the engine like a packet would.  `sensors` must
+    /// already be within [0, 1] — there is no sanitization here.
+    pub fn evaluate_vector(
+        &self,
+        sensors: &[f32; SENSOR_VECTOR_LEN],
+        persona: PersonaTrait,
+        state: &mut SmootherState
+    ) -> (f32, f32, f32) {
+        let mut levels = *sensors;
+        let rates = state.step(&mut levels, persona);
+        let mut features = [0.0f32; EMOTION_FEATURES];
+        features[..SENSOR_VECTOR_LEN].copy_from_slice(&levels);
+        features[SENSOR_VECTOR_LEN..].copy_from_slice(&rates);
+        self.evaluate(&features, persona)
+    }
 }
 
 // ─────────────────────────────────────────────────────────────────────
@@ -41,8 +202,10 @@ pub struct VadResult {
 
 /// Process a sensor packet through the appropriate VAD pipeline.
 ///
-/// * `data_type == 1` → audio RMS energy VAD
+/// * `data_type == 1` → audio RMS energy VAD over the sensor's rolling
+///   PCM `window`
 /// * `data_type == 2` → emotional Valence-Arousal-Dominance VAD
+///   evaluated by `engine`
 /// * anything else    → falls back to audio VAD
 ///
 /// The `persona` trait applies additive weight deltas to the emotional
@@ -50,15 +213,55 @@ pub struct VadResult {
 ///
 /// The `smoother` applies EMA decay to the idle_time channel so the
 /// robot drifts into sadness gradually rather than instantly.
+/// `thresholds` are the sensor's effective active thresholds (see
+/// `devices.rs`).
 #[inline]
-pub fn process_packet(
+pub fn process_packet_with(
     packet: &SensorPacket,
     persona: PersonaTrait,
-    smoother: &SensorSmoother
+    smoother: &SensorSmoother,
+    window: &AudioWindow,
+    engine: &EmotionEngine,
+    thresholds: Thresholds
 ) -> VadResult {
     match packet.data_type {
-        DATA_TYPE_SENSOR_VECTOR => compute_emotional_vad(packet, persona, smoother),
-        DATA_TYPE_AUDIO | _ => compute_audio_vad(packet),
+        DATA_TYPE_SENSOR_VECTOR =>
+            compute_emotional_vad(packet, persona, smoother, engine, thresholds.arousal),
+        // DATA_TYPE_AUDIO and unknown types
+        _ => compute_audio_vad(packet, window, thresholds.audio),
+    }
+}
+
+/// [`process_packet_with`] plus a shadow emotional engine evaluated on the
+/// same smoothed features.
+///
+/// Returns `(primary, shadow)`; the shadow result is only produced for
+/// sensor-vector packets and is meant for comparison, never for sending.
+/// Features are computed once, so the smoother advances exactly as it
+/// would without a shadow.
+#[inline]
+pub fn process_packet_shadowed(
+    packet: &SensorPacket,
+    persona: PersonaTrait,
+    smoother: &SensorSmoother,
+    window: &AudioWindow,
+    engine: &EmotionEngine,
+    shadow: &EmotionEngine,
+    thresholds: Thresholds
+) -> (VadResult, Option<VadResult>) {
+    if packet.data_type != DATA_TYPE_SENSOR_VECTOR {
+        return (compute_audio_vad(packet, window, thresholds.audio), None);
+    }
+    let arousal_threshold = thresholds.arousal;
+    match emotion_features(packet, persona, smoother) {
+        Some(features) =>
+            (
+                emotional_result(packet, engine.evaluate(&features, persona), arousal_threshold),
+                Some(
+                    emotional_result(packet, shadow.evaluate(&features, persona), arousal_threshold)
+                ),
+            ),
+        None => (emotional_result(packet, (0.0, 0.0, 0.0), arousal_threshold), None),
     }
 }
 
@@ -66,14 +269,20 @@ pub fn process_packet(
 //  1.  Audio VAD  (original energy-based detector)
 // ═════════════════════════════════════════════════════════════════════
 
-/// Energy threshold for voice activity detection.
-const VAD_ENERGY_THRESHOLD: f64 = 30.0;
+/// Default energy threshold for voice activity detection.
+pub const VAD_ENERGY_THRESHOLD: f64 = 30.0;
 
-/// Audio RMS energy VAD — treats payload as 16-bit LE PCM samples.
+/// Audio RMS energy VAD — payload is PCM in `packet.sample_format`,
+/// normalised to 16-bit before measuring.
+///
+/// Energy is measured over the sensor's rolling window (see
+/// `audio_window.rs`) rather than just this packet's samples.
 #[inline]
-fn compute_audio_vad(packet: &SensorPacket) -> VadResult {
-    let energy = compute_rms_energy(&packet.payload);
-    let is_active = energy > VAD_ENERGY_THRESHOLD;
+pub fn compute_audio_vad(packet: &SensorPacket, window: &AudioWindow, threshold: f64) -> VadResult {
+    let pcm = packet.sample_format.to_s16le(&packet.payload);
+    let features = window.push(packet.sensor_id, &pcm);
+    let energy = features.rms;
+    let is_active = energy > threshold;
 
     VadResult {
         sensor_id: packet.sensor_id,
@@ -81,31 +290,17 @@ fn compute_audio_vad(packet: &SensorPacket) -> VadResult {
         kind: VadKind::Audio,
         is_active,
         energy,
-        threshold: VAD_ENERGY_THRESHOLD,
+        threshold,
+        dbfs: features.dbfs,
+        zcr: features.zcr,
+        band_ratio: features.band_ratio,
         valence: 0.0,
         arousal: 0.0,
         dominance: 0.0,
+        trace: packet.trace,
     }
 }
 
-/// Compute RMS energy of a byte buffer interpreted as 16-bit LE PCM samples.
-#[inline]
-fn compute_rms_energy(data: &[u8]) -> f64 {
-    if data.len() < 2 {
-        return 0.0;
-    }
-
-    let n_samples = data.len() / 2;
-    let mut sum_sq: f64 = 0.0;
-
-    for i in 0..n_samples {
-        let sample = i16::from_le_bytes([data[i * 2], data[i * 2 + 1]]) as f64;
-        sum_sq += sample * sample;
-    }
-
-    (sum_sq / (n_samples as f64)).sqrt()
-}
-
 // ═════════════════════════════════════════════════════════════════════
 //  2.  Emotional VAD  (Valence – Arousal – Dominance)
 // ═════════════════════════════════════════════════════════════════════
@@ -113,6 +308,11 @@ fn compute_rms_energy(data: &[u8]) -> f64 {
 //  Maps a 10-channel environmental sensor vector to a V/A/D triple
 //  using fixed linear weight vectors with bias, clamped to [0, 1].
 //
+//  The model input is 20 features: the 10 (smoothed) channel levels
+//  followed by their 10 rate-of-change envelopes from the smoother, so
+//  each weight vector is 21 wide (20 features + bias).  Persona deltas
+//  only touch the level half and the bias.
+//
 //  Sensor channel order (all normalised 0–1):
 //    0  battery_low     5  lifted
 //    1  people_count    6  idle_time
@@ -129,8 +329,14 @@ fn compute_rms_energy(data: &[u8]) -> f64 {
 //  Dominance – sense of control & familiarity
 //              minus vulnerability (threats, low battery, being grabbed)
 
-/// Arousal threshold above which `is_active` is set for emotional VAD.
-const EMOTIONAL_ACTIVE_THRESHOLD: f32 = 0.35;
+/// Default arousal threshold above which `is_active` is set for emotional VAD.
+pub const EMOTIONAL_ACTIVE_THRESHOLD: f32 = 0.35;
+
+/// Built-in thresholds when no CLI / per-device override applies.
+pub const DEFAULT_THRESHOLDS: Thresholds = Thresholds {
+    audio: VAD_ENERGY_THRESHOLD,
+    arousal: EMOTIONAL_ACTIVE_THRESHOLD,
+};
 
 //                                bat   ppl   kno   unk   fal   lft   idl   snd   voi   mot   bias
 const VALENCE_W: [f32; 11] = [-0.05, 0.15, 0.3, -0.2, -0.2, -0.15, -0.1, 0.05, 0.15, 0.0, 0.3];
@@ -139,56 +345,209 @@ const DOMINANCE_W: [f32; 11] = [
     -0.15, 0.1, 0.25, -0.2, -0.15, -0.15, -0.05, 0.05, 0.15, 0.05, 0.35,
 ];
 
+//  Rate-of-change weights — sudden changes are mostly *arousing*; a new
+//  known face is a small valence lift, a sudden grab/fall a small hit to
+//  valence and dominance.
+//                                  bat   ppl   kno   unk   fal   lft   idl   snd   voi   mot
+const VALENCE_DW: [f32; 10] = [0.0, 0.05, 0.1, -0.1, -0.1, -0.05, 0.0, 0.0, 0.0, 0.0];
+const AROUSAL_DW: [f32; 10] = [0.0, 0.3, 0.1, 0.2, 0.2, 0.2, 0.0, 0.2, 0.1, 0.3];
+const DOMINANCE_DW: [f32; 10] = [0.0, 0.0, 0.0, -0.05, -0.1, -0.1, 0.0, 0.0, 0.0, 0.0];
+
+/// Number of emotional model inputs: channel levels + rates of change.
+pub const EMOTION_FEATURES: usize = 2 * SENSOR_VECTOR_LEN;
+
+/// Base (pre-persona) weights for one V/A/D axis.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AxisWeights {
+    /// Weights for the 10 channel levels.
+    pub levels: [f32; SENSOR_VECTOR_LEN],
+    /// Weights for the 10 rate-of-change features.
+    pub rates: [f32; SENSOR_VECTOR_LEN],
+    pub bias: f32,
+}
+
+impl AxisWeights {
+    fn from_consts(level_w: &[f32; SENSOR_VECTOR_LEN + 1], rate_w: &[f32; SENSOR_VECTOR_LEN]) -> Self {
+        let mut levels = [0.0f32; SENSOR_VECTOR_LEN];
+        levels.copy_from_slice(&level_w[..SENSOR_VECTOR_LEN]);
+        Self { levels, rates: *rate_w, bias: level_w[SENSOR_VECTOR_LEN] }
+    }
+
+    /// Build from a full `[levels | rates | bias]` vector.
+    pub fn from_full(w: &[f32; EMOTION_FEATURES + 1]) -> Self {
+        let mut levels = [0.0f32; SENSOR_VECTOR_LEN];
+        let mut rates = [0.0f32; SENSOR_VECTOR_LEN];
+        levels.copy_from_slice(&w[..SENSOR_VECTOR_LEN]);
+        rates.copy_from_slice(&w[SENSOR_VECTOR_LEN..EMOTION_FEATURES]);
+        Self { levels, rates, bias: w[EMOTION_FEATURES] }
+    }
+
+    /// Apply persona deltas (levels + bias) and return the full
+    /// `[levels | rates | bias]` vector.
+    pub fn with_persona(&self, delta: &[f32; SENSOR_VECTOR_LEN + 1]) -> [f32; EMOTION_FEATURES + 1] {
+        let mut level_w = [0.0f32; SENSOR_VECTOR_LEN + 1];
+        level_w[..SENSOR_VECTOR_LEN].copy_from_slice(&self.levels);
+        level_w[SENSOR_VECTOR_LEN] = self.bias;
+        feature_weights(&apply_deltas(&level_w, delta), &self.rates)
+    }
+}
+
+/// Full linear emotional model: one weight set per axis.
+///
+/// Serialises to the weights TOML written by `calibrate` and loaded with
+/// `--emotion-weights`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EmotionWeights {
+    pub valence: AxisWeights,
+    pub arousal: AxisWeights,
+    pub dominance: AxisWeights,
+}
+
+impl Default for EmotionWeights {
+    /// The hand-tuned built-in weights.
+    fn default() -> Self {
+        Self {
+            valence: AxisWeights::from_consts(&VALENCE_W, &VALENCE_DW),
+            arousal: AxisWeights::from_consts(&AROUSAL_W, &AROUSAL_DW),
+            dominance: AxisWeights::from_consts(&DOMINANCE_W, &DOMINANCE_DW),
+        }
+    }
+}
+
+impl EmotionWeights {
+    /// Load weights from a TOML file.
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let text = std::fs
+            ::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read weights file {path}: {e}"))?;
+        toml::from_str(&text).map_err(|e| anyhow::anyhow!("invalid weights file {path}: {e}"))
+    }
+
+    /// These weights with `persona`'s deltas applied to the levels and
+    /// bias of each axis (rate weights are not persona-adjusted).
+    pub fn with_persona(&self, persona: PersonaTrait) -> Self {
+        let deltas = persona_weight_deltas(persona);
+        Self {
+            valence: AxisWeights::from_full(&self.valence.with_persona(&deltas.valence)),
+            arousal: AxisWeights::from_full(&self.arousal.with_persona(&deltas.arousal)),
+            dominance: AxisWeights::from_full(&self.dominance.with_persona(&deltas.dominance)),
+        }
+    }
+
+    /// Write weights as TOML.
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Assemble a full `[levels | rates | bias]` weight vector from the
+/// (persona-adjusted) level weights and the rate-of-change weights.
+#[inline]
+fn feature_weights(
+    level_w: &[f32; SENSOR_VECTOR_LEN + 1],
+    rate_w: &[f32; SENSOR_VECTOR_LEN]
+) -> [f32; EMOTION_FEATURES + 1] {
+    let mut out = [0.0f32; EMOTION_FEATURES + 1];
+    out[..SENSOR_VECTOR_LEN].copy_from_slice(&level_w[..SENSOR_VECTOR_LEN]);
+    out[SENSOR_VECTOR_LEN..EMOTION_FEATURES].copy_from_slice(rate_w);
+    out[EMOTION_FEATURES] = level_w[SENSOR_VECTOR_LEN];
+    out
+}
+
 /// Compute emotional VAD from a sensor-vector payload.
 ///
-/// The active `persona` trait applies additive deltas to the base
-/// V/A/D weight vectors before the dot-product computation.
+/// With the linear engine, the active `persona` trait applies additive
+/// deltas to the base V/A/D weight vectors before the dot-product
+/// computation.  The ONNX engine receives the persona as a one-hot input
+/// instead, and falls back to the linear weights if inference fails.
 ///
 /// Falls back to a zero result if the payload is too short.
 #[inline]
 fn compute_emotional_vad(
     packet: &SensorPacket,
     persona: PersonaTrait,
-    smoother: &SensorSmoother
+    smoother: &SensorSmoother,
+    engine: &EmotionEngine,
+    arousal_threshold: f32
 ) -> VadResult {
-    let sv = SensorVector::from_payload(&packet.payload);
-
-    // Apply persona-specific weight deltas
-    let deltas = persona_weight_deltas(persona);
-    let val_w = apply_deltas(&VALENCE_W, &deltas.valence);
-    let aro_w = apply_deltas(&AROUSAL_W, &deltas.arousal);
-    let dom_w = apply_deltas(&DOMINANCE_W, &deltas.dominance);
-
-    let (valence, arousal, dominance) = match sv {
-        Some(v) => {
-            let mut s = v.as_array();
-            // Smooth idle_time via EMA so sadness ramps gradually
-            smoother.smooth(packet.sensor_id, &mut s, persona);
-            (weighted_sum(&s, &val_w), weighted_sum(&s, &aro_w), weighted_sum(&s, &dom_w))
-        }
+    let vad = match emotion_features(packet, persona, smoother) {
+        Some(features) => engine.evaluate(&features, persona),
         None => (0.0, 0.0, 0.0),
     };
+    emotional_result(packet, vad, arousal_threshold)
+}
 
+/// Run the smoother over a sensor-vector payload and assemble the 20
+/// `[levels | rates]` model features.  `None` if the payload is too short.
+///
+/// This advances the smoother state, so call it exactly once per packet.
+#[inline]
+fn emotion_features(
+    packet: &SensorPacket,
+    persona: PersonaTrait,
+    smoother: &SensorSmoother
+) -> Option<[f32; EMOTION_FEATURES]> {
+    let sv = SensorVector::from_payload(&packet.payload)?;
+    let mut s = sv.as_array();
+    // Smooth idle_time via EMA so sadness ramps gradually,
+    // and pick up the per-channel rate-of-change features
+    let rates = smoother.smooth(packet.sensor_id, &mut s, persona);
+    let mut features = [0.0f32; EMOTION_FEATURES];
+    features[..SENSOR_VECTOR_LEN].copy_from_slice(&s);
+    features[SENSOR_VECTOR_LEN..].copy_from_slice(&rates);
+    Some(features)
+}
+
+/// Wrap a V/A/D triple in an emotional [`VadResult`].
+#[inline]
+fn emotional_result(
+    packet: &SensorPacket,
+    (valence, arousal, dominance): (f32, f32, f32),
+    arousal_threshold: f32
+) -> VadResult {
     VadResult {
         sensor_id: packet.sensor_id,
         seq: packet.seq,
         kind: VadKind::Emotional,
-        is_active: arousal > EMOTIONAL_ACTIVE_THRESHOLD,
+        is_active: arousal > arousal_threshold,
         energy: 0.0,
         threshold: 0.0,
+        dbfs: DBFS_FLOOR,
+        zcr: 0.0,
+        band_ratio: 0.0,
         valence,
         arousal,
         dominance,
+        trace: packet.trace,
     }
 }
 
-/// Dot-product of a 10-element sensor array with an 11-element weight
+/// Linear V/A/D with persona weight deltas applied on top of `weights`.
+#[inline]
+fn linear_vad(
+    features: &[f32; EMOTION_FEATURES],
+    persona: PersonaTrait,
+    weights: &EmotionWeights
+) -> (f32, f32, f32) {
+    let deltas = persona_weight_deltas(persona);
+    let val_w = weights.valence.with_persona(&deltas.valence);
+    let aro_w = weights.arousal.with_persona(&deltas.arousal);
+    let dom_w = weights.dominance.with_persona(&deltas.dominance);
+    (
+        weighted_sum(features, &val_w),
+        weighted_sum(features, &aro_w),
+        weighted_sum(features, &dom_w),
+    )
+}
+
+/// Dot-product of the 20-element feature array with a 21-element weight
 /// vector (last element = bias), clamped to [0.0, 1.0].
 #[inline]
-fn weighted_sum(sensors: &[f32; 10], weights: &[f32; 11]) -> f32 {
-    let mut sum = weights[10]; // bias
-    for i in 0..10 {
-        sum += sensors[i] * weights[i];
+fn weighted_sum(features: &[f32; EMOTION_FEATURES], weights: &[f32; EMOTION_FEATURES + 1]) -> f32 {
+    let mut sum = weights[EMOTION_FEATURES]; // bias
+    for (f, w) in features.iter().zip(weights.iter()) {
+        sum += f * w;
     }
     sum.clamp(0.0, 1.0)
 }
@@ -200,10 +559,18 @@ fn weighted_sum(sensors: &[f32; 10], weights: &[f32; 11]) -> f32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::pcm::SampleFormat;
     use crate::persona::PersonaTrait;
-    use crate::sensor::SENSOR_VECTOR_BYTES;
+    use crate::sensor::{ DATA_TYPE_AUDIO, SENSOR_VECTOR_BYTES };
     use crate::sensor_smoother::SensorSmoother;
 
+    /// [`process_packet_with`] with the default engine and thresholds
+    /// and no PCM window.
+    fn process_packet(packet: &SensorPacket, persona: PersonaTrait, smoother: &SensorSmoother) -> VadResult {
+        let window = AudioWindow::new(std::time::Duration::ZERO);
+        process_packet_with(packet, persona, smoother, &window, &EmotionEngine::default(), DEFAULT_THRESHOLDS)
+    }
+
     // ── Audio VAD tests ──────────────────────────────────────────────
 
     #[test]
@@ -212,8 +579,12 @@ mod tests {
             sensor_id: 1,
             timestamp_us: 0,
             data_type: DATA_TYPE_AUDIO,
+            sample_format: SampleFormat::S16,
             seq: 0,
             payload: vec![0u8; 64],
+            tenant: Default::default(),
+            ingested_at: None,
+            trace: None,
         };
         let smoother = SensorSmoother::new();
         let result = process_packet(&packet, PersonaTrait::Obedient, &smoother);
@@ -228,8 +599,12 @@ mod tests {
             sensor_id: 1,
             timestamp_us: 0,
             data_type: DATA_TYPE_AUDIO,
+            sample_format: SampleFormat::S16,
             seq: 0,
             payload: vec![0xff, 0x7f, 0xff, 0x7f, 0xff, 0x7f, 0xff, 0x7f],
+            tenant: Default::default(),
+            ingested_at: None,
+            trace: None,
         };
         let smoother = SensorSmoother::new();
         let result = process_packet(&packet, PersonaTrait::Obedient, &smoother);
@@ -238,6 +613,54 @@ mod tests {
         assert!(result.energy > VAD_ENERGY_THRESHOLD);
     }
 
+    #[test]
+    fn test_per_sensor_threshold_override() {
+        // Moderate level: active with the default threshold, not with a raised one
+        let payload: Vec<u8> = [100i16, -100, 100, -100].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let packet = SensorPacket {
+            sensor_id: 1,
+            timestamp_us: 0,
+            data_type: DATA_TYPE_AUDIO,
+            sample_format: SampleFormat::S16,
+            seq: 0,
+            payload,
+            tenant: Default::default(),
+            ingested_at: None,
+            trace: None,
+        };
+        let smoother = SensorSmoother::new();
+        let window = AudioWindow::new(std::time::Duration::ZERO);
+        let engine = EmotionEngine::default();
+
+        let r = process_packet_with(&packet, PersonaTrait::Obedient, &smoother, &window, &engine, DEFAULT_THRESHOLDS);
+        assert!(r.is_active);
+
+        let loud_room = Thresholds { audio: 150.0, ..DEFAULT_THRESHOLDS };
+        let r = process_packet_with(&packet, PersonaTrait::Obedient, &smoother, &window, &engine, loud_room);
+        assert!(!r.is_active);
+        assert_eq!(r.threshold, 150.0);
+    }
+
+    #[test]
+    fn test_float_pcm_energy_matches_s16() {
+        // Half-scale f32 must measure like half-scale s16, not as garbage bytes
+        let payload: Vec<u8> = [0.5f32, -0.5, 0.5, -0.5].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let packet = SensorPacket {
+            sensor_id: 1,
+            timestamp_us: 0,
+            data_type: DATA_TYPE_AUDIO,
+            sample_format: SampleFormat::F32,
+            seq: 0,
+            payload,
+            tenant: Default::default(),
+            ingested_at: None,
+            trace: None,
+        };
+        let smoother = SensorSmoother::new();
+        let result = process_packet(&packet, PersonaTrait::Obedient, &smoother);
+        assert!((result.energy - 16384.0).abs() < 1.0, "energy={}", result.energy);
+    }
+
     // ── Emotional VAD tests ──────────────────────────────────────────
 
     /// Helper: build a SensorPacket with data_type=2 from a float slice.
@@ -250,8 +673,12 @@ mod tests {
             sensor_id: 42,
             timestamp_us: 0,
             data_type: DATA_TYPE_SENSOR_VECTOR,
+            sample_format: SampleFormat::S16,
             seq: 1,
             payload,
+            tenant: Default::default(),
+            ingested_at: None,
+            trace: None,
         }
     }
 
@@ -352,14 +779,110 @@ mod tests {
         assert!(r.is_active, "expected active for excited");
     }
 
+    #[test]
+    fn test_sudden_change_spikes_arousal_then_decays() {
+        // Quiet room, then people walk in and the robot gets bumped
+        let smoother = SensorSmoother::new();
+        let calm = [0.1, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.1, 0.0, 0.0];
+        let busy = [0.1, 0.8, 0.0, 0.0, 0.0, 0.0, 0.0, 0.1, 0.0, 0.6];
+        warm_smoother(&smoother, &calm, 20, PersonaTrait::Obedient);
+
+        // The first packet of a step is held back by the spike filter
+        process_packet(&sensor_packet_from_floats(&busy), PersonaTrait::Obedient, &smoother);
+        let spike = process_packet(&sensor_packet_from_floats(&busy), PersonaTrait::Obedient, &smoother);
+        warm_smoother(&smoother, &busy, 30, PersonaTrait::Obedient);
+        let steady = process_packet(&sensor_packet_from_floats(&busy), PersonaTrait::Obedient, &smoother);
+
+        assert!(
+            spike.arousal > steady.arousal + 0.1,
+            "spike arousal={:.3} should exceed steady arousal={:.3}",
+            spike.arousal,
+            steady.arousal
+        );
+    }
+
+    #[test]
+    fn test_shadow_matches_primary_and_steps_smoother_once() {
+        let vals = [0.1, 0.8, 0.0, 0.0, 0.0, 0.0, 0.0, 0.1, 0.0, 0.6];
+        let pkt = sensor_packet_from_floats(&vals);
+        let engine = EmotionEngine::default();
+
+        let plain = SensorSmoother::new();
+        let a = process_packet(&pkt, PersonaTrait::Obedient, &plain);
+        let b = process_packet(&pkt, PersonaTrait::Obedient, &plain);
+
+        let shadowed = SensorSmoother::new();
+        let window = AudioWindow::new(std::time::Duration::ZERO);
+        let (a2, sa) = process_packet_shadowed(
+            &pkt,
+            PersonaTrait::Obedient,
+            &shadowed,
+            &window,
+            &engine,
+            &engine,
+            DEFAULT_THRESHOLDS
+        );
+        let (b2, _) = process_packet_shadowed(
+            &pkt,
+            PersonaTrait::Obedient,
+            &shadowed,
+            &window,
+            &engine,
+            &engine,
+            DEFAULT_THRESHOLDS
+        );
+
+        assert_eq!((a.valence, a.arousal), (a2.valence, a2.arousal));
+        assert_eq!((b.valence, b.arousal), (b2.valence, b2.arousal));
+        assert_eq!(sa.unwrap().arousal, a2.arousal);
+    }
+
+    #[test]
+    fn test_preview_uses_persona_weights() {
+        let engine = EmotionEngine::default();
+        let sensors = [0.1, 0.8, 0.6, 0.0, 0.0, 0.0, 0.2, 0.5, 0.3, 0.6];
+        for persona in PersonaTrait::ALL {
+            let w = engine.persona_weights(persona).unwrap();
+            let dot = |axis: &AxisWeights| {
+                let sum: f32 = sensors.iter().zip(axis.levels).map(|(s, w)| s * w).sum();
+                (sum + axis.bias).clamp(0.0, 1.0)
+            };
+            let (v, a, d) = engine.preview(&sensors, persona);
+            assert!((v - dot(&w.valence)).abs() < 1e-6, "{persona}");
+            assert!((a - dot(&w.arousal)).abs() < 1e-6, "{persona}");
+            assert!((d - dot(&w.dominance)).abs() < 1e-6, "{persona}");
+        }
+        let base = EmotionWeights::default();
+        let stubborn = base.with_persona(PersonaTrait::Stubborn);
+        assert!((stubborn.dominance.bias - (base.dominance.bias + 0.15)).abs() < 1e-6);
+        assert_eq!(stubborn.dominance.rates, base.dominance.rates);
+    }
+
+    #[test]
+    fn test_evaluate_vector_matches_packet_path() {
+        let engine = EmotionEngine::default();
+        let smoother = SensorSmoother::new();
+        let mut state = SmootherState::default();
+        for vals in [[0.1, 0.8, 0.0, 0.0, 0.0, 0.0, 0.9, 0.1, 0.0, 0.6], [0.1, 0.4, 0.0, 0.4, 0.9, 0.0, 0.9, 0.6, 0.0, 0.9]] {
+            let r = process_packet(&sensor_packet_from_floats(&vals), PersonaTrait::Cute, &smoother);
+            let (v, a, d) = engine.evaluate_vector(&vals, PersonaTrait::Cute, &mut state);
+            assert_eq!((r.valence, r.arousal, r.dominance), (v, a, d));
+        }
+        assert!(state.rate[3] > 0.0 && state.idle_time > 0.0);
+    }
+
     #[test]
     fn test_short_payload_returns_zeros() {
         let pkt = SensorPacket {
             sensor_id: 1,
             timestamp_us: 0,
             data_type: DATA_TYPE_SENSOR_VECTOR,
+            sample_format: SampleFormat::S16,
             seq: 0,
             payload: vec![0u8; 8],
+            tenant: Default::default(),
+            ingested_at: None,
+            trace: None,
         };
         let smoother = SensorSmoother::new();
         let r = process_packet(&pkt, PersonaTrait::Obedient, &smoother);
@@ -368,4 +891,14 @@ mod tests {
         assert_eq!(r.arousal, 0.0);
         assert_eq!(r.dominance, 0.0);
     }
+
+    #[cfg(feature = "onnx")]
+    #[test]
+    fn test_inference_failures_are_counted_and_logged_sparsely() {
+        let stats = Stats::new();
+        let failures = InferenceFailures::new(stats.clone());
+        let logged: Vec<u64> = (0..1000).filter_map(|_| failures.record()).collect();
+        assert_eq!(logged, [1, 10, 100, 1000]);
+        assert_eq!(stats.totals().inference_errors, 1000);
+    }
 }
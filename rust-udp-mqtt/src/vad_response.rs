@@ -1,10 +1,18 @@
 use crate::vad::{ VadResult, VadKind };
+use std::collections::HashMap;
+use std::time::Duration;
 
 /// Binary response format for VAD results via UDP
-/// Wire format (24 bytes fixed):
+/// Wire format v1 (34 bytes fixed):
 ///   [ sensor_id: u32 LE ][ seq: u64 LE ][ is_active: u8 ][ kind: u8 ]
 ///   [ energy: f32 LE ][ threshold: f32 LE ]
 ///   [ valence: f32 LE ][ arousal: f32 LE ][ dominance: f32 LE ]
+///
+/// Wire format v2 (46 bytes) appends the audio window features:
+///   [ dbfs: f32 LE ][ zcr: f32 LE ][ band_ratio: f32 LE ]
+///
+/// v2 is a strict extension, so clients can tell the versions apart by
+/// datagram length and v1 parsers that ignore trailing bytes keep working.
 #[derive(Debug, Clone)]
 pub struct VadResponsePacket {
     pub sensor_id: u32,
@@ -16,8 +24,16 @@ pub struct VadResponsePacket {
     pub valence: f32,
     pub arousal: f32,
     pub dominance: f32,
+    pub dbfs: f32,
+    pub zcr: f32,
+    pub band_ratio: f32,
 }
 
+/// Size of a v1 response packet.
+pub const VAD_RESPONSE_V1_LEN: usize = 34;
+/// Size of a v2 response packet.
+pub const VAD_RESPONSE_V2_LEN: usize = VAD_RESPONSE_V1_LEN + 12;
+
 impl VadResponsePacket {
     /// Serialize VAD result to binary packet
     pub fn from_vad_result(result: &VadResult) -> Self {
@@ -38,12 +54,16 @@ impl VadResponsePacket {
             valence: result.valence,
             arousal: result.arousal,
             dominance: result.dominance,
+            dbfs: result.dbfs,
+            zcr: result.zcr,
+            band_ratio: result.band_ratio,
         }
     }
 
-    /// Serialize to bytes (little-endian)
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(48);
+    /// Serialize to bytes (little-endian) in the given wire version
+    /// (1 or 2; anything above 2 is treated as 2).
+    pub fn to_bytes(&self, version: u8) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(VAD_RESPONSE_V2_LEN);
         bytes.extend_from_slice(&self.sensor_id.to_le_bytes());
         bytes.extend_from_slice(&self.seq.to_le_bytes());
         bytes.push(self.is_active);
@@ -53,6 +73,280 @@ impl VadResponsePacket {
         bytes.extend_from_slice(&self.valence.to_le_bytes());
         bytes.extend_from_slice(&self.arousal.to_le_bytes());
         bytes.extend_from_slice(&self.dominance.to_le_bytes());
+        if version >= 2 {
+            bytes.extend_from_slice(&self.dbfs.to_le_bytes());
+            bytes.extend_from_slice(&self.zcr.to_le_bytes());
+            bytes.extend_from_slice(&self.band_ratio.to_le_bytes());
+        }
         bytes
     }
+
+    /// Decode a response datagram (inverse of [`Self::to_bytes`]); the
+    /// wire version is told by length.  v1 leaves the v2 features at 0;
+    /// trailing bytes are ignored.
+    pub fn from_bytes(buf: &[u8]) -> Option<(Self, u8)> {
+        if buf.len() < VAD_RESPONSE_V1_LEN {
+            return None;
+        }
+        let f32_at = |at: usize| f32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]]);
+        let version = if buf.len() >= VAD_RESPONSE_V2_LEN { 2 } else { 1 };
+        let (dbfs, zcr, band_ratio) = if version == 2 { (f32_at(34), f32_at(38), f32_at(42)) } else { (0.0, 0.0, 0.0) };
+        let packet = VadResponsePacket {
+            sensor_id: u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+            seq: u64::from_le_bytes([buf[4], buf[5], buf[6], buf[7], buf[8], buf[9], buf[10], buf[11]]),
+            is_active: buf[12],
+            kind: buf[13],
+            energy: f32_at(14),
+            threshold: f32_at(18),
+            valence: f32_at(22),
+            arousal: f32_at(26),
+            dominance: f32_at(30),
+            dbfs,
+            zcr,
+            band_ratio,
+        };
+        Some((packet, version))
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Response coalescing
+// ─────────────────────────────────────────────────────────────────────
+//
+//  High-rate sensors (200 Hz) don't need a response datagram per packet.
+//  With a coalescing window, results are buffered per sensor and kind
+//  (audio and emotional results never mix) and one response per sensor
+//  and kind is flushed each window:
+//
+//    latest  the most recent result, as-is
+//    mean    continuous fields averaged over the window; `is_active` is
+//            set if *any* result in the window was active (so short
+//            bursts are never coalesced away); seq/threshold from the
+//            latest result
+
+/// How buffered results for one sensor are merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CoalesceMode {
+    /// Send the most recent result
+    Latest,
+    /// Send the window average (is_active = any active)
+    Mean,
+}
+
+/// Response-path settings.
+#[derive(Debug, Clone, Copy)]
+pub struct ResponseOptions {
+    /// Wire version passed to [`VadResponsePacket::to_bytes`]
+    pub version: u8,
+    /// Coalescing window (zero = one response per result)
+    pub coalesce: Duration,
+    pub coalesce_mode: CoalesceMode,
+}
+
+struct Pending {
+    latest: VadResult,
+    any_active: bool,
+    count: u32,
+    sums: [f64; 7],
+}
+
+/// Per-sensor, per-kind buffer of results awaiting the next flush.
+pub struct Coalescer {
+    mode: CoalesceMode,
+    pending: HashMap<(u32, VadKind), Pending>,
+}
+
+impl Coalescer {
+    pub fn new(mode: CoalesceMode) -> Self {
+        Self { mode, pending: HashMap::new() }
+    }
+
+    fn fields(r: &VadResult) -> [f64; 7] {
+        [
+            r.energy,
+            r.valence as f64,
+            r.arousal as f64,
+            r.dominance as f64,
+            r.dbfs as f64,
+            r.zcr as f64,
+            r.band_ratio as f64,
+        ]
+    }
+
+    /// Buffer one result.
+    pub fn push(&mut self, result: VadResult) {
+        let fields = Self::fields(&result);
+        let key = (result.sensor_id, result.kind);
+        match self.pending.get_mut(&key) {
+            Some(p) => {
+                p.any_active |= result.is_active;
+                p.count += 1;
+                for (sum, f) in p.sums.iter_mut().zip(fields) {
+                    *sum += f;
+                }
+                p.latest = result;
+            }
+            None => {
+                self.pending.insert(key, Pending {
+                    any_active: result.is_active,
+                    count: 1,
+                    sums: fields,
+                    latest: result,
+                });
+            }
+        }
+    }
+
+    /// Take one merged result per buffered sensor and kind.
+    pub fn drain(&mut self) -> Vec<VadResult> {
+        let mode = self.mode;
+        self.pending
+            .drain()
+            .map(|(_, p)| {
+                let mut r = p.latest;
+                if mode == CoalesceMode::Mean {
+                    let n = p.count as f64;
+                    let [energy, v, a, d, dbfs, zcr, band] = p.sums.map(|s| s / n);
+                    r.energy = energy;
+                    r.valence = v as f32;
+                    r.arousal = a as f32;
+                    r.dominance = d as f32;
+                    r.dbfs = dbfs as f32;
+                    r.zcr = zcr as f32;
+                    r.band_ratio = band as f32;
+                    r.is_active = p.any_active;
+                }
+                r
+            })
+            .collect()
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn emotional(sensor_id: u32, seq: u64, arousal: f32) -> VadResult {
+        VadResult {
+            sensor_id,
+            seq,
+            kind: VadKind::Emotional,
+            is_active: arousal > 0.35,
+            energy: 0.0,
+            threshold: 0.0,
+            dbfs: -96.0,
+            zcr: 0.0,
+            band_ratio: 0.0,
+            valence: 0.5,
+            arousal,
+            dominance: 0.5,
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn test_latest_keeps_last_per_sensor() {
+        let mut c = Coalescer::new(CoalesceMode::Latest);
+        c.push(emotional(1, 0, 0.9));
+        c.push(emotional(1, 1, 0.1));
+        c.push(emotional(2, 0, 0.2));
+
+        let mut out = c.drain();
+        out.sort_by_key(|r| r.sensor_id);
+        assert_eq!(out.len(), 2);
+        assert_eq!((out[0].seq, out[0].arousal, out[0].is_active), (1, 0.1, false));
+        assert!(c.drain().is_empty());
+    }
+
+    #[test]
+    fn test_mean_averages_and_keeps_bursts_active() {
+        let mut c = Coalescer::new(CoalesceMode::Mean);
+        c.push(emotional(1, 0, 0.9));
+        c.push(emotional(1, 1, 0.1));
+
+        let out = c.drain();
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].seq, 1);
+        assert!((out[0].arousal - 0.5).abs() < 1e-6);
+        assert!(out[0].is_active);
+    }
+
+    #[test]
+    fn test_kinds_of_one_sensor_stay_apart() {
+        let audio = VadResult {
+            kind: VadKind::Audio,
+            is_active: true,
+            energy: 120.0,
+            dbfs: -20.0,
+            valence: 0.0,
+            arousal: 0.0,
+            dominance: 0.0,
+            ..emotional(1, 5, 0.0)
+        };
+        for mode in [CoalesceMode::Latest, CoalesceMode::Mean] {
+            let mut c = Coalescer::new(mode);
+            c.push(audio.clone());
+            c.push(emotional(1, 6, 0.9));
+            c.push(audio.clone());
+
+            let mut out = c.drain();
+            out.sort_by_key(|r| r.seq);
+            assert_eq!(out.len(), 2, "{mode:?}");
+            assert_eq!((out[0].kind, out[0].energy, out[0].dbfs), (VadKind::Audio, 120.0, -20.0));
+            assert_eq!((out[1].kind, out[1].arousal, out[1].energy), (VadKind::Emotional, 0.9, 0.0));
+        }
+    }
+
+    #[test]
+    fn test_v2_carries_audio_features() {
+        let r = VadResult { kind: VadKind::Audio, dbfs: -18.5, zcr: 0.12, band_ratio: 0.75, ..emotional(1, 0, 0.5) };
+        let pkt = VadResponsePacket::from_vad_result(&r);
+        assert_eq!(pkt.to_bytes(1).len(), VAD_RESPONSE_V1_LEN);
+
+        let v2 = pkt.to_bytes(2);
+        assert_eq!(v2.len(), VAD_RESPONSE_V2_LEN);
+        assert_eq!(&v2[34..38], &(-18.5f32).to_le_bytes());
+        assert_eq!(&v2[38..42], &0.12f32.to_le_bytes());
+        assert_eq!(&v2[42..46], &0.75f32.to_le_bytes());
+        let (decoded, _) = VadResponsePacket::from_bytes(&v2).unwrap();
+        assert_eq!((decoded.dbfs, decoded.zcr, decoded.band_ratio), (-18.5, 0.12, 0.75));
+
+        // v1 clients never see the features
+        let (decoded, version) = VadResponsePacket::from_bytes(&pkt.to_bytes(1)).unwrap();
+        assert_eq!((version, decoded.dbfs, decoded.zcr, decoded.band_ratio), (1, 0.0, 0.0, 0.0));
+    }
+
+    proptest! {
+        #[test]
+        fn test_serialization_round_trips(
+            sensor_id in any::<u32>(),
+            seq in any::<u64>(),
+            flags in any::<[u8; 2]>(),
+            fields in any::<[u32; 8]>(),
+            version in 0u8..=4
+        ) {
+            // Floats from raw bits: NaNs and infinities included
+            let [energy, threshold, valence, arousal, dominance, dbfs, zcr, band_ratio] = fields.map(f32::from_bits);
+            let pkt = VadResponsePacket {
+                sensor_id, seq, is_active: flags[0], kind: flags[1],
+                energy, threshold, valence, arousal, dominance, dbfs, zcr, band_ratio,
+            };
+            let bytes = pkt.to_bytes(version);
+            let v2 = pkt.to_bytes(2);
+            let expected_len = if version >= 2 { VAD_RESPONSE_V2_LEN } else { VAD_RESPONSE_V1_LEN };
+            prop_assert_eq!(bytes.len(), expected_len);
+            // v1 is a prefix of v2: old clients keep parsing
+            prop_assert_eq!(&v2[..VAD_RESPONSE_V1_LEN], &pkt.to_bytes(1)[..]);
+
+            let (decoded, got) = VadResponsePacket::from_bytes(&bytes).unwrap();
+            prop_assert_eq!(got, version.clamp(1, 2));
+            prop_assert_eq!(decoded.to_bytes(got), bytes.clone());
+            prop_assert!(VadResponsePacket::from_bytes(&bytes[..VAD_RESPONSE_V1_LEN - 1]).is_none());
+        }
+    }
 }
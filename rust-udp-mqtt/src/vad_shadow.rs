@@ -0,0 +1,150 @@
+use crate::vad::VadResult;
+use std::sync::Mutex;
+use std::sync::Arc;
+use std::time::Duration;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Shadow-mode engine comparison
+// ─────────────────────────────────────────────────────────────────────
+//
+//  When `--shadow-emotion-engine` is set, every sensor-vector packet is
+//  scored by both the primary and the shadow engine on the *same*
+//  smoothed features.  Only the primary result is sent; the shadow's
+//  divergence is accumulated here and logged each stats interval so an
+//  engine switch can be justified with production numbers.
+
+/// Accumulated divergence between primary and shadow results.
+#[derive(Debug, Default, Clone, Copy)]
+struct Accum {
+    compared: u64,
+    active_disagree: u64,
+    sum_abs: [f64; 3],
+    max_abs: [f32; 3],
+}
+
+/// Thread-safe divergence counters shared across VAD workers.
+#[derive(Debug, Default)]
+pub struct ShadowStats {
+    inner: Mutex<Accum>,
+}
+
+/// Divergence over one reporting interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSnapshot {
+    pub compared: u64,
+    /// Packets where `is_active` differed between the two engines
+    pub active_disagree: u64,
+    /// Mean |primary − shadow| for valence, arousal, dominance
+    pub mean_abs: [f32; 3],
+    /// Max |primary − shadow| for valence, arousal, dominance
+    pub max_abs: [f32; 3],
+}
+
+impl ShadowStats {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Record one primary/shadow pair.
+    pub fn record(&self, primary: &VadResult, shadow: &VadResult) {
+        let diffs = [
+            (primary.valence - shadow.valence).abs(),
+            (primary.arousal - shadow.arousal).abs(),
+            (primary.dominance - shadow.dominance).abs(),
+        ];
+        let mut acc = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        acc.compared += 1;
+        if primary.is_active != shadow.is_active {
+            acc.active_disagree += 1;
+        }
+        for (i, d) in diffs.iter().enumerate() {
+            acc.sum_abs[i] += *d as f64;
+            acc.max_abs[i] = acc.max_abs[i].max(*d);
+        }
+    }
+
+    /// Snapshot and reset counters.
+    pub fn snapshot_and_reset(&self) -> ShadowSnapshot {
+        let acc = std::mem::take(&mut *self.inner.lock().unwrap_or_else(|e| e.into_inner()));
+        let n = acc.compared.max(1) as f64;
+        ShadowSnapshot {
+            compared: acc.compared,
+            active_disagree: acc.active_disagree,
+            mean_abs: acc.sum_abs.map(|s| (s / n) as f32),
+            max_abs: acc.max_abs,
+        }
+    }
+}
+
+/// Background divergence reporter task.
+pub async fn shadow_reporter(stats: Arc<ShadowStats>, interval_secs: u64) {
+    if interval_secs == 0 {
+        std::future::pending::<()>().await;
+        return;
+    }
+
+    let interval = Duration::from_secs(interval_secs);
+    loop {
+        tokio::time::sleep(interval).await;
+        let snap = stats.snapshot_and_reset();
+        if snap.compared == 0 {
+            continue;
+        }
+        tracing::info!(
+            compared = snap.compared,
+            active_disagree = snap.active_disagree,
+            mean_dv = format!("{:.3}", snap.mean_abs[0]),
+            mean_da = format!("{:.3}", snap.mean_abs[1]),
+            mean_dd = format!("{:.3}", snap.mean_abs[2]),
+            max_dv = format!("{:.3}", snap.max_abs[0]),
+            max_da = format!("{:.3}", snap.max_abs[1]),
+            max_dd = format!("{:.3}", snap.max_abs[2]),
+            "👥 shadow engine divergence"
+        );
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vad::VadKind;
+
+    fn result(v: f32, a: f32, d: f32, is_active: bool) -> VadResult {
+        VadResult {
+            sensor_id: 1,
+            seq: 0,
+            kind: VadKind::Emotional,
+            is_active,
+            energy: 0.0,
+            threshold: 0.0,
+            dbfs: 0.0,
+            zcr: 0.0,
+            band_ratio: 0.0,
+            valence: v,
+            arousal: a,
+            dominance: d,
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn test_divergence_mean_max_and_reset() {
+        let stats = ShadowStats::new();
+        stats.record(&result(0.5, 0.5, 0.5, true), &result(0.4, 0.5, 0.5, true));
+        stats.record(&result(0.5, 0.5, 0.5, true), &result(0.2, 0.3, 0.5, false));
+
+        let snap = stats.snapshot_and_reset();
+        assert_eq!(snap.compared, 2);
+        assert_eq!(snap.active_disagree, 1);
+        assert!((snap.mean_abs[0] - 0.2).abs() < 1e-5);
+        assert!((snap.max_abs[0] - 0.3).abs() < 1e-5);
+        assert!((snap.max_abs[1] - 0.2).abs() < 1e-5);
+        assert_eq!(snap.max_abs[2], 0.0);
+
+        assert_eq!(stats.snapshot_and_reset().compared, 0);
+    }
+}
@@ -0,0 +1,242 @@
+//! `validate` subcommand — pre-flight checks for a `serve` configuration.
+//!
+//! Takes exactly the `serve` flags and checks, without starting anything:
+//!
+//! * weight / model files load (`--emotion-weights`, `--emotion-model-path`,
+//!   and the shadow equivalents)
+//! * UDP audio / sensor / test ports and the REST API port can be bound
+//!   (a running bridge on the same ports is reported as a conflict)
+//! * the instructions template (`--openai-instructions-file`) reads and
+//!   only uses known placeholders
+//! * the safety banner (`--safety-banner-file`) is locked and matches its pin
+//! * an OpenAI API key is present when `--openai-realtime` is set
+//! * the TTS backend (`--tts-backend`) has a key
+//! * the speaker engine (`--speaker-engine`) loads its model
+//! * the audio-event classifier (`--audio-event-model-path`) loads
+//! * voice command templates (`--voice-commands-dir`) decode
+//! * `--audio-save-dir` exists (or can be created) and is writable
+//! * thresholds and `--distress-*` parameters are in range
+//! * `--tenants-file` parses; the per-instance checks (ports, template,
+//!   key, save dir) then run once per tenant
+//!
+//! Prints one line per check and exits non-zero if any failed.
+
+use crate::audio_events::AudioEventClassifier;
+use crate::clock;
+use crate::config::Config;
+use crate::devices::DeviceConfig;
+use crate::distress::DistressParams;
+use crate::prompt;
+use crate::safety;
+use crate::speakers::{ Diarizer, SpeakerEngineKind };
+use crate::stats::Stats;
+use crate::tenants;
+use crate::tts::TtsEngine;
+use crate::vad::EmotionEngine;
+use crate::voice_commands::VoiceCommands;
+
+struct Report {
+    failures: usize,
+}
+
+impl Report {
+    fn check(&mut self, what: &str, result: anyhow::Result<()>) {
+        match result {
+            Ok(()) => println!("✅ {what}"),
+            Err(e) => {
+                self.failures += 1;
+                println!("❌ {what}: {e}");
+            }
+        }
+    }
+}
+
+/// Entry point for `vad-sensor-bridge validate`.
+pub async fn run(config: &Config) -> anyhow::Result<()> {
+    let mut report = Report { failures: 0 };
+
+    report.check(
+        &format!("emotion engine ({:?})", config.emotion_engine),
+        EmotionEngine::from_config(
+            config.emotion_engine,
+            &config.emotion_model_path,
+            &config.emotion_weights,
+            &Stats::new()
+        ).map(|_| ())
+    );
+    if let Some(kind) = config.shadow_emotion_engine {
+        report.check(
+            &format!("shadow emotion engine ({kind:?})"),
+            EmotionEngine::from_config(
+                kind,
+                &config.shadow_emotion_model_path,
+                &config.shadow_emotion_weights,
+                &Stats::new()
+            ).map(|_| ())
+        );
+    }
+
+    report.check(
+        "thresholds",
+        (DeviceConfig {
+            audio_threshold: Some(config.audio_threshold),
+            arousal_threshold: Some(config.arousal_threshold),
+            ..Default::default()
+        })
+            .validate()
+            .map_err(|e| anyhow::anyhow!(e))
+    );
+    if config.distress_alarm {
+        report.check("distress alarm", DistressParams::from_config(config).map(|_| ()));
+    }
+
+    // Per-instance checks: once, or once per tenant with `--tenants-file`
+    let instances = if config.tenants_file.is_empty() {
+        vec![config.clone()]
+    } else {
+        let what = format!("tenants file {}", config.tenants_file);
+        match tenants::load(&config.tenants_file, config) {
+            Ok(tenants) => {
+                report.check(&what, Ok(()));
+                tenants
+            }
+            Err(e) => {
+                report.check(&what, Err(e));
+                Vec::new()
+            }
+        }
+    };
+    for instance in &instances {
+        check_instance(&mut report, instance).await;
+    }
+
+    if !config.safety_banner_file.is_empty() {
+        report.check(
+            &format!("safety banner {}", config.safety_banner_file),
+            safety::read_locked_banner(&config.safety_banner_file, &config.safety_banner_sha256).map(|_| ())
+        );
+    }
+
+    if let Some(kind) = config.tts_backend {
+        report.check(
+            &format!("TTS backend ({kind:?})"),
+            TtsEngine::from_config(config).map(|_| ())
+        );
+    }
+
+    if !config.voice_commands_dir.is_empty() {
+        report.check(
+            &format!("voice commands {}", config.voice_commands_dir),
+            VoiceCommands::load(&config.voice_commands_dir, config.voice_command_threshold).map(|_| ())
+        );
+    }
+
+    if config.speaker_engine != SpeakerEngineKind::Off {
+        report.check(
+            &format!("speaker engine ({:?})", config.speaker_engine),
+            Diarizer::from_config(config).map(|_| ())
+        );
+    }
+
+    if !config.audio_event_model_path.is_empty() {
+        report.check(
+            &format!("audio-event model {}", config.audio_event_model_path),
+            AudioEventClassifier::from_config(config, clock::system()).map(|_| ())
+        );
+    }
+
+    if report.failures > 0 {
+        anyhow::bail!("{} check(s) failed", report.failures);
+    }
+    println!("configuration OK");
+    Ok(())
+}
+
+/// Ports, prompt, OpenAI key and recordings dir of one bridge instance
+/// (labels are prefixed with the tenant id in multi-tenant mode).
+async fn check_instance(report: &mut Report, config: &Config) {
+    let tenant = if config.tenant_id.is_empty() {
+        String::new()
+    } else {
+        format!("[{}] ", config.tenant_id)
+    };
+    let ports = [
+        ("audio", config.audio_port),
+        ("sensor", config.sensor_port),
+        ("test", config.test_port),
+    ];
+    for (name, port) in ports {
+        let addr = format!("{}:{}", config.host, port);
+        report.check(
+            &format!("{tenant}UDP {name} port {addr}"),
+            std::net::UdpSocket
+                ::bind(&addr)
+                .map(|_| ())
+                .map_err(Into::into)
+        );
+    }
+    let api_addr = format!("{}:{}", config.host, config.api_port);
+    report.check(
+        &format!("{tenant}TCP API port {api_addr}"),
+        tokio::net::TcpListener
+            ::bind(&api_addr).await
+            .map(|_| ())
+            .map_err(Into::into)
+    );
+
+    report.check(
+        &format!("{tenant}OpenAI instructions template"),
+        prompt::load_template(config).and_then(|template| {
+            let unknown = prompt::unknown_placeholders(&template);
+            if unknown.is_empty() {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("unknown placeholder(s): {}", unknown.join(", ")))
+            }
+        })
+    );
+
+    if config.openai_realtime {
+        report.check(
+            &format!("{tenant}OpenAI API key"),
+            if config.openai_api_key.trim().is_empty() {
+                Err(anyhow::anyhow!("--openai-realtime set but no --openai-api-key / OPENAI_API_KEY"))
+            } else {
+                Ok(())
+            }
+        );
+    }
+
+    report.check(
+        &format!("{tenant}audio save dir {}", config.audio_save_dir),
+        check_writable(&config.audio_save_dir)
+    );
+}
+
+/// Create `dir` if needed and prove it is writable with a probe file.
+pub fn check_writable(dir: &str) -> anyhow::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = std::path::Path::new(dir).join(".vad-bridge-write-test");
+    std::fs::write(&probe, b"ok")?;
+    std::fs::remove_file(&probe)?;
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_writable() {
+        let dir = std::env::temp_dir().join(format!("vad-validate-{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        assert!(check_writable(dir).is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+
+        assert!(check_writable("/proc/definitely/not/writable").is_err());
+    }
+}
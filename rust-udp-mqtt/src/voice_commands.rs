@@ -0,0 +1,377 @@
+use crate::mel::{ self, MelBands, FRAME, HOP };
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{ debug, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Local voice commands (keyword spotting, no LLM round trip)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  A few commands must work even when OpenAI is slow or down, and
+//  "stop" must take effect well inside 200 ms.  They are spotted on the
+//  server's audio path, by template matching against recordings of each
+//  phrase in `--voice-commands-dir`:
+//
+//    <dir>/stop/*.wav          "stop", "be quiet", ...
+//    <dir>/volume_down/*.wav   "volume down", "quieter", ...
+//    <dir>/volume_up/*.wav     "volume up", "louder", ...
+//
+//  Every session's mono audio is cut into utterances at pauses (voiced
+//  frames, ending after HANGOVER_FRAMES of silence).  An utterance of
+//  roughly a template's length is compared with it by dynamic time
+//  warping over log mel-band energies; the mean frame distance of the
+//  best alignment must be under `--voice-command-threshold`.  Commands
+//  are therefore spotted when said on their own, not inside a sentence.
+//
+//  A match is handed to `transport_udp`, which drops the session's audio
+//  (the command never reaches OpenAI) and acts on it at once:
+//
+//    stop         the reply / clip / TTS the device is hearing is
+//                 cancelled and the device told to stop playback
+//    volume_*     a volume control packet (±`--volume-step`)
+//
+//  Templates are best recorded on the robot itself: session WAVs cut to
+//  the phrase work, as does any WAV the clip loader reads.
+
+/// Mel bands of the command features (100 Hz – 6 kHz).
+const BANDS: usize = 20;
+/// Frames quieter than this RMS (s16) are treated as silence.
+const VOICED_RMS: f32 = 300.0;
+/// Silent frames that end an utterance (100 ms: the spotting latency).
+const HANGOVER_FRAMES: usize = 10;
+/// Utterances shorter than this (frames) are ignored.
+const MIN_FRAMES: usize = 10;
+
+/// What a spotted phrase does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoiceCommand {
+    Stop,
+    VolumeDown,
+    VolumeUp,
+}
+
+impl VoiceCommand {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VoiceCommand::Stop => "stop",
+            VoiceCommand::VolumeDown => "volume_down",
+            VoiceCommand::VolumeUp => "volume_up",
+        }
+    }
+
+    fn from_dir(name: &str) -> Option<Self> {
+        [VoiceCommand::Stop, VoiceCommand::VolumeDown, VoiceCommand::VolumeUp]
+            .into_iter()
+            .find(|c| c.as_str() == name)
+    }
+}
+
+/// A command spotted in `addr`'s session audio.
+#[derive(Debug, Clone)]
+pub struct Detected {
+    pub addr: SocketAddr,
+    pub command: VoiceCommand,
+    /// Template file stem that matched
+    pub phrase: String,
+    /// Mean frame distance of the match
+    pub distance: f32,
+}
+
+struct Template {
+    command: VoiceCommand,
+    phrase: String,
+    frames: Vec<Vec<f32>>,
+}
+
+struct Shared {
+    bank: MelBands,
+    templates: Vec<Template>,
+    threshold: f32,
+    /// Utterances longer than this (frames) cannot match any template
+    max_frames: usize,
+}
+
+/// Loaded command templates plus the queue detections go through.
+/// Clone-friendly; `Default` spots nothing.
+#[derive(Clone, Default)]
+pub struct VoiceCommands {
+    shared: Option<Arc<Shared>>,
+    tx: Option<mpsc::Sender<Detected>>,
+}
+
+impl VoiceCommands {
+    /// Load `<dir>/<command>/*.wav` ("" = disabled).  The receiver gets
+    /// every detection.
+    pub fn load(dir: &str, threshold: f32) -> anyhow::Result<(Self, mpsc::Receiver<Detected>)> {
+        let (tx, rx) = mpsc::channel(16);
+        if dir.is_empty() {
+            return Ok((Self::default(), rx));
+        }
+        let bank = MelBands::new(BANDS, 100.0, 6_000.0);
+        let mut templates = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(|e| anyhow::anyhow!("voice commands dir {dir}: {e}"))? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if !path.is_dir() {
+                continue;
+            }
+            let Some(command) = VoiceCommand::from_dir(name) else {
+                warn!(dir = %path.display(), "unknown voice command directory — ignored");
+                continue;
+            };
+            for wav in std::fs::read_dir(&path)? {
+                let wav = wav?.path();
+                if wav.extension().and_then(|e| e.to_str()) != Some("wav") {
+                    continue;
+                }
+                let pcm = crate::clips
+                    ::decode_wav(&std::fs::read(&wav)?)
+                    .map_err(|e| anyhow::anyhow!("{}: {e}", wav.display()))?;
+                let samples: Vec<i16> = pcm
+                    .chunks_exact(2)
+                    .map(|c| i16::from_le_bytes([c[0], c[1]]))
+                    .collect();
+                let frames = features(&bank, &samples);
+                if frames.len() < MIN_FRAMES {
+                    anyhow::bail!("{}: less than {} ms of speech", wav.display(), MIN_FRAMES * 10);
+                }
+                let phrase = wav
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or_default()
+                    .to_string();
+                templates.push(Template { command, phrase, frames });
+            }
+        }
+        if templates.is_empty() {
+            anyhow::bail!("no voice command templates in {dir}");
+        }
+        let max_frames = templates
+            .iter()
+            .map(|t| t.frames.len() * 2)
+            .max()
+            .unwrap_or(0);
+        info!(dir = %dir, templates = templates.len(), threshold, "🗝️ voice commands loaded");
+        Ok((Self { shared: Some(Arc::new(Shared { bank, templates, threshold, max_frames })), tx: Some(tx) }, rx))
+    }
+
+    /// A fresh spotter for one session (None when disabled).
+    pub fn spotter(&self) -> Option<CommandSpotter> {
+        let shared = self.shared.clone()?;
+        Some(CommandSpotter { shared, samples: Vec::new(), utterance: Vec::new(), silent: 0, overflow: false })
+    }
+
+    /// Queue a detection for execution (never blocks).
+    pub fn dispatch(&self, detected: Detected) {
+        if let Some(ref tx) = self.tx {
+            if tx.try_send(detected).is_err() {
+                warn!("voice command queue full — dropping command");
+            }
+        }
+    }
+}
+
+/// Streaming spotter over one session's 16 kHz s16 mono audio.
+pub struct CommandSpotter {
+    shared: Arc<Shared>,
+    /// Samples not yet framed
+    samples: Vec<i16>,
+    /// Features of the current utterance's voiced frames
+    utterance: Vec<Vec<f32>>,
+    silent: usize,
+    /// The current utterance is too long to be a command
+    overflow: bool,
+}
+
+impl CommandSpotter {
+    /// Feed s16 LE audio; returns the command and its distance if an
+    /// utterance just ended and matched one.
+    pub fn push(&mut self, addr: SocketAddr, pcm: &[u8]) -> Option<Detected> {
+        self.samples.extend(pcm.chunks_exact(2).map(|c| i16::from_le_bytes([c[0], c[1]])));
+        let mut detected = None;
+        let mut start = 0;
+        while start + FRAME <= self.samples.len() {
+            let frame = &self.samples[start..start + FRAME];
+            start += HOP;
+            if mel::rms(frame) >= VOICED_RMS {
+                self.silent = 0;
+                if !self.overflow {
+                    let features = normalized(self.shared.bank.log_energies(frame));
+                    self.utterance.push(features);
+                    if self.utterance.len() > self.shared.max_frames {
+                        self.overflow = true;
+                        self.utterance.clear();
+                    }
+                }
+                continue;
+            }
+            if self.utterance.is_empty() && !self.overflow {
+                continue;
+            }
+            self.silent += 1;
+            if self.silent >= HANGOVER_FRAMES {
+                if let Some(m) = self.shared.best_match(&self.utterance) {
+                    detected = Some(Detected { addr, command: m.0, phrase: m.1, distance: m.2 });
+                }
+                self.utterance.clear();
+                self.overflow = false;
+                self.silent = 0;
+            }
+        }
+        self.samples.drain(..start);
+        detected
+    }
+}
+
+impl Shared {
+    /// Closest template under the threshold: `(command, phrase, distance)`.
+    fn best_match(&self, utterance: &[Vec<f32>]) -> Option<(VoiceCommand, String, f32)> {
+        if utterance.len() < MIN_FRAMES {
+            return None;
+        }
+        let (template, distance) = self.templates
+            .iter()
+            .filter(|t| utterance.len() * 2 >= t.frames.len() && utterance.len() <= t.frames.len() * 2)
+            .map(|t| (t, dtw(utterance, &t.frames)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))?;
+        debug!(phrase = %template.phrase, distance = format!("{:.2}", distance), "voice command candidate");
+        (distance < self.threshold).then(|| (template.command, template.phrase.clone(), distance))
+    }
+}
+
+// ── Features + matching ──────────────────────────────────────────────
+
+/// Features of the voiced frames of a whole recording.
+fn features(bank: &MelBands, pcm: &[i16]) -> Vec<Vec<f32>> {
+    pcm.windows(FRAME)
+        .step_by(HOP)
+        .filter(|frame| mel::rms(frame) >= VOICED_RMS)
+        .map(|frame| normalized(bank.log_energies(frame)))
+        .collect()
+}
+
+/// Remove the mean across bands (loudness-independent).
+fn normalized(mut bands: Vec<f32>) -> Vec<f32> {
+    let mean = bands.iter().sum::<f32>() / (bands.len() as f32);
+    bands.iter_mut().for_each(|b| {
+        *b -= mean;
+    });
+    bands
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f32>()
+        .sqrt()
+}
+
+/// Mean frame distance along the best dynamic-time-warping alignment of
+/// `a` and `b` (steps: advance either or both).
+fn dtw(a: &[Vec<f32>], b: &[Vec<f32>]) -> f32 {
+    // (accumulated cost, path length) per cell of the previous row
+    let mut prev = vec![(f32::INFINITY, 0u32); b.len() + 1];
+    prev[0] = (0.0, 0);
+    for fa in a {
+        let mut row = vec![(f32::INFINITY, 0u32); b.len() + 1];
+        for (j, fb) in b.iter().enumerate() {
+            let best = [prev[j], prev[j + 1], row[j]]
+                .into_iter()
+                .min_by(|x, y| x.0.total_cmp(&y.0))
+                .unwrap_or((f32::INFINITY, 0));
+            row[j + 1] = (best.0 + distance(fa, fb), best.1 + 1);
+        }
+        prev = row;
+    }
+    let (cost, len) = prev[b.len()];
+    if len == 0 { f32::INFINITY } else { cost / (len as f32) }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A "word": a sequence of 100 ms tones with a little harmonic buzz.
+    fn word(tones: &[f32], gain: f32, stretch: f32) -> Vec<i16> {
+        let seg = (1_600.0 * stretch) as usize;
+        tones
+            .iter()
+            .flat_map(|&f| {
+                (0..seg).map(move |n| {
+                    let t = (n as f32) / 16_000.0;
+                    let s = (2.0 * std::f32::consts::PI * f * t).sin() + 0.3 * (4.0 * std::f32::consts::PI * f * t).sin();
+                    (s * gain) as i16
+                })
+            })
+            .collect()
+    }
+
+    fn bytes(pcm: &[i16]) -> Vec<u8> {
+        pcm.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    fn shared(threshold: f32) -> Arc<Shared> {
+        let bank = MelBands::new(BANDS, 100.0, 6_000.0);
+        let stop = features(&bank, &word(&[400.0, 900.0, 2_000.0, 700.0], 4_000.0, 1.0));
+        let louder = features(&bank, &word(&[1_500.0, 300.0, 500.0, 3_000.0], 4_000.0, 1.0));
+        let max_frames = stop.len().max(louder.len()) * 2;
+        Arc::new(Shared {
+            bank,
+            templates: vec![
+                Template { command: VoiceCommand::Stop, phrase: "stop".into(), frames: stop },
+                Template { command: VoiceCommand::VolumeUp, phrase: "louder".into(), frames: louder }
+            ],
+            threshold,
+            max_frames,
+        })
+    }
+
+    #[test]
+    fn test_dtw_separates_words() {
+        let shared = shared(f32::INFINITY);
+        let quick_loud_stop = features(&shared.bank, &word(&[400.0, 900.0, 2_000.0, 700.0], 12_000.0, 0.8));
+        let other = features(&shared.bank, &word(&[250.0, 3_500.0, 1_200.0, 600.0], 4_000.0, 1.0));
+        let (command, _, same) = shared.best_match(&quick_loud_stop).unwrap();
+        assert_eq!(command, VoiceCommand::Stop);
+        let to_stop = dtw(&other, &shared.templates[0].frames);
+        assert!(same * 3.0 < to_stop, "{same} vs {to_stop}");
+    }
+
+    #[test]
+    fn test_spotter_fires_after_the_pause() {
+        let addr: SocketAddr = "10.0.0.7:5000".parse().unwrap();
+        let mut spotter = CommandSpotter {
+            shared: shared(4.0),
+            samples: Vec::new(),
+            utterance: Vec::new(),
+            silent: 0,
+            overflow: false,
+        };
+        let mut audio = word(&[400.0, 900.0, 2_000.0, 700.0], 6_000.0, 1.1);
+        audio.extend(vec![0i16; 3_200]);
+        let mut hits = Vec::new();
+        // 20 ms chunks, as ESPs send them
+        for chunk in bytes(&audio).chunks(640) {
+            hits.extend(spotter.push(addr, chunk));
+        }
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].command, hits[0].phrase.as_str()), (VoiceCommand::Stop, "stop"));
+
+        // A long sentence never matches, even if it contains the word
+        let mut sentence = word(&[250.0, 3_500.0, 1_200.0, 600.0, 800.0, 1_900.0, 2_600.0], 6_000.0, 1.0);
+        sentence.extend(word(&[400.0, 900.0, 2_000.0, 700.0], 6_000.0, 1.0));
+        sentence.extend(word(&[1_000.0, 450.0, 3_100.0, 600.0, 800.0, 1_900.0, 2_600.0], 6_000.0, 1.0));
+        sentence.extend(vec![0i16; 3_200]);
+        assert!(spotter.push(addr, &bytes(&sentence)).is_none());
+    }
+}
@@ -0,0 +1,168 @@
+use crate::devices::{ DeviceRegistry, OutputLevel };
+use crate::esp_audio_protocol::{ build_mute, build_s2d_mute, build_s2d_set_volume, build_set_volume };
+use serde::Serialize;
+use tokio::sync::{ mpsc, oneshot };
+use tracing::info;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Robot output level (volume / mute)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Operators (`PUT /devices/{device}/volume`) and the voice command
+//  spotter change an ESP's playback level remotely:
+//
+//    legacy ESPs     CTRL_SET_VOLUME [volume %] / CTRL_MUTE [1 | 0]
+//    notify ESPs     S2D frames 0x0B [volume %] / 0x0C [1 | 0]
+//
+//  Only what a change touches is sent.  The level set last is kept in
+//  the device registry, which is also the base of relative (spoken)
+//  volume steps.  During quiet hours (`quiet_hours.rs`) the volume sent
+//  is capped; the registry keeps the uncapped level to restore after.
+
+/// A change to one device's output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputChange {
+    /// Absolute settings; `None` leaves that setting as it is
+    Set {
+        volume: Option<u8>,
+        muted: Option<bool>,
+    },
+    /// Relative volume step in percentage points (clamped to 0..=100)
+    Step(i8),
+}
+
+/// Why a volume request failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VolumeError {
+    UnknownDevice(String),
+    /// The UDP side is not running (no receiver for requests)
+    Unavailable,
+}
+
+impl std::fmt::Display for VolumeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VolumeError::UnknownDevice(d) => write!(f, "unknown device: {d}"),
+            VolumeError::Unavailable => write!(f, "audio transport not running"),
+        }
+    }
+}
+
+/// A device's output after a change.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputStatus {
+    pub device: String,
+    pub addr: String,
+    #[serde(flatten)]
+    pub level: OutputLevel,
+}
+
+/// A request from the API to change `device`'s (MAC or `ip:port`) output.
+pub struct VolumeRequest {
+    pub device: String,
+    pub change: OutputChange,
+    pub reply: oneshot::Sender<Result<OutputStatus, VolumeError>>,
+}
+
+/// Output level tracking + the queue REST changes go through.
+/// Clone-friendly.
+#[derive(Clone)]
+pub struct VolumeControl {
+    devices: DeviceRegistry,
+    requests: mpsc::Sender<VolumeRequest>,
+}
+
+impl VolumeControl {
+    pub fn new(devices: DeviceRegistry) -> (Self, mpsc::Receiver<VolumeRequest>) {
+        let (requests, rx) = mpsc::channel(16);
+        (Self { devices, requests }, rx)
+    }
+
+    /// Last level sent to `device`.
+    pub fn level(&self, device: &str) -> OutputLevel {
+        self.devices.output(device)
+    }
+
+    /// Ask the UDP side to apply `change` to `device`.
+    pub async fn request(&self, device: &str, change: OutputChange) -> Result<OutputStatus, VolumeError> {
+        let (reply, rx) = oneshot::channel();
+        let req = VolumeRequest { device: device.to_string(), change, reply };
+        self.requests.send(req).await.map_err(|_| VolumeError::Unavailable)?;
+        rx.await.map_err(|_| VolumeError::Unavailable)?
+    }
+
+    /// Record `change` for `device` and build the packets that carry it
+    /// (`mac` = notify-protocol device; `seq` for legacy controls; `cap`
+    /// = quiet-hours limit on the volume sent).
+    pub fn apply(
+        &self,
+        device: &str,
+        change: OutputChange,
+        mac: Option<[u8; 6]>,
+        seq: u16,
+        cap: Option<u8>
+    ) -> (OutputLevel, Vec<Vec<u8>>) {
+        let mut level = self.devices.output(device);
+        let (volume, muted) = match change {
+            OutputChange::Set { volume, muted } => (volume.map(|v| v.min(100)), muted),
+            OutputChange::Step(step) => (Some((level.volume as i16 + step as i16).clamp(0, 100) as u8), None),
+        };
+        let mut packets = Vec::with_capacity(2);
+        if let Some(volume) = volume {
+            level.volume = volume;
+            packets.push(volume_packet(volume.min(cap.unwrap_or(100)), mac, seq));
+        }
+        if let Some(muted) = muted {
+            level.muted = muted;
+            packets.push(match mac {
+                Some(_) => build_s2d_mute(muted).to_vec(),
+                None => build_mute(seq.wrapping_add(1), muted),
+            });
+        }
+        self.devices.set_output(device, level);
+        info!(device = %device, volume = level.volume, muted = level.muted, cap = ?cap, "🔊 output level set");
+        (level, packets)
+    }
+}
+
+/// The SET_VOLUME packet for a legacy (`mac` = None) or notify device.
+pub fn volume_packet(volume: u8, mac: Option<[u8; 6]>, seq: u16) -> Vec<u8> {
+    match mac {
+        Some(_) => build_s2d_set_volume(volume).to_vec(),
+        None => build_set_volume(seq, volume),
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::Thresholds;
+    use crate::esp_audio_protocol::{ CTRL_MUTE, CTRL_SET_VOLUME, S2D_CMD_SET_VOLUME };
+
+    #[test]
+    fn test_apply_tracks_level_and_builds_packets() {
+        let devices = DeviceRegistry::new(Thresholds { audio: 30.0, arousal: 0.35 });
+        let (volume, _rx) = VolumeControl::new(devices.clone());
+
+        // Legacy: both settings, as controls
+        let (level, packets) = volume.apply("10.0.0.7:5000", OutputChange::Set { volume: Some(40), muted: Some(true) }, None, 9, None);
+        assert_eq!(level, OutputLevel { volume: 40, muted: true });
+        assert_eq!(packets[0][4..], [CTRL_SET_VOLUME, 40]);
+        assert_eq!(packets[1][4..], [CTRL_MUTE, 1]);
+
+        // Steps are relative to the tracked level and clamped
+        let (level, packets) = volume.apply("10.0.0.7:5000", OutputChange::Step(-50), None, 11, None);
+        assert_eq!(level, OutputLevel { volume: 0, muted: true });
+        assert_eq!(packets.len(), 1);
+
+        // Notify devices get S2D frames; names are case-insensitive
+        let mac = Some([0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff]);
+        let (_, packets) = volume.apply("AA:BB:CC:DD:EE:FF", OutputChange::Step(-10), mac, 0, Some(30));
+        assert_eq!((packets[0][4], packets[0][5]), (S2D_CMD_SET_VOLUME, 30));
+        assert_eq!(devices.output("aa:bb:cc:dd:ee:ff"), OutputLevel { volume: 90, muted: false });
+    }
+}
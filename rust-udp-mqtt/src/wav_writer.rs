@@ -0,0 +1,367 @@
+use std::io::SeekFrom;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{ AsyncSeekExt, AsyncWriteExt };
+use tokio::sync::{ mpsc, oneshot };
+use tracing::{ debug, info, warn };
+
+// ─────────────────────────────────────────────────────────────────────
+//  Streaming session WAVs (crash-safe)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  A session's audio goes to disk as it arrives instead of being held
+//  in RAM until SESSION_END:
+//
+//    SESSION_START   <dir>/esp_<ip>_<YYYYmmdd_HHMMSS>.wav.part created,
+//                    44-byte header with zero sizes (`-1`, `-2` … added
+//                    before `.wav` when a session from the same IP
+//                    started in the same second)
+//    audio           appended by one writer task per session (receivers
+//                    only queue the chunk — never blocks, safe under a
+//                    session map guard)
+//    every --wav-flush-secs
+//                    buffered data flushed and the RIFF / data sizes in
+//                    the header rewritten: the part file is a valid WAV
+//                    up to the last flush
+//    SESSION_END     header fixed, fsync, renamed to `.wav`
+//    CANCEL          part file deleted
+//
+//  Multi-channel sessions are written interleaved, every channel kept.
+//  A crash mid-session therefore loses at most the last flush interval.
+//  At startup `recover` fixes the headers of left-over part files from
+//  their length and renames them to `.wav`.  A stream whose handle is
+//  dropped without SESSION_END (the device restarted its session) is
+//  finished, not discarded.
+
+/// 16 kHz, 16-bit PCM.
+const SAMPLE_RATE: u32 = 16_000;
+const HEADER_LEN: u64 = 44;
+
+/// Suffix of WAVs still being written.
+const PART_SUFFIX: &str = ".part";
+
+/// Sessions from one IP starting within the same second that get a
+/// file of their own.
+const MAX_NAME_SUFFIX: u32 = 1000;
+
+/// 44-byte WAV header for `data_len` bytes of 16 kHz s16 with
+/// `channels` interleaved channels.
+pub fn wav_header(data_len: u32, channels: u16) -> [u8; 44] {
+    let bits_per_sample: u16 = 16;
+    let byte_rate = SAMPLE_RATE * ((bits_per_sample as u32) / 8) * (channels as u32);
+    let block_align = channels * (bits_per_sample / 8);
+
+    let mut h = [0u8; 44];
+    h[0..4].copy_from_slice(b"RIFF");
+    h[4..8].copy_from_slice(&(36 + data_len).to_le_bytes());
+    h[8..12].copy_from_slice(b"WAVE");
+    h[12..16].copy_from_slice(b"fmt ");
+    h[16..20].copy_from_slice(&(16u32).to_le_bytes()); // sub-chunk size
+    h[20..22].copy_from_slice(&(1u16).to_le_bytes()); // PCM format
+    h[22..24].copy_from_slice(&channels.to_le_bytes());
+    h[24..28].copy_from_slice(&SAMPLE_RATE.to_le_bytes());
+    h[28..32].copy_from_slice(&byte_rate.to_le_bytes());
+    h[32..34].copy_from_slice(&block_align.to_le_bytes());
+    h[34..36].copy_from_slice(&bits_per_sample.to_le_bytes());
+    h[36..40].copy_from_slice(b"data");
+    h[40..44].copy_from_slice(&data_len.to_le_bytes());
+    h
+}
+
+/// Starts session WAV streams in `--audio-save-dir`.  Clone-friendly.
+#[derive(Clone)]
+pub struct WavRecorder {
+    dir: Arc<str>,
+    flush_every: Duration,
+}
+
+enum WavCmd {
+    Audio(Vec<u8>),
+    Finish(oneshot::Sender<anyhow::Result<String>>),
+    Discard,
+}
+
+/// One session's WAV being written.  Dropping it finishes the file.
+pub struct WavStream {
+    tx: mpsc::UnboundedSender<WavCmd>,
+}
+
+impl WavRecorder {
+    pub fn new(dir: &str, flush_every: Duration) -> Self {
+        Self { dir: Arc::from(dir), flush_every: flush_every.max(Duration::from_millis(100)) }
+    }
+
+    /// Start the WAV of a session from `src` with `channels` channels.
+    pub fn start(&self, src: SocketAddr, channels: u16) -> WavStream {
+        let ts = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let ip = src.ip().to_string().replace(['.', ':'], "_");
+        let stem = format!("{}/esp_{ip}_{ts}", self.dir);
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(write_loop(stem, channels.max(1), self.flush_every, rx));
+        WavStream { tx }
+    }
+
+    /// Finish part files left behind by a crash; returns how many.
+    pub fn recover(&self) -> usize {
+        let Ok(entries) = std::fs::read_dir(&*self.dir) else {
+            return 0;
+        };
+        let mut recovered = 0;
+        for entry in entries.flatten() {
+            let part = entry.path();
+            let Some(name) = part.to_str().and_then(|p| p.strip_suffix(PART_SUFFIX)) else {
+                continue;
+            };
+            if !name.ends_with(".wav") {
+                continue;
+            }
+            let name = name.to_string();
+            match recover_part(&part, &name) {
+                Ok(bytes) => {
+                    info!(path = %name, bytes, "🩹 recovered interrupted session WAV");
+                    recovered += 1;
+                }
+                Err(e) => warn!(path = %part.display(), error = %e, "failed to recover session WAV"),
+            }
+        }
+        recovered
+    }
+}
+
+fn recover_part(part: &std::path::Path, wav: &str) -> std::io::Result<u64> {
+    use std::io::{ Read, Seek, Write };
+    let mut file = std::fs::OpenOptions::new().read(true).write(true).open(part)?;
+    let len = file.metadata()?.len();
+    let mut header = [0u8; 44];
+    let channels = match file.read_exact(&mut header) {
+        Ok(()) => u16::from_le_bytes([header[22], header[23]]).max(1),
+        Err(_) => 1,
+    };
+    // A partly written last frame is cut off
+    let frame = (channels as u64) * 2;
+    let data_len = len.saturating_sub(HEADER_LEN) / frame * frame;
+    file.set_len(HEADER_LEN + data_len)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&wav_header(data_len as u32, channels))?;
+    file.sync_all()?;
+    std::fs::rename(part, wav)?;
+    Ok(data_len)
+}
+
+impl WavStream {
+    /// Queue a chunk of 16 kHz s16le PCM.
+    pub fn write(&self, pcm: &[u8]) {
+        let _ = self.tx.send(WavCmd::Audio(pcm.to_vec()));
+    }
+
+    /// Complete the file; returns its path once it is on disk.
+    pub async fn finish(self) -> anyhow::Result<String> {
+        let (reply, rx) = oneshot::channel();
+        self.tx
+            .send(WavCmd::Finish(reply))
+            .map_err(|_| anyhow::anyhow!("WAV writer stopped"))?;
+        rx.await.map_err(|_| anyhow::anyhow!("WAV writer stopped"))?
+    }
+
+    /// Delete the file (cancelled session).
+    pub fn discard(self) {
+        let _ = self.tx.send(WavCmd::Discard);
+    }
+}
+
+/// Append audio to `<stem>.wav.part` until finished or discarded.
+async fn write_loop(stem: String, channels: u16, flush_every: Duration, mut rx: mpsc::UnboundedReceiver<WavCmd>) {
+    let (path, mut file) = match create(&stem, channels).await {
+        Ok(created) => created,
+        Err(e) => {
+            warn!(path = %stem, error = %e, "failed to create session WAV");
+            while let Some(cmd) = rx.recv().await {
+                if let WavCmd::Finish(reply) = cmd {
+                    let _ = reply.send(Err(anyhow::anyhow!("{stem}.wav: {e}")));
+                }
+            }
+            return;
+        }
+    };
+    let part = format!("{path}{PART_SUFFIX}");
+    debug!(path = %part, "💾 session WAV started");
+
+    let mut data_len: u32 = 0;
+    let mut dirty = false;
+    let mut flush = tokio::time::interval(flush_every);
+    flush.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            cmd = rx.recv() => match cmd {
+                Some(WavCmd::Audio(pcm)) => {
+                    if let Err(e) = file.write_all(&pcm).await {
+                        warn!(path = %part, error = %e, "failed to write session WAV");
+                        continue;
+                    }
+                    data_len = data_len.saturating_add(pcm.len() as u32);
+                    dirty = true;
+                }
+                Some(WavCmd::Finish(reply)) => {
+                    let _ = reply.send(complete(file, data_len, channels, &part, &path).await.map(|()| path));
+                    return;
+                }
+                // Handle dropped without SESSION_END: keep what was recorded
+                None => {
+                    if let Err(e) = complete(file, data_len, channels, &part, &path).await {
+                        warn!(path = %part, error = %e, "failed to finish session WAV");
+                    }
+                    return;
+                }
+                Some(WavCmd::Discard) => {
+                    drop(file);
+                    let _ = tokio::fs::remove_file(&part).await;
+                    debug!(path = %part, "session WAV discarded");
+                    return;
+                }
+            },
+            _ = flush.tick(), if dirty => {
+                if let Err(e) = write_header(&mut file, data_len, channels).await {
+                    warn!(path = %part, error = %e, "failed to flush session WAV");
+                }
+                dirty = false;
+            }
+        }
+    }
+}
+
+/// Claim a WAV path no other session holds: `<stem>.wav`, else
+/// `<stem>-1.wav`, `<stem>-2.wav` …  The part file is created with
+/// `create_new`, so two streams never share it, and a name whose
+/// finished WAV already exists is skipped.  Returns the WAV path and
+/// its part file, header written.
+async fn create(stem: &str, channels: u16) -> std::io::Result<(String, tokio::fs::File)> {
+    if let Some(dir) = std::path::Path::new(stem).parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    for n in 0..MAX_NAME_SUFFIX {
+        let path = if n == 0 { format!("{stem}.wav") } else { format!("{stem}-{n}.wav") };
+        let part = format!("{path}{PART_SUFFIX}");
+        let mut file = match tokio::fs::OpenOptions::new().write(true).create_new(true).open(&part).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        };
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            drop(file);
+            let _ = tokio::fs::remove_file(&part).await;
+            continue;
+        }
+        file.write_all(&wav_header(0, channels)).await?;
+        return Ok((path, file));
+    }
+    Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "no free session WAV name"))
+}
+
+/// Rewrite the header sizes and push everything to the OS.
+async fn write_header(file: &mut tokio::fs::File, data_len: u32, channels: u16) -> std::io::Result<()> {
+    file.seek(SeekFrom::Start(0)).await?;
+    file.write_all(&wav_header(data_len, channels)).await?;
+    file.seek(SeekFrom::End(0)).await?;
+    file.flush().await
+}
+
+async fn complete(
+    mut file: tokio::fs::File,
+    data_len: u32,
+    channels: u16,
+    part: &str,
+    path: &str
+) -> anyhow::Result<()> {
+    write_header(&mut file, data_len, channels).await?;
+    file.sync_all().await?;
+    drop(file);
+    tokio::fs::rename(part, path).await?;
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("vad-wav-{name}-{}", std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_stream_flushes_and_finishes() {
+        let dir = temp_dir("stream");
+        let recorder = WavRecorder::new(dir.to_str().unwrap(), Duration::from_millis(100));
+        let src: SocketAddr = "10.0.0.7:5000".parse().unwrap();
+
+        let wav = recorder.start(src, 1);
+        wav.write(&[1u8; 640]);
+        wav.write(&[2u8; 320]);
+
+        // Before SESSION_END the part file already has valid sizes
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let part = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        assert!(part.to_str().unwrap().ends_with(".wav.part"));
+        let bytes = std::fs::read(&part).unwrap();
+        assert_eq!(bytes.len(), 44 + 960);
+        assert_eq!(bytes[..44], wav_header(960, 1));
+
+        let path = wav.finish().await.unwrap();
+        assert!(!part.exists());
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(bytes[..44], wav_header(960, 1));
+        assert_eq!(bytes[44 + 640], 2);
+
+        // A cancelled session leaves nothing behind
+        let wav = recorder.start(src, 1);
+        wav.write(&[1u8; 64]);
+        wav.discard();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(std::fs::read_dir(&dir).map(|d| d.count()).unwrap_or(0) == 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_same_second_sessions_get_their_own_file() {
+        let dir = temp_dir("same-second");
+        let recorder = WavRecorder::new(dir.to_str().unwrap(), Duration::from_secs(1));
+        let src: SocketAddr = "10.0.0.7:5000".parse().unwrap();
+
+        let first = recorder.start(src, 1);
+        first.write(&[1u8; 64]);
+        let second = recorder.start(src, 1);
+        second.write(&[2u8; 32]);
+        let first = first.finish().await.unwrap();
+        let second = second.finish().await.unwrap();
+        // A third one must not reuse the finished names either
+        let third = recorder.start(src, 1).finish().await.unwrap();
+
+        let lens = [&first, &second, &third].map(|p| std::fs::metadata(p).map(|m| m.len()).ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(first != second && second != third && first != third);
+        assert_eq!(lens, [Some(44 + 64), Some(44 + 32), Some(44)]);
+    }
+
+    #[test]
+    fn test_recover_part_file() {
+        let dir = temp_dir("recover");
+        std::fs::create_dir_all(&dir).unwrap();
+        let part = dir.join("esp_10_0_0_7_20261016_150012.wav.part");
+        let mut bytes = wav_header(0, 2).to_vec();
+        bytes.extend_from_slice(&[7u8; 1003]); // crash mid-frame
+        std::fs::write(&part, &bytes).unwrap();
+        std::fs::write(dir.join("notes.txt.part"), b"x").unwrap();
+
+        let recorder = WavRecorder::new(dir.to_str().unwrap(), Duration::from_secs(1));
+        assert_eq!(recorder.recover(), 1);
+        let wav = std::fs::read(dir.join("esp_10_0_0_7_20261016_150012.wav")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(wav.len(), 44 + 1000);
+        assert_eq!(wav[..44], wav_header(1000, 2));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::esp_audio_protocol::{ build_packet, EspPacket, NotifyPacket, OtaMessage, TelemetryMessage, PKT_OTA, PKT_TELEMETRY };
+use crate::pcm::SampleFormat;
+use crate::sensor::{ SensorPacket, SensorVector, DATA_TYPE_AUDIO, HEADER_SIZE };
+use crate::tenants::TenantId;
+use crate::vad_response::VadResponsePacket;
+use arbitrary::Unstructured;
+
+// ─────────────────────────────────────────────────────────────────────
+//  Wire parser invariants (fuzz targets + property tests)
+// ─────────────────────────────────────────────────────────────────────
+//
+//  Every parser that reads datagrams off the open UDP ports must hold,
+//  for *any* input bytes:
+//
+//    never panic     (out-of-bounds slicing, overflowing lengths)
+//    round trip      whatever it accepts re-encodes to the same wire
+//                    bytes, and encoded values parse back unchanged
+//
+//  The checks below assert both; `fuzz/` runs them under libFuzzer
+//  (`cargo fuzz run <target>`) and the tests here run them on seeded
+//  random and mutated packets with every `cargo test`.
+//
+//  Built for tests and with `--features fuzz` only.
+
+/// `EspPacket::parse` (and the OTA / telemetry payload parsers behind it).
+pub fn esp_packet(data: &[u8]) {
+    let Some(pkt) = EspPacket::parse(data) else {
+        return;
+    };
+    assert_eq!(build_packet(pkt.seq_num, pkt.pkt_type, pkt.flags, &pkt.payload), data);
+    let _ = (pkt.control_cmd(), pkt.start_channels());
+    if pkt.pkt_type == PKT_OTA {
+        let _ = OtaMessage::parse(&pkt.payload);
+    }
+    if pkt.pkt_type == PKT_TELEMETRY {
+        let _ = TelemetryMessage::parse(&pkt.payload);
+    }
+}
+
+/// `NotifyPacket::parse`: the header must end inside the datagram.
+pub fn notify_packet(data: &[u8]) {
+    let Some(parsed) = NotifyPacket::parse(data) else {
+        return;
+    };
+    assert!((12..=data.len()).contains(&parsed.header_end), "header_end {} of {}", parsed.header_end, data.len());
+    assert_eq!(parsed.packet.mac_str().len(), 17);
+}
+
+/// `SensorPacket::from_binary` (and the sensor-vector payload parser).
+pub fn sensor_packet(data: &[u8]) {
+    let Some(pkt) = SensorPacket::from_binary(data) else {
+        return;
+    };
+    let bytes = pkt.to_binary();
+    assert_eq!(bytes.len(), HEADER_SIZE + pkt.payload.len());
+    assert_sensor_eq(&SensorPacket::from_binary(&bytes).expect("re-encoded packet parses"), &pkt);
+    if let Some(vector) = SensorVector::from_payload(&pkt.payload) {
+        let _ = vector.as_array();
+    }
+}
+
+/// `VadResponsePacket::from_bytes`: decoded fields re-encode to the
+/// datagram's bytes (floats compared bit for bit, NaNs included).
+pub fn vad_response(data: &[u8]) {
+    let Some((pkt, version)) = VadResponsePacket::from_bytes(data) else {
+        return;
+    };
+    let bytes = pkt.to_bytes(version);
+    assert_eq!(bytes, data[..bytes.len()]);
+}
+
+/// Structured round trip: an arbitrary sensor packet survives
+/// `to_binary` → `from_binary`.
+pub fn sensor_packet_roundtrip(u: &mut Unstructured) -> arbitrary::Result<()> {
+    let formats = [SampleFormat::S16, SampleFormat::S8, SampleFormat::S24, SampleFormat::S32, SampleFormat::F32];
+    let data_type = u.arbitrary()?;
+    // Only audio carries a sample format; others always parse as s16
+    let sample_format = *u.choose(&formats)?;
+    let mut pkt = SensorPacket {
+        sensor_id: u.arbitrary()?,
+        timestamp_us: u.arbitrary()?,
+        data_type,
+        sample_format: if data_type == DATA_TYPE_AUDIO { sample_format } else { SampleFormat::default() },
+        seq: u.arbitrary()?,
+        payload: Vec::new(),
+        tenant: TenantId::default(),
+        ingested_at: None,
+        trace: None,
+    };
+    let len = u.int_in_range(0..=u16::MAX as usize)?.min(u.len());
+    pkt.payload = u.bytes(len)?.to_vec();
+    assert_sensor_eq(&SensorPacket::from_binary(&pkt.to_binary()).expect("encoded packet parses"), &pkt);
+    Ok(())
+}
+
+/// Structured round trip: arbitrary responses survive `to_bytes` →
+/// `from_bytes` in both wire versions.
+pub fn vad_response_roundtrip(u: &mut Unstructured) -> arbitrary::Result<()> {
+    let pkt = VadResponsePacket {
+        sensor_id: u.arbitrary()?,
+        seq: u.arbitrary()?,
+        is_active: u.arbitrary()?,
+        kind: u.arbitrary()?,
+        energy: u.arbitrary()?,
+        threshold: u.arbitrary()?,
+        valence: u.arbitrary()?,
+        arousal: u.arbitrary()?,
+        dominance: u.arbitrary()?,
+        dbfs: u.arbitrary()?,
+        zcr: u.arbitrary()?,
+        band_ratio: u.arbitrary()?,
+    };
+    for version in [1, 2] {
+        let bytes = pkt.to_bytes(version);
+        let (decoded, got) = VadResponsePacket::from_bytes(&bytes).expect("encoded response decodes");
+        assert_eq!((got, decoded.to_bytes(version)), (version, bytes));
+    }
+    Ok(())
+}
+
+fn assert_sensor_eq(a: &SensorPacket, b: &SensorPacket) {
+    assert_eq!(
+        (a.sensor_id, a.timestamp_us, a.data_type, a.sample_format, a.seq, &a.payload),
+        (b.sensor_id, b.timestamp_us, b.data_type, b.sample_format, b.seq, &b.payload)
+    );
+}
+
+// ─────────────────────────────────────────────────────────────────────
+//  Tests
+// ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::esp_audio_protocol::{ PKT_AUDIO_UP, PKT_CONTROL };
+
+    /// Deterministic pseudo-random bytes (splitmix64).
+    fn random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                let mut z = state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                (z ^ (z >> 31)) as u8
+            })
+            .collect()
+    }
+
+    /// Well-formed datagrams of each kind, so mutations land near the
+    /// accepting paths instead of failing the first length check.
+    fn seeds() -> Vec<Vec<u8>> {
+        let sensor = SensorPacket::from_binary(&[0u8; HEADER_SIZE]).unwrap();
+        let vector = SensorPacket {
+            data_type: crate::sensor::DATA_TYPE_SENSOR_VECTOR,
+            payload: SensorVector::default().to_payload(),
+            ..sensor.clone()
+        };
+        let response = VadResponsePacket::from_bytes(&[0u8; 64]).unwrap().0;
+        vec![
+            build_packet(1, PKT_AUDIO_UP, 0, &[0u8; 64]),
+            build_packet(2, PKT_CONTROL, 1, &[1, 2]),
+            build_packet(3, PKT_OTA, 0, &[1, 0, 0, 0, 0]),
+            build_packet(4, PKT_TELEMETRY, 0, &[2, 3, 0x10, 0x0e, 0, 0]),
+            vec![0xaa, 0xb0, 0, 10, 0x51, 1, 2, 3, 4, 5, 6, 0, 0xff, 0xf5, 9, 9],
+            vector.to_binary(),
+            response.to_bytes(1),
+            response.to_bytes(2)
+        ]
+    }
+
+    fn check_all(data: &[u8]) {
+        esp_packet(data);
+        notify_packet(data);
+        sensor_packet(data);
+        vad_response(data);
+        let mut u = Unstructured::new(data);
+        sensor_packet_roundtrip(&mut u).unwrap();
+        vad_response_roundtrip(&mut u).unwrap();
+    }
+
+    #[test]
+    fn test_parsers_hold_on_random_and_truncated_input() {
+        for seed in 0..500u64 {
+            check_all(&random_bytes(seed, (seed as usize) % 200));
+        }
+        for seed in seeds() {
+            for end in 0..=seed.len() {
+                check_all(&seed[..end]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_format_checked_for_audio_only() {
+        use crate::sensor::DATA_TYPE_SENSOR_VECTOR;
+        let vector = SensorPacket::new(1, DATA_TYPE_SENSOR_VECTOR, SensorVector::default().to_payload());
+        let audio = SensorPacket::new(1, DATA_TYPE_AUDIO, vec![0; 64]);
+        for (pkt, accepted) in [(vector, true), (audio, false)] {
+            // A legacy sender's junk in the formerly reserved byte
+            let mut bytes = pkt.to_binary();
+            bytes[13] = 0x7f;
+            let parsed = SensorPacket::from_binary(&bytes);
+            assert_eq!(parsed.is_some(), accepted, "data type {}", pkt.data_type);
+            if let Some(parsed) = parsed {
+                assert_eq!(parsed.sample_format, SampleFormat::S16);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parsers_hold_on_mutated_packets() {
+        for (i, seed) in seeds().into_iter().enumerate() {
+            for round in 0..300u64 {
+                let noise = random_bytes(((i as u64) << 32) | round, 8);
+                let mut data = seed.clone();
+                // Overwrite up to four bytes, header included
+                for pair in noise.chunks(2).take(1 + (round as usize) % 4) {
+                    let at = (pair[0] as usize) % data.len();
+                    data[at] = pair[1];
+                }
+                check_all(&data);
+            }
+        }
+    }
+}